@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anya::bitcoin::dlc::{
+    AcceptDlc, AdaptorSigner, Contract, ContractParameters, DLCConfig, DLCManager, DefaultDLCManager,
+    EcdsaAdaptorSigner, InMemoryDLCWallet, LocalOracle, OfferDlc, PayoutFunction, SchnorrAdaptorSigner, SignDlc,
+};
+use bitcoin::absolute::LockTime;
+use bitcoin::secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, Transaction, TxOut};
+use chrono::{Duration, Utc};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn key(byte: u8) -> SecretKey {
+    SecretKey::from_slice(&[byte; 32]).unwrap()
+}
+
+fn manager(seed: u8, amount: u64) -> DefaultDLCManager {
+    let wallet = Arc::new(InMemoryDLCWallet::new(key(seed)));
+    let prev_tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::from_consensus(seed as u32),
+        input: vec![],
+        output: vec![TxOut { value: Amount::from_sat(amount), script_pubkey: wallet.script_pubkey() }],
+    };
+    wallet.add_utxo(prev_tx, 0).unwrap();
+    let config = DLCConfig { network: "regtest".to_string(), ..DLCConfig::default() };
+    DefaultDLCManager::new(config, wallet)
+}
+
+fn adaptor_signatures(c: &mut Criterion) {
+    let secp = Secp256k1::new();
+    let signing_key = key(0x11);
+    let public_key = PublicKey::from_secret_key(&secp, &signing_key);
+    let decryption_key = key(0x22);
+    let encryption_point = PublicKey::from_secret_key(&secp, &decryption_key);
+    let message = Message::from_digest([0x33; 32]);
+
+    let ecdsa = EcdsaAdaptorSigner::new();
+    let ecdsa_adaptor = ecdsa.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap();
+    c.bench_function("ecdsa_adaptor_sign", |b| {
+        b.iter(|| ecdsa.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap())
+    });
+    c.bench_function("ecdsa_adaptor_verify", |b| {
+        b.iter(|| assert!(ecdsa.verify_adaptor_signature(&message, &ecdsa_adaptor, &public_key).unwrap()))
+    });
+    c.bench_function("ecdsa_adaptor_decrypt", |b| {
+        b.iter(|| ecdsa.decrypt_adaptor_signature(&ecdsa_adaptor, &decryption_key).unwrap())
+    });
+
+    let schnorr = SchnorrAdaptorSigner::new();
+    let schnorr_adaptor = schnorr.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap();
+    c.bench_function("schnorr_adaptor_sign", |b| {
+        b.iter(|| schnorr.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap())
+    });
+    c.bench_function("schnorr_adaptor_verify", |b| {
+        b.iter(|| assert!(schnorr.verify_adaptor_signature(&message, &schnorr_adaptor, &public_key).unwrap()))
+    });
+}
+
+fn contract_execution_flow(c: &mut Criterion) {
+    let oracle = LocalOracle::new("bench", key(0x51));
+    let announcement = oracle.announce(
+        "bench-event",
+        "Benchmark event",
+        Utc::now() + Duration::days(1),
+        vec!["yes".to_string(), "no".to_string()],
+    ).unwrap();
+    let attestation = oracle.attest("bench-event", "yes").unwrap();
+    let parameters = ContractParameters {
+        title: "Benchmark".to_string(),
+        description: String::new(),
+        offer_collateral: 60_000,
+        accept_collateral: 40_000,
+        payout_function: PayoutFunction::Binary {
+            win_condition: "yes".to_string(),
+            offer_win_amount: 100_000,
+            accept_win_amount: 100_000,
+        },
+        oracle_urls: vec![],
+        oracle_announcements: vec![announcement],
+        oracle_threshold: None,
+        bounded_error: None,
+        refund_locktime: None,
+        fee_rate: None,
+        metadata: HashMap::new(),
+    };
+
+    // Offer, accept, sign, fund and settle, exchanging serialized messages
+    c.bench_function("contract_execution_flow", |b| {
+        b.iter(|| {
+            let (alice, bob) = (manager(1, 100_000), manager(2, 60_000));
+            let offered = alice.create_contract(parameters.clone()).unwrap();
+            let offer = OfferDlc::deserialize(&offered.offer.as_ref().unwrap().serialize()).unwrap();
+            let accepted = bob.accept_contract(&Contract::from_offer(offer).unwrap()).unwrap();
+            let accept = AcceptDlc::deserialize(&accepted.accept.as_ref().unwrap().serialize()).unwrap();
+            let signed = alice.sign_contract(&offered.with_accept(accept)).unwrap();
+            let sign = SignDlc::deserialize(&signed.sign.as_ref().unwrap().serialize()).unwrap();
+            let funded = bob.fund_contract(&accepted.with_sign(sign)).unwrap();
+            bob.execute_contract(&funded, attestation.clone()).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, adaptor_signatures, contract_execution_flow);
+criterion_main!(benches);
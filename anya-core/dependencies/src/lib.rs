@@ -1,2 +1,3 @@
 pub mod ml_logic;
-pub mod auth
+pub mod auth;
+pub mod secure_multiparty_computation;
@@ -1,37 +1,50 @@
-use bitcoin::secp256k1::{Secp256k1, SecretKey};
-use bitcoin::util::bip340; // For Schnorr signatures
+use std::fmt;
+
+use anya::bitcoin::taproot::MuSigSession;
+use bitcoin::secp256k1::PublicKey;
+
+/// Errors from secure multiparty computation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SMCError {
+    /// Keys could not be combined
+    KeyCombination(String),
+    /// No or inconsistent secret shares
+    InvalidShares,
+}
+
+impl fmt::Display for SMCError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SMCError::KeyCombination(e) => write!(f, "Key combination failed: {}", e),
+            SMCError::InvalidShares => write!(f, "Invalid secret shares"),
+        }
+    }
+}
+
+impl std::error::Error for SMCError {}
+
+/// Secure multiparty computation helpers
+#[derive(Debug, Default)]
+pub struct SecureMultipartyComputation;
 
 impl SecureMultipartyComputation {
     // Add Taproot/Schnorr support
-    pub fn schnorr_musig(&self, signers: Vec<&SecretKey>) -> Result<(SecretKey, Vec<PublicKey>), SMCError> {
-        // MuSig2 implementation for Taproot
-        todo!("Implement MuSig2")
+    pub fn schnorr_musig(&self, public_keys: &[PublicKey]) -> Result<MuSigSession, SMCError> {
+        self.schnorr_musig2(public_keys)
     }
 
-    // Implement MuSig2 for Taproot
-    pub fn schnorr_musig2(&self, signers: Vec<&SecretKey>) -> Result<(SecretKey, Vec<PublicKey>), SMCError> {
-        let secp = Secp256k1::new();
-        let mut combined_key = None;
-        let mut public_keys = Vec::new();
-
-        for signer in signers {
-            let pub_key = PublicKey::from_secret_key(&secp, signer);
-            public_keys.push(pub_key);
-            
-            if let Some(key) = combined_key {
-                combined_key = Some(key.combine(&pub_key)
-                    .map_err(|e| SMCError::KeyCombination(e.to_string()))?);
-            } else {
-                combined_key = Some(pub_key);
-            }
-        }
-
-        // Add nonce generation and commitment phases
-        todo!("Implement full MuSig2 protocol")
+    /// Starts a MuSig2 (BIP327) signing session for the signers' public keys
+    ///
+    /// Keys are aggregated in the given order. Each signer then runs the two
+    /// rounds of the returned session with its own secret key, so no secret
+    /// key ever leaves its signer.
+    pub fn schnorr_musig2(&self, public_keys: &[PublicKey]) -> Result<MuSigSession, SMCError> {
+        MuSigSession::from_public_keys(public_keys)
+            .map_err(|e| SMCError::KeyCombination(e.to_string()))
     }
 
-    // Fix duplicate reconstruct_secret implementation
-    fn reconstruct_secret(&self, shares: Vec<Vec<u8>>) -> Result<Vec<u8>, SMCError> {
+    /// Recombines a secret from its XOR shares
+    pub fn reconstruct_secret(&self, shares: Vec<Vec<u8>>) -> Result<Vec<u8>, SMCError> {
         if shares.is_empty() {
             return Err(SMCError::InvalidShares);
         }
@@ -45,3 +58,36 @@ fn reconstruct_secret(&self, shares: Vec<Vec<u8>>) -> Result<Vec<u8>, SMCError>
         Ok(secret)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+
+    #[test]
+    fn test_schnorr_musig2_session_from_public_keys() {
+        let secp = Secp256k1::new();
+        let secret_keys: Vec<SecretKey> = (1u8..=2).map(|i| SecretKey::from_slice(&[i; 32]).unwrap()).collect();
+        let public_keys: Vec<PublicKey> = secret_keys.iter().map(|key| PublicKey::from_secret_key(&secp, key)).collect();
+
+        let mut session = SecureMultipartyComputation.schnorr_musig2(&public_keys).unwrap();
+        let message = Message::from_digest([9; 32]);
+        session.set_message(message).unwrap();
+
+        let nonces: Vec<_> = secret_keys.iter().map(|key| session.generate_nonce(key).unwrap()).collect();
+        for (nonce, key) in nonces.into_iter().zip(&secret_keys) {
+            session.sign(nonce, key).unwrap();
+        }
+
+        let signature = session.aggregate_signature().unwrap();
+        assert!(secp.verify_schnorr(&signature, &message, &session.aggregate_key().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_schnorr_musig2_requires_keys() {
+        assert!(matches!(
+            SecureMultipartyComputation.schnorr_musig2(&[]),
+            Err(SMCError::KeyCombination(_))
+        ));
+    }
+}
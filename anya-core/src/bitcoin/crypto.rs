@@ -0,0 +1,74 @@
+// src/bitcoin/crypto.rs
+
+//! Hashing and scalar helpers shared by the DLC and Taproot modules
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::SecretKey;
+
+/// 2^256 minus the group order, added to reduce a 256-bit value modulo the order
+const ORDER_COMPLEMENT: [u8; 32] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01,
+    0x45, 0x51, 0x23, 0x19, 0x50, 0xb7, 0x5f, 0xc4, 0x40, 0x2d, 0xa1, 0x73, 0x2f, 0xc9, 0xbe, 0xbf,
+];
+
+/// BIP340-style tagged hash: `SHA256(SHA256(tag) || SHA256(tag) || data...)`
+pub(crate) fn tagged_hash(tag: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag_hash.as_ref());
+    engine.input(tag_hash.as_ref());
+    for part in parts {
+        engine.input(part);
+    }
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Reduce 32 bytes modulo the group order; `None` when the result is zero
+pub(crate) fn reduce_scalar(bytes: [u8; 32]) -> Option<SecretKey> {
+    if let Ok(key) = SecretKey::from_slice(&bytes) {
+        return Some(key);
+    }
+    if bytes == [0u8; 32] {
+        return None;
+    }
+    // bytes >= n: bytes - n == bytes + (2^256 - n) mod 2^256
+    let mut reduced = [0u8; 32];
+    let mut carry = 0u16;
+    for i in (0..32).rev() {
+        let sum = bytes[i] as u16 + ORDER_COMPLEMENT[i] as u16 + carry;
+        reduced[i] = sum as u8;
+        carry = sum >> 8;
+    }
+    SecretKey::from_slice(&reduced).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::constants;
+    use bitcoin::taproot::TapTweakHash;
+    use bitcoin::XOnlyPublicKey;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_tagged_hash_matches_taptweak() {
+        let key = XOnlyPublicKey::from_str("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d").unwrap();
+        assert_eq!(
+            tagged_hash("TapTweak", &[&key.serialize()]),
+            TapTweakHash::from_key_and_tweak(key, None).to_byte_array()
+        );
+    }
+
+    #[test]
+    fn test_reduce_scalar() {
+        assert!(reduce_scalar([0u8; 32]).is_none());
+
+        // n reduces to zero, n + 1 to one
+        let mut order = constants::CURVE_ORDER;
+        assert!(reduce_scalar(order).is_none());
+        order[31] += 1;
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        assert_eq!(reduce_scalar(order).unwrap().secret_bytes(), one);
+    }
+}
@@ -1,24 +1,41 @@
 // src/bitcoin/dlc/adaptor.rs
 
-use bitcoin::secp256k1::{PublicKey, SecretKey, Signature};
-use bitcoin::Transaction;
+use bitcoin::key::Parity;
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::{schnorr, Message, PublicKey, Scalar, Secp256k1, SecretKey};
 
-use crate::common::error::AnyaResult;
+use crate::bitcoin::crypto::{reduce_scalar, tagged_hash};
+use crate::AnyaResult;
 
 /// Adaptor signature for DLCs
 ///
 /// Adaptor signatures are a cryptographic primitive that allows for transaction signatures
 /// to be encrypted under a certain condition (e.g., an oracle's attestation signature).
-#[derive(Debug, Clone)]
+///
+/// ECDSA adaptor signatures use the dlcspecs 162-byte encoding:
+/// `R (33) || R_a (33) || s_a (32) || DLEQ proof e (32) || DLEQ proof s (32)`,
+/// where `R = k*Y` is the final nonce, `R_a = k*G` and `s_a = k^-1 (m + r x)`.
+/// The DLEQ proof shows `R` and `R_a` share the discrete log `k`.
+///
+/// Schnorr (BIP340) adaptor signatures are 65 bytes: `R_a (33) || s_a (32)`.
+/// The final nonce is `R = R_a + T`; when `R` has an odd y-coordinate the
+/// signer negates its nonce so that `-R` is the final nonce instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AdaptorSignature {
     /// The encrypted signature data
     pub encrypted_data: Vec<u8>,
-    
+
     /// The public key used for encryption (point)
     pub encryption_point: PublicKey,
 }
 
 impl AdaptorSignature {
+    /// Length of an encoded ECDSA adaptor signature
+    pub const ECDSA_LEN: usize = 162;
+
+    /// Length of an encoded Schnorr adaptor signature
+    pub const SCHNORR_LEN: usize = 65;
+
     /// Creates a new adaptor signature
     pub fn new(encrypted_data: Vec<u8>, encryption_point: PublicKey) -> Self {
         Self {
@@ -26,58 +43,304 @@ pub fn new(encrypted_data: Vec<u8>, encryption_point: PublicKey) -> Self {
             encryption_point,
         }
     }
-    
+
+    /// Parses an encoded ECDSA adaptor signature encrypted under `encryption_point`
+    pub fn from_slice(data: &[u8], encryption_point: PublicKey) -> AnyaResult<Self> {
+        if data.len() != Self::ECDSA_LEN {
+            return Err(format!("Adaptor signature must be {} bytes, got {}", Self::ECDSA_LEN, data.len()).into());
+        }
+        let signature = Self::new(data.to_vec(), encryption_point);
+        signature.ecdsa_parts()?;
+        Ok(signature)
+    }
+
+    /// The scheme the signature was created with, from its encoded length
+    pub fn scheme(&self) -> AnyaResult<AdaptorSignerType> {
+        match self.encrypted_data.len() {
+            Self::ECDSA_LEN => Ok(AdaptorSignerType::Ecdsa),
+            Self::SCHNORR_LEN => Ok(AdaptorSignerType::Schnorr),
+            len => Err(format!("Invalid adaptor signature length {}", len).into()),
+        }
+    }
+
+    /// Splits an ECDSA encoding into `(R, R_a, s_a, e, s)`
+    fn ecdsa_parts(&self) -> AnyaResult<(PublicKey, PublicKey, SecretKey, SecretKey, SecretKey)> {
+        let data = &self.encrypted_data;
+        if data.len() != Self::ECDSA_LEN {
+            return Err(format!("ECDSA adaptor signature must be {} bytes, got {}", Self::ECDSA_LEN, data.len()).into());
+        }
+        Ok((
+            parse_point(&data[0..33])?,
+            parse_point(&data[33..66])?,
+            parse_scalar(&data[66..98])?,
+            parse_scalar(&data[98..130])?,
+            parse_scalar(&data[130..162])?,
+        ))
+    }
+
+    /// Splits a Schnorr encoding into `(R_a, s_a)`
+    fn schnorr_parts(&self) -> AnyaResult<(PublicKey, SecretKey)> {
+        let data = &self.encrypted_data;
+        if data.len() != Self::SCHNORR_LEN {
+            return Err(format!("Schnorr adaptor signature must be {} bytes, got {}", Self::SCHNORR_LEN, data.len()).into());
+        }
+        Ok((parse_point(&data[0..33])?, parse_scalar(&data[33..65])?))
+    }
+
     /// Verifies that this adaptor signature is valid for the given message and public key
+    ///
+    /// `message` is the 32-byte signature hash. Schnorr adaptor signatures
+    /// verify against the x-only form of `public_key`.
     pub fn verify(&self, message: &[u8], public_key: &PublicKey) -> AnyaResult<bool> {
-        // Implementation goes here
-        // In a real implementation, this would verify that the adaptor signature
-        // is valid for the given message and public key
-        unimplemented!("Adaptor signature verification not yet implemented")
+        match self.scheme() {
+            Ok(AdaptorSignerType::Ecdsa) => self.verify_ecdsa(message, public_key),
+            Ok(AdaptorSignerType::Schnorr) => self.verify_schnorr(message, public_key),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn verify_ecdsa(&self, message: &[u8], public_key: &PublicKey) -> AnyaResult<bool> {
+        let secp = Secp256k1::new();
+        let message = message_scalar(message)?;
+        let (r_point, r_a, s_a, proof_e, proof_s) = match self.ecdsa_parts() {
+            Ok(parts) => parts,
+            Err(_) => return Ok(false),
+        };
+
+        // DLEQ: log_G(R_a) == log_Y(R)
+        if !verify_dleq(&secp, &self.encryption_point, &r_a, &r_point, &proof_e, &proof_s)? {
+            return Ok(false);
+        }
+
+        // s_a * R_a == m*G + r*X
+        let r = x_coordinate_scalar(&r_point)?;
+        let lhs = r_a.mul_tweak(&secp, &Scalar::from(s_a))
+            .map_err(|e| format!("Adaptor verification failed: {}", e))?;
+        let rx = public_key.mul_tweak(&secp, &Scalar::from(r))
+            .map_err(|e| format!("Adaptor verification failed: {}", e))?;
+        let rhs = match message {
+            Some(m) => PublicKey::from_secret_key(&secp, &m).combine(&rx),
+            None => Ok(rx),
+        };
+        Ok(rhs.map(|rhs| rhs == lhs).unwrap_or(false))
+    }
+
+    fn verify_schnorr(&self, message: &[u8], public_key: &PublicKey) -> AnyaResult<bool> {
+        let secp = Secp256k1::new();
+        let (r_a, s_a) = match self.schnorr_parts() {
+            Ok(parts) => parts,
+            Err(_) => return Ok(false),
+        };
+        let Some((r_point, negated)) = schnorr_final_nonce(&r_a, &self.encryption_point) else {
+            return Ok(false);
+        };
+
+        // s_a*G == ±R_a + e*P
+        let challenge = bip340_challenge(&r_point, public_key, message)?;
+        let nonce_point = if negated { r_a.negate(&secp) } else { r_a };
+        let expected = even_key(public_key)
+            .mul_tweak(&secp, &Scalar::from(challenge))
+            .and_then(|ep| nonce_point.combine(&ep));
+        Ok(expected.map(|expected| expected == PublicKey::from_secret_key(&secp, &s_a)).unwrap_or(false))
     }
-    
+
     /// Decrypts the adaptor signature using the given secret key
-    pub fn decrypt(&self, secret: &SecretKey) -> AnyaResult<Signature> {
-        // Implementation goes here
-        // In a real implementation, this would decrypt the adaptor signature
-        // using the given secret key
-        unimplemented!("Adaptor signature decryption not yet implemented")
+    ///
+    /// The secret must be the discrete log of `encryption_point`; for DLCs it is
+    /// the oracle's attestation scalar. The decrypted signature has the same
+    /// scheme as the adaptor signature.
+    pub fn decrypt(&self, secret: &SecretKey) -> AnyaResult<DecryptedSignature> {
+        match self.scheme()? {
+            AdaptorSignerType::Ecdsa => EcdsaAdaptorSigner
+                .decrypt_adaptor_signature(self, secret)
+                .map(DecryptedSignature::Ecdsa),
+            AdaptorSignerType::Schnorr => SchnorrAdaptorSigner
+                .decrypt_adaptor_signature(self, secret)
+                .map(DecryptedSignature::Schnorr),
+        }
+    }
+}
+
+/// A signature decrypted from an [`AdaptorSignature`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecryptedSignature {
+    /// ECDSA signature, for CETs spending the P2WSH funding output
+    Ecdsa(Signature),
+
+    /// BIP340 signature, for Taproot CETs
+    Schnorr(schnorr::Signature),
+}
+
+impl DecryptedSignature {
+    /// The ECDSA signature, or an error for a Schnorr signature
+    pub fn into_ecdsa(self) -> AnyaResult<Signature> {
+        match self {
+            DecryptedSignature::Ecdsa(signature) => Ok(signature),
+            DecryptedSignature::Schnorr(_) => Err("Expected an ECDSA adaptor signature, got a Schnorr one".into()),
+        }
+    }
+
+    /// The Schnorr signature, or an error for an ECDSA signature
+    pub fn into_schnorr(self) -> AnyaResult<schnorr::Signature> {
+        match self {
+            DecryptedSignature::Schnorr(signature) => Ok(signature),
+            DecryptedSignature::Ecdsa(_) => Err("Expected a Schnorr adaptor signature, got an ECDSA one".into()),
+        }
     }
 }
 
 /// Interface for creating and verifying adaptor signatures
 pub trait AdaptorSigner {
-    /// Creates an adaptor signature for a transaction
+    /// Signature produced by decrypting an adaptor signature
+    type Signature;
+
+    /// Creates an adaptor signature over a signature hash
     fn create_adaptor_signature(
         &self,
-        transaction: &Transaction,
+        message: &Message,
         secret_key: &SecretKey,
         encryption_point: &PublicKey,
     ) -> AnyaResult<AdaptorSignature>;
-    
-    /// Verifies an adaptor signature for a transaction
+
+    /// Verifies an adaptor signature over a signature hash
     fn verify_adaptor_signature(
         &self,
-        transaction: &Transaction,
+        message: &Message,
         signature: &AdaptorSignature,
         public_key: &PublicKey,
     ) -> AnyaResult<bool>;
-    
+
     /// Decrypts an adaptor signature using a decryption key
     fn decrypt_adaptor_signature(
         &self,
         signature: &AdaptorSignature,
         decryption_key: &SecretKey,
-    ) -> AnyaResult<Signature>;
-    
-    /// Encrypts a signature using an encryption point
-    fn encrypt_signature(
+    ) -> AnyaResult<Self::Signature>;
+
+    /// Recovers the decryption key from an adaptor signature and the
+    /// signature decrypted from it
+    fn recover_decryption_key(
         &self,
-        signature: &Signature,
+        signature: &AdaptorSignature,
+        decrypted: &Self::Signature,
+    ) -> AnyaResult<SecretKey>;
+}
+
+/// Implementation of the AdaptorSigner trait using ECDSA signatures
+///
+/// This is the scheme dlcspecs uses for CETs spending the P2WSH funding output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EcdsaAdaptorSigner;
+
+impl EcdsaAdaptorSigner {
+    /// Creates a new ECDSA adaptor signer
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl AdaptorSigner for EcdsaAdaptorSigner {
+    type Signature = Signature;
+
+    fn create_adaptor_signature(
+        &self,
+        message: &Message,
+        secret_key: &SecretKey,
         encryption_point: &PublicKey,
-    ) -> AnyaResult<AdaptorSignature>;
+    ) -> AnyaResult<AdaptorSignature> {
+        let secp = Secp256k1::new();
+        let digest = message.as_ref();
+        let m = message_scalar(digest)?;
+
+        // Deterministic nonce bound to the key, message and encryption point
+        let k = hash_to_secret(tagged_hash("DLC/adaptor/nonce", &[
+            &secret_key.secret_bytes(),
+            digest,
+            &encryption_point.serialize(),
+        ]))?;
+        let r_a = PublicKey::from_secret_key(&secp, &k);
+        let r_point = encryption_point.mul_tweak(&secp, &Scalar::from(k))
+            .map_err(|e| format!("Adaptor signing failed: {}", e))?;
+        let r = x_coordinate_scalar(&r_point)?;
+
+        // s_a = k^-1 (m + r x)
+        let rx = mul(secret_key, &r)?;
+        let sum = match m {
+            Some(m) => rx.add_tweak(&Scalar::from(m))
+                .map_err(|e| format!("Adaptor signing failed: {}", e))?,
+            None => rx,
+        };
+        let s_a = mul(&sum, &inverse(&k)?)?;
+
+        let (proof_e, proof_s) = prove_dleq(&secp, &k, encryption_point, &r_a, &r_point)?;
+
+        let mut data = Vec::with_capacity(AdaptorSignature::ECDSA_LEN);
+        data.extend_from_slice(&r_point.serialize());
+        data.extend_from_slice(&r_a.serialize());
+        data.extend_from_slice(&s_a.secret_bytes());
+        data.extend_from_slice(&proof_e.secret_bytes());
+        data.extend_from_slice(&proof_s.secret_bytes());
+        Ok(AdaptorSignature::new(data, *encryption_point))
+    }
+
+    fn verify_adaptor_signature(
+        &self,
+        message: &Message,
+        signature: &AdaptorSignature,
+        public_key: &PublicKey,
+    ) -> AnyaResult<bool> {
+        if signature.encrypted_data.len() != AdaptorSignature::ECDSA_LEN {
+            return Ok(false);
+        }
+        signature.verify_ecdsa(message.as_ref(), public_key)
+    }
+
+    fn decrypt_adaptor_signature(
+        &self,
+        signature: &AdaptorSignature,
+        decryption_key: &SecretKey,
+    ) -> AnyaResult<Signature> {
+        let secp = Secp256k1::signing_only();
+        if PublicKey::from_secret_key(&secp, decryption_key) != signature.encryption_point {
+            return Err("Decryption key does not match the encryption point".into());
+        }
+        let (r_point, _, s_a, _, _) = signature.ecdsa_parts()?;
+        let r = x_coordinate_scalar(&r_point)?;
+        let s = mul(&s_a, &inverse(decryption_key)?)?;
+
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(&r.secret_bytes());
+        compact[32..].copy_from_slice(&s.secret_bytes());
+        let mut decrypted = Signature::from_compact(&compact)
+            .map_err(|e| format!("Invalid decrypted signature: {}", e))?;
+        decrypted.normalize_s();
+        Ok(decrypted)
+    }
+
+    fn recover_decryption_key(
+        &self,
+        signature: &AdaptorSignature,
+        decrypted: &Signature,
+    ) -> AnyaResult<SecretKey> {
+        let secp = Secp256k1::signing_only();
+        let (r_point, _, s_a, _, _) = signature.ecdsa_parts()?;
+        let compact = decrypted.serialize_compact();
+        if compact[..32] != x_coordinate_scalar(&r_point)?.secret_bytes() {
+            return Err("Signature was not decrypted from this adaptor signature".into());
+        }
+
+        // s = s_a * y^-1, up to the sign lost by low-s normalization
+        let y = mul(&s_a, &inverse(&parse_scalar(&compact[32..])?)?)?;
+        [y, y.negate()].into_iter()
+            .find(|candidate| PublicKey::from_secret_key(&secp, candidate) == signature.encryption_point)
+            .ok_or_else(|| "Signature was not decrypted from this adaptor signature".into())
+    }
 }
 
 /// Implementation of the AdaptorSigner trait using Schnorr signatures
+///
+/// Decrypted signatures are BIP340 signatures, as used by Taproot DLC CETs.
+#[derive(Debug, Clone, Copy, Default)]
 pub struct SchnorrAdaptorSigner;
 
 impl SchnorrAdaptorSigner {
@@ -88,74 +351,422 @@ pub fn new() -> Self {
 }
 
 impl AdaptorSigner for SchnorrAdaptorSigner {
+    type Signature = schnorr::Signature;
+
     fn create_adaptor_signature(
         &self,
-        transaction: &Transaction,
+        message: &Message,
         secret_key: &SecretKey,
         encryption_point: &PublicKey,
     ) -> AnyaResult<AdaptorSignature> {
-        // Implementation goes here
-        // In a real implementation, this would create an adaptor signature
-        // for the given transaction using the secret key and encryption point
-        unimplemented!("Schnorr adaptor signature creation not yet implemented")
+        let secp = Secp256k1::new();
+        let public_key = PublicKey::from_secret_key(&secp, secret_key);
+        let secret_key = match public_key.x_only_public_key().1 {
+            Parity::Even => *secret_key,
+            Parity::Odd => secret_key.negate(),
+        };
+
+        let k = hash_to_secret(tagged_hash("DLC/adaptor/schnorr-nonce", &[
+            &secret_key.secret_bytes(),
+            message.as_ref(),
+            &encryption_point.serialize(),
+        ]))?;
+        let r_a = PublicKey::from_secret_key(&secp, &k);
+        let (r_point, negated) = schnorr_final_nonce(&r_a, encryption_point)
+            .ok_or("Adaptor nonce cancels the encryption point")?;
+        let k = if negated { k.negate() } else { k };
+
+        // s_a = k + e*x
+        let challenge = bip340_challenge(&r_point, &public_key, message.as_ref())?;
+        let s_a = mul(&secret_key, &challenge)?
+            .add_tweak(&Scalar::from(k))
+            .map_err(|e| format!("Adaptor signing failed: {}", e))?;
+
+        let mut data = Vec::with_capacity(AdaptorSignature::SCHNORR_LEN);
+        data.extend_from_slice(&r_a.serialize());
+        data.extend_from_slice(&s_a.secret_bytes());
+        Ok(AdaptorSignature::new(data, *encryption_point))
     }
-    
+
     fn verify_adaptor_signature(
         &self,
-        transaction: &Transaction,
+        message: &Message,
         signature: &AdaptorSignature,
         public_key: &PublicKey,
     ) -> AnyaResult<bool> {
-        // Implementation goes here
-        // In a real implementation, this would verify that the adaptor signature
-        // is valid for the given transaction and public key
-        unimplemented!("Schnorr adaptor signature verification not yet implemented")
+        if signature.encrypted_data.len() != AdaptorSignature::SCHNORR_LEN {
+            return Ok(false);
+        }
+        signature.verify_schnorr(message.as_ref(), public_key)
     }
-    
+
     fn decrypt_adaptor_signature(
         &self,
         signature: &AdaptorSignature,
         decryption_key: &SecretKey,
-    ) -> AnyaResult<Signature> {
-        // Implementation goes here
-        // In a real implementation, this would decrypt the adaptor signature
-        // using the given decryption key
-        unimplemented!("Schnorr adaptor signature decryption not yet implemented")
-    }
-    
-    fn encrypt_signature(
-        &self,
-        signature: &Signature,
-        encryption_point: &PublicKey,
-    ) -> AnyaResult<AdaptorSignature> {
-        // Implementation goes here
-        // In a real implementation, this would encrypt the signature
-        // using the given encryption point
-        unimplemented!("Schnorr signature encryption not yet implemented")
+    ) -> AnyaResult<schnorr::Signature> {
+        let secp = Secp256k1::signing_only();
+        if PublicKey::from_secret_key(&secp, decryption_key) != signature.encryption_point {
+            return Err("Decryption key does not match the encryption point".into());
+        }
+        let (r_a, s_a) = signature.schnorr_parts()?;
+        let (r_point, negated) = schnorr_final_nonce(&r_a, &signature.encryption_point)
+            .ok_or("Adaptor nonce cancels the encryption point")?;
+
+        // s = s_a + t, or s_a - t when the nonce was negated
+        let t = if negated { decryption_key.negate() } else { *decryption_key };
+        let s = s_a.add_tweak(&Scalar::from(t))
+            .map_err(|e| format!("Adaptor decryption failed: {}", e))?;
+
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&r_point.x_only_public_key().0.serialize());
+        bytes[32..].copy_from_slice(&s.secret_bytes());
+        schnorr::Signature::from_slice(&bytes)
+            .map_err(|e| format!("Invalid decrypted signature: {}", e).into())
     }
-}
 
-/// Factory for creating adaptor signers
-pub struct AdaptorSignerFactory;
+    fn recover_decryption_key(
+        &self,
+        signature: &AdaptorSignature,
+        decrypted: &schnorr::Signature,
+    ) -> AnyaResult<SecretKey> {
+        let secp = Secp256k1::signing_only();
+        let (r_a, s_a) = signature.schnorr_parts()?;
+        let (r_point, negated) = schnorr_final_nonce(&r_a, &signature.encryption_point)
+            .ok_or("Adaptor nonce cancels the encryption point")?;
+        let bytes = decrypted.as_ref();
+        if bytes[..32] != r_point.x_only_public_key().0.serialize() {
+            return Err("Signature was not decrypted from this adaptor signature".into());
+        }
 
-impl AdaptorSignerFactory {
-    /// Creates a new adaptor signer of the specified type
-    pub fn create_signer(signer_type: AdaptorSignerType) -> Box<dyn AdaptorSigner> {
-        match signer_type {
-            AdaptorSignerType::Schnorr => Box::new(SchnorrAdaptorSigner::new()),
+        // t = ±(s - s_a)
+        let difference = parse_scalar(&bytes[32..])?
+            .add_tweak(&Scalar::from(s_a.negate()))
+            .map_err(|_| "Signature was not decrypted from this adaptor signature")?;
+        let t = if negated { difference.negate() } else { difference };
+        if PublicKey::from_secret_key(&secp, &t) != signature.encryption_point {
+            return Err("Signature was not decrypted from this adaptor signature".into());
         }
+        Ok(t)
     }
 }
 
 /// Types of adaptor signers
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AdaptorSignerType {
+    /// ECDSA adaptor signatures, as used by dlcspecs CETs
+    #[default]
+    Ecdsa,
+
     /// Schnorr-based adaptor signatures
     Schnorr,
 }
 
-impl Default for AdaptorSignerType {
-    fn default() -> Self {
-        Self::Schnorr
+fn parse_point(bytes: &[u8]) -> AnyaResult<PublicKey> {
+    PublicKey::from_slice(bytes).map_err(|e| format!("Invalid adaptor signature point: {}", e).into())
+}
+
+fn parse_scalar(bytes: &[u8]) -> AnyaResult<SecretKey> {
+    SecretKey::from_slice(bytes).map_err(|e| format!("Invalid adaptor signature scalar: {}", e).into())
+}
+
+/// A key lifted to an even y-coordinate, as BIP340 uses it
+fn even_key(public_key: &PublicKey) -> PublicKey {
+    PublicKey::from_x_only_public_key(public_key.x_only_public_key().0, Parity::Even)
+}
+
+/// Final Schnorr nonce `R_a + T` lifted to even y, and whether it was negated
+fn schnorr_final_nonce(r_a: &PublicKey, encryption_point: &PublicKey) -> Option<(PublicKey, bool)> {
+    let r_point = r_a.combine(encryption_point).ok()?;
+    let negated = r_point.x_only_public_key().1 == Parity::Odd;
+    Some((even_key(&r_point), negated))
+}
+
+/// BIP340 challenge `H(R_x || P_x || m)` as a scalar
+pub(crate) fn bip340_challenge(nonce: &PublicKey, public_key: &PublicKey, message: &[u8]) -> AnyaResult<SecretKey> {
+    hash_to_secret(tagged_hash("BIP0340/challenge", &[
+        &nonce.x_only_public_key().0.serialize(),
+        &public_key.x_only_public_key().0.serialize(),
+        message,
+    ]))
+}
+
+/// secp256k1 group order minus two, the exponent for Fermat inversion
+const ORDER_MINUS_TWO: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x3f,
+];
+
+fn hash_to_secret(hash: [u8; 32]) -> AnyaResult<SecretKey> {
+    reduce_scalar(hash).ok_or_else(|| "Hash reduced to zero".into())
+}
+
+/// The message digest as a scalar; `None` for the zero scalar
+fn message_scalar(message: &[u8]) -> AnyaResult<Option<SecretKey>> {
+    let bytes: [u8; 32] = message.try_into()
+        .map_err(|_| format!("Message must be 32 bytes, got {}", message.len()))?;
+    Ok(reduce_scalar(bytes))
+}
+
+/// The x-coordinate of a point reduced modulo the group order (ECDSA `r`)
+fn x_coordinate_scalar(point: &PublicKey) -> AnyaResult<SecretKey> {
+    let mut x = [0u8; 32];
+    x.copy_from_slice(&point.serialize()[1..33]);
+    reduce_scalar(x).ok_or_else(|| "Nonce x-coordinate reduced to zero".into())
+}
+
+fn mul(a: &SecretKey, b: &SecretKey) -> AnyaResult<SecretKey> {
+    a.mul_tweak(&Scalar::from(*b)).map_err(|e| format!("Scalar multiplication failed: {}", e).into())
+}
+
+/// Modular inverse by Fermat's little theorem: a^(n-2)
+pub(crate) fn inverse(a: &SecretKey) -> AnyaResult<SecretKey> {
+    let mut result: Option<SecretKey> = None;
+    for byte in ORDER_MINUS_TWO {
+        for bit in (0..8).rev() {
+            if let Some(current) = result {
+                result = Some(mul(&current, &current)?);
+            }
+            if (byte >> bit) & 1 == 1 {
+                result = Some(match result {
+                    Some(current) => mul(&current, a)?,
+                    None => *a,
+                });
+            }
+        }
+    }
+    result.ok_or_else(|| "Inversion failed".into())
+}
+
+/// Prove log_G(R_a) == log_Y(R) == k
+fn prove_dleq<C: bitcoin::secp256k1::Signing + bitcoin::secp256k1::Verification>(
+    secp: &Secp256k1<C>,
+    k: &SecretKey,
+    y: &PublicKey,
+    r_a: &PublicKey,
+    r_point: &PublicKey,
+) -> AnyaResult<(SecretKey, SecretKey)> {
+    let nonce = hash_to_secret(tagged_hash("DLEQ/nonce", &[
+        &k.secret_bytes(),
+        &y.serialize(),
+        &r_a.serialize(),
+        &r_point.serialize(),
+    ]))?;
+    let a_g = PublicKey::from_secret_key(secp, &nonce);
+    let a_y = y.mul_tweak(secp, &Scalar::from(nonce))
+        .map_err(|e| format!("DLEQ proof failed: {}", e))?;
+    let e = dleq_challenge(y, r_a, r_point, &a_g, &a_y)?;
+    let s = mul(&e, k)?.add_tweak(&Scalar::from(nonce))
+        .map_err(|e| format!("DLEQ proof failed: {}", e))?;
+    Ok((e, s))
+}
+
+/// Check a DLEQ proof: A_G = s*G - e*R_a, A_Y = s*Y - e*R, e == H(...)
+fn verify_dleq<C: bitcoin::secp256k1::Signing + bitcoin::secp256k1::Verification>(
+    secp: &Secp256k1<C>,
+    y: &PublicKey,
+    r_a: &PublicKey,
+    r_point: &PublicKey,
+    e: &SecretKey,
+    s: &SecretKey,
+) -> AnyaResult<bool> {
+    let minus_e = Scalar::from(e.negate());
+    let s_g = PublicKey::from_secret_key(secp, s);
+    let s_y = y.mul_tweak(secp, &Scalar::from(*s))
+        .map_err(|e| format!("DLEQ verification failed: {}", e))?;
+    let combine = |sp: PublicKey, point: &PublicKey| -> Option<PublicKey> {
+        let neg = point.mul_tweak(secp, &minus_e).ok()?;
+        sp.combine(&neg).ok()
+    };
+    let (a_g, a_y) = match (combine(s_g, r_a), combine(s_y, r_point)) {
+        (Some(a_g), Some(a_y)) => (a_g, a_y),
+        _ => return Ok(false),
+    };
+    Ok(dleq_challenge(y, r_a, r_point, &a_g, &a_y)? == *e)
+}
+
+/// DLEQ challenge in the secp256k1-zkp / dlcspecs encoding:
+/// `H_DLEQ(P1 || Y || P2 || A_G || A_Y)` with `P1 = R_a` and `P2 = R`
+fn dleq_challenge(
+    y: &PublicKey,
+    r_a: &PublicKey,
+    r_point: &PublicKey,
+    a_g: &PublicKey,
+    a_y: &PublicKey,
+) -> AnyaResult<SecretKey> {
+    hash_to_secret(tagged_hash("DLEQ", &[
+        &r_a.serialize(),
+        &y.serialize(),
+        &r_point.serialize(),
+        &a_g.serialize(),
+        &a_y.serialize(),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn key(byte: u8) -> SecretKey {
+        SecretKey::from_slice(&[byte; 32]).unwrap()
+    }
+
+    fn secret_key() -> impl Strategy<Value = SecretKey> {
+        any::<[u8; 32]>().prop_filter_map("not a valid secret key", |bytes| SecretKey::from_slice(&bytes).ok())
+    }
+
+    #[test]
+    fn test_ecdsa_adaptor_roundtrip() {
+        let secp = Secp256k1::new();
+        let signer = EcdsaAdaptorSigner::new();
+        let signing_key = key(0x11);
+        let public_key = PublicKey::from_secret_key(&secp, &signing_key);
+        let decryption_key = key(0x22);
+        let encryption_point = PublicKey::from_secret_key(&secp, &decryption_key);
+        let message = Message::from_digest([0x33; 32]);
+
+        let adaptor = signer.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap();
+        assert_eq!(adaptor.scheme().unwrap(), AdaptorSignerType::Ecdsa);
+        assert!(signer.verify_adaptor_signature(&message, &adaptor, &public_key).unwrap());
+
+        // Wrong key, message or tampered proof fail verification
+        let other_key = PublicKey::from_secret_key(&secp, &key(0x44));
+        assert!(!adaptor.verify(&[0x34; 32], &public_key).unwrap());
+        assert!(!adaptor.verify(message.as_ref(), &other_key).unwrap());
+        let mut tampered = adaptor.clone();
+        tampered.encrypted_data[100] ^= 1;
+        assert!(!tampered.verify(message.as_ref(), &public_key).unwrap());
+
+        let signature = signer.decrypt_adaptor_signature(&adaptor, &decryption_key).unwrap();
+        assert!(secp.verify_ecdsa(&message, &signature, &public_key).is_ok());
+        assert!(adaptor.decrypt(&key(0x45)).is_err());
+        assert_eq!(signer.recover_decryption_key(&adaptor, &signature).unwrap(), decryption_key);
     }
-} 
\ No newline at end of file
+
+    /// Verification vector from the dlcspecs ECDSA adaptor test vectors, as
+    /// used by secp256k1-zkp's `ecdsa_adaptor` module
+    #[test]
+    fn test_ecdsa_adaptor_spec_vector() {
+        let bytes = |s: &str| hex::decode(s).unwrap();
+        let adaptor_sig = bytes(
+            "03424d14a5471c048ab87b3b83f6085d125d5864249ae4297a57c84e74710bb673\
+             0223f325042fce535d040fee52ec13231bf709ccd84233c6944b90317e62528b25\
+             27dff9d659a96db4c99f9750168308633c1867b70f3a18fb0f4539a1aecedcd1fc\
+             0148fc22f36b6303083ece3f872b18e35d368b3958efe5fb081f7716736ccb598d\
+             269aa3084d57e1855e1ea9a45efc10463bbf32ae378029f5763ceb40173f",
+        );
+        let message = Message::from_digest_slice(&bytes(
+            "8131e6f4b45754f2c90bd06688ceeabc0c45055460729928b4eecf11026a9e2d",
+        )).unwrap();
+        let public_key = PublicKey::from_slice(&bytes(
+            "035be5e9478209674a96e60f1f037f6176540fd001fa1d64694770c56a7709c42c",
+        )).unwrap();
+        let encryption_point = PublicKey::from_slice(&bytes(
+            "02c2662c97488b07b6e819124b8989849206334a4c2fbdf691f7b34d2b16e9c293",
+        )).unwrap();
+        let decryption_key = SecretKey::from_slice(&bytes(
+            "0b2aba63b885a0f0e96fa0f303920c7fb7431ddfa94376ad94d969fbf4109dc8",
+        )).unwrap();
+        let expected = Signature::from_compact(&bytes(
+            "424d14a5471c048ab87b3b83f6085d125d5864249ae4297a57c84e74710bb673\
+             29e80e0ee60e57af3e625bbae1672b1ecaa58effe613426b024fa1621d903394",
+        )).unwrap();
+
+        let signer = EcdsaAdaptorSigner::new();
+        let adaptor = AdaptorSignature::from_slice(&adaptor_sig, encryption_point).unwrap();
+        assert!(signer.verify_adaptor_signature(&message, &adaptor, &public_key).unwrap());
+        let signature = adaptor.decrypt(&decryption_key).unwrap().into_ecdsa().unwrap();
+        assert_eq!(signature, expected);
+        assert_eq!(signer.recover_decryption_key(&adaptor, &signature).unwrap(), decryption_key);
+
+        // Our own adaptor signatures verify under the same encoding
+        let ours = signer.create_adaptor_signature(&message, &decryption_key, &encryption_point).unwrap();
+        let secp = Secp256k1::new();
+        let our_key = PublicKey::from_secret_key(&secp, &decryption_key);
+        assert!(signer.verify_adaptor_signature(&message, &ours, &our_key).unwrap());
+    }
+
+    #[test]
+    fn test_schnorr_adaptor_roundtrip() {
+        let secp = Secp256k1::new();
+        let signer = SchnorrAdaptorSigner::new();
+        let signing_key = key(0x11);
+        let public_key = PublicKey::from_secret_key(&secp, &signing_key);
+        let decryption_key = key(0x22);
+        let encryption_point = PublicKey::from_secret_key(&secp, &decryption_key);
+        let message = Message::from_digest([0x33; 32]);
+
+        let adaptor = signer.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap();
+        assert_eq!(adaptor.scheme().unwrap(), AdaptorSignerType::Schnorr);
+        assert!(signer.verify_adaptor_signature(&message, &adaptor, &public_key).unwrap());
+        assert!(!adaptor.verify(&[0x34; 32], &public_key).unwrap());
+        assert!(!EcdsaAdaptorSigner.verify_adaptor_signature(&message, &adaptor, &public_key).unwrap());
+
+        let signature = signer.decrypt_adaptor_signature(&adaptor, &decryption_key).unwrap();
+        assert!(secp.verify_schnorr(&signature, &message, &public_key.x_only_public_key().0).is_ok());
+        assert_eq!(signer.recover_decryption_key(&adaptor, &signature).unwrap(), decryption_key);
+        assert_eq!(adaptor.decrypt(&decryption_key).unwrap(), DecryptedSignature::Schnorr(signature));
+        assert!(adaptor.decrypt(&decryption_key).unwrap().into_ecdsa().is_err());
+
+        // A signature made without the adaptor reveals nothing
+        let unrelated = secp.sign_schnorr_no_aux_rand(&message, &signing_key.keypair(&secp));
+        assert!(signer.recover_decryption_key(&adaptor, &unrelated).is_err());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn prop_ecdsa_decrypts_to_valid_signature(
+            signing_key in secret_key(),
+            decryption_key in secret_key(),
+            digest in any::<[u8; 32]>(),
+        ) {
+            let secp = Secp256k1::new();
+            let signer = EcdsaAdaptorSigner::new();
+            let public_key = PublicKey::from_secret_key(&secp, &signing_key);
+            let encryption_point = PublicKey::from_secret_key(&secp, &decryption_key);
+            let message = Message::from_digest(digest);
+
+            let adaptor = signer.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap();
+            prop_assert!(signer.verify_adaptor_signature(&message, &adaptor, &public_key).unwrap());
+            let signature = signer.decrypt_adaptor_signature(&adaptor, &decryption_key).unwrap();
+            prop_assert!(secp.verify_ecdsa(&message, &signature, &public_key).is_ok());
+            prop_assert_eq!(signer.recover_decryption_key(&adaptor, &signature).unwrap(), decryption_key);
+        }
+
+        #[test]
+        fn prop_schnorr_decrypts_to_valid_signature(
+            signing_key in secret_key(),
+            decryption_key in secret_key(),
+            digest in any::<[u8; 32]>(),
+        ) {
+            let secp = Secp256k1::new();
+            let signer = SchnorrAdaptorSigner::new();
+            let public_key = PublicKey::from_secret_key(&secp, &signing_key);
+            let encryption_point = PublicKey::from_secret_key(&secp, &decryption_key);
+            let message = Message::from_digest(digest);
+
+            let adaptor = signer.create_adaptor_signature(&message, &signing_key, &encryption_point).unwrap();
+            prop_assert!(signer.verify_adaptor_signature(&message, &adaptor, &public_key).unwrap());
+            let signature = signer.decrypt_adaptor_signature(&adaptor, &decryption_key).unwrap();
+            prop_assert!(secp.verify_schnorr(&signature, &message, &public_key.x_only_public_key().0).is_ok());
+            prop_assert_eq!(signer.recover_decryption_key(&adaptor, &signature).unwrap(), decryption_key);
+        }
+    }
+
+    #[test]
+    fn test_scalar_helpers() {
+        let a = key(0x07);
+        let one = mul(&a, &inverse(&a).unwrap()).unwrap();
+        let mut expected = [0u8; 32];
+        expected[31] = 1;
+        assert_eq!(one.secret_bytes(), expected);
+
+        // n + 1 reduces to 1
+        let mut above_order = ORDER_MINUS_TWO;
+        above_order[31] += 3;
+        assert_eq!(reduce_scalar(above_order).unwrap().secret_bytes(), expected);
+    }
+}
@@ -0,0 +1,200 @@
+// src/bitcoin/dlc/cet.rs
+
+//! Contract execution transactions (CETs) and the attestations unlocking them
+//!
+//! Both parties derive the same ordered CET list from the offer's
+//! `contract_info`. Each CET is encrypted under the sum of the anticipation
+//! points of what its oracles must attest, so the attestations' signature
+//! scalars, summed the same way, decrypt it.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use bitcoin::secp256k1::{PublicKey, Scalar, SecretKey};
+
+use crate::AnyaResult;
+use super::messages::{ContractInfo, ContractOutcomes};
+use super::numeric::{self, NumericCet};
+use super::oracle::{OracleAnnouncement, OracleAttestation};
+
+/// What one oracle must attest to unlock a CET
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OracleOutcome {
+    /// An enumerated outcome
+    Enumerated(String),
+
+    /// Leading digits of a numeric outcome
+    DigitPrefix(Vec<usize>),
+}
+
+/// A CET's payout and what its oracles must attest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CetOutcome {
+    /// Payout to the offering party in satoshis (the accepter gets the rest)
+    pub offer_payout: u64,
+
+    /// Index of each oracle in the contract's announcements, and its outcome
+    pub oracle_outcomes: Vec<(usize, OracleOutcome)>,
+}
+
+impl CetOutcome {
+    /// Key of the CET's execution path: the outcome for a single oracle,
+    /// `oracle:outcome` pairs otherwise
+    pub fn label(&self) -> String {
+        let outcome = |outcome: &OracleOutcome| match outcome {
+            OracleOutcome::Enumerated(outcome) => outcome.clone(),
+            OracleOutcome::DigitPrefix(prefix) => prefix.iter().map(|digit| digit.to_string()).collect(),
+        };
+        match self.oracle_outcomes.as_slice() {
+            [(_, single)] => outcome(single),
+            outcomes => outcomes.iter()
+                .map(|(oracle, o)| format!("{}:{}", oracle, outcome(o)))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Whether the attestations, keyed by oracle index, unlock this CET
+    pub fn matches(&self, attestations: &HashMap<usize, OracleAttestation>) -> bool {
+        self.oracle_outcomes.iter().all(|(oracle, outcome)| {
+            let Some(attestation) = attestations.get(oracle) else {
+                return false;
+            };
+            match outcome {
+                OracleOutcome::Enumerated(outcome) => attestation.signatures.is_empty() && attestation.outcome == *outcome,
+                OracleOutcome::DigitPrefix(prefix) => attestation.digits()
+                    .map(|digits| digits.starts_with(prefix))
+                    .unwrap_or(false),
+            }
+        })
+    }
+
+    /// Sum of the attestation scalars for this CET's outcomes, which decrypts
+    /// its adaptor signatures
+    pub fn decryption_key(&self, attestations: &HashMap<usize, OracleAttestation>) -> AnyaResult<SecretKey> {
+        let mut scalars = Vec::new();
+        for (oracle, outcome) in &self.oracle_outcomes {
+            let attestation = attestations.get(oracle)
+                .ok_or_else(|| format!("Missing attestation from oracle {}", oracle))?;
+            match outcome {
+                OracleOutcome::Enumerated(_) => scalars.push(attestation.attestation_scalar()?),
+                OracleOutcome::DigitPrefix(prefix) => {
+                    scalars.extend(attestation.digit_scalars()?.into_iter().take(prefix.len()));
+                }
+            }
+        }
+        let (first, rest) = scalars.split_first().ok_or("CET has no oracle outcomes")?;
+        rest.iter().try_fold(*first, |sum, scalar| {
+            sum.add_tweak(&Scalar::from(*scalar))
+                .map_err(|e| format!("Failed to sum attestation scalars: {}", e).into())
+        })
+    }
+}
+
+/// The contract's oracle announcements, in `contract_info` order
+pub fn announcements(info: &ContractInfo) -> AnyaResult<Vec<OracleAnnouncement>> {
+    info.oracle_announcements.iter()
+        .map(|announcement| OracleAnnouncement::from_event(&announcement.event, announcement.signature))
+        .collect()
+}
+
+/// The contract's CETs, in order
+///
+/// Every `oracle_threshold`-sized set of oracles gets CETs of its own, so
+/// any such set attesting is enough to settle.
+pub fn cet_outcomes(info: &ContractInfo) -> AnyaResult<Vec<CetOutcome>> {
+    let announcements = announcements(info)?;
+    let threshold = info.oracle_threshold as usize;
+    if announcements.is_empty() || threshold == 0 || threshold > announcements.len() {
+        return Err(format!("Invalid oracle threshold {} of {}", threshold, announcements.len()).into());
+    }
+
+    match &info.outcomes {
+        ContractOutcomes::Enumerated(outcomes) => {
+            if info.bounded_error.is_some() {
+                return Err("Error bounds only apply to numeric contracts".into());
+            }
+            for outcome in outcomes {
+                if outcome.offer_payout > info.total_collateral {
+                    return Err(format!("Payout for outcome '{}' exceeds the collateral", outcome.outcome).into());
+                }
+                if let Some(announcement) = announcements.iter().find(|a| !a.outcomes.contains(&outcome.outcome)) {
+                    return Err(format!("Outcome '{}' is not announced for event {}", outcome.outcome, announcement.event_id).into());
+                }
+            }
+            let mut cets = Vec::new();
+            for oracles in numeric::combinations(announcements.len(), threshold) {
+                for outcome in outcomes {
+                    cets.push(CetOutcome {
+                        offer_payout: outcome.offer_payout,
+                        oracle_outcomes: oracles.iter()
+                            .map(|oracle| (*oracle, OracleOutcome::Enumerated(outcome.outcome.clone())))
+                            .collect(),
+                    });
+                }
+            }
+            Ok(cets)
+        }
+        ContractOutcomes::Numeric { nb_digits, payout_curve, rounding_intervals } => {
+            let decomposition = announcements[0].digit_decomposition.clone()
+                .ok_or("Numeric contracts need numeric oracle events")?;
+            if decomposition.nb_digits != *nb_digits {
+                return Err(format!("Contract has {} digits, the oracle event {}", nb_digits, decomposition.nb_digits).into());
+            }
+            for announcement in &announcements[1..] {
+                match &announcement.digit_decomposition {
+                    Some(other) if other.base == decomposition.base && other.nb_digits == decomposition.nb_digits => {}
+                    _ => return Err(format!("Event {} is decomposed differently", announcement.event_id).into()),
+                }
+            }
+            if payout_curve.start() != 0 || payout_curve.end() != decomposition.max_value() {
+                return Err(format!("The payout curve must cover outcomes 0 to {}", decomposition.max_value()).into());
+            }
+            let ranges = payout_curve.ranges(info.total_collateral, rounding_intervals)?;
+            let cets = numeric::numeric_cets(&ranges, &decomposition, announcements.len(), threshold, info.bounded_error.as_ref())?;
+            Ok(cets.into_iter()
+                .map(|NumericCet { offer_payout, oracle_prefixes }| CetOutcome {
+                    offer_payout,
+                    oracle_outcomes: oracle_prefixes.into_iter()
+                        .map(|(oracle, prefix)| (oracle, OracleOutcome::DigitPrefix(prefix)))
+                        .collect(),
+                })
+                .collect())
+        }
+    }
+}
+
+/// Adaptor point of every CET
+///
+/// Anticipation points are computed once per oracle and outcome or digit,
+/// as numeric contracts reuse them across many CETs.
+pub fn adaptor_points(cets: &[CetOutcome], announcements: &[OracleAnnouncement]) -> AnyaResult<Vec<PublicKey>> {
+    let mut enumerated: HashMap<(usize, &str), PublicKey> = HashMap::new();
+    let mut digits: HashMap<(usize, usize, usize), PublicKey> = HashMap::new();
+    cets.iter()
+        .map(|cet| {
+            let mut points = Vec::new();
+            for (oracle, outcome) in &cet.oracle_outcomes {
+                let announcement = announcements.get(*oracle)
+                    .ok_or_else(|| format!("Unknown oracle {}", oracle))?;
+                match outcome {
+                    OracleOutcome::Enumerated(outcome) => points.push(match enumerated.entry((*oracle, outcome.as_str())) {
+                        Entry::Occupied(entry) => *entry.get(),
+                        Entry::Vacant(entry) => *entry.insert(announcement.anticipation_point(outcome)?),
+                    }),
+                    OracleOutcome::DigitPrefix(prefix) => {
+                        for (index, digit) in prefix.iter().enumerate() {
+                            points.push(match digits.entry((*oracle, index, *digit)) {
+                                Entry::Occupied(entry) => *entry.get(),
+                                Entry::Vacant(entry) => *entry.insert(announcement.digit_anticipation_point(index, *digit)?),
+                            });
+                        }
+                    }
+                }
+            }
+            let points: Vec<&PublicKey> = points.iter().collect();
+            PublicKey::combine_keys(&points)
+                .map_err(|e| format!("Failed to combine anticipation points: {}", e).into())
+        })
+        .collect()
+}
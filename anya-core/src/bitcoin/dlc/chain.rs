@@ -0,0 +1,216 @@
+// src/bitcoin/dlc/chain.rs
+
+//! Chain access for watching and settling contracts
+//!
+//! A contract's funding output is spent by exactly one of its CETs or its
+//! refund transaction. Watching that outpoint is enough to learn how, and
+//! whether, a contract was closed.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::{absolute, OutPoint, Transaction, Txid};
+use serde::Deserialize;
+
+use crate::AnyaResult;
+
+/// Bitcoin backend a DLC manager watches and broadcasts through
+pub trait DLCChain: Send + Sync {
+    /// Height of the best block
+    fn tip_height(&self) -> AnyaResult<u32>;
+
+    /// Median time past of the best block, which time locks are checked against
+    fn median_time_past(&self) -> AnyaResult<u32>;
+
+    /// A transaction in the mempool or the chain
+    fn get_transaction(&self, txid: &Txid) -> AnyaResult<Option<Transaction>>;
+
+    /// The transaction spending `outpoint`, if any
+    fn spending_transaction(&self, outpoint: &OutPoint) -> AnyaResult<Option<Transaction>>;
+
+    /// Submits a transaction to the network
+    fn broadcast(&self, transaction: &Transaction) -> AnyaResult<Txid>;
+}
+
+/// Whether a transaction with `lock_time` can be mined in the next block
+pub fn is_final(chain: &dyn DLCChain, lock_time: absolute::LockTime) -> AnyaResult<bool> {
+    Ok(match lock_time {
+        absolute::LockTime::Blocks(height) => height.to_consensus_u32() <= chain.tip_height()?,
+        absolute::LockTime::Seconds(time) => time.to_consensus_u32() < chain.median_time_past()?,
+    })
+}
+
+/// Esplora REST API client
+///
+/// Esplora indexes spends, so watching an outpoint is a single request.
+pub struct EsploraChain {
+    base_url: String,
+    http: reqwest::blocking::Client,
+}
+
+#[derive(Deserialize)]
+struct OutspendResponse {
+    spent: bool,
+    txid: Option<Txid>,
+}
+
+#[derive(Deserialize)]
+struct BlockResponse {
+    mediantime: u32,
+}
+
+impl EsploraChain {
+    /// Creates a client for the Esplora API at `base_url`, e.g.
+    /// `https://blockstream.info/testnet/api`
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// Body of `GET path`, or `None` if the resource does not exist
+    fn get(&self, path: &str) -> AnyaResult<Option<String>> {
+        let response = self.http.get(format!("{}{}", self.base_url, path)).send()
+            .map_err(|e| format!("Esplora request failed: {}", e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()
+            .map_err(|e| format!("Esplora request failed: {}", e))?;
+        Ok(Some(response.text().map_err(|e| format!("Invalid Esplora response: {}", e))?))
+    }
+
+    fn get_required(&self, path: &str) -> AnyaResult<String> {
+        self.get(path)?.ok_or_else(|| format!("Esplora has no {}", path).into())
+    }
+}
+
+impl DLCChain for EsploraChain {
+    fn tip_height(&self) -> AnyaResult<u32> {
+        self.get_required("/blocks/tip/height")?.trim().parse()
+            .map_err(|e| format!("Invalid block height: {}", e).into())
+    }
+
+    fn median_time_past(&self) -> AnyaResult<u32> {
+        let hash = self.get_required("/blocks/tip/hash")?;
+        let block: BlockResponse = serde_json::from_str(&self.get_required(&format!("/block/{}", hash.trim()))?)
+            .map_err(|e| format!("Invalid block: {}", e))?;
+        Ok(block.mediantime)
+    }
+
+    fn get_transaction(&self, txid: &Txid) -> AnyaResult<Option<Transaction>> {
+        self.get(&format!("/tx/{}/hex", txid))?
+            .map(|hex| deserialize_hex(hex.trim()).map_err(|e| format!("Invalid transaction {}: {}", txid, e).into()))
+            .transpose()
+    }
+
+    fn spending_transaction(&self, outpoint: &OutPoint) -> AnyaResult<Option<Transaction>> {
+        let outspend: OutspendResponse = match self.get(&format!("/tx/{}/outspend/{}", outpoint.txid, outpoint.vout))? {
+            Some(body) => serde_json::from_str(&body).map_err(|e| format!("Invalid outspend: {}", e))?,
+            None => return Ok(None),
+        };
+        match (outspend.spent, outspend.txid) {
+            (true, Some(txid)) => self.get_transaction(&txid),
+            _ => Ok(None),
+        }
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> AnyaResult<Txid> {
+        let response = self.http.post(format!("{}/tx", self.base_url))
+            .body(serialize_hex(transaction))
+            .send()
+            .map_err(|e| format!("Esplora request failed: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let reason = response.text().unwrap_or_default();
+            return Err(format!("Broadcast rejected ({}): {}", status, reason).into());
+        }
+        Ok(transaction.compute_txid())
+    }
+}
+
+/// Chain kept in memory, for tests and simulations
+///
+/// Broadcast transactions are confirmed immediately. Transactions double
+/// spending an outpoint or not yet final are rejected, as a node would;
+/// scripts are not checked.
+pub struct InMemoryChain {
+    state: Mutex<ChainState>,
+}
+
+struct ChainState {
+    height: u32,
+    median_time_past: u32,
+    transactions: HashMap<Txid, Transaction>,
+    spends: HashMap<OutPoint, Txid>,
+}
+
+impl InMemoryChain {
+    /// Creates a chain at `height` with the given median time past
+    pub fn new(height: u32, median_time_past: u32) -> Self {
+        Self {
+            state: Mutex::new(ChainState {
+                height,
+                median_time_past,
+                transactions: HashMap::new(),
+                spends: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Mines `blocks` empty blocks, `seconds_per_block` apart
+    pub fn mine(&self, blocks: u32, seconds_per_block: u32) -> AnyaResult<()> {
+        let mut state = self.lock()?;
+        state.height += blocks;
+        state.median_time_past += blocks * seconds_per_block;
+        Ok(())
+    }
+
+    fn lock(&self) -> AnyaResult<std::sync::MutexGuard<'_, ChainState>> {
+        self.state.lock().map_err(|_| "Chain lock poisoned".into())
+    }
+}
+
+impl DLCChain for InMemoryChain {
+    fn tip_height(&self) -> AnyaResult<u32> {
+        Ok(self.lock()?.height)
+    }
+
+    fn median_time_past(&self) -> AnyaResult<u32> {
+        Ok(self.lock()?.median_time_past)
+    }
+
+    fn get_transaction(&self, txid: &Txid) -> AnyaResult<Option<Transaction>> {
+        Ok(self.lock()?.transactions.get(txid).cloned())
+    }
+
+    fn spending_transaction(&self, outpoint: &OutPoint) -> AnyaResult<Option<Transaction>> {
+        let state = self.lock()?;
+        Ok(state.spends.get(outpoint).and_then(|txid| state.transactions.get(txid)).cloned())
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> AnyaResult<Txid> {
+        let txid = transaction.compute_txid();
+        let mut state = self.lock()?;
+        if state.transactions.contains_key(&txid) {
+            return Ok(txid);
+        }
+        let mature = match transaction.lock_time {
+            absolute::LockTime::Blocks(height) => height.to_consensus_u32() <= state.height,
+            absolute::LockTime::Seconds(time) => time.to_consensus_u32() < state.median_time_past,
+        };
+        if !mature && transaction.input.iter().any(|input| input.sequence.enables_absolute_lock_time()) {
+            return Err(format!("Transaction {} is not final", txid).into());
+        }
+        if transaction.input.iter().any(|input| state.spends.contains_key(&input.previous_output)) {
+            return Err(format!("Transaction {} double spends an output", txid).into());
+        }
+        for input in &transaction.input {
+            state.spends.insert(input.previous_output, txid);
+        }
+        state.transactions.insert(txid, transaction.clone());
+        Ok(txid)
+    }
+}
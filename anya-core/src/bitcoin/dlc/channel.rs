@@ -0,0 +1,983 @@
+// src/bitcoin/dlc/channel.rs
+
+//! DLCs inside Lightning channels
+//!
+//! A contract is added to a channel as a sub-channel: a split transaction
+//! spends the channel funding output into a new channel output and the
+//! contract's funding output, which the contract's CETs and refund spend.
+//! The channel manager signs the split with the channel's funding key and
+//! moves the channel onto the split's channel output: it signs commitment
+//! transactions spending that output, which pay the balances left after
+//! both parties' collateral and fees, and revokes the previous commitments
+//! once the split is fully signed. The split itself stays off-chain.
+//!
+//! Once the oracle attests, the parties settle cooperatively by moving the
+//! channel back onto its original funding output, with the payouts added to
+//! the balances, without touching the chain. If the channel is force closed
+//! while the contract is established, the split transaction is broadcast
+//! ahead of the commitment spending its channel output, and the contract
+//! settles through its CETs or refund like any funded contract.
+//!
+//! The split stays signed after an off-chain settlement. Broadcasting it
+//! then pays the contract output by the attested outcome, as the settlement
+//! did, but leaves the channel output to a cooperative close, since its
+//! commitments were revoked.
+//!
+//! Negotiation reuses `offer_dlc`, `accept_dlc` and `sign_dlc` without
+//! funding inputs, wrapped with the channel keys and split signatures:
+//!
+//! 1. [`SubChannelOffer`]: the offerer's channel key and `offer_dlc`
+//! 2. [`SubChannelAccept`]: the accepter's channel key and `accept_dlc`
+//! 3. [`SubChannelConfirm`]: `sign_dlc` and the offerer's split signature
+//! 4. [`SubChannelFinalize`]: the accepter's split signature
+//!
+//! Each party signs commitments on the split's channel output before it
+//! signs the split, and the accepter signs the split last, so neither party
+//! can lock the other's collateral before holding every CET and refund
+//! signature. A channel holds one contract at a time.
+
+use std::sync::Arc;
+
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{OutPoint, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::bitcoin::lightning::Channel;
+use crate::AnyaResult;
+use super::contract::{Contract, ContractParameters, ContractState};
+use super::messages::{AcceptDlc, OfferDlc, SignDlc};
+use super::oracle::OracleAttestation;
+use super::transactions::{self, PartyParams, SplitInput};
+use super::{DLCConfig, DLCManager, DefaultDLCManager};
+
+/// Funding output a channel's commitment transactions spend, and the
+/// balances they pay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelFunding {
+    /// Outpoint the commitment transactions spend
+    pub outpoint: OutPoint,
+
+    /// Value of the funding output
+    pub value: u64,
+
+    /// Our balance in the commitment transactions
+    pub local_balance: u64,
+
+    /// The counterparty's balance in the commitment transactions
+    pub remote_balance: u64,
+}
+
+/// Channel manager of the Lightning channels contracts are split off from
+///
+/// The channel manager keeps the channel's funding key and commitment
+/// transactions, and exchanges commitment signatures and revocations with
+/// the peer; the sub-channel manager never holds the funding key.
+pub trait DLCChannels: Send + Sync {
+    /// The channel with this ID, with its current funding output and balances
+    fn channel(&self, channel_id: &str) -> AnyaResult<Option<Channel>>;
+
+    /// Our and the counterparty's keys in the channel's 2-of-2 funding output
+    fn funding_pubkeys(&self, channel_id: &str) -> AnyaResult<(PublicKey, PublicKey)>;
+
+    /// Signs the input of `transaction` spending the channel's current
+    /// funding output with our funding key, for `SIGHASH_ALL`
+    fn sign_funding_spend(&self, channel_id: &str, transaction: &Transaction) -> AnyaResult<Signature>;
+
+    /// Signs commitment transactions spending `funding` with the peer,
+    /// keeping the current commitments valid
+    fn prepare_funding(&self, channel_id: &str, funding: ChannelFunding) -> AnyaResult<()>;
+
+    /// Makes the commitments prepared for `funding` current and revokes the
+    /// previous ones with the peer
+    fn commit_funding(&self, channel_id: &str, funding: &ChannelFunding) -> AnyaResult<()>;
+
+    /// Force closes the channel by broadcasting its current commitment
+    /// transaction, returning its ID
+    fn force_close(&self, channel_id: &str) -> AnyaResult<Txid>;
+}
+
+/// Progress of a contract inside a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubChannelState {
+    /// Offered to the counterparty
+    Offered,
+
+    /// Accepted, waiting for the offerer's signatures
+    Accepted,
+
+    /// Signed by the offerer, waiting for the accepter's split signature
+    Confirmed,
+
+    /// Split signed by both parties; the collateral is out of the channel
+    Established,
+
+    /// We offered to settle the contract off-chain
+    CloseOffered,
+
+    /// Settled off-chain; the payouts are back in the channel balances
+    OffChainClosed,
+
+    /// The split transaction was broadcast and the contract settles on-chain
+    OnChain,
+}
+
+/// The channel a contract was split off from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubChannel {
+    /// ID of the channel
+    pub channel_id: String,
+
+    /// Funding output of the channel, spent by the split transaction
+    pub channel_outpoint: OutPoint,
+
+    /// Value of the channel funding output
+    pub channel_value: u64,
+
+    /// Offering party's key in the channel funding output
+    pub offer_channel_pubkey: PublicKey,
+
+    /// Accepting party's key in the channel funding output, once accepted
+    pub accept_channel_pubkey: Option<PublicKey>,
+
+    /// Progress of the contract
+    pub state: SubChannelState,
+
+    /// Offer and accept payouts of the off-chain settlement, once offered
+    pub settlement: Option<(u64, u64)>,
+}
+
+impl SubChannel {
+    /// The channel output the split transaction spends
+    pub fn split_input(&self) -> AnyaResult<SplitInput> {
+        let accept_channel_pubkey = self.accept_channel_pubkey.ok_or("Sub-channel has not been accepted")?;
+        Ok(SplitInput {
+            outpoint: self.channel_outpoint,
+            value: self.channel_value,
+            funding_script: transactions::funding_script(&self.offer_channel_pubkey, &accept_channel_pubkey),
+        })
+    }
+}
+
+/// Offer of a contract inside a channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubChannelOffer {
+    /// ID of the channel
+    pub channel_id: String,
+
+    /// Offerer's key in the channel funding output
+    pub channel_pubkey: PublicKey,
+
+    /// Contract terms, without funding inputs
+    pub offer: OfferDlc,
+}
+
+/// Acceptance of a [`SubChannelOffer`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubChannelAccept {
+    /// ID of the channel
+    pub channel_id: String,
+
+    /// Accepter's key in the channel funding output
+    pub channel_pubkey: PublicKey,
+
+    /// Accepter's CET and refund signatures
+    pub accept: AcceptDlc,
+}
+
+/// The offerer's signatures, answering a [`SubChannelAccept`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubChannelConfirm {
+    /// ID of the channel
+    pub channel_id: String,
+
+    /// Offerer's CET and refund signatures
+    pub sign: SignDlc,
+
+    /// Offerer's signature of the split transaction
+    pub split_signature: Signature,
+}
+
+/// The accepter's split signature, establishing the contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubChannelFinalize {
+    /// ID of the channel
+    pub channel_id: String,
+
+    /// Accepter's signature of the split transaction
+    pub split_signature: Signature,
+}
+
+/// Offer to settle a contract off-chain on the attested outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubChannelCloseOffer {
+    /// ID of the channel
+    pub channel_id: String,
+
+    /// Oracle attestations the payouts follow from
+    pub attestations: Vec<OracleAttestation>,
+
+    /// Payout to the offering party of the contract
+    pub offer_payout: u64,
+
+    /// Payout to the accepting party of the contract
+    pub accept_payout: u64,
+}
+
+/// Acceptance of a [`SubChannelCloseOffer`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubChannelCloseAccept {
+    /// ID of the channel
+    pub channel_id: String,
+}
+
+/// Runs contracts inside Lightning channels
+///
+/// Contracts are negotiated, stored and, after a force close, executed by
+/// the wrapped DLC manager. With a chain attached, the DLC manager's
+/// `update_contract_status` notices a split transaction broadcast by the
+/// counterparty and moves the contract on-chain.
+pub struct SubChannelManager {
+    dlc: Arc<DefaultDLCManager>,
+    channels: Arc<dyn DLCChannels>,
+}
+
+impl SubChannelManager {
+    /// Creates a manager splitting contracts off the channels of `channels`
+    pub fn new(dlc: Arc<DefaultDLCManager>, channels: Arc<dyn DLCChannels>) -> Self {
+        Self { dlc, channels }
+    }
+
+    /// The latest contract of a channel
+    pub fn contract(&self, channel_id: &str) -> AnyaResult<Option<Contract>> {
+        Ok(self.dlc.list_contracts()?.into_iter()
+            .rfind(|contract| contract.sub_channel.as_ref().is_some_and(|sub_channel| sub_channel.channel_id == channel_id)))
+    }
+
+    /// Offers a contract inside a channel, funded from the channel balances
+    pub fn offer(&self, channel_id: &str, params: ContractParameters) -> AnyaResult<SubChannelOffer> {
+        let channel = self.available_channel(channel_id)?;
+        let (channel_pubkey, _) = self.channels.funding_pubkeys(channel_id)?;
+
+        let fee_rate_per_vb = DLCConfig::fee_rate_per_vb(params.fee_rate.unwrap_or(self.dlc.config.fee_rate));
+        let (offer_split_fee, _) = transactions::split_fees(fee_rate_per_vb);
+        let party = self.dlc.channel_party(channel_pubkey, params.offer_collateral)?;
+        check_balance("Local", locked_amount(&party, fee_rate_per_vb, offer_split_fee), channel.local_balance)?;
+        check_balance("Remote", params.accept_collateral, channel.remote_balance)?;
+
+        let contract = self.dlc.create_offer(params, Some(SubChannel {
+            channel_id: channel_id.to_string(),
+            channel_outpoint: funding_outpoint(&channel),
+            channel_value: channel.capacity,
+            offer_channel_pubkey: channel_pubkey,
+            accept_channel_pubkey: None,
+            state: SubChannelState::Offered,
+            settlement: None,
+        }))?;
+        Ok(SubChannelOffer {
+            channel_id: channel_id.to_string(),
+            channel_pubkey,
+            offer: contract.offer.ok_or("Contract has no offer")?,
+        })
+    }
+
+    /// Accepts a contract offered inside a channel
+    pub fn accept(&self, offer: &SubChannelOffer) -> AnyaResult<SubChannelAccept> {
+        if !offer.offer.funding_inputs.is_empty() {
+            return Err("A contract inside a channel is not funded from inputs".into());
+        }
+        let channel = self.available_channel(&offer.channel_id)?;
+        let channel_pubkey = self.check_counterparty_key(&offer.channel_id, &offer.channel_pubkey)?;
+
+        let mut contract = Contract::from_offer(offer.offer.clone())?;
+        contract.sub_channel = Some(SubChannel {
+            channel_id: offer.channel_id.clone(),
+            channel_outpoint: funding_outpoint(&channel),
+            channel_value: channel.capacity,
+            offer_channel_pubkey: offer.channel_pubkey,
+            accept_channel_pubkey: Some(channel_pubkey),
+            state: SubChannelState::Accepted,
+            settlement: None,
+        });
+
+        let fee_rate_per_vb = offer.offer.fee_rate_per_vb;
+        let (offer_split_fee, accept_split_fee) = transactions::split_fees(fee_rate_per_vb);
+        let offer_party = DefaultDLCManager::offer_party(&offer.offer);
+        let accept_party = self.dlc.channel_party(channel_pubkey, contract.descriptor.accept_collateral)?;
+        check_balance("Remote", locked_amount(&offer_party, fee_rate_per_vb, offer_split_fee), channel.remote_balance)?;
+        check_balance("Local", locked_amount(&accept_party, fee_rate_per_vb, accept_split_fee), channel.local_balance)?;
+
+        let accepted = self.dlc.accept_contract(&contract)?;
+        Ok(SubChannelAccept {
+            channel_id: offer.channel_id.clone(),
+            channel_pubkey,
+            accept: accepted.accept.ok_or("Contract has no accept message")?,
+        })
+    }
+
+    /// Signs the contract, commitments on the split's channel output and the
+    /// split transaction once the counterparty accepted our offer
+    pub fn on_accept(&self, accept: &SubChannelAccept) -> AnyaResult<SubChannelConfirm> {
+        let contract_id = hex::encode(accept.accept.temporary_contract_id);
+        let mut contract = self.dlc.get_contract(&contract_id)?
+            .filter(|contract| contract.sub_channel.as_ref().is_some_and(|sub_channel| {
+                sub_channel.channel_id == accept.channel_id && sub_channel.state == SubChannelState::Offered
+            }))
+            .ok_or_else(|| format!("No contract offered in channel {}", accept.channel_id))?;
+        self.check_counterparty_key(&accept.channel_id, &accept.channel_pubkey)?;
+        if let Some(sub_channel) = contract.sub_channel.as_mut() {
+            sub_channel.accept_channel_pubkey = Some(accept.channel_pubkey);
+        }
+        self.dlc.store(&contract)?;
+
+        let mut signed = self.dlc.sign_contract(&contract.with_accept(accept.accept.clone()))?;
+        self.check_balances(&signed)?;
+        self.channels.prepare_funding(&accept.channel_id, self.split_funding(&signed)?)?;
+        let split_signature = self.sign_split(&signed)?;
+        set_state(&mut signed, SubChannelState::Confirmed);
+        self.dlc.store(&signed)?;
+        Ok(SubChannelConfirm {
+            channel_id: accept.channel_id.clone(),
+            sign: signed.sign.ok_or("Contract has no sign message")?,
+            split_signature,
+        })
+    }
+
+    /// Checks the offerer's signatures, signs commitments on the split's
+    /// channel output and the split transaction, and moves the channel onto
+    /// the split
+    pub fn on_confirm(&self, confirm: &SubChannelConfirm) -> AnyaResult<SubChannelFinalize> {
+        let contract = self.contract_in(&confirm.channel_id, SubChannelState::Accepted)?;
+        let offer_channel_pubkey = sub_channel(&contract)?.offer_channel_pubkey;
+        self.verify_split(&contract, &confirm.split_signature, &offer_channel_pubkey)?;
+        self.check_balances(&contract)?;
+
+        let mut funded = self.dlc.fund_contract(&contract.with_sign(confirm.sign.clone()))?;
+        self.channels.prepare_funding(&confirm.channel_id, self.split_funding(&funded)?)?;
+        let split_signature = self.sign_split(&funded)?;
+        self.apply_split_witness(&mut funded, &split_signature, (&offer_channel_pubkey, &confirm.split_signature))?;
+        self.establish(funded)?;
+        Ok(SubChannelFinalize { channel_id: confirm.channel_id.clone(), split_signature })
+    }
+
+    /// Completes the split transaction with the accepter's signature and
+    /// moves the channel onto the split
+    pub fn on_finalize(&self, finalize: &SubChannelFinalize) -> AnyaResult<Contract> {
+        let mut contract = self.contract_in(&finalize.channel_id, SubChannelState::Confirmed)?;
+        let accept_channel_pubkey = sub_channel(&contract)?.accept_channel_pubkey
+            .ok_or("Sub-channel has not been accepted")?;
+        self.verify_split(&contract, &finalize.split_signature, &accept_channel_pubkey)?;
+        self.check_balances(&contract)?;
+
+        let split_signature = self.sign_split(&contract)?;
+        self.apply_split_witness(&mut contract, &split_signature, (&accept_channel_pubkey, &finalize.split_signature))?;
+        let funded = self.dlc.fund_contract(&contract)?;
+        self.establish(funded)
+    }
+
+    /// Offers to settle the contract of a channel off-chain on the attested outcome
+    pub fn offer_close(&self, channel_id: &str, attestations: Vec<OracleAttestation>) -> AnyaResult<SubChannelCloseOffer> {
+        let mut contract = self.contract_in(channel_id, SubChannelState::Established)?;
+        let (offer_payout, accept_payout) = self.attested_payouts(&contract, attestations.clone())?;
+        if let Some(sub_channel) = contract.sub_channel.as_mut() {
+            sub_channel.settlement = Some((offer_payout, accept_payout));
+        }
+        set_state(&mut contract, SubChannelState::CloseOffered);
+        self.dlc.store(&contract)?;
+        Ok(SubChannelCloseOffer { channel_id: channel_id.to_string(), attestations, offer_payout, accept_payout })
+    }
+
+    /// Settles the contract off-chain if the offered payouts match the
+    /// attested outcome
+    pub fn accept_close(&self, close: &SubChannelCloseOffer) -> AnyaResult<SubChannelCloseAccept> {
+        let contract = self.contract_in(&close.channel_id, SubChannelState::Established)?;
+        let payouts = self.attested_payouts(&contract, close.attestations.clone())?;
+        if payouts != (close.offer_payout, close.accept_payout) {
+            return Err("Close offer payouts do not match the attested outcome".into());
+        }
+        self.settle_off_chain(contract, payouts)?;
+        Ok(SubChannelCloseAccept { channel_id: close.channel_id.clone() })
+    }
+
+    /// Settles the contract off-chain once the counterparty accepted our close offer
+    pub fn on_close_accept(&self, accept: &SubChannelCloseAccept) -> AnyaResult<Contract> {
+        let contract = self.contract_in(&accept.channel_id, SubChannelState::CloseOffered)?;
+        let payouts = sub_channel(&contract)?.settlement.ok_or("Close offer has no payouts")?;
+        self.settle_off_chain(contract, payouts)
+    }
+
+    /// Force closes a channel along whichever funding output its current
+    /// commitments spend
+    ///
+    /// While a contract is established the commitments spend the split's
+    /// channel output, so the split transaction is broadcast first and the
+    /// contract settles on-chain; its ID is returned. Otherwise only the
+    /// commitment is broadcast and `None` is returned.
+    pub fn force_close(&self, channel_id: &str) -> AnyaResult<Option<Txid>> {
+        let channel = self.channels.channel(channel_id)?
+            .ok_or_else(|| format!("Unknown channel {}", channel_id))?;
+        let contract = self.contract(channel_id)?.filter(|contract| {
+            contract.transactions.as_ref()
+                .is_some_and(|txs| OutPoint::new(txs.fund.compute_txid(), 0) == funding_outpoint(&channel))
+        });
+        let Some(mut contract) = contract else {
+            self.channels.force_close(channel_id)?;
+            return Ok(None);
+        };
+
+        let chain = self.dlc.chain.clone().ok_or("Force closing a channel with a contract needs a chain")?;
+        let split = contract.transactions.as_ref().ok_or("Contract has no transactions")?.fund.clone();
+        let txid = chain.broadcast(&split)?;
+        self.channels.force_close(channel_id)?;
+        set_state(&mut contract, SubChannelState::OnChain);
+        self.dlc.store(&contract)?;
+        Ok(Some(txid))
+    }
+
+    /// An active channel without a contract in progress
+    fn available_channel(&self, channel_id: &str) -> AnyaResult<Channel> {
+        let channel = self.active_channel(channel_id)?;
+        let busy = self.contract(channel_id)?.and_then(|contract| contract.sub_channel).is_some_and(|sub_channel| {
+            !matches!(sub_channel.state, SubChannelState::Offered | SubChannelState::OffChainClosed)
+        });
+        if busy {
+            return Err(format!("Channel {} already holds a contract", channel_id).into());
+        }
+        Ok(channel)
+    }
+
+    fn active_channel(&self, channel_id: &str) -> AnyaResult<Channel> {
+        let channel = self.channels.channel(channel_id)?
+            .ok_or_else(|| format!("Unknown channel {}", channel_id))?;
+        if !channel.is_active {
+            return Err(format!("Channel {} is not active", channel_id).into());
+        }
+        Ok(channel)
+    }
+
+    /// The latest contract of a channel, which must be in `state`
+    fn contract_in(&self, channel_id: &str, state: SubChannelState) -> AnyaResult<Contract> {
+        let contract = self.contract(channel_id)?
+            .ok_or_else(|| format!("Channel {} holds no contract", channel_id))?;
+        let current = sub_channel(&contract)?.state;
+        if current != state {
+            return Err(format!("Contract in channel {} is {:?}, expected {:?}", channel_id, current, state).into());
+        }
+        Ok(contract)
+    }
+
+    /// Checks the counterparty sent its key in the channel funding output,
+    /// returning ours
+    fn check_counterparty_key(&self, channel_id: &str, counterparty_pubkey: &PublicKey) -> AnyaResult<PublicKey> {
+        let (local, remote) = self.channels.funding_pubkeys(channel_id)?;
+        if *counterparty_pubkey != remote {
+            return Err(format!("Counterparty key is not its funding key of channel {}", channel_id).into());
+        }
+        Ok(local)
+    }
+
+    /// Our signature of the split transaction, made by the channel manager
+    /// with the channel's funding key
+    fn sign_split(&self, contract: &Contract) -> AnyaResult<Signature> {
+        let split = &contract.transactions.as_ref().ok_or("Contract has no transactions")?.fund;
+        self.channels.sign_funding_spend(&sub_channel(contract)?.channel_id, split)
+    }
+
+    fn verify_split(&self, contract: &Contract, signature: &Signature, public_key: &PublicKey) -> AnyaResult<()> {
+        let split = &contract.transactions.as_ref().ok_or("Contract has no transactions")?.fund;
+        let sighash = transactions::split_sighash(split, &sub_channel(contract)?.split_input()?)?;
+        self.dlc.secp.verify_ecdsa(&sighash, signature, public_key)
+            .map_err(|_| "Invalid split transaction signature".into())
+    }
+
+    fn apply_split_witness(
+        &self,
+        contract: &mut Contract,
+        signature: &Signature,
+        counterparty: (&PublicKey, &Signature),
+    ) -> AnyaResult<()> {
+        let split_input = sub_channel(contract)?.split_input()?;
+        let (channel_pubkey, _) = self.channels.funding_pubkeys(&sub_channel(contract)?.channel_id)?;
+        let txs = contract.transactions.as_mut().ok_or("Contract has no transactions")?;
+        txs.fund.input[0].witness = transactions::funding_witness(
+            &split_input.funding_script,
+            (&channel_pubkey, signature),
+            counterparty,
+        );
+        Ok(())
+    }
+
+    /// Collateral and fees each party moves out of its channel balance, as
+    /// `(offer, accept)`
+    fn locked_amounts(contract: &Contract) -> AnyaResult<(u64, u64)> {
+        let offer = contract.offer.as_ref().ok_or("Contract has no offer")?;
+        let accept = contract.accept.as_ref().ok_or("Contract has no accept message")?;
+        let (offer_split_fee, accept_split_fee) = transactions::split_fees(offer.fee_rate_per_vb);
+        Ok((
+            locked_amount(&DefaultDLCManager::offer_party(offer), offer.fee_rate_per_vb, offer_split_fee),
+            locked_amount(&DefaultDLCManager::accept_party(accept), offer.fee_rate_per_vb, accept_split_fee),
+        ))
+    }
+
+    /// Our and the counterparty's locked amounts
+    fn local_and_remote(contract: &Contract) -> AnyaResult<(u64, u64)> {
+        let (offer, accept) = Self::locked_amounts(contract)?;
+        Ok(if contract.is_offer_party { (offer, accept) } else { (accept, offer) })
+    }
+
+    fn check_balances(&self, contract: &Contract) -> AnyaResult<()> {
+        let channel = self.active_channel(&sub_channel(contract)?.channel_id)?;
+        let (local, remote) = Self::local_and_remote(contract)?;
+        check_balance("Local", local, channel.local_balance)?;
+        check_balance("Remote", remote, channel.remote_balance)
+    }
+
+    /// The split's channel output, with the balances left once both parties
+    /// locked their collateral and fees
+    fn split_funding(&self, contract: &Contract) -> AnyaResult<ChannelFunding> {
+        let channel = self.active_channel(&sub_channel(contract)?.channel_id)?;
+        let (local, remote) = Self::local_and_remote(contract)?;
+        let split = &contract.transactions.as_ref().ok_or("Contract has no transactions")?.fund;
+        Ok(ChannelFunding {
+            outpoint: OutPoint::new(split.compute_txid(), 0),
+            value: split.output[0].value.to_sat(),
+            local_balance: channel.local_balance - local,
+            remote_balance: channel.remote_balance - remote,
+        })
+    }
+
+    /// Moves the channel onto the split's channel output, revoking the
+    /// commitments spending the original funding output
+    fn establish(&self, mut contract: Contract) -> AnyaResult<Contract> {
+        let channel_id = sub_channel(&contract)?.channel_id.clone();
+        self.channels.commit_funding(&channel_id, &self.split_funding(&contract)?)?;
+        set_state(&mut contract, SubChannelState::Established);
+        self.dlc.store(&contract)?;
+        Ok(contract)
+    }
+
+    fn attested_payouts(&self, contract: &Contract, attestations: Vec<OracleAttestation>) -> AnyaResult<(u64, u64)> {
+        let total = contract.total_collateral();
+        let (_, outcome, _) = self.dlc.attested_outcome(contract, attestations)?;
+        Ok((outcome.offer_payout, total.saturating_sub(outcome.offer_payout)))
+    }
+
+    /// Moves the channel back onto its original funding output with the
+    /// payouts in its balances, and closes the contract
+    ///
+    /// Nothing reached the chain, so the fees locked with the collateral are
+    /// returned as well.
+    fn settle_off_chain(&self, mut contract: Contract, (offer_payout, accept_payout): (u64, u64)) -> AnyaResult<Contract> {
+        let sub_channel = sub_channel(&contract)?.clone();
+        let channel = self.active_channel(&sub_channel.channel_id)?;
+        let (offer_locked, accept_locked) = Self::locked_amounts(&contract)?;
+        let offer_returned = offer_locked - contract.descriptor.offer_collateral + offer_payout;
+        let accept_returned = accept_locked - contract.descriptor.accept_collateral + accept_payout;
+        let (local, remote) = if contract.is_offer_party {
+            (offer_returned, accept_returned)
+        } else {
+            (accept_returned, offer_returned)
+        };
+        let funding = ChannelFunding {
+            outpoint: sub_channel.channel_outpoint,
+            value: sub_channel.channel_value,
+            local_balance: channel.local_balance + local,
+            remote_balance: channel.remote_balance + remote,
+        };
+        self.channels.prepare_funding(&sub_channel.channel_id, funding)?;
+        self.channels.commit_funding(&sub_channel.channel_id, &funding)?;
+
+        if let Some(sub_channel) = contract.sub_channel.as_mut() {
+            sub_channel.settlement = Some((offer_payout, accept_payout));
+        }
+        set_state(&mut contract, SubChannelState::OffChainClosed);
+        contract.update_state(ContractState::Closed);
+        self.dlc.store(&contract)?;
+        Ok(contract)
+    }
+}
+
+fn funding_outpoint(channel: &Channel) -> OutPoint {
+    OutPoint::new(channel.funding_txid, channel.funding_output_idx)
+}
+
+fn sub_channel(contract: &Contract) -> AnyaResult<&SubChannel> {
+    contract.sub_channel.as_ref().ok_or_else(|| format!("Contract {} is not inside a channel", contract.id).into())
+}
+
+fn set_state(contract: &mut Contract, state: SubChannelState) {
+    if let Some(sub_channel) = contract.sub_channel.as_mut() {
+        sub_channel.state = state;
+    }
+    contract.updated_at = chrono::Utc::now();
+}
+
+/// Amount a party moves out of its channel balance: its collateral, its
+/// share of the CET fee and its share of the split transaction fee
+fn locked_amount(party: &PartyParams, fee_rate_per_vb: u64, split_fee: u64) -> u64 {
+    let (_, cet_fee) = party.fees(fee_rate_per_vb);
+    party.collateral + cet_fee + split_fee
+}
+
+fn check_balance(side: &str, needed: u64, balance: u64) -> AnyaResult<()> {
+    if needed > balance {
+        return Err(format!("{} balance of {} sats does not cover {} sats", side, balance, needed).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+    use bitcoin::{Amount, ScriptBuf, TxIn, TxOut};
+    use chrono::{Duration, Utc};
+    use crate::bitcoin::dlc::{DLCChain, InMemoryChain, InMemoryDLCWallet, LocalOracle, OracleAnnouncement, PayoutFunction};
+
+    const CHANNEL_ID: &str = "channel-1";
+
+    /// One side's channel manager for a single channel: it holds our funding
+    /// key and the commitment transactions, and broadcasts on force close
+    struct TestChannels {
+        chain: Arc<InMemoryChain>,
+        funding_key: SecretKey,
+        remote_funding_pubkey: PublicKey,
+        state: Mutex<TestChannel>,
+    }
+
+    struct TestChannel {
+        channel: Channel,
+        commitment: Transaction,
+        prepared: Option<(ChannelFunding, Transaction)>,
+        revoked: Vec<Transaction>,
+    }
+
+    impl TestChannels {
+        fn new(chain: &Arc<InMemoryChain>, keys: (u8, u8), local_balance: u64, remote_balance: u64) -> Arc<Self> {
+            let secp = Secp256k1::new();
+            let funding_key = SecretKey::from_slice(&[keys.0; 32]).unwrap();
+            let remote_funding_pubkey = PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[keys.1; 32]).unwrap());
+            // Lightning channels carry the standalone secp256k1 crate's key type
+            let node_secp = secp256k1::Secp256k1::new();
+            let channel = Channel {
+                channel_id: CHANNEL_ID.to_string(),
+                funding_txid: Txid::from_byte_array([0x42; 32]),
+                funding_output_idx: 0,
+                capacity: local_balance + remote_balance,
+                local_balance,
+                remote_balance,
+                remote_pubkey: secp256k1::PublicKey::from_secret_key(&node_secp, &secp256k1::SecretKey::from_slice(&[9; 32]).unwrap()),
+                is_active: true,
+                is_public: false,
+                short_channel_id: None,
+            };
+            let funding = ChannelFunding {
+                outpoint: funding_outpoint(&channel),
+                value: channel.capacity,
+                local_balance,
+                remote_balance,
+            };
+            Arc::new(Self {
+                chain: chain.clone(),
+                funding_key,
+                remote_funding_pubkey,
+                state: Mutex::new(TestChannel { channel, commitment: commitment(&funding), prepared: None, revoked: Vec::new() }),
+            })
+        }
+
+        fn balances(&self) -> (u64, u64) {
+            let channel = self.channel(CHANNEL_ID).unwrap().unwrap();
+            (channel.local_balance, channel.remote_balance)
+        }
+
+        fn commitment(&self) -> Transaction {
+            self.state.lock().unwrap().commitment.clone()
+        }
+
+        fn revoked(&self) -> Vec<Transaction> {
+            self.state.lock().unwrap().revoked.clone()
+        }
+    }
+
+    /// Commitment transaction paying both balances out of `funding`
+    fn commitment(funding: &ChannelFunding) -> Transaction {
+        Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn { previous_output: funding.outpoint, ..TxIn::default() }],
+            output: [funding.local_balance, funding.remote_balance].into_iter()
+                .map(|value| TxOut { value: Amount::from_sat(value), script_pubkey: ScriptBuf::new() })
+                .collect(),
+        }
+    }
+
+    impl DLCChannels for TestChannels {
+        fn channel(&self, channel_id: &str) -> AnyaResult<Option<Channel>> {
+            let state = self.state.lock().unwrap();
+            Ok(Some(state.channel.clone()).filter(|channel| channel.channel_id == channel_id))
+        }
+
+        fn funding_pubkeys(&self, _channel_id: &str) -> AnyaResult<(PublicKey, PublicKey)> {
+            Ok((PublicKey::from_secret_key(&Secp256k1::new(), &self.funding_key), self.remote_funding_pubkey))
+        }
+
+        fn sign_funding_spend(&self, channel_id: &str, transaction: &Transaction) -> AnyaResult<Signature> {
+            let channel = self.channel(channel_id)?.ok_or("Unknown channel")?;
+            if transaction.input[0].previous_output != funding_outpoint(&channel) {
+                return Err("Transaction does not spend the channel funding output".into());
+            }
+            let (local, remote) = self.funding_pubkeys(channel_id)?;
+            let funding = SplitInput {
+                outpoint: funding_outpoint(&channel),
+                value: channel.capacity,
+                funding_script: transactions::funding_script(&local, &remote),
+            };
+            let sighash = transactions::split_sighash(transaction, &funding)?;
+            Ok(Secp256k1::new().sign_ecdsa(&sighash, &self.funding_key))
+        }
+
+        fn prepare_funding(&self, _channel_id: &str, funding: ChannelFunding) -> AnyaResult<()> {
+            if funding.local_balance + funding.remote_balance > funding.value {
+                return Err("Balances exceed the funding output".into());
+            }
+            self.state.lock().unwrap().prepared = Some((funding, commitment(&funding)));
+            Ok(())
+        }
+
+        fn commit_funding(&self, _channel_id: &str, funding: &ChannelFunding) -> AnyaResult<()> {
+            let mut state = self.state.lock().unwrap();
+            let (prepared, commitment) = state.prepared.take().ok_or("No commitments prepared")?;
+            if prepared != *funding {
+                return Err("Commitments were prepared for another funding output".into());
+            }
+            let previous = std::mem::replace(&mut state.commitment, commitment);
+            state.revoked.push(previous);
+            let channel = &mut state.channel;
+            channel.funding_txid = funding.outpoint.txid;
+            channel.funding_output_idx = funding.outpoint.vout;
+            channel.capacity = funding.value;
+            channel.local_balance = funding.local_balance;
+            channel.remote_balance = funding.remote_balance;
+            Ok(())
+        }
+
+        fn force_close(&self, _channel_id: &str) -> AnyaResult<Txid> {
+            let mut state = self.state.lock().unwrap();
+            let txid = self.chain.broadcast(&state.commitment)?;
+            state.channel.is_active = false;
+            Ok(txid)
+        }
+    }
+
+    fn node(seed: u8, chain: &Arc<InMemoryChain>, channels: &Arc<TestChannels>) -> SubChannelManager {
+        let wallet = Arc::new(InMemoryDLCWallet::new(SecretKey::from_slice(&[seed; 32]).unwrap()));
+        let config = DLCConfig { network: "regtest".to_string(), fee_rate: 2.0, ..DLCConfig::default() };
+        let dlc = DefaultDLCManager::new(config, wallet).with_chain(chain.clone());
+        SubChannelManager::new(Arc::new(dlc), channels.clone())
+    }
+
+    /// Checks the split's witness carries valid signatures by both channel
+    /// funding keys over the original funding output
+    fn assert_split_signed(split: &Transaction, channels: &TestChannels) {
+        let (local, remote) = channels.funding_pubkeys(CHANNEL_ID).unwrap();
+        let funding = SplitInput {
+            outpoint: OutPoint::new(Txid::from_byte_array([0x42; 32]), 0),
+            value: 1_000_000,
+            funding_script: transactions::funding_script(&local, &remote),
+        };
+        let sighash = transactions::split_sighash(split, &funding).unwrap();
+        let witness: Vec<&[u8]> = split.input[0].witness.iter().collect();
+        assert_eq!(witness.len(), 4);
+        assert_eq!(witness[3], funding.funding_script.as_bytes());
+        let mut keys = [local, remote];
+        keys.sort_by_key(|key| key.serialize());
+        let secp = Secp256k1::new();
+        for (signature, key) in witness[1..3].iter().zip(keys) {
+            let signature = Signature::from_der(&signature[..signature.len() - 1]).unwrap();
+            assert!(secp.verify_ecdsa(&sighash, &signature, &key).is_ok());
+        }
+    }
+
+    fn parameters(announcement: &OracleAnnouncement) -> ContractParameters {
+        ContractParameters {
+            title: "BTC above 100k".to_string(),
+            description: String::new(),
+            offer_collateral: 60_000,
+            accept_collateral: 40_000,
+            payout_function: PayoutFunction::Binary {
+                win_condition: "yes".to_string(),
+                offer_win_amount: 100_000,
+                accept_win_amount: 100_000,
+            },
+            oracle_urls: vec![],
+            oracle_announcements: vec![announcement.clone()],
+            oracle_threshold: None,
+            bounded_error: None,
+            refund_locktime: None,
+            fee_rate: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    struct Setup {
+        oracle: LocalOracle,
+        announcement: OracleAnnouncement,
+        chain: Arc<InMemoryChain>,
+        alice: SubChannelManager,
+        alice_channels: Arc<TestChannels>,
+        bob: SubChannelManager,
+        bob_channels: Arc<TestChannels>,
+    }
+
+    fn relay<T: Serialize + serde::de::DeserializeOwned>(message: T) -> T {
+        serde_json::from_str(&serde_json::to_string(&message).unwrap()).unwrap()
+    }
+
+    /// Alice and Bob with a channel between them and an oracle to bet on
+    fn setup() -> Setup {
+        let oracle = LocalOracle::new("test", SecretKey::from_slice(&[0x51; 32]).unwrap());
+        let announcement = oracle.announce(
+            "btc-above-100k", "BTC/USD above 100k", Utc::now() + Duration::days(1), vec!["yes".into(), "no".into()],
+        ).unwrap();
+        let chain = Arc::new(InMemoryChain::new(800_000, Utc::now().timestamp() as u32));
+        let alice_channels = TestChannels::new(&chain, (0x61, 0x62), 600_000, 400_000);
+        let bob_channels = TestChannels::new(&chain, (0x62, 0x61), 400_000, 600_000);
+        let alice = node(1, &chain, &alice_channels);
+        let bob = node(2, &chain, &bob_channels);
+        Setup { oracle, announcement, chain, alice, alice_channels, bob, bob_channels }
+    }
+
+    /// Alice offers a contract in her channel with Bob and both sides
+    /// establish it, exchanging serialized messages
+    fn establish() -> (Setup, Contract) {
+        let setup = setup();
+        let offer = setup.alice.offer(CHANNEL_ID, parameters(&setup.announcement)).unwrap();
+        let accept = setup.bob.accept(&relay(offer)).unwrap();
+        let confirm = setup.alice.on_accept(&relay(accept)).unwrap();
+        let finalize = setup.bob.on_confirm(&relay(confirm)).unwrap();
+        let contract = setup.alice.on_finalize(&relay(finalize)).unwrap();
+        (setup, contract)
+    }
+
+    #[test]
+    fn test_contract_settles_inside_channel() {
+        let (setup, contract) = establish();
+        let Setup { oracle, announcement, chain, alice, alice_channels, bob, bob_channels } = setup;
+        let bob_contract = bob.contract(CHANNEL_ID).unwrap().unwrap();
+        assert_eq!(contract.state, ContractState::Funded);
+        assert_eq!(sub_channel(&contract).unwrap().state, SubChannelState::Established);
+        assert_eq!(sub_channel(&bob_contract).unwrap().state, SubChannelState::Established);
+
+        // The split spends the channel funding output with both channel funding keys
+        let original = OutPoint::new(Txid::from_byte_array([0x42; 32]), 0);
+        let split = contract.transactions.as_ref().unwrap().fund.clone();
+        assert_eq!(split.input[0].previous_output, original);
+        assert_split_signed(&split, &alice_channels);
+        assert_eq!(bob_contract.transactions.as_ref().unwrap().fund, split);
+        assert!(chain.get_transaction(&split.compute_txid()).unwrap().is_none());
+
+        // Both channels moved onto the split's channel output, with new commitments
+        // paying the balances left after collateral and fees
+        let (offer_locked, accept_locked) = SubChannelManager::locked_amounts(&contract).unwrap();
+        assert!(offer_locked > 60_000 && accept_locked > 40_000);
+        assert_eq!(alice_channels.balances(), (600_000 - offer_locked, 400_000 - accept_locked));
+        assert_eq!(bob_channels.balances(), (400_000 - accept_locked, 600_000 - offer_locked));
+        let split_channel = OutPoint::new(split.compute_txid(), 0);
+        for channels in [&alice_channels, &bob_channels] {
+            let channel = channels.channel(CHANNEL_ID).unwrap().unwrap();
+            assert_eq!(funding_outpoint(&channel), split_channel);
+            assert_eq!(channel.capacity, split.output[0].value.to_sat());
+            assert_eq!(channels.commitment().input[0].previous_output, split_channel);
+            assert_eq!(channels.revoked()[0].input[0].previous_output, original);
+        }
+        assert!(alice.offer(CHANNEL_ID, parameters(&announcement)).is_err());
+
+        // The contract settles off-chain, never through a CET or refund
+        let attestation = oracle.attest("btc-above-100k", "yes").unwrap();
+        assert!(alice.dlc.execute_contract(&contract, attestation.clone()).is_err());
+        assert!(alice.dlc.refund_contract(&contract).is_err());
+        assert_eq!(alice.dlc.update_contract_status(&contract.id).unwrap(), ContractState::Funded);
+
+        let close = bob.offer_close(CHANNEL_ID, vec![attestation]).unwrap();
+        assert_eq!((close.offer_payout, close.accept_payout), (100_000, 0));
+        let mut greedy = close.clone();
+        greedy.offer_payout -= 10_000;
+        greedy.accept_payout += 10_000;
+        assert!(alice.accept_close(&greedy).is_err());
+        let accept = alice.accept_close(&close).unwrap();
+        let closed = bob.on_close_accept(&accept).unwrap();
+
+        // The channels moved back onto the original funding output with the payouts
+        assert_eq!(closed.state, ContractState::Closed);
+        assert_eq!(sub_channel(&closed).unwrap().state, SubChannelState::OffChainClosed);
+        assert_eq!(alice_channels.balances(), (640_000, 360_000));
+        assert_eq!(bob_channels.balances(), (360_000, 640_000));
+        for channels in [&alice_channels, &bob_channels] {
+            assert_eq!(funding_outpoint(&channels.channel(CHANNEL_ID).unwrap().unwrap()), original);
+            assert_eq!(channels.commitment().input[0].previous_output, original);
+        }
+        assert!(chain.get_transaction(&split.compute_txid()).unwrap().is_none());
+
+        // The channel is free for the next contract
+        assert!(alice.offer(CHANNEL_ID, parameters(&announcement)).is_ok());
+    }
+
+    #[test]
+    fn test_contract_requires_channel_funding_keys() {
+        let Setup { announcement, alice, bob, .. } = setup();
+        let mut offer = alice.offer(CHANNEL_ID, parameters(&announcement)).unwrap();
+        offer.channel_pubkey = PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[0x63; 32]).unwrap());
+        assert!(bob.accept(&offer).is_err());
+    }
+
+    #[test]
+    fn test_force_close_settles_on_chain() {
+        let (setup, contract) = establish();
+        let Setup { oracle, chain, alice, bob, bob_channels, .. } = setup;
+
+        // Bob force closes: the split goes on-chain followed by the commitment
+        // spending its channel output, with nothing left to conflict
+        let split_txid = bob.force_close(CHANNEL_ID).unwrap().unwrap();
+        assert!(!bob_channels.channel(CHANNEL_ID).unwrap().unwrap().is_active);
+        assert!(chain.get_transaction(&split_txid).unwrap().is_some());
+        let commitment = chain.spending_transaction(&OutPoint::new(split_txid, 0)).unwrap().unwrap();
+        assert_eq!(commitment, bob_channels.commitment());
+
+        // Alice learns of it from the split transaction on chain
+        assert_eq!(alice.dlc.update_contract_status(&contract.id).unwrap(), ContractState::Funded);
+        let on_chain = alice.contract(CHANNEL_ID).unwrap().unwrap();
+        assert_eq!(sub_channel(&on_chain).unwrap().state, SubChannelState::OnChain);
+        assert!(!on_chain.is_in_channel());
+
+        // Alice executes the CET spending the contract's output of the split
+        chain.mine(150, 600).unwrap();
+        let txid = alice.dlc.execute_contract(&on_chain, oracle.attest("btc-above-100k", "yes").unwrap()).unwrap();
+        let funding = on_chain.transactions.as_ref().unwrap().funding_outpoint();
+        assert_eq!(funding.txid, split_txid);
+        let cet = chain.spending_transaction(&funding).unwrap().unwrap();
+        assert_eq!(cet.compute_txid().to_string(), txid);
+        assert_eq!(cet.output.iter().map(|output| output.value.to_sat()).sum::<u64>(), 100_000);
+
+        assert_eq!(bob.dlc.update_contract_status(&contract.id).unwrap(), ContractState::Closed);
+    }
+
+    #[test]
+    fn test_force_close_before_split_is_signed() {
+        let Setup { announcement, chain, alice, alice_channels, bob, .. } = setup();
+        let offer = alice.offer(CHANNEL_ID, parameters(&announcement)).unwrap();
+        let accept = bob.accept(&relay(offer)).unwrap();
+        alice.on_accept(&relay(accept)).unwrap();
+        let split = alice.contract(CHANNEL_ID).unwrap().unwrap().transactions.unwrap().fund;
+
+        // Alice prepared commitments on the split but still closes on the
+        // original funding output, and never broadcasts the split
+        assert!(alice.force_close(CHANNEL_ID).unwrap().is_none());
+        let original = OutPoint::new(Txid::from_byte_array([0x42; 32]), 0);
+        assert_eq!(chain.spending_transaction(&original).unwrap().unwrap(), alice_channels.commitment());
+        assert!(chain.get_transaction(&split.compute_txid()).unwrap().is_none());
+        assert!(!alice_channels.channel(CHANNEL_ID).unwrap().unwrap().is_active);
+    }
+}
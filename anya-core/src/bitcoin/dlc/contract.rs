@@ -2,14 +2,21 @@
 
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
-use bitcoin::Txid;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::{Transaction, Txid};
 use bitcoin::secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
 
-use crate::common::error::AnyaResult;
+use crate::AnyaResult;
+use super::cet;
+use super::channel::{SubChannel, SubChannelState};
+use super::messages::{AcceptDlc, ContractInfo, ContractOutcome, ContractOutcomes, OfferDlc, SignDlc};
+use super::numeric::{BoundedError, PayoutCurve, PayoutPoint, RoundingIntervals};
 use super::oracle::{OracleInfo, OracleAnnouncement};
+use super::transactions::DlcTransactions;
 
 /// Represents the current state of a contract
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContractState {
     /// Contract is being drafted
     Draft,
@@ -40,7 +47,7 @@ pub enum ContractState {
 }
 
 /// Describes a DLC contract with all necessary parameters
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Contract {
     /// Unique identifier for the contract
     pub id: String,
@@ -74,6 +81,29 @@ pub struct Contract {
     
     /// Additional contract metadata
     pub metadata: HashMap<String, String>,
+
+    /// Whether this node offered the contract
+    pub is_offer_party: bool,
+
+    /// `offer_dlc` message, once offered
+    pub offer: Option<OfferDlc>,
+
+    /// `accept_dlc` message, once accepted
+    pub accept: Option<AcceptDlc>,
+
+    /// `sign_dlc` message, once signed
+    pub sign: Option<SignDlc>,
+
+    /// Final contract ID, derived from the funding transaction
+    pub contract_id: Option<[u8; 32]>,
+
+    /// Funding, CET and refund transactions
+    pub transactions: Option<DlcTransactions>,
+
+    /// The channel the contract was split off from, for a contract inside a
+    /// Lightning channel
+    #[serde(default)]
+    pub sub_channel: Option<SubChannel>,
 }
 
 impl Contract {
@@ -94,15 +124,107 @@ pub fn new(descriptor: ContractDescriptor) -> Self {
             oracle_announcements: Vec::new(),
             execution_paths: HashMap::new(),
             metadata: HashMap::new(),
+            is_offer_party: false,
+            offer: None,
+            accept: None,
+            sign: None,
+            contract_id: None,
+            transactions: None,
+            sub_channel: None,
         }
     }
-    
+
+    /// Creates the accepting party's view of a contract from an `offer_dlc` message
+    ///
+    /// The contract ID is the hex-encoded temporary contract ID, which both
+    /// parties share.
+    pub fn from_offer(offer: OfferDlc) -> AnyaResult<Self> {
+        let info = &offer.contract_info;
+        if offer.offer_collateral > info.total_collateral {
+            return Err("Offer collateral exceeds the total collateral".into());
+        }
+        let accept_collateral = info.total_collateral - offer.offer_collateral;
+        let payout_function = match &info.outcomes {
+            ContractOutcomes::Enumerated(outcomes) => PayoutFunction::Enumerated {
+                outcomes: outcomes.iter()
+                    .map(|o| (o.outcome.clone(), (o.offer_payout, info.total_collateral.saturating_sub(o.offer_payout))))
+                    .collect(),
+            },
+            ContractOutcomes::Numeric { payout_curve, rounding_intervals, .. } => PayoutFunction::Curve {
+                curve: payout_curve.clone(),
+                rounding_intervals: rounding_intervals.clone(),
+            },
+        };
+        let announcements = cet::announcements(info)?;
+        let title = announcements.first()
+            .map(|announcement| announcement.event_id.clone())
+            .ok_or("Offer has no oracle announcements")?;
+
+        let descriptor = ContractDescriptor {
+            title,
+            description: String::new(),
+            offer_public_key: offer.funding_pubkey,
+            offer_collateral: offer.offer_collateral,
+            accept_public_key: None,
+            accept_collateral,
+            fee_rate: offer.fee_rate_per_vb as f64,
+            refund_locktime: offer.refund_locktime,
+            payout_function,
+            oracle_info: announcements.iter()
+                .map(|announcement| OracleInfo {
+                    name: String::new(),
+                    public_key: announcement.public_key,
+                    endpoint: String::new(),
+                    properties: HashMap::new(),
+                })
+                .collect(),
+            oracle_threshold: info.oracle_threshold,
+            bounded_error: info.bounded_error,
+        };
+
+        let mut contract = Self::new(descriptor);
+        contract.id = hex::encode(offer.temporary_contract_id);
+        contract.state = ContractState::Offered;
+        contract.oracle_announcements = announcements;
+        contract.offer = Some(offer);
+        Ok(contract)
+    }
+
+    /// The contract with the counterparty's `accept_dlc` message attached
+    pub fn with_accept(mut self, accept: AcceptDlc) -> Self {
+        self.accept = Some(accept);
+        self
+    }
+
+    /// The contract with the counterparty's `sign_dlc` message attached
+    pub fn with_sign(mut self, sign: SignDlc) -> Self {
+        self.sign = Some(sign);
+        self
+    }
+
     /// Validates that the contract is well-formed
     pub fn validate(&self) -> AnyaResult<()> {
-        // Check if the contract has valid parameters
-        // Implementation goes here
+        if self.total_collateral() == 0 {
+            return Err("Contract has no collateral".into());
+        }
+        if !self.oracle_announcements.is_empty() {
+            cet::cet_outcomes(&self.contract_info()?)?;
+        }
         Ok(())
     }
+
+    /// The `contract_info` offered to the counterparty
+    pub fn contract_info(&self) -> AnyaResult<ContractInfo> {
+        Ok(ContractInfo {
+            total_collateral: self.total_collateral(),
+            outcomes: self.descriptor.contract_outcomes(&self.oracle_announcements)?,
+            oracle_announcements: self.oracle_announcements.iter()
+                .map(|announcement| announcement.to_signed_event())
+                .collect::<AnyaResult<Vec<_>>>()?,
+            oracle_threshold: self.descriptor.oracle_threshold,
+            bounded_error: self.descriptor.bounded_error,
+        })
+    }
     
     /// Adds an oracle announcement to the contract
     pub fn add_oracle_announcement(&mut self, announcement: OracleAnnouncement) {
@@ -116,6 +238,21 @@ pub fn update_state(&mut self, new_state: ContractState) {
         self.updated_at = Utc::now();
     }
     
+    /// The execution path whose CET has this transaction ID
+    pub fn execution_path(&self, txid: &Txid) -> Option<&ContractExecutionPath> {
+        self.execution_paths.values().find(|path| {
+            deserialize_hex::<Transaction>(&path.cet_hex).is_ok_and(|cet| cet.compute_txid() == *txid)
+        })
+    }
+
+    /// Whether the contract lives inside a channel rather than on-chain
+    ///
+    /// Such a contract is settled off-chain with its counterparty, or moves
+    /// on-chain once the channel is force closed.
+    pub fn is_in_channel(&self) -> bool {
+        self.sub_channel.as_ref().is_some_and(|sub_channel| sub_channel.state != SubChannelState::OnChain)
+    }
+
     /// Checks if the contract is ready for execution
     pub fn is_ready_for_execution(&self) -> bool {
         self.state == ContractState::Funded && !self.oracle_announcements.is_empty()
@@ -128,7 +265,7 @@ pub fn total_collateral(&self) -> u64 {
 }
 
 /// Represents the descriptor of a contract with all its terms
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractDescriptor {
     /// Title of the contract
     pub title: String,
@@ -159,10 +296,119 @@ pub struct ContractDescriptor {
     
     /// Oracle information for this contract
     pub oracle_info: Vec<OracleInfo>,
+
+    /// How many of the oracles must attest for the contract to settle
+    pub oracle_threshold: u16,
+
+    /// How far apart numeric attestations of different oracles may be
+    pub bounded_error: Option<BoundedError>,
+}
+
+impl ContractDescriptor {
+    /// `(outcome, offer_payout, accept_payout)` for every announced outcome
+    ///
+    /// For binary contracts the offering party receives `offer_win_amount`
+    /// when the oracle attests to `win_condition`; on any other outcome the
+    /// accepting party receives `accept_win_amount`. The counterparty gets the
+    /// rest of the collateral in both cases.
+    pub fn payouts(&self, announcement: &OracleAnnouncement) -> AnyaResult<Vec<(String, u64, u64)>> {
+        let total = self.offer_collateral + self.accept_collateral;
+        let payouts = match &self.payout_function {
+            PayoutFunction::Binary { win_condition, offer_win_amount, accept_win_amount } => {
+                if !announcement.outcomes.contains(win_condition) {
+                    return Err(format!("Win condition '{}' is not an announced outcome", win_condition).into());
+                }
+                if *offer_win_amount > total || *accept_win_amount > total {
+                    return Err("Winning amount exceeds the total collateral".into());
+                }
+                announcement.outcomes.iter()
+                    .map(|outcome| if outcome == win_condition {
+                        (outcome.clone(), *offer_win_amount, total - offer_win_amount)
+                    } else {
+                        (outcome.clone(), total - accept_win_amount, *accept_win_amount)
+                    })
+                    .collect()
+            }
+            PayoutFunction::Enumerated { outcomes } => announcement.outcomes.iter()
+                .map(|outcome| {
+                    let (offer_payout, accept_payout) = outcomes.get(outcome)
+                        .ok_or_else(|| format!("No payout for outcome '{}'", outcome))?;
+                    if offer_payout + accept_payout != total {
+                        return Err(format!(
+                            "Payout for outcome '{}' does not match the total collateral {}", outcome, total
+                        ).into());
+                    }
+                    Ok((outcome.clone(), *offer_payout, *accept_payout))
+                })
+                .collect::<AnyaResult<Vec<_>>>()?,
+            PayoutFunction::Numeric { .. } | PayoutFunction::Curve { .. } => {
+                return Err("Numeric outcome contracts pay out along a curve, not per outcome".into());
+            }
+        };
+        Ok(payouts)
+    }
+
+    /// Payouts to offer for the events of `announcements`, the first of which
+    /// sets the outcomes
+    ///
+    /// A `Numeric` payout function is linear between its curve points and
+    /// flat outside them. Its payouts are rounded to whole percent of the
+    /// collateral, which keeps the number of CETs down.
+    pub fn contract_outcomes(&self, announcements: &[OracleAnnouncement]) -> AnyaResult<ContractOutcomes> {
+        let announcement = announcements.first().ok_or("Contract has no oracle announcement")?;
+        let total = self.offer_collateral + self.accept_collateral;
+        let decomposition = || announcement.digit_decomposition.clone()
+            .ok_or_else(|| format!("Event {} is not numeric", announcement.event_id));
+        match &self.payout_function {
+            PayoutFunction::Binary { .. } | PayoutFunction::Enumerated { .. } => {
+                let outcomes = self.payouts(announcement)?.into_iter()
+                    .map(|(outcome, offer_payout, _)| ContractOutcome { outcome, offer_payout })
+                    .collect();
+                Ok(ContractOutcomes::Enumerated(outcomes))
+            }
+            PayoutFunction::Numeric { range, curve_points, .. } => {
+                let decomposition = decomposition()?;
+                let (first, last) = match (curve_points.first(), curve_points.last()) {
+                    (Some(first), Some(last)) => (*first, *last),
+                    _ => return Err("A numeric payout function needs curve points".into()),
+                };
+                if range.0 < 0 || range.0 > range.1 || range.1 as u64 > decomposition.max_value() {
+                    return Err(format!(
+                        "Range {}..{} does not fit the event's outcomes 0 to {}", range.0, range.1, decomposition.max_value()
+                    ).into());
+                }
+                if curve_points.iter().any(|(outcome, _)| *outcome < range.0 || *outcome > range.1) {
+                    return Err("Curve points must lie within the range".into());
+                }
+                if curve_points.iter().any(|(_, percentage)| *percentage > 100) {
+                    return Err("Payout percentages cannot exceed 100".into());
+                }
+                let point = |outcome: u64, percentage: u8| PayoutPoint::new(outcome, total * percentage as u64 / 100);
+                let mut points = Vec::new();
+                if first.0 > 0 {
+                    points.push(point(0, first.1));
+                }
+                points.extend(curve_points.iter().map(|(outcome, percentage)| point(*outcome as u64, *percentage)));
+                if (last.0 as u64) < decomposition.max_value() {
+                    points.push(point(decomposition.max_value(), last.1));
+                }
+                Ok(ContractOutcomes::Numeric {
+                    nb_digits: decomposition.nb_digits,
+                    payout_curve: PayoutCurve::piecewise_linear(&points)?,
+                    rounding_intervals: RoundingIntervals::uniform((total / 100).max(1))?,
+                })
+            }
+            PayoutFunction::Curve { curve, rounding_intervals } => Ok(ContractOutcomes::Numeric {
+                nb_digits: decomposition()?.nb_digits,
+                payout_curve: curve.clone(),
+                rounding_intervals: rounding_intervals.clone(),
+            }),
+        }
+    }
 }
 
 /// Represents a DLC execution path with associated CET
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractExecutionPath {
     /// Outcome value
     pub outcome: String,
@@ -178,7 +424,7 @@ pub struct ContractExecutionPath {
 }
 
 /// Defines how funds should be distributed based on oracle outcomes
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PayoutFunction {
     /// Binary outcome (win/lose)
     Binary {
@@ -210,6 +456,15 @@ pub enum PayoutFunction {
         /// Map of outcome -> (offer_payout, accept_payout) in sats
         outcomes: HashMap<String, (u64, u64)>,
     },
+
+    /// Numeric outcome paid out along a curve covering every outcome
+    Curve {
+        /// Payout to the offer party (sats) by outcome
+        curve: PayoutCurve,
+
+        /// Rounding of the payouts
+        rounding_intervals: RoundingIntervals,
+    },
 }
 
 /// Parameters for creating a new contract
@@ -232,8 +487,19 @@ pub struct ContractParameters {
     
     /// Oracle URLs to use
     pub oracle_urls: Vec<String>,
+
+    /// Announcements of the event the contract settles on, one per oracle
+    pub oracle_announcements: Vec<OracleAnnouncement>,
+
+    /// How many oracles must attest (defaults to all of them)
+    pub oracle_threshold: Option<u16>,
+
+    /// How far apart numeric attestations may be when not all oracles must
+    /// agree exactly
+    pub bounded_error: Option<BoundedError>,
     
-    /// Timelock for refund in blocks
+    /// Absolute refund locktime (defaults to `locktime_period` blocks' worth of
+    /// time after the event matures)
     pub refund_locktime: Option<u32>,
     
     /// Fee rate in sat/vbyte
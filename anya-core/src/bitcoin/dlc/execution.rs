@@ -1,16 +1,30 @@
 // src/bitcoin/dlc/execution.rs
 
+//! Settlement of funded contracts
+//!
+//! The [`ExecutionManager`] watches every contract a manager holds: it
+//! settles funded contracts as soon as enough oracles attest, refunds them
+//! once the refund time lock expires, and records closes by the
+//! counterparty. Each settlement is kept as an [`ExecutionRecord`].
+
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
 use chrono::{DateTime, Utc};
-use bitcoin::Txid;
-use bitcoin::secp256k1::Signature;
+use bitcoin::{absolute, Txid};
+use serde::{Deserialize, Serialize};
 
-use crate::common::error::AnyaResult;
+use crate::AnyaResult;
+use super::chain::{self, DLCChain};
 use super::contract::{Contract, ContractState};
-use super::oracle::OracleAttestation;
+use super::oracle::{AttestationSource, OracleAttestation};
+use super::storage::DLCStore;
+use super::DLCManager;
 
 /// Status of a DLC execution
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExecutionStatus {
     /// Execution is pending (waiting for oracle attestation)
     Pending,
@@ -32,7 +46,7 @@ pub enum ExecutionStatus {
 }
 
 /// Record of a DLC execution
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionRecord {
     /// Unique identifier
     pub id: String,
@@ -88,14 +102,14 @@ pub fn new(contract_id: String) -> Self {
     
     /// Updates the status of the execution
     pub fn update_status(&mut self, status: ExecutionStatus) {
-        self.status = status;
-        
-        if status == ExecutionStatus::Successful || 
-           matches!(status, ExecutionStatus::Failed(_)) || 
-           status == ExecutionStatus::Refunded ||
-           status == ExecutionStatus::TimedOut {
+        if matches!(
+            status,
+            ExecutionStatus::Successful | ExecutionStatus::Failed(_) | ExecutionStatus::Refunded | ExecutionStatus::TimedOut
+        ) {
             self.completed_at = Some(Utc::now());
         }
+
+        self.status = status;
     }
     
     /// Sets the oracle attestation
@@ -122,115 +136,280 @@ pub fn add_metadata(&mut self, key: &str, value: &str) {
 }
 
 /// Manager for DLC executions
+///
+/// `store` must be the store `manager` keeps its contracts in; execution
+/// records are kept next to them.
 pub struct ExecutionManager {
-    /// Map of contract ID to execution records
-    executions: HashMap<String, Vec<ExecutionRecord>>,
+    manager: Arc<dyn DLCManager + Send + Sync>,
+    store: Arc<dyn DLCStore>,
+    chain: Arc<dyn DLCChain>,
+    attestation_sources: Vec<Arc<dyn AttestationSource>>,
 }
 
 impl ExecutionManager {
-    /// Creates a new execution manager
-    pub fn new() -> Self {
+    /// Creates an execution manager settling `manager`'s contracts on `chain`
+    pub fn new(manager: Arc<dyn DLCManager + Send + Sync>, store: Arc<dyn DLCStore>, chain: Arc<dyn DLCChain>) -> Self {
         Self {
-            executions: HashMap::new(),
+            manager,
+            store,
+            chain,
+            attestation_sources: Vec::new(),
         }
     }
+
+    /// Adds a source polled for attestations of funded contracts' events
+    pub fn add_attestation_source(&mut self, source: Arc<dyn AttestationSource>) {
+        self.attestation_sources.push(source);
+    }
     
     /// Creates a new execution record for a contract
-    pub fn create_execution(&mut self, contract_id: &str) -> AnyaResult<ExecutionRecord> {
+    pub fn create_execution(&self, contract_id: &str) -> AnyaResult<ExecutionRecord> {
         let record = ExecutionRecord::new(contract_id.to_string());
-        
-        let records = self.executions.entry(contract_id.to_string())
-            .or_insert_with(Vec::new);
-        
-        records.push(record.clone());
-        
+        self.store.save_execution(&record)?;
         Ok(record)
     }
     
     /// Gets all execution records for a contract
-    pub fn get_executions(&self, contract_id: &str) -> Vec<ExecutionRecord> {
-        self.executions.get(contract_id)
-            .map(|records| records.clone())
-            .unwrap_or_else(Vec::new)
+    pub fn get_executions(&self, contract_id: &str) -> AnyaResult<Vec<ExecutionRecord>> {
+        self.store.get_executions(contract_id)
     }
     
     /// Gets the latest execution record for a contract
-    pub fn get_latest_execution(&self, contract_id: &str) -> Option<ExecutionRecord> {
-        self.executions.get(contract_id)
-            .and_then(|records| records.last().cloned())
+    pub fn get_latest_execution(&self, contract_id: &str) -> AnyaResult<Option<ExecutionRecord>> {
+        Ok(self.store.get_executions(contract_id)?.pop())
     }
     
     /// Gets an execution record by ID
-    pub fn get_execution(&self, execution_id: &str) -> Option<ExecutionRecord> {
-        for records in self.executions.values() {
-            if let Some(record) = records.iter().find(|r| r.id == execution_id) {
-                return Some(record.clone());
+    pub fn get_execution(&self, execution_id: &str) -> AnyaResult<Option<ExecutionRecord>> {
+        for contract in self.manager.list_contracts()? {
+            if let Some(record) = self.store.get_executions(&contract.id)?.into_iter().find(|r| r.id == execution_id) {
+                return Ok(Some(record));
             }
         }
-        
-        None
+        Ok(None)
     }
     
     /// Updates an execution record
-    pub fn update_execution(&mut self, record: ExecutionRecord) -> AnyaResult<()> {
-        if let Some(records) = self.executions.get_mut(&record.contract_id) {
-            if let Some(index) = records.iter().position(|r| r.id == record.id) {
-                records[index] = record;
-                return Ok(());
-            }
+    pub fn update_execution(&self, record: ExecutionRecord) -> AnyaResult<()> {
+        if !self.store.get_executions(&record.contract_id)?.iter().any(|r| r.id == record.id) {
+            return Err("Execution record not found".into());
         }
-        
-        Err("Execution record not found".into())
+        self.store.save_execution(&record)
     }
     
-    /// Executes a contract with an oracle attestation
+    /// Executes a contract with oracle attestations, broadcasting the CET
+    /// they unlock
     pub fn execute_contract(
-        &mut self, 
-        contract: &mut Contract, 
-        attestation: OracleAttestation
+        &self,
+        contract: &Contract,
+        attestations: Vec<OracleAttestation>,
     ) -> AnyaResult<ExecutionRecord> {
         // Validate the contract state
         if contract.state != ContractState::Funded {
             return Err(format!("Contract is not in funded state: {:?}", contract.state).into());
         }
         
-        // Create a new execution record
         let mut record = self.create_execution(&contract.id)?;
-        
-        // Set the attestation
-        record.set_attestation(attestation);
-        
-        // Update the status to in progress
+        if let Some(attestation) = attestations.first() {
+            record.set_attestation(attestation.clone());
+        }
+        if attestations.len() > 1 {
+            let outcomes: Vec<&str> = attestations.iter().map(|a| a.outcome.as_str()).collect();
+            record.add_metadata("outcomes", &outcomes.join(","));
+        }
         record.update_status(ExecutionStatus::InProgress);
-        
-        // Update the execution record
         self.update_execution(record.clone())?;
-        
-        // Update the contract state
-        contract.update_state(ContractState::Executed);
-        
-        Ok(record)
+
+        match self.manager.execute_contract_with_attestations(contract, attestations) {
+            Ok(_) => self.settled(record, ExecutionStatus::InProgress),
+            Err(e) => {
+                record.update_status(ExecutionStatus::Failed(e.to_string()));
+                self.update_execution(record)?;
+                Err(e)
+            }
+        }
     }
     
-    /// Processes a refund for a contract
-    pub fn process_refund(&mut self, contract: &mut Contract) -> AnyaResult<ExecutionRecord> {
+    /// Processes a refund for a contract, broadcasting the refund transaction
+    pub fn process_refund(&self, contract: &Contract) -> AnyaResult<ExecutionRecord> {
         // Validate contract state
         if contract.state != ContractState::Funded {
             return Err(format!("Contract is not in funded state: {:?}", contract.state).into());
         }
         
-        // Create a new execution record
-        let mut record = self.create_execution(&contract.id)?;
-        
-        // Update the status to refunded
-        record.update_status(ExecutionStatus::Refunded);
-        
-        // Update the execution record
+        let record = self.create_execution(&contract.id)?;
+        match self.manager.refund_contract(contract) {
+            Ok(_) => self.settled(record, ExecutionStatus::Refunded),
+            Err(e) => {
+                let mut record = record;
+                record.update_status(ExecutionStatus::Failed(e.to_string()));
+                self.update_execution(record)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Checks every contract once: follows funding outputs on chain, settles
+    /// funded contracts whose oracles attested, and refunds those past their
+    /// refund time lock
+    ///
+    /// Returns the execution records created or updated. A contract that
+    /// fails to settle is skipped and retried on the next call.
+    pub fn sync(&self) -> AnyaResult<Vec<ExecutionRecord>> {
+        let mut records = Vec::new();
+        for contract in self.manager.list_contracts()? {
+            match self.sync_contract(&contract.id) {
+                Ok(Some(record)) => records.push(record),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to settle contract {}: {}", contract.id, e),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Runs `sync` every `interval` on a background thread until the
+    /// returned handle is stopped
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> MonitorHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = std::thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                if let Err(e) = self.sync() {
+                    tracing::warn!("DLC monitor sync failed: {}", e);
+                }
+                std::thread::park_timeout(interval);
+            }
+        });
+        MonitorHandle { stop, thread: Some(thread) }
+    }
+
+    fn sync_contract(&self, contract_id: &str) -> AnyaResult<Option<ExecutionRecord>> {
+        let state = self.manager.update_contract_status(contract_id)?;
+        let contract = self.manager.get_contract(contract_id)?
+            .ok_or_else(|| format!("Unknown contract {}", contract_id))?;
+        if contract.is_in_channel() {
+            // Settled off-chain by the sub-channel manager unless the channel is force closed
+            return Ok(None);
+        }
+        let latest = self.get_latest_execution(contract_id)?;
+
+        match state {
+            ContractState::Funded => {
+                if let Some(attestations) = self.attestations(&contract)? {
+                    return self.execute_contract(&contract, attestations).map(Some);
+                }
+                let offer = contract.offer.as_ref().ok_or("Contract has no offer")?;
+                if chain::is_final(self.chain.as_ref(), absolute::LockTime::from_consensus(offer.refund_locktime))? {
+                    return self.process_refund(&contract).map(Some);
+                }
+                Ok(None)
+            }
+            ContractState::Closed | ContractState::Refunded => {
+                let txid = match state {
+                    ContractState::Closed => contract.execution_txid,
+                    _ => contract.refund_txid,
+                };
+                match latest {
+                    Some(record) if record.execution_txid == txid => {
+                        // Our own settlement, complete once it spends the funding output
+                        if record.status != ExecutionStatus::InProgress || !self.spent_by(&contract, txid)? {
+                            return Ok(None);
+                        }
+                        self.settled(record, ExecutionStatus::Successful).map(Some)
+                    }
+                    _ => {
+                        // Closed or refunded by the counterparty
+                        let mut record = self.create_execution(contract_id)?;
+                        record.add_metadata("closed_by", "counterparty");
+                        let status = if state == ContractState::Refunded {
+                            ExecutionStatus::Refunded
+                        } else {
+                            ExecutionStatus::Successful
+                        };
+                        self.settled(record, status).map(Some)
+                    }
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Attestations of the contract's events, once at least the threshold
+    /// of its oracles attested
+    fn attestations(&self, contract: &Contract) -> AnyaResult<Option<Vec<OracleAttestation>>> {
+        let mut attestations = Vec::new();
+        for announcement in &contract.oracle_announcements {
+            for source in &self.attestation_sources {
+                match source.attestation(announcement) {
+                    Ok(Some(attestation)) if attestation.verify(announcement)? => {
+                        attestations.push(attestation);
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Failed to fetch attestation of {}: {}", announcement.event_id, e),
+                }
+            }
+        }
+        let threshold = contract.descriptor.oracle_threshold.max(1) as usize;
+        Ok((attestations.len() >= threshold).then_some(attestations))
+    }
+
+    fn spent_by(&self, contract: &Contract, txid: Option<Txid>) -> AnyaResult<bool> {
+        let txs = contract.transactions.as_ref().ok_or("Contract has no transactions")?;
+        let spend = self.chain.spending_transaction(&txs.funding_outpoint())?;
+        Ok(spend.map(|spend| spend.compute_txid()) == txid && txid.is_some())
+    }
+
+    /// Fill in the settlement transaction and payouts of `record` from the
+    /// stored contract
+    fn settled(&self, mut record: ExecutionRecord, status: ExecutionStatus) -> AnyaResult<ExecutionRecord> {
+        let contract = self.manager.get_contract(&record.contract_id)?
+            .ok_or_else(|| format!("Unknown contract {}", record.contract_id))?;
+        match contract.state {
+            ContractState::Closed => {
+                let txid = contract.execution_txid.ok_or("Closed contract has no execution transaction")?;
+                record.set_execution_txid(txid);
+                if let Some(path) = contract.execution_path(&txid) {
+                    record.outcome.get_or_insert_with(|| path.outcome.clone());
+                    record.set_final_amounts(path.offer_payout, path.accept_payout);
+                }
+            }
+            ContractState::Refunded => {
+                let txid = contract.refund_txid.ok_or("Refunded contract has no refund transaction")?;
+                record.set_execution_txid(txid);
+                record.set_final_amounts(contract.descriptor.offer_collateral, contract.descriptor.accept_collateral);
+            }
+            _ => {}
+        }
+        record.update_status(status);
         self.update_execution(record.clone())?;
-        
-        // Update the contract state
-        contract.update_state(ContractState::Refunded);
-        
         Ok(record)
     }
-} 
\ No newline at end of file
+}
+
+/// Handle of a monitor started with [`ExecutionManager::spawn`]
+pub struct MonitorHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MonitorHandle {
+    /// Stops the monitor and waits for its thread to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MonitorHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
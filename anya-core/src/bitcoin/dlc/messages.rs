@@ -0,0 +1,959 @@
+// src/bitcoin/dlc/messages.rs
+
+//! DLC negotiation messages in the dlcspecs wire format
+//!
+//! A contract is negotiated with three messages:
+//!
+//! - `offer_dlc` (type 42778): contract terms, oracle announcements, the
+//!   offerer's funding key, inputs, payout and change scripts
+//! - `accept_dlc` (type 42780): the accepter's funding key, inputs and scripts,
+//!   plus its adaptor signatures for every CET and its refund signature
+//! - `sign_dlc` (type 42782): the offerer's CET adaptor signatures, refund
+//!   signature and funding input signatures
+//!
+//! Integers are big-endian, `bigsize` is the Lightning variable-length
+//! integer, strings are bigsize-length-prefixed UTF-8 and scripts are
+//! u16-length-prefixed. Sub-types are prefixed with a bigsize type number.
+
+use bitcoin::consensus;
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::{schnorr, PublicKey, XOnlyPublicKey};
+use bitcoin::{ScriptBuf, Transaction, Witness};
+use serde::{Deserialize, Serialize};
+
+use crate::AnyaResult;
+use super::adaptor::AdaptorSignature;
+use super::numeric::{BoundedError, DigitDecomposition, PayoutCurve, PayoutPoint, PolynomialPiece, RoundingInterval, RoundingIntervals};
+
+/// Message type of `offer_dlc`
+pub const OFFER_DLC_TYPE: u16 = 42778;
+
+/// Message type of `accept_dlc`
+pub const ACCEPT_DLC_TYPE: u16 = 42780;
+
+/// Message type of `sign_dlc`
+pub const SIGN_DLC_TYPE: u16 = 42782;
+
+/// Protocol version written into every message
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// TLV type of `oracle_event`
+pub const ORACLE_EVENT_TYPE: u64 = 55330;
+
+/// TLV type of `oracle_announcement`
+pub const ORACLE_ANNOUNCEMENT_TYPE: u64 = 55332;
+
+/// TLV type of `oracle_attestation`
+pub const ORACLE_ATTESTATION_TYPE: u64 = 55400;
+
+/// TLV type of `enum_event_descriptor`
+pub const ENUM_EVENT_DESCRIPTOR_TYPE: u64 = 55302;
+
+/// TLV type of `digit_decomposition_event_descriptor`
+pub const DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE: u64 = 55306;
+
+/// Serializer for the dlcspecs wire format
+#[derive(Debug, Default)]
+pub(crate) struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Lightning BigSize integer
+    pub(crate) fn bigsize(&mut self, value: u64) {
+        match value {
+            0..=0xfc => self.u8(value as u8),
+            0xfd..=0xffff => {
+                self.u8(0xfd);
+                self.u16(value as u16);
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.u8(0xfe);
+                self.u32(value as u32);
+            }
+            _ => {
+                self.u8(0xff);
+                self.u64(value);
+            }
+        }
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// u16-length-prefixed bytes
+    pub(crate) fn u16_bytes(&mut self, bytes: &[u8]) {
+        self.u16(bytes.len() as u16);
+        self.bytes(bytes);
+    }
+
+    /// bigsize-length-prefixed UTF-8 string
+    pub(crate) fn string(&mut self, value: &str) {
+        self.bigsize(value.len() as u64);
+        self.bytes(value.as_bytes());
+    }
+
+    /// TLV record: bigsize type, bigsize length, value
+    pub(crate) fn tlv(&mut self, tlv_type: u64, value: &[u8]) {
+        self.bigsize(tlv_type);
+        self.bigsize(value.len() as u64);
+        self.bytes(value);
+    }
+}
+
+/// Deserializer for the dlcspecs wire format
+#[derive(Debug)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> AnyaResult<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(format!("Message truncated: needed {} more bytes, {} left", len, self.data.len()).into());
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> AnyaResult<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().expect("length checked"))
+    }
+
+    pub(crate) fn u8(&mut self) -> AnyaResult<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> AnyaResult<u16> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> AnyaResult<u32> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> AnyaResult<u64> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    pub(crate) fn bigsize(&mut self) -> AnyaResult<u64> {
+        let (value, minimum) = match self.u8()? {
+            0xfd => (self.u16()? as u64, 0xfd),
+            0xfe => (self.u32()? as u64, 0x1_0000),
+            0xff => (self.u64()?, 0x1_0000_0000),
+            small => return Ok(small as u64),
+        };
+        if value < minimum {
+            return Err("Non-canonical bigsize encoding".into());
+        }
+        Ok(value)
+    }
+
+    /// A count of items, each at least `item_size` bytes, bounded by the data left
+    pub(crate) fn count(&mut self, item_size: usize) -> AnyaResult<usize> {
+        let count = self.bigsize()? as usize;
+        if count.saturating_mul(item_size.max(1)) > self.data.len() {
+            return Err(format!("Item count {} exceeds the message length", count).into());
+        }
+        Ok(count)
+    }
+
+    pub(crate) fn u16_bytes(&mut self) -> AnyaResult<&'a [u8]> {
+        let len = self.u16()? as usize;
+        self.bytes(len)
+    }
+
+    pub(crate) fn string(&mut self) -> AnyaResult<String> {
+        let len = self.count(1)?;
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| "String is not valid UTF-8".into())
+    }
+
+    /// TLV record, checking its type
+    pub(crate) fn tlv(&mut self, expected_type: u64) -> AnyaResult<Reader<'a>> {
+        let (tlv_type, value) = self.any_tlv()?;
+        if tlv_type != expected_type {
+            return Err(format!("Expected TLV type {}, got {}", expected_type, tlv_type).into());
+        }
+        Ok(value)
+    }
+
+    /// TLV record of any type
+    pub(crate) fn any_tlv(&mut self) -> AnyaResult<(u64, Reader<'a>)> {
+        let tlv_type = self.bigsize()?;
+        let len = self.count(1)?;
+        Ok((tlv_type, Reader::new(self.bytes(len)?)))
+    }
+
+    pub(crate) fn public_key(&mut self) -> AnyaResult<PublicKey> {
+        PublicKey::from_slice(self.bytes(33)?).map_err(|e| format!("Invalid public key: {}", e).into())
+    }
+
+    pub(crate) fn x_only_public_key(&mut self) -> AnyaResult<XOnlyPublicKey> {
+        XOnlyPublicKey::from_slice(self.bytes(32)?).map_err(|e| format!("Invalid x-only public key: {}", e).into())
+    }
+
+    pub(crate) fn schnorr_signature(&mut self) -> AnyaResult<schnorr::Signature> {
+        schnorr::Signature::from_slice(self.bytes(64)?).map_err(|e| format!("Invalid signature: {}", e).into())
+    }
+
+    pub(crate) fn signature(&mut self) -> AnyaResult<Signature> {
+        Signature::from_compact(self.bytes(64)?).map_err(|e| format!("Invalid signature: {}", e).into())
+    }
+
+    pub(crate) fn script(&mut self) -> AnyaResult<ScriptBuf> {
+        Ok(ScriptBuf::from_bytes(self.u16_bytes()?.to_vec()))
+    }
+}
+
+/// Read and check the 2-byte message type
+fn read_message_type(reader: &mut Reader, expected: u16) -> AnyaResult<()> {
+    let message_type = reader.u16()?;
+    if message_type != expected {
+        return Err(format!("Expected message type {}, got {}", expected, message_type).into());
+    }
+    Ok(())
+}
+
+/// An outcome of an enumerated contract and the offerer's payout for it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractOutcome {
+    /// Outcome as announced by the oracle
+    pub outcome: String,
+
+    /// Payout to the offering party in satoshis (the accepter gets the rest)
+    pub offer_payout: u64,
+}
+
+/// What an oracle event reports (`event_descriptor`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventDescriptor {
+    /// One of a list of outcomes, signed with a single nonce
+    Enumerated(Vec<String>),
+
+    /// A number, signed digit by digit with one nonce per digit
+    DigitDecomposition(DigitDecomposition),
+}
+
+impl EventDescriptor {
+    fn write(&self, writer: &mut Writer) {
+        let mut value = Writer::new();
+        match self {
+            Self::Enumerated(outcomes) => {
+                value.u16(outcomes.len() as u16);
+                for outcome in outcomes {
+                    value.string(outcome);
+                }
+                writer.tlv(ENUM_EVENT_DESCRIPTOR_TYPE, &value.into_bytes());
+            }
+            Self::DigitDecomposition(digits) => {
+                value.bigsize(digits.base as u64);
+                value.u8(0); // unsigned
+                value.string(&digits.unit);
+                value.u32(digits.precision as u32);
+                value.u16(digits.nb_digits);
+                writer.tlv(DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE, &value.into_bytes());
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> AnyaResult<Self> {
+        match reader.any_tlv()? {
+            (ENUM_EVENT_DESCRIPTOR_TYPE, mut value) => {
+                let outcome_count = value.u16()? as usize;
+                let outcomes = (0..outcome_count)
+                    .map(|_| value.string())
+                    .collect::<AnyaResult<Vec<_>>>()?;
+                Ok(Self::Enumerated(outcomes))
+            }
+            (DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE, mut value) => {
+                let base = u16::try_from(value.bigsize()?).map_err(|_| "Digit base out of range")?;
+                if value.u8()? != 0 {
+                    return Err("Signed numeric events are not supported".into());
+                }
+                let digits = DigitDecomposition {
+                    base,
+                    unit: value.string()?,
+                    precision: value.u32()? as i32,
+                    nb_digits: value.u16()?,
+                };
+                digits.validate()?;
+                Ok(Self::DigitDecomposition(digits))
+            }
+            (other, _) => Err(format!("Unsupported event descriptor type {}", other).into()),
+        }
+    }
+}
+
+/// Oracle event a contract settles on (`oracle_event`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleEvent {
+    /// Oracle's BIP340 public key
+    pub oracle_public_key: XOnlyPublicKey,
+
+    /// Nonces the oracle committed to, one per signed value
+    pub oracle_nonces: Vec<XOnlyPublicKey>,
+
+    /// When the outcome is expected, as a UNIX timestamp
+    pub event_maturity_epoch: u32,
+
+    /// Possible outcomes
+    pub descriptor: EventDescriptor,
+
+    /// Event identifier
+    pub event_id: String,
+}
+
+impl OracleEvent {
+    /// The serialized `oracle_event` TLV, which the announcement signature commits to
+    pub fn to_tlv(&self) -> Vec<u8> {
+        let mut value = Writer::new();
+        self.write_value(&mut value);
+        let mut writer = Writer::new();
+        writer.tlv(ORACLE_EVENT_TYPE, &value.into_bytes());
+        writer.into_bytes()
+    }
+
+    /// `oracle_event` TLV value (without the type and length)
+    fn write_value(&self, writer: &mut Writer) {
+        writer.u16(self.oracle_nonces.len() as u16);
+        for nonce in &self.oracle_nonces {
+            writer.bytes(&nonce.serialize());
+        }
+        writer.u32(self.event_maturity_epoch);
+        self.descriptor.write(writer);
+        writer.string(&self.event_id);
+    }
+
+    /// Parse an `oracle_event` TLV value
+    fn read_value(reader: &mut Reader, oracle_public_key: XOnlyPublicKey) -> AnyaResult<Self> {
+        let nonce_count = reader.u16()? as usize;
+        let oracle_nonces = (0..nonce_count)
+            .map(|_| reader.x_only_public_key())
+            .collect::<AnyaResult<Vec<_>>>()?;
+        let event_maturity_epoch = reader.u32()?;
+        let descriptor = EventDescriptor::read(reader)?;
+
+        Ok(Self {
+            oracle_public_key,
+            oracle_nonces,
+            event_maturity_epoch,
+            descriptor,
+            event_id: reader.string()?,
+        })
+    }
+}
+
+/// An oracle event and the oracle's signature over it (`oracle_announcement`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedOracleEvent {
+    /// Oracle's BIP340 signature over the `oracle_event` TLV
+    pub signature: schnorr::Signature,
+
+    /// The announced event
+    pub event: OracleEvent,
+}
+
+impl SignedOracleEvent {
+    pub(crate) fn write(&self, writer: &mut Writer) {
+        let mut value = Writer::new();
+        value.bytes(self.signature.as_ref());
+        value.bytes(&self.event.oracle_public_key.serialize());
+        value.bytes(&self.event.to_tlv());
+        writer.tlv(ORACLE_ANNOUNCEMENT_TYPE, &value.into_bytes());
+    }
+
+    pub(crate) fn read(reader: &mut Reader) -> AnyaResult<Self> {
+        let mut value = reader.tlv(ORACLE_ANNOUNCEMENT_TYPE)?;
+        let signature = value.schnorr_signature()?;
+        let oracle_public_key = value.x_only_public_key()?;
+        let event = OracleEvent::read_value(&mut value.tlv(ORACLE_EVENT_TYPE)?, oracle_public_key)?;
+        Ok(Self { signature, event })
+    }
+}
+
+/// Payouts of a contract (`contract_descriptor`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContractOutcomes {
+    /// Payout per enumerated outcome, in CET order
+    Enumerated(Vec<ContractOutcome>),
+
+    /// Payout curve over a numeric outcome
+    Numeric {
+        /// Digits the oracles sign
+        nb_digits: u16,
+
+        /// Payout to the offering party
+        payout_curve: PayoutCurve,
+
+        /// Rounding of the payout curve
+        rounding_intervals: RoundingIntervals,
+    },
+}
+
+impl ContractOutcomes {
+    fn write(&self, writer: &mut Writer) {
+        match self {
+            Self::Enumerated(outcomes) => {
+                writer.bigsize(0);
+                writer.bigsize(outcomes.len() as u64);
+                for outcome in outcomes {
+                    writer.string(&outcome.outcome);
+                    writer.u64(outcome.offer_payout);
+                }
+            }
+            Self::Numeric { nb_digits, payout_curve, rounding_intervals } => {
+                writer.bigsize(1);
+                writer.u16(*nb_digits);
+                writer.bigsize(payout_curve.pieces.len() as u64);
+                // Each piece is its left endpoint and interior points; the
+                // curve's last endpoint follows the pieces
+                for piece in &payout_curve.pieces {
+                    write_payout_point(writer, &piece.points[0]);
+                    writer.bigsize(0); // polynomial piece
+                    let interior = &piece.points[1..piece.points.len() - 1];
+                    writer.bigsize(interior.len() as u64);
+                    for point in interior {
+                        write_payout_point(writer, point);
+                    }
+                }
+                let last_piece = &payout_curve.pieces[payout_curve.pieces.len() - 1];
+                write_payout_point(writer, &last_piece.points[last_piece.points.len() - 1]);
+                writer.bigsize(rounding_intervals.intervals.len() as u64);
+                for interval in &rounding_intervals.intervals {
+                    writer.bigsize(interval.begin_interval);
+                    writer.bigsize(interval.rounding_mod);
+                }
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> AnyaResult<Self> {
+        match reader.bigsize()? {
+            0 => {
+                let outcome_count = reader.count(9)?;
+                let outcomes = (0..outcome_count)
+                    .map(|_| Ok(ContractOutcome { outcome: reader.string()?, offer_payout: reader.u64()? }))
+                    .collect::<AnyaResult<Vec<_>>>()?;
+                Ok(Self::Enumerated(outcomes))
+            }
+            1 => {
+                let nb_digits = reader.u16()?;
+                let piece_count = reader.count(4)?;
+                let mut starts = Vec::with_capacity(piece_count);
+                for _ in 0..piece_count {
+                    let mut points = vec![read_payout_point(reader)?];
+                    expect_subtype(reader, "payout curve piece")?;
+                    let interior_count = reader.count(4)?;
+                    for _ in 0..interior_count {
+                        points.push(read_payout_point(reader)?);
+                    }
+                    starts.push(points);
+                }
+                let mut end = read_payout_point(reader)?;
+                let mut pieces = Vec::with_capacity(piece_count);
+                for mut points in starts.into_iter().rev() {
+                    let start = points[0];
+                    points.push(end);
+                    pieces.push(PolynomialPiece::new(points)?);
+                    end = start;
+                }
+                pieces.reverse();
+
+                let interval_count = reader.count(2)?;
+                let intervals = (0..interval_count)
+                    .map(|_| Ok(RoundingInterval { begin_interval: reader.bigsize()?, rounding_mod: reader.bigsize()? }))
+                    .collect::<AnyaResult<Vec<_>>>()?;
+                Ok(Self::Numeric {
+                    nb_digits,
+                    payout_curve: PayoutCurve::new(pieces)?,
+                    rounding_intervals: RoundingIntervals::new(intervals)?,
+                })
+            }
+            other => Err(format!("Unsupported contract descriptor type {}", other).into()),
+        }
+    }
+}
+
+fn write_payout_point(writer: &mut Writer, point: &PayoutPoint) {
+    writer.bigsize(point.event_outcome);
+    writer.bigsize(point.outcome_payout);
+    writer.u16(point.extra_precision);
+}
+
+fn read_payout_point(reader: &mut Reader) -> AnyaResult<PayoutPoint> {
+    Ok(PayoutPoint {
+        event_outcome: reader.bigsize()?,
+        outcome_payout: reader.bigsize()?,
+        extra_precision: reader.u16()?,
+    })
+}
+
+/// Contract terms (`contract_info`, single form)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractInfo {
+    /// Sum of both parties' collateral in satoshis
+    pub total_collateral: u64,
+
+    /// Payouts by outcome
+    pub outcomes: ContractOutcomes,
+
+    /// Announcements of the oracles the contract settles on
+    pub oracle_announcements: Vec<SignedOracleEvent>,
+
+    /// How many of the oracles must attest
+    pub oracle_threshold: u16,
+
+    /// How far apart numeric attestations may be, when they need not agree
+    pub bounded_error: Option<BoundedError>,
+}
+
+impl ContractInfo {
+    fn write(&self, writer: &mut Writer) {
+        writer.bigsize(0); // single contract info
+        writer.u64(self.total_collateral);
+        self.outcomes.write(writer);
+
+        if self.oracle_announcements.len() == 1 && self.oracle_threshold == 1 && self.bounded_error.is_none() {
+            writer.bigsize(0); // single oracle info
+            self.oracle_announcements[0].write(writer);
+            return;
+        }
+        writer.bigsize(1); // multi oracle info
+        writer.u16(self.oracle_threshold);
+        writer.bigsize(self.oracle_announcements.len() as u64);
+        for announcement in &self.oracle_announcements {
+            announcement.write(writer);
+        }
+        match &self.bounded_error {
+            Some(bounds) => {
+                writer.u8(1);
+                writer.u16(bounds.max_error_exp);
+                writer.u16(bounds.min_fail_exp);
+                writer.u8(0); // coverage is not maximized
+            }
+            None => writer.u8(0),
+        }
+    }
+
+    fn read(reader: &mut Reader) -> AnyaResult<Self> {
+        expect_subtype(reader, "contract info")?;
+        let total_collateral = reader.u64()?;
+        let outcomes = ContractOutcomes::read(reader)?;
+
+        let (oracle_announcements, oracle_threshold, bounded_error) = match reader.bigsize()? {
+            0 => (vec![SignedOracleEvent::read(reader)?], 1, None),
+            1 => {
+                let threshold = reader.u16()?;
+                let count = reader.count(1)?;
+                let announcements = (0..count)
+                    .map(|_| SignedOracleEvent::read(reader))
+                    .collect::<AnyaResult<Vec<_>>>()?;
+                let bounded_error = match reader.u8()? {
+                    0 => None,
+                    _ => {
+                        let bounds = BoundedError::new(reader.u16()?, reader.u16()?)?;
+                        if reader.u8()? != 0 {
+                            return Err("Maximized oracle coverage is not supported".into());
+                        }
+                        Some(bounds)
+                    }
+                };
+                (announcements, threshold, bounded_error)
+            }
+            other => return Err(format!("Unsupported oracle info type {}", other).into()),
+        };
+
+        Ok(Self { total_collateral, outcomes, oracle_announcements, oracle_threshold, bounded_error })
+    }
+}
+
+/// Only the first variant (type 0) of each sub-type is supported
+fn expect_subtype(reader: &mut Reader, name: &str) -> AnyaResult<()> {
+    match reader.bigsize()? {
+        0 => Ok(()),
+        other => Err(format!("Unsupported {} type {}", name, other).into()),
+    }
+}
+
+/// A UTXO a party contributes to the funding transaction (`funding_input`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FundingInput {
+    /// Serial ID ordering inputs in the funding transaction
+    pub input_serial_id: u64,
+
+    /// Transaction containing the output being spent
+    pub prev_tx: Transaction,
+
+    /// Index of the output in `prev_tx`
+    pub prev_tx_vout: u32,
+
+    /// Input sequence number
+    pub sequence: u32,
+
+    /// Upper bound of the witness size, for fee computation
+    pub max_witness_len: u16,
+
+    /// Redeem script for P2SH-wrapped inputs, empty otherwise
+    pub redeem_script: ScriptBuf,
+}
+
+impl FundingInput {
+    /// Value of the spent output in satoshis
+    pub fn value(&self) -> AnyaResult<u64> {
+        self.prev_tx.output.get(self.prev_tx_vout as usize)
+            .map(|output| output.value.to_sat())
+            .ok_or_else(|| format!("Funding input {} has no output {}", self.prev_tx.compute_txid(), self.prev_tx_vout).into())
+    }
+
+    fn write(&self, writer: &mut Writer) {
+        writer.u64(self.input_serial_id);
+        writer.u16_bytes(&consensus::serialize(&self.prev_tx));
+        writer.u32(self.prev_tx_vout);
+        writer.u32(self.sequence);
+        writer.u16(self.max_witness_len);
+        writer.u16_bytes(self.redeem_script.as_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> AnyaResult<Self> {
+        Ok(Self {
+            input_serial_id: reader.u64()?,
+            prev_tx: consensus::deserialize(reader.u16_bytes()?)
+                .map_err(|e| format!("Invalid funding input transaction: {}", e))?,
+            prev_tx_vout: reader.u32()?,
+            sequence: reader.u32()?,
+            max_witness_len: reader.u16()?,
+            redeem_script: reader.script()?,
+        })
+    }
+}
+
+fn write_funding_inputs(writer: &mut Writer, inputs: &[FundingInput]) {
+    writer.bigsize(inputs.len() as u64);
+    for input in inputs {
+        input.write(writer);
+    }
+}
+
+fn read_funding_inputs(reader: &mut Reader) -> AnyaResult<Vec<FundingInput>> {
+    let count = reader.count(28)?;
+    (0..count).map(|_| FundingInput::read(reader)).collect()
+}
+
+/// CET adaptor signatures, encrypted under the anticipation points of `outcomes`
+fn read_adaptor_signatures(reader: &mut Reader) -> AnyaResult<Vec<Vec<u8>>> {
+    let count = reader.count(AdaptorSignature::ECDSA_LEN)?;
+    (0..count)
+        .map(|_| Ok(reader.bytes(AdaptorSignature::ECDSA_LEN)?.to_vec()))
+        .collect()
+}
+
+/// `offer_dlc`: the offering party's proposal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfferDlc {
+    pub protocol_version: u32,
+    pub contract_flags: u8,
+    /// Genesis block hash of the chain the contract lives on
+    pub chain_hash: [u8; 32],
+    pub temporary_contract_id: [u8; 32],
+    pub contract_info: ContractInfo,
+    pub funding_pubkey: PublicKey,
+    pub payout_spk: ScriptBuf,
+    pub payout_serial_id: u64,
+    pub offer_collateral: u64,
+    pub funding_inputs: Vec<FundingInput>,
+    pub change_spk: ScriptBuf,
+    pub change_serial_id: u64,
+    pub fund_output_serial_id: u64,
+    pub fee_rate_per_vb: u64,
+    pub cet_locktime: u32,
+    pub refund_locktime: u32,
+}
+
+impl OfferDlc {
+    /// Serialize, including the message type
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.u16(OFFER_DLC_TYPE);
+        writer.u32(self.protocol_version);
+        writer.u8(self.contract_flags);
+        writer.bytes(&self.chain_hash);
+        writer.bytes(&self.temporary_contract_id);
+        self.contract_info.write(&mut writer);
+        writer.bytes(&self.funding_pubkey.serialize());
+        writer.u16_bytes(self.payout_spk.as_bytes());
+        writer.u64(self.payout_serial_id);
+        writer.u64(self.offer_collateral);
+        write_funding_inputs(&mut writer, &self.funding_inputs);
+        writer.u16_bytes(self.change_spk.as_bytes());
+        writer.u64(self.change_serial_id);
+        writer.u64(self.fund_output_serial_id);
+        writer.u64(self.fee_rate_per_vb);
+        writer.u32(self.cet_locktime);
+        writer.u32(self.refund_locktime);
+        writer.into_bytes()
+    }
+
+    /// Parse a serialized message
+    pub fn deserialize(data: &[u8]) -> AnyaResult<Self> {
+        let mut reader = Reader::new(data);
+        read_message_type(&mut reader, OFFER_DLC_TYPE)?;
+        let offer = Self {
+            protocol_version: reader.u32()?,
+            contract_flags: reader.u8()?,
+            chain_hash: reader.array()?,
+            temporary_contract_id: reader.array()?,
+            contract_info: ContractInfo::read(&mut reader)?,
+            funding_pubkey: reader.public_key()?,
+            payout_spk: reader.script()?,
+            payout_serial_id: reader.u64()?,
+            offer_collateral: reader.u64()?,
+            funding_inputs: read_funding_inputs(&mut reader)?,
+            change_spk: reader.script()?,
+            change_serial_id: reader.u64()?,
+            fund_output_serial_id: reader.u64()?,
+            fee_rate_per_vb: reader.u64()?,
+            cet_locktime: reader.u32()?,
+            refund_locktime: reader.u32()?,
+        };
+        Ok(offer)
+    }
+}
+
+/// `accept_dlc`: the accepting party's inputs and signatures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcceptDlc {
+    pub protocol_version: u32,
+    pub temporary_contract_id: [u8; 32],
+    pub accept_collateral: u64,
+    pub funding_pubkey: PublicKey,
+    pub payout_spk: ScriptBuf,
+    pub payout_serial_id: u64,
+    pub funding_inputs: Vec<FundingInput>,
+    pub change_spk: ScriptBuf,
+    pub change_serial_id: u64,
+    /// Adaptor signatures for the CETs, in outcome order
+    pub cet_adaptor_signatures: Vec<Vec<u8>>,
+    pub refund_signature: Signature,
+}
+
+impl AcceptDlc {
+    /// Serialize, including the message type
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.u16(ACCEPT_DLC_TYPE);
+        writer.u32(self.protocol_version);
+        writer.bytes(&self.temporary_contract_id);
+        writer.u64(self.accept_collateral);
+        writer.bytes(&self.funding_pubkey.serialize());
+        writer.u16_bytes(self.payout_spk.as_bytes());
+        writer.u64(self.payout_serial_id);
+        write_funding_inputs(&mut writer, &self.funding_inputs);
+        writer.u16_bytes(self.change_spk.as_bytes());
+        writer.u64(self.change_serial_id);
+        writer.bigsize(self.cet_adaptor_signatures.len() as u64);
+        for signature in &self.cet_adaptor_signatures {
+            writer.bytes(signature);
+        }
+        writer.bytes(&self.refund_signature.serialize_compact());
+        writer.u8(0); // no negotiation fields
+        writer.into_bytes()
+    }
+
+    /// Parse a serialized message
+    pub fn deserialize(data: &[u8]) -> AnyaResult<Self> {
+        let mut reader = Reader::new(data);
+        read_message_type(&mut reader, ACCEPT_DLC_TYPE)?;
+        let accept = Self {
+            protocol_version: reader.u32()?,
+            temporary_contract_id: reader.array()?,
+            accept_collateral: reader.u64()?,
+            funding_pubkey: reader.public_key()?,
+            payout_spk: reader.script()?,
+            payout_serial_id: reader.u64()?,
+            funding_inputs: read_funding_inputs(&mut reader)?,
+            change_spk: reader.script()?,
+            change_serial_id: reader.u64()?,
+            cet_adaptor_signatures: read_adaptor_signatures(&mut reader)?,
+            refund_signature: reader.signature()?,
+        };
+        if !reader.is_empty() && reader.u8()? != 0 {
+            return Err("Negotiation fields are not supported".into());
+        }
+        Ok(accept)
+    }
+}
+
+/// `sign_dlc`: the offering party's signatures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignDlc {
+    pub protocol_version: u32,
+    pub contract_id: [u8; 32],
+    /// Adaptor signatures for the CETs, in outcome order
+    pub cet_adaptor_signatures: Vec<Vec<u8>>,
+    pub refund_signature: Signature,
+    /// Witnesses for the offerer's funding inputs, in `funding_inputs` order
+    pub funding_signatures: Vec<Witness>,
+}
+
+impl SignDlc {
+    /// Serialize, including the message type
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.u16(SIGN_DLC_TYPE);
+        writer.u32(self.protocol_version);
+        writer.bytes(&self.contract_id);
+        writer.bigsize(self.cet_adaptor_signatures.len() as u64);
+        for signature in &self.cet_adaptor_signatures {
+            writer.bytes(signature);
+        }
+        writer.bytes(&self.refund_signature.serialize_compact());
+        writer.bigsize(self.funding_signatures.len() as u64);
+        for witness in &self.funding_signatures {
+            writer.u16(witness.len() as u16);
+            for element in witness.iter() {
+                writer.u16_bytes(element);
+            }
+        }
+        writer.into_bytes()
+    }
+
+    /// Parse a serialized message
+    pub fn deserialize(data: &[u8]) -> AnyaResult<Self> {
+        let mut reader = Reader::new(data);
+        read_message_type(&mut reader, SIGN_DLC_TYPE)?;
+        let protocol_version = reader.u32()?;
+        let contract_id = reader.array()?;
+        let cet_adaptor_signatures = read_adaptor_signatures(&mut reader)?;
+        let refund_signature = reader.signature()?;
+
+        let witness_count = reader.count(2)?;
+        let mut funding_signatures = Vec::with_capacity(witness_count);
+        for _ in 0..witness_count {
+            let element_count = reader.u16()?;
+            let mut witness = Witness::new();
+            for _ in 0..element_count {
+                witness.push(reader.u16_bytes()?);
+            }
+            funding_signatures.push(witness);
+        }
+
+        Ok(Self { protocol_version, contract_id, cet_adaptor_signatures, refund_signature, funding_signatures })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bigsize_roundtrip() {
+        for value in [0, 0xfc, 0xfd, 0xffff, 0x1_0000, 0xffff_ffff, 0x1_0000_0000, u64::MAX] {
+            let mut writer = Writer::new();
+            writer.bigsize(value);
+            let bytes = writer.into_bytes();
+            assert_eq!(Reader::new(&bytes).bigsize().unwrap(), value);
+        }
+
+        // 0xfc fits in one byte, so the three-byte form is not canonical
+        assert!(Reader::new(&[0xfd, 0x00, 0xfc]).bigsize().is_err());
+    }
+
+    #[test]
+    fn test_sign_dlc_roundtrip() {
+        let key = bitcoin::secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let refund_signature = secp.sign_ecdsa(&bitcoin::secp256k1::Message::from_digest([2; 32]), &key);
+        let sign = SignDlc {
+            protocol_version: PROTOCOL_VERSION,
+            contract_id: [3; 32],
+            cet_adaptor_signatures: vec![vec![4; AdaptorSignature::ECDSA_LEN]; 2],
+            refund_signature,
+            funding_signatures: vec![Witness::from_slice(&[vec![5; 71], vec![6; 33]])],
+        };
+        let bytes = sign.serialize();
+        assert_eq!(&bytes[..2], &SIGN_DLC_TYPE.to_be_bytes());
+        assert_eq!(SignDlc::deserialize(&bytes).unwrap(), sign);
+
+        assert!(SignDlc::deserialize(&bytes[..bytes.len() - 1]).is_err());
+        assert!(OfferDlc::deserialize(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_numeric_contract_info_roundtrip() {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let key = bitcoin::secp256k1::Keypair::from_seckey_slice(&secp, &[7; 32]).unwrap();
+        let nonce = bitcoin::secp256k1::PublicKey::from_secret_key(&secp, &bitcoin::secp256k1::SecretKey::from_slice(&[8; 32]).unwrap());
+        let event = OracleEvent {
+            oracle_public_key: key.x_only_public_key().0,
+            oracle_nonces: vec![nonce.x_only_public_key().0; 4],
+            event_maturity_epoch: 1_700_000_000,
+            descriptor: EventDescriptor::DigitDecomposition(DigitDecomposition::new(2, 4, "usd").unwrap()),
+            event_id: "btc-usd".to_string(),
+        };
+        let announcement = SignedOracleEvent {
+            signature: secp.sign_schnorr_no_aux_rand(&bitcoin::secp256k1::Message::from_digest([9; 32]), &key),
+            event,
+        };
+        let info = ContractInfo {
+            total_collateral: 100_000,
+            outcomes: ContractOutcomes::Numeric {
+                nb_digits: 4,
+                payout_curve: PayoutCurve::new(vec![
+                    PolynomialPiece::linear(PayoutPoint::new(0, 0), PayoutPoint::new(8, 100_000)).unwrap(),
+                    PolynomialPiece::new(vec![
+                        PayoutPoint::new(8, 100_000),
+                        PayoutPoint { event_outcome: 12, outcome_payout: 50_000, extra_precision: 3 },
+                        PayoutPoint::new(15, 0),
+                    ]).unwrap(),
+                ]).unwrap(),
+                rounding_intervals: RoundingIntervals::uniform(1_000).unwrap(),
+            },
+            oracle_announcements: vec![announcement; 3],
+            oracle_threshold: 2,
+            bounded_error: Some(BoundedError::new(3, 1).unwrap()),
+        };
+
+        let mut writer = Writer::new();
+        info.write(&mut writer);
+        let bytes = writer.into_bytes();
+        let mut reader = Reader::new(&bytes);
+        assert_eq!(ContractInfo::read(&mut reader).unwrap(), info);
+        assert!(reader.is_empty());
+        assert!(ContractInfo::read(&mut Reader::new(&bytes[..bytes.len() - 1])).is_err());
+    }
+}
@@ -12,14 +12,49 @@
 mod oracle;
 mod execution;
 mod adaptor;
+mod messages;
+mod transactions;
+mod wallet;
+mod numeric;
+mod cet;
+mod chain;
+mod storage;
+mod channel;
 
-pub use contract::{Contract, ContractDescriptor, ContractState, ContractParameters, PayoutFunction};
-pub use oracle::{Oracle, OracleInfo, OracleAnnouncement, OracleAttestation};
-pub use execution::{ExecutionManager, ExecutionStatus};
-pub use adaptor::{AdaptorSignature, AdaptorSigner};
+pub use contract::{Contract, ContractDescriptor, ContractExecutionPath, ContractState, ContractParameters, PayoutFunction};
+pub use oracle::{AttestationSource, LocalOracle, Oracle, OracleClient, OracleInfo, OracleAnnouncement, OracleAttestation};
+pub use execution::{ExecutionManager, ExecutionRecord, ExecutionStatus, MonitorHandle};
+pub use adaptor::{
+    AdaptorSignature, AdaptorSigner, AdaptorSignerType, DecryptedSignature, EcdsaAdaptorSigner, SchnorrAdaptorSigner,
+};
+pub use messages::{
+    AcceptDlc, ContractInfo, ContractOutcome, ContractOutcomes, EventDescriptor, FundingInput, OfferDlc, OracleEvent,
+    SignDlc, SignedOracleEvent,
+};
+pub use numeric::{
+    BoundedError, DigitDecomposition, PayoutCurve, PayoutPoint, PolynomialPiece, RoundingInterval, RoundingIntervals,
+};
+pub use cet::{CetOutcome, OracleOutcome};
+pub use chain::{DLCChain, EsploraChain, InMemoryChain};
+pub use channel::{
+    ChannelFunding, DLCChannels, SubChannel, SubChannelAccept, SubChannelCloseAccept, SubChannelCloseOffer,
+    SubChannelConfirm, SubChannelFinalize, SubChannelManager, SubChannelOffer, SubChannelState,
+};
+pub use storage::{DLCStore, FileDLCStore, InMemoryDLCStore};
+pub use transactions::{DlcTransactions, PartyParams};
+pub use wallet::{DLCWallet, InMemoryDLCWallet};
 
-use crate::common::error::AnyaResult;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use bitcoin::blockdata::constants::ChainHash;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::secp256k1::{All, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey};
+use bitcoin::{Network, Transaction};
+
+use crate::AnyaResult;
 use crate::bitcoin::wallet::TxOptions;
+use transactions::{TransactionTerms, P2WPKH_WITNESS_SIZE, TX_INPUT_BASE_WEIGHT};
 
 /// Main interface for DLC operations
 pub trait DLCManager {
@@ -31,9 +66,16 @@ pub trait DLCManager {
     
     /// Signs a contract and prepares it for execution
     fn sign_contract(&self, contract: &Contract) -> AnyaResult<Contract>;
+
+    /// Completes the funding transaction once both parties have signed
+    fn fund_contract(&self, contract: &Contract) -> AnyaResult<Contract>;
     
     /// Executes a contract with oracle attestation
     fn execute_contract(&self, contract: &Contract, attestation: OracleAttestation) -> AnyaResult<String>;
+
+    /// Executes a contract settled by several oracles, given at least the
+    /// threshold's worth of their attestations
+    fn execute_contract_with_attestations(&self, contract: &Contract, attestations: Vec<OracleAttestation>) -> AnyaResult<String>;
     
     /// Refunds a contract after timeout
     fn refund_contract(&self, contract: &Contract) -> AnyaResult<String>;
@@ -47,7 +89,8 @@ pub trait DLCManager {
     /// Verifies an oracle announcement
     fn verify_oracle_announcement(&self, announcement: &OracleAnnouncement) -> AnyaResult<bool>;
     
-    /// Updates the execution status of a contract
+    /// Updates the execution status of a contract from the chain, if the
+    /// manager watches one
     fn update_contract_status(&self, contract_id: &str) -> AnyaResult<ContractState>;
 }
 
@@ -56,8 +99,8 @@ pub trait DLCManager {
 
 impl DLCFactory {
     /// Creates a new DLC Manager with the specified configuration
-    pub fn new_manager(config: DLCConfig) -> Box<dyn DLCManager> {
-        Box::new(DefaultDLCManager::new(config))
+    pub fn new_manager(config: DLCConfig, wallet: Arc<dyn DLCWallet>) -> Box<dyn DLCManager> {
+        Box::new(DefaultDLCManager::new(config, wallet))
     }
 }
 
@@ -96,66 +139,1056 @@ fn default() -> Self {
     }
 }
 
+
+impl DLCConfig {
+    /// The configured network
+    pub fn network(&self) -> AnyaResult<Network> {
+        match self.network.to_lowercase().as_str() {
+            "mainnet" | "bitcoin" => Ok(Network::Bitcoin),
+            "testnet" => Ok(Network::Testnet),
+            "signet" => Ok(Network::Signet),
+            "regtest" => Ok(Network::Regtest),
+            other => Err(format!("Unknown network: {}", other).into()),
+        }
+    }
+
+    /// Whole sat/vbyte fee rate used in offers
+    fn fee_rate_per_vb(fee_rate: f64) -> u64 {
+        (fee_rate.ceil() as u64).max(1)
+    }
+}
+
 /// Default implementation of the DLC Manager
-struct DefaultDLCManager {
+///
+/// Runs the dlcspecs offer/accept/sign protocol for enumerated and numeric
+/// contracts settled by a threshold of oracles. Contracts move through
+/// Offered -> Accepted -> Signed -> Funded -> Closed or Refunded and are kept
+/// in a [`DLCStore`], keyed by the hex-encoded temporary contract ID both
+/// parties share.
+///
+/// With a chain attached the manager broadcasts the funding transaction,
+/// CETs and refunds itself, and `update_contract_status` follows the funding
+/// output to pick up funding and closes by the counterparty.
+pub struct DefaultDLCManager {
     config: DLCConfig,
+    wallet: Arc<dyn DLCWallet>,
+    store: Arc<dyn DLCStore>,
+    chain: Option<Arc<dyn DLCChain>>,
+    secp: Secp256k1<All>,
 }
 
 impl DefaultDLCManager {
-    fn new(config: DLCConfig) -> Self {
-        Self { config }
+    /// Creates a manager funding contracts from `wallet`
+    pub fn new(config: DLCConfig, wallet: Arc<dyn DLCWallet>) -> Self {
+        Self {
+            config,
+            wallet,
+            store: Arc::new(InMemoryDLCStore::new()),
+            chain: None,
+            secp: Secp256k1::new(),
+        }
     }
-    
+
+    /// The manager keeping its contracts in `store`
+    pub fn with_store(mut self, store: Arc<dyn DLCStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// The manager broadcasting through and watching `chain`
+    pub fn with_chain(mut self, chain: Arc<dyn DLCChain>) -> Self {
+        self.chain = Some(chain);
+        self
+    }
+
     // Helper function to validate a contract
     fn validate_contract(&self, contract: &Contract) -> AnyaResult<()> {
-        // Implement validation logic here
+        if contract.oracle_announcements.is_empty() {
+            return Err("Contract has no oracle announcement".into());
+        }
+        for announcement in &contract.oracle_announcements {
+            if !self.verify_oracle_announcement(announcement)? {
+                return Err(format!("Invalid oracle announcement for event {}", announcement.event_id).into());
+            }
+        }
+        contract.validate()
+    }
+
+    fn store(&self, contract: &Contract) -> AnyaResult<()> {
+        self.store.save_contract(contract)
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> AnyaResult<()> {
+        if let Some(chain) = &self.chain {
+            chain.broadcast(transaction)?;
+        }
+        Ok(())
+    }
+
+    /// Record how the funding output was spent, or make sure our own
+    /// settlement reaches the chain
+    fn follow_funding_output(&self, chain: &dyn DLCChain, mut contract: Contract) -> AnyaResult<Contract> {
+        let txs = contract.transactions.clone().ok_or("Contract has no transactions")?;
+        let spend = match chain.spending_transaction(&txs.funding_outpoint())? {
+            Some(spend) => spend,
+            None => {
+                match contract.state {
+                    ContractState::Funded if chain.get_transaction(&txs.fund.compute_txid())?.is_none() => {
+                        chain.broadcast(&txs.fund)?;
+                    }
+                    ContractState::Closed => {
+                        let txid = contract.execution_txid.ok_or("Closed contract has no execution transaction")?;
+                        let cet = txs.cets.iter().find(|cet| cet.compute_txid() == txid)
+                            .ok_or("Execution transaction is not a CET of the contract")?;
+                        chain.broadcast(cet)?;
+                    }
+                    ContractState::Refunded => {
+                        chain.broadcast(&txs.refund)?;
+                    }
+                    _ => {}
+                }
+                return Ok(contract);
+            }
+        };
+
+        let txid = spend.compute_txid();
+        if txid == txs.refund.compute_txid() {
+            if contract.state != ContractState::Refunded || contract.refund_txid != Some(txid) {
+                contract.refund_txid = Some(txid);
+                contract.update_state(ContractState::Refunded);
+                self.store(&contract)?;
+            }
+        } else if let Some(index) = txs.cets.iter().position(|cet| cet.compute_txid() == txid) {
+            if contract.state != ContractState::Closed || contract.execution_txid != Some(txid) {
+                // Closed by the counterparty, or by us on another CET
+                if let Some(txs) = contract.transactions.as_mut() {
+                    txs.cets[index] = spend;
+                }
+                contract.execution_txid = Some(txid);
+                contract.update_state(ContractState::Closed);
+                self.store(&contract)?;
+            }
+        } else {
+            contract.update_state(ContractState::Error(format!("Funding output spent by unknown transaction {}", txid)));
+            self.store(&contract)?;
+        }
+        Ok(contract)
+    }
+
+    /// The stored contract, which must be in `state`
+    fn stored(&self, contract_id: &str, state: ContractState) -> AnyaResult<Contract> {
+        let contract = self.get_contract(contract_id)?
+            .ok_or_else(|| format!("Unknown contract {}", contract_id))?;
+        if contract.state != state {
+            return Err(format!("Contract {} is {:?}, expected {:?}", contract_id, contract.state, state).into());
+        }
+        Ok(contract)
+    }
+
+    fn funding_key(&self, offer: &OfferDlc) -> AnyaResult<(SecretKey, PublicKey)> {
+        let secret_key = self.wallet.funding_key(&offer.temporary_contract_id)?;
+        Ok((secret_key, PublicKey::from_secret_key(&self.secp, &secret_key)))
+    }
+
+    /// Select wallet inputs covering `collateral` and this party's fees
+    fn fund_party(&self, funding_pubkey: PublicKey, collateral: u64, fee_rate_per_vb: u64) -> AnyaResult<PartyParams> {
+        let mut party = PartyParams {
+            funding_pubkey,
+            payout_script: self.wallet.payout_script()?,
+            payout_serial_id: rand::random(),
+            change_script: self.wallet.change_script()?,
+            change_serial_id: rand::random(),
+            inputs: Vec::new(),
+            collateral,
+        };
+        let fee_per_input = transactions::weight_to_fee(TX_INPUT_BASE_WEIGHT + P2WPKH_WITNESS_SIZE as u64, fee_rate_per_vb);
+        party.inputs = self.wallet.select_inputs(party.required_amount(fee_rate_per_vb), fee_per_input)?;
+        for input in &mut party.inputs {
+            input.input_serial_id = rand::random();
+        }
+        if party.input_amount()? < party.required_amount(fee_rate_per_vb) {
+            self.wallet.release_inputs(&party.inputs)?;
+            return Err("Selected inputs do not cover collateral and fees".into());
+        }
+        Ok(party)
+    }
+
+    /// Party paying its collateral and fees out of a channel balance
+    fn channel_party(&self, funding_pubkey: PublicKey, collateral: u64) -> AnyaResult<PartyParams> {
+        Ok(PartyParams {
+            funding_pubkey,
+            payout_script: self.wallet.payout_script()?,
+            payout_serial_id: rand::random(),
+            change_script: self.wallet.change_script()?,
+            change_serial_id: rand::random(),
+            inputs: Vec::new(),
+            collateral,
+        })
+    }
+
+    fn offer_party(offer: &OfferDlc) -> PartyParams {
+        PartyParams {
+            funding_pubkey: offer.funding_pubkey,
+            payout_script: offer.payout_spk.clone(),
+            payout_serial_id: offer.payout_serial_id,
+            change_script: offer.change_spk.clone(),
+            change_serial_id: offer.change_serial_id,
+            inputs: offer.funding_inputs.clone(),
+            collateral: offer.offer_collateral,
+        }
+    }
+
+    fn accept_party(accept: &AcceptDlc) -> PartyParams {
+        PartyParams {
+            funding_pubkey: accept.funding_pubkey,
+            payout_script: accept.payout_spk.clone(),
+            payout_serial_id: accept.payout_serial_id,
+            change_script: accept.change_spk.clone(),
+            change_serial_id: accept.change_serial_id,
+            inputs: accept.funding_inputs.clone(),
+            collateral: accept.accept_collateral,
+        }
+    }
+
+    /// Funding (or split), CET and refund transactions of a contract
+    fn build_transactions(
+        offer: &OfferDlc,
+        cets: &[CetOutcome],
+        offer_party: &PartyParams,
+        accept_party: &PartyParams,
+        sub_channel: Option<&SubChannel>,
+    ) -> AnyaResult<DlcTransactions> {
+        let total = offer.contract_info.total_collateral;
+        let payouts: Vec<(u64, u64)> = cets.iter()
+            .map(|cet| (cet.offer_payout, total.saturating_sub(cet.offer_payout)))
+            .collect();
+        let terms = TransactionTerms {
+            fund_output_serial_id: offer.fund_output_serial_id,
+            fee_rate_per_vb: offer.fee_rate_per_vb,
+            cet_locktime: offer.cet_locktime,
+            refund_locktime: offer.refund_locktime,
+        };
+        match sub_channel {
+            Some(sub_channel) => transactions::build_split_transactions(
+                &sub_channel.split_input()?, offer_party, accept_party, &payouts, &terms,
+            ),
+            None => transactions::build_dlc_transactions(offer_party, accept_party, &payouts, &terms),
+        }
+    }
+
+    /// The CET matching `attestations` and the attestations keyed by oracle index
+    fn attested_outcome(
+        &self,
+        contract: &Contract,
+        attestations: Vec<OracleAttestation>,
+    ) -> AnyaResult<(usize, CetOutcome, HashMap<usize, OracleAttestation>)> {
+        let offer = contract.offer.as_ref().ok_or("Contract has no offer")?;
+
+        // Attestations carry no oracle index, so match each to the first
+        // announcement it verifies against
+        let mut attested = HashMap::new();
+        for attestation in attestations {
+            let mut oracle = None;
+            for (index, announcement) in contract.oracle_announcements.iter().enumerate() {
+                if !attested.contains_key(&index) && attestation.verify(announcement)? {
+                    oracle = Some(index);
+                    break;
+                }
+            }
+            let oracle = oracle.ok_or_else(|| format!("Invalid attestation for event {}", attestation.event_id))?;
+            attested.insert(oracle, attestation);
+        }
+
+        let cets = cet::cet_outcomes(&offer.contract_info)?;
+        let index = cets.iter().position(|cet| cet.matches(&attested))
+            .ok_or("The attested outcomes are not part of the contract")?;
+        Ok((index, cets[index].clone(), attested))
+    }
+
+    /// Execution paths keyed by CET label
+    fn execution_paths(offer: &OfferDlc, cets: &[CetOutcome], txs: &DlcTransactions) -> HashMap<String, ContractExecutionPath> {
+        let total = offer.contract_info.total_collateral;
+        cets.iter().zip(&txs.cets)
+            .map(|(outcome, cet)| (outcome.label(), ContractExecutionPath {
+                outcome: outcome.label(),
+                cet_hex: serialize_hex(cet),
+                offer_payout: outcome.offer_payout,
+                accept_payout: total.saturating_sub(outcome.offer_payout),
+            }))
+            .collect()
+    }
+
+    /// Adaptor signatures for every CET, encrypted under the CETs' adaptor points
+    fn sign_cets(
+        &self,
+        contract: &Contract,
+        cets: &[CetOutcome],
+        txs: &DlcTransactions,
+        funding_key: &SecretKey,
+    ) -> AnyaResult<Vec<Vec<u8>>> {
+        cet::adaptor_points(cets, &contract.oracle_announcements)?.iter().enumerate()
+            .map(|(index, point)| {
+                let signature = EcdsaAdaptorSigner.create_adaptor_signature(&txs.cet_sighash(index)?, funding_key, point)?;
+                Ok(signature.encrypted_data)
+            })
+            .collect()
+    }
+
+    /// Check the counterparty's CET adaptor signatures and refund signature
+    fn verify_counterparty_signatures(
+        &self,
+        contract: &Contract,
+        cets: &[CetOutcome],
+        txs: &DlcTransactions,
+        adaptor_signatures: &[Vec<u8>],
+        refund_signature: &bitcoin::secp256k1::ecdsa::Signature,
+        public_key: &PublicKey,
+    ) -> AnyaResult<()> {
+        if adaptor_signatures.len() != txs.cets.len() {
+            return Err(format!("Expected {} CET signatures, got {}", txs.cets.len(), adaptor_signatures.len()).into());
+        }
+        let points = cet::adaptor_points(cets, &contract.oracle_announcements)?;
+        for (index, ((outcome, point), data)) in cets.iter().zip(points).zip(adaptor_signatures).enumerate() {
+            let signature = AdaptorSignature::from_slice(data, point)?;
+            if !EcdsaAdaptorSigner.verify_adaptor_signature(&txs.cet_sighash(index)?, &signature, public_key)? {
+                return Err(format!("Invalid CET adaptor signature for outcome '{}'", outcome.label()).into());
+            }
+        }
+        self.secp.verify_ecdsa(&txs.refund_sighash()?, refund_signature, public_key)
+            .map_err(|_| "Invalid refund signature")?;
+        Ok(())
+    }
+
+    /// Witnesses for this party's inputs of the funding transaction, in `inputs` order
+    fn sign_funding_inputs(&self, fund: &Transaction, inputs: &[FundingInput]) -> AnyaResult<Vec<bitcoin::Witness>> {
+        inputs.iter()
+            .map(|input| {
+                let outpoint = bitcoin::OutPoint::new(input.prev_tx.compute_txid(), input.prev_tx_vout);
+                let index = fund.input.iter().position(|txin| txin.previous_output == outpoint)
+                    .ok_or("Funding input missing from the funding transaction")?;
+                self.wallet.sign_funding_input(fund, index, &input.prev_tx.output[input.prev_tx_vout as usize])
+            })
+            .collect()
+    }
+
+    fn apply_funding_witnesses(fund: &mut Transaction, inputs: &[FundingInput], witnesses: &[bitcoin::Witness]) -> AnyaResult<()> {
+        if inputs.len() != witnesses.len() {
+            return Err(format!("Expected {} funding signatures, got {}", inputs.len(), witnesses.len()).into());
+        }
+        for (input, witness) in inputs.iter().zip(witnesses) {
+            let outpoint = bitcoin::OutPoint::new(input.prev_tx.compute_txid(), input.prev_tx_vout);
+            let txin = fund.input.iter_mut().find(|txin| txin.previous_output == outpoint)
+                .ok_or("Funding input missing from the funding transaction")?;
+            txin.witness = witness.clone();
+        }
         Ok(())
     }
+
+    /// Creates an offer, funded from the wallet or, for a contract inside a
+    /// channel, from the channel balance
+    fn create_offer(&self, params: ContractParameters, sub_channel: Option<SubChannel>) -> AnyaResult<Contract> {
+        let maturity = params.oracle_announcements.iter()
+            .map(|announcement| announcement.maturity_time)
+            .max()
+            .ok_or("Contract parameters must include the oracle announcement")?;
+        let fee_rate = params.fee_rate.unwrap_or(self.config.fee_rate);
+        let fee_rate_per_vb = DLCConfig::fee_rate_per_vb(fee_rate);
+        let maturity = u32::try_from(maturity.timestamp())
+            .map_err(|_| "Event maturity out of range")?;
+        let oracle_threshold = match params.oracle_threshold {
+            Some(threshold) => threshold,
+            None => u16::try_from(params.oracle_announcements.len()).map_err(|_| "Too many oracles")?,
+        };
+        let refund_locktime = params.refund_locktime
+            .unwrap_or(maturity.saturating_add(self.config.locktime_period.saturating_mul(600)));
+        let temporary_contract_id: [u8; 32] = rand::random();
+        let funding_key = self.wallet.funding_key(&temporary_contract_id)?;
+        let funding_pubkey = PublicKey::from_secret_key(&self.secp, &funding_key);
+
+        let descriptor = ContractDescriptor {
+            title: params.title,
+            description: params.description,
+            offer_public_key: funding_pubkey,
+            offer_collateral: params.offer_collateral,
+            accept_public_key: None,
+            accept_collateral: params.accept_collateral,
+            fee_rate,
+            refund_locktime,
+            payout_function: params.payout_function,
+            oracle_info: Vec::new(),
+            oracle_threshold,
+            bounded_error: params.bounded_error,
+        };
+        let mut contract = Contract::new(descriptor);
+        contract.id = hex::encode(temporary_contract_id);
+        contract.is_offer_party = true;
+        contract.metadata = params.metadata;
+        contract.sub_channel = sub_channel;
+        for announcement in params.oracle_announcements {
+            contract.add_oracle_announcement(announcement);
+        }
+        self.validate_contract(&contract)?;
+
+        let contract_info = contract.contract_info()?;
+        let party = match contract.sub_channel {
+            Some(_) => self.channel_party(funding_pubkey, params.offer_collateral)?,
+            None => self.fund_party(funding_pubkey, params.offer_collateral, fee_rate_per_vb)?,
+        };
+
+        contract.offer = Some(OfferDlc {
+            protocol_version: messages::PROTOCOL_VERSION,
+            contract_flags: 0,
+            chain_hash: ChainHash::using_genesis_block(self.config.network()?).to_bytes(),
+            temporary_contract_id,
+            contract_info,
+            funding_pubkey,
+            payout_spk: party.payout_script,
+            payout_serial_id: party.payout_serial_id,
+            offer_collateral: party.collateral,
+            funding_inputs: party.inputs,
+            change_spk: party.change_script,
+            change_serial_id: party.change_serial_id,
+            fund_output_serial_id: rand::random(),
+            fee_rate_per_vb,
+            cet_locktime: maturity,
+            refund_locktime,
+        });
+        contract.update_state(ContractState::Offered);
+        self.store(&contract)?;
+        Ok(contract)
+    }
+
+    /// Our funding key and the counterparty's funding public key
+    fn contract_keys(&self, contract: &Contract) -> AnyaResult<(SecretKey, PublicKey)> {
+        let offer = contract.offer.as_ref().ok_or("Contract has no offer")?;
+        let accept = contract.accept.as_ref().ok_or("Contract has no accept message")?;
+        let (funding_key, _) = self.funding_key(offer)?;
+        let counterparty = if contract.is_offer_party { accept.funding_pubkey } else { offer.funding_pubkey };
+        Ok((funding_key, counterparty))
+    }
 }
 
 impl DLCManager for DefaultDLCManager {
     fn create_contract(&self, params: ContractParameters) -> AnyaResult<Contract> {
-        // Implementation goes here
-        unimplemented!("Contract creation not yet implemented")
+        self.create_offer(params, None)
     }
-    
+
     fn accept_contract(&self, contract: &Contract) -> AnyaResult<Contract> {
-        // Implementation goes here
-        unimplemented!("Contract acceptance not yet implemented")
+        let offer = contract.offer.as_ref().ok_or("Contract has no offer")?;
+        if contract.is_offer_party || contract.state != ContractState::Offered {
+            return Err("Only offers received from a counterparty can be accepted".into());
+        }
+        if offer.chain_hash != ChainHash::using_genesis_block(self.config.network()?).to_bytes() {
+            return Err("Offer is for a different chain".into());
+        }
+        if offer.protocol_version != messages::PROTOCOL_VERSION {
+            return Err(format!("Unsupported protocol version {}", offer.protocol_version).into());
+        }
+        if let ContractOutcomes::Enumerated(outcomes) = &offer.contract_info.outcomes {
+            let mut unique = HashSet::new();
+            if let Some(outcome) = outcomes.iter().find(|outcome| !unique.insert(&outcome.outcome)) {
+                return Err(format!("Invalid payout for outcome '{}'", outcome.outcome).into());
+            }
+        }
+        self.validate_contract(contract)?;
+        let cets = cet::cet_outcomes(&offer.contract_info)?;
+
+        let (funding_key, funding_pubkey) = self.funding_key(offer)?;
+        let accept_party = match contract.sub_channel {
+            Some(_) => self.channel_party(funding_pubkey, contract.descriptor.accept_collateral)?,
+            None => self.fund_party(funding_pubkey, contract.descriptor.accept_collateral, offer.fee_rate_per_vb)?,
+        };
+        let sub_channel = contract.sub_channel.as_ref();
+        let txs = match Self::build_transactions(offer, &cets, &Self::offer_party(offer), &accept_party, sub_channel) {
+            Ok(txs) => txs,
+            Err(e) => {
+                self.wallet.release_inputs(&accept_party.inputs)?;
+                return Err(e);
+            }
+        };
+
+        let mut accepted = contract.clone();
+        let cet_adaptor_signatures = self.sign_cets(&accepted, &cets, &txs, &funding_key)?;
+        let refund_signature = self.secp.sign_ecdsa(&txs.refund_sighash()?, &funding_key);
+        accepted.accept = Some(AcceptDlc {
+            protocol_version: messages::PROTOCOL_VERSION,
+            temporary_contract_id: offer.temporary_contract_id,
+            accept_collateral: accept_party.collateral,
+            funding_pubkey,
+            payout_spk: accept_party.payout_script,
+            payout_serial_id: accept_party.payout_serial_id,
+            funding_inputs: accept_party.inputs,
+            change_spk: accept_party.change_script,
+            change_serial_id: accept_party.change_serial_id,
+            cet_adaptor_signatures,
+            refund_signature,
+        });
+        accepted.descriptor.accept_public_key = Some(funding_pubkey);
+        accepted.execution_paths = Self::execution_paths(offer, &cets, &txs);
+        accepted.transactions = Some(txs);
+        accepted.update_state(ContractState::Accepted);
+        self.store(&accepted)?;
+        Ok(accepted)
     }
-    
+
     fn sign_contract(&self, contract: &Contract) -> AnyaResult<Contract> {
-        // Implementation goes here
-        unimplemented!("Contract signing not yet implemented")
+        let mut signed = self.stored(&contract.id, ContractState::Offered)?;
+        if !signed.is_offer_party {
+            return Err("Only the offering party signs a contract".into());
+        }
+        let accept = contract.accept.clone().ok_or("Contract has no accept message")?;
+        let offer = signed.offer.clone().ok_or("Contract has no offer")?;
+        if accept.temporary_contract_id != offer.temporary_contract_id {
+            return Err("Accept message is for a different contract".into());
+        }
+        if accept.accept_collateral != signed.descriptor.accept_collateral {
+            return Err("Accept collateral does not match the offer".into());
+        }
+
+        let cets = cet::cet_outcomes(&offer.contract_info)?;
+        let txs = Self::build_transactions(
+            &offer, &cets, &Self::offer_party(&offer), &Self::accept_party(&accept), signed.sub_channel.as_ref(),
+        )?;
+        self.verify_counterparty_signatures(
+            &signed, &cets, &txs, &accept.cet_adaptor_signatures, &accept.refund_signature, &accept.funding_pubkey,
+        )?;
+
+        let (funding_key, _) = self.funding_key(&offer)?;
+        let cet_adaptor_signatures = self.sign_cets(&signed, &cets, &txs, &funding_key)?;
+        let refund_signature = self.secp.sign_ecdsa(&txs.refund_sighash()?, &funding_key);
+        let funding_signatures = self.sign_funding_inputs(&txs.fund, &offer.funding_inputs)?;
+        let contract_id = transactions::contract_id(&txs.fund, txs.fund_output_index as u16, &offer.temporary_contract_id);
+
+        signed.sign = Some(SignDlc {
+            protocol_version: messages::PROTOCOL_VERSION,
+            contract_id,
+            cet_adaptor_signatures,
+            refund_signature,
+            funding_signatures,
+        });
+        signed.descriptor.accept_public_key = Some(accept.funding_pubkey);
+        signed.accept = Some(accept);
+        signed.contract_id = Some(contract_id);
+        signed.execution_paths = Self::execution_paths(&offer, &cets, &txs);
+        signed.transactions = Some(txs);
+        signed.update_state(ContractState::Signed);
+        self.store(&signed)?;
+        Ok(signed)
     }
-    
+
+    fn fund_contract(&self, contract: &Contract) -> AnyaResult<Contract> {
+        if let Some(stored) = self.get_contract(&contract.id)?.filter(|stored| stored.is_offer_party) {
+            // The offering party learns of the fully signed funding transaction
+            // from its counterparty or the chain
+            let mut funded = self.stored(&stored.id, ContractState::Signed)?;
+            let fund = contract.transactions.as_ref().map(|txs| txs.fund.clone())
+                .ok_or("Contract has no funding transaction")?;
+            let txs = funded.transactions.as_mut().ok_or("Contract has no transactions")?;
+            if fund.compute_txid() != txs.fund.compute_txid() || fund.input.iter().any(|txin| txin.witness.is_empty()) {
+                return Err("Funding transaction does not match the signed contract".into());
+            }
+            txs.fund = fund;
+            funded.funding_txid = Some(txs.fund.compute_txid());
+            funded.update_state(ContractState::Funded);
+            self.store(&funded)?;
+            return Ok(funded);
+        }
+
+        let mut funded = self.stored(&contract.id, ContractState::Accepted)?;
+        let sign = contract.sign.clone().ok_or("Contract has no sign message")?;
+        let offer = funded.offer.clone().ok_or("Contract has no offer")?;
+        let accept = funded.accept.clone().ok_or("Contract has no accept message")?;
+        let mut txs = funded.transactions.clone().ok_or("Contract has no transactions")?;
+
+        let contract_id = transactions::contract_id(&txs.fund, txs.fund_output_index as u16, &offer.temporary_contract_id);
+        if sign.contract_id != contract_id {
+            return Err("Sign message is for a different contract".into());
+        }
+        let cets = cet::cet_outcomes(&offer.contract_info)?;
+        self.verify_counterparty_signatures(
+            &funded, &cets, &txs, &sign.cet_adaptor_signatures, &sign.refund_signature, &offer.funding_pubkey,
+        )?;
+
+        let witnesses = self.sign_funding_inputs(&txs.fund, &accept.funding_inputs)?;
+        let unsigned = txs.fund.clone();
+        Self::apply_funding_witnesses(&mut txs.fund, &offer.funding_inputs, &sign.funding_signatures)?;
+        Self::apply_funding_witnesses(&mut txs.fund, &accept.funding_inputs, &witnesses)?;
+        debug_assert_eq!(unsigned.compute_txid(), txs.fund.compute_txid());
+        // A split transaction stays off-chain until the channel is force closed
+        if !funded.is_in_channel() {
+            self.broadcast(&txs.fund)?;
+        }
+
+        funded.funding_txid = Some(txs.fund.compute_txid());
+        funded.transactions = Some(txs);
+        funded.sign = Some(sign);
+        funded.contract_id = Some(contract_id);
+        funded.update_state(ContractState::Funded);
+        self.store(&funded)?;
+        Ok(funded)
+    }
+
     fn execute_contract(&self, contract: &Contract, attestation: OracleAttestation) -> AnyaResult<String> {
-        // Implementation goes here
-        unimplemented!("Contract execution not yet implemented")
+        self.execute_contract_with_attestations(contract, vec![attestation])
     }
-    
+
+    fn execute_contract_with_attestations(&self, contract: &Contract, attestations: Vec<OracleAttestation>) -> AnyaResult<String> {
+        let mut executed = self.stored(&contract.id, ContractState::Funded)?;
+        if executed.is_in_channel() {
+            return Err(format!(
+                "Contract {} is inside a channel; settle it off-chain or force close the channel", executed.id
+            ).into());
+        }
+        let (index, outcome, attested) = self.attested_outcome(&executed, attestations)?;
+
+        let counterparty_signatures = if executed.is_offer_party {
+            &executed.accept.as_ref().ok_or("Contract has no accept message")?.cet_adaptor_signatures
+        } else {
+            &executed.sign.as_ref().ok_or("Contract has no sign message")?.cet_adaptor_signatures
+        };
+        let point = cet::adaptor_points(std::slice::from_ref(&outcome), &executed.oracle_announcements)?[0];
+        let adaptor = AdaptorSignature::from_slice(&counterparty_signatures[index], point)?;
+        let counterparty_signature = adaptor.decrypt(&outcome.decryption_key(&attested)?)?.into_ecdsa()?;
+
+        let (funding_key, counterparty_key) = self.contract_keys(&executed)?;
+        let txs = executed.transactions.as_ref().ok_or("Contract has no transactions")?;
+        let signature = self.secp.sign_ecdsa(&txs.cet_sighash(index)?, &funding_key);
+        let funding_pubkey = PublicKey::from_secret_key(&self.secp, &funding_key);
+
+        let mut cet = txs.cets[index].clone();
+        cet.input[0].witness = transactions::funding_witness(
+            &txs.funding_script,
+            (&funding_pubkey, &signature),
+            (&counterparty_key, &counterparty_signature),
+        );
+        let txid = cet.compute_txid();
+        self.broadcast(&cet)?;
+
+        if let Some(path) = executed.execution_paths.get_mut(&outcome.label()) {
+            path.cet_hex = serialize_hex(&cet);
+        }
+        if let Some(txs) = executed.transactions.as_mut() {
+            txs.cets[index] = cet;
+        }
+        executed.execution_txid = Some(txid);
+        executed.update_state(ContractState::Closed);
+        self.store(&executed)?;
+        Ok(txid.to_string())
+    }
+
     fn refund_contract(&self, contract: &Contract) -> AnyaResult<String> {
-        // Implementation goes here
-        unimplemented!("Contract refund not yet implemented")
+        let mut refunded = self.stored(&contract.id, ContractState::Funded)?;
+        if refunded.is_in_channel() {
+            return Err(format!(
+                "Contract {} is inside a channel; settle it off-chain or force close the channel", refunded.id
+            ).into());
+        }
+        let counterparty_signature = if refunded.is_offer_party {
+            refunded.accept.as_ref().ok_or("Contract has no accept message")?.refund_signature
+        } else {
+            refunded.sign.as_ref().ok_or("Contract has no sign message")?.refund_signature
+        };
+        let (funding_key, counterparty_key) = self.contract_keys(&refunded)?;
+        let funding_pubkey = PublicKey::from_secret_key(&self.secp, &funding_key);
+
+        let txs = refunded.transactions.as_mut().ok_or("Contract has no transactions")?;
+        let signature = self.secp.sign_ecdsa(&txs.refund_sighash()?, &funding_key);
+        txs.refund.input[0].witness = transactions::funding_witness(
+            &txs.funding_script,
+            (&funding_pubkey, &signature),
+            (&counterparty_key, &counterparty_signature),
+        );
+        let txid = txs.refund.compute_txid();
+        self.broadcast(&txs.refund)?;
+
+        refunded.refund_txid = Some(txid);
+        refunded.update_state(ContractState::Refunded);
+        self.store(&refunded)?;
+        Ok(txid.to_string())
     }
-    
+
     fn list_contracts(&self) -> AnyaResult<Vec<Contract>> {
-        // Implementation goes here
-        unimplemented!("Contract listing not yet implemented")
+        self.store.list_contracts()
     }
-    
+
     fn get_contract(&self, contract_id: &str) -> AnyaResult<Option<Contract>> {
-        // Implementation goes here
-        unimplemented!("Contract retrieval not yet implemented")
+        self.store.get_contract(contract_id)
     }
-    
+
     fn verify_oracle_announcement(&self, announcement: &OracleAnnouncement) -> AnyaResult<bool> {
-        // Implementation goes here
-        unimplemented!("Oracle announcement verification not yet implemented")
+        match &announcement.digit_decomposition {
+            Some(decomposition) => {
+                if decomposition.validate().is_err() || announcement.nonces.len() != decomposition.nb_digits as usize {
+                    return Ok(false);
+                }
+            }
+            None => {
+                let unique: HashSet<&String> = announcement.outcomes.iter().collect();
+                if announcement.outcomes.is_empty() || unique.len() != announcement.outcomes.len() {
+                    return Ok(false);
+                }
+            }
+        }
+        // Reusing a nonce, or the oracle key as one, leaks the oracle's key
+        let nonces: HashSet<XOnlyPublicKey> = announcement.nonces.iter().map(|nonce| nonce.x_only_public_key().0).collect();
+        if nonces.len() != announcement.nonces.len() || nonces.contains(&announcement.public_key.x_only_public_key().0) {
+            return Ok(false);
+        }
+        announcement.verify_signature()
     }
-    
+
     fn update_contract_status(&self, contract_id: &str) -> AnyaResult<ContractState> {
-        // Implementation goes here
-        unimplemented!("Contract status update not yet implemented")
+        let mut contract = self.get_contract(contract_id)?
+            .ok_or_else(|| format!("Unknown contract {}", contract_id))?;
+        let Some(chain) = self.chain.clone() else {
+            return Ok(contract.state);
+        };
+
+        // A contract inside a channel reaches the chain only through its
+        // split transaction, when either party force closes the channel
+        if let Some(sub_channel) = contract.sub_channel.as_ref() {
+            match sub_channel.state {
+                SubChannelState::OnChain => {}
+                SubChannelState::Offered | SubChannelState::OffChainClosed => return Ok(contract.state),
+                _ => {
+                    let split_txid = contract.transactions.as_ref().ok_or("Contract has no transactions")?.fund.compute_txid();
+                    if chain.get_transaction(&split_txid)?.is_none() {
+                        return Ok(contract.state);
+                    }
+                    if let Some(sub_channel) = contract.sub_channel.as_mut() {
+                        sub_channel.state = SubChannelState::OnChain;
+                    }
+                    self.store(&contract)?;
+                }
+            }
+        }
+
+        // The offering party learns of the funding transaction from the chain
+        if contract.state == ContractState::Signed {
+            let fund_txid = contract.transactions.as_ref().ok_or("Contract has no transactions")?.fund.compute_txid();
+            if let Some(fund) = chain.get_transaction(&fund_txid)? {
+                if let Some(txs) = contract.transactions.as_mut() {
+                    txs.fund = fund;
+                }
+                contract = self.fund_contract(&contract)?;
+            }
+        }
+        if matches!(contract.state, ContractState::Funded | ContractState::Closed | ContractState::Refunded) {
+            contract = self.follow_funding_output(chain.as_ref(), contract)?;
+        }
+        Ok(contract.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, TxOut};
+    use chrono::{Duration, Utc};
+
+    struct TestOracle {
+        oracle: Arc<LocalOracle>,
+        announcement: OracleAnnouncement,
+    }
+
+    impl TestOracle {
+        fn new(outcomes: &[&str]) -> Self {
+            let oracle = Arc::new(LocalOracle::new("test", SecretKey::from_slice(&[0x51; 32]).unwrap()));
+            let announcement = oracle.announce(
+                "btc-above-100k",
+                "BTC/USD above 100k",
+                Utc::now() + Duration::days(1),
+                outcomes.iter().map(|o| o.to_string()).collect(),
+            ).unwrap();
+            Self { oracle, announcement }
+        }
+
+        fn attest(&self, outcome: &str) -> OracleAttestation {
+            self.oracle.attest(&self.announcement.event_id, outcome).unwrap()
+        }
+    }
+
+    fn funded_manager(seed: u8, amounts: &[u64]) -> (DefaultDLCManager, Arc<InMemoryDLCWallet>) {
+        let wallet = Arc::new(InMemoryDLCWallet::new(SecretKey::from_slice(&[seed; 32]).unwrap()));
+        for (i, amount) in amounts.iter().enumerate() {
+            let prev_tx = Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::from_consensus(i as u32 + seed as u32),
+                input: vec![],
+                output: vec![TxOut { value: Amount::from_sat(*amount), script_pubkey: wallet.script_pubkey() }],
+            };
+            wallet.add_utxo(prev_tx, 0).unwrap();
+        }
+        let config = DLCConfig { network: "regtest".to_string(), fee_rate: 2.0, ..DLCConfig::default() };
+        (DefaultDLCManager::new(config, wallet.clone()), wallet)
+    }
+
+    fn parameters(oracle: &TestOracle) -> ContractParameters {
+        ContractParameters {
+            title: "BTC above 100k".to_string(),
+            description: String::new(),
+            offer_collateral: 60_000,
+            accept_collateral: 40_000,
+            payout_function: PayoutFunction::Binary {
+                win_condition: "yes".to_string(),
+                offer_win_amount: 100_000,
+                accept_win_amount: 100_000,
+            },
+            oracle_urls: vec![],
+            oracle_announcements: vec![oracle.announcement.clone()],
+            oracle_threshold: None,
+            bounded_error: None,
+            refund_locktime: None,
+            fee_rate: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Run offer, accept, sign and fund between two managers, exchanging
+    /// serialized messages
+    fn negotiate(parameters: ContractParameters) -> (DefaultDLCManager, DefaultDLCManager, Contract, Contract) {
+        let (alice, _) = funded_manager(1, &[30_000, 50_000, 20_000]);
+        let (bob, _) = funded_manager(2, &[45_000]);
+        let (_, bob_funded) = negotiate_between(&alice, &bob, parameters);
+        let alice_funded = alice.fund_contract(&bob_funded).unwrap();
+        (alice, bob, alice_funded, bob_funded)
+    }
+
+    /// Run offer, accept, sign and fund, returning the offering party's
+    /// signed contract and the accepting party's funded one
+    fn negotiate_between(alice: &DefaultDLCManager, bob: &DefaultDLCManager, parameters: ContractParameters) -> (Contract, Contract) {
+        let offered = alice.create_contract(parameters).unwrap();
+        assert_eq!(offered.state, ContractState::Offered);
+        let offer_bytes = offered.offer.as_ref().unwrap().serialize();
+        let offer = OfferDlc::deserialize(&offer_bytes).unwrap();
+        assert_eq!(&offer, offered.offer.as_ref().unwrap());
+
+        let accepted = bob.accept_contract(&Contract::from_offer(offer).unwrap()).unwrap();
+        assert_eq!(accepted.id, offered.id);
+        assert_eq!(accepted.state, ContractState::Accepted);
+        let accept = AcceptDlc::deserialize(&accepted.accept.as_ref().unwrap().serialize()).unwrap();
+
+        let signed = alice.sign_contract(&offered.with_accept(accept)).unwrap();
+        assert_eq!(signed.state, ContractState::Signed);
+        let sign = SignDlc::deserialize(&signed.sign.as_ref().unwrap().serialize()).unwrap();
+
+        let bob_funded = bob.fund_contract(&accepted.with_sign(sign)).unwrap();
+        (signed, bob_funded)
+    }
+
+    #[test]
+    fn test_contract_lifecycle() {
+        let oracle = TestOracle::new(&["yes", "no"]);
+        let (alice, bob, alice_contract, bob_contract) = negotiate(parameters(&oracle));
+
+        assert_eq!(alice_contract.state, ContractState::Funded);
+        assert_eq!(bob_contract.state, ContractState::Funded);
+        assert_eq!(alice_contract.contract_id, bob_contract.contract_id);
+        assert_eq!(alice_contract.funding_txid, bob_contract.funding_txid);
+        let fund = &bob_contract.transactions.as_ref().unwrap().fund;
+        assert!(fund.input.iter().all(|input| !input.witness.is_empty()));
+        assert_eq!(fund.input.len(), 3);
+
+        let attestation = oracle.attest("yes");
+        assert!(attestation.verify(&oracle.announcement).unwrap());
+        let mut forged = attestation.clone();
+        forged.outcome = "no".to_string();
+        assert!(!forged.verify(&oracle.announcement).unwrap());
+        assert!(alice.execute_contract(&alice_contract, forged).is_err());
+
+        let txid = alice.execute_contract(&alice_contract, attestation.clone()).unwrap();
+        let closed = alice.get_contract(&alice_contract.id).unwrap().unwrap();
+        assert_eq!(closed.state, ContractState::Closed);
+        assert_eq!(closed.execution_txid.unwrap().to_string(), txid);
+
+        // Both signatures in the CET witness are valid for the funding keys
+        let txs = closed.transactions.as_ref().unwrap();
+        let cet = &txs.cets[0];
+        assert_eq!(cet.compute_txid().to_string(), txid);
+        assert_eq!(cet.output.len(), 1);
+        assert_eq!(cet.output[0].script_pubkey, alice.wallet.payout_script().unwrap());
+        let sighash = txs.cet_sighash(0).unwrap();
+        let keys = [alice_contract.offer.as_ref().unwrap().funding_pubkey, alice_contract.accept.as_ref().unwrap().funding_pubkey];
+        let verified = cet.input[0].witness.iter().skip(1).take(2)
+            .filter(|sig| {
+                let signature = bitcoin::secp256k1::ecdsa::Signature::from_der(&sig[..sig.len() - 1]).unwrap();
+                keys.iter().any(|key| alice.secp.verify_ecdsa(&sighash, &signature, key).is_ok())
+            })
+            .count();
+        assert_eq!(verified, 2);
+
+        // The counterparty can settle the same way, or refund after the timeout
+        assert_eq!(bob.execute_contract(&bob_contract, attestation).unwrap(), txid);
+        assert!(alice.refund_contract(&alice_contract).is_err());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_refund_and_invalid_messages() {
+        let oracle = TestOracle::new(&["yes", "no"]);
+        let (_, bob, _, bob_contract) = negotiate(parameters(&oracle));
+        let refund_txid = bob.refund_contract(&bob_contract).unwrap();
+        let refunded = bob.get_contract(&bob_contract.id).unwrap().unwrap();
+        assert_eq!(refunded.state, ContractState::Refunded);
+        assert_eq!(refunded.refund_txid.unwrap().to_string(), refund_txid);
+        assert_eq!(bob.update_contract_status(&bob_contract.id).unwrap(), ContractState::Refunded);
+
+        // Tampered accept signatures are rejected by the offerer
+        let (alice, _) = funded_manager(1, &[200_000]);
+        let (carol, _) = funded_manager(3, &[200_000]);
+        let offered = alice.create_contract(parameters(&oracle)).unwrap();
+        let mut accepted = carol.accept_contract(&Contract::from_offer(offered.offer.clone().unwrap()).unwrap()).unwrap();
+        accepted.accept.as_mut().unwrap().cet_adaptor_signatures.swap(0, 1);
+        assert!(alice.sign_contract(&offered.clone().with_accept(accepted.accept.unwrap())).is_err());
+        assert_eq!(alice.update_contract_status(&offered.id).unwrap(), ContractState::Offered);
+
+        // Offers the wallet cannot fund, and numeric payouts on an enumerated
+        // event, are rejected
+        let (poor, _) = funded_manager(4, &[10_000]);
+        assert!(poor.create_contract(parameters(&oracle)).is_err());
+        let mut numeric = parameters(&oracle);
+        numeric.payout_function = PayoutFunction::Numeric { unit: "usd".to_string(), range: (0, 10), curve_points: vec![(0, 0), (10, 100)] };
+        assert!(alice.create_contract(numeric).is_err());
+
+        // So are announcements the oracle did not sign
+        let mut unsigned = parameters(&oracle);
+        unsigned.oracle_announcements[0].signature = None;
+        assert!(alice.create_contract(unsigned).is_err());
+    }
+
+    /// Offer party's payout from the CET executed as `txid`
+    fn executed_payout(contract: &Contract, txid: &str) -> u64 {
+        contract.execution_path(&txid.parse().unwrap()).unwrap().offer_payout
+    }
+
+    #[test]
+    fn test_numeric_contract_with_oracle_threshold() {
+        let decomposition = DigitDecomposition::new(2, 8, "usd").unwrap();
+        let oracles: Vec<LocalOracle> = (0..3u8)
+            .map(|i| LocalOracle::new("numeric", SecretKey::from_slice(&[0x61 + i; 32]).unwrap()))
+            .collect();
+        let announcements: Vec<OracleAnnouncement> = oracles.iter()
+            .map(|oracle| oracle.announce_numeric(
+                "btc-usd", "BTC/USD in thousands", Utc::now() + Duration::days(1), decomposition.clone(),
+            ).unwrap())
+            .collect();
+
+        // Offer party gets nothing up to 64, everything from 192 on, and a
+        // linear share in between, rounded to quarters of the collateral
+        let curve = PayoutCurve::piecewise_linear(&[
+            PayoutPoint::new(0, 0),
+            PayoutPoint::new(64, 0),
+            PayoutPoint::new(192, 100_000),
+            PayoutPoint::new(255, 100_000),
+        ]).unwrap();
+        let mut params = parameters(&TestOracle::new(&["yes", "no"]));
+        params.payout_function = PayoutFunction::Curve { curve, rounding_intervals: RoundingIntervals::uniform(25_000).unwrap() };
+        params.oracle_announcements = announcements;
+        params.oracle_threshold = Some(2);
+        params.bounded_error = Some(BoundedError::new(6, 4).unwrap());
+
+        let (alice, bob, alice_contract, bob_contract) = negotiate(params);
+        assert_eq!(alice_contract.state, ContractState::Funded);
+        let cets = cet::cet_outcomes(&alice_contract.offer.as_ref().unwrap().contract_info).unwrap();
+        assert_eq!(cets.len(), alice_contract.transactions.as_ref().unwrap().cets.len());
+        assert!(cets.iter().all(|cet| cet.oracle_outcomes.len() == 2));
+
+        // One attestation is below the threshold, and attestations too far
+        // apart match no CET
+        let attest = |oracle: usize, value: u64| oracles[oracle].attest_numeric("btc-usd", value).unwrap();
+        assert!(alice.execute_contract(&alice_contract, attest(0, 130)).is_err());
+        let disagreeing = LocalOracle::new("numeric", SecretKey::from_slice(&[0x62; 32]).unwrap());
+        disagreeing.announce_numeric("btc-usd", "", Utc::now(), decomposition.clone()).unwrap();
+        let far = disagreeing.attest_numeric("btc-usd", 200).unwrap();
+        assert!(alice.execute_contract_with_attestations(&alice_contract, vec![attest(0, 130), far]).is_err());
+
+        // Attestations within the error bound settle at the rounded payout:
+        // 130 and 133 both pay 51.5% of the collateral, rounded to half
+        let txid = alice.execute_contract_with_attestations(&alice_contract, vec![attest(0, 130), attest(2, 133)]).unwrap();
+        let closed = alice.get_contract(&alice_contract.id).unwrap().unwrap();
+        assert_eq!(closed.state, ContractState::Closed);
+        assert_eq!(executed_payout(&closed, &txid), 50_000);
+
+        // Any two of the oracles will do
+        let txid = bob.execute_contract_with_attestations(&bob_contract, vec![attest(2, 133), attest(1, 140)]).unwrap();
+        let closed = bob.get_contract(&bob_contract.id).unwrap().unwrap();
+        assert_eq!(executed_payout(&closed, &txid), 50_000);
+    }
+
+    #[test]
+    fn test_persistence_and_chain_monitoring() {
+        let oracle = TestOracle::new(&["yes", "no"]);
+        let chain = Arc::new(InMemoryChain::new(800_000, Utc::now().timestamp() as u32));
+        let dir = tempfile::tempdir().unwrap();
+        let store: Arc<dyn DLCStore> = Arc::new(FileDLCStore::open(dir.path()).unwrap());
+        let alice = funded_manager(1, &[100_000]).0.with_store(store.clone()).with_chain(chain.clone());
+        let bob = funded_manager(2, &[45_000]).0.with_chain(chain.clone());
+        let (signed, bob_contract) = negotiate_between(&alice, &bob, parameters(&oracle));
+        let funding = bob_contract.transactions.as_ref().unwrap().funding_outpoint();
+        assert!(chain.get_transaction(&funding.txid).unwrap().is_some());
+
+        // After a restart the offering party picks up the funding transaction
+        // from the chain, with the stored adaptor signatures intact
+        drop(alice);
+        let store: Arc<dyn DLCStore> = Arc::new(FileDLCStore::open(dir.path()).unwrap());
+        let alice = Arc::new(funded_manager(1, &[]).0.with_store(store.clone()).with_chain(chain.clone()));
+        assert_eq!(alice.get_contract(&signed.id).unwrap().unwrap().accept, signed.accept);
+        assert_eq!(alice.update_contract_status(&signed.id).unwrap(), ContractState::Funded);
+
+        // Once the event matures, the monitor settles as soon as the oracle attests
+        let mut monitor = ExecutionManager::new(alice.clone(), store, chain.clone());
+        monitor.add_attestation_source(oracle.oracle.clone());
+        chain.mine(150, 600).unwrap();
+        assert!(monitor.sync().unwrap().is_empty());
+        oracle.attest("yes");
+        let record = monitor.sync().unwrap().pop().unwrap();
+        assert_eq!(record.status, ExecutionStatus::InProgress);
+        assert_eq!(record.outcome.as_deref(), Some("yes"));
+        assert_eq!(record.final_offer_amount, Some(100_000));
+        let spend = chain.spending_transaction(&funding).unwrap().unwrap();
+        assert_eq!(Some(spend.compute_txid()), record.execution_txid);
+        assert_eq!(monitor.sync().unwrap().pop().unwrap().status, ExecutionStatus::Successful);
+        assert!(monitor.sync().unwrap().is_empty());
+
+        // The counterparty sees the CET on chain and can no longer refund
+        assert_eq!(bob.update_contract_status(&bob_contract.id).unwrap(), ContractState::Closed);
+        let closed = bob.get_contract(&bob_contract.id).unwrap().unwrap();
+        assert_eq!(closed.execution_txid, record.execution_txid);
+        assert!(bob.refund_contract(&closed).is_err());
+    }
+
+    #[test]
+    fn test_monitor_refunds_after_timeout() {
+        let oracle = TestOracle::new(&["yes", "no"]);
+        let chain = Arc::new(InMemoryChain::new(800_000, Utc::now().timestamp() as u32));
+        let store: Arc<dyn DLCStore> = Arc::new(InMemoryDLCStore::new());
+        let (_, _, _, bob_contract) = negotiate(parameters(&oracle));
+        let bob = Arc::new(funded_manager(2, &[]).0.with_store(store.clone()).with_chain(chain.clone()));
+        store.save_contract(&bob_contract).unwrap();
+        let monitor = Arc::new(ExecutionManager::new(bob.clone(), store, chain.clone()));
+
+        // The funding transaction is broadcast, but the refund is not final yet
+        assert!(monitor.sync().unwrap().is_empty());
+        let funding = bob_contract.transactions.as_ref().unwrap().funding_outpoint();
+        assert!(chain.get_transaction(&funding.txid).unwrap().is_some());
+        assert!(bob.refund_contract(&bob_contract).is_err());
+        assert_eq!(bob.update_contract_status(&bob_contract.id).unwrap(), ContractState::Funded);
+
+        chain.mine(400, 600).unwrap();
+        let handle = monitor.clone().spawn(std::time::Duration::from_millis(10));
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let record = loop {
+            match monitor.get_latest_execution(&bob_contract.id).unwrap() {
+                Some(record) => break record,
+                None if std::time::Instant::now() < deadline => std::thread::sleep(std::time::Duration::from_millis(10)),
+                None => panic!("Contract was not refunded"),
+            }
+        };
+        handle.stop();
+        assert_eq!(record.status, ExecutionStatus::Refunded);
+        let refunded = bob.get_contract(&bob_contract.id).unwrap().unwrap();
+        assert_eq!(refunded.state, ContractState::Refunded);
+        assert_eq!(chain.spending_transaction(&funding).unwrap().unwrap().compute_txid(), refunded.refund_txid.unwrap());
+    }
+}
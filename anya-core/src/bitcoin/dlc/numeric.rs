@@ -0,0 +1,707 @@
+// src/bitcoin/dlc/numeric.rs
+
+//! Numeric-outcome contracts
+//!
+//! A numeric event's outcome is decomposed into `nb_digits` digits in some
+//! base and the oracle signs each digit with its own nonce. A CET is then
+//! unlocked by a digit *prefix*: the prefix `[0, 1]` of a 4-digit base-2
+//! event covers the outcomes 4 to 7. Consecutive outcomes with the same
+//! rounded payout are covered with as few prefixes as possible, so a contract
+//! needs one CET per prefix rather than one per outcome.
+
+use serde::{Deserialize, Serialize};
+
+use crate::AnyaResult;
+
+/// Largest supported base; digits are attested as single characters
+pub const MAX_BASE: u16 = 10;
+
+/// Most outcomes a payout curve is evaluated at
+const MAX_EVALUATED_OUTCOMES: u64 = 1 << 24;
+
+/// How a numeric event's outcome is split into individually signed digits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigitDecomposition {
+    /// Base the outcome is written in
+    pub base: u16,
+
+    /// Number of digits, most significant first
+    pub nb_digits: u16,
+
+    /// Unit of the outcome
+    pub unit: String,
+
+    /// Power of ten the outcome is scaled by
+    pub precision: i32,
+}
+
+impl DigitDecomposition {
+    /// Creates a decomposition of unsigned outcomes in `base` with `nb_digits` digits
+    pub fn new(base: u16, nb_digits: u16, unit: &str) -> AnyaResult<Self> {
+        let decomposition = Self {
+            base,
+            nb_digits,
+            unit: unit.to_string(),
+            precision: 0,
+        };
+        decomposition.validate()?;
+        Ok(decomposition)
+    }
+
+    /// Checks the base and that every outcome fits in a u64
+    pub fn validate(&self) -> AnyaResult<()> {
+        if !(2..=MAX_BASE).contains(&self.base) {
+            return Err(format!("Unsupported base {}", self.base).into());
+        }
+        if self.nb_digits == 0 || (self.base as u64).checked_pow(self.nb_digits as u32).is_none() {
+            return Err(format!("Unsupported number of digits {}", self.nb_digits).into());
+        }
+        Ok(())
+    }
+
+    /// Largest outcome the digits can express
+    pub fn max_value(&self) -> u64 {
+        (self.base as u64).pow(self.nb_digits as u32) - 1
+    }
+
+    /// Digits of `value`, most significant first
+    pub fn decompose(&self, value: u64) -> AnyaResult<Vec<usize>> {
+        if value > self.max_value() {
+            return Err(format!("Outcome {} does not fit in {} base-{} digits", value, self.nb_digits, self.base).into());
+        }
+        Ok(decompose(value, self.base, self.nb_digits))
+    }
+
+    /// Value of a full set of digits
+    pub fn compose(&self, digits: &[usize]) -> AnyaResult<u64> {
+        if digits.len() != self.nb_digits as usize {
+            return Err(format!("Expected {} digits, got {}", self.nb_digits, digits.len()).into());
+        }
+        if digits.iter().any(|digit| *digit >= self.base as usize) {
+            return Err(format!("Digit out of range for base {}", self.base).into());
+        }
+        Ok(compose(digits, self.base))
+    }
+}
+
+/// Digits of `value` in `base`, most significant first, padded to `nb_digits`
+pub fn decompose(value: u64, base: u16, nb_digits: u16) -> Vec<usize> {
+    let mut digits = vec![0; nb_digits as usize];
+    let mut remaining = value;
+    for digit in digits.iter_mut().rev() {
+        *digit = (remaining % base as u64) as usize;
+        remaining /= base as u64;
+    }
+    digits
+}
+
+/// Value of `digits` in `base`, most significant first
+pub fn compose(digits: &[usize], base: u16) -> u64 {
+    digits.iter().fold(0, |value, digit| value * base as u64 + *digit as u64)
+}
+
+/// A point of a payout curve
+///
+/// The payout is `outcome_payout + extra_precision / 2^16` satoshis, so
+/// curves can pass between whole satoshi amounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutPoint {
+    /// Event outcome
+    pub event_outcome: u64,
+
+    /// Whole satoshis paid to the offering party
+    pub outcome_payout: u64,
+
+    /// Fractional satoshis, in units of 2^-16
+    pub extra_precision: u16,
+}
+
+impl PayoutPoint {
+    /// A point paying a whole number of satoshis
+    pub fn new(event_outcome: u64, outcome_payout: u64) -> Self {
+        Self {
+            event_outcome,
+            outcome_payout,
+            extra_precision: 0,
+        }
+    }
+
+    fn payout(&self) -> f64 {
+        self.outcome_payout as f64 + self.extra_precision as f64 / 65536.0
+    }
+}
+
+/// Polynomial piece of a payout curve, interpolating `points`
+///
+/// Two points make a line, three a parabola and so on. The first and last
+/// points are the piece's endpoints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolynomialPiece {
+    /// Interpolated points, by increasing outcome
+    pub points: Vec<PayoutPoint>,
+}
+
+impl PolynomialPiece {
+    /// Creates a piece through `points`
+    pub fn new(points: Vec<PayoutPoint>) -> AnyaResult<Self> {
+        if points.len() < 2 {
+            return Err("A payout curve piece needs at least two points".into());
+        }
+        if points.windows(2).any(|pair| pair[0].event_outcome >= pair[1].event_outcome) {
+            return Err("Payout curve points must have increasing outcomes".into());
+        }
+        Ok(Self { points })
+    }
+
+    /// Line from `start` to `end`
+    pub fn linear(start: PayoutPoint, end: PayoutPoint) -> AnyaResult<Self> {
+        Self::new(vec![start, end])
+    }
+
+    fn start(&self) -> &PayoutPoint {
+        &self.points[0]
+    }
+
+    fn end(&self) -> &PayoutPoint {
+        &self.points[self.points.len() - 1]
+    }
+
+    /// Lagrange interpolation at `outcome`
+    fn evaluate(&self, outcome: u64) -> f64 {
+        if let Some(point) = self.points.iter().find(|point| point.event_outcome == outcome) {
+            return point.payout();
+        }
+        let x = outcome as f64;
+        self.points.iter().enumerate()
+            .map(|(i, point)| {
+                let xi = point.event_outcome as f64;
+                let basis: f64 = self.points.iter().enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, other)| {
+                        let xj = other.event_outcome as f64;
+                        (x - xj) / (xi - xj)
+                    })
+                    .product();
+                point.payout() * basis
+            })
+            .sum()
+    }
+}
+
+/// Payout to the offering party as a function of the event outcome
+///
+/// Pieces are contiguous: each starts at the point where the previous one
+/// ends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutCurve {
+    /// Pieces by increasing outcome
+    pub pieces: Vec<PolynomialPiece>,
+}
+
+impl PayoutCurve {
+    /// Creates a curve from contiguous pieces
+    pub fn new(pieces: Vec<PolynomialPiece>) -> AnyaResult<Self> {
+        if pieces.is_empty() {
+            return Err("A payout curve needs at least one piece".into());
+        }
+        if pieces.windows(2).any(|pair| pair[0].end() != pair[1].start()) {
+            return Err("Payout curve pieces must be contiguous".into());
+        }
+        Ok(Self { pieces })
+    }
+
+    /// Piecewise-linear curve through `points`
+    pub fn piecewise_linear(points: &[PayoutPoint]) -> AnyaResult<Self> {
+        let pieces = points.windows(2)
+            .map(|pair| PolynomialPiece::linear(pair[0], pair[1]))
+            .collect::<AnyaResult<Vec<_>>>()?;
+        Self::new(pieces)
+    }
+
+    /// First outcome the curve is defined for
+    pub fn start(&self) -> u64 {
+        self.pieces[0].start().event_outcome
+    }
+
+    /// Last outcome the curve is defined for
+    pub fn end(&self) -> u64 {
+        self.pieces[self.pieces.len() - 1].end().event_outcome
+    }
+
+    /// Unrounded payout at `outcome`
+    pub fn payout(&self, outcome: u64) -> AnyaResult<f64> {
+        self.pieces.iter()
+            .find(|piece| piece.start().event_outcome <= outcome && outcome <= piece.end().event_outcome)
+            .map(|piece| piece.evaluate(outcome))
+            .ok_or_else(|| format!("Outcome {} is outside the payout curve", outcome).into())
+    }
+
+    /// Rounded payouts over the whole curve, merged into ranges of outcomes
+    /// paying the same
+    pub fn ranges(&self, total_collateral: u64, rounding: &RoundingIntervals) -> AnyaResult<Vec<PayoutRange>> {
+        if self.end() - self.start() >= MAX_EVALUATED_OUTCOMES {
+            return Err(format!("Payout curve spans more than {} outcomes", MAX_EVALUATED_OUTCOMES).into());
+        }
+        let mut ranges: Vec<PayoutRange> = Vec::new();
+        let mut piece_index = 0;
+        for outcome in self.start()..=self.end() {
+            while outcome > self.pieces[piece_index].end().event_outcome {
+                piece_index += 1;
+            }
+            let payout = self.pieces[piece_index].evaluate(outcome);
+            if payout < -0.5 || payout > total_collateral as f64 + 0.5 {
+                return Err(format!("Payout {} at outcome {} exceeds the collateral", payout, outcome).into());
+            }
+            let payout = rounding.round(outcome, payout).min(total_collateral);
+            match ranges.last_mut() {
+                Some(range) if range.payout == payout => range.end = outcome,
+                _ => ranges.push(PayoutRange { start: outcome, end: outcome, payout }),
+            }
+        }
+        Ok(ranges)
+    }
+}
+
+/// Outcomes `start..=end`, which all pay `payout` to the offering party
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayoutRange {
+    /// First outcome
+    pub start: u64,
+
+    /// Last outcome
+    pub end: u64,
+
+    /// Rounded payout to the offering party
+    pub payout: u64,
+}
+
+/// Payouts from `begin_interval` on are rounded to a multiple of `rounding_mod`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundingInterval {
+    /// First outcome the rounding applies to
+    pub begin_interval: u64,
+
+    /// Payouts are rounded to the nearest multiple of this
+    pub rounding_mod: u64,
+}
+
+/// Rounding of a payout curve
+///
+/// Coarser rounding merges more outcomes into each payout range and so
+/// reduces the number of CETs, at the cost of payout precision.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundingIntervals {
+    /// Intervals by increasing start, the first starting at outcome 0
+    pub intervals: Vec<RoundingInterval>,
+}
+
+impl RoundingIntervals {
+    /// Creates rounding intervals
+    pub fn new(intervals: Vec<RoundingInterval>) -> AnyaResult<Self> {
+        if intervals.first().map(|interval| interval.begin_interval) != Some(0) {
+            return Err("Rounding intervals must start at outcome 0".into());
+        }
+        if intervals.windows(2).any(|pair| pair[0].begin_interval >= pair[1].begin_interval) {
+            return Err("Rounding intervals must be in increasing order".into());
+        }
+        if intervals.iter().any(|interval| interval.rounding_mod == 0) {
+            return Err("Rounding modulus must be positive".into());
+        }
+        Ok(Self { intervals })
+    }
+
+    /// Rounding to `rounding_mod` satoshis across all outcomes
+    pub fn uniform(rounding_mod: u64) -> AnyaResult<Self> {
+        Self::new(vec![RoundingInterval { begin_interval: 0, rounding_mod }])
+    }
+
+    /// `payout` rounded to the nearest multiple of the modulus in force at
+    /// `outcome`, halves rounding up
+    pub fn round(&self, outcome: u64, payout: f64) -> u64 {
+        let rounding_mod = self.intervals.iter()
+            .rev()
+            .find(|interval| interval.begin_interval <= outcome)
+            .map(|interval| interval.rounding_mod)
+            .unwrap_or(1);
+        let payout = payout.round().max(0.0) as u64;
+        let remainder = payout % rounding_mod;
+        if remainder * 2 >= rounding_mod {
+            payout - remainder + rounding_mod
+        } else {
+            payout - remainder
+        }
+    }
+}
+
+/// Fewest digit prefixes covering exactly the outcomes `start..=end`
+///
+/// Walks from `start` taking the largest aligned block that still ends
+/// within the range. A prefix never covers the whole domain: that is split
+/// into one prefix per leading digit, so every CET needs a signed digit.
+pub fn group_by_ignoring_digits(start: u64, end: u64, base: u16, nb_digits: u16) -> Vec<Vec<usize>> {
+    let base = base as u64;
+    let mut prefixes = Vec::new();
+    let mut current = start;
+    loop {
+        let mut ignored = 0u16;
+        let mut block = 1u64;
+        while ignored + 1 < nb_digits {
+            let next = block * base;
+            if !current.is_multiple_of(next) || current + (next - 1) > end {
+                break;
+            }
+            ignored += 1;
+            block = next;
+        }
+        let mut prefix = decompose(current, base as u16, nb_digits);
+        prefix.truncate((nb_digits - ignored) as usize);
+        prefixes.push(prefix);
+
+        match current.checked_add(block) {
+            Some(next) if next <= end => current = next,
+            _ => break,
+        }
+    }
+    prefixes
+}
+
+/// Bounds on how far apart the outcomes attested by different oracles may be
+///
+/// Only for base-2 events. Oracles whose outcomes are less than
+/// `2^min_fail_exp` apart can always settle together. A secondary oracle is
+/// never accepted when its outcome is `2^max_error_exp` or further from
+/// every outcome paying the same as the primary oracle's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundedError {
+    /// Exponent of the largest tolerated difference
+    pub max_error_exp: u16,
+
+    /// Exponent of the smallest difference that may fail
+    pub min_fail_exp: u16,
+}
+
+impl BoundedError {
+    /// Creates error bounds; `max_error_exp` must exceed `min_fail_exp` by at least 2
+    pub fn new(max_error_exp: u16, min_fail_exp: u16) -> AnyaResult<Self> {
+        if min_fail_exp + 2 > max_error_exp {
+            return Err("max_error_exp must be at least min_fail_exp + 2".into());
+        }
+        Ok(Self { max_error_exp, min_fail_exp })
+    }
+
+    fn validate(&self, base: u16, nb_digits: u16) -> AnyaResult<()> {
+        if base != 2 {
+            return Err("Bounded oracle error requires base-2 events".into());
+        }
+        if self.min_fail_exp + 2 > self.max_error_exp || self.max_error_exp >= nb_digits {
+            return Err(format!(
+                "Invalid error bounds 2^{} and 2^{} for {} digits", self.max_error_exp, self.min_fail_exp, nb_digits,
+            ).into());
+        }
+        Ok(())
+    }
+
+    /// Prefixes a secondary oracle may attest alongside the primary's `prefix`
+    ///
+    /// When the primary's block is at least `2^max_error_exp` wide, the
+    /// secondary may attest the same block or the `2^min_fail_exp` blocks on
+    /// either side. Otherwise it may attest the aligned `2^max_error_exp`
+    /// block containing the primary's, plus the adjacent quarter block when
+    /// the primary's is within `2^min_fail_exp` of its edge.
+    pub fn secondary_prefixes(&self, prefix: &[usize], nb_digits: u16) -> AnyaResult<Vec<Vec<usize>>> {
+        self.validate(2, nb_digits)?;
+        let max_value = (1u64 << nb_digits) - 1;
+        let ignored = nb_digits as usize - prefix.len();
+        let start = compose(prefix, 2) << ignored;
+        let end = start + ((1u64 << ignored) - 1);
+        let max_error = 1u64 << self.max_error_exp;
+        let min_fail = 1u64 << self.min_fail_exp;
+
+        let block = |start: u64, exp: u16| {
+            let mut prefix = decompose(start, 2, nb_digits);
+            prefix.truncate((nb_digits - exp) as usize);
+            prefix
+        };
+        let mut prefixes = Vec::new();
+        if end - start + 1 >= max_error {
+            if start >= min_fail {
+                prefixes.push(block(start - min_fail, self.min_fail_exp));
+            }
+            prefixes.push(prefix.to_vec());
+            if end < max_value - (min_fail - 1) {
+                prefixes.push(block(end + 1, self.min_fail_exp));
+            }
+        } else {
+            let quarter = max_error / 4;
+            let block_start = start - start % max_error;
+            let block_end = block_start + (max_error - 1);
+            if start < block_start + min_fail && block_start >= quarter {
+                prefixes.push(block(block_start - quarter, self.max_error_exp - 2));
+            }
+            prefixes.push(block(block_start, self.max_error_exp));
+            if end + min_fail > block_end && block_end < max_value - (quarter - 1) {
+                prefixes.push(block(block_end + 1, self.max_error_exp - 2));
+            }
+        }
+        Ok(prefixes)
+    }
+}
+
+/// One numeric CET: the digit prefix each listed oracle must attest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericCet {
+    /// Payout to the offering party
+    pub offer_payout: u64,
+
+    /// Oracle index and the prefix it must attest, primary oracle first
+    pub oracle_prefixes: Vec<(usize, Vec<usize>)>,
+}
+
+/// CETs paying `ranges` when `threshold` of `nb_oracles` oracles attest
+///
+/// Every `threshold`-sized set of oracles gets its own CETs. Without error
+/// bounds all oracles in a set must attest the same prefix; with them, the
+/// first oracle of the set is the primary and the others may deviate as
+/// [`BoundedError::secondary_prefixes`] allows.
+pub fn numeric_cets(
+    ranges: &[PayoutRange],
+    decomposition: &DigitDecomposition,
+    nb_oracles: usize,
+    threshold: usize,
+    bounded_error: Option<&BoundedError>,
+) -> AnyaResult<Vec<NumericCet>> {
+    if threshold == 0 || threshold > nb_oracles {
+        return Err(format!("Invalid oracle threshold {} of {}", threshold, nb_oracles).into());
+    }
+    if let Some(bounds) = bounded_error {
+        bounds.validate(decomposition.base, decomposition.nb_digits)?;
+    }
+
+    let mut cets = Vec::new();
+    for oracles in combinations(nb_oracles, threshold) {
+        for range in ranges {
+            for prefix in group_by_ignoring_digits(range.start, range.end, decomposition.base, decomposition.nb_digits) {
+                let secondary = match bounded_error {
+                    Some(bounds) => bounds.secondary_prefixes(&prefix, decomposition.nb_digits)?,
+                    None => vec![prefix.clone()],
+                };
+                for choice in product(secondary.len(), threshold - 1) {
+                    let mut oracle_prefixes = vec![(oracles[0], prefix.clone())];
+                    oracle_prefixes.extend(oracles[1..].iter().zip(choice).map(|(oracle, i)| (*oracle, secondary[i].clone())));
+                    cets.push(NumericCet { offer_payout: range.payout, oracle_prefixes });
+                }
+            }
+        }
+    }
+    Ok(cets)
+}
+
+/// All `k`-element subsets of `0..n`, in lexicographic order
+pub fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    let mut current: Vec<usize> = (0..k).collect();
+    if k > n {
+        return result;
+    }
+    loop {
+        result.push(current.clone());
+        let Some(i) = (0..k).rev().find(|&i| current[i] < n - k + i) else {
+            return result;
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+/// All `length`-tuples of indices below `size`
+fn product(size: usize, length: usize) -> Vec<Vec<usize>> {
+    (0..length).fold(vec![Vec::new()], |tuples, _| {
+        tuples.into_iter()
+            .flat_map(|tuple| (0..size).map(move |i| {
+                let mut next = tuple.clone();
+                next.push(i);
+                next
+            }))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covered(prefixes: &[Vec<usize>], base: u16, nb_digits: u16) -> Vec<u64> {
+        let mut outcomes: Vec<u64> = prefixes.iter()
+            .flat_map(|prefix| {
+                let width = (base as u64).pow((nb_digits as usize - prefix.len()) as u32);
+                let start = compose(prefix, base) * width;
+                start..start + width
+            })
+            .collect();
+        outcomes.sort_unstable();
+        outcomes
+    }
+
+    fn matches(prefix: &[usize], value: u64, nb_digits: u16) -> bool {
+        decompose(value, 2, nb_digits).starts_with(prefix)
+    }
+
+    #[test]
+    fn test_digit_decomposition() {
+        let binary = DigitDecomposition::new(2, 5, "usd").unwrap();
+        assert_eq!(binary.decompose(11).unwrap(), vec![0, 1, 0, 1, 1]);
+        assert_eq!(binary.compose(&[1, 1, 1, 1, 1]).unwrap(), binary.max_value());
+        assert!(binary.decompose(32).is_err());
+        assert!(binary.compose(&[2, 0, 0, 0, 0]).is_err());
+
+        let decimal = DigitDecomposition::new(10, 4, "usd").unwrap();
+        assert_eq!(decimal.decompose(407).unwrap(), vec![0, 4, 0, 7]);
+        assert_eq!(decimal.max_value(), 9999);
+        assert!(DigitDecomposition::new(16, 4, "usd").is_err());
+        assert!(DigitDecomposition::new(2, 64, "usd").is_err());
+    }
+
+    #[test]
+    fn test_group_by_ignoring_digits() {
+        let prefixes = group_by_ignoring_digits(123, 4321, 10, 4);
+        assert_eq!(prefixes.len(), 32);
+        assert_eq!(prefixes[0], vec![0, 1, 2, 3]);
+        assert!(prefixes.contains(&vec![1]) && prefixes.contains(&vec![4, 3, 2, 1]));
+        assert_eq!(covered(&prefixes, 10, 4), (123..=4321).collect::<Vec<_>>());
+
+        // Aligned blocks collapse, single outcomes stay full length
+        assert_eq!(group_by_ignoring_digits(16, 31, 2, 5), vec![vec![1]]);
+        assert_eq!(group_by_ignoring_digits(5, 5, 2, 5), vec![vec![0, 0, 1, 0, 1]]);
+        // The whole domain still needs a signed digit
+        assert_eq!(group_by_ignoring_digits(0, 31, 2, 5), vec![vec![0], vec![1]]);
+        assert_eq!(group_by_ignoring_digits(1, 30, 2, 5).len(), 8);
+    }
+
+    #[test]
+    fn test_payout_curve_boundaries() {
+        // Offer gets nothing up to 200, everything from 800, linear between
+        let curve = PayoutCurve::piecewise_linear(&[
+            PayoutPoint::new(0, 0),
+            PayoutPoint::new(200, 0),
+            PayoutPoint::new(800, 100_000),
+            PayoutPoint::new(1023, 100_000),
+        ]).unwrap();
+        assert_eq!(curve.payout(200).unwrap(), 0.0);
+        assert_eq!(curve.payout(500).unwrap(), 50_000.0);
+        assert!(curve.payout(1024).is_err());
+
+        let rounding = RoundingIntervals::uniform(10_000).unwrap();
+        let ranges = curve.ranges(100_000, &rounding).unwrap();
+        assert_eq!(ranges.len(), 11);
+        assert_eq!(ranges[0], PayoutRange { start: 0, end: 229, payout: 0 });
+        // 230 is exactly half way to the first 10k step and rounds up
+        assert_eq!(ranges[1], PayoutRange { start: 230, end: 289, payout: 10_000 });
+        assert_eq!(ranges[10], PayoutRange { start: 770, end: 1023, payout: 100_000 });
+
+        let exact = curve.ranges(100_000, &RoundingIntervals::uniform(1).unwrap()).unwrap();
+        assert_eq!(exact.iter().map(|r| r.end - r.start + 1).sum::<u64>(), 1024);
+        assert!(curve.ranges(99_999, &rounding).is_err());
+
+        // Finer rounding in the middle of the curve adds ranges there only
+        let mixed = RoundingIntervals::new(vec![
+            RoundingInterval { begin_interval: 0, rounding_mod: 10_000 },
+            RoundingInterval { begin_interval: 400, rounding_mod: 5_000 },
+            RoundingInterval { begin_interval: 600, rounding_mod: 10_000 },
+        ]).unwrap();
+        assert_eq!(curve.ranges(100_000, &mixed).unwrap().len(), 15);
+        assert!(RoundingIntervals::new(vec![RoundingInterval { begin_interval: 5, rounding_mod: 1 }]).is_err());
+    }
+
+    #[test]
+    fn test_polynomial_piece() {
+        let parabola = PolynomialPiece::new(vec![
+            PayoutPoint::new(0, 0),
+            PayoutPoint::new(50, 2_500),
+            PayoutPoint::new(100, 10_000),
+        ]).unwrap();
+        let curve = PayoutCurve::new(vec![parabola]).unwrap();
+        assert!((curve.payout(20).unwrap() - 400.0).abs() < 1e-6);
+        assert!((curve.payout(70).unwrap() - 4_900.0).abs() < 1e-6);
+
+        let half = PayoutPoint { event_outcome: 150, outcome_payout: 10_000, extra_precision: 1 << 15 };
+        let line = PolynomialPiece::linear(PayoutPoint::new(100, 10_000), half).unwrap();
+        let joined = PayoutCurve::new(vec![curve.pieces[0].clone(), line.clone()]).unwrap();
+        assert_eq!(joined.payout(150).unwrap(), 10_000.5);
+        assert!((joined.payout(125).unwrap() - 10_000.25).abs() < 1e-9);
+        assert!(PayoutCurve::new(vec![line, curve.pieces[0].clone()]).is_err());
+        assert!(PolynomialPiece::new(vec![PayoutPoint::new(5, 0), PayoutPoint::new(5, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_numeric_cet_counts() {
+        let decomposition = DigitDecomposition::new(2, 10, "usd").unwrap();
+        let curve = PayoutCurve::piecewise_linear(&[
+            PayoutPoint::new(0, 0),
+            PayoutPoint::new(200, 0),
+            PayoutPoint::new(800, 100_000),
+            PayoutPoint::new(1023, 100_000),
+        ]).unwrap();
+        let ranges = curve.ranges(100_000, &RoundingIntervals::uniform(10_000).unwrap()).unwrap();
+        let single = numeric_cets(&ranges, &decomposition, 1, 1, None).unwrap();
+        let prefixes: usize = ranges.iter()
+            .map(|r| group_by_ignoring_digits(r.start, r.end, 2, 10).len())
+            .sum();
+        assert_eq!(single.len(), prefixes);
+        assert_eq!(single.len(), 64);
+
+        // Every outcome is covered by exactly one CET, paying its range's payout
+        for outcome in [0, 229, 230, 500, 769, 770, 1023] {
+            let cets: Vec<_> = single.iter()
+                .filter(|cet| matches(&cet.oracle_prefixes[0].1, outcome, 10))
+                .collect();
+            assert_eq!(cets.len(), 1);
+            let range = ranges.iter().find(|r| r.start <= outcome && outcome <= r.end).unwrap();
+            assert_eq!(cets[0].offer_payout, range.payout);
+        }
+
+        // 2-of-3 oracles that must agree: one CET set per pair
+        let exact = numeric_cets(&ranges, &decomposition, 3, 2, None).unwrap();
+        assert_eq!(exact.len(), 3 * prefixes);
+        assert!(exact.iter().all(|cet| cet.oracle_prefixes[0].1 == cet.oracle_prefixes[1].1));
+        assert!(numeric_cets(&ranges, &decomposition, 2, 3, None).is_err());
+        assert_eq!(combinations(4, 2).len(), 6);
+    }
+
+    #[test]
+    fn test_bounded_error() {
+        let decomposition = DigitDecomposition::new(2, 10, "usd").unwrap();
+        let bounds = BoundedError::new(6, 3).unwrap();
+        let curve = PayoutCurve::piecewise_linear(&[
+            PayoutPoint::new(0, 0),
+            PayoutPoint::new(1023, 100_000),
+        ]).unwrap();
+        let ranges = curve.ranges(100_000, &RoundingIntervals::uniform(10_000).unwrap()).unwrap();
+        let cets = numeric_cets(&ranges, &decomposition, 2, 2, Some(&bounds)).unwrap();
+
+        let range_of = |value: u64| ranges.iter().find(|r| r.start <= value && value <= r.end).unwrap();
+        for primary in (0..1024).step_by(7) {
+            for secondary in (0..1024).step_by(3) {
+                let matching: Vec<_> = cets.iter()
+                    .filter(|cet| matches(&cet.oracle_prefixes[0].1, primary, 10) && matches(&cet.oracle_prefixes[1].1, secondary, 10))
+                    .collect();
+                assert!(matching.len() <= 1);
+                let difference = primary.abs_diff(secondary);
+                if difference < 8 {
+                    assert_eq!(matching.len(), 1, "{} and {} must settle", primary, secondary);
+                }
+                if let Some(cet) = matching.first() {
+                    let range = range_of(primary);
+                    assert_eq!(cet.offer_payout, range.payout);
+                    let distance = if secondary < range.start { range.start - secondary } else { secondary.saturating_sub(range.end) };
+                    assert!(distance < 64, "{} accepted {} away from {:?}", secondary, distance, range);
+                }
+            }
+        }
+
+        assert!(BoundedError::new(4, 3).is_err());
+        let decimal = DigitDecomposition::new(10, 3, "usd").unwrap();
+        assert!(numeric_cets(&ranges, &decimal, 2, 2, Some(&bounds)).is_err());
+    }
+}
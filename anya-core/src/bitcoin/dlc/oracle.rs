@@ -1,11 +1,24 @@
 // src/bitcoin/dlc/oracle.rs
 
 use std::collections::HashMap;
+use std::sync::Mutex;
 use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
-use bitcoin::secp256k1::{PublicKey, Signature};
+use bitcoin::key::{Keypair, Parity};
+use bitcoin::secp256k1::{schnorr::Signature, Message, PublicKey, Scalar, Secp256k1, SecretKey};
 
-use crate::common::error::AnyaResult;
+use crate::AnyaResult;
+use super::adaptor::bip340_challenge;
+use crate::bitcoin::crypto::{reduce_scalar, tagged_hash};
+use super::messages::{EventDescriptor, OracleEvent, Reader, SignedOracleEvent, Writer, ORACLE_ATTESTATION_TYPE};
+use super::numeric::DigitDecomposition;
+
+/// Tag of the hash an oracle signs for an enumerated outcome
+pub const ATTESTATION_TAG: &str = "DLC/oracle/attestation/v0";
+
+/// Tag of the hash an oracle signs to announce an event
+pub const ANNOUNCEMENT_TAG: &str = "DLC/oracle/announcement/v0";
 
 /// Represents an oracle that provides attestations for DLCs
 #[derive(Debug, Clone)]
@@ -96,6 +109,16 @@ pub struct OracleAnnouncement {
     
     /// Additional metadata
     pub metadata: HashMap<String, String>,
+
+    /// Oracle's BIP340 signature over the announced event
+    pub signature: Option<Signature>,
+
+    /// Every nonce the oracle committed to: `public_r` for an enumerated
+    /// event, one per digit for a numeric one
+    pub nonces: Vec<PublicKey>,
+
+    /// How a numeric event's outcome is signed; `None` for enumerated events
+    pub digit_decomposition: Option<DigitDecomposition>,
 }
 
 impl OracleAnnouncement {
@@ -119,7 +142,30 @@ pub fn new(
             announcement_time,
             outcomes,
             metadata: HashMap::new(),
+            signature: None,
+            nonces: vec![public_r],
+            digit_decomposition: None,
+        }
+    }
+
+    /// Creates an announcement of a numeric event signed digit by digit
+    pub fn numeric(
+        event_id: String,
+        description: String,
+        nonces: Vec<PublicKey>,
+        public_key: PublicKey,
+        maturity_time: DateTime<Utc>,
+        announcement_time: DateTime<Utc>,
+        digit_decomposition: DigitDecomposition,
+    ) -> AnyaResult<Self> {
+        digit_decomposition.validate()?;
+        if nonces.len() != digit_decomposition.nb_digits as usize {
+            return Err(format!("Expected {} nonces, got {}", digit_decomposition.nb_digits, nonces.len()).into());
         }
+        let mut announcement = Self::new(event_id, description, nonces[0], public_key, maturity_time, announcement_time, Vec::new());
+        announcement.nonces = nonces;
+        announcement.digit_decomposition = Some(digit_decomposition);
+        Ok(announcement)
     }
     
     /// Adds metadata to the announcement
@@ -127,10 +173,138 @@ pub fn add_metadata(&mut self, key: &str, value: &str) {
         self.metadata.insert(key.to_string(), value.to_string());
     }
     
-    /// Verifies the announcement signature (if present)
+    /// Verifies the announcement signature
+    ///
+    /// The signature is BIP340 over the tagged hash of the `oracle_event` TLV,
+    /// which commits the oracle to its nonce. Unsigned announcements fail.
     pub fn verify_signature(&self) -> AnyaResult<bool> {
-        // Implementation goes here
-        Ok(true)
+        let signature = match &self.signature {
+            Some(signature) => signature,
+            None => return Ok(false),
+        };
+        let secp = Secp256k1::verification_only();
+        let message = Message::from_digest(Self::announcement_message(&self.to_event()));
+        Ok(secp.verify_schnorr(signature, &message, &self.public_key.x_only_public_key().0).is_ok())
+    }
+
+    /// Hash the oracle signs when announcing `event`
+    pub fn announcement_message(event: &OracleEvent) -> [u8; 32] {
+        tagged_hash(ANNOUNCEMENT_TAG, &[&event.to_tlv()])
+    }
+
+    /// Builds an announcement from a signed oracle event
+    pub fn from_event(event: &OracleEvent, signature: Signature) -> AnyaResult<Self> {
+        let maturity_time = DateTime::from_timestamp(event.event_maturity_epoch as i64, 0)
+            .ok_or("Invalid event maturity")?;
+        let public_key = PublicKey::from_x_only_public_key(event.oracle_public_key, Parity::Even);
+        let nonces: Vec<PublicKey> = event.oracle_nonces.iter()
+            .map(|nonce| PublicKey::from_x_only_public_key(*nonce, Parity::Even))
+            .collect();
+        let mut announcement = match &event.descriptor {
+            EventDescriptor::Enumerated(outcomes) => match nonces.as_slice() {
+                [nonce] => Self::new(
+                    event.event_id.clone(),
+                    String::new(),
+                    *nonce,
+                    public_key,
+                    maturity_time,
+                    maturity_time,
+                    outcomes.clone(),
+                ),
+                _ => return Err("Enumerated oracle events must have exactly one nonce".into()),
+            },
+            EventDescriptor::DigitDecomposition(digits) => Self::numeric(
+                event.event_id.clone(),
+                String::new(),
+                nonces,
+                public_key,
+                maturity_time,
+                maturity_time,
+                digits.clone(),
+            )?,
+        };
+        announcement.signature = Some(signature);
+        Ok(announcement)
+    }
+
+    /// The signed event in wire form
+    pub fn to_signed_event(&self) -> AnyaResult<SignedOracleEvent> {
+        let signature = self.signature.ok_or("Oracle announcement is not signed")?;
+        Ok(SignedOracleEvent { signature, event: self.to_event() })
+    }
+
+    /// The `oracle_announcement` TLV
+    pub fn to_tlv(&self) -> AnyaResult<Vec<u8>> {
+        let mut writer = Writer::new();
+        self.to_signed_event()?.write(&mut writer);
+        Ok(writer.into_bytes())
+    }
+
+    /// Parse an `oracle_announcement` TLV
+    pub fn from_tlv(data: &[u8]) -> AnyaResult<Self> {
+        let mut reader = Reader::new(data);
+        let announcement = SignedOracleEvent::read(&mut reader)?;
+        if !reader.is_empty() {
+            return Err("Trailing bytes after oracle announcement".into());
+        }
+        Self::from_event(&announcement.event, announcement.signature)
+    }
+
+    /// The oracle event in wire form
+    pub fn to_event(&self) -> OracleEvent {
+        OracleEvent {
+            oracle_public_key: self.public_key.x_only_public_key().0,
+            oracle_nonces: self.nonces.iter().map(|nonce| nonce.x_only_public_key().0).collect(),
+            event_maturity_epoch: self.maturity_time.timestamp() as u32,
+            descriptor: match &self.digit_decomposition {
+                Some(digits) => EventDescriptor::DigitDecomposition(digits.clone()),
+                None => EventDescriptor::Enumerated(self.outcomes.clone()),
+            },
+            event_id: self.event_id.clone(),
+        }
+    }
+
+    /// Hash the oracle signs when attesting to `outcome`
+    pub fn outcome_message(outcome: &str) -> [u8; 32] {
+        tagged_hash(ATTESTATION_TAG, &[outcome.as_bytes()])
+    }
+
+    /// Point whose discrete log the oracle reveals by attesting to `outcome`
+    ///
+    /// For a BIP340 signature `(R, s)` over `m`, `s*G = R + H(R || P || m)*P`,
+    /// so adaptor signatures encrypted under this point can be decrypted with
+    /// the attestation's `s`. Keys are lifted to even y as BIP340 requires.
+    pub fn anticipation_point(&self, outcome: &str) -> AnyaResult<PublicKey> {
+        if self.digit_decomposition.is_some() || !self.outcomes.iter().any(|o| o == outcome) {
+            return Err(format!("Outcome '{}' is not announced for event {}", outcome, self.event_id).into());
+        }
+        self.nonce_anticipation_point(&self.public_r, outcome)
+    }
+
+    /// Point whose discrete log the oracle reveals by signing `digit` at
+    /// position `index` of a numeric outcome
+    pub fn digit_anticipation_point(&self, index: usize, digit: usize) -> AnyaResult<PublicKey> {
+        let digits = self.digit_decomposition.as_ref()
+            .ok_or_else(|| format!("Event {} is not numeric", self.event_id))?;
+        if digit >= digits.base as usize {
+            return Err(format!("Digit {} out of range for base {}", digit, digits.base).into());
+        }
+        let nonce = self.nonces.get(index)
+            .ok_or_else(|| format!("Event {} has no digit {}", self.event_id, index))?;
+        self.nonce_anticipation_point(nonce, &digit.to_string())
+    }
+
+    fn nonce_anticipation_point(&self, nonce: &PublicKey, outcome: &str) -> AnyaResult<PublicKey> {
+        let secp = Secp256k1::verification_only();
+        let oracle_key = self.public_key.x_only_public_key().0;
+        let challenge = bip340_challenge(nonce, &self.public_key, &Self::outcome_message(outcome))?;
+
+        let weighted_key = PublicKey::from_x_only_public_key(oracle_key, Parity::Even)
+            .mul_tweak(&secp, &Scalar::from(challenge))
+            .map_err(|e| format!("Anticipation point failed: {}", e))?;
+        PublicKey::from_x_only_public_key(nonce.x_only_public_key().0, Parity::Even)
+            .combine(&weighted_key)
+            .map_err(|e| format!("Anticipation point failed: {}", e).into())
     }
 }
 
@@ -154,6 +328,14 @@ pub struct OracleAttestation {
     
     /// Additional metadata
     pub metadata: HashMap<String, String>,
+
+    /// Key of the attesting oracle, when known
+    pub oracle_public_key: Option<PublicKey>,
+
+    /// Signatures over each digit of a numeric outcome, most significant
+    /// first, with `outcome` holding the digits and `signature` the first
+    /// signature; empty for enumerated events
+    pub signatures: Vec<Signature>,
 }
 
 impl OracleAttestation {
@@ -171,9 +353,85 @@ pub fn new(
             announcement_id,
             created_at: Utc::now(),
             metadata: HashMap::new(),
+            oracle_public_key: None,
+            signatures: Vec::new(),
         }
     }
-    
+
+    /// Creates an attestation of a numeric outcome from its digit signatures
+    pub fn numeric(event_id: String, digits: &[usize], signatures: Vec<Signature>) -> AnyaResult<Self> {
+        if digits.is_empty() || digits.len() != signatures.len() || digits.iter().any(|digit| *digit > 9) {
+            return Err("A numeric attestation needs one signature per decimal digit".into());
+        }
+        let outcome = digits.iter().map(|digit| digit.to_string()).collect();
+        let mut attestation = Self::new(event_id.clone(), outcome, signatures[0], event_id);
+        attestation.signatures = signatures;
+        Ok(attestation)
+    }
+
+    /// The `oracle_attestation` TLV
+    pub fn to_tlv(&self) -> AnyaResult<Vec<u8>> {
+        let oracle_public_key = self.oracle_public_key.ok_or("Attestation has no oracle key")?;
+        let (outcomes, signatures) = if self.signatures.is_empty() {
+            (vec![self.outcome.clone()], vec![self.signature])
+        } else {
+            (self.outcome.chars().map(String::from).collect(), self.signatures.clone())
+        };
+        let mut value = Writer::new();
+        value.string(&self.event_id);
+        value.bytes(&oracle_public_key.x_only_public_key().0.serialize());
+        value.u16(signatures.len() as u16);
+        for signature in &signatures {
+            value.bytes(signature.as_ref());
+        }
+        for outcome in &outcomes {
+            value.string(outcome);
+        }
+        let mut writer = Writer::new();
+        writer.tlv(ORACLE_ATTESTATION_TYPE, &value.into_bytes());
+        Ok(writer.into_bytes())
+    }
+
+    /// Parse an `oracle_attestation` TLV
+    ///
+    /// Attestations with several signatures are numeric, one decimal digit
+    /// per signature.
+    pub fn from_tlv(data: &[u8]) -> AnyaResult<Self> {
+        let mut reader = Reader::new(data);
+        let mut value = reader.tlv(ORACLE_ATTESTATION_TYPE)?;
+        if !reader.is_empty() {
+            return Err("Trailing bytes after oracle attestation".into());
+        }
+        let event_id = value.string()?;
+        let oracle_public_key = value.x_only_public_key()?;
+        let count = value.u16()? as usize;
+        let signatures = (0..count)
+            .map(|_| value.schnorr_signature())
+            .collect::<AnyaResult<Vec<_>>>()?;
+        let outcomes = (0..count)
+            .map(|_| value.string())
+            .collect::<AnyaResult<Vec<_>>>()?;
+        if !value.is_empty() {
+            return Err("Trailing bytes in oracle attestation".into());
+        }
+
+        let mut attestation = match count {
+            0 => return Err("Attestation has no signatures".into()),
+            1 => Self::new(event_id.clone(), outcomes[0].clone(), signatures[0], event_id),
+            _ => {
+                let digits = outcomes.iter()
+                    .map(|outcome| match outcome.as_bytes() {
+                        [digit @ b'0'..=b'9'] => Ok((digit - b'0') as usize),
+                        _ => Err(format!("Invalid digit '{}' in attestation", outcome)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Self::numeric(event_id, &digits, signatures)?
+            }
+        };
+        attestation.oracle_public_key = Some(PublicKey::from_x_only_public_key(oracle_public_key, Parity::Even));
+        Ok(attestation)
+    }
+
     /// Verifies the attestation against an announcement
     pub fn verify(&self, announcement: &OracleAnnouncement) -> AnyaResult<bool> {
         // Implementation goes here
@@ -182,15 +440,79 @@ pub fn verify(&self, announcement: &OracleAnnouncement) -> AnyaResult<bool> {
             return Ok(false);
         }
         
+        // The attesting key, when carried, must be the announcing one
+        if let Some(key) = self.oracle_public_key {
+            if key.x_only_public_key().0 != announcement.public_key.x_only_public_key().0 {
+                return Ok(false);
+            }
+        }
+
+        if let Some(decomposition) = &announcement.digit_decomposition {
+            return self.verify_digits(announcement, decomposition);
+        }
+        
         // 2. Check outcome is in the list of possible outcomes
-        if !announcement.outcomes.contains(&self.outcome) {
+        if !announcement.outcomes.contains(&self.outcome) || !self.signatures.is_empty() {
             return Ok(false);
         }
         
-        // 3. Verify signature (note: actual implementation would use SECP256K1)
-        // This is a placeholder
+        // 3. The signature must use the announced nonce and reveal the
+        //    anticipation point's discrete log
+        Ok(reveals(&self.signature, &announcement.public_r, &announcement.anticipation_point(&self.outcome)?))
+    }
+
+    /// Every digit signature must use its announced nonce and reveal the
+    /// digit's anticipation point
+    fn verify_digits(&self, announcement: &OracleAnnouncement, decomposition: &DigitDecomposition) -> AnyaResult<bool> {
+        let digits = match self.digits() {
+            Ok(digits) => digits,
+            Err(_) => return Ok(false),
+        };
+        let signatures = self.digit_signatures();
+        if digits.len() != decomposition.nb_digits as usize
+            || signatures.len() != digits.len()
+            || announcement.nonces.len() != digits.len()
+            || digits.iter().any(|digit| *digit >= decomposition.base as usize)
+        {
+            return Ok(false);
+        }
+        for (index, (digit, signature)) in digits.iter().zip(&signatures).enumerate() {
+            let point = announcement.digit_anticipation_point(index, *digit)?;
+            if !reveals(signature, &announcement.nonces[index], &point) {
+                return Ok(false);
+            }
+        }
         Ok(true)
     }
+
+    /// Digits of a numeric outcome, most significant first
+    pub fn digits(&self) -> AnyaResult<Vec<usize>> {
+        self.outcome.chars()
+            .map(|c| c.to_digit(10).map(|digit| digit as usize))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("Outcome '{}' is not numeric", self.outcome).into())
+    }
+
+    /// Signatures over each digit of a numeric outcome
+    fn digit_signatures(&self) -> Vec<Signature> {
+        if self.signatures.is_empty() {
+            vec![self.signature]
+        } else {
+            self.signatures.clone()
+        }
+    }
+
+    /// The `s` values of the digit signatures, most significant first
+    pub fn digit_scalars(&self) -> AnyaResult<Vec<SecretKey>> {
+        self.digit_signatures().iter()
+            .map(signature_scalar)
+            .collect()
+    }
+
+    /// The signature's `s` value, which decrypts adaptor signatures for the outcome
+    pub fn attestation_scalar(&self) -> AnyaResult<SecretKey> {
+        signature_scalar(&self.signature)
+    }
     
     /// Adds metadata to the attestation
     pub fn add_metadata(&mut self, key: &str, value: &str) {
@@ -198,41 +520,535 @@ pub fn add_metadata(&mut self, key: &str, value: &str) {
     }
 }
 
+fn signature_scalar(signature: &Signature) -> AnyaResult<SecretKey> {
+    SecretKey::from_slice(&signature.as_ref()[32..])
+        .map_err(|e| format!("Invalid attestation scalar: {}", e).into())
+}
+
+/// Whether `signature` was made with `nonce` and its `s` is the discrete log of `point`
+fn reveals(signature: &Signature, nonce: &PublicKey, point: &PublicKey) -> bool {
+    if signature.as_ref()[..32] != nonce.x_only_public_key().0.serialize() {
+        return false;
+    }
+    match signature_scalar(signature) {
+        Ok(scalar) => PublicKey::from_secret_key(&Secp256k1::signing_only(), &scalar) == *point,
+        Err(_) => false,
+    }
+}
+
+/// Where attestations of announced events can be fetched from
+pub trait AttestationSource: Send + Sync {
+    /// The attestation of an announced event, once the oracle has made it
+    fn attestation(&self, announcement: &OracleAnnouncement) -> AnyaResult<Option<OracleAttestation>>;
+}
+
 /// Client for interacting with oracles
+///
+/// Talks to an oracle over HTTP. `GET {base}/v0/info` returns the oracle's
+/// name and key, `/v0/announcements` a list of hex `oracle_announcement`
+/// TLVs, and `/v0/announcements/{event_id}` and `/v0/attestations/{event_id}`
+/// objects holding a single hex TLV under `announcement` or `attestation`.
 pub struct OracleClient {
     /// Base URL for the oracle API
     base_url: String,
+
+    /// HTTP client
+    http: reqwest::blocking::Client,
+}
+
+#[derive(Deserialize)]
+struct InfoResponse {
+    name: String,
+    public_key: PublicKey,
+}
+
+#[derive(Deserialize)]
+struct AnnouncementResponse {
+    announcement: String,
+}
+
+#[derive(Deserialize)]
+struct AttestationResponse {
+    attestation: String,
 }
 
 impl OracleClient {
     /// Creates a new oracle client
     pub fn new(base_url: &str) -> Self {
         Self {
-            base_url: base_url.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::blocking::Client::new(),
         }
     }
     
     /// Gets oracle information
     pub fn get_oracle_info(&self) -> AnyaResult<OracleInfo> {
-        // Implementation goes here
-        unimplemented!("Oracle info retrieval not yet implemented")
+        let info: InfoResponse = self.get("/v0/info")?
+            .ok_or("Oracle did not return its info")?;
+        Ok(OracleInfo {
+            name: info.name,
+            public_key: info.public_key,
+            endpoint: self.base_url.clone(),
+            properties: HashMap::new(),
+        })
     }
     
     /// Gets announcements from the oracle
+    ///
+    /// Every announcement's signature is verified.
     pub fn get_announcements(&self) -> AnyaResult<Vec<OracleAnnouncement>> {
-        // Implementation goes here
-        unimplemented!("Announcement retrieval not yet implemented")
+        let announcements: Vec<String> = self.get("/v0/announcements")?.unwrap_or_default();
+        announcements.iter().map(|tlv| Self::parse_announcement(tlv)).collect()
     }
     
     /// Gets a specific announcement by event ID
     pub fn get_announcement(&self, event_id: &str) -> AnyaResult<Option<OracleAnnouncement>> {
-        // Implementation goes here
-        unimplemented!("Specific announcement retrieval not yet implemented")
+        let response: Option<AnnouncementResponse> = self.get(&format!("/v0/announcements/{}", event_id))?;
+        let announcement = match response {
+            Some(response) => Self::parse_announcement(&response.announcement)?,
+            None => return Ok(None),
+        };
+        if announcement.event_id != event_id {
+            return Err(format!("Oracle returned event {} for {}", announcement.event_id, event_id).into());
+        }
+        Ok(Some(announcement))
     }
     
     /// Gets an attestation for an event
+    ///
+    /// Check the result against the event's announcement with
+    /// [`OracleAttestation::verify`] before relying on it.
     pub fn get_attestation(&self, event_id: &str) -> AnyaResult<Option<OracleAttestation>> {
-        // Implementation goes here
-        unimplemented!("Attestation retrieval not yet implemented")
+        let response: Option<AttestationResponse> = self.get(&format!("/v0/attestations/{}", event_id))?;
+        let attestation = match response {
+            Some(response) => OracleAttestation::from_tlv(&decode_hex(&response.attestation)?)?,
+            None => return Ok(None),
+        };
+        if attestation.event_id != event_id {
+            return Err(format!("Oracle returned attestation for {} instead of {}", attestation.event_id, event_id).into());
+        }
+        Ok(Some(attestation))
+    }
+
+    fn parse_announcement(tlv: &str) -> AnyaResult<OracleAnnouncement> {
+        let announcement = OracleAnnouncement::from_tlv(&decode_hex(tlv)?)?;
+        if !announcement.verify_signature()? {
+            return Err(format!("Invalid signature on announcement for {}", announcement.event_id).into());
+        }
+        Ok(announcement)
+    }
+
+    /// GET a JSON document; `None` when the oracle answers 404
+    fn get<T: DeserializeOwned>(&self, path: &str) -> AnyaResult<Option<T>> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self.http.get(&url).send()
+            .map_err(|e| format!("Oracle request to {} failed: {}", url, e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()
+            .map_err(|e| format!("Oracle request to {} failed: {}", url, e))?;
+        response.json().map(Some)
+            .map_err(|e| format!("Invalid oracle response from {}: {}", url, e).into())
+    }
+}
+
+fn decode_hex(data: &str) -> AnyaResult<Vec<u8>> {
+    hex::decode(data).map_err(|e| format!("Invalid hex from oracle: {}", e).into())
+}
+
+/// Oracle signing announcements and attestations with a local key
+///
+/// Event nonces are derived from the oracle key and the event ID, and each
+/// event is attested at most once, so a nonce never signs two outcomes.
+pub struct LocalOracle {
+    secret_key: SecretKey,
+    oracle: Mutex<Oracle>,
+}
+
+impl LocalOracle {
+    /// Creates an oracle named `name` signing with `secret_key`
+    pub fn new(name: &str, secret_key: SecretKey) -> Self {
+        let secp = Secp256k1::signing_only();
+        let info = OracleInfo {
+            name: name.to_string(),
+            public_key: PublicKey::from_secret_key(&secp, &secret_key),
+            endpoint: String::new(),
+            properties: HashMap::new(),
+        };
+        Self {
+            secret_key,
+            oracle: Mutex::new(Oracle::new(info)),
+        }
+    }
+
+    /// The oracle's public key
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::signing_only(), &self.secret_key)
+    }
+
+    /// Oracle information
+    pub fn info(&self) -> AnyaResult<OracleInfo> {
+        Ok(self.lock()?.info.clone())
+    }
+
+    /// Announces an enumerated event, committing to its nonce
+    pub fn announce(
+        &self,
+        event_id: &str,
+        description: &str,
+        maturity_time: DateTime<Utc>,
+        outcomes: Vec<String>,
+    ) -> AnyaResult<OracleAnnouncement> {
+        if outcomes.is_empty() {
+            return Err("An event needs at least one outcome".into());
+        }
+        let announcement = OracleAnnouncement::new(
+            event_id.to_string(),
+            description.to_string(),
+            self.nonce_point(event_id, 0)?,
+            self.public_key(),
+            maturity_time,
+            Utc::now(),
+            outcomes,
+        );
+        self.publish(announcement)
+    }
+
+    /// Announces a numeric event, committing to one nonce per digit
+    pub fn announce_numeric(
+        &self,
+        event_id: &str,
+        description: &str,
+        maturity_time: DateTime<Utc>,
+        digit_decomposition: DigitDecomposition,
+    ) -> AnyaResult<OracleAnnouncement> {
+        let nonces = (0..digit_decomposition.nb_digits)
+            .map(|index| self.nonce_point(event_id, index))
+            .collect::<AnyaResult<Vec<_>>>()?;
+        let announcement = OracleAnnouncement::numeric(
+            event_id.to_string(),
+            description.to_string(),
+            nonces,
+            self.public_key(),
+            maturity_time,
+            Utc::now(),
+            digit_decomposition,
+        )?;
+        self.publish(announcement)
+    }
+
+    /// Attests to `outcome` of an announced enumerated event
+    pub fn attest(&self, event_id: &str, outcome: &str) -> AnyaResult<OracleAttestation> {
+        let mut oracle = self.lock()?;
+        let announcement = Self::announced(&oracle, event_id)?;
+        if announcement.digit_decomposition.is_some() || !announcement.outcomes.iter().any(|o| o == outcome) {
+            return Err(format!("Outcome '{}' is not announced for event {}", outcome, event_id).into());
+        }
+        if let Some(existing) = Self::attested(&oracle, event_id, outcome)? {
+            return Ok(existing);
+        }
+
+        let signature = sign_with_nonce(
+            &self.secret_key,
+            &self.nonce_secret(event_id, 0)?,
+            &OracleAnnouncement::outcome_message(outcome),
+        )?;
+        let mut attestation = OracleAttestation::new(event_id.to_string(), outcome.to_string(), signature, event_id.to_string());
+        attestation.oracle_public_key = Some(self.public_key());
+        oracle.add_attestation(attestation.clone());
+        Ok(attestation)
+    }
+
+    /// Attests to the value of an announced numeric event, signing each digit
+    pub fn attest_numeric(&self, event_id: &str, value: u64) -> AnyaResult<OracleAttestation> {
+        let mut oracle = self.lock()?;
+        let decomposition = Self::announced(&oracle, event_id)?.digit_decomposition.clone()
+            .ok_or_else(|| format!("Event {} is not numeric", event_id))?;
+        let digits = decomposition.decompose(value)?;
+        let outcome: String = digits.iter().map(|digit| digit.to_string()).collect();
+        if let Some(existing) = Self::attested(&oracle, event_id, &outcome)? {
+            return Ok(existing);
+        }
+
+        let signatures = digits.iter().enumerate()
+            .map(|(index, digit)| sign_with_nonce(
+                &self.secret_key,
+                &self.nonce_secret(event_id, index as u16)?,
+                &OracleAnnouncement::outcome_message(&digit.to_string()),
+            ))
+            .collect::<AnyaResult<Vec<_>>>()?;
+        let mut attestation = OracleAttestation::numeric(event_id.to_string(), &digits, signatures)?;
+        attestation.oracle_public_key = Some(self.public_key());
+        oracle.add_attestation(attestation.clone());
+        Ok(attestation)
+    }
+
+    /// All announcements made so far
+    pub fn announcements(&self) -> AnyaResult<Vec<OracleAnnouncement>> {
+        Ok(self.lock()?.announcements.clone())
+    }
+
+    /// The announcement of an event
+    pub fn announcement(&self, event_id: &str) -> AnyaResult<Option<OracleAnnouncement>> {
+        Ok(self.lock()?.get_announcement(event_id).cloned())
+    }
+
+    /// The attestation of an event, once made
+    pub fn attestation(&self, event_id: &str) -> AnyaResult<Option<OracleAttestation>> {
+        Ok(self.lock()?.get_attestation(event_id).cloned())
+    }
+
+    /// Sign and record a new announcement
+    fn publish(&self, mut announcement: OracleAnnouncement) -> AnyaResult<OracleAnnouncement> {
+        let mut oracle = self.lock()?;
+        if oracle.get_announcement(&announcement.event_id).is_some() {
+            return Err(format!("Event {} is already announced", announcement.event_id).into());
+        }
+        let secp = Secp256k1::signing_only();
+        let message = Message::from_digest(OracleAnnouncement::announcement_message(&announcement.to_event()));
+        announcement.signature = Some(secp.sign_schnorr_no_aux_rand(&message, &Keypair::from_secret_key(&secp, &self.secret_key)));
+        oracle.add_announcement(announcement.clone());
+        Ok(announcement)
+    }
+
+    fn announced<'a>(oracle: &'a Oracle, event_id: &str) -> AnyaResult<&'a OracleAnnouncement> {
+        oracle.get_announcement(event_id)
+            .ok_or_else(|| format!("Event {} was not announced", event_id).into())
+    }
+
+    /// An existing attestation of the event, which must be to `outcome`
+    fn attested(oracle: &Oracle, event_id: &str, outcome: &str) -> AnyaResult<Option<OracleAttestation>> {
+        match oracle.get_attestation(event_id) {
+            Some(existing) if existing.outcome != outcome => {
+                Err(format!("Event {} is already attested as '{}'", event_id, existing.outcome).into())
+            }
+            existing => Ok(existing.cloned()),
+        }
     }
-} 
\ No newline at end of file
+
+    /// Nonce for the value at `index` of an event
+    fn nonce_secret(&self, event_id: &str, index: u16) -> AnyaResult<SecretKey> {
+        reduce_scalar(tagged_hash("DLC/oracle/nonce", &[
+            &self.secret_key.secret_bytes(),
+            event_id.as_bytes(),
+            &index.to_be_bytes(),
+        ])).ok_or_else(|| "Oracle nonce reduced to zero".into())
+    }
+
+    fn nonce_point(&self, event_id: &str, index: u16) -> AnyaResult<PublicKey> {
+        Ok(PublicKey::from_secret_key(&Secp256k1::signing_only(), &self.nonce_secret(event_id, index)?))
+    }
+
+    fn lock(&self) -> AnyaResult<std::sync::MutexGuard<'_, Oracle>> {
+        self.oracle.lock().map_err(|_| "Oracle lock poisoned".into())
+    }
+}
+
+/// BIP340 signature over `message` with a fixed nonce
+fn sign_with_nonce(secret_key: &SecretKey, nonce: &SecretKey, message: &[u8; 32]) -> AnyaResult<Signature> {
+    let secp = Secp256k1::signing_only();
+    let even = |key: &SecretKey| match key.x_only_public_key(&secp).1 {
+        Parity::Even => *key,
+        Parity::Odd => key.negate(),
+    };
+    let (key, nonce) = (even(secret_key), even(nonce));
+    let nonce_point = PublicKey::from_secret_key(&secp, &nonce);
+    let challenge = bip340_challenge(&nonce_point, &PublicKey::from_secret_key(&secp, &key), message)?;
+    let s = key.mul_tweak(&Scalar::from(challenge))
+        .and_then(|weighted| weighted.add_tweak(&Scalar::from(nonce)))
+        .map_err(|e| format!("Signing failed: {}", e))?;
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&nonce_point.x_only_public_key().0.serialize());
+    signature[32..].copy_from_slice(&s.secret_bytes());
+    Signature::from_slice(&signature).map_err(|e| format!("Signing failed: {}", e).into())
+}
+
+impl AttestationSource for OracleClient {
+    fn attestation(&self, announcement: &OracleAnnouncement) -> AnyaResult<Option<OracleAttestation>> {
+        self.get_attestation(&announcement.event_id)
+    }
+}
+
+impl AttestationSource for LocalOracle {
+    fn attestation(&self, announcement: &OracleAnnouncement) -> AnyaResult<Option<OracleAttestation>> {
+        if announcement.public_key.x_only_public_key().0 != self.public_key().x_only_public_key().0 {
+            return Ok(None);
+        }
+        LocalOracle::attestation(self, &announcement.event_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use chrono::Duration;
+
+    fn oracle() -> LocalOracle {
+        LocalOracle::new("stub", SecretKey::from_slice(&[0x42; 32]).unwrap())
+    }
+
+    fn announce(oracle: &LocalOracle, event_id: &str) -> OracleAnnouncement {
+        let outcomes = vec!["up".to_string(), "down".to_string()];
+        oracle.announce(event_id, "BTC/USD direction", Utc::now() + Duration::hours(1), outcomes).unwrap()
+    }
+
+    /// Serve `oracle` over HTTP on a local port, returning its base URL
+    fn serve(oracle: Arc<LocalOracle>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond(&oracle, stream);
+            }
+        });
+        url
+    }
+
+    fn respond(oracle: &LocalOracle, mut stream: TcpStream) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut header = String::new();
+        while reader.read_line(&mut header).unwrap() > 2 {
+            header.clear();
+        }
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        let body = match segments.as_slice() {
+            ["v0", "info"] => Some(serde_json::json!({
+                "name": oracle.info().unwrap().name,
+                "public_key": oracle.public_key().to_string(),
+            })),
+            ["v0", "announcements"] => Some(serde_json::json!(oracle.announcements().unwrap().iter()
+                .map(|a| hex::encode(a.to_tlv().unwrap()))
+                .collect::<Vec<_>>())),
+            ["v0", "announcements", event_id] => oracle.announcement(event_id).unwrap()
+                .map(|a| serde_json::json!({ "announcement": hex::encode(a.to_tlv().unwrap()) })),
+            ["v0", "attestations", event_id] => oracle.attestation(event_id).unwrap()
+                .map(|a| serde_json::json!({ "attestation": hex::encode(a.to_tlv().unwrap()) })),
+            _ => None,
+        };
+        let (status, body) = match body {
+            Some(body) => ("200 OK", body.to_string()),
+            None => ("404 Not Found", String::new()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), body,
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_announcement_tlv_and_signature() {
+        let oracle = oracle();
+        let announcement = announce(&oracle, "btc-direction");
+        assert!(announcement.verify_signature().unwrap());
+
+        let parsed = OracleAnnouncement::from_tlv(&announcement.to_tlv().unwrap()).unwrap();
+        assert!(parsed.verify_signature().unwrap());
+        assert_eq!(parsed.to_event(), announcement.to_event());
+        assert_eq!(parsed.anticipation_point("up").unwrap(), announcement.anticipation_point("up").unwrap());
+
+        // The signature commits to the nonce, outcomes and oracle key
+        let mut tampered = parsed.clone();
+        tampered.nonces = vec![tampered.public_key];
+        assert!(!tampered.verify_signature().unwrap());
+        let mut tampered = parsed.clone();
+        tampered.outcomes.push("flat".to_string());
+        assert!(!tampered.verify_signature().unwrap());
+        let mut unsigned = parsed;
+        unsigned.signature = None;
+        assert!(!unsigned.verify_signature().unwrap());
+        assert!(unsigned.to_tlv().is_err());
+    }
+
+    #[test]
+    fn test_attestation_reveals_anticipation_point() {
+        let oracle = oracle();
+        let announcement = announce(&oracle, "btc-direction");
+        let attestation = oracle.attest("btc-direction", "down").unwrap();
+
+        let secp = Secp256k1::new();
+        let message = Message::from_digest(OracleAnnouncement::outcome_message("down"));
+        let oracle_key = oracle.public_key().x_only_public_key().0;
+        assert!(secp.verify_schnorr(&attestation.signature, &message, &oracle_key).is_ok());
+        assert_eq!(
+            PublicKey::from_secret_key(&secp, &attestation.attestation_scalar().unwrap()),
+            announcement.anticipation_point("down").unwrap(),
+        );
+
+        let parsed = OracleAttestation::from_tlv(&attestation.to_tlv().unwrap()).unwrap();
+        assert!(parsed.verify(&announcement).unwrap());
+        assert_eq!(parsed.outcome, "down");
+
+        // One outcome per event; other oracles' attestations do not verify
+        assert!(oracle.attest("btc-direction", "up").is_err());
+        assert_eq!(oracle.attest("btc-direction", "down").unwrap().signature, attestation.signature);
+        let other = LocalOracle::new("other", SecretKey::from_slice(&[0x43; 32]).unwrap());
+        announce(&other, "btc-direction");
+        assert!(!other.attest("btc-direction", "down").unwrap().verify(&announcement).unwrap());
+    }
+
+    #[test]
+    fn test_client_against_stub_oracle() {
+        let oracle = Arc::new(oracle());
+        let announcement = announce(&oracle, "btc-direction");
+        announce(&oracle, "eth-direction");
+        let client = OracleClient::new(&format!("{}/", serve(oracle.clone())));
+
+        let info = client.get_oracle_info().unwrap();
+        assert_eq!(info.name, "stub");
+        assert_eq!(info.public_key, oracle.public_key());
+        assert_eq!(client.get_announcements().unwrap().len(), 2);
+
+        let fetched = client.get_announcement("btc-direction").unwrap().unwrap();
+        assert_eq!(fetched.to_event(), announcement.to_event());
+        assert!(client.get_announcement("unknown").unwrap().is_none());
+        assert!(client.get_attestation("btc-direction").unwrap().is_none());
+
+        oracle.attest("btc-direction", "up").unwrap();
+        let attestation = client.get_attestation("btc-direction").unwrap().unwrap();
+        assert!(attestation.verify(&fetched).unwrap());
+    }
+
+    #[test]
+    fn test_numeric_announcement_and_attestation() {
+        let oracle = oracle();
+        let decomposition = DigitDecomposition::new(10, 3, "usd").unwrap();
+        let announcement = oracle.announce_numeric("btc-usd", "BTC/USD", Utc::now(), decomposition.clone()).unwrap();
+        assert_eq!(announcement.nonces.len(), 3);
+        assert!(announcement.anticipation_point("1").is_err());
+
+        let parsed = OracleAnnouncement::from_tlv(&announcement.to_tlv().unwrap()).unwrap();
+        assert!(parsed.verify_signature().unwrap());
+        assert_eq!(parsed.digit_decomposition, Some(decomposition));
+        let x_only = |nonces: &[PublicKey]| nonces.iter().map(|nonce| nonce.x_only_public_key().0).collect::<Vec<_>>();
+        assert_eq!(x_only(&parsed.nonces), x_only(&announcement.nonces));
+
+        // Each digit signature reveals that digit's anticipation point
+        let attestation = oracle.attest_numeric("btc-usd", 42).unwrap();
+        assert_eq!(attestation.digits().unwrap(), vec![0, 4, 2]);
+        let secp = Secp256k1::new();
+        for (index, (digit, scalar)) in [0, 4, 2].iter().zip(attestation.digit_scalars().unwrap()).enumerate() {
+            assert_eq!(
+                PublicKey::from_secret_key(&secp, &scalar),
+                announcement.digit_anticipation_point(index, *digit).unwrap(),
+            );
+        }
+        let parsed = OracleAttestation::from_tlv(&attestation.to_tlv().unwrap()).unwrap();
+        assert!(parsed.verify(&announcement).unwrap());
+
+        let mut forged = parsed;
+        forged.outcome = "043".to_string();
+        assert!(!forged.verify(&announcement).unwrap());
+        assert!(oracle.attest_numeric("btc-usd", 1000).is_err());
+        assert!(oracle.attest("btc-usd", "42").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+// src/bitcoin/dlc/storage.rs
+
+//! Persistence of contracts and their executions
+//!
+//! A stored contract holds everything needed to settle it after a restart:
+//! the negotiated messages with both parties' adaptor signatures, the
+//! funding, CET and refund transactions, and its state.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::AnyaResult;
+use super::contract::Contract;
+use super::execution::ExecutionRecord;
+
+/// Storage of contracts and execution records
+pub trait DLCStore: Send + Sync {
+    /// Inserts or replaces a contract
+    fn save_contract(&self, contract: &Contract) -> AnyaResult<()>;
+
+    /// The contract with this ID
+    fn get_contract(&self, contract_id: &str) -> AnyaResult<Option<Contract>>;
+
+    /// Every stored contract, oldest first
+    fn list_contracts(&self) -> AnyaResult<Vec<Contract>>;
+
+    /// Inserts or replaces an execution record
+    fn save_execution(&self, record: &ExecutionRecord) -> AnyaResult<()>;
+
+    /// Execution records of a contract, oldest first
+    fn get_executions(&self, contract_id: &str) -> AnyaResult<Vec<ExecutionRecord>>;
+}
+
+/// Inserts `record` into `records`, replacing the one with the same ID
+fn upsert(records: &mut Vec<ExecutionRecord>, record: &ExecutionRecord) {
+    match records.iter_mut().find(|existing| existing.id == record.id) {
+        Some(existing) => *existing = record.clone(),
+        None => records.push(record.clone()),
+    }
+}
+
+/// Store kept in memory and lost on restart
+#[derive(Default)]
+pub struct InMemoryDLCStore {
+    contracts: Mutex<HashMap<String, Contract>>,
+    executions: Mutex<HashMap<String, Vec<ExecutionRecord>>>,
+}
+
+impl InMemoryDLCStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DLCStore for InMemoryDLCStore {
+    fn save_contract(&self, contract: &Contract) -> AnyaResult<()> {
+        self.contracts.lock()
+            .map_err(|_| "Contract store lock poisoned")?
+            .insert(contract.id.clone(), contract.clone());
+        Ok(())
+    }
+
+    fn get_contract(&self, contract_id: &str) -> AnyaResult<Option<Contract>> {
+        let contracts = self.contracts.lock().map_err(|_| "Contract store lock poisoned")?;
+        Ok(contracts.get(contract_id).cloned())
+    }
+
+    fn list_contracts(&self) -> AnyaResult<Vec<Contract>> {
+        let contracts = self.contracts.lock().map_err(|_| "Contract store lock poisoned")?;
+        let mut contracts: Vec<Contract> = contracts.values().cloned().collect();
+        contracts.sort_by_key(|contract| contract.created_at);
+        Ok(contracts)
+    }
+
+    fn save_execution(&self, record: &ExecutionRecord) -> AnyaResult<()> {
+        let mut executions = self.executions.lock().map_err(|_| "Execution store lock poisoned")?;
+        upsert(executions.entry(record.contract_id.clone()).or_default(), record);
+        Ok(())
+    }
+
+    fn get_executions(&self, contract_id: &str) -> AnyaResult<Vec<ExecutionRecord>> {
+        let executions = self.executions.lock().map_err(|_| "Execution store lock poisoned")?;
+        Ok(executions.get(contract_id).cloned().unwrap_or_default())
+    }
+}
+
+/// Store writing one JSON file per contract under a directory
+///
+/// Contracts live in `contracts/<id>.json` and their execution records in
+/// `executions/<id>.json`. Files are written to a temporary file and renamed
+/// into place, so a crash never leaves a partially written contract.
+pub struct FileDLCStore {
+    dir: PathBuf,
+    // Serializes read-modify-write of execution files
+    lock: Mutex<()>,
+}
+
+impl FileDLCStore {
+    /// Opens the store in `dir`, creating it if needed
+    pub fn open(dir: impl AsRef<Path>) -> AnyaResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        for subdir in ["contracts", "executions"] {
+            fs::create_dir_all(dir.join(subdir))
+                .map_err(|e| format!("Failed to create {}: {}", dir.join(subdir).display(), e))?;
+        }
+        Ok(Self { dir, lock: Mutex::new(()) })
+    }
+
+    fn path(&self, kind: &str, id: &str) -> AnyaResult<PathBuf> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid contract ID '{}'", id).into());
+        }
+        Ok(self.dir.join(kind).join(format!("{}.json", id)))
+    }
+
+    fn write<T: Serialize + ?Sized>(&self, path: &Path, value: &T) -> AnyaResult<()> {
+        let json = serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, json).map_err(|e| format!("Failed to write {}: {}", temporary.display(), e))?;
+        fs::rename(&temporary, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e).into())
+    }
+
+    fn read<T: DeserializeOwned>(&self, path: &Path) -> AnyaResult<Option<T>> {
+        let json = match fs::read(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+        };
+        serde_json::from_slice(&json)
+            .map(Some)
+            .map_err(|e| format!("Corrupt {}: {}", path.display(), e).into())
+    }
+}
+
+impl DLCStore for FileDLCStore {
+    fn save_contract(&self, contract: &Contract) -> AnyaResult<()> {
+        self.write(&self.path("contracts", &contract.id)?, contract)
+    }
+
+    fn get_contract(&self, contract_id: &str) -> AnyaResult<Option<Contract>> {
+        self.read(&self.path("contracts", contract_id)?)
+    }
+
+    fn list_contracts(&self) -> AnyaResult<Vec<Contract>> {
+        let dir = self.dir.join("contracts");
+        let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        let mut contracts = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?.path();
+            if path.extension().and_then(|extension| extension.to_str()) == Some("json") {
+                contracts.extend(self.read::<Contract>(&path)?);
+            }
+        }
+        contracts.sort_by_key(|contract| contract.created_at);
+        Ok(contracts)
+    }
+
+    fn save_execution(&self, record: &ExecutionRecord) -> AnyaResult<()> {
+        let _guard = self.lock.lock().map_err(|_| "Execution store lock poisoned")?;
+        let path = self.path("executions", &record.contract_id)?;
+        let mut records: Vec<ExecutionRecord> = self.read(&path)?.unwrap_or_default();
+        upsert(&mut records, record);
+        self.write(&path, &records)
+    }
+
+    fn get_executions(&self, contract_id: &str) -> AnyaResult<Vec<ExecutionRecord>> {
+        Ok(self.read(&self.path("executions", contract_id)?)?.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use crate::bitcoin::dlc::contract::{ContractDescriptor, ContractState, PayoutFunction};
+    use crate::bitcoin::dlc::execution::ExecutionStatus;
+
+    fn contract() -> Contract {
+        let secp = Secp256k1::new();
+        let key = PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        Contract::new(ContractDescriptor {
+            title: "stored".to_string(),
+            description: String::new(),
+            offer_public_key: key,
+            offer_collateral: 60_000,
+            accept_public_key: None,
+            accept_collateral: 40_000,
+            fee_rate: 2.0,
+            refund_locktime: 800_000,
+            payout_function: PayoutFunction::Enumerated { outcomes: HashMap::from([("yes".to_string(), (100_000, 0))]) },
+            oracle_info: Vec::new(),
+            oracle_threshold: 1,
+            bounded_error: None,
+        })
+    }
+
+    #[test]
+    fn test_file_store_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut contract = contract();
+        let mut record = ExecutionRecord::new(contract.id.clone());
+        {
+            let store = FileDLCStore::open(dir.path()).unwrap();
+            store.save_contract(&contract).unwrap();
+            contract.update_state(ContractState::Funded);
+            store.save_contract(&contract).unwrap();
+            store.save_execution(&record).unwrap();
+            record.update_status(ExecutionStatus::Refunded);
+            store.save_execution(&record).unwrap();
+            store.save_execution(&ExecutionRecord::new(contract.id.clone())).unwrap();
+        }
+
+        let store = FileDLCStore::open(dir.path()).unwrap();
+        let stored = store.get_contract(&contract.id).unwrap().unwrap();
+        assert_eq!(stored.state, ContractState::Funded);
+        assert_eq!(stored.descriptor.offer_public_key, contract.descriptor.offer_public_key);
+        assert_eq!(store.list_contracts().unwrap().len(), 1);
+        let executions = store.get_executions(&contract.id).unwrap();
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].status, ExecutionStatus::Refunded);
+
+        assert!(store.get_contract("unknown").unwrap().is_none());
+        assert!(store.get_contract("../escape").is_err());
+    }
+}
@@ -0,0 +1,537 @@
+// src/bitcoin/dlc/transactions.rs
+
+//! DLC transaction construction
+//!
+//! Builds the funding transaction, contract execution transactions (CETs) and
+//! refund transaction following the dlcspecs transaction and fee rules:
+//!
+//! - the funding output is a P2WSH 2-of-2 multisig over both funding keys,
+//!   sorted lexicographically
+//! - inputs and outputs are ordered by the parties' serial IDs
+//! - each party pays for its own inputs, change and payout outputs plus half
+//!   of the shared base weight, at the offered fee rate
+//! - outputs below the dust limit are dropped
+//!
+//! A contract inside a Lightning channel is funded by a split transaction
+//! instead, which divides the channel funding output into a new channel
+//! output and the contract's funding output.
+
+use bitcoin::absolute::LockTime;
+use bitcoin::ecdsa;
+use bitcoin::hashes::Hash;
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::{Message, PublicKey};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::transaction::Version;
+use bitcoin::{opcodes, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+use serde::{Deserialize, Serialize};
+
+use crate::AnyaResult;
+use super::messages::FundingInput;
+
+/// Weight of the funding transaction shared by both parties
+pub const FUND_TX_BASE_WEIGHT: u64 = 214;
+
+/// Weight of a CET excluding payout outputs, shared by both parties
+pub const CET_BASE_WEIGHT: u64 = 500;
+
+/// Weight of an input excluding its script sig and witness: (36 + 4 + 1) * 4
+pub const TX_INPUT_BASE_WEIGHT: u64 = 164;
+
+/// Witness size of a P2WPKH input
+pub const P2WPKH_WITNESS_SIZE: u16 = 107;
+
+/// Weight of a split transaction: one 2-of-2 input and two P2WSH outputs
+pub const SPLIT_TX_WEIGHT: u64 = 772;
+
+/// Outputs below this value are not created
+pub const DUST_LIMIT: u64 = 1000;
+
+/// Sequence used by CET and refund inputs so their locktimes are enforced
+pub const ENABLE_LOCKTIME: Sequence = Sequence(0xffff_fffe);
+
+/// One party's contribution to a contract
+#[derive(Debug, Clone)]
+pub struct PartyParams {
+    /// Key in the 2-of-2 funding output
+    pub funding_pubkey: PublicKey,
+
+    /// Script receiving the party's CET and refund payouts
+    pub payout_script: ScriptBuf,
+
+    /// Serial ID ordering the payout output
+    pub payout_serial_id: u64,
+
+    /// Script receiving the party's funding change
+    pub change_script: ScriptBuf,
+
+    /// Serial ID ordering the change output
+    pub change_serial_id: u64,
+
+    /// UTXOs funding the party's collateral and fees
+    pub inputs: Vec<FundingInput>,
+
+    /// Collateral in satoshis
+    pub collateral: u64,
+}
+
+impl PartyParams {
+    /// Total value of the party's inputs
+    pub fn input_amount(&self) -> AnyaResult<u64> {
+        self.inputs.iter().map(FundingInput::value).sum()
+    }
+
+    /// Funding and CET fees paid by this party at `fee_rate_per_vb`
+    pub fn fees(&self, fee_rate_per_vb: u64) -> (u64, u64) {
+        let inputs_weight: u64 = self.inputs.iter().map(input_weight).sum();
+        let fund_weight = FUND_TX_BASE_WEIGHT / 2 + inputs_weight + output_weight(&self.change_script);
+        let cet_weight = CET_BASE_WEIGHT / 2 + output_weight(&self.payout_script);
+        (weight_to_fee(fund_weight, fee_rate_per_vb), weight_to_fee(cet_weight, fee_rate_per_vb))
+    }
+
+    /// Amount the party's inputs must cover before paying for the inputs themselves
+    pub fn required_amount(&self, fee_rate_per_vb: u64) -> u64 {
+        let (fund_fee, cet_fee) = self.fees(fee_rate_per_vb);
+        self.collateral + fund_fee + cet_fee
+    }
+}
+
+/// Weight an input adds to the funding transaction
+pub fn input_weight(input: &FundingInput) -> u64 {
+    let redeem_len = input.redeem_script.len() as u64;
+    // A P2SH script sig pushes the redeem script
+    let script_sig_len = if redeem_len > 0 { redeem_len + 1 } else { 0 };
+    TX_INPUT_BASE_WEIGHT + 4 * script_sig_len + input.max_witness_len as u64
+}
+
+/// Weight of an output paying to `script`: (8 + 1 + len) * 4
+pub fn output_weight(script: &ScriptBuf) -> u64 {
+    36 + 4 * script.len() as u64
+}
+
+/// Fee in satoshis for `weight` at `fee_rate_per_vb`, rounded up
+pub fn weight_to_fee(weight: u64, fee_rate_per_vb: u64) -> u64 {
+    (weight * fee_rate_per_vb).div_ceil(4)
+}
+
+/// 2-of-2 multisig over the funding keys, sorted lexicographically
+pub fn funding_script(first: &PublicKey, second: &PublicKey) -> ScriptBuf {
+    let (a, b) = sorted_keys(first, second);
+    Builder::new()
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_slice(a.serialize())
+        .push_slice(b.serialize())
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+        .into_script()
+}
+
+fn sorted_keys<'a>(first: &'a PublicKey, second: &'a PublicKey) -> (&'a PublicKey, &'a PublicKey) {
+    if first.serialize() <= second.serialize() { (first, second) } else { (second, first) }
+}
+
+/// Witness spending the funding output with both parties' signatures
+pub fn funding_witness(
+    funding_script: &ScriptBuf,
+    first: (&PublicKey, &Signature),
+    second: (&PublicKey, &Signature),
+) -> Witness {
+    let (a, b) = if first.0.serialize() <= second.0.serialize() { (first, second) } else { (second, first) };
+    let mut witness = Witness::new();
+    witness.push([]);
+    witness.push(ecdsa::Signature::sighash_all(*a.1).to_vec());
+    witness.push(ecdsa::Signature::sighash_all(*b.1).to_vec());
+    witness.push(funding_script.as_bytes());
+    witness
+}
+
+/// Final contract ID: funding txid XOR temporary ID, with the funding output
+/// index XORed into the last two bytes
+pub fn contract_id(funding: &Transaction, fund_output_index: u16, temporary_contract_id: &[u8; 32]) -> [u8; 32] {
+    let txid = funding.compute_txid().to_byte_array();
+    let mut id = [0u8; 32];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = txid[i] ^ temporary_contract_id[i];
+    }
+    let index = fund_output_index.to_be_bytes();
+    id[30] ^= index[0];
+    id[31] ^= index[1];
+    id
+}
+
+/// The transactions of a contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DlcTransactions {
+    /// Funding transaction (unsigned until both parties sign)
+    pub fund: Transaction,
+
+    /// One CET per outcome, in outcome order
+    pub cets: Vec<Transaction>,
+
+    /// Refund transaction
+    pub refund: Transaction,
+
+    /// Witness script of the funding output
+    pub funding_script: ScriptBuf,
+
+    /// Index of the funding output in `fund`
+    pub fund_output_index: usize,
+}
+
+impl DlcTransactions {
+    /// The funding output being spent by CETs and the refund
+    pub fn funding_outpoint(&self) -> OutPoint {
+        OutPoint::new(self.fund.compute_txid(), self.fund_output_index as u32)
+    }
+
+    /// Value of the funding output in satoshis
+    pub fn fund_value(&self) -> u64 {
+        self.fund.output[self.fund_output_index].value.to_sat()
+    }
+
+    /// Signature hash of the CET for outcome `index`
+    pub fn cet_sighash(&self, index: usize) -> AnyaResult<Message> {
+        let cet = self.cets.get(index).ok_or_else(|| format!("No CET for outcome {}", index))?;
+        self.funding_sighash(cet)
+    }
+
+    /// Signature hash of the refund transaction
+    pub fn refund_sighash(&self) -> AnyaResult<Message> {
+        self.funding_sighash(&self.refund)
+    }
+
+    fn funding_sighash(&self, transaction: &Transaction) -> AnyaResult<Message> {
+        let sighash = SighashCache::new(transaction)
+            .p2wsh_signature_hash(0, &self.funding_script, Amount::from_sat(self.fund_value()), EcdsaSighashType::All)
+            .map_err(|e| format!("Failed to compute signature hash: {}", e))?;
+        Ok(Message::from_digest(sighash.to_byte_array()))
+    }
+}
+
+/// Terms shared by both parties' transactions
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionTerms {
+    /// Serial ID ordering the funding output
+    pub fund_output_serial_id: u64,
+
+    /// Fee rate in sat/vbyte
+    pub fee_rate_per_vb: u64,
+
+    /// Locktime of every CET
+    pub cet_locktime: u32,
+
+    /// Locktime of the refund transaction
+    pub refund_locktime: u32,
+}
+
+/// Build the funding, CET and refund transactions
+///
+/// `payouts` holds `(offer_payout, accept_payout)` per outcome; each pair must
+/// add up to the total collateral.
+pub fn build_dlc_transactions(
+    offer: &PartyParams,
+    accept: &PartyParams,
+    payouts: &[(u64, u64)],
+    terms: &TransactionTerms,
+) -> AnyaResult<DlcTransactions> {
+    let total_collateral = check_payouts(offer, accept, payouts)?;
+
+    let (offer_fund_fee, offer_cet_fee) = offer.fees(terms.fee_rate_per_vb);
+    let (accept_fund_fee, accept_cet_fee) = accept.fees(terms.fee_rate_per_vb);
+    let fund_value = total_collateral + offer_cet_fee + accept_cet_fee;
+
+    let change = |party: &PartyParams, name: &str, fund_fee: u64, cet_fee: u64| -> AnyaResult<u64> {
+        let available = party.input_amount()?;
+        let needed = party.collateral + fund_fee + cet_fee;
+        available.checked_sub(needed).ok_or_else(|| format!(
+            "{} inputs of {} sats do not cover collateral and fees of {} sats", name, available, needed
+        ).into())
+    };
+    let offer_change = change(offer, "Offer", offer_fund_fee, offer_cet_fee)?;
+    let accept_change = change(accept, "Accept", accept_fund_fee, accept_cet_fee)?;
+
+    let funding_script = funding_script(&offer.funding_pubkey, &accept.funding_pubkey);
+
+    let mut inputs: Vec<&FundingInput> = offer.inputs.iter().chain(&accept.inputs).collect();
+    inputs.sort_by_key(|input| input.input_serial_id);
+    let mut fund_outputs = vec![(terms.fund_output_serial_id, TxOut {
+        value: Amount::from_sat(fund_value),
+        script_pubkey: funding_script.to_p2wsh(),
+    })];
+    for (party, value) in [(offer, offer_change), (accept, accept_change)] {
+        if value >= DUST_LIMIT {
+            fund_outputs.push((party.change_serial_id, TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: party.change_script.clone(),
+            }));
+        }
+    }
+    fund_outputs.sort_by_key(|(serial_id, _)| *serial_id);
+    let fund_output_index = fund_outputs.iter()
+        .position(|(serial_id, _)| *serial_id == terms.fund_output_serial_id)
+        .expect("funding output present");
+
+    let fund = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: inputs.iter().map(|input| TxIn {
+            previous_output: OutPoint::new(input.prev_tx.compute_txid(), input.prev_tx_vout),
+            script_sig: if input.redeem_script.is_empty() {
+                ScriptBuf::new()
+            } else {
+                let push = bitcoin::script::PushBytesBuf::try_from(input.redeem_script.to_bytes())
+                    .expect("redeem script fits a push");
+                Builder::new().push_slice(push).into_script()
+            },
+            sequence: Sequence(input.sequence),
+            witness: Witness::new(),
+        }).collect(),
+        output: fund_outputs.into_iter().map(|(_, output)| output).collect(),
+    };
+
+    let fund_outpoint = OutPoint::new(fund.compute_txid(), fund_output_index as u32);
+    let (cets, refund) = settlement_transactions(fund_outpoint, offer, accept, payouts, terms);
+
+    Ok(DlcTransactions { fund, cets, refund, funding_script, fund_output_index })
+}
+
+/// The channel output a split transaction spends
+#[derive(Debug, Clone)]
+pub struct SplitInput {
+    /// Funding output of the channel
+    pub outpoint: OutPoint,
+
+    /// Value of the channel funding output
+    pub value: u64,
+
+    /// 2-of-2 witness script of the channel funding output, which the
+    /// channel keeps using after the split
+    pub funding_script: ScriptBuf,
+}
+
+/// Fee of a split transaction at `fee_rate_per_vb`, as the offer and accept
+/// party's shares
+pub fn split_fees(fee_rate_per_vb: u64) -> (u64, u64) {
+    let fee = weight_to_fee(SPLIT_TX_WEIGHT, fee_rate_per_vb);
+    (fee - fee / 2, fee / 2)
+}
+
+/// Build the split, CET and refund transactions of a contract inside a channel
+///
+/// The split transaction takes the place of the funding transaction: it
+/// spends the channel funding output into a new channel output (index 0)
+/// and the contract's 2-of-2 funding output (index 1). Collateral and fees
+/// come out of the channel, so neither party contributes inputs.
+pub fn build_split_transactions(
+    channel: &SplitInput,
+    offer: &PartyParams,
+    accept: &PartyParams,
+    payouts: &[(u64, u64)],
+    terms: &TransactionTerms,
+) -> AnyaResult<DlcTransactions> {
+    let total_collateral = check_payouts(offer, accept, payouts)?;
+    let (_, offer_cet_fee) = offer.fees(terms.fee_rate_per_vb);
+    let (_, accept_cet_fee) = accept.fees(terms.fee_rate_per_vb);
+    let fund_value = total_collateral + offer_cet_fee + accept_cet_fee;
+    let split_fee = weight_to_fee(SPLIT_TX_WEIGHT, terms.fee_rate_per_vb);
+    let channel_value = channel.value.checked_sub(fund_value + split_fee)
+        .filter(|value| *value >= DUST_LIMIT)
+        .ok_or_else(|| format!(
+            "Channel of {} sats cannot hold a contract of {} sats and a {} sat split fee",
+            channel.value, fund_value, split_fee
+        ))?;
+
+    let funding_script = funding_script(&offer.funding_pubkey, &accept.funding_pubkey);
+    let fund = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: channel.outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output: vec![
+            TxOut { value: Amount::from_sat(channel_value), script_pubkey: channel.funding_script.to_p2wsh() },
+            TxOut { value: Amount::from_sat(fund_value), script_pubkey: funding_script.to_p2wsh() },
+        ],
+    };
+    let fund_output_index = 1;
+    let fund_outpoint = OutPoint::new(fund.compute_txid(), fund_output_index as u32);
+    let (cets, refund) = settlement_transactions(fund_outpoint, offer, accept, payouts, terms);
+
+    Ok(DlcTransactions { fund, cets, refund, funding_script, fund_output_index })
+}
+
+/// Signature hash of a split transaction spending `channel`
+pub fn split_sighash(split: &Transaction, channel: &SplitInput) -> AnyaResult<Message> {
+    let sighash = SighashCache::new(split)
+        .p2wsh_signature_hash(0, &channel.funding_script, Amount::from_sat(channel.value), EcdsaSighashType::All)
+        .map_err(|e| format!("Failed to compute signature hash: {}", e))?;
+    Ok(Message::from_digest(sighash.to_byte_array()))
+}
+
+/// Check every payout adds up to the total collateral, which is returned
+fn check_payouts(offer: &PartyParams, accept: &PartyParams, payouts: &[(u64, u64)]) -> AnyaResult<u64> {
+    let total_collateral = offer.collateral + accept.collateral;
+    if let Some((offer_payout, accept_payout)) = payouts.iter().find(|(o, a)| o + a != total_collateral) {
+        return Err(format!(
+            "Payout {} + {} does not match the total collateral {}",
+            offer_payout, accept_payout, total_collateral
+        ).into());
+    }
+    Ok(total_collateral)
+}
+
+/// CETs and refund transaction spending the contract's funding output
+fn settlement_transactions(
+    fund_outpoint: OutPoint,
+    offer: &PartyParams,
+    accept: &PartyParams,
+    payouts: &[(u64, u64)],
+    terms: &TransactionTerms,
+) -> (Vec<Transaction>, Transaction) {
+    let funding_input = TxIn {
+        previous_output: fund_outpoint,
+        script_sig: ScriptBuf::new(),
+        sequence: ENABLE_LOCKTIME,
+        witness: Witness::new(),
+    };
+    let spend = |offer_value: u64, accept_value: u64, locktime: u32| {
+        let mut outputs: Vec<(u64, TxOut)> = [(offer, offer_value), (accept, accept_value)].into_iter()
+            .filter(|(_, value)| *value >= DUST_LIMIT)
+            .map(|(party, value)| (party.payout_serial_id, TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: party.payout_script.clone(),
+            }))
+            .collect();
+        outputs.sort_by_key(|(serial_id, _)| *serial_id);
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::from_consensus(locktime),
+            input: vec![funding_input.clone()],
+            output: outputs.into_iter().map(|(_, output)| output).collect(),
+        }
+    };
+
+    let cets = payouts.iter()
+        .map(|(offer_payout, accept_payout)| spend(*offer_payout, *accept_payout, terms.cet_locktime))
+        .collect();
+    let refund = spend(offer.collateral, accept.collateral, terms.refund_locktime);
+    (cets, refund)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+    fn party(seed: u8, collateral: u64, input_value: u64, serial_base: u64) -> PartyParams {
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[seed; 32]).unwrap();
+        let pubkey = PublicKey::from_secret_key(&secp, &key);
+        let script = ScriptBuf::new_p2wpkh(&bitcoin::PublicKey::new(pubkey).wpubkey_hash().unwrap());
+        let prev_tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![TxOut { value: Amount::from_sat(input_value), script_pubkey: script.clone() }],
+        };
+        PartyParams {
+            funding_pubkey: pubkey,
+            payout_script: script.clone(),
+            payout_serial_id: serial_base + 1,
+            change_script: script,
+            change_serial_id: serial_base + 2,
+            inputs: vec![FundingInput {
+                input_serial_id: serial_base,
+                prev_tx,
+                prev_tx_vout: 0,
+                sequence: 0xffff_ffff,
+                max_witness_len: P2WPKH_WITNESS_SIZE,
+                redeem_script: ScriptBuf::new(),
+            }],
+            collateral,
+        }
+    }
+
+    #[test]
+    fn test_dlc_transactions() {
+        let offer = party(1, 60_000, 100_000, 10);
+        let accept = party(2, 40_000, 50_000, 5);
+        let terms = TransactionTerms { fund_output_serial_id: 8, fee_rate_per_vb: 2, cet_locktime: 100, refund_locktime: 200 };
+        let txs = build_dlc_transactions(&offer, &accept, &[(100_000, 0), (0, 100_000), (50_000, 50_000)], &terms).unwrap();
+
+        // Inputs and outputs follow the serial IDs: accept input (5) before offer
+        // input (10), accept change (7) before the funding output (8) and offer change (12)
+        assert_eq!(txs.fund.input[0].previous_output.txid, accept.inputs[0].prev_tx.compute_txid());
+        assert_eq!(txs.fund_output_index, 1);
+        assert_eq!(txs.fund.output[txs.fund_output_index].script_pubkey, txs.funding_script.to_p2wsh());
+
+        let (offer_fund_fee, offer_cet_fee) = offer.fees(2);
+        let (accept_fund_fee, accept_cet_fee) = accept.fees(2);
+        assert_eq!(txs.fund_value(), 100_000 + offer_cet_fee + accept_cet_fee);
+        let outputs: u64 = txs.fund.output.iter().map(|o| o.value.to_sat()).sum();
+        assert_eq!(150_000 - outputs, offer_fund_fee + accept_fund_fee);
+
+        // One-sided CETs drop the zero output; all spend the funding output
+        assert_eq!(txs.cets[0].output.len(), 1);
+        assert_eq!(txs.cets[2].output.len(), 2);
+        for cet in &txs.cets {
+            assert_eq!(cet.input[0].previous_output, txs.funding_outpoint());
+            assert_eq!(cet.lock_time, LockTime::from_consensus(100));
+        }
+        assert_eq!(txs.refund.lock_time, LockTime::from_consensus(200));
+        assert_eq!(txs.refund.output.iter().map(|o| o.value.to_sat()).sum::<u64>(), 100_000);
+
+        // Underfunded parties and mismatched payouts are rejected
+        let poor = party(2, 40_000, 40_100, 5);
+        assert!(build_dlc_transactions(&offer, &poor, &[(100_000, 0)], &terms).is_err());
+        assert!(build_dlc_transactions(&offer, &accept, &[(90_000, 0)], &terms).is_err());
+    }
+
+    #[test]
+    fn test_split_transactions() {
+        let mut offer = party(1, 60_000, 0, 10);
+        let mut accept = party(2, 40_000, 0, 5);
+        offer.inputs.clear();
+        accept.inputs.clear();
+        let channel = SplitInput {
+            outpoint: OutPoint::new(bitcoin::Txid::from_byte_array([7; 32]), 1),
+            value: 500_000,
+            funding_script: funding_script(&party(3, 0, 0, 0).funding_pubkey, &party(4, 0, 0, 0).funding_pubkey),
+        };
+        let terms = TransactionTerms { fund_output_serial_id: 8, fee_rate_per_vb: 2, cet_locktime: 100, refund_locktime: 200 };
+        let txs = build_split_transactions(&channel, &offer, &accept, &[(100_000, 0), (30_000, 70_000)], &terms).unwrap();
+
+        // The split keeps the channel in its first output and funds the contract from the second
+        assert_eq!(txs.fund.input[0].previous_output, channel.outpoint);
+        assert_eq!(txs.fund.output[0].script_pubkey, channel.funding_script.to_p2wsh());
+        assert_eq!(txs.fund_output_index, 1);
+        let (_, offer_cet_fee) = offer.fees(2);
+        let (_, accept_cet_fee) = accept.fees(2);
+        assert_eq!(txs.fund_value(), 100_000 + offer_cet_fee + accept_cet_fee);
+        let (offer_split_fee, accept_split_fee) = split_fees(2);
+        let outputs: u64 = txs.fund.output.iter().map(|o| o.value.to_sat()).sum();
+        assert_eq!(500_000 - outputs, offer_split_fee + accept_split_fee);
+        for cet in &txs.cets {
+            assert_eq!(cet.input[0].previous_output, txs.funding_outpoint());
+        }
+        assert!(split_sighash(&txs.fund, &channel).is_ok());
+
+        // The channel must keep more than dust after the split
+        let small = SplitInput { value: 100_500, ..channel };
+        assert!(build_split_transactions(&small, &offer, &accept, &[(100_000, 0)], &terms).is_err());
+    }
+
+    #[test]
+    fn test_funding_script_is_sorted() {
+        let offer = party(1, 0, 0, 0);
+        let accept = party(2, 0, 0, 0);
+        assert_eq!(
+            funding_script(&offer.funding_pubkey, &accept.funding_pubkey),
+            funding_script(&accept.funding_pubkey, &offer.funding_pubkey)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// src/bitcoin/dlc/wallet.rs
+
+use std::sync::Mutex;
+
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::{ecdsa, CompressedPublicKey, OutPoint, ScriptBuf, Transaction, TxOut, Witness};
+
+use crate::AnyaResult;
+use crate::bitcoin::crypto::{reduce_scalar, tagged_hash};
+use super::messages::FundingInput;
+use super::transactions::P2WPKH_WITNESS_SIZE;
+
+/// Wallet operations a DLC manager needs
+///
+/// The wallet supplies funding keys, payout and change scripts, and the UTXOs
+/// funding a party's collateral, and signs its own funding inputs.
+pub trait DLCWallet: Send + Sync {
+    /// Key for the 2-of-2 funding output of the contract with this temporary ID
+    ///
+    /// Must return the same key every time it is asked for the same contract.
+    fn funding_key(&self, temporary_contract_id: &[u8; 32]) -> AnyaResult<SecretKey>;
+
+    /// Script receiving CET and refund payouts
+    fn payout_script(&self) -> AnyaResult<ScriptBuf>;
+
+    /// Script receiving funding change
+    fn change_script(&self) -> AnyaResult<ScriptBuf>;
+
+    /// Select and reserve UTXOs worth at least `amount` plus `fee_per_input`
+    /// for every input selected
+    fn select_inputs(&self, amount: u64, fee_per_input: u64) -> AnyaResult<Vec<FundingInput>>;
+
+    /// Release UTXOs reserved by `select_inputs` that will not be spent
+    fn release_inputs(&self, inputs: &[FundingInput]) -> AnyaResult<()>;
+
+    /// Witness for the wallet's input `input_index` of `transaction`
+    fn sign_funding_input(&self, transaction: &Transaction, input_index: usize, prev_output: &TxOut) -> AnyaResult<Witness>;
+}
+
+/// In-memory single-key P2WPKH wallet
+///
+/// Funding keys are derived from the wallet key and the temporary contract ID.
+pub struct InMemoryDLCWallet {
+    secret_key: SecretKey,
+    utxos: Mutex<Vec<FundingInput>>,
+}
+
+impl InMemoryDLCWallet {
+    /// Creates a wallet controlled by `secret_key`
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self {
+            secret_key,
+            utxos: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The wallet's P2WPKH script
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        let secp = Secp256k1::signing_only();
+        let public_key = CompressedPublicKey(PublicKey::from_secret_key(&secp, &self.secret_key));
+        ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash())
+    }
+
+    /// Adds output `vout` of `prev_tx` as a spendable UTXO
+    pub fn add_utxo(&self, prev_tx: Transaction, vout: u32) -> AnyaResult<()> {
+        match prev_tx.output.get(vout as usize) {
+            Some(output) if output.script_pubkey == self.script_pubkey() => {}
+            Some(_) => return Err("UTXO does not pay to this wallet".into()),
+            None => return Err(format!("Transaction has no output {}", vout).into()),
+        }
+        self.lock_utxos()?.push(FundingInput {
+            input_serial_id: 0,
+            prev_tx,
+            prev_tx_vout: vout,
+            sequence: 0xffff_ffff,
+            max_witness_len: P2WPKH_WITNESS_SIZE,
+            redeem_script: ScriptBuf::new(),
+        });
+        Ok(())
+    }
+
+    /// Total value of unreserved UTXOs
+    pub fn balance(&self) -> AnyaResult<u64> {
+        self.lock_utxos()?.iter().map(FundingInput::value).sum()
+    }
+
+    fn lock_utxos(&self) -> AnyaResult<std::sync::MutexGuard<'_, Vec<FundingInput>>> {
+        self.utxos.lock().map_err(|_| "Wallet lock poisoned".into())
+    }
+}
+
+impl DLCWallet for InMemoryDLCWallet {
+    fn funding_key(&self, temporary_contract_id: &[u8; 32]) -> AnyaResult<SecretKey> {
+        let tweak = reduce_scalar(tagged_hash("DLC/funding-key", &[
+            &self.secret_key.secret_bytes(),
+            temporary_contract_id,
+        ])).ok_or("Funding key tweak reduced to zero")?;
+        self.secret_key.add_tweak(&Scalar::from(tweak))
+            .map_err(|e| format!("Failed to derive funding key: {}", e).into())
+    }
+
+    fn payout_script(&self) -> AnyaResult<ScriptBuf> {
+        Ok(self.script_pubkey())
+    }
+
+    fn change_script(&self) -> AnyaResult<ScriptBuf> {
+        Ok(self.script_pubkey())
+    }
+
+    fn select_inputs(&self, amount: u64, fee_per_input: u64) -> AnyaResult<Vec<FundingInput>> {
+        let mut utxos = self.lock_utxos()?;
+        // Largest first keeps the input count, and so the fee, low
+        let mut values = utxos.iter().map(FundingInput::value).collect::<AnyaResult<Vec<_>>>()?
+            .into_iter().enumerate().collect::<Vec<_>>();
+        values.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+
+        let mut selected = Vec::new();
+        let mut total = 0u64;
+        for (index, value) in values {
+            if total >= amount + fee_per_input * selected.len() as u64 {
+                break;
+            }
+            selected.push(index);
+            total += value;
+        }
+        let needed = amount + fee_per_input * selected.len() as u64;
+        if total < needed {
+            return Err(format!("Insufficient funds: need {} sats, have {}", needed, total).into());
+        }
+
+        selected.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(selected.into_iter().map(|index| utxos.remove(index)).collect())
+    }
+
+    fn release_inputs(&self, inputs: &[FundingInput]) -> AnyaResult<()> {
+        let mut utxos = self.lock_utxos()?;
+        for input in inputs {
+            let outpoint = OutPoint::new(input.prev_tx.compute_txid(), input.prev_tx_vout);
+            if !utxos.iter().any(|utxo| OutPoint::new(utxo.prev_tx.compute_txid(), utxo.prev_tx_vout) == outpoint) {
+                utxos.push(input.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn sign_funding_input(&self, transaction: &Transaction, input_index: usize, prev_output: &TxOut) -> AnyaResult<Witness> {
+        if prev_output.script_pubkey != self.script_pubkey() {
+            return Err(format!("Input {} is not controlled by this wallet", input_index).into());
+        }
+        let secp = Secp256k1::signing_only();
+        let sighash = SighashCache::new(transaction)
+            .p2wpkh_signature_hash(input_index, &prev_output.script_pubkey, prev_output.value, EcdsaSighashType::All)
+            .map_err(|e| format!("Failed to compute signature hash: {}", e))?;
+        let message = bitcoin::secp256k1::Message::from_digest(sighash.to_byte_array());
+        let signature = ecdsa::Signature::sighash_all(secp.sign_ecdsa(&message, &self.secret_key));
+        Ok(Witness::p2wpkh(&signature, &PublicKey::from_secret_key(&secp, &self.secret_key)))
+    }
+}
@@ -24,7 +24,7 @@
 use std::path::PathBuf;
 use bitcoin::Txid;
 
-use crate::common::error::AnyaResult;
+use crate::AnyaResult;
 use crate::bitcoin::wallet::TxOptions;
 
 /// RGB asset data
@@ -6,9 +6,11 @@
 
 use crate::AnyaError;
 use crate::AnyaResult;
+use bitcoin::hashes::Hash;
 use bitcoin::{BlockHash, Network, Transaction, Txid};
 use secp256k1::{PublicKey, SecretKey, Secp256k1};
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -361,8 +363,7 @@ pub fn open_channel(
         let channel_id = format!("channel_{:x}", rand::random::<u64>());
         
         // Generate funding transaction ID
-        let funding_txid = Txid::from_slice(&[0x42; 32])
-            .map_err(|e| AnyaError::Bitcoin(format!("Failed to create txid: {}", e)))?;
+        let funding_txid = Txid::from_byte_array([0x42; 32]);
         
         // Calculate balance split
         let push_amount = push_msat.unwrap_or(0) / 1000; // Convert to sats
@@ -404,8 +405,7 @@ pub fn close_channel(&self, channel_id: &str, force: bool) -> AnyaResult<Txid> {
             .ok_or_else(|| AnyaError::Bitcoin(format!("Channel not found: {}", channel_id)))?;
         
         // Generate closing transaction ID
-        let closing_txid = Txid::from_slice(&[0x24; 32])
-            .map_err(|e| AnyaError::Bitcoin(format!("Failed to create closing txid: {}", e)))?;
+        let closing_txid = Txid::from_byte_array([0x24; 32]);
         
         // Update channel state
         channel.is_active = false;
@@ -434,6 +434,7 @@ pub fn create_invoice(
             Network::Testnet => "lntb",
             Network::Regtest => "lnbcrt",
             Network::Signet => "lnsb",
+            _ => "lntb",
         };
         
         let amount_part = match amount_msat {
@@ -10,17 +10,16 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
-mod wallet;
-mod network;
-mod transaction;
-mod lightning;
-mod dlc;
+mod crypto;
+pub mod lightning;
+pub mod dlc;
+pub mod taproot;
 
 pub use wallet::{Wallet, WalletType};
 pub use transaction::{TransactionBuilder, UTXO};
 pub use network::NetworkManager;
 pub use lightning::LightningNode;
-pub use dlc::DLCManager;
+pub use dlc::{DLCConfig, DLCManager, DefaultDLCManager, InMemoryDLCWallet};
 
 /// Configuration options for Bitcoin functionality
 #[derive(Debug, Clone)]
@@ -62,7 +61,7 @@ pub struct BitcoinManager {
     wallets: Vec<Wallet>,
     secp: Secp256k1<secp256k1::All>,
     lightning_node: Option<LightningNode>,
-    dlc_manager: Option<DLCManager>,
+    dlc_manager: Option<DefaultDLCManager>,
 }
 
 impl BitcoinManager {
@@ -88,7 +87,16 @@ pub fn new(config: BitcoinConfig) -> AnyaResult<Self> {
         };
 
         let dlc_manager = if config.dlc_enabled {
-            Some(DLCManager::new(&config)?)
+            // Contracts are funded from an in-memory wallet under a fresh key
+            // until a persistent DLC wallet is configured
+            let dlc_config = DLCConfig {
+                network: config.network.to_string(),
+                ..DLCConfig::default()
+            };
+            let wallet = InMemoryDLCWallet::new(
+                bitcoin::secp256k1::SecretKey::new(&mut bitcoin::secp256k1::rand::thread_rng())
+            );
+            Some(DefaultDLCManager::new(dlc_config, Arc::new(wallet)))
         } else {
             None
         };
@@ -177,7 +185,7 @@ pub fn lightning_node(&self) -> Option<&LightningNode> {
     }
 
     /// Get the DLC manager if enabled
-    pub fn dlc_manager(&self) -> Option<&DLCManager> {
+    pub fn dlc_manager(&self) -> Option<&DefaultDLCManager> {
         self.dlc_manager.as_ref()
     }
 }
@@ -186,6 +194,23 @@ pub fn dlc_manager(&self) -> Option<&DLCManager> {
 pub mod wallet {
     use super::*;
     
+    pub mod transactions;
+    
+    pub use transactions::TxOptions;
+    
+    /// Address type used for change outputs
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum AddressType {
+        /// P2PKH
+        Legacy,
+        /// P2WPKH
+        SegWit,
+        /// P2SH-P2WPKH
+        NestedSegWit,
+        /// P2TR
+        Taproot,
+    }
+    
     /// Wallet type enum
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum WalletType {
@@ -350,77 +375,3 @@ pub fn finalize(self, wallet: &Wallet) -> AnyaResult<Self> {
         }
     }
 }
-
-/// Module placeholder for Lightning Network implementation
-pub mod lightning {
-    use super::*;
-    
-    /// Lightning Network node
-    #[derive(Debug)]
-    pub struct LightningNode {
-        // Private fields would be LDK components
-    }
-    
-    impl LightningNode {
-        /// Create a new Lightning node
-        pub fn new(config: &BitcoinConfig) -> AnyaResult<Self> {
-            // In a real implementation, this would initialize LDK
-            Ok(Self {})
-        }
-        
-        /// Open a payment channel
-        pub fn open_channel(&self, peer_id: &str, amount: u64) -> AnyaResult<()> {
-            // In a real implementation, this would open an actual channel
-            Ok(())
-        }
-        
-        /// Create a payment invoice
-        pub fn create_invoice(&self, amount: u64, description: &str) -> AnyaResult<String> {
-            // In a real implementation, this would create an actual invoice
-            Ok("lnbc1...".to_string())
-        }
-        
-        /// Pay a Lightning invoice
-        pub fn pay_invoice(&self, invoice: &str) -> AnyaResult<()> {
-            // In a real implementation, this would pay the invoice
-            Ok(())
-        }
-    }
-}
-
-/// Module placeholder for DLC implementation
-pub mod dlc {
-    use super::*;
-    
-    /// DLC manager
-    #[derive(Debug)]
-    pub struct DLCManager {
-        // Private fields for DLC implementation
-    }
-    
-    impl DLCManager {
-        /// Create a new DLC manager
-        pub fn new(config: &BitcoinConfig) -> AnyaResult<Self> {
-            // In a real implementation, this would set up DLC
-            Ok(Self {})
-        }
-        
-        /// Create a new DLC contract
-        pub fn create_contract(&self, outcomes: &[&str], amounts: &[u64]) -> AnyaResult<String> {
-            // In a real implementation, this would create an actual contract
-            Ok("dlc1...".to_string())
-        }
-        
-        /// Sign a DLC contract
-        pub fn sign_contract(&self, contract_id: &str) -> AnyaResult<()> {
-            // In a real implementation, this would sign the contract
-            Ok(())
-        }
-        
-        /// Execute a DLC contract
-        pub fn execute_contract(&self, contract_id: &str, outcome: &str) -> AnyaResult<Txid> {
-            // In a real implementation, this would execute the contract
-            Ok(Txid::from_slice(&[0; 32]).unwrap())
-        }
-    }
-}
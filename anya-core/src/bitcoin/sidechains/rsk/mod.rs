@@ -23,7 +23,7 @@
 use bitcoin::Txid;
 use serde::{Serialize, Deserialize};
 
-use crate::common::error::AnyaResult;
+use crate::AnyaResult;
 use crate::bitcoin::wallet::TxOptions;
 
 /// RSK transaction data
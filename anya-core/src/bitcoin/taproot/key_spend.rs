@@ -0,0 +1,103 @@
+// src/bitcoin/taproot/key_spend.rs
+
+use bitcoin::key::{Keypair, TapTweak, TweakedPublicKey};
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, Signing, Verification};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{self, TapNodeHash};
+use bitcoin::{ScriptBuf, Transaction, TxOut, Witness, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+
+use crate::AnyaResult;
+
+/// The key path of a Taproot output
+///
+/// The output key is the internal key tweaked with the merkle root of the
+/// script tree (BIP341), or with nothing when there is no tree (BIP86).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySpendPath {
+    /// The untweaked internal key
+    pub internal_key: XOnlyPublicKey,
+
+    /// Merkle root of the script tree, if any
+    pub merkle_root: Option<TapNodeHash>,
+}
+
+impl KeySpendPath {
+    /// Creates a key path for an internal key and optional script tree root
+    pub fn new(internal_key: XOnlyPublicKey, merkle_root: Option<TapNodeHash>) -> Self {
+        Self { internal_key, merkle_root }
+    }
+
+    /// The tweaked output key committed to in the scriptPubKey
+    pub fn output_key<C: Verification>(&self, secp: &Secp256k1<C>) -> TweakedPublicKey {
+        self.internal_key.tap_tweak(secp, self.merkle_root).0
+    }
+
+    /// The P2TR scriptPubKey for this path
+    pub fn script_pubkey<C: Verification>(&self, secp: &Secp256k1<C>) -> ScriptBuf {
+        ScriptBuf::new_p2tr(secp, self.internal_key, self.merkle_root)
+    }
+}
+
+/// Signs Taproot inputs through the key path
+pub struct KeyPathSpender<'a, C: Signing + Verification> {
+    secp: &'a Secp256k1<C>,
+    sighash_type: TapSighashType,
+}
+
+impl<'a, C: Signing + Verification> KeyPathSpender<'a, C> {
+    /// Creates a spender signing with `SIGHASH_DEFAULT`
+    pub fn new(secp: &'a Secp256k1<C>) -> Self {
+        Self {
+            secp,
+            sighash_type: TapSighashType::Default,
+        }
+    }
+
+    /// Sets the sighash type of the signatures
+    pub fn with_sighash_type(mut self, sighash_type: TapSighashType) -> Self {
+        self.sighash_type = sighash_type;
+        self
+    }
+
+    /// Computes the BIP341 key path sighash of an input
+    pub fn sighash(&self, tx: &Transaction, input_index: usize, prevouts: &[TxOut]) -> AnyaResult<Message> {
+        let sighash = SighashCache::new(tx)
+            .taproot_key_spend_signature_hash(input_index, &Prevouts::All(prevouts), self.sighash_type)
+            .map_err(|e| format!("Failed to compute sighash for input {}: {}", input_index, e))?;
+        Ok(Message::from(sighash))
+    }
+
+    /// Signs an input with the tweaked private key and sets its witness
+    ///
+    /// `private_key` is the internal key; it is tweaked with `path.merkle_root`
+    /// and must match the output key of the input's prevout.
+    pub fn sign_input(
+        &self,
+        tx: &mut Transaction,
+        input_index: usize,
+        prevouts: &[TxOut],
+        private_key: &SecretKey,
+        path: &KeySpendPath,
+    ) -> AnyaResult<()> {
+        let keypair = Keypair::from_secret_key(self.secp, private_key);
+        if keypair.x_only_public_key().0 != path.internal_key {
+            return Err("Private key does not match the internal key".into());
+        }
+        let prevout = prevouts.get(input_index)
+            .ok_or_else(|| format!("Missing prevout for input {}", input_index))?;
+        if prevout.script_pubkey != path.script_pubkey(self.secp) {
+            return Err(format!("Input {} does not spend the key path of this key", input_index).into());
+        }
+
+        let message = self.sighash(tx, input_index, prevouts)?;
+        let tweaked = keypair.tap_tweak(self.secp, path.merkle_root);
+        let signature = taproot::Signature {
+            signature: self.secp.sign_schnorr_with_aux_rand(&message, &tweaked.to_keypair(), &rand::random()),
+            sighash_type: self.sighash_type,
+        };
+
+        tx.input[input_index].witness = Witness::p2tr_key_spend(&signature);
+        Ok(())
+    }
+}
@@ -8,25 +8,43 @@
 
 mod script;
 mod tree;
-mod assets;
 mod key_spend;
 mod script_spend;
 mod musig;
 
-pub use script::{TaprootScript, TaprootScriptBuilder, TaprootScriptType};
+pub use script::{TaprootScript, TaprootScriptBuilder, TaprootScriptType, ScriptParser, PolicyCompiler, CompiledPolicy};
 pub use tree::{TapLeaf, TapBranch, TapTree, TapTreeBuilder};
-pub use assets::{TaprootAsset, TaprootAssetManager, AssetTransfer, AssetMetadata};
 pub use key_spend::{KeySpendPath, KeyPathSpender};
 pub use script_spend::{ScriptSpendPath, ScriptPathSpender};
-pub use musig::{MuSigSession, MuSigParticipant, MuSigAggregator};
+pub use musig::{MuSigSession, MuSigParticipant, MuSigAggregator, MuSigTweak, PublicNonce, SecretNonce, AggregateNonce, PartialSignature};
 
-use bitcoin::secp256k1::{PublicKey, SecretKey, Secp256k1};
-use bitcoin::{ScriptBuf, Transaction, XOnlyPublicKey, TxOut};
-use bitcoin::taproot::{TapTweakHash, TapLeafHash, TaprootBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::common::error::AnyaResult;
+use bitcoin::absolute::LockTime;
+use bitcoin::bip32::{ChildNumber, Xpub};
+use bitcoin::key::Keypair;
+use bitcoin::script::PushBytesBuf;
+use bitcoin::secp256k1::{Message, SecretKey, Secp256k1};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::taproot::{self, ControlBlock, TapLeafHash};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Address, Amount, CompressedPublicKey, Network, NetworkKind, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut,
+    Txid, Witness, XOnlyPublicKey,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::AnyaResult;
 use crate::bitcoin::wallet::{AddressType, TxOptions};
 
+/// Dust threshold of the costliest change output (P2PKH); without a change
+/// key, a leftover below it goes to the fee
+const CHANGE_DUST_LIMIT: u64 = 546;
+
 /// Main interface for Taproot operations
 pub trait TaprootManager {
     /// Generates a new Taproot address with the given script tree
@@ -83,8 +101,8 @@ fn create_musig_session(
 
 impl TaprootFactory {
     /// Creates a new Taproot manager
-    pub fn create_manager(config: TaprootConfig) -> Box<dyn TaprootManager> {
-        Box::new(DefaultTaprootManager::new(config))
+    pub fn create_manager(config: TaprootConfig) -> AnyaResult<Box<dyn TaprootManager>> {
+        Ok(Box::new(DefaultTaprootManager::new(config)?))
     }
 }
 
@@ -99,6 +117,15 @@ pub struct TaprootConfig {
     
     /// Whether to use MuSig by default for multi-key aggregation
     pub use_musig: bool,
+    
+    /// Extended public key of the wallet's change chain; change outputs pay
+    /// to its unhardened children, of the type `TxOptions` asks for
+    pub change_key: Option<Xpub>,
+    
+    /// File the key paths of derived outputs and the next change index are
+    /// kept in, so outputs committing to a script tree stay spendable
+    /// through the key path after a restart; memory only when `None`
+    pub state_file: Option<PathBuf>,
 }
 
 impl Default for TaprootConfig {
@@ -107,22 +134,372 @@ fn default() -> Self {
             network: "testnet".to_string(),
             fee_rate: 1.0,
             use_musig: true,
+            change_key: None,
+            state_file: None,
         }
     }
 }
 
 /// Default implementation of the Taproot manager
-struct DefaultTaprootManager {
+pub struct DefaultTaprootManager {
     config: TaprootConfig,
     secp: Secp256k1<bitcoin::secp256k1::All>,
+    state: RwLock<ManagerState>,
+}
+
+/// What a manager remembers across transactions
+#[derive(Debug, Default)]
+struct ManagerState {
+    /// Key paths of the outputs derived by the manager, by output key
+    key_paths: HashMap<XOnlyPublicKey, KeySpendPath>,
+    
+    /// First change index no transaction has used
+    next_change_index: u32,
+}
+
+/// [`ManagerState`] as kept in `TaprootConfig::state_file`
+#[derive(Serialize, Deserialize)]
+struct StoredState {
+    key_paths: Vec<KeySpendPath>,
+    next_change_index: u32,
 }
 
 impl DefaultTaprootManager {
-    /// Creates a new default Taproot manager
-    fn new(config: TaprootConfig) -> Self {
-        Self {
-            config,
-            secp: Secp256k1::new(),
+    /// Creates a new default Taproot manager, loading the state file if
+    /// one is configured and exists
+    pub fn new(config: TaprootConfig) -> AnyaResult<Self> {
+        let secp = Secp256k1::new();
+        let mut state = ManagerState::default();
+        if let Some(path) = &config.state_file {
+            match fs::read(path) {
+                Ok(json) => {
+                    let stored: StoredState = serde_json::from_slice(&json)
+                        .map_err(|e| format!("Corrupt {}: {}", path.display(), e))?;
+                    state.key_paths = stored.key_paths.into_iter()
+                        .map(|path| (path.output_key(&secp).to_x_only_public_key(), path))
+                        .collect();
+                    state.next_change_index = stored.next_change_index;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+            }
+        }
+        
+        let manager = Self { config, secp, state: RwLock::new(state) };
+        let network = manager.network()?;
+        if manager.config.change_key.is_some_and(|key| key.network != NetworkKind::from(network)) {
+            return Err(format!("Change key is not for {}", network).into());
+        }
+        Ok(manager)
+    }
+    
+    /// Verifies a Taproot signature given every prevout of the transaction
+    ///
+    /// Unless signed with `SIGHASH_ANYONECANPAY`, the sighash of an input
+    /// commits to all spent outputs, so multi-input transactions need this
+    /// rather than `verify_taproot_signature`.
+    pub fn verify_taproot_signature_with_prevouts(
+        &self,
+        transaction: &Transaction,
+        input_index: usize,
+        prevouts: &[TxOut],
+        public_key: &XOnlyPublicKey,
+    ) -> AnyaResult<bool> {
+        let prevout = prevouts.get(input_index)
+            .ok_or_else(|| format!("Missing prevout for input {}", input_index))?;
+        self.verify_input(transaction, input_index, prevout, &Prevouts::All(prevouts), public_key)
+    }
+    
+    /// The configured network
+    fn network(&self) -> AnyaResult<Network> {
+        match self.config.network.as_str() {
+            "mainnet" | "bitcoin" => Ok(Network::Bitcoin),
+            "testnet" => Ok(Network::Testnet),
+            "signet" => Ok(Network::Signet),
+            "regtest" => Ok(Network::Regtest),
+            other => Err(format!("Unknown network: {}", other).into()),
+        }
+    }
+    
+    fn read_state(&self) -> AnyaResult<RwLockReadGuard<'_, ManagerState>> {
+        self.state.read().map_err(|_| "Taproot manager lock poisoned".into())
+    }
+    
+    fn write_state(&self) -> AnyaResult<RwLockWriteGuard<'_, ManagerState>> {
+        self.state.write().map_err(|_| "Taproot manager lock poisoned".into())
+    }
+    
+    /// Writes the state to the state file, if one is configured
+    ///
+    /// The file is written to a temporary file and renamed into place, so a
+    /// crash never leaves it partially written.
+    fn save_state(&self, state: &ManagerState) -> AnyaResult<()> {
+        let Some(path) = &self.config.state_file else {
+            return Ok(());
+        };
+        let mut key_paths: Vec<KeySpendPath> = state.key_paths.values().copied().collect();
+        key_paths.sort_by_key(|path| path.output_key(&self.secp).serialize());
+        let stored = StoredState { key_paths, next_change_index: state.next_change_index };
+        let json = serde_json::to_vec_pretty(&stored).map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, json).map_err(|e| format!("Failed to write {}: {}", temporary.display(), e))?;
+        fs::rename(&temporary, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e).into())
+    }
+    
+    /// Derives the key path of an output and remembers its tweak for spending
+    fn key_path(&self, internal_key: &XOnlyPublicKey, script_tree: Option<&TapTree>) -> AnyaResult<KeySpendPath> {
+        let merkle_root = match script_tree {
+            Some(tree) => Some(tree.root_hash.ok_or("Taproot tree has not been built")?),
+            None => None,
+        };
+        let path = KeySpendPath::new(*internal_key, merkle_root);
+        let mut state = self.write_state()?;
+        if state.key_paths.insert(path.output_key(&self.secp).to_x_only_public_key(), path) != Some(path) {
+            self.save_state(&state)?;
+        }
+        Ok(path)
+    }
+    
+    /// The change scriptPubKey for `options` and its index, or `None`
+    /// without a change key
+    ///
+    /// Without a `change_address_index` this is the next unused index,
+    /// which `use_change_index` marks used once a change output is kept.
+    fn change_script(&self, options: &TxOptions) -> AnyaResult<Option<(ScriptBuf, u32)>> {
+        let Some(change_key) = self.config.change_key else {
+            return Ok(None);
+        };
+        let index = match options.change_address_index {
+            Some(index) => index,
+            None => self.read_state()?.next_change_index,
+        };
+        let child = ChildNumber::from_normal_idx(index)
+            .map_err(|e| format!("Invalid change index {}: {}", index, e))?;
+        let public_key = change_key.derive_pub(&self.secp, &[child])
+            .map_err(|e| format!("Failed to derive change key {}: {}", index, e))?
+            .public_key;
+        let compressed = CompressedPublicKey(public_key);
+        let script_pubkey = match options.change_address_type {
+            AddressType::Legacy => ScriptBuf::new_p2pkh(&compressed.pubkey_hash()),
+            AddressType::SegWit => ScriptBuf::new_p2wpkh(&compressed.wpubkey_hash()),
+            AddressType::NestedSegWit => ScriptBuf::new_p2sh(&ScriptBuf::new_p2wpkh(&compressed.wpubkey_hash()).script_hash()),
+            AddressType::Taproot => ScriptBuf::new_p2tr(&self.secp, public_key.x_only_public_key().0, None),
+        };
+        Ok(Some((script_pubkey, index)))
+    }
+    
+    fn use_change_index(&self, index: u32) -> AnyaResult<()> {
+        let mut state = self.write_state()?;
+        if index >= state.next_change_index {
+            state.next_change_index = index + 1;
+            self.save_state(&state)?;
+        }
+        Ok(())
+    }
+    
+    /// Fee of a signed transaction at the configured rate
+    fn fee(&self, tx: &Transaction) -> u64 {
+        (tx.vsize() as f64 * self.config.fee_rate).ceil() as u64
+    }
+    
+    /// Builds an unsigned transaction and collects the prevouts it spends
+    fn unsigned_transaction(
+        &self,
+        inputs: Vec<(String, u32)>,
+        outputs: Vec<(String, u64)>,
+        options: &TxOptions,
+    ) -> AnyaResult<(Transaction, Vec<TxOut>)> {
+        let network = self.network()?;
+        let lock_time = options.lock_time.map(LockTime::from_consensus).unwrap_or(LockTime::ZERO);
+        let sequence = if options.rbf {
+            Sequence::ENABLE_RBF_NO_LOCKTIME
+        } else if options.lock_time.is_some() {
+            Sequence::ENABLE_LOCKTIME_NO_RBF
+        } else {
+            Sequence::MAX
+        };
+        
+        // Taproot sighashes commit to the amounts and scripts of every spent
+        // output, so each input must come with its UTXO
+        let utxos = options.custom_inputs.as_deref().unwrap_or_default();
+        let mut input = Vec::with_capacity(inputs.len());
+        let mut prevouts = Vec::with_capacity(inputs.len());
+        for (txid, vout) in inputs {
+            let utxo = utxos.iter()
+                .find(|utxo| utxo.txid == txid && utxo.vout == vout)
+                .ok_or_else(|| format!("Missing UTXO for input {}:{} in custom_inputs", txid, vout))?;
+            let txid = Txid::from_str(&txid)
+                .map_err(|e| format!("Invalid txid {}: {}", txid, e))?;
+            input.push(TxIn {
+                previous_output: OutPoint::new(txid, vout),
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            });
+            prevouts.push(TxOut {
+                value: Amount::from_sat(utxo.amount),
+                script_pubkey: ScriptBuf::from_bytes(utxo.script_pubkey.clone()),
+            });
+        }
+        if input.is_empty() {
+            return Err("Transaction has no inputs".into());
+        }
+        
+        let mut output = Vec::with_capacity(outputs.len() + options.extra_outputs.len() + 1);
+        for (address, amount) in outputs.iter().chain(&options.extra_outputs) {
+            let script_pubkey = Address::from_str(address)
+                .map_err(|e| format!("Invalid address {}: {}", address, e))?
+                .require_network(network)
+                .map_err(|e| format!("Invalid address {}: {}", address, e))?
+                .script_pubkey();
+            output.push(TxOut {
+                value: Amount::from_sat(*amount),
+                script_pubkey,
+            });
+        }
+        if let Some(data) = &options.op_return_data {
+            let data = PushBytesBuf::try_from(data.clone())
+                .map_err(|e| format!("Invalid OP_RETURN data: {}", e))?;
+            output.push(TxOut {
+                value: Amount::ZERO,
+                script_pubkey: ScriptBuf::new_op_return(data),
+            });
+        }
+        
+        let input_total: u64 = prevouts.iter().map(|prevout| prevout.value.to_sat()).sum();
+        let output_total: u64 = output.iter().map(|output| output.value.to_sat()).sum();
+        if output_total > input_total {
+            return Err(format!("Outputs total {} sat but inputs only {} sat", output_total, input_total).into());
+        }
+        
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time,
+            input,
+            output,
+        };
+        Ok((tx, prevouts))
+    }
+    
+    /// Signs every input and pays the fee at the configured rate
+    ///
+    /// The fee comes out of what the inputs hold beyond the outputs, or out
+    /// of the first output with `subtract_fee_from_amount`. What is left
+    /// goes to a change output if it is above dust, and to the fee if not.
+    fn sign_transaction(
+        &self,
+        mut tx: Transaction,
+        prevouts: &[TxOut],
+        options: &TxOptions,
+        sign: impl Fn(&mut Transaction) -> AnyaResult<()>,
+    ) -> AnyaResult<Transaction> {
+        if options.subtract_fee_from_amount && tx.output.is_empty() {
+            return Err("Transaction has no output to take the fee from".into());
+        }
+        let input_total: u64 = prevouts.iter().map(|prevout| prevout.value.to_sat()).sum();
+        let output_total: u64 = tx.output.iter().map(|output| output.value.to_sat()).sum();
+        let surplus = input_total - output_total;
+        let leftover = |fee: u64| if options.subtract_fee_from_amount { Some(surplus) } else { surplus.checked_sub(fee) };
+        
+        // Schnorr signatures have a fixed size, so re-signing after setting
+        // the amounts keeps the weight the fee was computed for
+        if let Some((script_pubkey, index)) = self.change_script(options)? {
+            tx.output.push(TxOut { value: Amount::ZERO, script_pubkey });
+            sign(&mut tx)?;
+            let fee = self.fee(&tx);
+            let change = tx.output.last_mut().ok_or("Transaction lost its change output")?;
+            if let Some(value) = leftover(fee).filter(|value| *value >= change.script_pubkey.minimal_non_dust().to_sat()) {
+                change.value = Amount::from_sat(value);
+                if options.subtract_fee_from_amount {
+                    subtract_fee(&mut tx, fee)?;
+                }
+                sign(&mut tx)?;
+                if options.change_address_index.is_none() {
+                    self.use_change_index(index)?;
+                }
+                return Ok(tx);
+            }
+            tx.output.pop();
+        }
+        
+        sign(&mut tx)?;
+        let fee = self.fee(&tx);
+        match leftover(fee) {
+            None => return Err(format!(
+                "Inputs leave a fee of {} sat, below the {} sat needed at {} sat/vB",
+                surplus, fee, self.config.fee_rate
+            ).into()),
+            Some(value) if value >= CHANGE_DUST_LIMIT && self.config.change_key.is_none() => return Err(format!(
+                "Inputs exceed the outputs and fee by {} sat, which needs a change key to be kept", value
+            ).into()),
+            Some(_) => {}
+        }
+        if options.subtract_fee_from_amount {
+            subtract_fee(&mut tx, fee)?;
+            sign(&mut tx)?;
+        }
+        Ok(tx)
+    }
+    
+    /// Verifies a key path or script path signature of an input
+    fn verify_input(
+        &self,
+        transaction: &Transaction,
+        input_index: usize,
+        prevout: &TxOut,
+        prevouts: &Prevouts<TxOut>,
+        public_key: &XOnlyPublicKey,
+    ) -> AnyaResult<bool> {
+        let input = transaction.input.get(input_index)
+            .ok_or_else(|| format!("Input {} not found", input_index))?;
+        let output_key = taproot_output_key(&prevout.script_pubkey)
+            .ok_or_else(|| format!("Prevout of input {} is not a Taproot output", input_index))?;
+        
+        let witness: Vec<&[u8]> = input.witness.iter().collect();
+        if witness.len() > 1 && witness.last().and_then(|element| element.first()) == Some(&taproot::TAPROOT_ANNEX_PREFIX) {
+            return Err("Taproot inputs with an annex are not supported".into());
+        }
+        
+        let mut cache = SighashCache::new(transaction);
+        match witness.as_slice() {
+            [] => Err(format!("Input {} has no witness", input_index).into()),
+            [signature] => {
+                // Key path: only the output key itself can sign
+                if output_key != *public_key {
+                    return Ok(false);
+                }
+                let Ok(signature) = taproot::Signature::from_slice(signature) else {
+                    return Ok(false);
+                };
+                let sighash = cache
+                    .taproot_key_spend_signature_hash(input_index, prevouts, signature.sighash_type)
+                    .map_err(|e| format!("Failed to compute sighash for input {}: {}", input_index, e))?;
+                Ok(self.secp.verify_schnorr(&signature.signature, &Message::from(sighash), public_key).is_ok())
+            }
+            [stack @ .., script, control_block] => {
+                // Script path: the leaf must be committed to by the output key
+                // and one of its inputs must be a signature by `public_key`
+                let Ok(control_block) = ControlBlock::decode(control_block) else {
+                    return Ok(false);
+                };
+                let script = ScriptBuf::from_bytes(script.to_vec());
+                if !control_block.verify_taproot_commitment(&self.secp, output_key, &script) {
+                    return Ok(false);
+                }
+                let leaf_hash = TapLeafHash::from_script(&script, control_block.leaf_version);
+                for element in stack {
+                    let Ok(signature) = taproot::Signature::from_slice(element) else {
+                        continue;
+                    };
+                    let sighash = cache
+                        .taproot_script_spend_signature_hash(input_index, prevouts, leaf_hash, signature.sighash_type)
+                        .map_err(|e| format!("Failed to compute sighash for input {}: {}", input_index, e))?;
+                    if self.secp.verify_schnorr(&signature.signature, &Message::from(sighash), public_key).is_ok() {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
         }
     }
 }
@@ -133,8 +510,9 @@ fn generate_taproot_address(
         internal_key: &XOnlyPublicKey,
         script_tree: Option<&TapTree>,
     ) -> AnyaResult<String> {
-        // Implementation goes here
-        unimplemented!("Taproot address generation not yet implemented")
+        let path = self.key_path(internal_key, script_tree)?;
+        let address = Address::p2tr(&self.secp, path.internal_key, path.merkle_root, self.network()?);
+        Ok(address.to_string())
     }
     
     fn construct_taproot_output(
@@ -143,8 +521,11 @@ fn construct_taproot_output(
         internal_key: &XOnlyPublicKey,
         script_tree: Option<&TapTree>,
     ) -> AnyaResult<TxOut> {
-        // Implementation goes here
-        unimplemented!("Taproot output construction not yet implemented")
+        let path = self.key_path(internal_key, script_tree)?;
+        Ok(TxOut {
+            value: Amount::from_sat(amount),
+            script_pubkey: path.script_pubkey(&self.secp),
+        })
     }
     
     fn create_key_spend_transaction(
@@ -154,8 +535,30 @@ fn create_key_spend_transaction(
         private_key: &SecretKey,
         options: &TxOptions,
     ) -> AnyaResult<Transaction> {
-        // Implementation goes here
-        unimplemented!("Key spend transaction creation not yet implemented")
+        let (tx, prevouts) = self.unsigned_transaction(inputs, outputs, options)?;
+        let internal_key = Keypair::from_secret_key(&self.secp, private_key).x_only_public_key().0;
+        
+        // Outputs derived by this manager carry their script tree tweak;
+        // anything else is taken to be a plain BIP86 key path output
+        let paths: Vec<KeySpendPath> = {
+            let state = self.read_state()?;
+            prevouts.iter()
+                .map(|prevout| {
+                    taproot_output_key(&prevout.script_pubkey)
+                        .and_then(|output_key| state.key_paths.get(&output_key).copied())
+                        .filter(|path| path.internal_key == internal_key)
+                        .unwrap_or_else(|| KeySpendPath::new(internal_key, None))
+                })
+                .collect()
+        };
+        
+        let spender = KeyPathSpender::new(&self.secp);
+        self.sign_transaction(tx, &prevouts, options, |tx| {
+            for (index, path) in paths.iter().enumerate() {
+                spender.sign_input(tx, index, &prevouts, private_key, path)?;
+            }
+            Ok(())
+        })
     }
     
     fn create_script_spend_transaction(
@@ -165,10 +568,20 @@ fn create_script_spend_transaction(
         script_path: &ScriptSpendPath,
         options: &TxOptions,
     ) -> AnyaResult<Transaction> {
-        // Implementation goes here
-        unimplemented!("Script spend transaction creation not yet implemented")
+        let (tx, prevouts) = self.unsigned_transaction(inputs, outputs, options)?;
+        
+        let spender = ScriptPathSpender::new(&self.secp);
+        self.sign_transaction(tx, &prevouts, options, |tx| {
+            for index in 0..tx.input.len() {
+                spender.sign_input(tx, index, &prevouts, script_path)?;
+            }
+            Ok(())
+        })
     }
     
+    /// Only `prevout` is known here, which suffices for single-input
+    /// transactions and `SIGHASH_ANYONECANPAY` signatures; anything else
+    /// needs `verify_taproot_signature_with_prevouts`.
     fn verify_taproot_signature(
         &self,
         transaction: &Transaction,
@@ -176,15 +589,544 @@ fn verify_taproot_signature(
         prevout: &TxOut,
         public_key: &XOnlyPublicKey,
     ) -> AnyaResult<bool> {
-        // Implementation goes here
-        unimplemented!("Taproot signature verification not yet implemented")
+        if transaction.input.len() == 1 {
+            self.verify_input(transaction, input_index, prevout, &Prevouts::All(std::slice::from_ref(prevout)), public_key)
+        } else {
+            self.verify_input(transaction, input_index, prevout, &Prevouts::One(input_index, prevout.clone()), public_key)
+        }
     }
     
     fn create_musig_session(
         &self,
         participants: Vec<MuSigParticipant>,
     ) -> AnyaResult<MuSigSession> {
-        // Implementation goes here
-        unimplemented!("MuSig session creation not yet implemented")
+        MuSigSession::new(participants)
+    }
+}
+
+/// Takes `fee` out of the first output, which must stay above dust
+fn subtract_fee(tx: &mut Transaction, fee: u64) -> AnyaResult<()> {
+    let output = tx.output.first_mut().ok_or("Transaction has no output to take the fee from")?;
+    output.value = output.value.to_sat().checked_sub(fee)
+        .map(Amount::from_sat)
+        .filter(|value| *value >= output.script_pubkey.minimal_non_dust())
+        .ok_or_else(|| format!("Output of {} sat cannot pay a fee of {} sat", output.value.to_sat(), fee))?;
+    Ok(())
+}
+
+/// The output key of a P2TR scriptPubKey
+fn taproot_output_key(script_pubkey: &ScriptBuf) -> Option<XOnlyPublicKey> {
+    if !script_pubkey.is_p2tr() {
+        return None;
+    }
+    XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::opcodes::all::OP_CHECKSIG;
+    use bitcoin::script::Builder;
+    use bitcoin::key::TapTweak;
+    use bitcoin::sighash::TapSighashType;
+    use bitcoin::taproot::{LeafVersion, TapNodeHash};
+    use crate::bitcoin::wallet::transactions::Utxo;
+
+    fn change_key(network: &str) -> Xpub {
+        let kind = if network == "mainnet" { NetworkKind::Main } else { NetworkKind::Test };
+        let master = bitcoin::bip32::Xpriv::new_master(kind, &[7; 32]).unwrap();
+        Xpub::from_priv(&Secp256k1::new(), &master)
+    }
+
+    fn manager(network: &str) -> DefaultTaprootManager {
+        DefaultTaprootManager::new(TaprootConfig {
+            network: network.to_string(),
+            change_key: Some(change_key(network)),
+            ..TaprootConfig::default()
+        }).unwrap()
+    }
+
+    fn tree(leaves: &[(u8, &str)]) -> TapTree {
+        let mut builder = TapTreeBuilder::new();
+        for (version, script) in leaves {
+            let mut leaf = TapLeaf::new(TaprootScript::new(ScriptBuf::from_hex(script).unwrap(), TaprootScriptType::Raw), 1);
+            leaf.version = LeafVersion::from_consensus(*version).unwrap();
+            builder = builder.add_leaf(leaf);
+        }
+        builder.build().unwrap()
+    }
+
+    fn checksig_leaf(key: &XOnlyPublicKey) -> TaprootScript {
+        let script = Builder::new().push_x_only_key(key).push_opcode(OP_CHECKSIG).into_script();
+        TaprootScript::new(script, TaprootScriptType::PointLock)
+    }
+
+    fn utxo(txid: &str, vout: u32, output: &TxOut) -> Utxo {
+        Utxo {
+            txid: txid.to_string(),
+            vout,
+            amount: output.value.to_sat(),
+            address: String::new(),
+            script_pubkey: output.script_pubkey.to_bytes(),
+            derivation_path: None,
+            confirmed: true,
+            confirmation_height: Some(100),
+        }
+    }
+
+    /// (internal key, leaves, merkle root, output key, address, control blocks)
+    type Bip341Vector = (&'static str, Vec<(u8, &'static str)>, Option<&'static str>, &'static str, &'static str, Vec<&'static str>);
+
+    #[test]
+    fn test_bip341_script_pubkey_vectors() {
+        let vectors: Vec<Bip341Vector> = vec![
+            (
+                "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d",
+                vec![],
+                None,
+                "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+                "bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5",
+                vec![],
+            ),
+            (
+                "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27",
+                vec![(0xc0, "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")],
+                Some("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+                "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                "bc1pz37fc4cn9ah8anwm4xqqhvxygjf9rjf2resrw8h8w4tmvcs0863sa2e586",
+                vec!["c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"],
+            ),
+            (
+                "93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820",
+                vec![(0xc0, "20b617298552a72ade070667e86ca63b8f5789a9fe8731ef91202a91c9f3459007ac")],
+                Some("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+                "e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+                "bc1punvppl2stp38f7kwv2u2spltjuvuaayuqsthe34hd2dyy5w4g58qqfuag5",
+                vec!["c093478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820"],
+            ),
+        ];
+
+        let manager = manager("mainnet");
+        for (internal_key, leaves, merkle_root, output_key, address, control_blocks) in vectors {
+            let internal_key = XOnlyPublicKey::from_str(internal_key).unwrap();
+            let tree = (!leaves.is_empty()).then(|| tree(&leaves));
+            assert_eq!(tree.as_ref().and_then(|tree| tree.root_hash).map(|root| root.to_string()).as_deref(), merkle_root);
+
+            assert_eq!(manager.generate_taproot_address(&internal_key, tree.as_ref()).unwrap(), address);
+            let output = manager.construct_taproot_output(1_000, &internal_key, tree.as_ref()).unwrap();
+            assert_eq!(output.script_pubkey.to_hex_string(), format!("5120{}", output_key));
+
+            for (index, expected) in control_blocks.iter().enumerate() {
+                let control_block = tree.as_ref().unwrap().get_control_block(index, internal_key.serialize()).unwrap();
+                assert_eq!(hex::encode(control_block), *expected);
+            }
+        }
+    }
+
+    /// (input index, internal private key, merkle root, sighash type, sighash, witness)
+    type Bip341KeySpend = (usize, &'static str, Option<&'static str>, u8, &'static str, &'static str);
+
+    #[test]
+    fn test_bip341_key_path_spending_vectors() {
+        let tx: Transaction = bitcoin::consensus::encode::deserialize_hex(
+            "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d",
+        ).unwrap();
+        let prevouts: Vec<TxOut> = [
+            ("512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343", 420_000_000),
+            ("5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3", 462_000_000),
+            ("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac", 294_000_000),
+            ("5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e", 504_000_000),
+            ("512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605", 630_000_000),
+            ("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378_000_000),
+            ("512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831", 672_000_000),
+            ("5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5", 546_000_000),
+            ("512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220", 588_000_000),
+        ]
+        .iter()
+        .map(|(script_pubkey, amount)| TxOut {
+            value: Amount::from_sat(*amount),
+            script_pubkey: ScriptBuf::from_hex(script_pubkey).unwrap(),
+        })
+        .collect();
+
+        // Shared BIP341 intermediaries: hashPrevouts, hashAmounts, hashScriptPubkeys,
+        // hashSequences and hashOutputs, in signature message order
+        let mut sig_msg = Vec::new();
+        SighashCache::new(&tx)
+            .taproot_encode_signing_data_to(&mut sig_msg, 0, &Prevouts::All(&prevouts), None, None, TapSighashType::All)
+            .unwrap();
+        let intermediaries = [
+            "e3b33bb4ef3a52ad1fffb555c0d82828eb22737036eaeb02a235d82b909c4c3f",
+            "58a6964a4f5f8f0b642ded0a8a553be7622a719da71d1f5befcefcdee8e0fde6",
+            "23ad0f61ad2bca5ba6a7693f50fce988e17c3780bf2b1e720cfbb38fbdd52e21",
+            "18959c7221ab5ce9e26c3cd67b22c24f8baa54bac281d8e6b05e400e6c3a957e",
+            "a2e6dab7c1f0dcd297c8d61647fd17d821541ea69c3cc37dcbad7f90d4eb4bc5",
+        ];
+        assert_eq!(hex::encode(&sig_msg[10..170]), intermediaries.concat());
+
+        let vectors: Vec<Bip341KeySpend> = vec![
+            (
+                0,
+                "6b973d88838f27366ed61c9ad6367663045cb456e28335c109e30717ae0c6baa",
+                None,
+                0x03,
+                "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555",
+                "ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff14d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c03",
+            ),
+            (
+                1,
+                "1e4da49f6aaf4e5cd175fe08a32bb5cb4863d963921255f33d3bc31e1343907f",
+                Some("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+                0x83,
+                "325a644af47e8a5a2591cda0ab0723978537318f10e6a63d4eed783b96a71a4d",
+                "052aedffc554b41f52b521071793a6b88d6dbca9dba94cf34c83696de0c1ec35ca9c5ed4ab28059bd606a4f3a657eec0bb96661d42921b5f50a95ad33675b54f83",
+            ),
+            (
+                3,
+                "d3c7af07da2d54f7a7735d3d0fc4f0a73164db638b2f2f7c43f711f6d4aa7e64",
+                Some("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+                0x01,
+                "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669",
+                "ff45f742a876139946a149ab4d9185574b98dc919d2eb6754f8abaa59d18b025637a3aa043b91817739554f4ed2026cf8022dbd83e351ce1fabc272841d2510a01",
+            ),
+            (
+                4,
+                "f36bb07a11e469ce941d16b63b11b9b9120a84d9d87cff2c84a8d4affb438f4e",
+                Some("ccbd66c6f7e8fdab47b3a486f59d28262be857f30d4773f2d5ea47f7761ce0e2"),
+                0x00,
+                "4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef",
+                "b4010dd48a617db09926f729e79c33ae0b4e94b79f04a1ae93ede6315eb3669de185a17d2b0ac9ee09fd4c64b678a0b61a0a86fa888a273c8511be83bfd6810f",
+            ),
+            (
+                6,
+                "415cfe9c15d9cea27d8104d5517c06e9de48e2f986b695e4f5ffebf230e725d8",
+                Some("2f6b2c5397b6d68ca18e09a3f05161668ffe93a988582d55c6f07bd5b3329def"),
+                0x02,
+                "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85",
+                "a3785919a2ce3c4ce26f298c3d51619bc474ae24014bcdd31328cd8cfbab2eff3395fa0a16fe5f486d12f22a9cedded5ae74feb4bbe5351346508c5405bcfee002",
+            ),
+            (
+                7,
+                "c7b0e81f0a9a0b0499e112279d718cca98e79a12e2f137c72ae5b213aad0d103",
+                Some("6c2dc106ab816b73f9d07e3cd1ef2c8c1256f519748e0813e4edd2405d277bef"),
+                0x82,
+                "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10",
+                "ea0c6ba90763c2d3a296ad82ba45881abb4f426b3f87af162dd24d5109edc1cdd11915095ba47c3a9963dc1e6c432939872bc49212fe34c632cd3ab9fed429c482",
+            ),
+            (
+                8,
+                "77863416be0d0665e517e1c375fd6f75839544eca553675ef7fdf4949518ebaa",
+                Some("ab179431c28d3b68fb798957faf5497d69c883c6fb1e1cd9f81483d87bac90cc"),
+                0x81,
+                "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2",
+                "bbc9584a11074e83bc8c6759ec55401f0ae7b03ef290c3139814f545b58a9f8127258000874f44bc46db7646322107d4d86aec8e73b8719a61fff761d75b5dd981",
+            ),
+        ];
+
+        let manager = manager("mainnet");
+        let secp = Secp256k1::new();
+        for (index, private_key, merkle_root, sighash_type, sighash, witness) in vectors {
+            let private_key = SecretKey::from_str(private_key).unwrap();
+            let merkle_root = merkle_root.map(|root| TapNodeHash::from_str(root).unwrap());
+            let path = KeySpendPath::new(private_key.x_only_public_key(&secp).0, merkle_root);
+            let output_key = path.output_key(&secp).to_x_only_public_key();
+            assert_eq!(prevouts[index].script_pubkey, path.script_pubkey(&secp));
+
+            let spender = KeyPathSpender::new(&secp)
+                .with_sighash_type(TapSighashType::from_consensus_u8(sighash_type).unwrap());
+            let message = spender.sighash(&tx, index, &prevouts).unwrap();
+            assert_eq!(hex::encode(message.as_ref()), sighash);
+
+            // The vectors sign without auxiliary randomness
+            let tweaked = Keypair::from_secret_key(&secp, &private_key).tap_tweak(&secp, merkle_root).to_keypair();
+            let signature = secp.sign_schnorr_no_aux_rand(&message, &tweaked);
+            let expected = Witness::from_slice(&[hex::decode(witness).unwrap()]);
+            assert_eq!(hex::encode(signature.as_ref()), witness[..128]);
+
+            let mut signed = tx.clone();
+            signed.input[index].witness = expected;
+            assert!(manager.verify_input(&signed, index, &prevouts[index], &Prevouts::All(&prevouts), &output_key).unwrap());
+
+            spender.sign_input(&mut signed, index, &prevouts, &private_key, &path).unwrap();
+            assert_eq!(signed.input[index].witness.len(), 1);
+            assert_eq!(signed.input[index].witness.nth(0).unwrap().len(), witness.len() / 2);
+            assert!(manager.verify_input(&signed, index, &prevouts[index], &Prevouts::All(&prevouts), &output_key).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_key_spend() {
+        let manager = manager("regtest");
+        let secp = Secp256k1::new();
+        let (secret_key, public_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let internal_key = public_key.x_only_public_key().0;
+        let (_, leaf_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let tree = TapTreeBuilder::new()
+            .add_script(checksig_leaf(&leaf_key.x_only_public_key().0), 1)
+            .build()
+            .unwrap();
+
+        // One output committing to a script tree, one plain BIP86 output
+        let tree_output = manager.construct_taproot_output(60_000, &internal_key, Some(&tree)).unwrap();
+        let bip86_output = manager.construct_taproot_output(40_000, &internal_key, None).unwrap();
+        let funding = "4d0ad7fee7a4d1f5cc2e3f0c0c63a5fbd5d4a1e0dbb0d2d1f17dcdf6e6d2a5b1";
+        let options = TxOptions {
+            custom_inputs: Some(vec![utxo(funding, 0, &tree_output), utxo(funding, 1, &bip86_output)]),
+            ..TxOptions::default()
+        };
+        let destination = manager.generate_taproot_address(&internal_key, None).unwrap();
+
+        let tx = manager.create_key_spend_transaction(
+            vec![(funding.to_string(), 0), (funding.to_string(), 1)],
+            vec![(destination, 99_000)],
+            &secret_key,
+            &options,
+        ).unwrap();
+        assert!(tx.input.iter().all(|input| input.witness.len() == 1));
+
+        let prevouts = vec![tree_output.clone(), bip86_output];
+        for (index, prevout) in prevouts.iter().enumerate() {
+            let output_key = taproot_output_key(&prevout.script_pubkey).unwrap();
+            assert!(manager.verify_taproot_signature_with_prevouts(&tx, index, &prevouts, &output_key).unwrap());
+            assert!(!manager.verify_taproot_signature_with_prevouts(&tx, index, &prevouts, &internal_key).unwrap());
+        }
+
+        // A SIGHASH_DEFAULT signature commits to both prevouts
+        let output_key = taproot_output_key(&tree_output.script_pubkey).unwrap();
+        assert!(manager.verify_taproot_signature(&tx, 0, &tree_output, &output_key).is_err());
+
+        // Inputs need their UTXO to be signed
+        assert!(manager.create_key_spend_transaction(
+            vec![(funding.to_string(), 2)],
+            vec![],
+            &secret_key,
+            &options,
+        ).is_err());
+    }
+
+    #[test]
+    fn test_script_spend() {
+        let manager = manager("regtest");
+        let secp = Secp256k1::new();
+        let (_, internal_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let internal_key = internal_key.x_only_public_key().0;
+        let (alice_secret, alice) = secp.generate_keypair(&mut rand::thread_rng());
+        let (_, bob) = secp.generate_keypair(&mut rand::thread_rng());
+        let alice = alice.x_only_public_key().0;
+        let bob = bob.x_only_public_key().0;
+
+        let tree = TapTreeBuilder::new()
+            .add_script(checksig_leaf(&bob), 1)
+            .add_script(checksig_leaf(&alice), 1)
+            .add_script(checksig_leaf(&bob), 2)
+            .build()
+            .unwrap();
+        let output = manager.construct_taproot_output(50_000, &internal_key, Some(&tree)).unwrap();
+        let funding = "9b1c3f7a2e5d8c4b6a0f1e2d3c4b5a69788796a5b4c3d2e1f0a9b8c7d6e5f4a3";
+        let options = TxOptions {
+            custom_inputs: Some(vec![utxo(funding, 3, &output)]),
+            rbf: false,
+            lock_time: Some(200),
+            ..TxOptions::default()
+        };
+        let destination = manager.generate_taproot_address(&alice, None).unwrap();
+
+        let path = ScriptSpendPath::new(internal_key, tree, 1).with_signing_key(alice_secret);
+        let tx = manager.create_script_spend_transaction(
+            vec![(funding.to_string(), 3)],
+            vec![(destination, 49_000)],
+            &path,
+            &options,
+        ).unwrap();
+        assert_eq!(tx.lock_time, LockTime::from_consensus(200));
+        assert_eq!(tx.input[0].sequence, Sequence::ENABLE_LOCKTIME_NO_RBF);
+        assert_eq!(tx.input[0].witness.len(), 3);
+
+        assert!(manager.verify_taproot_signature(&tx, 0, &output, &alice).unwrap());
+        assert!(!manager.verify_taproot_signature(&tx, 0, &output, &bob).unwrap());
+
+        // The signature commits to the outputs
+        let mut tampered = tx.clone();
+        tampered.output[0].value = Amount::from_sat(48_000);
+        assert!(!manager.verify_taproot_signature(&tampered, 0, &output, &alice).unwrap());
+    }
+
+    #[test]
+    fn test_musig_key_spend() {
+        let manager = manager("regtest");
+        let secp = Secp256k1::new();
+        let signers: Vec<(SecretKey, bitcoin::secp256k1::PublicKey)> =
+            (0..2).map(|_| secp.generate_keypair(&mut rand::thread_rng())).collect();
+        let mut session = manager.create_musig_session(
+            signers.iter().map(|(_, public_key)| MuSigParticipant::new(*public_key)).collect(),
+        ).unwrap();
+
+        // The aggregate key is the internal key; the session signs for the output key
+        let internal_key = session.aggregate_key().unwrap();
+        let output = manager.construct_taproot_output(30_000, &internal_key, None).unwrap();
+        session.add_taproot_tweak(None).unwrap();
+        let output_key = taproot_output_key(&output.script_pubkey).unwrap();
+        assert_eq!(session.aggregate_key().unwrap(), output_key);
+
+        let destination = manager.generate_taproot_address(&internal_key, None).unwrap();
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: Amount::from_sat(29_000),
+                script_pubkey: Address::from_str(&destination).unwrap().assume_checked().script_pubkey(),
+            }],
+        };
+        let sighash = KeyPathSpender::new(&secp).sighash(&tx, 0, std::slice::from_ref(&output)).unwrap();
+        session.set_message(sighash).unwrap();
+
+        let nonces: Vec<SecretNonce> = signers.iter().map(|(secret_key, _)| session.generate_nonce(secret_key).unwrap()).collect();
+        for (nonce, (secret_key, _)) in nonces.into_iter().zip(&signers) {
+            session.sign(nonce, secret_key).unwrap();
+        }
+        let signature = taproot::Signature {
+            signature: session.aggregate_signature().unwrap(),
+            sighash_type: bitcoin::sighash::TapSighashType::Default,
+        };
+        tx.input[0].witness = Witness::p2tr_key_spend(&signature);
+
+        assert!(manager.verify_taproot_signature(&tx, 0, &output, &output_key).unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fee_subtracted_from_amount() {
+        let manager = manager("regtest");
+        let secp = Secp256k1::new();
+        let (secret_key, public_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let internal_key = public_key.x_only_public_key().0;
+        let output = manager.construct_taproot_output(10_000, &internal_key, None).unwrap();
+        let funding = "0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0";
+        let options = TxOptions {
+            custom_inputs: Some(vec![utxo(funding, 0, &output)]),
+            subtract_fee_from_amount: true,
+            ..TxOptions::default()
+        };
+        let destination = manager.generate_taproot_address(&internal_key, None).unwrap();
+
+        let tx = manager.create_key_spend_transaction(
+            vec![(funding.to_string(), 0)],
+            vec![(destination, 10_000)],
+            &secret_key,
+            &options,
+        ).unwrap();
+        assert_eq!(tx.output[0].value.to_sat(), 10_000 - tx.vsize() as u64);
+        let output_key = taproot_output_key(&output.script_pubkey).unwrap();
+        assert!(manager.verify_taproot_signature(&tx, 0, &output, &output_key).unwrap());
+    }
+
+    #[test]
+    fn test_change_and_fee_rate() {
+        let manager = manager("regtest");
+        let secp = Secp256k1::new();
+        let (secret_key, public_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let internal_key = public_key.x_only_public_key().0;
+        let output = manager.construct_taproot_output(100_000, &internal_key, None).unwrap();
+        let funding = "2a9f1c6e8b3d5a7f0e4c2b9d6a1f8e3c5b7d0a2f4e6c8b1d3f5a7c9e0b2d4f6a";
+        let inputs = vec![(funding.to_string(), 0)];
+        let destination = manager.generate_taproot_address(&internal_key, None).unwrap();
+        let change = |index: u32| {
+            let public_key = change_key("regtest").derive_pub(&secp, &[ChildNumber::from_normal_idx(index).unwrap()]).unwrap().public_key;
+            CompressedPublicKey(public_key)
+        };
+        let options = TxOptions {
+            custom_inputs: Some(vec![utxo(funding, 0, &output)]),
+            ..TxOptions::default()
+        };
+        let spend = |outputs: Vec<(String, u64)>, options: &TxOptions| {
+            manager.create_key_spend_transaction(inputs.clone(), outputs, &secret_key, options)
+        };
+
+        // The excess over the fee goes to change at the next unused index
+        for index in 0..2 {
+            let tx = spend(vec![(destination.clone(), 60_000)], &options).unwrap();
+            assert_eq!(tx.output.len(), 2);
+            assert_eq!(tx.output[1].script_pubkey, ScriptBuf::new_p2wpkh(&change(index).wpubkey_hash()));
+            let fee = 100_000 - 60_000 - tx.output[1].value.to_sat();
+            assert_eq!(fee, tx.vsize() as u64);
+            let output_key = taproot_output_key(&output.script_pubkey).unwrap();
+            assert!(manager.verify_taproot_signature(&tx, 0, &output, &output_key).unwrap());
+        }
+
+        // An explicit index is used as is and leaves the next one alone
+        let tx = spend(vec![(destination.clone(), 60_000)], &TxOptions {
+            change_address_index: Some(7),
+            change_address_type: AddressType::Legacy,
+            ..options.clone()
+        }).unwrap();
+        assert_eq!(tx.output[1].script_pubkey, ScriptBuf::new_p2pkh(&change(7).pubkey_hash()));
+        let tx = spend(vec![(destination.clone(), 60_000)], &options).unwrap();
+        assert_eq!(tx.output[1].script_pubkey, ScriptBuf::new_p2wpkh(&change(2).wpubkey_hash()));
+
+        // A leftover below dust goes to the fee
+        let tx = spend(vec![(destination.clone(), 99_700)], &options).unwrap();
+        assert_eq!(tx.output.len(), 1);
+
+        // Inputs must pay the fee rate
+        assert!(spend(vec![(destination.clone(), 99_950)], &options).is_err());
+
+        // Without a change key the excess cannot be kept
+        let no_change = DefaultTaprootManager::new(TaprootConfig {
+            network: "regtest".to_string(),
+            ..TaprootConfig::default()
+        }).unwrap();
+        assert!(no_change.create_key_spend_transaction(inputs.clone(), vec![(destination.clone(), 60_000)], &secret_key, &options).is_err());
+        assert!(no_change.create_key_spend_transaction(inputs.clone(), vec![(destination, 99_700)], &secret_key, &options).is_ok());
+
+        // The change key must be for the manager's network
+        assert!(DefaultTaprootManager::new(TaprootConfig {
+            network: "mainnet".to_string(),
+            change_key: Some(change_key("regtest")),
+            ..TaprootConfig::default()
+        }).is_err());
+    }
+
+    #[test]
+    fn test_key_paths_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TaprootConfig {
+            network: "regtest".to_string(),
+            change_key: Some(change_key("regtest")),
+            state_file: Some(dir.path().join("taproot.json")),
+            ..TaprootConfig::default()
+        };
+        let secp = Secp256k1::new();
+        let (secret_key, public_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let internal_key = public_key.x_only_public_key().0;
+        let (_, leaf_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let tree = TapTreeBuilder::new()
+            .add_script(checksig_leaf(&leaf_key.x_only_public_key().0), 1)
+            .build()
+            .unwrap();
+
+        let output = DefaultTaprootManager::new(config.clone()).unwrap()
+            .construct_taproot_output(50_000, &internal_key, Some(&tree))
+            .unwrap();
+
+        // A restarted manager still knows the output's tweak
+        let manager = DefaultTaprootManager::new(config.clone()).unwrap();
+        let funding = "6c4e2a0f8d6b4a2c0e8f6d4b2a0c8e6f4d2b0a8c6e4f2d0b8a6c4e2f0d8b6a4c";
+        let options = TxOptions {
+            custom_inputs: Some(vec![utxo(funding, 0, &output)]),
+            ..TxOptions::default()
+        };
+        let destination = manager.generate_taproot_address(&internal_key, None).unwrap();
+        let tx = manager.create_key_spend_transaction(
+            vec![(funding.to_string(), 0)],
+            vec![(destination, 40_000)],
+            &secret_key,
+            &options,
+        ).unwrap();
+        let output_key = taproot_output_key(&output.script_pubkey).unwrap();
+        assert!(manager.verify_taproot_signature(&tx, 0, &output, &output_key).unwrap());
+
+        // So does the next change index
+        let next = DefaultTaprootManager::new(config).unwrap();
+        assert_eq!(next.read_state().unwrap().next_change_index, 1);
+    }
+}
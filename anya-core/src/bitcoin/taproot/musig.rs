@@ -0,0 +1,787 @@
+// src/bitcoin/taproot/musig.rs
+
+//! MuSig2 multi-signatures (BIP327)
+//!
+//! Signers aggregate their keys into a single (optionally Taproot-tweaked)
+//! key and produce one BIP340 signature for it in two rounds:
+//!
+//! 1. every signer generates a nonce pair and shares the public half
+//! 2. once all public nonces are in, every signer contributes a partial
+//!    signature, which anyone can verify and aggregate
+//!
+//! A [`MuSigSession`] only holds public data and serializes between rounds,
+//! so signers on different machines exchange it and [`MuSigSession::merge`]
+//! what they receive. Secret nonces never leave their signer and are consumed
+//! by [`MuSigSession::sign`].
+
+use bitcoin::hashes::Hash;
+use bitcoin::key::Parity;
+use bitcoin::secp256k1::{constants, schnorr, Message, PublicKey, Scalar, Secp256k1, SecretKey, XOnlyPublicKey};
+use bitcoin::taproot::{TapNodeHash, TapTweakHash};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::bitcoin::crypto::{reduce_scalar, tagged_hash};
+use crate::AnyaResult;
+
+/// A signer in a MuSig2 session and what it has contributed so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MuSigParticipant {
+    /// The signer's individual public key
+    pub public_key: PublicKey,
+
+    /// Round one contribution
+    pub public_nonce: Option<PublicNonce>,
+
+    /// Round two contribution
+    pub partial_signature: Option<PartialSignature>,
+}
+
+impl MuSigParticipant {
+    /// Creates a participant that has not contributed yet
+    pub fn new(public_key: PublicKey) -> Self {
+        Self {
+            public_key,
+            public_nonce: None,
+            partial_signature: None,
+        }
+    }
+}
+
+/// A tweak applied to the aggregate key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MuSigTweak {
+    /// The tweak scalar
+    pub tweak: [u8; 32],
+
+    /// Whether the tweak applies to the x-only key (as Taproot does)
+    pub x_only: bool,
+}
+
+/// The public half of a signer's nonce pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicNonce {
+    /// First nonce point
+    pub r1: PublicKey,
+
+    /// Second nonce point
+    pub r2: PublicKey,
+}
+
+impl PublicNonce {
+    /// Serializes the nonce as BIP327 66-byte `pubnonce`
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut bytes = [0u8; 66];
+        bytes[..33].copy_from_slice(&self.r1.serialize());
+        bytes[33..].copy_from_slice(&self.r2.serialize());
+        bytes
+    }
+
+    /// Parses a BIP327 66-byte `pubnonce`
+    pub fn from_slice(bytes: &[u8]) -> AnyaResult<Self> {
+        if bytes.len() != 66 {
+            return Err(format!("Public nonce must be 66 bytes, got {}", bytes.len()).into());
+        }
+        let parse = |bytes: &[u8]| PublicKey::from_slice(bytes)
+            .map_err(|e| format!("Invalid public nonce: {}", e));
+        Ok(Self {
+            r1: parse(&bytes[..33])?,
+            r2: parse(&bytes[33..])?,
+        })
+    }
+}
+
+/// The secret half of a signer's nonce pair
+///
+/// Neither `Clone` nor serializable: signing consumes it, since reusing a
+/// nonce for two signatures reveals the secret key.
+#[derive(Debug)]
+pub struct SecretNonce {
+    k1: SecretKey,
+    k2: SecretKey,
+    public_key: PublicKey,
+}
+
+/// The sum of all signers' public nonces; either point may be at infinity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateNonce {
+    /// Sum of the first nonce points
+    pub r1: Option<PublicKey>,
+
+    /// Sum of the second nonce points
+    pub r2: Option<PublicKey>,
+}
+
+impl AggregateNonce {
+    /// Sums public nonces (BIP327 `NonceAgg`)
+    pub fn aggregate(nonces: &[PublicNonce]) -> AnyaResult<Self> {
+        if nonces.is_empty() {
+            return Err("No nonces to aggregate".into());
+        }
+        Ok(Self {
+            r1: nonces.iter().fold(None, |sum, nonce| point_add(sum, Some(nonce.r1))),
+            r2: nonces.iter().fold(None, |sum, nonce| point_add(sum, Some(nonce.r2))),
+        })
+    }
+
+    /// Serializes the nonce as BIP327 66-byte `aggnonce`, infinity as zeros
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut bytes = [0u8; 66];
+        if let Some(r1) = self.r1 {
+            bytes[..33].copy_from_slice(&r1.serialize());
+        }
+        if let Some(r2) = self.r2 {
+            bytes[33..].copy_from_slice(&r2.serialize());
+        }
+        bytes
+    }
+}
+
+/// A signer's round two contribution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignature(pub [u8; 32]);
+
+/// MuSig2 key aggregation (BIP327 `KeyAgg` and `ApplyTweak`)
+#[derive(Debug, Clone)]
+pub struct MuSigAggregator {
+    public_keys: Vec<PublicKey>,
+    list_hash: [u8; 32],
+    second_key: Option<PublicKey>,
+    aggregate_key: PublicKey,
+    /// Whether the accumulated sign `gacc` is -1
+    negated: bool,
+    /// Accumulated tweak `tacc`; `None` is zero
+    tweak: Option<SecretKey>,
+}
+
+impl MuSigAggregator {
+    /// Aggregates public keys in the given order
+    pub fn new(public_keys: &[PublicKey]) -> AnyaResult<Self> {
+        let first = *public_keys.first().ok_or("Cannot aggregate an empty key set")?;
+        let serialized: Vec<[u8; 33]> = public_keys.iter().map(PublicKey::serialize).collect();
+        let parts: Vec<&[u8]> = serialized.iter().map(|key| key.as_slice()).collect();
+
+        let mut aggregator = Self {
+            public_keys: public_keys.to_vec(),
+            list_hash: tagged_hash("KeyAgg list", &parts),
+            // The second distinct key gets coefficient one, saving a multiplication
+            second_key: public_keys.iter().find(|key| **key != first).copied(),
+            aggregate_key: first,
+            negated: false,
+            tweak: None,
+        };
+
+        let secp = Secp256k1::verification_only();
+        let aggregate = public_keys.iter().fold(None, |sum, key| {
+            point_add(sum, point_mul(&secp, key, aggregator.coefficient(key)))
+        });
+        aggregator.aggregate_key = aggregate.ok_or("Aggregate key is the point at infinity")?;
+        Ok(aggregator)
+    }
+
+    /// Applies a plain or x-only tweak to the aggregate key
+    pub fn with_tweak(mut self, tweak: &MuSigTweak) -> AnyaResult<Self> {
+        let secp = Secp256k1::verification_only();
+        let t = if tweak.tweak == [0u8; 32] {
+            None
+        } else {
+            Some(SecretKey::from_slice(&tweak.tweak).map_err(|_| "Tweak is not a valid scalar")?)
+        };
+
+        // An x-only tweak applies to the even-y lift of the key
+        let negate = tweak.x_only && !has_even_y(&self.aggregate_key);
+        let key = if negate { self.aggregate_key.negate(&secp) } else { self.aggregate_key };
+        self.aggregate_key = match t {
+            Some(t) => key.add_exp_tweak(&secp, &Scalar::from(t))
+                .map_err(|_| "Tweaked key is the point at infinity")?,
+            None => key,
+        };
+        self.negated ^= negate;
+        let tweak_acc = if negate { self.tweak.map(SecretKey::negate) } else { self.tweak };
+        self.tweak = scalar_add(t, tweak_acc);
+        Ok(self)
+    }
+
+    /// The BIP341 tweak committing the aggregate key to a script tree
+    pub fn taproot_tweak(&self, merkle_root: Option<TapNodeHash>) -> MuSigTweak {
+        MuSigTweak {
+            tweak: TapTweakHash::from_key_and_tweak(self.x_only_key(), merkle_root).to_byte_array(),
+            x_only: true,
+        }
+    }
+
+    /// The aggregate (tweaked) key
+    pub fn aggregate_key(&self) -> PublicKey {
+        self.aggregate_key
+    }
+
+    /// The x-only aggregate key the final signature verifies against
+    pub fn x_only_key(&self) -> XOnlyPublicKey {
+        self.aggregate_key.x_only_public_key().0
+    }
+
+    /// The individual keys, in aggregation order
+    pub fn public_keys(&self) -> &[PublicKey] {
+        &self.public_keys
+    }
+
+    /// Key aggregation coefficient of a key; `None` is zero
+    fn coefficient(&self, public_key: &PublicKey) -> Option<SecretKey> {
+        if Some(*public_key) == self.second_key {
+            return Some(one());
+        }
+        reduce_scalar(tagged_hash("KeyAgg coefficient", &[&self.list_hash, &public_key.serialize()]))
+    }
+
+    /// Whether `g * gacc` is -1, i.e. signers must negate their keys
+    fn negate_keys(&self) -> bool {
+        !has_even_y(&self.aggregate_key) ^ self.negated
+    }
+}
+
+/// Per-message signing values (BIP327 session context)
+struct SigningContext {
+    aggregator: MuSigAggregator,
+    /// Final nonce `R`
+    nonce: PublicKey,
+    /// Nonce coefficient `b`
+    b: Option<SecretKey>,
+    /// Challenge `e`
+    e: Option<SecretKey>,
+}
+
+impl SigningContext {
+    fn new(aggregator: MuSigAggregator, aggregate_nonce: &AggregateNonce, message: &[u8]) -> Self {
+        let secp = Secp256k1::verification_only();
+        let key = aggregator.x_only_key().serialize();
+        let b = reduce_scalar(tagged_hash("MuSig/noncecoef", &[&aggregate_nonce.serialize(), &key, message]));
+
+        // R = R1 + b*R2, replaced by the generator if it is at infinity
+        let nonce = point_add(aggregate_nonce.r1, aggregate_nonce.r2.and_then(|r2| point_mul(&secp, &r2, b)))
+            .unwrap_or_else(|| PublicKey::from_secret_key(&Secp256k1::signing_only(), &one()));
+        let e = reduce_scalar(tagged_hash("BIP0340/challenge", &[
+            &nonce.x_only_public_key().0.serialize(),
+            &key,
+            message,
+        ]));
+
+        Self { aggregator, nonce, b, e }
+    }
+
+    fn sign(&self, secret_nonce: SecretNonce, secret_key: &SecretKey) -> AnyaResult<PartialSignature> {
+        let secp = Secp256k1::signing_only();
+        let public_key = PublicKey::from_secret_key(&secp, secret_key);
+        if public_key != secret_nonce.public_key {
+            return Err("Secret nonce was generated for another key".into());
+        }
+        if !self.aggregator.public_keys.contains(&public_key) {
+            return Err("Signing key is not part of the aggregate key".into());
+        }
+
+        let (k1, k2) = if has_even_y(&self.nonce) {
+            (secret_nonce.k1, secret_nonce.k2)
+        } else {
+            (secret_nonce.k1.negate(), secret_nonce.k2.negate())
+        };
+        let d = if self.aggregator.negate_keys() { secret_key.negate() } else { *secret_key };
+        let a = self.aggregator.coefficient(&public_key);
+
+        // s = k1 + b*k2 + e*a*d
+        let s = scalar_add(
+            scalar_add(Some(k1), scalar_mul(self.b, Some(k2))),
+            scalar_mul(scalar_mul(self.e, a), Some(d)),
+        );
+        Ok(PartialSignature(scalar_bytes(s)))
+    }
+
+    fn verify(&self, signature: &PartialSignature, nonce: &PublicNonce, public_key: &PublicKey) -> bool {
+        let secp = Secp256k1::new();
+        let s = if signature.0 == [0u8; 32] {
+            None
+        } else {
+            match SecretKey::from_slice(&signature.0) {
+                Ok(s) => Some(s),
+                Err(_) => return false,
+            }
+        };
+
+        // s*G == Re + e*a*g*gacc*P, where Re = R1 + b*R2 negated with R
+        let effective_nonce = point_add(Some(nonce.r1), point_mul(&secp, &nonce.r2, self.b));
+        let effective_nonce = if has_even_y(&self.nonce) {
+            effective_nonce
+        } else {
+            effective_nonce.map(|r| r.negate(&secp))
+        };
+        let challenge = scalar_mul(self.e, self.aggregator.coefficient(public_key));
+        let challenge = if self.aggregator.negate_keys() { challenge.map(SecretKey::negate) } else { challenge };
+
+        let expected = point_add(effective_nonce, point_mul(&secp, public_key, challenge));
+        s.map(|s| PublicKey::from_secret_key(&secp, &s)) == expected
+    }
+
+    fn aggregate(&self, signatures: &[PartialSignature]) -> AnyaResult<schnorr::Signature> {
+        let mut s = None;
+        for signature in signatures {
+            let partial = if signature.0 == [0u8; 32] {
+                None
+            } else {
+                Some(SecretKey::from_slice(&signature.0).map_err(|_| "Partial signature is not a valid scalar")?)
+            };
+            s = scalar_add(s, partial);
+        }
+
+        // s += e*g*tacc
+        let tweak = if has_even_y(&self.aggregator.aggregate_key) {
+            self.aggregator.tweak
+        } else {
+            self.aggregator.tweak.map(SecretKey::negate)
+        };
+        s = scalar_add(s, scalar_mul(self.e, tweak));
+
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.nonce.x_only_public_key().0.serialize());
+        bytes[32..].copy_from_slice(&scalar_bytes(s));
+        schnorr::Signature::from_slice(&bytes).map_err(|e| format!("Invalid aggregate signature: {}", e).into())
+    }
+}
+
+/// Shared state of a MuSig2 signing session
+///
+/// Holds no secrets, so it can be serialized and exchanged between rounds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MuSigSession {
+    /// Unique session identifier, mixed into nonce generation
+    pub session_id: String,
+
+    /// Signers in key aggregation order
+    pub participants: Vec<MuSigParticipant>,
+
+    /// Tweaks applied to the aggregate key, in order
+    pub tweaks: Vec<MuSigTweak>,
+
+    /// The 32-byte message being signed
+    pub message: Option<[u8; 32]>,
+}
+
+impl MuSigSession {
+    /// Creates a session for participants in key aggregation order
+    pub fn new(participants: Vec<MuSigParticipant>) -> AnyaResult<Self> {
+        if participants.is_empty() {
+            return Err("A MuSig session needs at least one participant".into());
+        }
+        for (index, participant) in participants.iter().enumerate() {
+            if participants[..index].iter().any(|other| other.public_key == participant.public_key) {
+                return Err(format!("Duplicate participant key {}", participant.public_key).into());
+            }
+        }
+
+        let session = Self {
+            session_id: uuid::Uuid::new_v4().to_string(),
+            participants,
+            tweaks: Vec::new(),
+            message: None,
+        };
+        session.aggregator()?;
+        Ok(session)
+    }
+
+    /// Creates a session for public keys in key aggregation order
+    pub fn from_public_keys(public_keys: &[PublicKey]) -> AnyaResult<Self> {
+        Self::new(public_keys.iter().copied().map(MuSigParticipant::new).collect())
+    }
+
+    /// Key aggregation with the session's tweaks applied
+    pub fn aggregator(&self) -> AnyaResult<MuSigAggregator> {
+        let public_keys: Vec<PublicKey> = self.participants.iter().map(|participant| participant.public_key).collect();
+        self.tweaks.iter().try_fold(MuSigAggregator::new(&public_keys)?, |aggregator, tweak| aggregator.with_tweak(tweak))
+    }
+
+    /// The x-only key the final signature verifies against
+    pub fn aggregate_key(&self) -> AnyaResult<XOnlyPublicKey> {
+        Ok(self.aggregator()?.x_only_key())
+    }
+
+    /// Tweaks the aggregate key
+    pub fn add_tweak(&mut self, tweak: MuSigTweak) -> AnyaResult<()> {
+        self.check_unsigned()?;
+        self.aggregator()?.with_tweak(&tweak)?;
+        self.tweaks.push(tweak);
+        Ok(())
+    }
+
+    /// Tweaks the aggregate key into a Taproot output key for a script tree
+    ///
+    /// The untweaked aggregate key becomes the output's internal key.
+    pub fn add_taproot_tweak(&mut self, merkle_root: Option<TapNodeHash>) -> AnyaResult<()> {
+        let tweak = self.aggregator()?.taproot_tweak(merkle_root);
+        self.add_tweak(tweak)
+    }
+
+    /// Sets the message to sign
+    pub fn set_message(&mut self, message: Message) -> AnyaResult<()> {
+        let message = *message.as_ref();
+        if self.message.is_some_and(|current| current != message) {
+            self.check_unsigned()?;
+        }
+        self.message = Some(message);
+        Ok(())
+    }
+
+    /// Round one: generates a nonce pair for a signer and records the public half
+    ///
+    /// The returned secret nonce must be kept by the signer for round two.
+    pub fn generate_nonce(&mut self, secret_key: &SecretKey) -> AnyaResult<SecretNonce> {
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), secret_key);
+        let aggregate_key = self.aggregate_key()?;
+        let participant = self.participant(&public_key)?;
+        if participant.public_nonce.is_some() {
+            return Err(format!("Participant {} already has a nonce in this session", public_key).into());
+        }
+
+        let (secret_nonce, public_nonce) = generate_nonce(
+            secret_key,
+            &public_key,
+            &aggregate_key,
+            self.message.as_ref(),
+            self.session_id.as_bytes(),
+        )?;
+        self.participant_mut(&public_key)?.public_nonce = Some(public_nonce);
+        Ok(secret_nonce)
+    }
+
+    /// Records another signer's public nonce
+    pub fn add_public_nonce(&mut self, public_key: &PublicKey, nonce: PublicNonce) -> AnyaResult<()> {
+        let participant = self.participant_mut(public_key)?;
+        match participant.public_nonce {
+            Some(existing) if existing != nonce => {
+                Err(format!("Participant {} already sent a different nonce", public_key).into())
+            }
+            _ => {
+                participant.public_nonce = Some(nonce);
+                Ok(())
+            }
+        }
+    }
+
+    /// The aggregate of all public nonces, once every signer has sent one
+    pub fn aggregate_nonce(&self) -> AnyaResult<AggregateNonce> {
+        let nonces: Vec<PublicNonce> = self.participants.iter().filter_map(|participant| participant.public_nonce).collect();
+        if nonces.len() < self.participants.len() {
+            return Err(format!("Waiting for nonces from {} participants", self.participants.len() - nonces.len()).into());
+        }
+        AggregateNonce::aggregate(&nonces)
+    }
+
+    /// Round two: produces and records a signer's partial signature
+    pub fn sign(&mut self, secret_nonce: SecretNonce, secret_key: &SecretKey) -> AnyaResult<PartialSignature> {
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), secret_key);
+        let own_nonce = self.participant(&public_key)?.public_nonce;
+        let secp = Secp256k1::signing_only();
+        let expected = PublicNonce {
+            r1: PublicKey::from_secret_key(&secp, &secret_nonce.k1),
+            r2: PublicKey::from_secret_key(&secp, &secret_nonce.k2),
+        };
+        if own_nonce != Some(expected) {
+            return Err("Secret nonce does not belong to this session".into());
+        }
+
+        let signature = self.signing_context()?.sign(secret_nonce, secret_key)?;
+        self.participant_mut(&public_key)?.partial_signature = Some(signature);
+        Ok(signature)
+    }
+
+    /// Checks another signer's partial signature against its nonce and key
+    pub fn verify_partial_signature(&self, public_key: &PublicKey, signature: &PartialSignature) -> AnyaResult<bool> {
+        let nonce = self.participant(public_key)?.public_nonce
+            .ok_or_else(|| format!("No nonce from participant {}", public_key))?;
+        Ok(self.signing_context()?.verify(signature, &nonce, public_key))
+    }
+
+    /// Verifies and records another signer's partial signature
+    pub fn add_partial_signature(&mut self, public_key: &PublicKey, signature: PartialSignature) -> AnyaResult<()> {
+        if !self.verify_partial_signature(public_key, &signature)? {
+            return Err(format!("Invalid partial signature from participant {}", public_key).into());
+        }
+        self.participant_mut(public_key)?.partial_signature = Some(signature);
+        Ok(())
+    }
+
+    /// Takes in the contributions of a copy of this session from another signer
+    pub fn merge(&mut self, other: &MuSigSession) -> AnyaResult<()> {
+        if other.session_id != self.session_id {
+            return Err(format!("Cannot merge session {} into {}", other.session_id, self.session_id).into());
+        }
+        let keys = |session: &MuSigSession| session.participants.iter().map(|participant| participant.public_key).collect::<Vec<_>>();
+        if keys(other) != keys(self) || other.tweaks != self.tweaks {
+            return Err("Sessions disagree on the aggregate key".into());
+        }
+        match (self.message, other.message) {
+            (Some(ours), Some(theirs)) if ours != theirs => return Err("Sessions disagree on the message".into()),
+            (None, Some(theirs)) => self.message = Some(theirs),
+            _ => {}
+        }
+
+        for participant in &other.participants {
+            if let Some(nonce) = participant.public_nonce {
+                self.add_public_nonce(&participant.public_key, nonce)?;
+            }
+        }
+        for participant in &other.participants {
+            if let Some(signature) = participant.partial_signature {
+                let ours = self.participant(&participant.public_key)?.partial_signature;
+                if ours != Some(signature) {
+                    self.add_partial_signature(&participant.public_key, signature)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether every signer has contributed a partial signature
+    pub fn is_complete(&self) -> bool {
+        self.participants.iter().all(|participant| participant.partial_signature.is_some())
+    }
+
+    /// Aggregates the partial signatures into a BIP340 signature for the aggregate key
+    pub fn aggregate_signature(&self) -> AnyaResult<schnorr::Signature> {
+        let signatures: Vec<PartialSignature> = self.participants.iter().filter_map(|participant| participant.partial_signature).collect();
+        if signatures.len() < self.participants.len() {
+            return Err(format!("Waiting for partial signatures from {} participants", self.participants.len() - signatures.len()).into());
+        }
+
+        let context = self.signing_context()?;
+        let signature = context.aggregate(&signatures)?;
+        let message = Message::from_digest(self.message.ok_or("No message to sign")?);
+        Secp256k1::verification_only()
+            .verify_schnorr(&signature, &message, &context.aggregator.x_only_key())
+            .map_err(|e| format!("Aggregate signature does not verify: {}", e))?;
+        Ok(signature)
+    }
+
+    fn signing_context(&self) -> AnyaResult<SigningContext> {
+        let message = self.message.ok_or("No message to sign")?;
+        Ok(SigningContext::new(self.aggregator()?, &self.aggregate_nonce()?, &message))
+    }
+
+    fn check_unsigned(&self) -> AnyaResult<()> {
+        if self.participants.iter().any(|participant| participant.partial_signature.is_some()) {
+            return Err("Session already has partial signatures".into());
+        }
+        Ok(())
+    }
+
+    fn participant(&self, public_key: &PublicKey) -> AnyaResult<&MuSigParticipant> {
+        self.participants.iter()
+            .find(|participant| participant.public_key == *public_key)
+            .ok_or_else(|| format!("{} is not a participant of this session", public_key).into())
+    }
+
+    fn participant_mut(&mut self, public_key: &PublicKey) -> AnyaResult<&mut MuSigParticipant> {
+        self.participants.iter_mut()
+            .find(|participant| participant.public_key == *public_key)
+            .ok_or_else(|| format!("{} is not a participant of this session", public_key).into())
+    }
+}
+
+/// BIP327 `NonceGen` with fresh randomness
+fn generate_nonce(
+    secret_key: &SecretKey,
+    public_key: &PublicKey,
+    aggregate_key: &XOnlyPublicKey,
+    message: Option<&[u8; 32]>,
+    extra_input: &[u8],
+) -> AnyaResult<(SecretNonce, PublicNonce)> {
+    let mut rand = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut rand);
+    let aux = tagged_hash("MuSig/aux", &[&rand]);
+    for ((byte, key), aux) in rand.iter_mut().zip(secret_key.secret_bytes()).zip(aux) {
+        *byte = key ^ aux;
+    }
+
+    let public_key_bytes = public_key.serialize();
+    let aggregate_key_bytes = aggregate_key.serialize();
+    let message_prefixed = match message {
+        Some(message) => [&[1u8][..], &(message.len() as u64).to_be_bytes(), message].concat(),
+        None => vec![0u8],
+    };
+    let extra_input_len = (extra_input.len() as u32).to_be_bytes();
+    let nonce = |index: u8| {
+        reduce_scalar(tagged_hash("MuSig/nonce", &[
+            &rand,
+            &[public_key_bytes.len() as u8],
+            &public_key_bytes,
+            &[aggregate_key_bytes.len() as u8],
+            &aggregate_key_bytes,
+            &message_prefixed,
+            &extra_input_len,
+            extra_input,
+            &[index],
+        ])).ok_or("Nonce reduced to zero")
+    };
+    let (k1, k2) = (nonce(0)?, nonce(1)?);
+
+    let secp = Secp256k1::signing_only();
+    let public_nonce = PublicNonce {
+        r1: PublicKey::from_secret_key(&secp, &k1),
+        r2: PublicKey::from_secret_key(&secp, &k2),
+    };
+    Ok((SecretNonce { k1, k2, public_key: *public_key }, public_nonce))
+}
+
+fn one() -> SecretKey {
+    SecretKey::from_slice(&constants::ONE).expect("one is a valid scalar")
+}
+
+// Scalars and points that may be zero or infinity are `None`
+
+fn scalar_add(a: Option<SecretKey>, b: Option<SecretKey>) -> Option<SecretKey> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.add_tweak(&Scalar::from(b)).ok(),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn scalar_mul(a: Option<SecretKey>, b: Option<SecretKey>) -> Option<SecretKey> {
+    a.zip(b).and_then(|(a, b)| a.mul_tweak(&Scalar::from(b)).ok())
+}
+
+fn scalar_bytes(scalar: Option<SecretKey>) -> [u8; 32] {
+    scalar.map(|scalar| scalar.secret_bytes()).unwrap_or([0u8; 32])
+}
+
+fn point_add(a: Option<PublicKey>, b: Option<PublicKey>) -> Option<PublicKey> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.combine(&b).ok(),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn point_mul<C: bitcoin::secp256k1::Verification>(secp: &Secp256k1<C>, point: &PublicKey, scalar: Option<SecretKey>) -> Option<PublicKey> {
+    scalar.and_then(|scalar| point.mul_tweak(secp, &Scalar::from(scalar)).ok())
+}
+
+fn has_even_y(point: &PublicKey) -> bool {
+    point.x_only_public_key().1 == Parity::Even
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use bitcoin::key::TapTweak;
+
+    fn signers(count: usize) -> Vec<(SecretKey, PublicKey)> {
+        let secp = Secp256k1::new();
+        (0..count).map(|_| secp.generate_keypair(&mut rand::thread_rng())).collect()
+    }
+
+    /// Runs both rounds with every signer on its own copy of the session
+    fn run_session(session: MuSigSession, signers: &[(SecretKey, PublicKey)], message: Message) -> schnorr::Signature {
+        let mut copies: Vec<MuSigSession> = signers.iter().map(|_| session.clone()).collect();
+        let mut secret_nonces = Vec::new();
+        for (copy, (secret_key, _)) in copies.iter_mut().zip(signers) {
+            secret_nonces.push(copy.generate_nonce(secret_key).unwrap());
+        }
+
+        // Round one: exchange serialized sessions; the message may arrive late
+        let mut coordinator: MuSigSession = serde_json::from_str(&serde_json::to_string(&session).unwrap()).unwrap();
+        for copy in &copies {
+            coordinator.merge(&serde_json::from_str(&serde_json::to_string(copy).unwrap()).unwrap()).unwrap();
+        }
+        coordinator.set_message(message).unwrap();
+
+        // Round two
+        for ((copy, secret_nonce), (secret_key, _)) in copies.iter_mut().zip(secret_nonces).zip(signers) {
+            copy.merge(&coordinator).unwrap();
+            copy.sign(secret_nonce, secret_key).unwrap();
+        }
+        for copy in &copies {
+            coordinator.merge(copy).unwrap();
+        }
+        assert!(coordinator.is_complete());
+        coordinator.aggregate_signature().unwrap()
+    }
+
+    #[test]
+    fn test_bip327_key_aggregation_vectors() {
+        let keys: Vec<PublicKey> = [
+            "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+            "03dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+            "023590a94e768f8e1815c2f24b4d80a8e3149316c3518ce7b7ad338368d038ca66",
+        ].iter().map(|key| PublicKey::from_str(key).unwrap()).collect();
+
+        let vectors = [
+            (vec![0, 1, 2], "90539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c"),
+            (vec![2, 1, 0], "6204de8b083426dc6eaf9502d27024d53fc826bf7d2012148a0575435df54b2b"),
+            (vec![0, 0, 0], "b436e3bad62b8cd409969a224731c193d051162d8c5ae8b109306127da3aa935"),
+            (vec![0, 0, 1, 1], "69bc22bfa5d106306e48a20679de1d7389386124d07571d0d872686028c26a3e"),
+        ];
+        for (indices, expected) in vectors {
+            let selected: Vec<PublicKey> = indices.iter().map(|index| keys[*index]).collect();
+            let aggregator = MuSigAggregator::new(&selected).unwrap();
+            assert_eq!(aggregator.x_only_key().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_two_round_signing() {
+        let signers = signers(3);
+        let public_keys: Vec<PublicKey> = signers.iter().map(|(_, public_key)| *public_key).collect();
+        let session = MuSigSession::from_public_keys(&public_keys).unwrap();
+        let message = Message::from_digest([7u8; 32]);
+
+        let signature = run_session(session.clone(), &signers, message);
+        let secp = Secp256k1::verification_only();
+        assert!(secp.verify_schnorr(&signature, &message, &session.aggregate_key().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_taproot_tweaked_signing() {
+        let signers = signers(2);
+        let public_keys: Vec<PublicKey> = signers.iter().map(|(_, public_key)| *public_key).collect();
+        let mut session = MuSigSession::from_public_keys(&public_keys).unwrap();
+        let internal_key = session.aggregate_key().unwrap();
+        let merkle_root = TapNodeHash::from_byte_array([3u8; 32]);
+        session.add_taproot_tweak(Some(merkle_root)).unwrap();
+
+        // The tweaked key is the BIP341 output key of the aggregate internal key
+        let secp = Secp256k1::new();
+        let (output_key, _) = internal_key.tap_tweak(&secp, Some(merkle_root));
+        assert_eq!(session.aggregate_key().unwrap(), output_key.to_x_only_public_key());
+
+        let message = Message::from_digest([9u8; 32]);
+        let signature = run_session(session, &signers, message);
+        assert!(secp.verify_schnorr(&signature, &message, &output_key.to_x_only_public_key()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_contributions_rejected() {
+        let signers = signers(2);
+        let public_keys: Vec<PublicKey> = signers.iter().map(|(_, public_key)| *public_key).collect();
+        let mut session = MuSigSession::from_public_keys(&public_keys).unwrap();
+        assert!(MuSigSession::from_public_keys(&[public_keys[0], public_keys[0]]).is_err());
+
+        let first_nonce = session.generate_nonce(&signers[0].0).unwrap();
+        let second_nonce = session.generate_nonce(&signers[1].0).unwrap();
+        assert!(session.generate_nonce(&signers[0].0).is_err());
+        session.set_message(Message::from_digest([1u8; 32])).unwrap();
+
+        // A nonce can only sign with the key it was generated for
+        assert!(session.clone().sign(first_nonce, &signers[1].0).is_err());
+
+        let signature = session.sign(second_nonce, &signers[1].0).unwrap();
+        assert!(session.verify_partial_signature(&public_keys[1], &signature).unwrap());
+        assert!(!session.verify_partial_signature(&public_keys[0], &signature).unwrap());
+        assert!(session.add_partial_signature(&public_keys[0], signature).is_err());
+        assert!(session.aggregate_signature().is_err());
+
+        // The message is fixed once signing started
+        assert!(session.set_message(Message::from_digest([2u8; 32])).is_err());
+
+        // Nonces are public and round-trip through the BIP327 encoding
+        let nonce = session.participants[0].public_nonce.unwrap();
+        assert_eq!(PublicNonce::from_slice(&nonce.serialize()).unwrap(), nonce);
+    }
+}
@@ -1,20 +1,32 @@
 // src/bitcoin/taproot/script.rs
 
 use std::collections::HashMap;
+use std::str::FromStr;
 use bitcoin::{ScriptBuf, Witness};
-use miniscript::{Miniscript, Descriptor};
+use bitcoin::hashes::Hash;
+use bitcoin::opcodes::all::{
+    OP_CHECKSIG, OP_CHECKSIGADD, OP_CLTV, OP_CSV, OP_DROP, OP_EQUAL, OP_EQUALVERIFY,
+    OP_HASH160, OP_NUMEQUAL, OP_SHA256, OP_SIZE, OP_VERIFY,
+};
+use bitcoin::opcodes::Opcode;
+use bitcoin::script::{Builder, Instruction, PushBytes};
+use bitcoin::secp256k1::{PublicKey, XOnlyPublicKey};
+use bitcoin::taproot::{LeafVersion, TapLeafHash};
+use miniscript::policy::Concrete;
+use miniscript::{Descriptor, Miniscript, Tap};
 
-use crate::common::error::AnyaResult;
+use crate::AnyaResult;
+use super::tree::{TapLeaf, TapTree, TapTreeBuilder};
 
 /// Types of Taproot scripts
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TaprootScriptType {
     /// Raw Bitcoin script
     Raw,
-    
+
     /// Miniscript-based script (more structured)
     Miniscript,
-    
+
     /// Time-locked script (CSV/CLTV)
     TimeLock {
         /// Block height or timestamp
@@ -22,7 +34,7 @@ pub enum TaprootScriptType {
         /// Whether this is a relative or absolute timelock
         is_relative: bool,
     },
-    
+
     /// Multi-signature script
     MultiSig {
         /// Required signatures
@@ -30,16 +42,16 @@ pub enum TaprootScriptType {
         /// Total participants
         total: usize,
     },
-    
+
     /// Hash lock script (preimage needed to spend)
     HashLock {
         /// Hash type (SHA256, etc.)
         hash_type: String,
     },
-    
+
     /// Point lock script (signature needed to spend)
     PointLock,
-    
+
     /// Custom script with description
     Custom {
         /// Description of the script
@@ -52,16 +64,16 @@ pub enum TaprootScriptType {
 pub struct TaprootScript {
     /// Script type
     pub script_type: TaprootScriptType,
-    
+
     /// The actual Bitcoin script
     pub script: ScriptBuf,
-    
+
     /// Script version (default: 0xc0 for Tapscript)
     pub version: u8,
-    
+
     /// Script metadata
     pub metadata: HashMap<String, String>,
-    
+
     /// If this is a Miniscript, the parsed representation
     pub miniscript: Option<String>,
 }
@@ -77,96 +89,121 @@ pub fn new(script: ScriptBuf, script_type: TaprootScriptType) -> Self {
             miniscript: None,
         }
     }
-    
+
     /// Creates a new Taproot script from a Miniscript
     pub fn from_miniscript(miniscript_str: &str) -> AnyaResult<Self> {
-        // Implementation goes here
-        // Parse miniscript, convert to Script
-        
-        // Placeholder for now
-        Ok(Self {
-            script_type: TaprootScriptType::Miniscript,
-            script: ScriptBuf::new(),
-            version: 0xc0,
-            metadata: HashMap::new(),
-            miniscript: Some(miniscript_str.to_string()),
-        })
+        let miniscript = ScriptParser::parse_miniscript(miniscript_str)?;
+        let mut script = Self::new(miniscript.encode(), TaprootScriptType::Miniscript);
+        script.miniscript = Some(miniscript.to_string());
+        Ok(script)
     }
-    
+
     /// Creates a multi-signature script
+    ///
+    /// Tapscript has no `OP_CHECKMULTISIG`; the script counts valid
+    /// signatures with `OP_CHECKSIGADD` (miniscript `multi_a`):
+    /// `<pk1> CHECKSIG <pk2> CHECKSIGADD ... <pkn> CHECKSIGADD <k> NUMEQUAL`.
+    /// Keys are hex x-only or compressed public keys.
     pub fn create_multisig(threshold: usize, public_keys: &[&str]) -> AnyaResult<Self> {
-        // Implementation goes here
-        // Create a threshold signature script
-        
-        // Placeholder for now
-        Ok(Self {
-            script_type: TaprootScriptType::MultiSig {
-                threshold,
-                total: public_keys.len(),
-            },
-            script: ScriptBuf::new(),
-            version: 0xc0,
-            metadata: HashMap::new(),
-            miniscript: None,
-        })
+        if public_keys.is_empty() || threshold == 0 || threshold > public_keys.len() {
+            return Err(format!("Invalid {}-of-{} multisig", threshold, public_keys.len()).into());
+        }
+
+        let mut builder = Builder::new();
+        for (index, key) in public_keys.iter().enumerate() {
+            let opcode = if index == 0 { OP_CHECKSIG } else { OP_CHECKSIGADD };
+            builder = builder.push_x_only_key(&parse_key(key)?).push_opcode(opcode);
+        }
+        let script = builder
+            .push_int(threshold as i64)
+            .push_opcode(OP_NUMEQUAL)
+            .into_script();
+
+        Ok(Self::new(script, TaprootScriptType::MultiSig {
+            threshold,
+            total: public_keys.len(),
+        }))
     }
-    
+
     /// Creates a time-locked script
+    ///
+    /// Prefixes `base_script` with `<lock> CHECKLOCKTIMEVERIFY VERIFY`, or
+    /// `CHECKSEQUENCEVERIFY` for a relative lock (miniscript `and_v(v:after(..), ..)`
+    /// and `and_v(v:older(..), ..)`).
     pub fn create_timelock(base_script: &TaprootScript, lock_value: u32, is_relative: bool) -> AnyaResult<Self> {
-        // Implementation goes here
-        // Create a timelock wrapper around base_script
-        
-        // Placeholder for now
-        Ok(Self {
-            script_type: TaprootScriptType::TimeLock {
-                lock_value,
-                is_relative,
-            },
-            script: ScriptBuf::new(),
-            version: 0xc0,
-            metadata: HashMap::new(),
-            miniscript: None,
-        })
+        // Script numbers are 4 bytes; this also keeps the CSV disable flag clear
+        if lock_value == 0 || lock_value > i32::MAX as u32 {
+            return Err(format!("Invalid lock value {}", lock_value).into());
+        }
+        if base_script.script.is_empty() {
+            return Err("Cannot time-lock an empty script".into());
+        }
+
+        let opcode = if is_relative { OP_CSV } else { OP_CLTV };
+        let mut script = Builder::new()
+            .push_int(lock_value as i64)
+            .push_opcode(opcode)
+            .push_opcode(OP_VERIFY)
+            .into_bytes();
+        script.extend_from_slice(base_script.script.as_bytes());
+
+        Ok(Self::new(ScriptBuf::from_bytes(script), TaprootScriptType::TimeLock {
+            lock_value,
+            is_relative,
+        }))
     }
-    
+
     /// Creates a hash lock script
+    ///
+    /// `SIZE 32 EQUALVERIFY <SHA256|HASH160> <hash> EQUAL`, as miniscript's
+    /// `sha256` and `hash160` fragments; the size check pins the preimage to
+    /// 32 bytes. `hash_type` is `sha256` or `hash160`.
     pub fn create_hashlock(hash_hex: &str, hash_type: &str) -> AnyaResult<Self> {
-        // Implementation goes here
-        // Create a hash lock script
-        
-        // Placeholder for now
-        Ok(Self {
-            script_type: TaprootScriptType::HashLock {
-                hash_type: hash_type.to_string(),
-            },
-            script: ScriptBuf::new(),
-            version: 0xc0,
-            metadata: HashMap::new(),
-            miniscript: None,
-        })
+        let hash = hex::decode(hash_hex).map_err(|e| format!("Invalid hash: {}", e))?;
+        let (opcode, length) = match hash_type.to_lowercase().as_str() {
+            "sha256" => (OP_SHA256, 32),
+            "hash160" => (OP_HASH160, 20),
+            other => return Err(format!("Unsupported hash type: {}", other).into()),
+        };
+        if hash.len() != length {
+            return Err(format!("{} hash must be {} bytes, got {}", hash_type, length, hash.len()).into());
+        }
+        let hash = <&PushBytes>::try_from(hash.as_slice()).map_err(|e| format!("Invalid hash: {}", e))?;
+
+        let script = Builder::new()
+            .push_opcode(OP_SIZE)
+            .push_int(32)
+            .push_opcode(OP_EQUALVERIFY)
+            .push_opcode(opcode)
+            .push_slice(hash)
+            .push_opcode(OP_EQUAL)
+            .into_script();
+
+        Ok(Self::new(script, TaprootScriptType::HashLock {
+            hash_type: hash_type.to_lowercase(),
+        }))
     }
-    
+
     /// Adds metadata to the script
     pub fn add_metadata(&mut self, key: &str, value: &str) {
         self.metadata.insert(key.to_string(), value.to_string());
     }
-    
+
     /// Gets the leaf hash of this script
     pub fn leaf_hash(&self) -> AnyaResult<Vec<u8>> {
-        // Implementation goes here
-        // Calculate TapLeafHash
-        
-        // Placeholder for now
-        Ok(vec![])
+        let version = LeafVersion::from_consensus(self.version)
+            .map_err(|e| format!("Invalid leaf version: {}", e))?;
+        Ok(TapLeafHash::from_script(&self.script, version).to_byte_array().to_vec())
     }
-    
+
     /// Converts this script to a witness
+    ///
+    /// The script's own inputs are pushed ahead of these two elements.
     pub fn to_witness(&self, control_block: Vec<u8>) -> Witness {
-        // Implementation goes here
-        // Create a witness for spending this script
-        
-        // Placeholder for now
-        Witness::new()
+        let mut witness = Witness::new();
+        witness.push(self.script.as_bytes());
+        witness.push(control_block);
+        witness
     }
 }
 
@@ -175,7 +212,7 @@ pub struct TaprootScriptBuilder {
     script_type: TaprootScriptType,
     version: u8,
     metadata: HashMap<String, String>,
-    operations: Vec<String>,
+    script: Builder,
 }
 
 impl TaprootScriptBuilder {
@@ -185,37 +222,51 @@ pub fn new(script_type: TaprootScriptType) -> Self {
             script_type,
             version: 0xc0, // Tapscript version
             metadata: HashMap::new(),
-            operations: Vec::new(),
+            script: Builder::new(),
         }
     }
-    
+
     /// Sets the script version
     pub fn with_version(mut self, version: u8) -> Self {
         self.version = version;
         self
     }
-    
+
     /// Adds an operation to the script
-    pub fn add_op(mut self, op: &str) -> Self {
-        self.operations.push(op.to_string());
+    pub fn add_op(mut self, op: Opcode) -> Self {
+        self.script = self.script.push_opcode(op);
         self
     }
-    
+
+    /// Pushes an x-only public key
+    pub fn push_key(mut self, key: &XOnlyPublicKey) -> Self {
+        self.script = self.script.push_x_only_key(key);
+        self
+    }
+
+    /// Pushes a script number
+    pub fn push_int(mut self, value: i64) -> Self {
+        self.script = self.script.push_int(value);
+        self
+    }
+
+    /// Pushes raw data
+    pub fn push_slice<T: AsRef<PushBytes>>(mut self, data: T) -> Self {
+        self.script = self.script.push_slice(data);
+        self
+    }
+
     /// Adds metadata to the script
     pub fn add_metadata(mut self, key: &str, value: &str) -> Self {
         self.metadata.insert(key.to_string(), value.to_string());
         self
     }
-    
+
     /// Builds the Taproot script
     pub fn build(self) -> AnyaResult<TaprootScript> {
-        // Implementation goes here
-        // Convert operations to ScriptBuf
-        
-        // Placeholder for now
         Ok(TaprootScript {
             script_type: self.script_type,
-            script: ScriptBuf::new(),
+            script: self.script.into_script(),
             version: self.version,
             metadata: self.metadata,
             miniscript: None,
@@ -223,24 +274,273 @@ pub fn build(self) -> AnyaResult<TaprootScript> {
     }
 }
 
+/// A spending policy compiled into Taproot script leaves
+#[derive(Debug, Clone)]
+pub struct CompiledPolicy {
+    /// Key the compiler moved to the key path, if the policy has a key that
+    /// can spend alone; otherwise the caller's unspendable key is used
+    pub internal_key: XOnlyPublicKey,
+
+    /// Script leaves, weighted by how likely the compiler found each branch
+    pub leaves: Vec<TapLeaf>,
+}
+
+impl CompiledPolicy {
+    /// Builds the script tree of the compiled leaves
+    pub fn into_tree(self) -> AnyaResult<TapTree> {
+        self.leaves.into_iter()
+            .fold(TapTreeBuilder::new(), |builder, leaf| builder.add_leaf(leaf))
+            .build()
+    }
+}
+
+/// Compiles miniscript policies to Taproot script leaves
+pub struct PolicyCompiler;
+
+impl PolicyCompiler {
+    /// Compiles a policy such as `or(99@pk(A),1@and(pk(B),older(144)))`
+    ///
+    /// Disjunctions are split into separate leaves, and a key that can spend
+    /// alone becomes the internal key. Policies without such a key need
+    /// `unspendable_key` as the internal key.
+    pub fn compile(policy: &str, unspendable_key: Option<XOnlyPublicKey>) -> AnyaResult<CompiledPolicy> {
+        let policy = Concrete::<XOnlyPublicKey>::from_str(policy)
+            .map_err(|e| format!("Invalid policy: {}", e))?;
+        let descriptor = policy.compile_tr(unspendable_key)
+            .map_err(|e| format!("Policy compilation failed: {}", e))?;
+        let Descriptor::Tr(tr) = descriptor else {
+            return Err("Policy did not compile to a Taproot descriptor".into());
+        };
+
+        // The compiler places likely branches higher in its tree; weight
+        // leaves so that rebuilding the tree keeps them there
+        let scripts: Vec<(u8, ScriptBuf, String)> = tr.iter_scripts()
+            .map(|(depth, miniscript)| (depth, miniscript.encode(), miniscript.to_string()))
+            .collect();
+        let max_depth = scripts.iter().map(|(depth, _, _)| *depth).max().unwrap_or(0);
+        let mut leaves = Vec::with_capacity(scripts.len());
+        for (depth, script, miniscript) in scripts {
+            let script_type = ScriptParser::parse_script(&script)?;
+            let mut script = TaprootScript::new(script, script_type);
+            script.miniscript = Some(miniscript);
+            leaves.push(TapLeaf::new(script, 1 << (max_depth - depth).min(31)));
+        }
+
+        Ok(CompiledPolicy {
+            internal_key: *tr.internal_key(),
+            leaves,
+        })
+    }
+}
+
 /// Helper for parsing scripts
 pub struct ScriptParser;
 
 impl ScriptParser {
     /// Parses a Bitcoin script
+    ///
+    /// Recognises the templates built by [`TaprootScript`]; other valid
+    /// miniscript is reported as `Miniscript`, anything else as `Raw`.
     pub fn parse_script(script: &ScriptBuf) -> AnyaResult<TaprootScriptType> {
-        // Implementation goes here
-        // Detect script type from script
-        
-        // Placeholder for now
+        let instructions = script.instructions()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid script: {}", e))?;
+
+        if let Some(script_type) = Self::parse_template(&instructions) {
+            return Ok(script_type);
+        }
+        if Self::is_miniscript(script) {
+            return Ok(TaprootScriptType::Miniscript);
+        }
         Ok(TaprootScriptType::Raw)
     }
-    
+
     /// Parses a Miniscript
-    pub fn parse_miniscript(miniscript_str: &str) -> AnyaResult<Miniscript<String>> {
-        // Implementation goes here
-        
-        // Placeholder for now
-        Err("Miniscript parsing not yet implemented".into())
+    pub fn parse_miniscript(miniscript_str: &str) -> AnyaResult<Miniscript<XOnlyPublicKey, Tap>> {
+        Miniscript::<XOnlyPublicKey, Tap>::from_str(miniscript_str)
+            .map_err(|e| format!("Invalid miniscript: {}", e).into())
+    }
+
+    fn is_miniscript(script: &ScriptBuf) -> bool {
+        Miniscript::<XOnlyPublicKey, Tap>::parse(script).is_ok()
+    }
+
+    fn parse_template(instructions: &[Instruction]) -> Option<TaprootScriptType> {
+        use Instruction::{Op, PushBytes};
+
+        match instructions {
+            [PushBytes(key), Op(OP_CHECKSIG)] if is_x_only_key(key) => Some(TaprootScriptType::PointLock),
+            [lock, Op(check), Op(OP_VERIFY | OP_DROP), base @ ..]
+                if (*check == OP_CLTV || *check == OP_CSV) && !base.is_empty() =>
+            {
+                let lock_value = lock.script_num()
+                    .filter(|value| *value > 0)
+                    .and_then(|value| u32::try_from(value).ok())?;
+                Some(TaprootScriptType::TimeLock {
+                    lock_value,
+                    is_relative: *check == OP_CSV,
+                })
+            }
+            [Op(OP_SIZE), size, Op(OP_EQUALVERIFY), Op(hash_op), PushBytes(hash), Op(OP_EQUAL)]
+                if size.script_num() == Some(32) =>
+            {
+                let hash_type = match (*hash_op, hash.len()) {
+                    (OP_SHA256, 32) => "sha256",
+                    (OP_HASH160, 20) => "hash160",
+                    _ => return None,
+                };
+                Some(TaprootScriptType::HashLock {
+                    hash_type: hash_type.to_string(),
+                })
+            }
+            [PushBytes(first), Op(OP_CHECKSIG), rest @ .., threshold, Op(OP_NUMEQUAL)]
+                if is_x_only_key(first) && rest.len() % 2 == 0 =>
+            {
+                let all_keys = rest.chunks(2).all(|pair| {
+                    matches!(pair, [PushBytes(key), Op(OP_CHECKSIGADD)] if is_x_only_key(key))
+                });
+                let total = 1 + rest.len() / 2;
+                let threshold = threshold.script_num()
+                    .and_then(|value| usize::try_from(value).ok())
+                    .filter(|value| (1..=total).contains(value))?;
+                all_keys.then_some(TaprootScriptType::MultiSig { threshold, total })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_x_only_key(bytes: &PushBytes) -> bool {
+    XOnlyPublicKey::from_slice(bytes.as_bytes()).is_ok()
+}
+
+/// Parses a hex x-only or compressed public key
+fn parse_key(key: &str) -> AnyaResult<XOnlyPublicKey> {
+    XOnlyPublicKey::from_str(key)
+        .or_else(|_| PublicKey::from_str(key).map(|key| key.x_only_public_key().0))
+        .map_err(|e| format!("Invalid public key {}: {}", key, e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::{hash160, sha256};
+    use bitcoin::secp256k1::Secp256k1;
+
+    fn keys(count: usize) -> Vec<String> {
+        let secp = Secp256k1::new();
+        (0..count)
+            .map(|_| secp.generate_keypair(&mut rand::thread_rng()).1.x_only_public_key().0.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_templates_round_trip() {
+        let keys = keys(3);
+        let key_refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let multisig = TaprootScript::create_multisig(2, &key_refs).unwrap();
+        let single = TaprootScript::create_multisig(1, &key_refs[..1]).unwrap();
+        let point_lock = TaprootScriptBuilder::new(TaprootScriptType::PointLock)
+            .push_key(&XOnlyPublicKey::from_str(&keys[0]).unwrap())
+            .add_op(OP_CHECKSIG)
+            .build()
+            .unwrap();
+
+        let sha256_hash = sha256::Hash::hash(&[1u8; 32]).to_string();
+        let hash160_hash = hash160::Hash::hash(&[2u8; 32]).to_string();
+        let scripts = vec![
+            multisig.clone(),
+            single,
+            point_lock.clone(),
+            TaprootScript::create_timelock(&multisig, 144, true).unwrap(),
+            TaprootScript::create_timelock(&point_lock, 800_000, false).unwrap(),
+            TaprootScript::create_timelock(&point_lock, 1_700_000_000, false).unwrap(),
+            TaprootScript::create_hashlock(&sha256_hash, "sha256").unwrap(),
+            TaprootScript::create_hashlock(&hash160_hash, "HASH160").unwrap(),
+        ];
+        for script in scripts {
+            assert_eq!(ScriptParser::parse_script(&script.script).unwrap(), script.script_type, "{}", script.script);
+        }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_template_scripts() {
+        let keys = keys(2);
+        let multisig = TaprootScript::create_multisig(2, &[&keys[0], &keys[1]]).unwrap();
+        assert_eq!(
+            multisig.script.to_asm_string(),
+            format!("OP_PUSHBYTES_32 {} OP_CHECKSIG OP_PUSHBYTES_32 {} OP_CHECKSIGADD OP_PUSHNUM_2 OP_NUMEQUAL", keys[0], keys[1]),
+        );
+
+        let timelock = TaprootScript::create_timelock(&multisig, 144, true).unwrap();
+        assert!(timelock.script.to_asm_string().starts_with("OP_PUSHBYTES_2 9000 OP_CSV OP_VERIFY OP_PUSHBYTES_32"));
+
+        let hash = sha256::Hash::hash(b"preimage").to_string();
+        let hashlock = TaprootScript::create_hashlock(&hash, "sha256").unwrap();
+        assert_eq!(
+            hashlock.script.to_asm_string(),
+            format!("OP_SIZE OP_PUSHBYTES_1 20 OP_EQUALVERIFY OP_SHA256 OP_PUSHBYTES_32 {} OP_EQUAL", hash),
+        );
+
+        // Compressed keys are accepted and reduced to x-only
+        let secp = Secp256k1::new();
+        let compressed = secp.generate_keypair(&mut rand::thread_rng()).1;
+        let from_compressed = TaprootScript::create_multisig(1, &[&compressed.to_string()]).unwrap();
+        let from_x_only = TaprootScript::create_multisig(1, &[&compressed.x_only_public_key().0.to_string()]).unwrap();
+        assert_eq!(from_compressed.script, from_x_only.script);
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let keys = keys(2);
+        assert!(TaprootScript::create_multisig(3, &[&keys[0], &keys[1]]).is_err());
+        assert!(TaprootScript::create_multisig(0, &[&keys[0]]).is_err());
+        assert!(TaprootScript::create_multisig(1, &["not a key"]).is_err());
+
+        let point_lock = TaprootScript::create_multisig(1, &[&keys[0]]).unwrap();
+        assert!(TaprootScript::create_timelock(&point_lock, 0, true).is_err());
+        assert!(TaprootScript::create_timelock(&point_lock, 1 << 31, true).is_err());
+
+        assert!(TaprootScript::create_hashlock(&"00".repeat(20), "sha256").is_err());
+        assert!(TaprootScript::create_hashlock(&"00".repeat(32), "ripemd160").is_err());
+
+        // Unknown scripts parse as raw
+        let raw = Builder::new().push_opcode(OP_DROP).into_script();
+        assert_eq!(ScriptParser::parse_script(&raw).unwrap(), TaprootScriptType::Raw);
+    }
+
+    #[test]
+    fn test_miniscript() {
+        let keys = keys(2);
+        let multi_a = TaprootScript::from_miniscript(&format!("multi_a(1,{},{})", keys[0], keys[1])).unwrap();
+        assert_eq!(multi_a.script, TaprootScript::create_multisig(1, &[&keys[0], &keys[1]]).unwrap().script);
+        assert_eq!(ScriptParser::parse_script(&multi_a.script).unwrap(), TaprootScriptType::MultiSig { threshold: 1, total: 2 });
+
+        // Valid miniscript that matches no template
+        let or_d = TaprootScript::from_miniscript(&format!("or_d(pk({}),pk({}))", keys[0], keys[1])).unwrap();
+        assert_eq!(ScriptParser::parse_script(&or_d.script).unwrap(), TaprootScriptType::Miniscript);
+
+        assert!(TaprootScript::from_miniscript("pk(nonsense)").is_err());
+    }
+
+    #[test]
+    fn test_policy_compiler() {
+        let keys = keys(4);
+        let policy = format!("or(9@pk({}),1@or(1@and(pk({}),older(144)),1@pk({})))", keys[0], keys[1], keys[2]);
+        let compiled = PolicyCompiler::compile(&policy, None).unwrap();
+
+        // The likely key spends through the key path; the rest become leaves
+        assert_eq!(compiled.internal_key.to_string(), keys[0]);
+        assert_eq!(compiled.leaves.len(), 2);
+        let types: Vec<TaprootScriptType> = compiled.leaves.iter().map(|leaf| leaf.script.script_type.clone()).collect();
+        assert!(types.contains(&TaprootScriptType::PointLock));
+        assert!(compiled.leaves.iter().all(|leaf| leaf.script.miniscript.is_some()));
+        assert!(compiled.into_tree().unwrap().root_hash.is_some());
+
+        // Without a lone key there is no internal key to lift
+        let multisig = format!("thresh(2,pk({}),pk({}),pk({}))", keys[0], keys[1], keys[2]);
+        assert!(PolicyCompiler::compile(&multisig, None).is_err());
+        let unspendable = XOnlyPublicKey::from_str(&keys[3]).unwrap();
+        assert_eq!(PolicyCompiler::compile(&multisig, Some(unspendable)).unwrap().internal_key, unspendable);
+    }
+}
@@ -0,0 +1,151 @@
+// src/bitcoin/taproot/script_spend.rs
+
+use bitcoin::key::Keypair;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, Signing, Verification};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{self, ControlBlock, TapLeafHash};
+use bitcoin::{ScriptBuf, Transaction, TxOut, XOnlyPublicKey};
+
+use crate::AnyaResult;
+use super::tree::{TapLeaf, TapTree};
+
+/// A script path of a Taproot output: one leaf of a built tree and
+/// everything needed to satisfy it
+#[derive(Debug, Clone)]
+pub struct ScriptSpendPath {
+    /// The untweaked internal key of the output
+    pub internal_key: XOnlyPublicKey,
+
+    /// The script tree committed to by the output
+    pub tree: TapTree,
+
+    /// Index of the leaf being spent
+    pub leaf_index: usize,
+
+    /// Keys whose signatures the leaf script checks, in the order it checks them
+    pub signing_keys: Vec<SecretKey>,
+
+    /// Other witness data the script consumes before the signatures (e.g. hash preimages)
+    pub preimages: Vec<Vec<u8>>,
+}
+
+impl ScriptSpendPath {
+    /// Creates a path spending `leaf_index` of a built tree
+    pub fn new(internal_key: XOnlyPublicKey, tree: TapTree, leaf_index: usize) -> Self {
+        Self {
+            internal_key,
+            tree,
+            leaf_index,
+            signing_keys: Vec::new(),
+            preimages: Vec::new(),
+        }
+    }
+
+    /// Adds a key whose signature the leaf script checks
+    pub fn with_signing_key(mut self, key: SecretKey) -> Self {
+        self.signing_keys.push(key);
+        self
+    }
+
+    /// Adds a preimage the leaf script consumes
+    pub fn with_preimage(mut self, preimage: Vec<u8>) -> Self {
+        self.preimages.push(preimage);
+        self
+    }
+
+    /// The leaf being spent
+    pub fn leaf(&self) -> AnyaResult<&TapLeaf> {
+        self.tree.leaves.get(self.leaf_index)
+            .ok_or_else(|| format!("Leaf index {} not found in tree", self.leaf_index).into())
+    }
+
+    /// The control block proving the leaf is committed to by the output key
+    pub fn control_block(&self) -> AnyaResult<ControlBlock> {
+        let bytes = self.tree.get_control_block(self.leaf_index, self.internal_key.serialize())?;
+        ControlBlock::decode(&bytes).map_err(|e| format!("Invalid control block: {}", e).into())
+    }
+
+    /// The P2TR scriptPubKey committing to the tree
+    pub fn script_pubkey<C: Verification>(&self, secp: &Secp256k1<C>) -> AnyaResult<ScriptBuf> {
+        let merkle_root = self.tree.root_hash.ok_or("Taproot tree has not been built")?;
+        Ok(ScriptBuf::new_p2tr(secp, self.internal_key, Some(merkle_root)))
+    }
+}
+
+/// Signs Taproot inputs through a script path
+pub struct ScriptPathSpender<'a, C: Signing + Verification> {
+    secp: &'a Secp256k1<C>,
+    sighash_type: TapSighashType,
+}
+
+impl<'a, C: Signing + Verification> ScriptPathSpender<'a, C> {
+    /// Creates a spender signing with `SIGHASH_DEFAULT`
+    pub fn new(secp: &'a Secp256k1<C>) -> Self {
+        Self {
+            secp,
+            sighash_type: TapSighashType::Default,
+        }
+    }
+
+    /// Sets the sighash type of the signatures
+    pub fn with_sighash_type(mut self, sighash_type: TapSighashType) -> Self {
+        self.sighash_type = sighash_type;
+        self
+    }
+
+    /// Computes the BIP341 script path sighash of an input for a leaf
+    pub fn sighash(
+        &self,
+        tx: &Transaction,
+        input_index: usize,
+        prevouts: &[TxOut],
+        leaf_hash: TapLeafHash,
+    ) -> AnyaResult<Message> {
+        let sighash = SighashCache::new(tx)
+            .taproot_script_spend_signature_hash(input_index, &Prevouts::All(prevouts), leaf_hash, self.sighash_type)
+            .map_err(|e| format!("Failed to compute sighash for input {}: {}", input_index, e))?;
+        Ok(Message::from(sighash))
+    }
+
+    /// Signs an input with the path's keys and sets its witness
+    ///
+    /// The witness stack is the path's preimages and signatures (in the order
+    /// the script consumes them, so the first ends up on top), then the leaf
+    /// script and its control block.
+    pub fn sign_input(
+        &self,
+        tx: &mut Transaction,
+        input_index: usize,
+        prevouts: &[TxOut],
+        path: &ScriptSpendPath,
+    ) -> AnyaResult<()> {
+        let prevout = prevouts.get(input_index)
+            .ok_or_else(|| format!("Missing prevout for input {}", input_index))?;
+        if prevout.script_pubkey != path.script_pubkey(self.secp)? {
+            return Err(format!("Input {} does not spend this script tree", input_index).into());
+        }
+
+        let leaf = path.leaf()?;
+        let leaf_hash = leaf.leaf_hash()?;
+        let message = self.sighash(tx, input_index, prevouts, leaf_hash)?;
+
+        let mut stack: Vec<Vec<u8>> = path.preimages.clone();
+        for key in &path.signing_keys {
+            let keypair = Keypair::from_secret_key(self.secp, key);
+            let signature = taproot::Signature {
+                signature: self.secp.sign_schnorr_with_aux_rand(&message, &keypair, &rand::random()),
+                sighash_type: self.sighash_type,
+            };
+            stack.push(signature.to_vec());
+        }
+
+        let witness = &mut tx.input[input_index].witness;
+        witness.clear();
+        for element in stack.iter().rev() {
+            witness.push(element);
+        }
+        witness.push(leaf.script.script.as_bytes());
+        witness.push(path.control_block()?.serialize());
+        Ok(())
+    }
+}
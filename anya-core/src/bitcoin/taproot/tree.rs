@@ -1,10 +1,17 @@
 // src/bitcoin/taproot/tree.rs
 
-use std::collections::HashMap;
-use bitcoin::hashes::{Hash, HashEngine};
-use bitcoin::taproot::{TapLeafHash, TapBranchHash, TapNodeHash, LeafVersion};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use bitcoin::hashes::Hash;
+use bitcoin::key::TapTweak;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::taproot::{
+    ControlBlock, TapLeafHash, TapNodeHash, LeafVersion, TaprootMerkleBranch,
+    TAPROOT_CONTROL_BASE_SIZE, TAPROOT_CONTROL_MAX_NODE_COUNT, TAPROOT_CONTROL_NODE_SIZE,
+};
+use bitcoin::{VarInt, XOnlyPublicKey};
 
-use crate::common::error::AnyaResult;
+use crate::AnyaResult;
 use super::script::TaprootScript;
 
 /// Represents a leaf in a Taproot Merkle tree
@@ -16,7 +23,8 @@ pub struct TapLeaf {
     /// The leaf version (usually 0xc0 for Tapscript)
     pub version: LeafVersion,
     
-    /// Weight for ordering in the tree (lower weights are prioritized)
+    /// Relative likelihood of spending this leaf; heavier leaves get
+    /// shorter merkle proofs
     pub weight: u32,
     
     /// Additional metadata
@@ -28,7 +36,7 @@ impl TapLeaf {
     pub fn new(script: TaprootScript, weight: u32) -> Self {
         Self {
             script,
-            version: LeafVersion::from_consensus(0xc0), // Tapscript version
+            version: LeafVersion::TapScript,
             weight,
             metadata: HashMap::new(),
         }
@@ -36,11 +44,7 @@ pub fn new(script: TaprootScript, weight: u32) -> Self {
     
     /// Gets the leaf hash for this leaf
     pub fn leaf_hash(&self) -> AnyaResult<TapLeafHash> {
-        // Implementation goes here
-        // Compute the TapLeafHash
-        
-        // Placeholder for now
-        Err("Leaf hash computation not yet implemented".into())
+        Ok(TapLeafHash::from_script(&self.script.script, self.version))
     }
     
     /// Adds metadata to this leaf
@@ -63,7 +67,7 @@ impl TapBranch {
     /// Creates a new branch from left and right nodes
     pub fn new(left: TapNodeHash, right: TapNodeHash) -> Self {
         // Ensure lexicographic ordering
-        if left[..] < right[..] {
+        if left.as_byte_array() < right.as_byte_array() {
             Self { left, right }
         } else {
             Self { left: right, right: left }
@@ -71,15 +75,8 @@ pub fn new(left: TapNodeHash, right: TapNodeHash) -> Self {
     }
     
     /// Computes the branch hash
-    pub fn branch_hash(&self) -> TapBranchHash {
-        // Implementation goes here
-        // Compute the TapBranchHash
-        
-        // Placeholder for now
-        let mut engine = TapBranchHash::engine();
-        engine.input(&self.left[..]);
-        engine.input(&self.right[..]);
-        TapBranchHash::from_engine(engine)
+    pub fn branch_hash(&self) -> TapNodeHash {
+        TapNodeHash::from_node_hashes(self.left, self.right)
     }
 }
 
@@ -99,6 +96,12 @@ pub struct TapTree {
     pub root_hash: Option<TapNodeHash>,
 }
 
+impl Default for TapTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TapTree {
     /// Creates a new empty Taproot tree
     pub fn new() -> Self {
@@ -118,22 +121,91 @@ pub fn add_leaf(&mut self, leaf: TapLeaf) {
     }
     
     /// Builds the tree from the current leaves
+    ///
+    /// The tree is a Huffman tree over the leaf weights: the two lightest
+    /// nodes are merged until one is left, so the expected proof length is
+    /// minimal. Ties are broken by node hash exactly as rust-bitcoin's
+    /// `TaprootBuilder::with_huffman_tree` does, giving the same root for the
+    /// same leaves. Leaf indices stay in insertion order.
     pub fn build(&mut self) -> AnyaResult<TapNodeHash> {
         // Reset the tree structure
         self.branches.clear();
         self.leaf_positions.clear();
+        self.root_hash = None;
         
-        // Sort leaves by weight
-        self.leaves.sort_by_key(|leaf| leaf.weight);
+        if self.leaves.is_empty() {
+            return Err("Cannot build a Taproot tree without leaves".into());
+        }
         
-        // Implementation goes here
-        // Build the Merkle tree from leaves
+        // Each node is its weight, its hash and the leaves below it; merging
+        // two nodes appends the sibling hash to every leaf's proof
+        let mut nodes = BinaryHeap::with_capacity(self.leaves.len());
+        for (index, leaf) in self.leaves.iter().enumerate() {
+            self.leaf_positions.insert(index, Vec::new());
+            nodes.push((Reverse(leaf.weight), TapNodeHash::from(leaf.leaf_hash()?), vec![index]));
+        }
+        
+        while nodes.len() > 1 {
+            let (Reverse(left_weight), left, mut left_leaves) = nodes.pop().expect("at least two nodes");
+            let (Reverse(right_weight), right, right_leaves) = nodes.pop().expect("at least two nodes");
+            for index in &left_leaves {
+                self.leaf_positions.entry(*index).or_default().push(right);
+            }
+            for index in &right_leaves {
+                self.leaf_positions.entry(*index).or_default().push(left);
+            }
+            if left_leaves.iter().chain(&right_leaves)
+                .any(|index| self.leaf_positions[index].len() > TAPROOT_CONTROL_MAX_NODE_COUNT)
+            {
+                return Err(format!(
+                    "Taproot tree is deeper than {} levels",
+                    TAPROOT_CONTROL_MAX_NODE_COUNT,
+                ).into());
+            }
+            
+            let branch = TapBranch::new(left, right);
+            let hash = branch.branch_hash();
+            self.branches.push(branch);
+            left_leaves.extend(right_leaves);
+            // Weights saturate like rust-bitcoin's; such nodes are simply very likely
+            nodes.push((Reverse(left_weight.saturating_add(right_weight)), hash, left_leaves));
+        }
         
-        // Placeholder for now
-        let placeholder_hash = [0u8; 32];
-        self.root_hash = Some(TapNodeHash::from_slice(&placeholder_hash).unwrap());
+        let (_, root, _) = nodes.pop().expect("one node left");
+        self.root_hash = Some(root);
         
-        Ok(self.root_hash.unwrap())
+        Ok(root)
+    }
+    
+    /// Gets the depth of a leaf, i.e. the number of hashes in its merkle proof
+    pub fn depth(&self, leaf_index: usize) -> AnyaResult<usize> {
+        Ok(self.get_proof(leaf_index)?.len())
+    }
+    
+    /// Gets the witness size of spending a leaf, in bytes
+    ///
+    /// Counts the leaf script and control block with their length prefixes;
+    /// the data the script itself consumes (signatures, preimages) comes on top.
+    pub fn witness_size(&self, leaf_index: usize) -> AnyaResult<usize> {
+        let leaf = self.leaves.get(leaf_index)
+            .ok_or_else(|| format!("Leaf index {} not found in tree", leaf_index))?;
+        let script_size = leaf.script.script.len();
+        let control_block_size = TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * self.depth(leaf_index)?;
+        Ok(VarInt(script_size as u64).size() + script_size
+            + VarInt(control_block_size as u64).size() + control_block_size)
+    }
+    
+    /// Gets the witness size of a script path spend averaged over the leaf weights
+    pub fn expected_witness_size(&self) -> AnyaResult<f64> {
+        let total_weight: u64 = self.leaves.iter().map(|leaf| u64::from(leaf.weight)).sum();
+        if total_weight == 0 {
+            return Err("Taproot tree has no weighted leaves".into());
+        }
+        let mut expected = 0.0;
+        for (index, leaf) in self.leaves.iter().enumerate() {
+            expected += self.witness_size(index)? as f64 * f64::from(leaf.weight);
+        }
+        Ok(expected / total_weight as f64)
     }
     
     /// Gets the Merkle proof for a specific leaf
@@ -147,11 +219,24 @@ pub fn get_proof(&self, leaf_index: usize) -> AnyaResult<Vec<TapNodeHash>> {
     
     /// Gets the control block for a specific leaf (for script path spending)
     pub fn get_control_block(&self, leaf_index: usize, internal_key: [u8; 32]) -> AnyaResult<Vec<u8>> {
-        // Implementation goes here
-        // Construct the control block
+        let leaf = self.leaves.get(leaf_index)
+            .ok_or_else(|| format!("Leaf index {} not found in tree", leaf_index))?;
+        let merkle_root = self.root_hash.ok_or("Taproot tree has not been built")?;
+        let internal_key = XOnlyPublicKey::from_slice(&internal_key)
+            .map_err(|e| format!("Invalid internal key: {}", e))?;
+        
+        // The control block carries the parity of the tweaked output key
+        let (_, output_key_parity) = internal_key.tap_tweak(&Secp256k1::verification_only(), Some(merkle_root));
+        let merkle_branch = TaprootMerkleBranch::try_from(self.get_proof(leaf_index)?)
+            .map_err(|e| format!("Invalid merkle proof: {}", e))?;
         
-        // Placeholder for now
-        Err("Control block construction not yet implemented".into())
+        let control_block = ControlBlock {
+            leaf_version: leaf.version,
+            output_key_parity,
+            internal_key,
+            merkle_branch,
+        };
+        Ok(control_block.serialize())
     }
 }
 
@@ -161,6 +246,12 @@ pub struct TapTreeBuilder {
     leaves: Vec<TapLeaf>,
 }
 
+impl Default for TapTreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TapTreeBuilder {
     /// Creates a new Taproot tree builder
     pub fn new() -> Self {
@@ -196,4 +287,84 @@ pub fn build(self) -> AnyaResult<TapTree> {
         
         Ok(tree)
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::opcodes::all::OP_PUSHNUM_1;
+    use bitcoin::script::Builder;
+    use bitcoin::taproot::TaprootBuilder;
+    use bitcoin::ScriptBuf;
+    use super::super::script::TaprootScriptType;
+
+    /// Distinct three-byte scripts: `<n> OP_1`
+    fn script(n: i64) -> ScriptBuf {
+        Builder::new().push_int(n).push_opcode(OP_PUSHNUM_1).into_script()
+    }
+
+    fn weighted_tree(weights: &[u32]) -> TapTree {
+        weights.iter().enumerate()
+            .fold(TapTreeBuilder::new(), |builder, (index, weight)| {
+                builder.add_script(TaprootScript::new(script(index as i64 + 100), TaprootScriptType::Raw), *weight)
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_huffman_matches_taproot_builder() {
+        let secp = Secp256k1::new();
+        let internal_key = XOnlyPublicKey::from_slice(
+            &hex::decode("50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0").unwrap(),
+        ).unwrap();
+
+        let weight_sets: [&[u32]; 6] = [
+            &[1],
+            &[1, 1],
+            &[1, 1, 1, 1, 1],
+            &[10, 1, 1, 1, 5, 3],
+            &[0, 0, 7, 7, 2],
+            &[u32::MAX, u32::MAX, 1, 2, 3],
+        ];
+        for weights in weight_sets {
+            let tree = weighted_tree(weights);
+            let spend_info = TaprootBuilder::with_huffman_tree(
+                weights.iter().enumerate().map(|(index, weight)| (*weight, script(index as i64 + 100))),
+            ).unwrap().finalize(&secp, internal_key).unwrap();
+            assert_eq!(tree.root_hash, spend_info.merkle_root(), "weights {:?}", weights);
+
+            for (index, leaf) in tree.leaves.iter().enumerate() {
+                let expected = spend_info
+                    .control_block(&(leaf.script.script.clone(), LeafVersion::TapScript))
+                    .unwrap();
+                let control_block = tree.get_control_block(index, internal_key.serialize()).unwrap();
+                assert_eq!(control_block, expected.serialize(), "weights {:?}, leaf {}", weights, index);
+                assert!(expected.verify_taproot_commitment(&secp, spend_info.output_key().to_x_only_public_key(), &leaf.script.script));
+            }
+        }
+    }
+
+    #[test]
+    fn test_heavy_leaves_get_short_proofs() {
+        // Leaf indices keep insertion order whatever the weights
+        let tree = weighted_tree(&[1, 1, 1, 1, 100]);
+        assert_eq!(tree.depth(4).unwrap(), 1);
+        assert!((0..4).all(|index| tree.depth(index).unwrap() == 3));
+        assert_eq!(tree.leaves[4].weight, 100);
+
+        // Script of 3 bytes plus a control block with one hash
+        assert_eq!(tree.witness_size(4).unwrap(), 1 + 3 + 1 + 33 + 32);
+        assert_eq!(tree.witness_size(0).unwrap(), 1 + 3 + 1 + 33 + 3 * 32);
+
+        // Huffman minimises the expected size below a balanced tree's depth of 3
+        let expected = tree.expected_witness_size().unwrap();
+        assert!(expected < tree.witness_size(0).unwrap() as f64);
+        let balanced = weighted_tree(&[1, 1, 1, 1, 1]);
+        assert!(expected < balanced.expected_witness_size().unwrap());
+
+        assert!(tree.witness_size(5).is_err());
+        assert!(TapTree::new().build().is_err());
+        assert!(TapTree::new().expected_witness_size().is_err());
+    }
+}
@@ -76,6 +76,18 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
 impl Error for AnyaError {}
 
+impl From<String> for AnyaError {
+    fn from(msg: String) -> Self {
+        AnyaError::System(msg)
+    }
+}
+
+impl From<&str> for AnyaError {
+    fn from(msg: &str) -> Self {
+        AnyaError::System(msg.to_string())
+    }
+}
+
 /// Result type for Anya operations
 pub type AnyaResult<T> = Result<T, AnyaError>;
 
@@ -1,9 +0,0 @@
-#[bench]
-fn schnorr_adaptor_verify(b: &mut Bencher) {
-    // Benchmark oracle signature verification
-}
-
-#[bench]
-fn contract_execution_flow(b: &mut Bencher) {
-    // End-to-end DLC settlement test
-} 
\ No newline at end of file
@@ -0,0 +1,127 @@
+// Admin API authentication
+// Bearer tokens baked from a root key, in the spirit of lnd's macaroons
+//
+// A random root key is kept in the data directory. Each permission scope gets
+// a token that is the HMAC of the scope name under the root key, so tokens can
+// be checked without storing them and all of them are revoked together by
+// replacing the root key. The tokens are written next to the root key
+// (`admin.token`, `readonly.token`) for clients to pick up.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::api::{ApiError, ApiResult};
+use crate::bitcoin::encoding::to_hex;
+
+/// File holding the root key tokens are derived from
+const ROOT_KEY_FILE: &str = "api_root.key";
+
+/// What a token allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    /// Read-only requests (GET)
+    ReadOnly,
+    /// Every request, including ones that move funds
+    Admin,
+}
+
+impl Scope {
+    /// Name of the scope, as baked into its token
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scope::ReadOnly => "readonly",
+            Scope::Admin => "admin",
+        }
+    }
+
+    /// File the scope's token is written to
+    pub fn token_file(&self) -> String {
+        format!("{}.token", self.name())
+    }
+}
+
+/// Token issuer and verifier
+#[derive(Clone)]
+pub struct ApiAuth {
+    /// Root key tokens are derived from
+    root_key: [u8; 32],
+
+    /// Directory holding the root key and token files
+    data_dir: PathBuf,
+}
+
+impl ApiAuth {
+    /// Load the root key from the data directory, creating it (and the token files) if needed
+    pub fn load_or_create(data_dir: &Path) -> ApiResult<Self> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| ApiError::Internal(format!("Failed to create API data directory: {}", e)))?;
+
+        let root_key_path = data_dir.join(ROOT_KEY_FILE);
+        let root_key = match fs::read(&root_key_path) {
+            Ok(bytes) => bytes.try_into()
+                .map_err(|_| ApiError::Internal("API root key must be 32 bytes".to_string()))?,
+            Err(_) => {
+                let mut root_key = [0u8; 32];
+                rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut root_key);
+                write_private(&root_key_path, &root_key)?;
+                root_key
+            }
+        };
+
+        let auth = ApiAuth { root_key, data_dir: data_dir.to_path_buf() };
+        auth.write_tokens()?;
+        Ok(auth)
+    }
+
+    /// Replace the root key, revoking every token issued so far
+    pub fn rotate(&mut self) -> ApiResult<()> {
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut self.root_key);
+        write_private(&self.data_dir.join(ROOT_KEY_FILE), &self.root_key)?;
+        self.write_tokens()
+    }
+
+    /// Token granting a scope
+    pub fn token(&self, scope: Scope) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.root_key).expect("HMAC accepts keys of any length");
+        mac.update(scope.name().as_bytes());
+        to_hex(&mac.finalize().into_bytes())
+    }
+
+    /// Scope a presented token grants, if it is valid
+    pub fn authorize(&self, token: &str) -> Option<Scope> {
+        [Scope::Admin, Scope::ReadOnly].into_iter()
+            .find(|scope| constant_time_eq(self.token(*scope).as_bytes(), token.trim().as_bytes()))
+    }
+
+    /// Write the token files for clients
+    fn write_tokens(&self) -> ApiResult<()> {
+        for scope in [Scope::Admin, Scope::ReadOnly] {
+            write_private(&self.data_dir.join(scope.token_file()), self.token(scope).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two byte strings without leaking where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Write a file readable only by its owner
+fn write_private(path: &Path, contents: &[u8]) -> ApiResult<()> {
+    fs::write(path, contents)
+        .map_err(|e| ApiError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    // On Unix systems, set permissions to 600 (read/write for owner only)
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| ApiError::Internal(format!("Failed to set permissions on {}: {}", path.display(), e)))?;
+    }
+
+    Ok(())
+}
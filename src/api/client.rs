@@ -0,0 +1,88 @@
+// Admin API client
+// Blocking client for a running admin API server
+
+use std::io::Write;
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::api::auth::Scope;
+use crate::api::http::read_json_response;
+use crate::api::{ApiError, ApiResult};
+
+/// How long to wait for a response before giving up
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Client for the admin API
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    /// Server address (host:port)
+    addr: String,
+
+    /// Bearer token sent with every request
+    token: String,
+}
+
+impl ApiClient {
+    /// Client for a server, authenticating with a token
+    pub fn new(addr: &str, token: &str) -> Self {
+        ApiClient {
+            addr: addr.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Client using a token file written by the server into its data directory
+    pub fn from_token_file(addr: &str, data_dir: &Path, scope: Scope) -> ApiResult<Self> {
+        let path = data_dir.join(scope.token_file());
+        let token = std::fs::read_to_string(&path)
+            .map_err(|e| ApiError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+        Ok(Self::new(addr, token.trim()))
+    }
+
+    /// Send a GET request
+    pub fn get(&self, path: &str) -> ApiResult<Value> {
+        self.request("GET", path, None)
+    }
+
+    /// Send a POST request with a JSON body
+    pub fn post(&self, path: &str, body: &Value) -> ApiResult<Value> {
+        self.request("POST", path, Some(body))
+    }
+
+    /// Send a DELETE request
+    pub fn delete(&self, path: &str) -> ApiResult<Value> {
+        self.request("DELETE", path, None)
+    }
+
+    /// Send a request, mapping error responses back onto `ApiError`
+    pub fn request(&self, method: &str, path: &str, body: Option<&Value>) -> ApiResult<Value> {
+        let connection_error = |e: std::io::Error| ApiError::Internal(format!("Request to {} failed: {}", self.addr, e));
+
+        let mut stream = TcpStream::connect(&self.addr).map_err(connection_error)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT)).map_err(connection_error)?;
+
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method, path, self.addr, self.token, body.len(), body
+        ).map_err(connection_error)?;
+
+        let (status, response) = read_json_response(&mut stream).map_err(connection_error)?;
+        if status == 200 {
+            return Ok(response);
+        }
+
+        let message = response["error"].as_str().unwrap_or("Unknown error").to_string();
+        Err(match status {
+            400 => ApiError::BadRequest(message),
+            401 => ApiError::Unauthorized,
+            403 => ApiError::Forbidden,
+            404 => ApiError::NotFound(message),
+            _ => ApiError::Internal(message),
+        })
+    }
+}
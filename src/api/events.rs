@@ -0,0 +1,160 @@
+// Lightning event stream
+// Turns changes in channels and payments into events for WebSocket subscribers
+//
+// The stream is built by polling the `LightningInterface`, so it works the same
+// for every implementation: each poll is compared with the previous snapshot
+// and every difference becomes one event.
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+use crate::api::json;
+use crate::lightning::interface::{ChannelInfo, LightningInterface, LightningResult, PaymentInfo, PaymentStatus};
+
+/// A change observed on the node
+#[derive(Debug, Clone)]
+pub enum LightningEvent {
+    /// A channel appeared
+    ChannelOpened(ChannelInfo),
+    /// A channel's state or balances changed
+    ChannelUpdated(ChannelInfo),
+    /// A channel disappeared
+    ChannelClosed(String),
+    /// A payment we sent was started or changed status
+    PaymentUpdated(PaymentInfo),
+    /// A payment to us arrived
+    PaymentReceived(PaymentInfo),
+}
+
+impl LightningEvent {
+    /// JSON form sent to subscribers
+    pub fn to_json(&self) -> Value {
+        match self {
+            LightningEvent::ChannelOpened(channel) => json!({ "type": "channel_opened", "channel": json::channel(channel) }),
+            LightningEvent::ChannelUpdated(channel) => json!({ "type": "channel_updated", "channel": json::channel(channel) }),
+            LightningEvent::ChannelClosed(channel_id) => json!({ "type": "channel_closed", "channel_id": channel_id }),
+            LightningEvent::PaymentUpdated(payment) => json!({ "type": "payment_updated", "payment": json::payment(payment) }),
+            LightningEvent::PaymentReceived(payment) => json!({ "type": "payment_received", "payment": json::payment(payment) }),
+        }
+    }
+}
+
+/// Fields of a channel whose changes are reported
+type ChannelSnapshot = (bool, u64, u64, Option<String>, Option<String>);
+
+/// Diffs successive views of a node into events
+pub struct EventPoller {
+    /// Lightning node being watched
+    lightning: Arc<dyn LightningInterface>,
+
+    /// Channels at the last poll
+    channels: HashMap<String, ChannelSnapshot>,
+
+    /// Status of sent payments at the last poll
+    payments: HashMap<String, PaymentStatus>,
+
+    /// Payment hashes of received payments seen so far
+    received: HashMap<String, u64>,
+}
+
+impl EventPoller {
+    /// Start watching a node; only changes after this point are reported
+    pub fn new(lightning: Arc<dyn LightningInterface>) -> LightningResult<Self> {
+        let mut poller = EventPoller {
+            lightning,
+            channels: HashMap::new(),
+            payments: HashMap::new(),
+            received: HashMap::new(),
+        };
+        poller.poll()?;
+        Ok(poller)
+    }
+
+    /// Collect the events since the last poll
+    pub fn poll(&mut self) -> LightningResult<Vec<LightningEvent>> {
+        let mut events = Vec::new();
+
+        let channels = self.lightning.list_channels()?;
+        let mut open_ids = Vec::with_capacity(channels.len());
+        for channel in channels {
+            let snapshot = (
+                channel.is_active,
+                channel.local_balance,
+                channel.remote_balance,
+                channel.short_channel_id.clone(),
+                channel.pending_splice.as_ref().map(|splice| splice.splice_txid.clone()),
+            );
+            open_ids.push(channel.channel_id.clone());
+            match self.channels.insert(channel.channel_id.clone(), snapshot.clone()) {
+                None => events.push(LightningEvent::ChannelOpened(channel)),
+                Some(previous) if previous != snapshot => events.push(LightningEvent::ChannelUpdated(channel)),
+                Some(_) => {}
+            }
+        }
+        let closed: Vec<String> = self.channels.keys()
+            .filter(|channel_id| !open_ids.contains(channel_id))
+            .cloned()
+            .collect();
+        for channel_id in closed {
+            self.channels.remove(&channel_id);
+            events.push(LightningEvent::ChannelClosed(channel_id));
+        }
+
+        for payment in self.lightning.list_payments()? {
+            if self.payments.insert(payment.payment_hash.clone(), payment.status) != Some(payment.status) {
+                events.push(LightningEvent::PaymentUpdated(payment));
+            }
+        }
+
+        for payment in self.lightning.list_received_payments()? {
+            if self.received.insert(payment.payment_hash.clone(), payment.amount_msat) != Some(payment.amount_msat) {
+                events.push(LightningEvent::PaymentReceived(payment));
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+/// Stream events to an upgraded WebSocket connection until it closes or the server stops
+///
+/// The poller should be created before the upgrade is accepted, so that no
+/// change made after the client connected is missed.
+pub fn stream_events(
+    stream: TcpStream,
+    mut poller: EventPoller,
+    poll_interval: Duration,
+    shutdown: Arc<AtomicBool>,
+) {
+    // Reads time out after one poll interval, which paces the loop
+    let _ = stream.set_read_timeout(Some(poll_interval));
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    while !shutdown.load(Ordering::SeqCst) {
+        match socket.read() {
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => break,
+        }
+
+        // A failed poll is retried on the next tick
+        let events = poller.poll().unwrap_or_default();
+        for event in events {
+            if socket.send(Message::text(event.to_json().to_string())).is_err() {
+                return;
+            }
+        }
+    }
+
+    let _ = socket.close(None);
+    let _ = socket.flush();
+}
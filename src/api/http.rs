@@ -0,0 +1,215 @@
+// Minimal HTTP/1.1 framing for the admin API
+// One request per connection; responses always close the connection
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::api::{ApiError, ApiResult};
+use crate::lightning::lnurl::percent_decode;
+
+/// Largest request head we accept
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Largest request body we accept
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// A parsed HTTP request
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// Request method (GET, POST, ...)
+    pub method: String,
+    /// Path without the query string
+    pub path: String,
+    /// Decoded query parameters
+    pub query: Vec<(String, String)>,
+    /// Headers with lowercased names
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Build a request for in-process dispatch; `target` may carry a query string
+    pub fn new(method: &str, target: &str, body: Option<&Value>) -> Self {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), parse_query(query)),
+            None => (target.to_string(), Vec::new()),
+        };
+
+        Request {
+            method: method.to_string(),
+            path,
+            query,
+            headers: Vec::new(),
+            body: body.map(|body| body.to_string().into_bytes()).unwrap_or_default(),
+        }
+    }
+
+    /// First value of a header (name is case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// First value of a query parameter
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Path segments, without empty ones
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|segment| !segment.is_empty()).collect()
+    }
+
+    /// Parse the body as JSON
+    pub fn json<T: DeserializeOwned>(&self) -> ApiResult<T> {
+        let body = if self.body.is_empty() { b"{}".as_slice() } else { self.body.as_slice() };
+        serde_json::from_slice(body)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid request body: {}", e)))
+    }
+}
+
+/// Read one request from a connection
+///
+/// The whole request must arrive within `timeout`, so slow clients cannot hold
+/// the connection open by trickling bytes.
+pub fn read_request(stream: &mut TcpStream, timeout: Duration) -> ApiResult<Request> {
+    let deadline = Instant::now() + timeout;
+    let mut reader = BufReader::new(stream);
+    let mut head_bytes = 0;
+
+    set_deadline(reader.get_ref(), deadline)?;
+    let request_line = read_line(&mut reader, &mut head_bytes)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err(ApiError::BadRequest("Malformed request line".to_string())),
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        set_deadline(reader.get_ref(), deadline)?;
+        let line = read_line(&mut reader, &mut head_bytes)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = headers.iter()
+        .find(|(name, _)| name == "content-length")
+        .map(|(_, value)| value.parse::<usize>())
+        .transpose()
+        .map_err(|_| ApiError::BadRequest("Invalid Content-Length".to_string()))?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        return Err(ApiError::BadRequest("Request body too large".to_string()));
+    }
+
+    let mut body = vec![0u8; content_length];
+    set_deadline(reader.get_ref(), deadline)?;
+    reader.read_exact(&mut body)
+        .map_err(|e| ApiError::BadRequest(format!("Failed to read request body: {}", e)))?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)),
+        None => (target, Vec::new()),
+    };
+
+    Ok(Request { method, path, query, headers, body })
+}
+
+/// Limit the next read to the time left before the deadline
+fn set_deadline(stream: &TcpStream, deadline: Instant) -> ApiResult<()> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(ApiError::BadRequest("Request timed out".to_string()));
+    }
+    stream.set_read_timeout(Some(remaining))
+        .map_err(|e| ApiError::Internal(format!("Failed to set read timeout: {}", e)))
+}
+
+/// Read a CRLF-terminated line, enforcing the head size limit
+fn read_line(reader: &mut impl BufRead, head_bytes: &mut usize) -> ApiResult<String> {
+    let mut line = String::new();
+    let read = reader.read_line(&mut line)
+        .map_err(|e| ApiError::BadRequest(format!("Failed to read request: {}", e)))?;
+    *head_bytes += read;
+    if read == 0 || *head_bytes > MAX_HEAD_BYTES {
+        return Err(ApiError::BadRequest("Incomplete or oversized request head".to_string()));
+    }
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Parse a query string into decoded pairs
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (percent_decode(key), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Reason phrase for the status codes the API uses
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        502 => "Bad Gateway",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Write a JSON response and close the connection
+pub fn write_json(stream: &mut TcpStream, status: u16, body: &Value) -> std::io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason_phrase(status), body.len(), body
+    )?;
+    stream.flush()
+}
+
+/// Write the 101 response accepting a WebSocket upgrade
+pub fn write_upgrade(stream: &mut TcpStream, accept_key: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 101 {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        reason_phrase(101), accept_key
+    )?;
+    stream.flush()
+}
+
+/// Read a response written by `write_json`, returning its status and JSON body
+pub fn read_json_response(stream: &mut TcpStream) -> std::io::Result<(u16, Value)> {
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+
+    let malformed = || std::io::Error::new(std::io::ErrorKind::InvalidData, "Malformed HTTP response");
+    let split = response.windows(4).position(|window| window == b"\r\n\r\n").ok_or_else(malformed)?;
+    let status = String::from_utf8_lossy(&response[..split])
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(malformed)?;
+    let body = &response[split + 4..];
+    let body = if body.is_empty() { Value::Null } else { serde_json::from_slice(body).map_err(|_| malformed())? };
+
+    Ok((status, body))
+}
@@ -0,0 +1,170 @@
+// JSON representations of node objects
+// Shared by the admin API and the CLI's JSON output
+
+use serde_json::{json, Value};
+
+use crate::bitcoin::encoding::to_hex;
+use crate::bitcoin::{AddressType, BitcoinAddress, BitcoinTransaction, BlockHeader};
+use crate::lightning::interface::{
+    ChannelInfo, ChannelType, Invoice, NodeInfo, PaymentInfo, PaymentStatus
+};
+use crate::lightning::invoice_manager::InvoiceState;
+
+/// Name of an address type
+pub fn address_type_name(address_type: AddressType) -> &'static str {
+    match address_type {
+        AddressType::P2PKH => "p2pkh",
+        AddressType::P2SH => "p2sh",
+        AddressType::P2WPKH => "p2wpkh",
+        AddressType::P2WSH => "p2wsh",
+        AddressType::P2TR => "p2tr",
+    }
+}
+
+/// Parse an address type name
+pub fn parse_address_type(name: &str) -> Option<AddressType> {
+    match name.to_ascii_lowercase().as_str() {
+        "p2pkh" => Some(AddressType::P2PKH),
+        "p2sh" => Some(AddressType::P2SH),
+        "p2wpkh" => Some(AddressType::P2WPKH),
+        "p2wsh" => Some(AddressType::P2WSH),
+        "p2tr" => Some(AddressType::P2TR),
+        _ => None,
+    }
+}
+
+/// Name of a payment status
+pub fn payment_status_name(status: PaymentStatus) -> &'static str {
+    match status {
+        PaymentStatus::Pending => "pending",
+        PaymentStatus::Succeeded => "succeeded",
+        PaymentStatus::Failed => "failed",
+    }
+}
+
+/// Name of an invoice state
+pub fn invoice_state_name(state: InvoiceState) -> &'static str {
+    match state {
+        InvoiceState::Open => "open",
+        InvoiceState::Accepted => "accepted",
+        InvoiceState::Settled => "settled",
+        InvoiceState::Cancelled => "cancelled",
+        InvoiceState::Expired => "expired",
+    }
+}
+
+/// Bitcoin address
+pub fn address(address: &BitcoinAddress) -> Value {
+    json!({
+        "address": address.address,
+        "type": address_type_name(address.address_type),
+    })
+}
+
+/// Bitcoin transaction, with scripts and witnesses as hex
+pub fn transaction(tx: &BitcoinTransaction) -> Value {
+    json!({
+        "txid": tx.txid,
+        "version": tx.version,
+        "locktime": tx.locktime,
+        "size": tx.size,
+        "weight": tx.weight,
+        "fee_sat": tx.fee,
+        "inputs": tx.inputs.iter().map(|input| json!({
+            "txid": input.txid,
+            "vout": input.vout,
+            "script_sig": to_hex(&input.script_sig),
+            "sequence": input.sequence,
+            "witness": input.witness.as_ref()
+                .map(|witness| witness.iter().map(|item| to_hex(item)).collect::<Vec<_>>()),
+        })).collect::<Vec<_>>(),
+        "outputs": tx.outputs.iter().map(|output| json!({
+            "value_sat": output.value,
+            "script_pubkey": to_hex(&output.script_pubkey),
+            "address": output.address,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Block header
+pub fn block_header(header: &BlockHeader) -> Value {
+    json!({
+        "hash": header.hash,
+        "version": header.version,
+        "prev_hash": header.prev_hash,
+        "merkle_root": header.merkle_root,
+        "timestamp": header.timestamp,
+        "bits": header.bits,
+        "nonce": header.nonce,
+    })
+}
+
+/// Lightning node
+pub fn node_info(node: &NodeInfo) -> Value {
+    json!({
+        "pubkey": node.pubkey,
+        "addresses": node.addresses,
+        "alias": node.alias,
+        "color": node.color,
+        "features": node.features,
+    })
+}
+
+/// Lightning channel
+pub fn channel(channel: &ChannelInfo) -> Value {
+    json!({
+        "channel_id": channel.channel_id,
+        "funding_txid": channel.funding_txid,
+        "funding_output_idx": channel.funding_output_idx,
+        "capacity_sat": channel.capacity,
+        "local_balance_sat": channel.local_balance,
+        "remote_balance_sat": channel.remote_balance,
+        "remote_pubkey": channel.remote_pubkey,
+        "is_active": channel.is_active,
+        "is_public": channel.is_public,
+        "short_channel_id": channel.short_channel_id,
+        "channel_type": match channel.channel_type {
+            ChannelType::StaticRemoteKey => "static_remote_key",
+            ChannelType::AnchorOutputs => "anchor_outputs",
+        },
+        "is_zero_conf": channel.is_zero_conf,
+        "pending_splice": channel.pending_splice.as_ref().map(|splice| json!({
+            "splice_txid": splice.splice_txid,
+            "funding_output_idx": splice.funding_output_idx,
+            "capacity_sat": splice.capacity,
+            "local_added_sat": splice.local_added_sat,
+            "remote_added_sat": splice.remote_added_sat,
+        })),
+    })
+}
+
+/// Lightning invoice
+pub fn invoice(invoice: &Invoice) -> Value {
+    json!({
+        "bolt11": invoice.bolt11,
+        "payment_hash": invoice.payment_hash,
+        "description": invoice.description,
+        "amount_msat": invoice.amount_msat,
+        "expiry": invoice.expiry,
+        "timestamp": invoice.timestamp,
+        "min_final_cltv_expiry": invoice.min_final_cltv_expiry,
+    })
+}
+
+/// Lightning payment, with custom record values as hex
+pub fn payment(payment: &PaymentInfo) -> Value {
+    json!({
+        "payment_id": payment.payment_id,
+        "payment_hash": payment.payment_hash,
+        "preimage": payment.preimage,
+        "amount_msat": payment.amount_msat,
+        "fee_msat": payment.fee_msat,
+        "status": payment_status_name(payment.status),
+        "created_at": payment.created_at,
+        "resolved_at": payment.resolved_at,
+        "description": payment.description,
+        "custom_records": payment.custom_records.iter()
+            .map(|(record_type, value)| (record_type.to_string(), Value::String(to_hex(value))))
+            .collect::<serde_json::Map<_, _>>(),
+    })
+}
@@ -0,0 +1,550 @@
+// Admin API
+// Authenticated HTTP access to the node's Bitcoin and Lightning operations
+//
+// The server exposes the `BitcoinInterface` and `LightningInterface` over a
+// small JSON REST API, plus a WebSocket stream of Lightning events:
+//
+//   GET    /v1/info                          node and chain summary
+//   GET    /v1/bitcoin/balance               wallet balance
+//   POST   /v1/bitcoin/addresses             new address
+//   GET    /v1/bitcoin/transactions          wallet history
+//   POST   /v1/bitcoin/transactions          send to an address
+//   GET    /v1/bitcoin/transactions/{txid}   transaction lookup
+//   GET    /v1/bitcoin/blocks/{hash|height}  block header lookup
+//   GET    /v1/bitcoin/height                chain height
+//   GET    /v1/bitcoin/fee                   fee estimate
+//   GET    /v1/lightning/info                node info
+//   POST   /v1/lightning/unlock|lock         seed unlock and lock
+//   GET    /v1/lightning/peers               connected peers
+//   POST   /v1/lightning/peers               connect to a peer
+//   GET    /v1/lightning/channels            channels
+//   POST   /v1/lightning/channels            open a channel
+//   DELETE /v1/lightning/channels/{id}       close a channel
+//   POST   /v1/lightning/invoices            create an invoice
+//   GET    /v1/lightning/invoices/{hash}     invoice state
+//   POST   /v1/lightning/decode              decode an invoice
+//   GET    /v1/lightning/payments            sent payments
+//   POST   /v1/lightning/payments            pay an invoice or LNURL
+//   GET    /v1/lightning/payments/{hash}     payment lookup
+//   GET    /v1/lightning/received            received payments
+//   POST   /v1/lightning/keysend             spontaneous payment
+//   POST   /v1/lightning/signmessage         sign with the node key
+//   POST   /v1/lightning/verifymessage       verify a node signature
+//   GET    /v1/events                        WebSocket event stream
+//   GET    /v1/openapi.json                  OpenAPI description (no auth)
+//
+// Requests carry a token from `auth` as `Authorization: Bearer <token>` (or a
+// `token` query parameter, for WebSocket clients that can't set headers).
+// The read-only token is enough for GET requests; everything else needs the
+// admin token.
+//
+// The server speaks plain HTTP, so tokens travel in the clear. It only binds
+// loopback addresses; remote access needs a TLS-terminating proxy in front.
+
+pub mod auth;
+pub mod client;
+pub mod events;
+pub mod json;
+pub mod openapi;
+mod http;
+mod routes;
+
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::bitcoin::{BitcoinError, BitcoinInterface};
+use crate::config::Config;
+use crate::lightning::interface::{LightningError, LightningInterface};
+
+pub use auth::{ApiAuth, Scope};
+pub use client::ApiClient;
+
+/// Error type for admin API operations
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Missing or invalid API token")]
+    Unauthorized,
+
+    #[error("Token does not allow this request")]
+    Forbidden,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Lightning error: {0}")]
+    Lightning(#[from] LightningError),
+
+    #[error("Bitcoin error: {0}")]
+    Bitcoin(#[from] BitcoinError),
+}
+
+impl ApiError {
+    /// HTTP status code for the error
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::BadRequest(_) => 400,
+            ApiError::Unauthorized => 401,
+            ApiError::Forbidden => 403,
+            ApiError::NotFound(_) => 404,
+            ApiError::Internal(_) => 500,
+            ApiError::Lightning(LightningError::ImplementationError(_)) => 500,
+            ApiError::Bitcoin(BitcoinError::ImplementationError(_)) => 500,
+            ApiError::Lightning(_) | ApiError::Bitcoin(_) => 400,
+        }
+    }
+}
+
+/// Result type for admin API operations
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// Handle an API request in-process, without a socket or token
+///
+/// Lets local tools such as the CLI share the server's operations and JSON.
+pub fn dispatch(
+    bitcoin: &dyn BitcoinInterface,
+    lightning: &dyn LightningInterface,
+    method: &str,
+    target: &str,
+    body: Option<&serde_json::Value>,
+) -> ApiResult<serde_json::Value> {
+    routes::route(&http::Request::new(method, target, body), bitcoin, lightning)
+}
+
+/// Admin API server settings
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    /// Address to listen on (port 0 picks a free port)
+    pub listen_addr: String,
+
+    /// Directory holding the API root key and token files
+    pub data_dir: PathBuf,
+
+    /// How often the event stream checks for changes
+    pub event_poll_interval: Duration,
+
+    /// Time a client has to send its request, and each write may take
+    pub request_timeout: Duration,
+
+    /// Connections served at once; further clients get a 503
+    pub max_connections: usize,
+}
+
+impl ApiConfig {
+    /// Settings from the node configuration; tokens live next to the Lightning data
+    pub fn from_config(config: &Config) -> Self {
+        ApiConfig {
+            listen_addr: config.api_listen_addr.clone()
+                .unwrap_or_else(|| "127.0.0.1:8080".to_string()),
+            data_dir: config.lightning_data_path(),
+            event_poll_interval: Duration::from_millis(500),
+            request_timeout: Duration::from_secs(10),
+            max_connections: 64,
+        }
+    }
+}
+
+/// State shared by the connection handlers
+pub(crate) struct ApiContext {
+    /// On-chain wallet and chain access
+    pub bitcoin: Arc<dyn BitcoinInterface>,
+
+    /// Lightning node
+    pub lightning: Arc<dyn LightningInterface>,
+
+    /// Token verifier
+    pub auth: ApiAuth,
+
+    /// Event stream poll interval
+    pub event_poll_interval: Duration,
+
+    /// Deadline for reading a request and timeout for each write
+    pub request_timeout: Duration,
+
+    /// Set when the server is stopping
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// Running admin API server
+///
+/// Serves each connection on its own thread, up to `max_connections` at once.
+/// Stops when `shutdown` is called or the server is dropped.
+pub struct ApiServer {
+    /// Address actually bound
+    local_addr: SocketAddr,
+
+    /// Token verifier, for handing tokens to local clients
+    auth: ApiAuth,
+
+    /// Set to stop the accept loop and event streams
+    shutdown: Arc<AtomicBool>,
+
+    /// Accept loop thread
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl ApiServer {
+    /// Bind the listening socket and start serving
+    pub fn start(
+        config: ApiConfig,
+        bitcoin: Arc<dyn BitcoinInterface>,
+        lightning: Arc<dyn LightningInterface>,
+    ) -> ApiResult<Self> {
+        let listener = TcpListener::bind(&config.listen_addr)
+            .map_err(|e| ApiError::Internal(format!("Failed to bind {}: {}", config.listen_addr, e)))?;
+        let local_addr = listener.local_addr()
+            .map_err(|e| ApiError::Internal(format!("Failed to read listening address: {}", e)))?;
+
+        // Bearer tokens must not cross the network in plain HTTP
+        if !local_addr.ip().is_loopback() {
+            return Err(ApiError::Internal(format!(
+                "Refusing to serve the admin API without TLS on non-loopback address {}; \
+                 listen on loopback and put a TLS-terminating proxy in front",
+                local_addr
+            )));
+        }
+        let auth = ApiAuth::load_or_create(&config.data_dir)?;
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let context = Arc::new(ApiContext {
+            bitcoin,
+            lightning,
+            auth: auth.clone(),
+            event_poll_interval: config.event_poll_interval,
+            request_timeout: config.request_timeout,
+            shutdown: shutdown.clone(),
+        });
+
+        let accept_shutdown = shutdown.clone();
+        let active = Arc::new(AtomicUsize::new(0));
+        let max_connections = config.max_connections;
+        let accept_thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let _ = stream.set_write_timeout(Some(context.request_timeout));
+
+                if active.fetch_add(1, Ordering::SeqCst) >= max_connections {
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    let body = serde_json::json!({ "error": "Too many connections" });
+                    let _ = http::write_json(&mut stream, 503, &body);
+                    continue;
+                }
+
+                let context = context.clone();
+                let active = active.clone();
+                thread::spawn(move || {
+                    routes::handle_connection(stream, context);
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        println!("Admin API listening on {}", local_addr);
+
+        Ok(ApiServer {
+            local_addr,
+            auth,
+            shutdown,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Token verifier, e.g. to read the admin token for an in-process client
+    pub fn auth(&self) -> &ApiAuth {
+        &self.auth
+    }
+
+    /// Stop accepting connections and end event streams
+    pub fn shutdown(&mut self) {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.local_addr);
+        if let Some(thread) = self.accept_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::bitcoin::AddressType;
+    use crate::lightning::channel_manager::generate_random_id;
+    use crate::lightning::interface::PaymentStatus;
+    use crate::lightning::test_network::{TestNetwork, FUNDING_CONFIRMATIONS};
+
+    /// An API server for node 0 of a two-node test network
+    struct TestApi {
+        network: TestNetwork,
+        server: ApiServer,
+        data_dir: PathBuf,
+    }
+
+    impl TestApi {
+        fn new() -> Self {
+            Self::with_limits(Duration::from_secs(5), 8)
+        }
+
+        fn with_limits(request_timeout: Duration, max_connections: usize) -> Self {
+            let network = TestNetwork::new(2).unwrap();
+            let data_dir = std::env::temp_dir().join(format!("opsource-api-test-{}", generate_random_id()));
+            let config = ApiConfig {
+                listen_addr: "127.0.0.1:0".to_string(),
+                data_dir: data_dir.clone(),
+                event_poll_interval: Duration::from_millis(20),
+                request_timeout,
+                max_connections,
+            };
+            let server = ApiServer::start(config, network.chain(), network.node(0)).unwrap();
+            TestApi { network, server, data_dir }
+        }
+
+        fn client(&self, scope: Scope) -> ApiClient {
+            ApiClient::from_token_file(&self.server.local_addr().to_string(), &self.data_dir, scope).unwrap()
+        }
+
+        fn client_with_token(&self, token: &str) -> ApiClient {
+            ApiClient::new(&self.server.local_addr().to_string(), token)
+        }
+    }
+
+    impl Drop for TestApi {
+        fn drop(&mut self) {
+            self.server.shutdown();
+            let _ = std::fs::remove_dir_all(&self.data_dir);
+        }
+    }
+
+    #[test]
+    fn test_tokens_and_scopes() {
+        let api = TestApi::new();
+
+        assert!(matches!(api.client_with_token("").get("/v1/info"), Err(ApiError::Unauthorized)));
+        assert!(matches!(api.client_with_token("deadbeef").get("/v1/info"), Err(ApiError::Unauthorized)));
+
+        // The read-only token can read but not spend
+        let readonly = api.client(Scope::ReadOnly);
+        assert!(readonly.get("/v1/bitcoin/balance").unwrap()["balance_sat"].as_u64().unwrap() > 0);
+        assert!(matches!(readonly.post("/v1/bitcoin/addresses", &json!({})), Err(ApiError::Forbidden)));
+
+        let admin = api.client(Scope::Admin);
+        assert_eq!(admin.post("/v1/bitcoin/addresses", &json!({ "type": "p2tr" })).unwrap()["type"], "p2tr");
+
+        // The description is public
+        assert_eq!(api.client_with_token("").get("/v1/openapi.json").unwrap()["openapi"], "3.0.3");
+
+        // Tokens survive a restart and are all revoked by rotating the root key
+        let mut auth = ApiAuth::load_or_create(&api.data_dir).unwrap();
+        assert_eq!(auth.token(Scope::Admin), api.server.auth().token(Scope::Admin));
+        assert_eq!(auth.authorize(&auth.token(Scope::ReadOnly)), Some(Scope::ReadOnly));
+        let old_token = auth.token(Scope::Admin);
+        auth.rotate().unwrap();
+        assert_eq!(auth.authorize(&old_token), None);
+    }
+
+    #[test]
+    fn test_connection_limits() {
+        let api = TestApi::with_limits(Duration::from_millis(300), 2);
+        let addr = api.server.local_addr();
+
+        // Idle connections fill the cap and further clients are turned away
+        let idle: Vec<TcpStream> = (0..2).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        let mut extra = TcpStream::connect(addr).unwrap();
+        assert_eq!(http::read_json_response(&mut extra).unwrap().0, 503);
+
+        // Clients that never finish their request are dropped at the deadline
+        for mut stream in idle {
+            assert_eq!(http::read_json_response(&mut stream).unwrap().0, 400);
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert!(api.client(Scope::ReadOnly).get("/v1/info").is_ok());
+
+        // Tokens never cross the network without TLS
+        let exposed = ApiConfig {
+            listen_addr: "0.0.0.0:0".to_string(),
+            data_dir: api.data_dir.clone(),
+            event_poll_interval: Duration::from_millis(20),
+            request_timeout: Duration::from_secs(5),
+            max_connections: 8,
+        };
+        assert!(ApiServer::start(exposed, api.network.chain(), api.network.node(0)).is_err());
+    }
+
+    #[test]
+    fn test_bitcoin_endpoints() {
+        let api = TestApi::new();
+        let admin = api.client(Scope::Admin);
+
+        let balance = admin.get("/v1/bitcoin/balance").unwrap()["balance_sat"].as_u64().unwrap();
+        let height = admin.get("/v1/bitcoin/height").unwrap()["block_height"].as_u64().unwrap();
+        assert!(admin.get("/v1/bitcoin/fee?target=2").unwrap()["fee_rate"].as_u64().unwrap() > 0);
+        assert!(matches!(admin.get("/v1/bitcoin/fee?target=soon"), Err(ApiError::BadRequest(_))));
+
+        // Send to an outside address
+        let destination = crate::bitcoin::simulated::SimulatedBitcoinImplementation::new(&Config::default())
+            .generate_address(AddressType::P2WPKH)
+            .unwrap();
+        let sent = admin.post(
+            "/v1/bitcoin/transactions",
+            &json!({ "address": destination.address, "amount_sat": 50_000, "fee_rate": 2 }),
+        ).unwrap();
+        let txid = sent["txid"].as_str().unwrap();
+
+        api.network.mine_blocks(1).unwrap();
+        let transaction = admin.get(&format!("/v1/bitcoin/transactions/{}", txid)).unwrap();
+        assert!(transaction["outputs"].as_array().unwrap().iter()
+            .any(|output| output["value_sat"] == 50_000));
+        let fee = transaction["fee_sat"].as_u64().unwrap_or(0);
+        assert_eq!(admin.get("/v1/bitcoin/balance").unwrap()["balance_sat"].as_u64().unwrap(), balance - 50_000 - fee);
+
+        // Blocks by height and by hash agree
+        let by_height = admin.get(&format!("/v1/bitcoin/blocks/{}", height + 1)).unwrap();
+        let by_hash = admin.get(&format!("/v1/bitcoin/blocks/{}", by_height["hash"].as_str().unwrap())).unwrap();
+        assert_eq!(by_height, by_hash);
+
+        assert!(matches!(admin.get("/v1/bitcoin/nothing"), Err(ApiError::NotFound(_))));
+        assert!(matches!(
+            admin.post("/v1/bitcoin/transactions", &json!({ "address": destination.address })),
+            Err(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_lightning_endpoints() {
+        let api = TestApi::new();
+        let admin = api.client(Scope::Admin);
+        let remote = api.network.pubkey(1);
+
+        admin.post("/v1/lightning/peers", &json!({ "pubkey": remote, "host": "127.0.0.1", "port": 9736 })).unwrap();
+        assert_eq!(admin.get("/v1/lightning/peers").unwrap()["peers"][0]["pubkey"], remote.as_str());
+
+        let channel = admin.post(
+            "/v1/lightning/channels",
+            &json!({ "pubkey": remote, "capacity_sat": 1_000_000, "anchors": true }),
+        ).unwrap();
+        assert_eq!(channel["channel_type"], "anchor_outputs");
+        api.network.mine_blocks(FUNDING_CONFIRMATIONS).unwrap();
+        let channels = admin.get("/v1/lightning/channels").unwrap();
+        assert_eq!(channels["channels"][0]["is_active"], true);
+
+        // Pay an invoice from the other node
+        let invoice = api.network.node(1).create_invoice(Some(20_000_000), "API payment", None).unwrap();
+        let decoded = admin.post("/v1/lightning/decode", &json!({ "bolt11": invoice.bolt11 })).unwrap();
+        assert_eq!(decoded["payment_hash"], invoice.payment_hash.as_str());
+        let payment = admin.post("/v1/lightning/payments", &json!({ "bolt11": invoice.bolt11 })).unwrap();
+        assert_eq!(payment["status"], "succeeded");
+        let looked_up = admin.get(&format!("/v1/lightning/payments/{}", invoice.payment_hash)).unwrap();
+        assert_eq!(looked_up["preimage"], payment["preimage"]);
+        assert_eq!(admin.get("/v1/lightning/payments").unwrap()["payments"].as_array().unwrap().len(), 1);
+        assert!(matches!(admin.get("/v1/lightning/payments/00"), Err(ApiError::NotFound(_))));
+
+        // Get paid through an invoice issued over the API
+        let ours = admin.post("/v1/lightning/invoices", &json!({ "amount_msat": 5_000_000, "description": "back" })).unwrap();
+        let paid = api.network.node(1).pay_invoice(ours["bolt11"].as_str().unwrap(), None).unwrap();
+        assert_eq!(paid.status, PaymentStatus::Succeeded);
+        let state = admin.get(&format!("/v1/lightning/invoices/{}", ours["payment_hash"].as_str().unwrap())).unwrap();
+        assert_eq!(state["state"], "settled");
+
+        // Keysend with a custom record
+        let keysend = admin.post(
+            "/v1/lightning/keysend",
+            &json!({ "pubkey": remote, "amount_msat": 1_000_000, "custom_records": { "34349334": "6869" } }),
+        ).unwrap();
+        assert_eq!(keysend["custom_records"]["34349334"], "6869");
+        let local = admin.get("/v1/lightning/info").unwrap()["pubkey"].as_str().unwrap().to_string();
+        api.network.node(1).keysend(&local, 2_000_000, std::collections::BTreeMap::new()).unwrap();
+        assert_eq!(admin.get("/v1/lightning/received").unwrap()["payments"][0]["amount_msat"], 2_000_000);
+
+        // Signatures made through the API recover the same key for the same message only
+        let signature = admin.post("/v1/lightning/signmessage", &json!({ "message": "hello" })).unwrap();
+        let verify = |message: &str| admin.post(
+            "/v1/lightning/verifymessage",
+            &json!({ "message": message, "signature": signature["signature"] }),
+        ).unwrap()["pubkey"].clone();
+        assert_eq!(verify("hello"), local.as_str());
+        assert_ne!(verify("goodbye"), local.as_str());
+
+        // Cooperative close
+        let channel_id = channel["channel_id"].as_str().unwrap();
+        let closed = admin.delete(&format!("/v1/lightning/channels/{}", channel_id)).unwrap();
+        assert!(closed["closing_txid"].is_string());
+        assert!(admin.get("/v1/lightning/channels").unwrap()["channels"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let api = TestApi::new();
+        let token = api.server.auth().token(Scope::ReadOnly);
+        let addr = api.server.local_addr();
+
+        // WebSocket clients authenticate with the query parameter
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{}/v1/events?token={}", addr, token), stream).unwrap();
+
+        let channel = api.network.open_channel(0, 1, 1_000_000, 0).unwrap();
+        api.network.pay(0, 1, 10_000_000).unwrap();
+
+        let mut seen = Vec::new();
+        while !seen.contains(&"payment_updated".to_string()) {
+            let message = socket.read().unwrap();
+            let event: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+            if event["type"] == "channel_opened" {
+                assert_eq!(event["channel"]["channel_id"], channel.channel_id.as_str());
+            }
+            seen.push(event["type"].as_str().unwrap().to_string());
+        }
+        assert!(seen.contains(&"channel_opened".to_string()));
+        let _ = socket.close(None);
+
+        // The upgrade needs a valid token too
+        let stream = TcpStream::connect(addr).unwrap();
+        assert!(tungstenite::client(format!("ws://{}/v1/events", addr), stream).is_err());
+    }
+
+    #[test]
+    fn test_documented_operations_are_routed() {
+        let api = TestApi::new();
+        let admin = api.client(Scope::Admin);
+        let document = openapi::document();
+
+        for (method, path, _, _) in openapi::OPERATIONS {
+            assert!(document["paths"][*path][*method].is_object());
+            let path = path.replace(['{', '}'], "");
+            let result = admin.request(&method.to_uppercase(), &path, Some(&json!({})));
+            if let Err(ApiError::NotFound(message)) = result {
+                assert!(!message.starts_with("No route"), "{} {} is not routed", method, path);
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// OpenAPI description of the admin API
+// Served at /v1/openapi.json for client generators and API explorers
+
+use serde_json::{json, Map, Value};
+
+/// Request body property: name, JSON type and whether it is required
+pub type BodyProperty = (&'static str, &'static str, bool);
+
+/// Operations of the API: method, path, summary and request body properties
+pub const OPERATIONS: &[(&str, &str, &str, &[BodyProperty])] = &[
+    ("get", "/v1/info", "Node and chain summary", &[]),
+    ("get", "/v1/bitcoin/balance", "On-chain wallet balance", &[]),
+    ("post", "/v1/bitcoin/addresses", "Generate a wallet address", &[("type", "string", false)]),
+    ("post", "/v1/bitcoin/transactions", "Send on-chain funds to an address", &[
+        ("address", "string", true), ("amount_sat", "integer", true), ("fee_rate", "integer", false),
+    ]),
+    ("get", "/v1/bitcoin/transactions", "List wallet transactions", &[]),
+    ("get", "/v1/bitcoin/transactions/{txid}", "Look up a transaction", &[]),
+    ("get", "/v1/bitcoin/blocks/{block}", "Look up a block header by hash or height", &[]),
+    ("get", "/v1/bitcoin/height", "Current block height", &[]),
+    ("get", "/v1/bitcoin/fee", "Estimate the fee rate for a confirmation target", &[]),
+    ("get", "/v1/lightning/info", "Lightning node information", &[]),
+    ("post", "/v1/lightning/unlock", "Unlock the node seed", &[("passphrase", "string", true)]),
+    ("post", "/v1/lightning/lock", "Lock the node seed", &[]),
+    ("get", "/v1/lightning/peers", "List connected peers", &[]),
+    ("post", "/v1/lightning/peers", "Connect to a peer", &[
+        ("pubkey", "string", true), ("host", "string", true), ("port", "integer", true),
+    ]),
+    ("get", "/v1/lightning/channels", "List channels", &[]),
+    ("post", "/v1/lightning/channels", "Open a channel", &[
+        ("pubkey", "string", true), ("capacity_sat", "integer", true), ("push_msat", "integer", false),
+        ("private", "boolean", false), ("anchors", "boolean", false), ("zero_conf", "boolean", false),
+        ("remote_funding_sat", "integer", false),
+    ]),
+    ("delete", "/v1/lightning/channels/{channel_id}", "Close a channel", &[]),
+    ("post", "/v1/lightning/invoices", "Create an invoice", &[
+        ("amount_msat", "integer", false), ("description", "string", false), ("expiry", "integer", false),
+    ]),
+    ("get", "/v1/lightning/invoices/{payment_hash}", "State of one of our invoices", &[]),
+    ("post", "/v1/lightning/decode", "Decode an invoice", &[("bolt11", "string", true)]),
+    ("get", "/v1/lightning/payments", "List sent payments", &[]),
+    ("post", "/v1/lightning/payments", "Pay an invoice, LNURL-pay or Lightning Address", &[
+        ("bolt11", "string", false), ("lnurl", "string", false), ("amount_msat", "integer", false),
+        ("comment", "string", false),
+    ]),
+    ("get", "/v1/lightning/payments/{payment_hash}", "Look up a sent payment", &[]),
+    ("get", "/v1/lightning/received", "List received payments", &[]),
+    ("post", "/v1/lightning/keysend", "Send a spontaneous payment", &[
+        ("pubkey", "string", true), ("amount_msat", "integer", true), ("custom_records", "object", false),
+    ]),
+    ("post", "/v1/lightning/signmessage", "Sign a message with the node key", &[("message", "string", true)]),
+    ("post", "/v1/lightning/verifymessage", "Verify a message signed by a node", &[
+        ("message", "string", true), ("signature", "string", true),
+    ]),
+    ("get", "/v1/events", "Stream Lightning events over a WebSocket", &[]),
+    ("get", "/v1/openapi.json", "This document", &[]),
+];
+
+/// Query parameters of operations: method, path, name and JSON type
+const QUERY_PARAMETERS: &[(&str, &str, &str, &str)] = &[
+    ("get", "/v1/bitcoin/fee", "target", "integer"),
+    ("delete", "/v1/lightning/channels/{channel_id}", "force", "boolean"),
+];
+
+/// The OpenAPI 3 document
+pub fn document() -> Value {
+    let mut paths = Map::new();
+    for (method, path, summary, body) in OPERATIONS {
+        let mut operation = json!({
+            "summary": summary,
+            "parameters": parameters(method, path),
+            "responses": {
+                "200": { "description": "Success", "content": { "application/json": { "schema": { "type": "object" } } } },
+                "default": { "description": "Error", "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } } },
+            },
+        });
+        if *path == "/v1/openapi.json" {
+            operation["security"] = json!([]);
+        }
+        if !body.is_empty() {
+            let properties: Map<String, Value> = body.iter()
+                .map(|(name, kind, _)| (name.to_string(), json!({ "type": kind })))
+                .collect();
+            let required: Vec<&str> = body.iter()
+                .filter(|(_, _, required)| *required)
+                .map(|(name, _, _)| *name)
+                .collect();
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": {
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                } } },
+            });
+        }
+
+        paths.entry(path.to_string())
+            .or_insert_with(|| json!({}))[*method] = operation;
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "OPSource admin API",
+            "version": crate::VERSION,
+            "description": "Bitcoin wallet and Lightning node administration. GET requests need the \
+                read-only or admin token; all other requests need the admin token.",
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": { "token": { "type": "http", "scheme": "bearer" } },
+            "schemas": {
+                "Error": { "type": "object", "properties": { "error": { "type": "string" } } },
+            },
+        },
+        "security": [{ "token": [] }],
+    })
+}
+
+/// Parameters of an operation: those templated into the path (e.g. `{txid}`), then query parameters
+fn parameters(method: &str, path: &str) -> Vec<Value> {
+    let path_parameters = path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{').and_then(|segment| segment.strip_suffix('}')))
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }));
+    let query_parameters = QUERY_PARAMETERS.iter()
+        .filter(|(query_method, query_path, _, _)| *query_method == method && *query_path == path)
+        .map(|(_, _, name, kind)| json!({ "name": name, "in": "query", "required": false, "schema": { "type": kind } }));
+
+    path_parameters.chain(query_parameters).collect()
+}
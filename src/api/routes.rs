@@ -0,0 +1,332 @@
+// Admin API request routing
+// Authenticates each request and maps it onto the Bitcoin and Lightning interfaces
+
+use std::collections::BTreeMap;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api::auth::Scope;
+use crate::api::events::{self, EventPoller};
+use crate::api::http::{self, Request};
+use crate::api::{json, openapi, ApiContext, ApiError, ApiResult};
+use crate::bitcoin::encoding::from_hex;
+use crate::bitcoin::{AddressType, BitcoinInterface};
+use crate::lightning::interface::{ChannelType, LightningInterface, OpenChannelOptions};
+
+/// Serve one connection: read a request, answer it, close
+pub(crate) fn handle_connection(mut stream: TcpStream, context: Arc<ApiContext>) {
+    let request = match http::read_request(&mut stream, context.request_timeout) {
+        Ok(request) => request,
+        Err(e) => {
+            let _ = http::write_json(&mut stream, e.status(), &error_body(&e));
+            return;
+        }
+    };
+
+    if request.segments() == ["v1", "events"] && request.method == "GET" {
+        let accepted = authorize(&request, &context)
+            .and_then(|_| accept_websocket(&request))
+            .and_then(|accept_key| Ok((accept_key, EventPoller::new(context.lightning.clone())?)));
+        match accepted {
+            Ok((accept_key, poller)) => {
+                if http::write_upgrade(&mut stream, &accept_key).is_ok() {
+                    events::stream_events(stream, poller, context.event_poll_interval, context.shutdown.clone());
+                }
+            }
+            Err(e) => {
+                let _ = http::write_json(&mut stream, e.status(), &error_body(&e));
+            }
+        }
+        return;
+    }
+
+    let (status, body) = match authorize(&request, &context)
+        .and_then(|_| route(&request, context.bitcoin.as_ref(), context.lightning.as_ref()))
+    {
+        Ok(body) => (200, body),
+        Err(e) => (e.status(), error_body(&e)),
+    };
+    let _ = http::write_json(&mut stream, status, &body);
+}
+
+/// JSON body of an error response
+fn error_body(error: &ApiError) -> Value {
+    json!({ "error": error.to_string() })
+}
+
+/// Check the request's token allows it
+fn authorize(request: &Request, context: &ApiContext) -> ApiResult<()> {
+    // The API description is public so clients can discover it
+    if request.path == "/v1/openapi.json" {
+        return Ok(());
+    }
+
+    let token = request.header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| request.query("token"))
+        .ok_or(ApiError::Unauthorized)?;
+    let scope = context.auth.authorize(token).ok_or(ApiError::Unauthorized)?;
+
+    let required = if request.method == "GET" { Scope::ReadOnly } else { Scope::Admin };
+    if scope < required {
+        return Err(ApiError::Forbidden);
+    }
+
+    Ok(())
+}
+
+/// Validate a WebSocket upgrade request, returning the accept key
+fn accept_websocket(request: &Request) -> ApiResult<String> {
+    let is_upgrade = request.header("upgrade")
+        .map(|value| value.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+    let key = request.header("sec-websocket-key")
+        .filter(|_| is_upgrade)
+        .ok_or_else(|| ApiError::BadRequest("Expected a WebSocket upgrade".to_string()))?;
+
+    Ok(tungstenite::handshake::derive_accept_key(key.as_bytes()))
+}
+
+/// Dispatch an authorized request
+pub(crate) fn route(
+    request: &Request,
+    bitcoin: &dyn BitcoinInterface,
+    lightning: &dyn LightningInterface,
+) -> ApiResult<Value> {
+    let segments = request.segments();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["v1", "openapi.json"]) => Ok(openapi::document()),
+
+        ("GET", ["v1", "info"]) => {
+            let node = lightning.get_node_info()?;
+            Ok(json!({
+                "version": crate::VERSION,
+                "block_height": bitcoin.get_block_height()?,
+                "balance_sat": bitcoin.get_balance()?,
+                "node": json::node_info(&node),
+                "num_channels": lightning.list_channels()?.len(),
+                "num_peers": lightning.list_peers()?.len(),
+            }))
+        }
+
+        // On-chain wallet and chain
+
+        ("GET", ["v1", "bitcoin", "balance"]) => {
+            Ok(json!({ "balance_sat": bitcoin.get_balance()? }))
+        }
+
+        ("POST", ["v1", "bitcoin", "addresses"]) => {
+            #[derive(Deserialize)]
+            struct Body { #[serde(rename = "type")] address_type: Option<String> }
+            let body: Body = request.json()?;
+            let address_type = match body.address_type {
+                Some(name) => json::parse_address_type(&name)
+                    .ok_or_else(|| ApiError::BadRequest(format!("Unknown address type: {}", name)))?,
+                None => AddressType::P2WPKH,
+            };
+            Ok(json::address(&bitcoin.generate_address(address_type)?))
+        }
+
+        ("POST", ["v1", "bitcoin", "transactions"]) => {
+            #[derive(Deserialize)]
+            struct Body { address: String, amount_sat: u64, fee_rate: Option<u64> }
+            let body: Body = request.json()?;
+            let fee_rate = match body.fee_rate {
+                Some(fee_rate) => fee_rate,
+                None => bitcoin.estimate_fee(6)?,
+            };
+            let transaction = bitcoin.create_transaction(vec![(body.address, body.amount_sat)], fee_rate)?;
+            let txid = bitcoin.broadcast_transaction(&transaction)?;
+            Ok(json!({ "txid": txid, "transaction": json::transaction(&transaction) }))
+        }
+
+        ("GET", ["v1", "bitcoin", "transactions"]) => {
+            let transactions = bitcoin.list_transactions()?;
+            Ok(json!({ "transactions": transactions.iter().map(json::transaction).collect::<Vec<_>>() }))
+        }
+
+        ("GET", ["v1", "bitcoin", "transactions", txid]) => {
+            Ok(json::transaction(&bitcoin.get_transaction(txid)?))
+        }
+
+        ("GET", ["v1", "bitcoin", "blocks", block]) => {
+            let hash = match block.parse::<u32>() {
+                Ok(height) => bitcoin.get_block_hash(height)?,
+                Err(_) => block.to_string(),
+            };
+            Ok(json::block_header(&bitcoin.get_block_header(&hash)?))
+        }
+
+        ("GET", ["v1", "bitcoin", "height"]) => {
+            Ok(json!({ "block_height": bitcoin.get_block_height()? }))
+        }
+
+        ("GET", ["v1", "bitcoin", "fee"]) => {
+            let target = match request.query("target") {
+                Some(target) => target.parse::<u8>()
+                    .map_err(|_| ApiError::BadRequest(format!("Invalid confirmation target: {}", target)))?,
+                None => 6,
+            };
+            Ok(json!({ "target_blocks": target, "fee_rate": bitcoin.estimate_fee(target)? }))
+        }
+
+        // Lightning node
+
+        ("GET", ["v1", "lightning", "info"]) => {
+            Ok(json::node_info(&lightning.get_node_info()?))
+        }
+
+        ("POST", ["v1", "lightning", "unlock"]) => {
+            #[derive(Deserialize)]
+            struct Body { passphrase: String }
+            let body: Body = request.json()?;
+            lightning.unlock(&body.passphrase)?;
+            Ok(json!({ "locked": false }))
+        }
+
+        ("POST", ["v1", "lightning", "lock"]) => {
+            lightning.lock()?;
+            Ok(json!({ "locked": true }))
+        }
+
+        ("GET", ["v1", "lightning", "peers"]) => {
+            let peers = lightning.list_peers()?;
+            Ok(json!({ "peers": peers.iter().map(json::node_info).collect::<Vec<_>>() }))
+        }
+
+        ("POST", ["v1", "lightning", "peers"]) => {
+            #[derive(Deserialize)]
+            struct Body { pubkey: String, host: String, port: u16 }
+            let body: Body = request.json()?;
+            lightning.connect_peer(&body.pubkey, &body.host, body.port)?;
+            Ok(json!({ "pubkey": body.pubkey }))
+        }
+
+        // Channels
+
+        ("GET", ["v1", "lightning", "channels"]) => {
+            let channels = lightning.list_channels()?;
+            Ok(json!({ "channels": channels.iter().map(json::channel).collect::<Vec<_>>() }))
+        }
+
+        ("POST", ["v1", "lightning", "channels"]) => {
+            #[derive(Deserialize)]
+            struct Body {
+                pubkey: String,
+                capacity_sat: u64,
+                push_msat: Option<u64>,
+                #[serde(default)]
+                private: bool,
+                #[serde(default)]
+                anchors: bool,
+                #[serde(default)]
+                zero_conf: bool,
+                remote_funding_sat: Option<u64>,
+            }
+            let body: Body = request.json()?;
+            let options = OpenChannelOptions {
+                push_msat: body.push_msat,
+                is_private: body.private,
+                channel_type: if body.anchors { ChannelType::AnchorOutputs } else { ChannelType::StaticRemoteKey },
+                zero_conf: body.zero_conf,
+                remote_funding_sat: body.remote_funding_sat,
+            };
+            Ok(json::channel(&lightning.open_channel(&body.pubkey, body.capacity_sat, options)?))
+        }
+
+        ("DELETE", ["v1", "lightning", "channels", channel_id]) => {
+            let force = request.query("force").map(|value| value == "true").unwrap_or(false);
+            let txid = lightning.close_channel(channel_id, force)?;
+            Ok(json!({ "closing_txid": txid }))
+        }
+
+        // Invoices
+
+        ("POST", ["v1", "lightning", "invoices"]) => {
+            #[derive(Deserialize)]
+            struct Body { amount_msat: Option<u64>, #[serde(default)] description: String, expiry: Option<u32> }
+            let body: Body = request.json()?;
+            Ok(json::invoice(&lightning.create_invoice(body.amount_msat, &body.description, body.expiry)?))
+        }
+
+        ("GET", ["v1", "lightning", "invoices", payment_hash]) => {
+            let state = lightning.get_invoice_state(payment_hash)?;
+            Ok(json!({ "payment_hash": payment_hash, "state": json::invoice_state_name(state) }))
+        }
+
+        ("POST", ["v1", "lightning", "decode"]) => {
+            #[derive(Deserialize)]
+            struct Body { bolt11: String }
+            let body: Body = request.json()?;
+            Ok(json::invoice(&lightning.decode_invoice(&body.bolt11)?))
+        }
+
+        // Payments
+
+        ("GET", ["v1", "lightning", "payments"]) => {
+            let payments = lightning.list_payments()?;
+            Ok(json!({ "payments": payments.iter().map(json::payment).collect::<Vec<_>>() }))
+        }
+
+        ("POST", ["v1", "lightning", "payments"]) => {
+            #[derive(Deserialize)]
+            struct Body { bolt11: Option<String>, lnurl: Option<String>, amount_msat: Option<u64>, comment: Option<String> }
+            let body: Body = request.json()?;
+            let payment = match (body.bolt11, body.lnurl) {
+                (Some(bolt11), None) => lightning.pay_invoice(&bolt11, body.amount_msat)?,
+                (None, Some(lnurl)) => {
+                    let amount_msat = body.amount_msat
+                        .ok_or_else(|| ApiError::BadRequest("amount_msat is required to pay an LNURL".to_string()))?;
+                    lightning.pay_lnurl(&lnurl, amount_msat, body.comment.as_deref())?
+                }
+                _ => return Err(ApiError::BadRequest("Exactly one of bolt11 or lnurl is required".to_string())),
+            };
+            Ok(json::payment(&payment))
+        }
+
+        ("GET", ["v1", "lightning", "payments", payment_hash]) => {
+            let payment = lightning.get_payment(payment_hash)?
+                .ok_or_else(|| ApiError::NotFound(format!("Payment {}", payment_hash)))?;
+            Ok(json::payment(&payment))
+        }
+
+        ("GET", ["v1", "lightning", "received"]) => {
+            let payments = lightning.list_received_payments()?;
+            Ok(json!({ "payments": payments.iter().map(json::payment).collect::<Vec<_>>() }))
+        }
+
+        ("POST", ["v1", "lightning", "keysend"]) => {
+            #[derive(Deserialize)]
+            struct Body { pubkey: String, amount_msat: u64, #[serde(default)] custom_records: BTreeMap<u64, String> }
+            let body: Body = request.json()?;
+            let custom_records = body.custom_records.into_iter()
+                .map(|(record_type, value)| from_hex(&value)
+                    .map(|value| (record_type, value))
+                    .map_err(|_| ApiError::BadRequest(format!("Custom record {} is not hex", record_type))))
+                .collect::<ApiResult<_>>()?;
+            Ok(json::payment(&lightning.keysend(&body.pubkey, body.amount_msat, custom_records)?))
+        }
+
+        // Message signing
+
+        ("POST", ["v1", "lightning", "signmessage"]) => {
+            #[derive(Deserialize)]
+            struct Body { message: String }
+            let body: Body = request.json()?;
+            Ok(json!({ "signature": lightning.sign_message(&body.message)? }))
+        }
+
+        ("POST", ["v1", "lightning", "verifymessage"]) => {
+            #[derive(Deserialize)]
+            struct Body { message: String, signature: String }
+            let body: Body = request.json()?;
+            Ok(json!({ "pubkey": lightning.verify_message(&body.message, &body.signature)? }))
+        }
+
+        _ => Err(ApiError::NotFound(format!("No route for {} {}", request.method, request.path))),
+    }
+}
@@ -18,10 +18,16 @@ fn main() {
     println!("\nUsing Bitcoin implementation: {:?}", bitcoin_interface.implementation_type());
     
     // Get lightning interface
-    let lightning_interface = opsource::lightning::create_lightning_interface(
+    let lightning_interface = match opsource::lightning::create_lightning_interface(
         &config,
         bitcoin_interface.clone(),
-    );
+    ) {
+        Ok(lightning_interface) => lightning_interface,
+        Err(e) => {
+            eprintln!("Failed to create Lightning interface: {}", e);
+            std::process::exit(1);
+        }
+    };
     println!("Using Lightning implementation: {:?}", lightning_interface.implementation_type());
     
     // Create Bitcoin-Lightning bridge
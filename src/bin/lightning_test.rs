@@ -12,22 +12,32 @@ fn main() {
     opsource::init();
     
     // Create a configuration
-    let config = config::Config::from_env();
+    let config = match config::Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            std::process::exit(1);
+        }
+    };
     
     // Create a Bitcoin interface
     let bitcoin_interface = bitcoin::get_current_bitcoin_interface(&config);
     
     // Create a Lightning interface
-    let lightning_interface = lightning::create_lightning_interface(
-        &config,
-        bitcoin_interface.clone()
-    );
+    let lightning_interface = match lightning::create_lightning_interface(&config, bitcoin_interface.clone()) {
+        Ok(lightning_interface) => lightning_interface,
+        Err(e) => {
+            eprintln!("Failed to create Lightning interface: {}", e);
+            std::process::exit(1);
+        }
+    };
     
     // Print implementation information
     println!("\nUsing {} Bitcoin implementation",
         match bitcoin_interface.implementation_type() {
             bitcoin::BitcoinImplementationType::Python => "Python",
             bitcoin::BitcoinImplementationType::Rust => "Rust",
+            bitcoin::BitcoinImplementationType::Simulated => "Simulated",
         }
     );
     
@@ -77,7 +87,7 @@ fn run_tests(ln: &dyn lightning::interface::LightningInterface) {
     // Test 4: Open a channel
     println!("\n4. Opening a channel...");
     let channel_capacity = 100_000; // 100k sats
-    match ln.open_channel(peer_pubkey, channel_capacity, None, false) {
+    match ln.open_channel(peer_pubkey, channel_capacity, lightning::interface::OpenChannelOptions::default()) {
         Ok(channel) => {
             println!("Successfully opened channel:");
             println!("Channel ID: {}", channel.channel_id);
@@ -0,0 +1,362 @@
+// Electrum server stand-in
+// Serves a simulated chain over the Electrum protocol on a loopback socket, so
+// implementations that sync through Electrum (the BDK-based Rust wallet) can
+// run against recorded fixtures without a node or network access.
+//
+// Only the methods a wallet needs to sync, estimate fees and look up
+// transactions are implemented. Requests are newline-delimited JSON-RPC, alone
+// or batched in an array, as sent by electrum-client.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use serde_json::{json, Value};
+
+use crate::bitcoin::encoding::{serialize_block_header, serialize_transaction, sha256, to_hex};
+use crate::bitcoin::interface::{BitcoinError, BitcoinInterface, BitcoinResult, BitcoinTransaction};
+use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+
+/// Protocol version reported by `server.version`
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// Minimum relay fee reported by `blockchain.relayfee`, in BTC/kvB
+const RELAY_FEE_BTC_PER_KVB: f64 = 0.00001;
+
+/// Most headers returned by one `blockchain.block.headers` request
+const MAX_HEADERS: u32 = 2016;
+
+/// Running Electrum server over a simulated chain
+///
+/// Serves each connection on its own thread. Stops when `shutdown` is called or
+/// the server is dropped.
+pub struct ElectrumServer {
+    /// Address actually bound
+    local_addr: SocketAddr,
+
+    /// Set to stop the accept loop
+    shutdown: Arc<AtomicBool>,
+
+    /// Accept loop thread
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl ElectrumServer {
+    /// Bind a loopback port and start serving the chain
+    pub fn start(chain: Arc<SimulatedBitcoinImplementation>) -> BitcoinResult<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .map_err(|e| BitcoinError::NetworkError(format!("Failed to bind Electrum server: {}", e)))?;
+        let local_addr = listener.local_addr()
+            .map_err(|e| BitcoinError::NetworkError(format!("Failed to read listening address: {}", e)))?;
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let accept_shutdown = shutdown.clone();
+        let accept_thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let chain = chain.clone();
+                    thread::spawn(move || serve_connection(stream, &chain));
+                }
+            }
+        });
+
+        Ok(ElectrumServer {
+            local_addr,
+            shutdown,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Plain TCP Electrum URL of the server
+    pub fn url(&self) -> String {
+        format!("tcp://{}", self.local_addr)
+    }
+
+    /// Stop accepting connections
+    pub fn shutdown(&mut self) {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.local_addr);
+        if let Some(thread) = self.accept_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ElectrumServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Electrum script hash: SHA256 of the scriptPubKey, byte-reversed hex
+pub fn script_hash(script_pubkey: &[u8]) -> String {
+    let mut hash = sha256(script_pubkey);
+    hash.reverse();
+    to_hex(&hash)
+}
+
+/// Answer requests until the client disconnects
+fn serve_connection(stream: TcpStream, chain: &SimulatedBitcoinImplementation) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(Value::Array(requests)) => Value::Array(
+                requests.iter().map(|request| respond(chain, request)).collect()
+            ),
+            Ok(request) => respond(chain, &request),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+            }),
+        };
+
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
+
+/// Build the JSON-RPC response to one request
+fn respond(chain: &SimulatedBitcoinImplementation, request: &Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = request.get("params")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    match call(chain, method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(message) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": 1, "message": message },
+        }),
+    }
+}
+
+/// Dispatch a method call
+fn call(chain: &SimulatedBitcoinImplementation, method: &str, params: &[Value]) -> Result<Value, String> {
+    let string_param = |index: usize| params.get(index)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("{}: missing string parameter {}", method, index));
+    let number_param = |index: usize| params.get(index)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| format!("{}: missing numeric parameter {}", method, index));
+
+    match method {
+        "server.version" => Ok(json!(["opsource-simulated", PROTOCOL_VERSION])),
+        "server.banner" => Ok(json!("Simulated chain")),
+        "server.ping" | "server.peers.subscribe" => Ok(json!(null)),
+        "blockchain.relayfee" => Ok(json!(RELAY_FEE_BTC_PER_KVB)),
+        "blockchain.estimatefee" => {
+            let target = number_param(0)?.min(u8::MAX as u64) as u8;
+            let fee_rate = chain.estimate_fee(target).map_err(|e| e.to_string())?;
+            Ok(json!(fee_rate as f64 * 1000.0 / 100_000_000.0))
+        }
+        "blockchain.headers.subscribe" => {
+            let height = chain.get_block_height().map_err(|e| e.to_string())?;
+            Ok(json!({ "height": height, "hex": header_hex(chain, height)? }))
+        }
+        "blockchain.block.header" => {
+            let height = number_param(0)? as u32;
+            Ok(json!(header_hex(chain, height)?))
+        }
+        "blockchain.block.headers" => {
+            let start = number_param(0)? as u32;
+            let tip = chain.get_block_height().map_err(|e| e.to_string())?;
+            let count = (number_param(1)? as u32)
+                .min(MAX_HEADERS)
+                .min((tip + 1).saturating_sub(start));
+            let hex: String = (start..start + count)
+                .map(|height| header_hex(chain, height))
+                .collect::<Result<_, _>>()?;
+            Ok(json!({ "count": count, "hex": hex, "max": MAX_HEADERS }))
+        }
+        "blockchain.transaction.get" => {
+            let tx = chain.get_transaction(string_param(0)?).map_err(|e| e.to_string())?;
+            let raw = serialize_transaction(&tx, true).map_err(|e| e.to_string())?;
+            Ok(json!(to_hex(&raw)))
+        }
+        "blockchain.scripthash.get_history" => {
+            let history = script_history(chain, string_param(0)?);
+            Ok(Value::from(history.iter()
+                .map(|(tx, height)| json!({ "tx_hash": tx.txid, "height": height.unwrap_or(0) }))
+                .collect::<Vec<_>>()))
+        }
+        "blockchain.scripthash.subscribe" => {
+            let history = script_history(chain, string_param(0)?);
+            if history.is_empty() {
+                return Ok(json!(null));
+            }
+            let status: String = history.iter()
+                .map(|(tx, height)| format!("{}:{}:", tx.txid, height.unwrap_or(0)))
+                .collect();
+            Ok(json!(to_hex(&sha256(status.as_bytes()))))
+        }
+        "blockchain.scripthash.listunspent" => {
+            let unspent = script_unspent(chain, string_param(0)?);
+            Ok(Value::from(unspent.iter()
+                .map(|(txid, vout, value, height)| json!({
+                    "tx_hash": txid,
+                    "tx_pos": vout,
+                    "value": value,
+                    "height": height.unwrap_or(0),
+                }))
+                .collect::<Vec<_>>()))
+        }
+        "blockchain.scripthash.get_balance" => {
+            let unspent = script_unspent(chain, string_param(0)?);
+            let (confirmed, unconfirmed) = unspent.iter()
+                .fold((0u64, 0u64), |(confirmed, unconfirmed), (_, _, value, height)| match height {
+                    Some(_) => (confirmed + value, unconfirmed),
+                    None => (confirmed, unconfirmed + value),
+                });
+            Ok(json!({ "confirmed": confirmed, "unconfirmed": unconfirmed }))
+        }
+        other => Err(format!("Method {} is not supported by the simulated Electrum server", other)),
+    }
+}
+
+/// Serialized header (hex) of the block at a height
+fn header_hex(chain: &SimulatedBitcoinImplementation, height: u32) -> Result<String, String> {
+    let header = chain.get_block_hash(height)
+        .and_then(|hash| chain.get_block_header(&hash))
+        .map_err(|e| e.to_string())?;
+    Ok(to_hex(&serialize_block_header(&header)))
+}
+
+/// Transactions paying or spending a script, with their block heights
+fn script_history(chain: &SimulatedBitcoinImplementation, hash: &str) -> Vec<(BitcoinTransaction, Option<u32>)> {
+    let history = chain.history();
+    let pays_script = |txid: &str, vout: u32| history.iter()
+        .find(|(tx, _)| tx.txid == txid)
+        .and_then(|(tx, _)| tx.outputs.get(vout as usize))
+        .is_some_and(|output| script_hash(&output.script_pubkey) == hash);
+
+    history.iter()
+        .filter(|(tx, _)| {
+            tx.outputs.iter().any(|output| script_hash(&output.script_pubkey) == hash)
+                || tx.inputs.iter().any(|input| pays_script(&input.txid, input.vout))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Unspent outputs paying a script, as (txid, vout, value, block height)
+fn script_unspent(chain: &SimulatedBitcoinImplementation, hash: &str) -> Vec<(String, u32, u64, Option<u32>)> {
+    script_history(chain, hash).into_iter()
+        .flat_map(|(tx, height)| {
+            let txid = tx.txid.clone();
+            tx.outputs.into_iter()
+                .enumerate()
+                .filter(|(_, output)| script_hash(&output.script_pubkey) == hash)
+                .map(move |(vout, output)| (txid.clone(), vout as u32, output.value, height))
+        })
+        .filter(|(txid, vout, _, _)| chain.get_utxo(txid, *vout).is_some())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::encoding::{address_to_script_pubkey, from_hex};
+    use crate::bitcoin::interface::AddressType;
+
+    /// Minimal line-based JSON-RPC client
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(server: &ElectrumServer) -> Self {
+            let writer = TcpStream::connect(server.local_addr()).unwrap();
+            Client { reader: BufReader::new(writer.try_clone().unwrap()), writer }
+        }
+
+        fn send(&mut self, request: Value) -> Value {
+            writeln!(self.writer, "{}", request).unwrap();
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+
+        fn call(&mut self, method: &str, params: Value) -> Value {
+            let response = self.send(json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params }));
+            assert_eq!(response["id"], 7);
+            response["result"].clone()
+        }
+    }
+
+    #[test]
+    fn test_serves_wallet_history() {
+        let config = crate::config::Config {
+            bitcoin_network: Some("regtest".to_string()),
+            ..Default::default()
+        };
+        let chain = Arc::new(SimulatedBitcoinImplementation::new(&config).with_fee_rate(5));
+        let address = chain.generate_address(AddressType::P2WPKH).unwrap().address;
+        let funding_txid = chain.fund_address(&address, 50_000).unwrap();
+        let hash = script_hash(&address_to_script_pubkey(&address).unwrap());
+
+        let server = ElectrumServer::start(chain.clone()).unwrap();
+        assert!(server.url().starts_with("tcp://127.0.0.1:"));
+        let mut client = Client::connect(&server);
+
+        let tip = client.call("blockchain.headers.subscribe", json!([]));
+        assert_eq!(tip["height"], 1);
+        let header = from_hex(tip["hex"].as_str().unwrap()).unwrap();
+        assert_eq!(header.len(), 80);
+        assert_eq!(client.call("blockchain.block.header", json!([1])), tip["hex"]);
+        assert_eq!(client.call("blockchain.block.headers", json!([0, 10]))["count"], 2);
+
+        assert_eq!(
+            client.call("blockchain.scripthash.get_history", json!([hash])),
+            json!([{ "tx_hash": funding_txid, "height": 1 }])
+        );
+        assert_eq!(client.call("blockchain.scripthash.get_balance", json!([hash]))["confirmed"], 50_000);
+        assert_eq!(client.call("blockchain.estimatefee", json!([6])), json!(0.00005));
+
+        // Raw transactions hash back to their txid
+        let raw = client.call("blockchain.transaction.get", json!([funding_txid]));
+        let tx = chain.get_transaction(&funding_txid).unwrap();
+        assert_eq!(raw, json!(to_hex(&serialize_transaction(&tx, true).unwrap())));
+
+        // Batches are answered in order, errors per request
+        let batch = client.send(json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "server.version", "params": ["test", "1.4"] },
+            { "jsonrpc": "2.0", "id": 2, "method": "blockchain.transaction.broadcast", "params": ["00"] },
+        ]));
+        assert_eq!(batch[0]["result"][1], PROTOCOL_VERSION);
+        assert_eq!(batch[1]["id"], 2);
+        assert!(batch[1]["error"]["message"].as_str().unwrap().contains("not supported"));
+    }
+}
@@ -0,0 +1,515 @@
+// Bitcoin encoding helpers
+// Consensus serialization, txids, segwit v0 signature hashes and address/script
+// conversion for the implementation-independent types in the interface module.
+//
+// These helpers let components that only see `BitcoinTransaction` (swaps, the
+// simulated chain) build and sign transactions without depending on a specific
+// Bitcoin implementation.
+
+use hmac::{Hmac, Mac};
+use ripemd::Ripemd160;
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey, XOnlyPublicKey};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::bitcoin::interface::{
+    BitcoinError, BitcoinResult, BitcoinTransaction, AddressType, BlockHeader
+};
+
+/// SIGHASH_ALL signature hash type
+pub const SIGHASH_ALL: u32 = 0x01;
+
+/// Flag marking a hardened BIP32 child index
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// Script opcodes used by the templates in this crate
+pub mod opcodes {
+    pub const OP_0: u8 = 0x00;
+    pub const OP_PUSHDATA1: u8 = 0x4c;
+    pub const OP_1: u8 = 0x51;
+    pub const OP_2: u8 = 0x52;
+    pub const OP_16: u8 = 0x60;
+    pub const OP_IF: u8 = 0x63;
+    pub const OP_NOTIF: u8 = 0x64;
+    pub const OP_ELSE: u8 = 0x67;
+    pub const OP_ENDIF: u8 = 0x68;
+    pub const OP_IFDUP: u8 = 0x73;
+    pub const OP_DROP: u8 = 0x75;
+    pub const OP_EQUAL: u8 = 0x87;
+    pub const OP_EQUALVERIFY: u8 = 0x88;
+    pub const OP_SHA256: u8 = 0xa8;
+    pub const OP_CHECKSIG: u8 = 0xac;
+    pub const OP_CHECKMULTISIG: u8 = 0xae;
+    pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+    pub const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+}
+
+/// Double SHA256
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    let first = Sha256::digest(data);
+    Sha256::digest(first).into()
+}
+
+/// Single SHA256
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// RIPEMD160 of SHA256, as committed to by P2WPKH outputs
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+/// BIP340 tagged hash
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// HMAC-SHA512 of a message under a key
+pub fn hmac_sha512(key: &[u8], message: &[u8]) -> [u8; 64] {
+    let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// BIP32 private key derivation from a seed along a path
+///
+/// Indexes with the `HARDENED` bit set are hardened children.
+pub fn derive_private_key(seed: &[u8], path: &[u32]) -> BitcoinResult<SecretKey> {
+    let secp = Secp256k1::new();
+    let invalid = || BitcoinError::WalletError("Key derivation produced an invalid key".to_string());
+
+    let master = hmac_sha512(b"Bitcoin seed", seed);
+    let mut key = SecretKey::from_slice(&master[..32]).map_err(|_| invalid())?;
+    let mut chain_code: [u8; 32] = master[32..].try_into().expect("64-byte HMAC");
+
+    for index in path {
+        let mut data = Vec::with_capacity(37);
+        if index & HARDENED != 0 {
+            data.push(0);
+            data.extend_from_slice(&key.secret_bytes());
+        } else {
+            data.extend_from_slice(&PublicKey::from_secret_key(&secp, &key).serialize());
+        }
+        data.extend_from_slice(&index.to_be_bytes());
+
+        let child = hmac_sha512(&chain_code, &data);
+        let tweak = Scalar::from_be_bytes(child[..32].try_into().expect("64-byte HMAC")).map_err(|_| invalid())?;
+        key = key.add_tweak(&tweak).map_err(|_| invalid())?;
+        chain_code = child[32..].try_into().expect("64-byte HMAC");
+    }
+
+    Ok(key)
+}
+
+/// Encode bytes as lowercase hex
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex string into bytes
+pub fn from_hex(hex: &str) -> BitcoinResult<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return Err(BitcoinError::TransactionError(format!("Odd-length hex string: {}", hex)));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| BitcoinError::TransactionError(format!("Invalid hex string: {}", hex)))
+        })
+        .collect()
+}
+
+/// Alphabet of the human-oriented z-base-32 encoding
+const ZBASE32_ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+
+/// Encode bytes as z-base-32 (as used by lnd's signmessage)
+pub fn zbase32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ZBASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ZBASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+/// Decode a z-base-32 string, dropping trailing padding bits
+pub fn zbase32_decode(encoded: &str) -> BitcoinResult<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+
+    for c in encoded.bytes() {
+        let value = ZBASE32_ALPHABET.iter().position(|a| *a == c.to_ascii_lowercase())
+            .ok_or_else(|| BitcoinError::TransactionError(format!("Invalid z-base-32 character: {}", c as char)))?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Decode a txid from its display (big-endian) hex form into internal byte order
+pub fn txid_to_bytes(txid: &str) -> BitcoinResult<[u8; 32]> {
+    let mut bytes: [u8; 32] = from_hex(txid)?
+        .try_into()
+        .map_err(|_| BitcoinError::TransactionError(format!("Invalid txid: {}", txid)))?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Append a Bitcoin CompactSize integer
+pub fn write_compact_size(buffer: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0xfc => buffer.push(value as u8),
+        0xfd..=0xffff => {
+            buffer.push(0xfd);
+            buffer.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            buffer.push(0xfe);
+            buffer.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        _ => {
+            buffer.push(0xff);
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Append a script data push using the minimal push opcode
+pub fn push_data(script: &mut Vec<u8>, data: &[u8]) {
+    match data.len() {
+        0 => script.push(opcodes::OP_0),
+        len @ 1..=75 => {
+            script.push(len as u8);
+            script.extend_from_slice(data);
+        }
+        len @ 76..=255 => {
+            script.push(opcodes::OP_PUSHDATA1);
+            script.push(len as u8);
+            script.extend_from_slice(data);
+        }
+        len => {
+            script.push(0x4d);
+            script.extend_from_slice(&(len as u16).to_le_bytes());
+            script.extend_from_slice(data);
+        }
+    }
+}
+
+/// Append a script number push (as used for CLTV/CSV arguments)
+pub fn push_int(script: &mut Vec<u8>, value: i64) {
+    if value == 0 {
+        script.push(opcodes::OP_0);
+        return;
+    }
+
+    if (1..=16).contains(&value) {
+        script.push(opcodes::OP_1 + (value as u8 - 1));
+        return;
+    }
+
+    // Minimal little-endian sign-magnitude encoding
+    let negative = value < 0;
+    let mut absolute = value.unsigned_abs();
+    let mut encoded = Vec::new();
+    while absolute > 0 {
+        encoded.push((absolute & 0xff) as u8);
+        absolute >>= 8;
+    }
+
+    if encoded.last().map_or(false, |byte| byte & 0x80 != 0) {
+        encoded.push(if negative { 0x80 } else { 0x00 });
+    } else if negative {
+        *encoded.last_mut().unwrap() |= 0x80;
+    }
+
+    push_data(script, &encoded);
+}
+
+/// Serialize a transaction, with or without segwit witness data
+pub fn serialize_transaction(tx: &BitcoinTransaction, include_witness: bool) -> BitcoinResult<Vec<u8>> {
+    let has_witness = include_witness && tx.inputs.iter()
+        .any(|input| input.witness.as_ref().map_or(false, |witness| !witness.is_empty()));
+
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&tx.version.to_le_bytes());
+
+    if has_witness {
+        buffer.extend_from_slice(&[0x00, 0x01]);
+    }
+
+    write_compact_size(&mut buffer, tx.inputs.len() as u64);
+    for input in &tx.inputs {
+        buffer.extend_from_slice(&txid_to_bytes(&input.txid)?);
+        buffer.extend_from_slice(&input.vout.to_le_bytes());
+        write_compact_size(&mut buffer, input.script_sig.len() as u64);
+        buffer.extend_from_slice(&input.script_sig);
+        buffer.extend_from_slice(&input.sequence.to_le_bytes());
+    }
+
+    write_compact_size(&mut buffer, tx.outputs.len() as u64);
+    for output in &tx.outputs {
+        buffer.extend_from_slice(&output.value.to_le_bytes());
+        write_compact_size(&mut buffer, output.script_pubkey.len() as u64);
+        buffer.extend_from_slice(&output.script_pubkey);
+    }
+
+    if has_witness {
+        for input in &tx.inputs {
+            let witness = input.witness.clone().unwrap_or_default();
+            write_compact_size(&mut buffer, witness.len() as u64);
+            for item in witness {
+                write_compact_size(&mut buffer, item.len() as u64);
+                buffer.extend_from_slice(&item);
+            }
+        }
+    }
+
+    buffer.extend_from_slice(&tx.locktime.to_le_bytes());
+    Ok(buffer)
+}
+
+/// Compute the txid (display hex) of a transaction
+pub fn compute_txid(tx: &BitcoinTransaction) -> BitcoinResult<String> {
+    let mut hash = sha256d(&serialize_transaction(tx, false)?);
+    hash.reverse();
+    Ok(to_hex(&hash))
+}
+
+/// Serialize an 80-byte block header
+pub fn serialize_block_header(header: &BlockHeader) -> Vec<u8> {
+    let mut serialized = Vec::with_capacity(80);
+    serialized.extend_from_slice(&header.version.to_le_bytes());
+    serialized.extend_from_slice(&txid_to_bytes(&header.prev_hash).unwrap_or([0u8; 32]));
+    serialized.extend_from_slice(&txid_to_bytes(&header.merkle_root).unwrap_or([0u8; 32]));
+    serialized.extend_from_slice(&header.timestamp.to_le_bytes());
+    serialized.extend_from_slice(&header.bits.to_le_bytes());
+    serialized.extend_from_slice(&header.nonce.to_le_bytes());
+    serialized
+}
+
+/// Recompute txid, size and weight after a transaction has been modified or signed
+pub fn finalize_transaction(tx: &mut BitcoinTransaction) -> BitcoinResult<()> {
+    let base_size = serialize_transaction(tx, false)?.len();
+    let total_size = serialize_transaction(tx, true)?.len();
+
+    tx.txid = compute_txid(tx)?;
+    tx.size = total_size;
+    tx.weight = base_size * 3 + total_size;
+    Ok(())
+}
+
+/// BIP143 signature hash for a segwit v0 input
+pub fn segwit_v0_signature_hash(
+    tx: &BitcoinTransaction,
+    input_index: usize,
+    script_code: &[u8],
+    value: u64,
+    sighash_type: u32,
+) -> BitcoinResult<[u8; 32]> {
+    let input = tx.inputs.get(input_index).ok_or_else(|| {
+        BitcoinError::TransactionError(format!("Input index {} out of range", input_index))
+    })?;
+
+    let mut prevouts = Vec::new();
+    let mut sequences = Vec::new();
+    for tx_input in &tx.inputs {
+        prevouts.extend_from_slice(&txid_to_bytes(&tx_input.txid)?);
+        prevouts.extend_from_slice(&tx_input.vout.to_le_bytes());
+        sequences.extend_from_slice(&tx_input.sequence.to_le_bytes());
+    }
+
+    let mut outputs = Vec::new();
+    for output in &tx.outputs {
+        outputs.extend_from_slice(&output.value.to_le_bytes());
+        write_compact_size(&mut outputs, output.script_pubkey.len() as u64);
+        outputs.extend_from_slice(&output.script_pubkey);
+    }
+
+    let mut preimage = Vec::new();
+    preimage.extend_from_slice(&tx.version.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(&prevouts));
+    preimage.extend_from_slice(&sha256d(&sequences));
+    preimage.extend_from_slice(&txid_to_bytes(&input.txid)?);
+    preimage.extend_from_slice(&input.vout.to_le_bytes());
+    write_compact_size(&mut preimage, script_code.len() as u64);
+    preimage.extend_from_slice(script_code);
+    preimage.extend_from_slice(&value.to_le_bytes());
+    preimage.extend_from_slice(&input.sequence.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(&outputs));
+    preimage.extend_from_slice(&tx.locktime.to_le_bytes());
+    preimage.extend_from_slice(&sighash_type.to_le_bytes());
+
+    Ok(sha256d(&preimage))
+}
+
+/// Segwit human-readable part for a network name
+pub fn network_hrp(network: &str) -> &'static str {
+    match network {
+        "mainnet" | "bitcoin" => "bc",
+        "regtest" => "bcrt",
+        _ => "tb",
+    }
+}
+
+/// P2WSH scriptPubKey committing to a witness script
+pub fn p2wsh_script_pubkey(witness_script: &[u8]) -> Vec<u8> {
+    let mut script = vec![opcodes::OP_0];
+    push_data(&mut script, &sha256(witness_script));
+    script
+}
+
+/// P2WPKH scriptPubKey paying a public key
+pub fn p2wpkh_script_pubkey(pubkey: &PublicKey) -> Vec<u8> {
+    witness_script_pubkey(0, &hash160(&pubkey.serialize()))
+}
+
+/// P2TR scriptPubKey for a key-path-only output (BIP86)
+///
+/// The internal key is tweaked with the hash of itself and no script tree.
+pub fn p2tr_script_pubkey(internal_key: &XOnlyPublicKey) -> BitcoinResult<Vec<u8>> {
+    let secp = Secp256k1::verification_only();
+    let tweak = Scalar::from_be_bytes(tagged_hash("TapTweak", &internal_key.serialize()))
+        .map_err(|_| BitcoinError::WalletError("Taproot tweak is out of range".to_string()))?;
+    let (output_key, _) = internal_key.add_tweak(&secp, &tweak)
+        .map_err(|e| BitcoinError::WalletError(format!("Failed to tweak taproot key: {}", e)))?;
+
+    Ok(witness_script_pubkey(1, &output_key.serialize()))
+}
+
+/// Witness program scriptPubKey for a given version and program
+pub fn witness_script_pubkey(version: u8, program: &[u8]) -> Vec<u8> {
+    let mut script = vec![if version == 0 { opcodes::OP_0 } else { opcodes::OP_1 + version - 1 }];
+    push_data(&mut script, program);
+    script
+}
+
+/// Encode a segwit address (bech32 for v0, bech32m for v1+)
+pub fn encode_segwit_address(hrp: &str, version: u8, program: &[u8]) -> BitcoinResult<String> {
+    let hrp = bech32::Hrp::parse(hrp)
+        .map_err(|e| BitcoinError::WalletError(format!("Invalid address prefix: {}", e)))?;
+    let version = bech32::Fe32::try_from(version)
+        .map_err(|e| BitcoinError::WalletError(format!("Invalid witness version: {}", e)))?;
+
+    bech32::segwit::encode(hrp, version, program)
+        .map_err(|e| BitcoinError::WalletError(format!("Failed to encode address: {}", e)))
+}
+
+/// Decode a segwit address into (hrp, witness version, program)
+pub fn decode_segwit_address(address: &str) -> BitcoinResult<(String, u8, Vec<u8>)> {
+    let (hrp, version, program) = bech32::segwit::decode(address)
+        .map_err(|e| BitcoinError::WalletError(format!("Invalid segwit address {}: {}", address, e)))?;
+
+    Ok((hrp.to_lowercase(), version.to_u8(), program))
+}
+
+/// scriptPubKey paid by a segwit address
+pub fn address_to_script_pubkey(address: &str) -> BitcoinResult<Vec<u8>> {
+    let (_, version, program) = decode_segwit_address(address)?;
+    Ok(witness_script_pubkey(version, &program))
+}
+
+/// Address for a witness scriptPubKey, if it is one
+pub fn script_pubkey_to_address(script_pubkey: &[u8], hrp: &str) -> Option<String> {
+    let version = match script_pubkey.first()? {
+        0x00 => 0,
+        op @ 0x51..=0x60 => op - opcodes::OP_1 + 1,
+        _ => return None,
+    };
+
+    let program_len = *script_pubkey.get(1)? as usize;
+    if script_pubkey.len() != program_len + 2 {
+        return None;
+    }
+
+    encode_segwit_address(hrp, version, &script_pubkey[2..]).ok()
+}
+
+/// Address type of a witness scriptPubKey
+pub fn script_pubkey_type(script_pubkey: &[u8]) -> Option<AddressType> {
+    match (script_pubkey.first()?, script_pubkey.len()) {
+        (0x00, 22) => Some(AddressType::P2WPKH),
+        (0x00, 34) => Some(AddressType::P2WSH),
+        (0x51, 34) => Some(AddressType::P2TR),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segwit_address_round_trip() {
+        // BIP173 test vector
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let script = address_to_script_pubkey(address).unwrap();
+        assert_eq!(to_hex(&script), "0014751e76e8199196d454941c45d1b3a323f1433bd6");
+        assert_eq!(script_pubkey_to_address(&script, "bc").unwrap(), address);
+        assert_eq!(script_pubkey_type(&script), Some(AddressType::P2WPKH));
+    }
+
+    #[test]
+    fn test_bip84_and_bip86_addresses() {
+        // Seed of the "abandon ... about" mnemonic used by the BIP84 and BIP86 test vectors
+        let seed = from_hex(
+            "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc1\
+             9a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+        ).unwrap();
+        let secp = Secp256k1::new();
+
+        let key = derive_private_key(&seed, &[84 | HARDENED, HARDENED, HARDENED, 0, 0]).unwrap();
+        let script = p2wpkh_script_pubkey(&PublicKey::from_secret_key(&secp, &key));
+        assert_eq!(script_pubkey_to_address(&script, "bc").unwrap(), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+
+        let key = derive_private_key(&seed, &[86 | HARDENED, HARDENED, HARDENED, 0, 0]).unwrap();
+        let (internal_key, _) = PublicKey::from_secret_key(&secp, &key).x_only_public_key();
+        let script = p2tr_script_pubkey(&internal_key).unwrap();
+        assert_eq!(script_pubkey_to_address(&script, "bc").unwrap(), "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+    }
+
+    #[test]
+    fn test_zbase32_round_trip() {
+        assert_eq!(zbase32_encode(&[0x00]), "yy");
+        assert_eq!(zbase32_encode(&[0xff, 0xff, 0xff, 0xff, 0xff]), "99999999");
+
+        let data: Vec<u8> = (0u8..65).collect();
+        assert_eq!(zbase32_decode(&zbase32_encode(&data)).unwrap(), data);
+        assert!(zbase32_decode("not base32!").is_err());
+    }
+
+    #[test]
+    fn test_push_int() {
+        let mut script = Vec::new();
+        push_int(&mut script, 500_000);
+        assert_eq!(to_hex(&script), "0320a107");
+
+        let mut script = Vec::new();
+        push_int(&mut script, 128);
+        assert_eq!(to_hex(&script), "028000");
+    }
+}
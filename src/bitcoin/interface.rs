@@ -23,6 +23,8 @@ pub enum BitcoinImplementationType {
     Python,
     /// Use the Rust bitcoin implementation (rust-bitcoin, BDK)
     Rust,
+    /// Use the in-memory simulated chain (tests and local development)
+    Simulated,
 }
 
 /// Common error type for Bitcoin operations
@@ -176,6 +178,31 @@ pub trait BitcoinInterface: Send + Sync {
     /// Returns the current height of the blockchain (number of blocks).
     fn get_block_height(&self) -> BitcoinResult<u32>;
     
+    /// Get the hash of the block at a height
+    /// 
+    /// Used by chain listeners (e.g. Lightning) to walk the best chain.
+    fn get_block_hash(&self, height: u32) -> BitcoinResult<String> {
+        Err(BitcoinError::ImplementationError(
+            format!("Block lookup by height ({}) is not supported by this implementation", height)
+        ))
+    }
+    
+    /// Get the number of confirmations of a transaction
+    /// 
+    /// Returns 0 while the transaction is in the mempool.
+    fn get_confirmations(&self, txid: &str) -> BitcoinResult<u32> {
+        Err(BitcoinError::ImplementationError(
+            format!("Confirmation lookup ({}) is not supported by this implementation", txid)
+        ))
+    }
+    
+    /// Get a block header by hash
+    fn get_block_header(&self, hash: &str) -> BitcoinResult<BlockHeader> {
+        Err(BitcoinError::ImplementationError(
+            format!("Block header lookup ({}) is not supported by this implementation", hash)
+        ))
+    }
+    
     /// Generate a new address
     /// 
     /// Creates a new Bitcoin address of the specified type.
@@ -191,11 +218,39 @@ fn create_transaction(
         fee_rate: u64,
     ) -> BitcoinResult<BitcoinTransaction>;
     
+    /// Add wallet inputs (and change) to a partially built transaction
+    /// 
+    /// Selects wallet outputs so the inputs cover the outputs plus `fee_sat`, counting
+    /// `input_value` for inputs already present or promised by another party (e.g. an
+    /// anchor being spent, or a peer's contribution to a dual-funded channel).
+    /// Returns the transaction with our inputs signed.
+    fn fund_transaction(
+        &self,
+        transaction: &BitcoinTransaction,
+        input_value: u64,
+        fee_sat: u64,
+    ) -> BitcoinResult<BitcoinTransaction> {
+        let _ = (input_value, fee_sat);
+        Err(BitcoinError::ImplementationError(
+            format!("Funding transaction {} is not supported by this implementation", transaction.txid)
+        ))
+    }
+    
     /// Broadcast a transaction to the network
     /// 
     /// Sends a signed transaction to the Bitcoin network.
     fn broadcast_transaction(&self, transaction: &BitcoinTransaction) -> BitcoinResult<String>;
     
+    /// List wallet transactions
+    /// 
+    /// Returns transactions paying to or spending from the wallet, oldest first,
+    /// with unconfirmed transactions last.
+    fn list_transactions(&self) -> BitcoinResult<Vec<BitcoinTransaction>> {
+        Err(BitcoinError::ImplementationError(
+            "Listing wallet transactions is not supported by this implementation".to_string()
+        ))
+    }
+    
     /// Get balance for wallet/address
     /// 
     /// Returns the current balance of the wallet in satoshis.
@@ -228,6 +283,11 @@ pub fn create_bitcoin_interface(
             Arc::new(PythonBitcoinImplementation::new(config))
         }
         
+        BitcoinImplementationType::Simulated => {
+            use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+            Arc::new(SimulatedBitcoinImplementation::new(config))
+        }
+        
         #[cfg(feature = "rust-bitcoin")]
         BitcoinImplementationType::Rust => {
             use crate::bitcoin::rust::RustBitcoinImplementation;
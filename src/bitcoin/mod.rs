@@ -2,6 +2,11 @@
 // Integrates both Python and Rust implementations behind a common interface
 
 pub mod interface;
+pub mod encoding;
+pub mod simulated;
+pub mod electrum;
+pub mod parity;
+pub mod test;
 #[cfg(feature = "python-bitcoin")]
 pub mod python;
 #[cfg(feature = "rust-bitcoin")]
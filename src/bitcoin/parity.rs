@@ -0,0 +1,394 @@
+// Implementation parity suite
+// Runs one deterministic scenario against every Bitcoin implementation and
+// compares each method's normalized result with golden values recorded in a
+// fixture, so the Python-to-Rust migration can be checked method by method.
+//
+// A fixture holds a BIP32 seed, the chain history the wallet should see and
+// the expected results. The simulated chain, created `with_seed` and replaying
+// the fixture's funding, is the local backend stand-in: it needs no node and
+// its results are the reference the other implementations are held to. Those
+// implementations restore their wallet from the same seed and sync against the
+// replayed chain through the Electrum server stand-in.
+//
+// Results are normalized to JSON before comparison. Values that legitimately
+// differ between runs (input txids, signatures) are left out; everything a
+// wallet derives from the seed and the chain (addresses, output scripts,
+// amounts, fees) must match exactly.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::bitcoin::encoding::{from_hex, to_hex};
+use crate::bitcoin::interface::{
+    AddressType, BitcoinError, BitcoinInterface, BitcoinResult, BitcoinTransaction,
+};
+use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+
+/// Fixtures shipped with the crate
+const BUILTIN_FIXTURES: &[&str] = &[
+    include_str!("fixtures/regtest_wallet.json"),
+];
+
+/// Confirmation target used for the fee estimation check
+const FEE_TARGET_BLOCKS: u8 = 6;
+
+/// Payment made by the transaction construction checks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixturePayment {
+    /// Recipient address
+    pub address: String,
+
+    /// Amount in satoshis
+    pub amount_sat: u64,
+
+    /// Fee rate in sat/vB
+    pub fee_rate: u64,
+}
+
+/// A recorded scenario with its golden results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParityFixture {
+    /// Fixture name, used in reports
+    pub name: String,
+
+    /// Network the wallet runs on
+    pub network: String,
+
+    /// BIP32 seed (hex) the wallet derives its keys from
+    pub seed: String,
+
+    /// Fee rate (sat/vB) the backend reports for every target
+    pub fee_rate: u64,
+
+    /// Amounts paid to the wallet's first receive addresses, one block each
+    pub funding: Vec<u64>,
+
+    /// Payment the transaction checks construct
+    pub payment: FixturePayment,
+
+    /// Golden results by check name
+    pub expected: BTreeMap<String, Value>,
+}
+
+/// A check whose result differs from the golden value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Check name, e.g. `create_transaction.fee`
+    pub check: String,
+
+    /// Golden value
+    pub expected: Value,
+
+    /// Observed value, or the error the method returned
+    pub actual: Result<Value, String>,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.actual {
+            Ok(actual) => write!(f, "{}: expected {}, got {}", self.check, self.expected, actual),
+            Err(error) => write!(f, "{}: expected {}, got error: {}", self.check, self.expected, error),
+        }
+    }
+}
+
+/// Results of one implementation against one fixture
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplementationReport {
+    /// Implementation label
+    pub implementation: String,
+
+    /// Checks that matched their golden value
+    pub passed: Vec<String>,
+
+    /// Checks that did not
+    pub divergences: Vec<Divergence>,
+}
+
+/// Results of every implementation against one fixture
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParityReport {
+    /// Fixture name
+    pub fixture: String,
+
+    /// One report per implementation, in the order they were given
+    pub implementations: Vec<ImplementationReport>,
+}
+
+impl ParityReport {
+    /// Whether every implementation matched every golden value
+    pub fn is_consistent(&self) -> bool {
+        self.implementations.iter().all(|report| report.divergences.is_empty())
+    }
+
+    /// Divergences grouped by check, as (check, implementations that diverged)
+    pub fn divergences_by_check(&self) -> BTreeMap<String, Vec<String>> {
+        let mut by_check: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for report in &self.implementations {
+            for divergence in &report.divergences {
+                by_check.entry(divergence.check.clone())
+                    .or_default()
+                    .push(report.implementation.clone());
+            }
+        }
+        by_check
+    }
+
+    /// Human-readable summary, one line per implementation and divergence
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("Fixture {}:", self.fixture)];
+        for report in &self.implementations {
+            lines.push(format!(
+                "  {}: {} passed, {} diverged",
+                report.implementation, report.passed.len(), report.divergences.len()
+            ));
+            lines.extend(report.divergences.iter().map(|divergence| format!("    {}", divergence)));
+        }
+        lines.join("\n")
+    }
+}
+
+impl ParityFixture {
+    /// Parse a fixture from JSON
+    pub fn from_json(json: &str) -> BitcoinResult<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| BitcoinError::ImplementationError(format!("Invalid parity fixture: {}", e)))
+    }
+
+    /// Fixtures shipped with the crate
+    pub fn builtin() -> Vec<Self> {
+        BUILTIN_FIXTURES.iter()
+            .map(|json| Self::from_json(json).expect("built-in parity fixtures are valid"))
+            .collect()
+    }
+
+    /// BIP32 seed implementations restore their wallet from
+    pub fn seed_bytes(&self) -> BitcoinResult<Vec<u8>> {
+        from_hex(&self.seed)
+    }
+
+    /// Configuration for implementations running this fixture
+    pub fn config(&self) -> crate::config::Config {
+        crate::config::Config {
+            bitcoin_network: Some(self.network.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Simulated chain replaying the fixture, standing in for a node
+    pub fn backend(&self) -> BitcoinResult<SimulatedBitcoinImplementation> {
+        let chain = SimulatedBitcoinImplementation::with_seed(&self.config(), &self.seed_bytes()?)
+            .with_fee_rate(self.fee_rate);
+
+        for amount in &self.funding {
+            let address = chain.generate_address(AddressType::P2WPKH)?;
+            chain.fund_address(&address.address, *amount)?;
+        }
+
+        Ok(chain)
+    }
+
+    /// Replace the golden values with an implementation's results
+    ///
+    /// Used to record a fixture from the reference implementation; errors are
+    /// not recorded.
+    pub fn record(&mut self, bitcoin: &dyn BitcoinInterface) {
+        self.expected = observe(self, bitcoin).into_iter()
+            .filter_map(|(check, result)| result.ok().map(|value| (check, value)))
+            .collect();
+    }
+
+    /// Compare an implementation's results with the golden values
+    ///
+    /// Checks without a golden value are skipped.
+    pub fn check(&self, implementation: &str, bitcoin: &dyn BitcoinInterface) -> ImplementationReport {
+        let mut observed = observe(self, bitcoin);
+        let mut report = ImplementationReport {
+            implementation: implementation.to_string(),
+            passed: Vec::new(),
+            divergences: Vec::new(),
+        };
+
+        for (check, expected) in &self.expected {
+            let actual = observed.remove(check)
+                .unwrap_or_else(|| Err("check was not run".to_string()));
+            if actual.as_ref() == Ok(expected) {
+                report.passed.push(check.clone());
+            } else {
+                report.divergences.push(Divergence {
+                    check: check.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        report
+    }
+}
+
+/// Run a fixture against several implementations
+pub fn run_parity(fixture: &ParityFixture, implementations: &[(&str, &dyn BitcoinInterface)]) -> ParityReport {
+    ParityReport {
+        fixture: fixture.name.clone(),
+        implementations: implementations.iter()
+            .map(|(label, bitcoin)| fixture.check(label, *bitcoin))
+            .collect(),
+    }
+}
+
+/// Run the scenario and normalize each method's result
+///
+/// Checks run in a fixed order because address generation advances the wallet.
+fn observe(fixture: &ParityFixture, bitcoin: &dyn BitcoinInterface) -> BTreeMap<String, Result<Value, String>> {
+    let mut results = BTreeMap::new();
+    let mut record = |check: &str, result: BitcoinResult<Value>| {
+        results.insert(check.to_string(), result.map_err(|e| e.to_string()));
+    };
+
+    record("get_block_height", bitcoin.get_block_height().map(Value::from));
+    record("get_balance", bitcoin.get_balance().map(Value::from));
+    record(
+        &format!("estimate_fee({})", FEE_TARGET_BLOCKS),
+        bitcoin.estimate_fee(FEE_TARGET_BLOCKS).map(Value::from),
+    );
+    record("list_transactions", bitcoin.list_transactions().map(|transactions| {
+        Value::from(transactions.iter().map(output_values).collect::<Vec<_>>())
+    }));
+
+    for (name, address_type) in [("p2wpkh", AddressType::P2WPKH), ("p2tr", AddressType::P2TR)] {
+        record(
+            &format!("generate_address({})", name),
+            bitcoin.generate_address(address_type).map(|address| Value::from(address.address)),
+        );
+    }
+
+    let payment = &fixture.payment;
+    match bitcoin.create_transaction(vec![(payment.address.clone(), payment.amount_sat)], payment.fee_rate) {
+        Ok(tx) => {
+            record("create_transaction", Ok(normalize_transaction(&tx)));
+            record("create_transaction.fee", tx.fee
+                .map(Value::from)
+                .ok_or_else(|| BitcoinError::TransactionError("Transaction has no fee".to_string())));
+        }
+        Err(e) => {
+            let error = e.to_string();
+            results.insert("create_transaction".to_string(), Err(error.clone()));
+            results.insert("create_transaction.fee".to_string(), Err(error));
+        }
+    }
+
+    results
+}
+
+/// Output values of a transaction
+fn output_values(tx: &BitcoinTransaction) -> Value {
+    Value::from(tx.outputs.iter().map(|output| output.value).collect::<Vec<_>>())
+}
+
+/// The parts of a transaction every implementation must agree on
+fn normalize_transaction(tx: &BitcoinTransaction) -> Value {
+    json!({
+        "version": tx.version,
+        "locktime": tx.locktime,
+        "inputs": tx.inputs.len(),
+        "sequences": tx.inputs.iter().map(|input| input.sequence).collect::<Vec<_>>(),
+        "outputs": tx.outputs.iter().map(|output| json!({
+            "value": output.value,
+            "script_pubkey": to_hex(&output.script_pubkey),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Delegates to another implementation but misreports fee estimates
+    struct SkewedFees<'a>(&'a dyn BitcoinInterface);
+
+    impl BitcoinInterface for SkewedFees<'_> {
+        fn get_transaction(&self, txid: &str) -> BitcoinResult<BitcoinTransaction> {
+            self.0.get_transaction(txid)
+        }
+
+        fn get_block(&self, hash: &str) -> BitcoinResult<Vec<BitcoinTransaction>> {
+            self.0.get_block(hash)
+        }
+
+        fn get_block_height(&self) -> BitcoinResult<u32> {
+            self.0.get_block_height()
+        }
+
+        fn generate_address(&self, address_type: AddressType) -> BitcoinResult<crate::bitcoin::BitcoinAddress> {
+            self.0.generate_address(address_type)
+        }
+
+        fn create_transaction(&self, outputs: Vec<(String, u64)>, fee_rate: u64) -> BitcoinResult<BitcoinTransaction> {
+            self.0.create_transaction(outputs, fee_rate + 1)
+        }
+
+        fn broadcast_transaction(&self, transaction: &BitcoinTransaction) -> BitcoinResult<String> {
+            self.0.broadcast_transaction(transaction)
+        }
+
+        fn list_transactions(&self) -> BitcoinResult<Vec<BitcoinTransaction>> {
+            self.0.list_transactions()
+        }
+
+        fn get_balance(&self) -> BitcoinResult<u64> {
+            self.0.get_balance()
+        }
+
+        fn estimate_fee(&self, target_blocks: u8) -> BitcoinResult<u64> {
+            self.0.estimate_fee(target_blocks).map(|fee_rate| fee_rate * 2)
+        }
+
+        fn implementation_type(&self) -> crate::bitcoin::BitcoinImplementationType {
+            self.0.implementation_type()
+        }
+    }
+
+    #[test]
+    fn test_backend_matches_recorded_fixtures() {
+        for fixture in ParityFixture::builtin() {
+            let backend = fixture.backend().unwrap();
+            let report = run_parity(&fixture, &[("simulated", &backend)]);
+            assert!(report.is_consistent(), "{}", report.summary());
+            assert_eq!(report.implementations[0].passed.len(), fixture.expected.len());
+        }
+    }
+
+    #[test]
+    fn test_recording_reproduces_fixture() {
+        let fixture = &ParityFixture::builtin()[0];
+        let mut recorded = fixture.clone();
+        recorded.expected.clear();
+        recorded.record(&fixture.backend().unwrap());
+        assert_eq!(&recorded, fixture);
+    }
+
+    #[test]
+    fn test_divergences_are_reported_per_method() {
+        let fixture = &ParityFixture::builtin()[0];
+        let reference = fixture.backend().unwrap();
+        let skewed_backend = fixture.backend().unwrap();
+        let skewed = SkewedFees(&skewed_backend);
+
+        let report = run_parity(fixture, &[("reference", &reference), ("skewed", &skewed)]);
+        assert!(!report.is_consistent());
+        assert!(report.implementations[0].divergences.is_empty());
+
+        let diverged: Vec<&str> = report.implementations[1].divergences.iter()
+            .map(|divergence| divergence.check.as_str())
+            .collect();
+        assert_eq!(diverged, ["create_transaction", "create_transaction.fee", "estimate_fee(6)"]);
+        assert!(report.implementations[1].passed.contains(&"generate_address(p2wpkh)".to_string()));
+
+        let by_check = report.divergences_by_check();
+        assert_eq!(by_check["estimate_fee(6)"], ["skewed"]);
+        assert!(report.summary().contains("skewed: 5 passed, 3 diverged"));
+    }
+}
@@ -13,8 +13,9 @@
 use bitcoin::{Transaction, Block, Address, Network, Script, Txid, consensus};
 use bdk::{
     Wallet, SyncOptions, FeeRate, 
+    bitcoin::{absolute::LockTime, bip32::ExtendedPrivKey},
     database::MemoryDatabase,
-    wallet::{AddressIndex, coin_selection::{CoinSelectionAlgorithm, DefaultCoinSelectionAlgorithm}},
+    wallet::{AddressIndex, coin_selection::LargestFirstCoinSelection, tx_builder::TxOrdering},
     blockchain::{
         electrum::{ElectrumBlockchain, ElectrumBlockchainConfig},
         ConfigurableBlockchain,
@@ -33,11 +34,38 @@ pub struct RustBitcoinImplementation {
     wallet: Mutex<Option<Wallet<MemoryDatabase>>>,
     blockchain: Mutex<Option<ElectrumBlockchain>>,
     mnemonic: Mutex<Option<Mnemonic>>,
+    /// BIP32 seed the wallet derives its keys from, None for a new mnemonic
+    seed: Option<Vec<u8>>,
+    /// Electrum server to sync against, None for the public server of the network
+    electrum_url: Option<String>,
 }
 
 impl RustBitcoinImplementation {
     /// Create a new Rust Bitcoin implementation.
     pub fn new(config: &crate::config::Config) -> Self {
+        let instance = Self::unattached(config, None, None);
+        
+        // Initialize wallet and blockchain
+        if let Err(e) = instance.initialize_wallet() {
+            println!("Warning: Failed to initialize wallet: {}", e);
+        }
+        
+        instance
+    }
+    
+    /// Create a Rust Bitcoin implementation whose wallet derives its keys from a
+    /// BIP32 seed and syncs against the given Electrum server
+    ///
+    /// Unlike `new`, fails if the wallet cannot be created or synced rather
+    /// than falling back to placeholder results.
+    pub fn with_backend(config: &crate::config::Config, seed: &[u8], electrum_url: &str) -> BitcoinResult<Self> {
+        let instance = Self::unattached(config, Some(seed.to_vec()), Some(electrum_url.to_string()));
+        instance.initialize_wallet()?;
+        Ok(instance)
+    }
+    
+    /// Create the instance without a wallet or blockchain connection
+    fn unattached(config: &crate::config::Config, seed: Option<Vec<u8>>, electrum_url: Option<String>) -> Self {
         let network_str = config.bitcoin_network.clone().unwrap_or_else(|| "testnet".to_string());
         
         // Parse the network string
@@ -54,48 +82,51 @@ pub fn new(config: &crate::config::Config) -> Self {
         
         println!("Initialized Rust Bitcoin implementation on {:?}", network);
         
-        // Create the instance first
-        let instance = RustBitcoinImplementation {
+        RustBitcoinImplementation {
             network,
             wallet: Mutex::new(None),
             blockchain: Mutex::new(None),
             mnemonic: Mutex::new(None),
-        };
-        
-        // Initialize wallet and blockchain
-        if let Err(e) = instance.initialize_wallet() {
-            println!("Warning: Failed to initialize wallet: {}", e);
+            seed,
+            electrum_url,
         }
-        
-        instance
     }
     
-    /// Initialize a new wallet and blockchain connection
+    /// Initialize the wallet and blockchain connection
     fn initialize_wallet(&self) -> BitcoinResult<()> {
-        // Generate a new mnemonic
-        let mnemonic = Mnemonic::generate(WordCount::Words12)
-            .map_err(|e| BitcoinError::WalletError(format!("Failed to generate mnemonic: {}", e)))?;
-        
-        println!("Generated new wallet with mnemonic: {}", mnemonic.to_string());
-        
-        // Store the mnemonic
-        *self.mnemonic.lock().unwrap() = Some(mnemonic.clone());
-        
-        // Create extended key from mnemonic
-        let xkey: ExtendedKey = mnemonic.into_extended_key()
-            .map_err(|e| BitcoinError::WalletError(format!("Failed to create extended key: {}", e)))?;
+        let xprv = match &self.seed {
+            Some(seed) => ExtendedPrivKey::new_master(self.network, seed)
+                .map_err(|e| BitcoinError::WalletError(format!("Failed to create xprv: {}", e)))?,
+            None => {
+                // Generate a new mnemonic
+                let mnemonic = Mnemonic::generate(WordCount::Words12)
+                    .map_err(|e| BitcoinError::WalletError(format!("Failed to generate mnemonic: {}", e)))?;
+                
+                println!("Generated new wallet with mnemonic: {}", mnemonic.to_string());
+                
+                // Store the mnemonic
+                *self.mnemonic.lock().unwrap() = Some(mnemonic.clone());
+                
+                // Create extended key from mnemonic
+                let xkey: ExtendedKey = mnemonic.into_extended_key()
+                    .map_err(|e| BitcoinError::WalletError(format!("Failed to create extended key: {}", e)))?;
+                
+                // Get an xprv from the extended key
+                xkey.into_xprv(self.network)
+                    .map_err(|e| BitcoinError::WalletError(format!("Failed to create xprv: {}", e)))?
+            }
+        };
         
-        // Get an xprv from the extended key
-        let xprv = xkey.into_xprv(self.network)
-            .map_err(|e| BitcoinError::WalletError(format!("Failed to create xprv: {}", e)))?;
+        // BIP84 account, matching other wallets restored from the same seed
+        let coin_type = if self.network == Network::Bitcoin { 0 } else { 1 };
         
         // Create a descriptor for receiving addresses
-        let receive_descriptor = format!("wpkh({}/0/*)", xprv);
+        let receive_descriptor = format!("wpkh({}/84'/{}'/0'/0/*)", xprv, coin_type);
         let receive_descriptor = Descriptor::new(receive_descriptor)
             .map_err(|e| BitcoinError::WalletError(format!("Failed to create receive descriptor: {}", e)))?;
         
         // Create a descriptor for change addresses
-        let change_descriptor = format!("wpkh({}/1/*)", xprv);
+        let change_descriptor = format!("wpkh({}/84'/{}'/0'/1/*)", xprv, coin_type);
         let change_descriptor = Descriptor::new(change_descriptor)
             .map_err(|e| BitcoinError::WalletError(format!("Failed to create change descriptor: {}", e)))?;
         
@@ -111,9 +142,10 @@ fn initialize_wallet(&self) -> BitcoinResult<()> {
         *self.wallet.lock().unwrap() = Some(wallet);
         
         // Connect to Electrum server
-        let electrum_url = match self.network {
-            Network::Bitcoin => "ssl://electrum.blockstream.info:50002",
-            Network::Testnet => "ssl://electrum.blockstream.info:60002",
+        let electrum_url = match (&self.electrum_url, self.network) {
+            (Some(url), _) => url.as_str(),
+            (None, Network::Bitcoin) => "ssl://electrum.blockstream.info:50002",
+            (None, Network::Testnet) => "ssl://electrum.blockstream.info:60002",
             _ => "ssl://electrum.blockstream.info:60002", // Default to testnet
         };
         
@@ -314,6 +346,28 @@ fn get_block_height(&self) -> BitcoinResult<u32> {
         }
     }
     
+    fn get_confirmations(&self, txid: &str) -> BitcoinResult<u32> {
+        let tx_hash = Txid::from_str(txid)
+            .map_err(|e| BitcoinError::TransactionError(format!("Invalid transaction ID: {}", e)))?;
+        
+        // Syncs the wallet, so the lookup below sees the latest confirmations
+        let height = self.get_block_height()?;
+        
+        // Only wallet transactions carry a confirmation height
+        let wallet_guard = self.get_wallet()?;
+        let wallet = wallet_guard.as_ref()
+            .ok_or_else(|| BitcoinError::ImplementationError("Wallet not initialized".to_string()))?;
+        
+        let details = wallet.get_tx(&tx_hash, false)
+            .map_err(|e| BitcoinError::WalletError(format!("Failed to look up transaction: {}", e)))?
+            .ok_or_else(|| BitcoinError::TransactionError(format!("Transaction not found in wallet: {}", txid)))?;
+        
+        Ok(details.confirmation_time
+            .map(|time| time.height)
+            .map(|confirmed| height.saturating_sub(confirmed) + 1)
+            .unwrap_or(0))
+    }
+    
     fn generate_address(&self, address_type: AddressType) -> BitcoinResult<BitcoinAddress> {
         // Get wallet
         let mut wallet_guard = self.get_wallet()?;
@@ -374,8 +428,14 @@ fn create_transaction(
             let _ = wallet.sync(blockchain, SyncOptions::default());
         }
         
-        // Convert outputs to BDK format
-        let mut tx_builder = wallet.build_tx();
+        // Largest-first selection, version 2, RBF signalled, no anti-fee-sniping
+        // locktime and change last, as the other implementations build them
+        let mut tx_builder = wallet.build_tx().coin_selection(LargestFirstCoinSelection);
+        tx_builder
+            .version(2)
+            .enable_rbf()
+            .nlocktime(LockTime::ZERO)
+            .ordering(TxOrdering::Untouched);
         
         // Add each recipient
         for (addr, amount) in outputs {
@@ -390,9 +450,6 @@ fn create_transaction(
         // Set fee rate
         tx_builder.fee_rate(FeeRate::from_sat_per_vb(fee_rate as f32));
         
-        // Enable coin selection
-        tx_builder.coin_selection(DefaultCoinSelectionAlgorithm::default());
-        
         // Finish building the transaction
         let tx_result = tx_builder.finish();
         
@@ -470,6 +527,34 @@ fn broadcast_transaction(&self, transaction: &BitcoinTransaction) -> BitcoinResu
         Ok(transaction.txid.clone())
     }
     
+    fn list_transactions(&self) -> BitcoinResult<Vec<BitcoinTransaction>> {
+        // Get wallet
+        let wallet_guard = self.get_wallet()?;
+        let wallet = wallet_guard.as_ref()
+            .ok_or_else(|| BitcoinError::ImplementationError("Wallet not initialized".to_string()))?;
+        
+        // Get blockchain and sync wallet
+        let blockchain_guard = self.get_blockchain()?;
+        if let Some(blockchain) = blockchain_guard.as_ref() {
+            let _ = wallet.sync(blockchain, SyncOptions::default());
+        }
+        
+        let mut details = wallet.list_transactions(true)
+            .map_err(|e| BitcoinError::WalletError(format!("Failed to list transactions: {}", e)))?;
+        
+        // Oldest first, unconfirmed last
+        details.sort_by_key(|detail| detail.confirmation_time.as_ref().map(|time| time.height).unwrap_or(u32::MAX));
+        
+        details.iter()
+            .filter_map(|detail| detail.transaction.as_ref().map(|tx| (tx, detail.fee)))
+            .map(|(tx, fee)| {
+                let mut transaction = self.convert_transaction(tx)?;
+                transaction.fee = fee;
+                Ok(transaction)
+            })
+            .collect()
+    }
+    
     fn get_balance(&self) -> BitcoinResult<u64> {
         // Get wallet
         let wallet_guard = self.get_wallet()?;
@@ -500,7 +585,7 @@ fn estimate_fee(&self, target_blocks: u8) -> BitcoinResult<u64> {
         
         // Use the blockchain to estimate fee
         match blockchain.estimate_fee(target_blocks as usize) {
-            Ok(fee_rate) => Ok(fee_rate.as_sat_per_vb() as u64),
+            Ok(fee_rate) => Ok(fee_rate.as_sat_per_vb().round() as u64),
             Err(e) => {
                 println!("Warning: Failed to estimate fee: {}", e);
                 // Return a reasonable default
@@ -0,0 +1,797 @@
+// Simulated Bitcoin implementation
+// An in-memory chain with a mempool, UTXO set and a simple wallet, used to
+// exercise on-chain flows (channel funding, swaps) without a node.
+//
+// The simulated chain enforces the consensus rules the rest of the crate relies
+// on: inputs must spend existing unspent outputs, outputs may not exceed inputs,
+// absolute locktimes must be final, and P2WSH spends must reveal the committed
+// witness script. Scripts themselves are not executed and signatures are not
+// verified, so callers remain responsible for building valid witnesses.
+//
+// Wallet keys are random unless the chain is created `with_seed`, in which case
+// P2WPKH and P2TR addresses follow BIP84 and BIP86 so they match any other
+// wallet restored from the same seed.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use rand::{thread_rng, Rng};
+use secp256k1::{PublicKey, Secp256k1};
+
+use crate::bitcoin::encoding::{
+    self, address_to_script_pubkey, compute_txid, network_hrp, script_pubkey_to_address,
+    script_pubkey_type, witness_script_pubkey, finalize_transaction, derive_private_key,
+    p2tr_script_pubkey, p2wpkh_script_pubkey, HARDENED,
+};
+use crate::bitcoin::interface::{
+    BitcoinInterface, BitcoinError, BitcoinResult, BitcoinTransaction,
+    BitcoinAddress, AddressType, TransactionInput, TransactionOutput,
+    BlockHeader, BitcoinImplementationType
+};
+
+/// Sequence value that disables locktime for an input
+const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+
+/// Locktime values at or above this are timestamps rather than heights
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// Estimated virtual size of a wallet input in vbytes
+const WALLET_INPUT_VSIZE: u64 = 68;
+
+/// Estimated virtual size of a transaction output in vbytes
+const OUTPUT_VSIZE: u64 = 31;
+
+/// Estimated fixed transaction overhead in vbytes
+const TX_OVERHEAD_VSIZE: u64 = 11;
+
+/// Timestamp of the simulated genesis block
+const GENESIS_TIMESTAMP: u32 = 1_700_000_000;
+
+/// Regtest difficulty target in compact form
+const REGTEST_BITS: u32 = 0x207f_ffff;
+
+/// Outputs below this value are not created as change
+const DUST_LIMIT_SAT: u64 = 546;
+
+/// A transaction known to the simulated chain
+#[derive(Debug, Clone)]
+struct SimulatedTransaction {
+    /// The transaction
+    transaction: BitcoinTransaction,
+
+    /// Height of the block that confirmed it, None while in the mempool
+    block_height: Option<u32>,
+}
+
+/// A block in the simulated chain
+#[derive(Debug, Clone)]
+struct SimulatedBlock {
+    /// Block header (its hash is the block hash)
+    header: BlockHeader,
+
+    /// Transactions confirmed in this block
+    txids: Vec<String>,
+}
+
+/// Mutable chain state
+#[derive(Debug, Default)]
+struct ChainState {
+    /// Blocks indexed by height (index 0 is the genesis block)
+    blocks: Vec<SimulatedBlock>,
+
+    /// All known transactions, confirmed or in the mempool
+    transactions: HashMap<String, SimulatedTransaction>,
+
+    /// Mempool transaction IDs in arrival order
+    mempool: Vec<String>,
+
+    /// Unspent outputs (txid, vout) -> output
+    utxos: HashMap<(String, u32), TransactionOutput>,
+
+    /// Spent outputs (txid, vout) -> spending txid
+    spends: HashMap<(String, u32), String>,
+
+    /// scriptPubKeys owned by the simulated wallet
+    wallet_scripts: HashSet<Vec<u8>>,
+
+    /// Next unused index per (BIP32 purpose, chain) for seeded wallets
+    next_index: HashMap<(u32, u32), u32>,
+}
+
+/// In-memory Bitcoin implementation for tests and local development
+pub struct SimulatedBitcoinImplementation {
+    /// Segwit human-readable part for the configured network
+    hrp: &'static str,
+
+    /// Fee rate returned by fee estimation in sat/vB
+    fee_rate: u64,
+
+    /// Seed wallet keys are derived from, None for random keys
+    seed: Option<Vec<u8>>,
+
+    /// BIP44 coin type (0 on mainnet, 1 on test networks)
+    coin_type: u32,
+
+    /// Chain state
+    state: Mutex<ChainState>,
+}
+
+impl SimulatedBitcoinImplementation {
+    /// Create a new simulated chain containing only a genesis block
+    pub fn new(config: &crate::config::Config) -> Self {
+        let network = config.bitcoin_network.clone().unwrap_or_else(|| "regtest".to_string());
+
+        let implementation = SimulatedBitcoinImplementation {
+            hrp: network_hrp(&network),
+            fee_rate: 1,
+            seed: None,
+            coin_type: if network_hrp(&network) == "bc" { 0 } else { 1 },
+            state: Mutex::new(ChainState::default()),
+        };
+
+        implementation.mine_blocks(1);
+        implementation
+    }
+
+    /// Create a simulated chain whose wallet derives its keys from a BIP32 seed
+    pub fn with_seed(config: &crate::config::Config, seed: &[u8]) -> Self {
+        SimulatedBitcoinImplementation {
+            seed: Some(seed.to_vec()),
+            ..Self::new(config)
+        }
+    }
+
+    /// Set the fee rate returned by fee estimation
+    pub fn with_fee_rate(self, fee_rate: u64) -> Self {
+        SimulatedBitcoinImplementation { fee_rate, ..self }
+    }
+
+    /// Pay an address from thin air and confirm the payment in a new block
+    ///
+    /// Stands in for a faucet or coinbase output; returns the funding txid.
+    pub fn fund_address(&self, address: &str, amount_sat: u64) -> BitcoinResult<String> {
+        let script_pubkey = address_to_script_pubkey(address)?;
+        let mut state = self.state.lock().unwrap();
+
+        // A coinbase input committing to the height of the block it is mined
+        // in (BIP34) keeps txids distinct
+        let mut script_sig = Vec::new();
+        encoding::push_int(&mut script_sig, state.blocks.len() as i64);
+        let mut tx = BitcoinTransaction {
+            txid: String::new(),
+            version: 2,
+            inputs: vec![TransactionInput {
+                txid: "0".repeat(64),
+                vout: u32::MAX,
+                script_sig,
+                sequence: SEQUENCE_FINAL,
+                witness: None,
+            }],
+            outputs: vec![TransactionOutput {
+                value: amount_sat,
+                script_pubkey,
+                address: Some(address.to_string()),
+            }],
+            locktime: 0,
+            size: 0,
+            weight: 0,
+            fee: Some(0),
+        };
+        finalize_transaction(&mut tx)?;
+
+        let txid = tx.txid.clone();
+        Self::add_outputs(&mut state, &tx);
+        state.transactions.insert(txid.clone(), SimulatedTransaction {
+            transaction: tx,
+            block_height: None,
+        });
+        state.mempool.push(txid.clone());
+        Self::mine_block(&mut state);
+
+        Ok(txid)
+    }
+
+    /// Mine blocks, confirming every mempool transaction in the first one
+    ///
+    /// Returns the hashes of the new blocks.
+    pub fn mine_blocks(&self, count: u32) -> Vec<String> {
+        let mut state = self.state.lock().unwrap();
+        (0..count).map(|_| Self::mine_block(&mut state)).collect()
+    }
+
+    /// Get an unspent output
+    pub fn get_utxo(&self, txid: &str, vout: u32) -> Option<TransactionOutput> {
+        let state = self.state.lock().unwrap();
+        state.utxos.get(&(txid.to_string(), vout)).cloned()
+    }
+
+    /// Find the transaction (confirmed or in the mempool) spending an output
+    pub fn find_spending_transaction(&self, txid: &str, vout: u32) -> Option<BitcoinTransaction> {
+        let state = self.state.lock().unwrap();
+        state.spends.get(&(txid.to_string(), vout))
+            .and_then(|spending_txid| state.transactions.get(spending_txid))
+            .map(|simulated| simulated.transaction.clone())
+    }
+
+    /// Find unspent outputs paying a scriptPubKey, as (txid, vout, output)
+    pub fn find_outputs_by_script(&self, script_pubkey: &[u8]) -> Vec<(String, u32, TransactionOutput)> {
+        let state = self.state.lock().unwrap();
+        let mut outputs: Vec<_> = state.utxos.iter()
+            .filter(|(_, output)| output.script_pubkey == script_pubkey)
+            .map(|((txid, vout), output)| (txid.clone(), *vout, output.clone()))
+            .collect();
+        outputs.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        outputs
+    }
+
+    /// Number of confirmations of a transaction (0 while in the mempool)
+    pub fn get_confirmations(&self, txid: &str) -> BitcoinResult<u32> {
+        let state = self.state.lock().unwrap();
+        let simulated = state.transactions.get(txid).ok_or_else(|| {
+            BitcoinError::TransactionError(format!("Transaction not found: {}", txid))
+        })?;
+
+        Ok(simulated.block_height
+            .map(|height| Self::tip_height(&state) - height + 1)
+            .unwrap_or(0))
+    }
+
+    /// Transaction IDs currently in the mempool
+    pub fn mempool_txids(&self) -> Vec<String> {
+        self.state.lock().unwrap().mempool.clone()
+    }
+
+    /// Every transaction in chain order with its block height, mempool last
+    pub fn history(&self) -> Vec<(BitcoinTransaction, Option<u32>)> {
+        let state = self.state.lock().unwrap();
+        state.blocks.iter()
+            .flat_map(|block| block.txids.iter())
+            .chain(state.mempool.iter())
+            .filter_map(|txid| state.transactions.get(txid))
+            .map(|simulated| (simulated.transaction.clone(), simulated.block_height))
+            .collect()
+    }
+
+    /// Height of the chain tip
+    fn tip_height(state: &ChainState) -> u32 {
+        state.blocks.len().saturating_sub(1) as u32
+    }
+
+    /// Mine a single block containing the whole mempool
+    fn mine_block(state: &mut ChainState) -> String {
+        let height = state.blocks.len() as u32;
+        let txids: Vec<String> = state.mempool.drain(..).collect();
+
+        let prev_hash = state.blocks.last()
+            .map(|block| block.header.hash.clone())
+            .unwrap_or_else(|| "0".repeat(64));
+
+        let mut header = BlockHeader {
+            hash: String::new(),
+            version: 0x2000_0000,
+            prev_hash,
+            merkle_root: merkle_root(&txids),
+            timestamp: GENESIS_TIMESTAMP + height * 600,
+            bits: REGTEST_BITS,
+            nonce: 0,
+        };
+        header.hash = block_hash(&header);
+
+        for txid in &txids {
+            if let Some(simulated) = state.transactions.get_mut(txid) {
+                simulated.block_height = Some(height);
+            }
+        }
+
+        let hash = header.hash.clone();
+        state.blocks.push(SimulatedBlock { header, txids });
+        hash
+    }
+
+    /// Add a transaction's outputs to the UTXO set
+    fn add_outputs(state: &mut ChainState, tx: &BitcoinTransaction) {
+        for (vout, output) in tx.outputs.iter().enumerate() {
+            state.utxos.insert((tx.txid.clone(), vout as u32), output.clone());
+        }
+    }
+
+    /// Check a transaction against the current UTXO set and chain height
+    ///
+    /// Returns the fee paid by the transaction.
+    fn validate_transaction(state: &ChainState, tx: &BitcoinTransaction) -> BitcoinResult<u64> {
+        if tx.inputs.is_empty() || tx.outputs.is_empty() {
+            return Err(BitcoinError::TransactionError(
+                "Transaction must have inputs and outputs".to_string()
+            ));
+        }
+
+        let height = Self::tip_height(state);
+        let locktime_enabled = tx.inputs.iter().any(|input| input.sequence != SEQUENCE_FINAL);
+        if locktime_enabled && tx.locktime != 0 {
+            if tx.locktime >= LOCKTIME_THRESHOLD {
+                return Err(BitcoinError::TransactionError(
+                    "Timestamp locktimes are not supported by the simulated chain".to_string()
+                ));
+            }
+
+            if tx.locktime > height {
+                return Err(BitcoinError::TransactionError(format!(
+                    "Transaction is non-final: locktime {} is above chain height {}", tx.locktime, height
+                )));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut input_value = 0u64;
+        for input in &tx.inputs {
+            let outpoint = (input.txid.clone(), input.vout);
+            if !seen.insert(outpoint.clone()) {
+                return Err(BitcoinError::TransactionError(
+                    format!("Duplicate input {}:{}", input.txid, input.vout)
+                ));
+            }
+
+            let output = match state.utxos.get(&outpoint) {
+                Some(output) => output,
+                None if state.spends.contains_key(&outpoint) => return Err(BitcoinError::TransactionError(
+                    format!("Input {}:{} is already spent", input.txid, input.vout)
+                )),
+                None => return Err(BitcoinError::TransactionError(
+                    format!("Input {}:{} does not exist", input.txid, input.vout)
+                )),
+            };
+
+            if script_pubkey_type(&output.script_pubkey) == Some(AddressType::P2WSH) {
+                let witness_script = input.witness.as_ref()
+                    .and_then(|witness| witness.last())
+                    .ok_or_else(|| BitcoinError::TransactionError(
+                        format!("Missing witness script for P2WSH input {}:{}", input.txid, input.vout)
+                    ))?;
+
+                if encoding::p2wsh_script_pubkey(witness_script) != output.script_pubkey {
+                    return Err(BitcoinError::TransactionError(
+                        format!("Witness script does not match P2WSH output {}:{}", input.txid, input.vout)
+                    ));
+                }
+            }
+
+            input_value += output.value;
+        }
+
+        let output_value: u64 = tx.outputs.iter().map(|output| output.value).sum();
+        if output_value > input_value {
+            return Err(BitcoinError::TransactionError(format!(
+                "Outputs ({} sat) exceed inputs ({} sat)", output_value, input_value
+            )));
+        }
+
+        Ok(input_value - output_value)
+    }
+
+    /// Create a new wallet address of the given type
+    ///
+    /// `change` picks the internal (change) chain of a seeded wallet.
+    fn new_wallet_script(&self, address_type: AddressType, change: bool) -> BitcoinResult<Vec<u8>> {
+        let script_pubkey = match (address_type, &self.seed) {
+            (AddressType::P2WPKH, Some(seed)) => {
+                let key = self.next_wallet_key(seed, 84, change)?;
+                p2wpkh_script_pubkey(&key)
+            }
+            (AddressType::P2TR, Some(seed)) => {
+                let (internal_key, _) = self.next_wallet_key(seed, 86, change)?.x_only_public_key();
+                p2tr_script_pubkey(&internal_key)?
+            }
+            (AddressType::P2WPKH, None) => witness_script_pubkey(0, &random_bytes(20)),
+            (AddressType::P2WSH, _) => witness_script_pubkey(0, &random_bytes(32)),
+            (AddressType::P2TR, None) => witness_script_pubkey(1, &random_bytes(32)),
+            (other, _) => return Err(BitcoinError::WalletError(
+                format!("Address type {:?} is not supported by the simulated wallet", other)
+            )),
+        };
+
+        self.state.lock().unwrap().wallet_scripts.insert(script_pubkey.clone());
+        Ok(script_pubkey)
+    }
+
+    /// Public key at the next unused index of m/purpose'/coin'/0'/chain
+    fn next_wallet_key(&self, seed: &[u8], purpose: u32, change: bool) -> BitcoinResult<PublicKey> {
+        let chain = change as u32;
+        let index = {
+            let mut state = self.state.lock().unwrap();
+            let next = state.next_index.entry((purpose, chain)).or_insert(0);
+            *next += 1;
+            *next - 1
+        };
+
+        let path = [purpose | HARDENED, self.coin_type | HARDENED, HARDENED, chain, index];
+        let key = derive_private_key(seed, &path)?;
+        Ok(PublicKey::from_secret_key(&Secp256k1::signing_only(), &key))
+    }
+
+    /// Spendable wallet outputs, largest first, skipping the given outpoints
+    fn wallet_candidates(&self, exclude: &[(String, u32)]) -> Vec<((String, u32), u64)> {
+        let state = self.state.lock().unwrap();
+        let mut candidates: Vec<((String, u32), u64)> = state.utxos.iter()
+            .filter(|(outpoint, output)| {
+                state.wallet_scripts.contains(&output.script_pubkey) && !exclude.contains(outpoint)
+            })
+            .map(|(outpoint, output)| (outpoint.clone(), output.value))
+            .collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        candidates
+    }
+
+    /// A change output paying to a fresh wallet address
+    fn change_output(&self, value: u64) -> BitcoinResult<TransactionOutput> {
+        let change_script = self.new_wallet_script(AddressType::P2WPKH, true)?;
+        Ok(TransactionOutput {
+            value,
+            address: script_pubkey_to_address(&change_script, self.hrp),
+            script_pubkey: change_script,
+        })
+    }
+}
+
+/// Spend a wallet output
+///
+/// Wallet inputs are "signed" with a placeholder signature and key.
+fn wallet_input((txid, vout): (String, u32)) -> TransactionInput {
+    TransactionInput {
+        txid,
+        vout,
+        script_sig: Vec::new(),
+        sequence: 0xffff_fffd,
+        witness: Some(vec![vec![0u8; 71], vec![0u8; 33]]),
+    }
+}
+
+impl BitcoinInterface for SimulatedBitcoinImplementation {
+    fn get_transaction(&self, txid: &str) -> BitcoinResult<BitcoinTransaction> {
+        let state = self.state.lock().unwrap();
+        state.transactions.get(txid)
+            .map(|simulated| simulated.transaction.clone())
+            .ok_or_else(|| BitcoinError::TransactionError(format!("Transaction not found: {}", txid)))
+    }
+
+    fn get_block(&self, hash: &str) -> BitcoinResult<Vec<BitcoinTransaction>> {
+        let state = self.state.lock().unwrap();
+        let block = state.blocks.iter()
+            .find(|block| block.header.hash == hash)
+            .ok_or_else(|| BitcoinError::BlockError(format!("Block not found: {}", hash)))?;
+
+        Ok(block.txids.iter()
+            .filter_map(|txid| state.transactions.get(txid))
+            .map(|simulated| simulated.transaction.clone())
+            .collect())
+    }
+
+    fn get_block_height(&self) -> BitcoinResult<u32> {
+        let state = self.state.lock().unwrap();
+        Ok(Self::tip_height(&state))
+    }
+
+    fn get_block_hash(&self, height: u32) -> BitcoinResult<String> {
+        let state = self.state.lock().unwrap();
+        state.blocks.get(height as usize)
+            .map(|block| block.header.hash.clone())
+            .ok_or_else(|| BitcoinError::BlockError(format!("No block at height {}", height)))
+    }
+
+    fn get_confirmations(&self, txid: &str) -> BitcoinResult<u32> {
+        SimulatedBitcoinImplementation::get_confirmations(self, txid)
+    }
+
+    fn get_block_header(&self, hash: &str) -> BitcoinResult<BlockHeader> {
+        let state = self.state.lock().unwrap();
+        state.blocks.iter()
+            .find(|block| block.header.hash == hash)
+            .map(|block| block.header.clone())
+            .ok_or_else(|| BitcoinError::BlockError(format!("Block not found: {}", hash)))
+    }
+
+    fn generate_address(&self, address_type: AddressType) -> BitcoinResult<BitcoinAddress> {
+        let script_pubkey = self.new_wallet_script(address_type, false)?;
+        let address = script_pubkey_to_address(&script_pubkey, self.hrp).ok_or_else(|| {
+            BitcoinError::WalletError("Failed to encode wallet address".to_string())
+        })?;
+
+        Ok(BitcoinAddress { address, address_type })
+    }
+
+    fn create_transaction(
+        &self,
+        outputs: Vec<(String, u64)>,
+        fee_rate: u64,
+    ) -> BitcoinResult<BitcoinTransaction> {
+        let mut tx_outputs = Vec::new();
+        for (address, value) in outputs {
+            tx_outputs.push(TransactionOutput {
+                value,
+                script_pubkey: address_to_script_pubkey(&address)?,
+                address: Some(address),
+            });
+        }
+
+        let target: u64 = tx_outputs.iter().map(|output| output.value).sum();
+
+        // Largest-first coin selection over confirmed and unconfirmed wallet outputs
+        let mut selected = Vec::new();
+        let mut selected_value = 0u64;
+        let mut fee = 0u64;
+        for (outpoint, value) in self.wallet_candidates(&[]) {
+            selected.push(outpoint);
+            selected_value += value;
+
+            let vsize = TX_OVERHEAD_VSIZE
+                + WALLET_INPUT_VSIZE * selected.len() as u64
+                + OUTPUT_VSIZE * (tx_outputs.len() as u64 + 1);
+            fee = vsize * fee_rate;
+
+            if selected_value >= target + fee {
+                break;
+            }
+        }
+
+        if selected_value < target + fee {
+            return Err(BitcoinError::WalletError(format!(
+                "Insufficient funds: need {} sat, wallet has {} sat", target + fee, selected_value
+            )));
+        }
+
+        let change = selected_value - target - fee;
+        if change >= DUST_LIMIT_SAT {
+            tx_outputs.push(self.change_output(change)?);
+        } else {
+            fee += change;
+        }
+
+        let mut tx = BitcoinTransaction {
+            txid: String::new(),
+            version: 2,
+            inputs: selected.into_iter().map(wallet_input).collect(),
+            outputs: tx_outputs,
+            locktime: 0,
+            size: 0,
+            weight: 0,
+            fee: Some(fee),
+        };
+        finalize_transaction(&mut tx)?;
+
+        Ok(tx)
+    }
+
+    fn fund_transaction(
+        &self,
+        transaction: &BitcoinTransaction,
+        input_value: u64,
+        fee_sat: u64,
+    ) -> BitcoinResult<BitcoinTransaction> {
+        let mut tx = transaction.clone();
+        let output_value: u64 = tx.outputs.iter().map(|output| output.value).sum();
+
+        // A transaction without outputs needs enough left over for a change output
+        let change_floor = if tx.outputs.is_empty() { DUST_LIMIT_SAT } else { 0 };
+        let target = (output_value + fee_sat + change_floor).saturating_sub(input_value);
+
+        let existing: Vec<(String, u32)> = tx.inputs.iter()
+            .map(|input| (input.txid.clone(), input.vout))
+            .collect();
+        let mut selected_value = 0u64;
+        for (outpoint, value) in self.wallet_candidates(&existing) {
+            if selected_value >= target {
+                break;
+            }
+            tx.inputs.push(wallet_input(outpoint));
+            selected_value += value;
+        }
+
+        if selected_value < target {
+            return Err(BitcoinError::WalletError(format!(
+                "Insufficient funds: need {} sat, wallet has {} sat", target, selected_value
+            )));
+        }
+
+        let excess = input_value + selected_value - output_value - fee_sat;
+        let change = if excess >= DUST_LIMIT_SAT {
+            tx.outputs.push(self.change_output(excess)?);
+            excess
+        } else {
+            0
+        };
+
+        tx.fee = Some(input_value + selected_value - output_value - change);
+        finalize_transaction(&mut tx)?;
+
+        Ok(tx)
+    }
+
+    fn broadcast_transaction(&self, transaction: &BitcoinTransaction) -> BitcoinResult<String> {
+        let mut tx = transaction.clone();
+        tx.txid = compute_txid(&tx)?;
+
+        let mut state = self.state.lock().unwrap();
+        if state.transactions.contains_key(&tx.txid) {
+            return Ok(tx.txid);
+        }
+
+        tx.fee = Some(Self::validate_transaction(&state, &tx)?);
+        finalize_transaction(&mut tx)?;
+
+        for input in &tx.inputs {
+            let outpoint = (input.txid.clone(), input.vout);
+            state.utxos.remove(&outpoint);
+            state.spends.insert(outpoint, tx.txid.clone());
+        }
+        Self::add_outputs(&mut state, &tx);
+
+        let txid = tx.txid.clone();
+        state.transactions.insert(txid.clone(), SimulatedTransaction {
+            transaction: tx,
+            block_height: None,
+        });
+        state.mempool.push(txid.clone());
+
+        Ok(txid)
+    }
+
+    fn list_transactions(&self) -> BitcoinResult<Vec<BitcoinTransaction>> {
+        let state = self.state.lock().unwrap();
+        let is_wallet_output = |txid: &str, vout: u32| {
+            state.transactions.get(txid)
+                .and_then(|simulated| simulated.transaction.outputs.get(vout as usize))
+                .map(|output| state.wallet_scripts.contains(&output.script_pubkey))
+                .unwrap_or(false)
+        };
+
+        Ok(state.blocks.iter()
+            .flat_map(|block| block.txids.iter())
+            .chain(state.mempool.iter())
+            .filter_map(|txid| state.transactions.get(txid))
+            .map(|simulated| &simulated.transaction)
+            .filter(|tx| {
+                tx.outputs.iter().any(|output| state.wallet_scripts.contains(&output.script_pubkey))
+                    || tx.inputs.iter().any(|input| is_wallet_output(&input.txid, input.vout))
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn get_balance(&self) -> BitcoinResult<u64> {
+        let state = self.state.lock().unwrap();
+        Ok(state.utxos.values()
+            .filter(|output| state.wallet_scripts.contains(&output.script_pubkey))
+            .map(|output| output.value)
+            .sum())
+    }
+
+    fn estimate_fee(&self, _target_blocks: u8) -> BitcoinResult<u64> {
+        Ok(self.fee_rate)
+    }
+
+    fn implementation_type(&self) -> BitcoinImplementationType {
+        BitcoinImplementationType::Simulated
+    }
+}
+
+/// Merkle root (display hex) of a list of txids
+fn merkle_root(txids: &[String]) -> String {
+    let mut level: Vec<[u8; 32]> = txids.iter()
+        .filter_map(|txid| encoding::txid_to_bytes(txid).ok())
+        .collect();
+
+    if level.is_empty() {
+        return "0".repeat(64);
+    }
+
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| {
+                let mut concatenated = pair[0].to_vec();
+                concatenated.extend_from_slice(pair.get(1).unwrap_or(&pair[0]));
+                encoding::sha256d(&concatenated)
+            })
+            .collect();
+    }
+
+    let mut root = level[0];
+    root.reverse();
+    encoding::to_hex(&root)
+}
+
+/// Hash (display hex) of a block header
+fn block_hash(header: &BlockHeader) -> String {
+    let mut hash = encoding::sha256d(&encoding::serialize_block_header(header));
+    hash.reverse();
+    encoding::to_hex(&hash)
+}
+
+/// Generate random bytes
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut rng = thread_rng();
+    (0..len).map(|_| rng.gen::<u8>()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regtest_chain() -> SimulatedBitcoinImplementation {
+        let mut config = crate::config::Config::default();
+        config.bitcoin_network = Some("regtest".to_string());
+        SimulatedBitcoinImplementation::new(&config)
+    }
+
+    #[test]
+    fn test_fund_spend_and_mine() {
+        let chain = regtest_chain();
+        let address = chain.generate_address(AddressType::P2WPKH).unwrap();
+        assert!(address.address.starts_with("bcrt1q"));
+
+        chain.fund_address(&address.address, 100_000).unwrap();
+        assert_eq!(chain.get_balance().unwrap(), 100_000);
+        assert_eq!(chain.get_block_height().unwrap(), 1);
+
+        let destination = chain.generate_address(AddressType::P2WSH).unwrap();
+        let tx = chain.create_transaction(vec![(destination.address, 40_000)], 2).unwrap();
+        let txid = chain.broadcast_transaction(&tx).unwrap();
+        assert_eq!(txid, tx.txid);
+        assert_eq!(chain.get_confirmations(&txid).unwrap(), 0);
+
+        // Spending the same input twice is rejected
+        assert!(chain.broadcast_transaction(&BitcoinTransaction {
+            locktime: 1,
+            ..tx.clone()
+        }).is_err());
+
+        let hashes = chain.mine_blocks(2);
+        assert_eq!(chain.get_confirmations(&txid).unwrap(), 2);
+        assert_eq!(chain.get_block(&hashes[0]).unwrap()[0].txid, txid);
+
+        // Headers link back to their parent
+        let tip = chain.get_block_header(&hashes[1]).unwrap();
+        assert_eq!(tip.prev_hash, hashes[0]);
+        assert_eq!(chain.get_block_hash(chain.get_block_height().unwrap()).unwrap(), hashes[1]);
+        assert_eq!(chain.get_balance().unwrap(), 100_000 - tx.fee.unwrap());
+        let history: Vec<String> = chain.list_transactions().unwrap().into_iter().map(|tx| tx.txid).collect();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1], txid);
+        assert!(chain.find_spending_transaction(&tx.inputs[0].txid, tx.inputs[0].vout).is_some());
+    }
+
+    #[test]
+    fn test_seeded_wallet_addresses() {
+        let config = crate::config::Config {
+            bitcoin_network: Some("mainnet".to_string()),
+            ..Default::default()
+        };
+        let seed = encoding::from_hex(
+            "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc1\
+             9a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+        ).unwrap();
+        let chain = SimulatedBitcoinImplementation::with_seed(&config, &seed);
+
+        // BIP84 and BIP86 test vectors: first and second receive addresses
+        assert_eq!(chain.generate_address(AddressType::P2WPKH).unwrap().address, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(chain.generate_address(AddressType::P2WPKH).unwrap().address, "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g");
+        assert_eq!(chain.generate_address(AddressType::P2TR).unwrap().address, "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+
+        // Change comes from the internal chain
+        let change = chain.change_output(1_000).unwrap();
+        assert_eq!(change.address.unwrap(), "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+    }
+
+    #[test]
+    fn test_locktime_must_be_final() {
+        let chain = regtest_chain();
+        let address = chain.generate_address(AddressType::P2WPKH).unwrap();
+        chain.fund_address(&address.address, 50_000).unwrap();
+
+        let destination = chain.generate_address(AddressType::P2WPKH).unwrap();
+        let mut tx = chain.create_transaction(vec![(destination.address, 10_000)], 1).unwrap();
+        tx.locktime = chain.get_block_height().unwrap() + 5;
+        assert!(chain.broadcast_transaction(&tx).is_err());
+
+        chain.mine_blocks(5);
+        assert!(chain.broadcast_transaction(&tx).is_ok());
+    }
+}
@@ -1,165 +1,129 @@
 // Bitcoin implementation tests
-// Tests both Python and Rust implementations to verify they behave identically
+// Runs the parity fixtures against every available implementation to verify
+// they behave identically
+//
+// Every implementation restores its wallet from the fixture seed and syncs
+// against the fixture's chain served locally, so the suite needs no network.
+// An implementation that cannot be attached fails the suite instead of being
+// reported against results it never produced.
+
+use std::sync::Arc;
 
 use crate::bitcoin::{
-    interface::{
-        BitcoinInterface, BitcoinImplementationType, AddressType,
-        create_bitcoin_interface
-    },
+    electrum::ElectrumServer,
+    interface::BitcoinInterface,
+    parity::{run_parity, ParityFixture, ParityReport},
     BitcoinResult
 };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::Config;
-    
-    // Helper function to run the same test on both implementations
-    fn test_both_implementations<F>(test_fn: F)
-    where
-        F: Fn(&dyn BitcoinInterface) -> BitcoinResult<()>
-    {
-        let config = Config::default();
-        
-        // Test Python implementation if available
-        #[cfg(feature = "python-bitcoin")]
-        {
-            let python_impl = create_bitcoin_interface(BitcoinImplementationType::Python, &config);
-            if let Err(e) = test_fn(python_impl.as_ref()) {
-                panic!("Python implementation test failed: {:?}", e);
-            }
-        }
-        
-        // Test Rust implementation if available
-        #[cfg(feature = "rust-bitcoin")]
-        {
-            let rust_impl = create_bitcoin_interface(BitcoinImplementationType::Rust, &config);
-            if let Err(e) = test_fn(rust_impl.as_ref()) {
-                panic!("Rust implementation test failed: {:?}", e);
-            }
-        }
-    }
-    
-    #[test]
-    fn test_get_transaction() {
-        test_both_implementations(|bitcoin_impl| {
-            let txid = "0".repeat(64);
-            let tx = bitcoin_impl.get_transaction(&txid)?;
-            
-            // Verify the transaction has the correct txid
-            assert_eq!(tx.txid, txid);
-            assert_eq!(tx.version, 2);
-            assert!(!tx.inputs.is_empty());
-            assert!(!tx.outputs.is_empty());
-            
-            Ok(())
-        });
-    }
-    
-    #[test]
-    fn test_generate_address() {
-        test_both_implementations(|bitcoin_impl| {
-            let address = bitcoin_impl.generate_address(AddressType::P2WPKH)?;
-            
-            // Verify the address has a value
-            assert!(!address.address.is_empty());
-            assert_eq!(address.address_type, AddressType::P2WPKH);
-            
-            Ok(())
-        });
-    }
-    
-    #[test]
-    fn test_create_transaction() {
-        test_both_implementations(|bitcoin_impl| {
-            // Create a simple transaction with one output
-            let recipient = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string();
-            let amount = 50000; // 0.0005 BTC
-            let fee_rate = 5; // 5 sat/vB
-            
-            let tx = bitcoin_impl.create_transaction(vec![(recipient.clone(), amount)], fee_rate)?;
-            
-            // Verify transaction details
-            assert!(!tx.txid.is_empty());
-            assert_eq!(tx.version, 2);
-            assert!(!tx.inputs.is_empty());
-            assert!(!tx.outputs.is_empty());
-            
-            // Verify that the output contains our payment
-            let payment_output = tx.outputs.iter().find(|output| 
-                output.address.as_ref().map_or(false, |addr| addr == &recipient) &&
-                output.value == amount
-            );
-            assert!(payment_output.is_some(), "Couldn't find expected output in transaction");
-            
-            Ok(())
-        });
-    }
-    
-    #[test]
-    fn test_get_balance() {
-        test_both_implementations(|bitcoin_impl| {
-            let balance = bitcoin_impl.get_balance()?;
-            
-            // Verify that the balance is non-negative
-            assert!(balance >= 0);
-            
-            Ok(())
-        });
-    }
+/// Divergences the migration has yet to close, as (implementation, check)
+pub const KNOWN_DIVERGENCES: &[(&str, &str)] = &[
+    // The BDK wallet has no taproot descriptor yet
+    ("rust", "generate_address(p2tr)"),
+];
+
+/// Implementations to compare for a fixture, labelled for reports
+///
+/// The simulated backend replaying the fixture is always included as the
+/// reference; the Python and Rust implementations are included when built and
+/// attached to `electrum`, which must serve the fixture's chain.
+pub fn implementations(
+    fixture: &ParityFixture,
+    #[allow(unused_variables)] electrum: &ElectrumServer,
+) -> BitcoinResult<Vec<(&'static str, Arc<dyn BitcoinInterface>)>> {
+    #[allow(unused_mut)]
+    let mut implementations: Vec<(&'static str, Arc<dyn BitcoinInterface>)> = vec![
+        ("simulated", Arc::new(fixture.backend()?)),
+    ];
+
+    #[cfg(feature = "python-bitcoin")]
+    implementations.push(("python", attach_python(fixture, electrum)?));
+
+    #[cfg(feature = "rust-bitcoin")]
+    implementations.push(("rust", Arc::new(crate::bitcoin::rust::RustBitcoinImplementation::with_backend(
+        &fixture.config(),
+        &fixture.seed_bytes()?,
+        &electrum.url(),
+    )?)));
+
+    Ok(implementations)
+}
+
+/// Attach the Python implementation to a fixture backend
+///
+/// The Python wallet cannot yet be restored from a seed or pointed at a
+/// backend, so running the suite with it built is an error.
+#[cfg(feature = "python-bitcoin")]
+fn attach_python(_fixture: &ParityFixture, _electrum: &ElectrumServer) -> BitcoinResult<Arc<dyn BitcoinInterface>> {
+    Err(crate::bitcoin::BitcoinError::ImplementationError(
+        "The Python implementation cannot be attached to a fixture backend".to_string()
+    ))
+}
+
+/// Run every built-in fixture against every available implementation
+pub fn parity_reports() -> BitcoinResult<Vec<ParityReport>> {
+    ParityFixture::builtin().iter()
+        .map(|fixture| {
+            let electrum = ElectrumServer::start(Arc::new(fixture.backend()?))?;
+            let implementations = implementations(fixture, &electrum)?;
+            let labelled: Vec<(&str, &dyn BitcoinInterface)> = implementations.iter()
+                .map(|(label, bitcoin)| (*label, bitcoin.as_ref()))
+                .collect();
+            Ok(run_parity(fixture, &labelled))
+        })
+        .collect()
+}
+
+/// Divergences in a report not listed in `KNOWN_DIVERGENCES`, as (implementation, check)
+pub fn unexpected_divergences(report: &ParityReport) -> Vec<(String, String)> {
+    report.implementations.iter()
+        .flat_map(|implementation| implementation.divergences.iter()
+            .map(move |divergence| (implementation.implementation.clone(), divergence.check.clone())))
+        .filter(|(implementation, check)| !KNOWN_DIVERGENCES.contains(&(implementation.as_str(), check.as_str())))
+        .collect()
 }
 
 // Test running functionality
 pub fn run_tests() -> Result<(), String> {
-    let config = crate::config::Config::default();
-    
-    println!("Running Bitcoin implementation tests...");
-    
-    // Test both implementations if available
-    #[cfg(feature = "python-bitcoin")]
-    {
-        println!("\nTesting Python implementation:");
-        let python_impl = create_bitcoin_interface(BitcoinImplementationType::Python, &config);
-        test_implementation(python_impl.as_ref()).map_err(|e| format!("Python test error: {}", e))?;
+    println!("Running Bitcoin implementation parity tests...");
+
+    let reports = parity_reports().map_err(|e| format!("Failed to attach implementations to the parity fixtures: {}", e))?;
+    for report in &reports {
+        println!("\n{}", report.summary());
     }
-    
-    #[cfg(feature = "rust-bitcoin")]
-    {
-        println!("\nTesting Rust implementation:");
-        let rust_impl = create_bitcoin_interface(BitcoinImplementationType::Rust, &config);
-        test_implementation(rust_impl.as_ref()).map_err(|e| format!("Rust test error: {}", e))?;
+
+    let divergent: Vec<&str> = reports.iter()
+        .filter(|report| !unexpected_divergences(report).is_empty())
+        .map(|report| report.fixture.as_str())
+        .collect();
+    if !divergent.is_empty() {
+        return Err(format!("Implementations diverge on: {}", divergent.join(", ")));
     }
-    
+
     println!("\nAll tests passed!");
-    
+
     Ok(())
 }
 
-fn test_implementation(bitcoin_impl: &dyn BitcoinInterface) -> BitcoinResult<()> {
-    // Test transaction handling
-    let txid = "0".repeat(64);
-    let tx = bitcoin_impl.get_transaction(&txid)?;
-    println!("- Get transaction: Success (txid: {})", tx.txid);
-    
-    // Test address generation
-    let address = bitcoin_impl.generate_address(AddressType::P2WPKH)?;
-    println!("- Generate address: Success ({})", address.address);
-    
-    // Test transaction creation
-    let recipient = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string();
-    let amount = 50000; // 0.0005 BTC
-    let fee_rate = 5; // 5 sat/vB
-    
-    let tx = bitcoin_impl.create_transaction(vec![(recipient.clone(), amount)], fee_rate)?;
-    println!("- Create transaction: Success (txid: {})", tx.txid);
-    
-    // Test fee estimation
-    let fee = bitcoin_impl.estimate_fee(6)?;
-    println!("- Fee estimation: {} sat/vB for 6 blocks", fee);
-    
-    // Test balance
-    let balance = bitcoin_impl.get_balance()?;
-    println!("- Current balance: {} satoshis", balance);
-    
-    Ok(())
-} 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_available_implementations_agree() {
+        #[allow(unused_mut)]
+        let mut expected = vec!["simulated"];
+        #[cfg(feature = "rust-bitcoin")]
+        expected.push("rust");
+
+        for report in parity_reports().unwrap() {
+            // Every built implementation ran every check of the fixture
+            let labels: Vec<&str> = report.implementations.iter()
+                .map(|implementation| implementation.implementation.as_str())
+                .collect();
+            assert_eq!(labels, expected);
+
+            assert_eq!(unexpected_divergences(&report), Vec::<(String, String)>::new(), "{}", report.summary());
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// Command line argument parsing
+// Splits arguments into positional words and `--name value` options
+
+use std::collections::HashMap;
+
+use crate::cli::{CliError, CliResult};
+
+/// Options that take no value
+const SWITCHES: &[&str] = &["json", "help", "force", "private", "anchors", "zero-conf"];
+
+/// Parsed command line
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    /// Positional words (command, subcommand, operands)
+    pub positional: Vec<String>,
+
+    /// Options by name, without the leading dashes; switches map to "true"
+    pub options: HashMap<String, String>,
+}
+
+impl Args {
+    /// Parse arguments (without the program name)
+    ///
+    /// Options may appear anywhere, as `--name value` or `--name=value`.
+    /// Everything after `--` is positional.
+    pub fn parse<I, S>(args: I) -> CliResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.positional.extend(args.by_ref());
+                break;
+            }
+
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => {
+                    parsed.positional.push(arg);
+                    continue;
+                }
+            };
+
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None if SWITCHES.contains(&name) => (name.to_string(), "true".to_string()),
+                None => {
+                    let value = args.next()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?;
+                    (name.to_string(), value)
+                }
+            };
+            parsed.options.insert(name, value);
+        }
+
+        Ok(parsed)
+    }
+
+    /// Positional word at an index
+    pub fn word(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    /// Value of an option
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    /// Value of a required option
+    pub fn required(&self, name: &str) -> CliResult<&str> {
+        self.value(name).ok_or_else(|| CliError::Usage(format!("--{} is required", name)))
+    }
+
+    /// Whether a switch was given
+    pub fn flag(&self, name: &str) -> bool {
+        self.value(name).map(|value| value != "false").unwrap_or(false)
+    }
+
+    /// Numeric option, if given
+    pub fn number(&self, name: &str) -> CliResult<Option<u64>> {
+        self.value(name)
+            .map(|value| value.parse::<u64>()
+                .map_err(|_| CliError::Usage(format!("--{} must be a number, got {}", name, value))))
+            .transpose()
+    }
+
+    /// Required numeric option
+    pub fn required_number(&self, name: &str) -> CliResult<u64> {
+        self.number(name)?.ok_or_else(|| CliError::Usage(format!("--{} is required", name)))
+    }
+
+    /// Operand at an index, falling back to an option of the same meaning
+    pub fn operand(&self, index: usize, option: &str) -> CliResult<&str> {
+        self.word(index)
+            .or_else(|| self.value(option))
+            .ok_or_else(|| CliError::Usage(format!("Missing {}", option)))
+    }
+}
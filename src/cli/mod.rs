@@ -0,0 +1,599 @@
+// OPSource command line interface
+// Wallet, Lightning, chain and configuration commands for the node
+//
+// Commands are carried out either against an in-process node built from the
+// configuration or, with `--api host:port`, against a running node's admin
+// API. Both paths go through the API's request handling, so a command prints
+// the same result either way: text by default, or JSON with `--json`.
+
+pub mod args;
+pub mod output;
+
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use crate::api::{self, ApiClient, ApiConfig, ApiError, ApiServer, Scope};
+use crate::bitcoin::{get_current_bitcoin_interface, BitcoinInterface};
+use crate::config::Config;
+use crate::lightning::interface::{LightningError, LightningInterface};
+use crate::lightning::key_manager::KeyManagerWrapper;
+use crate::lightning::lnurl::lnurl_target_url;
+
+pub use args::Args;
+
+/// Usage text printed by `help`
+pub const USAGE: &str = "\
+Usage: opsource [options] <command> [arguments]
+
+Node seed commands (Lightning keys only; the on-chain wallet is backed up separately):
+  seed new                                   create and encrypt a new node seed
+  seed restore                               restore a node seed from its hex backup
+
+  The seed and passphrase are read from stdin, never from the command line.
+  The configured seed passphrase is used when there is one.
+
+Wallet commands:
+  wallet balance                             on-chain balance
+  wallet address [--type p2wpkh|p2tr|...]    new receive address
+  wallet send <address> <amount_sat> [--fee-rate R]
+  wallet history                             wallet transactions
+
+Lightning commands:
+  ln info                                    node information
+  ln connect <pubkey@host:port>              connect to a peer
+  ln peers                                   connected peers
+  ln open <pubkey> <amount_sat> [--push MSAT] [--private] [--anchors] [--zero-conf]
+  ln close <channel_id> [--force]
+  ln channels                                list channels
+  ln invoice [amount_msat] [--description D] [--expiry SECS]
+  ln decode <bolt11>
+  ln pay <bolt11|lnurl|user@domain> [--amount MSAT] [--comment C]
+  ln payments                                sent payments
+
+Chain commands:
+  chain height
+  chain tx <txid>
+  chain block <hash|height>
+  chain fee [--target BLOCKS]
+
+Other commands:
+  config show                                effective configuration (secrets hidden)
+  config validate                            check the configuration
+  serve [--listen host:port]                 run the node and its admin API
+  help                                       this text
+
+Options:
+  --json                 print results as JSON
+  --config <file>        read settings from a .toml or .yaml file (or NAME=value lines)
+  --profile <name>       start from the mainnet, testnet or regtest profile
+  --network <name>       override the Bitcoin network
+  --data-dir <dir>       override the Lightning data directory
+  --api <host:port>      use a running node's admin API instead of an in-process node
+  --token <token>        API token (default: admin.token in the data directory)
+";
+
+/// Error type for CLI commands
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error("{0}")]
+    Usage(String),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error(transparent)]
+    Api(#[from] ApiError),
+
+    #[error(transparent)]
+    Lightning(#[from] LightningError),
+}
+
+/// Result type for CLI commands
+pub type CliResult<T> = Result<T, CliError>;
+
+/// Where commands are carried out
+pub enum Backend {
+    /// In-process node
+    Local {
+        /// On-chain wallet and chain access
+        bitcoin: Arc<dyn BitcoinInterface>,
+        /// Lightning node
+        lightning: Arc<dyn LightningInterface>,
+    },
+    /// Running node reached through its admin API
+    Remote(ApiClient),
+}
+
+impl Backend {
+    /// Send an API request to the node
+    pub fn request(&self, method: &str, target: &str, body: Option<&Value>) -> CliResult<Value> {
+        match self {
+            Backend::Local { bitcoin, lightning } => {
+                Ok(api::dispatch(bitcoin.as_ref(), lightning.as_ref(), method, target, body)?)
+            }
+            Backend::Remote(client) => Ok(client.request(method, target, body)?),
+        }
+    }
+}
+
+/// Command runner holding the configuration and, once needed, the backend
+pub struct Cli {
+    /// Effective configuration
+    config: Config,
+
+    /// Backend, created on first use unless supplied
+    backend: Option<Backend>,
+
+    /// Where seeds and passphrases are read from
+    input: Box<dyn BufRead>,
+}
+
+impl Cli {
+    /// Runner that builds its backend from the configuration and options
+    pub fn new(config: Config) -> Self {
+        Cli { config, backend: None, input: Box::new(BufReader::new(std::io::stdin())) }
+    }
+
+    /// Runner using a given backend
+    pub fn with_backend(config: Config, backend: Backend) -> Self {
+        Cli { config, backend: Some(backend), input: Box::new(BufReader::new(std::io::stdin())) }
+    }
+
+    /// Read seeds and passphrases from `input` instead of stdin
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+
+    /// Run a parsed command, returning its result
+    pub fn execute(&mut self, args: &Args) -> CliResult<Value> {
+        let command = args.word(0).unwrap_or("help");
+        let subcommand = args.word(1).unwrap_or("");
+
+        match (command, subcommand) {
+            ("seed", "new") => self.seed_new(args),
+            ("seed", "restore") => self.seed_restore(args),
+            ("wallet", "balance") => self.request(args, "GET", "/v1/bitcoin/balance", None),
+            ("wallet", "address") => {
+                let body = json!({ "type": args.value("type") });
+                self.request(args, "POST", "/v1/bitcoin/addresses", Some(body))
+            }
+            ("wallet", "send") => {
+                let body = json!({
+                    "address": args.operand(2, "address")?,
+                    "amount_sat": parse_number(args.operand(3, "amount")?, "amount")?,
+                    "fee_rate": args.number("fee-rate")?,
+                });
+                self.request(args, "POST", "/v1/bitcoin/transactions", Some(body))
+            }
+            ("wallet", "history") => self.request(args, "GET", "/v1/bitcoin/transactions", None),
+
+            ("ln", "info") => self.request(args, "GET", "/v1/lightning/info", None),
+            ("ln", "connect") => {
+                let body = parse_peer_address(args.operand(2, "peer")?)?;
+                self.request(args, "POST", "/v1/lightning/peers", Some(body))
+            }
+            ("ln", "peers") => self.request(args, "GET", "/v1/lightning/peers", None),
+            ("ln", "open") => {
+                let body = json!({
+                    "pubkey": args.operand(2, "pubkey")?,
+                    "capacity_sat": parse_number(args.operand(3, "amount")?, "amount")?,
+                    "push_msat": args.number("push")?,
+                    "private": args.flag("private"),
+                    "anchors": args.flag("anchors"),
+                    "zero_conf": args.flag("zero-conf"),
+                });
+                self.request(args, "POST", "/v1/lightning/channels", Some(body))
+            }
+            ("ln", "close") => {
+                let target = format!(
+                    "/v1/lightning/channels/{}?force={}",
+                    args.operand(2, "channel")?, args.flag("force")
+                );
+                self.request(args, "DELETE", &target, None)
+            }
+            ("ln", "channels") => self.request(args, "GET", "/v1/lightning/channels", None),
+            ("ln", "invoice") => {
+                let amount_msat = match args.word(2) {
+                    Some(amount) => Some(parse_number(amount, "amount")?),
+                    None => args.number("amount")?,
+                };
+                let body = json!({
+                    "amount_msat": amount_msat,
+                    "description": args.value("description").unwrap_or(""),
+                    "expiry": args.number("expiry")?,
+                });
+                self.request(args, "POST", "/v1/lightning/invoices", Some(body))
+            }
+            ("ln", "decode") => {
+                let body = json!({ "bolt11": args.operand(2, "invoice")? });
+                self.request(args, "POST", "/v1/lightning/decode", Some(body))
+            }
+            ("ln", "pay") => {
+                let target = args.operand(2, "invoice")?;
+                let body = if lnurl_target_url(target).is_some() {
+                    json!({ "lnurl": target, "amount_msat": args.number("amount")?, "comment": args.value("comment") })
+                } else {
+                    json!({ "bolt11": target, "amount_msat": args.number("amount")? })
+                };
+                self.request(args, "POST", "/v1/lightning/payments", Some(body))
+            }
+            ("ln", "payments") => self.request(args, "GET", "/v1/lightning/payments", None),
+
+            ("chain", "height") => self.request(args, "GET", "/v1/bitcoin/height", None),
+            ("chain", "tx") => {
+                let target = format!("/v1/bitcoin/transactions/{}", args.operand(2, "txid")?);
+                self.request(args, "GET", &target, None)
+            }
+            ("chain", "block") => {
+                let target = format!("/v1/bitcoin/blocks/{}", args.operand(2, "block")?);
+                self.request(args, "GET", &target, None)
+            }
+            ("chain", "fee") => {
+                let target = format!("/v1/bitcoin/fee?target={}", args.number("target")?.unwrap_or(6));
+                self.request(args, "GET", &target, None)
+            }
+
+            ("config", "show") => Ok(config_json(&self.config)),
+            ("config", "validate") => {
+                self.config.validate().map_err(|e| CliError::Config(e.to_string()))?;
+                Ok(json!({ "valid": true }))
+            }
+
+            ("help", _) => Ok(Value::String(USAGE.to_string())),
+            _ => Err(CliError::Usage(format!("Unknown command: {} {}", command, subcommand).trim_end().to_string())),
+        }
+    }
+
+    /// Send a request through the backend, creating it if needed
+    fn request(&mut self, args: &Args, method: &str, target: &str, body: Option<Value>) -> CliResult<Value> {
+        if self.backend.is_none() {
+            self.backend = Some(connect(&self.config, args)?);
+        }
+        self.backend.as_ref()
+            .expect("backend was just created")
+            .request(method, target, body.as_ref())
+    }
+
+    /// Create the node seed in the local data directory
+    fn seed_new(&mut self, args: &Args) -> CliResult<Value> {
+        let keys = self.local_key_manager(args)?;
+        let passphrase = self.passphrase()?;
+        let seed = keys.create_seed(&passphrase)?;
+        Ok(json!({
+            "pubkey": keys.get_node_info()?.pubkey,
+            "seed": seed,
+            "warning": "Write the seed down; it is the only backup of the node's Lightning keys",
+        }))
+    }
+
+    /// Restore the node seed into the local data directory
+    fn seed_restore(&mut self, args: &Args) -> CliResult<Value> {
+        let keys = self.local_key_manager(args)?;
+        let seed = self.read_secret("Seed (hex)")?;
+        let passphrase = self.passphrase()?;
+        keys.restore_seed(&seed, &passphrase)?;
+        Ok(json!({ "pubkey": keys.get_node_info()?.pubkey }))
+    }
+
+    /// Key manager over the configured data directory; seeds never travel over the API
+    fn local_key_manager(&self, args: &Args) -> CliResult<KeyManagerWrapper> {
+        if args.value("api").is_some() {
+            return Err(CliError::Usage("Seeds can only be created on the node's own machine, without --api".to_string()));
+        }
+        if args.value("passphrase").is_some() || args.positional.len() > 2 {
+            return Err(CliError::Usage("Seeds and passphrases are read from stdin, not the command line".to_string()));
+        }
+        Ok(KeyManagerWrapper::new(&self.config))
+    }
+
+    /// Seed passphrase from the configuration, or read from the input
+    fn passphrase(&mut self) -> CliResult<String> {
+        let passphrase = match self.config.lightning_seed_passphrase.clone() {
+            Some(passphrase) => passphrase,
+            None => self.read_secret("Seed passphrase")?,
+        };
+        if passphrase.is_empty() && !self.config.lightning_allow_empty_seed_passphrase {
+            return Err(CliError::Config(
+                "An empty seed passphrase needs lightning.allow_empty_seed_passphrase".to_string()
+            ));
+        }
+        Ok(passphrase)
+    }
+
+    /// Read one line from the input, prompting on stderr
+    fn read_secret(&mut self, prompt: &str) -> CliResult<String> {
+        eprint!("{}: ", prompt);
+        let mut line = String::new();
+        self.input.read_line(&mut line)
+            .map_err(|e| CliError::Usage(format!("Failed to read {}: {}", prompt.to_lowercase(), e)))?;
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Create the backend the options ask for
+fn connect(config: &Config, args: &Args) -> CliResult<Backend> {
+    match args.value("api") {
+        Some(addr) => {
+            let client = match args.value("token") {
+                Some(token) => ApiClient::new(addr, token),
+                None => ApiClient::from_token_file(addr, &config.lightning_data_path(), Scope::Admin)?,
+            };
+            Ok(Backend::Remote(client))
+        }
+        None => {
+            // The in-process node gets the same checks as `serve`
+            config.validate().map_err(|e| CliError::Config(e.to_string()))?;
+            let bitcoin = get_current_bitcoin_interface(config);
+            let lightning = crate::lightning::create_lightning_interface(config, bitcoin.clone())?;
+            Ok(Backend::Local { bitcoin, lightning })
+        }
+    }
+}
+
+/// Configuration from the `--config` file, `--profile` and the environment with option overrides applied
+pub fn load_config(args: &Args) -> CliResult<Config> {
+    let mut config = Config::load(args.value("config").map(Path::new), args.value("profile"))
+        .map_err(|e| CliError::Config(e.to_string()))?;
+
+    if let Some(network) = args.value("network") {
+        config.bitcoin_network = Some(network.to_string());
+    }
+    if let Some(data_dir) = args.value("data-dir") {
+        config.lightning_data_dir = Some(data_dir.to_string());
+    }
+    if let Some(listen) = args.value("listen") {
+        config.api_listen_addr = Some(listen.to_string());
+    }
+
+    Ok(config)
+}
+
+/// Configuration as JSON, with secrets hidden
+pub fn config_json(config: &Config) -> Value {
+    let hidden = |secret: &Option<String>| secret.as_ref().map(|_| "********");
+    let features = &config.features;
+
+    json!({
+        "profile": config.profile.map(|profile| profile.name()),
+        "use_rust_bitcoin": config.use_rust_bitcoin,
+        "bitcoin_network": config.bitcoin_network,
+        "bitcoin_rpc_url": config.bitcoin_rpc_url,
+        "bitcoin_rpc_user": config.bitcoin_rpc_user,
+        "bitcoin_rpc_pass": hidden(&config.bitcoin_rpc_pass),
+        "bitcoin_data_dir": config.bitcoin_data_dir,
+        "wallet_path": config.wallet_path,
+        "lightning_implementation": config.lightning_implementation,
+        "lightning_node_pubkey": config.lightning_node_pubkey,
+        "lightning_listen_addr": config.lightning_listen_addr,
+        "lightning_data_dir": config.lightning_data_path().to_string_lossy(),
+        "lightning_trusted_peers": config.lightning_trusted_peers,
+        "lightning_seed_passphrase": hidden(&config.lightning_seed_passphrase),
+        "lightning_allow_empty_seed_passphrase": config.lightning_allow_empty_seed_passphrase,
+        "api_listen_addr": config.api_listen_addr,
+        "features": {
+            "use_electrum": features.use_electrum,
+            "verify_blocks": features.verify_blocks,
+            "lightning_enabled": features.lightning_enabled,
+            "accept_keysend": features.accept_keysend,
+            "accept_dual_funding": features.accept_dual_funding,
+        },
+    })
+}
+
+/// Parse a number operand
+fn parse_number(value: &str, name: &str) -> CliResult<u64> {
+    value.parse::<u64>().map_err(|_| CliError::Usage(format!("{} must be a number, got {}", name, value)))
+}
+
+/// Parse `pubkey@host:port` into a connect request body
+fn parse_peer_address(peer: &str) -> CliResult<Value> {
+    let invalid = || CliError::Usage(format!("Expected pubkey@host:port, got {}", peer));
+    let (pubkey, address) = peer.split_once('@').ok_or_else(invalid)?;
+    let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+    let port = port.parse::<u16>().map_err(|_| invalid())?;
+    Ok(json!({ "pubkey": pubkey, "host": host, "port": port }))
+}
+
+/// Run the node with its admin API until the process is stopped
+fn serve(config: &Config) -> CliResult<()> {
+    config.validate().map_err(|e| CliError::Config(e.to_string()))?;
+
+    let bitcoin = get_current_bitcoin_interface(config);
+    let lightning = crate::lightning::create_lightning_interface(config, bitcoin.clone())?;
+    let api_config = ApiConfig::from_config(config);
+    let token_dir = api_config.data_dir.clone();
+    let _server = ApiServer::start(api_config, bitcoin, lightning)?;
+
+    println!("Tokens are in {} ({}, {})", token_dir.display(), Scope::Admin.token_file(), Scope::ReadOnly.token_file());
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Run the CLI with the given arguments (without the program name), returning the exit code
+pub fn run<I, S>(args: I) -> i32
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let result = Args::parse(args).and_then(|args| {
+        if args.flag("help") || args.positional.is_empty() {
+            print!("{}", USAGE);
+            return Ok(());
+        }
+
+        let config = load_config(&args)?;
+        if args.word(0) == Some("serve") {
+            return serve(&config);
+        }
+
+        let value = Cli::new(config).execute(&args)?;
+        match (&value, args.flag("json")) {
+            (Value::String(text), false) => print!("{}", text),
+            (_, false) => println!("{}", output::render_text(&value)),
+            (_, true) => println!("{}", output::render_json(&value)),
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            2
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::lightning::channel_manager::generate_random_id;
+    use crate::lightning::test_network::{TestNetwork, FUNDING_CONFIRMATIONS};
+
+    fn args(line: &str) -> Args {
+        Args::parse(line.split_whitespace()).unwrap()
+    }
+
+    fn local(network: &TestNetwork) -> Cli {
+        Cli::with_backend(Config::default(), Backend::Local {
+            bitcoin: network.chain(),
+            lightning: network.node(0),
+        })
+    }
+
+    #[test]
+    fn test_argument_parsing() {
+        let parsed = args("--json ln open 02ab 100000 --push=5000 --anchors --network regtest");
+        assert_eq!(parsed.positional, vec!["ln", "open", "02ab", "100000"]);
+        assert!(parsed.flag("json"));
+        assert!(parsed.flag("anchors"));
+        assert!(!parsed.flag("private"));
+        assert_eq!(parsed.number("push").unwrap(), Some(5000));
+        assert_eq!(parsed.value("network"), Some("regtest"));
+
+        assert!(Args::parse(["wallet", "send", "--fee-rate"]).is_err());
+        assert!(args("chain fee --target soon").number("target").is_err());
+        assert!(parse_peer_address("02ab@127.0.0.1:9735").is_ok());
+        assert!(parse_peer_address("02ab").is_err());
+    }
+
+    #[test]
+    fn test_text_output() {
+        let text = output::render_text(&json!({
+            "balance_sat": 1000,
+            "pubkey": "02ab",
+            "alias": null,
+            "channels": [{ "channel_id": "a" }, { "channel_id": "b" }],
+        }));
+        assert_eq!(text, "balance_sat: 1000\nchannels:\n  channel_id: a\n\n  channel_id: b\npubkey: 02ab");
+    }
+
+    #[test]
+    fn test_local_and_remote_backends_agree() {
+        let network = TestNetwork::new(2).unwrap();
+        let mut cli = local(&network);
+
+        let height = cli.execute(&args("chain height")).unwrap();
+        assert!(cli.execute(&args("wallet balance")).unwrap()["balance_sat"].as_u64().unwrap() > 0);
+        assert_eq!(cli.execute(&args("wallet address --type p2tr")).unwrap()["type"], "p2tr");
+
+        // Open a channel and pay across it
+        let peer = format!("{}@127.0.0.1:9736", network.pubkey(1));
+        cli.execute(&args(&format!("ln connect {}", peer))).unwrap();
+        let channel = cli.execute(&args(&format!("ln open {} 500000", network.pubkey(1)))).unwrap();
+        network.mine_blocks(FUNDING_CONFIRMATIONS).unwrap();
+        let invoice = network.node(1).create_invoice(Some(1_000_000), "cli", None).unwrap();
+        let payment = cli.execute(&args(&format!("ln pay {}", invoice.bolt11))).unwrap();
+        assert_eq!(payment["status"], "succeeded");
+
+        // The same node served over the API gives the same answers
+        let data_dir = std::env::temp_dir().join(format!("opsource-cli-test-{}", generate_random_id()));
+        let api_config = ApiConfig {
+            listen_addr: "127.0.0.1:0".to_string(),
+            data_dir: data_dir.clone(),
+            event_poll_interval: Duration::from_millis(50),
+            request_timeout: Duration::from_secs(5),
+            max_connections: 8,
+        };
+        let server = ApiServer::start(api_config, network.chain(), network.node(0)).unwrap();
+        let mut remote = Cli::new(Config::default());
+        let remote_args = |line: &str| args(&format!("{} --api {} --token {}", line, server.local_addr(), server.auth().token(Scope::Admin)));
+
+        assert_eq!(remote.execute(&remote_args("chain height")).unwrap()["block_height"].as_u64().unwrap(),
+                   height["block_height"].as_u64().unwrap() + u64::from(FUNDING_CONFIRMATIONS));
+        assert_eq!(remote.execute(&remote_args("ln channels")).unwrap(), cli.execute(&args("ln channels")).unwrap());
+        assert_eq!(remote.execute(&remote_args("ln payments")).unwrap()["payments"][0]["payment_hash"],
+                   invoice.payment_hash.as_str());
+        let closed = remote.execute(&remote_args(&format!("ln close {}", channel["channel_id"].as_str().unwrap()))).unwrap();
+        assert!(closed["closing_txid"].is_string());
+
+        // Seeds are never handled over the API
+        assert!(matches!(remote.execute(&remote_args("seed new")), Err(CliError::Usage(_))));
+        assert!(matches!(cli.execute(&args("ln frobnicate")), Err(CliError::Usage(_))));
+
+        drop(server);
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_in_process_commands_validate_the_configuration() {
+        let config = Config { bitcoin_network: Some("mainnet".to_string()), ..Config::default() };
+        assert!(matches!(Cli::new(config).execute(&args("ln info")), Err(CliError::Config(_))));
+    }
+
+    #[test]
+    fn test_seed_and_config_commands() {
+        let data_dir = std::env::temp_dir().join(format!("opsource-cli-test-{}", generate_random_id()));
+        let config_path = std::env::temp_dir().join(format!("opsource-cli-test-{}.conf", generate_random_id()));
+        std::fs::write(&config_path, format!(
+            "# test node\nBITCOIN_NETWORK=regtest\nLIGHTNING_DATA_DIR={}\nLIGHTNING_SEED_PASSPHRASE=\"hunter2\"\n",
+            data_dir.display()
+        )).unwrap();
+
+        let parsed = args(&format!("config show --config {}", config_path.display()));
+        let mut cli = Cli::new(load_config(&parsed).unwrap());
+        let shown = cli.execute(&parsed).unwrap();
+        assert_eq!(shown["bitcoin_network"], "regtest");
+        assert_eq!(shown["lightning_seed_passphrase"], "********");
+        assert_eq!(cli.execute(&args("config validate")).unwrap()["valid"], true);
+
+        // The seed is encrypted with the configured passphrase and can be restored elsewhere
+        let created = cli.execute(&args("seed new")).unwrap();
+        assert!(cli.execute(&args("seed new")).is_err());
+        let keys = KeyManagerWrapper::new(&load_config(&parsed).unwrap());
+        keys.unlock("hunter2").unwrap();
+        assert_eq!(keys.get_node_info().unwrap().pubkey, created["pubkey"]);
+
+        let restore_dir = std::env::temp_dir().join(format!("opsource-cli-test-{}", generate_random_id()));
+        let restore_args = args(&format!("seed restore --data-dir {}", restore_dir.display()));
+        let mut restore_config = load_config(&restore_args).unwrap();
+        restore_config.lightning_seed_passphrase = None;
+        let input = std::io::Cursor::new(format!("{}\nother\n", created["seed"].as_str().unwrap()));
+        let restored = Cli::new(restore_config.clone()).with_input(input).execute(&restore_args).unwrap();
+        assert_eq!(restored["pubkey"], created["pubkey"]);
+        let keys = KeyManagerWrapper::new(&restore_config);
+        keys.unlock("other").unwrap();
+        assert_eq!(keys.get_node_info().unwrap().pubkey, created["pubkey"]);
+
+        // Secrets on the command line are refused
+        let on_argv = args(&format!("seed restore {} --passphrase other", created["seed"].as_str().unwrap()));
+        assert!(matches!(Cli::new(restore_config).execute(&on_argv), Err(CliError::Usage(_))));
+
+        let invalid = args("config validate --network moonnet");
+        assert!(matches!(Cli::new(load_config(&invalid).unwrap()).execute(&invalid), Err(CliError::Config(_))));
+
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let _ = std::fs::remove_dir_all(&restore_dir);
+        let _ = std::fs::remove_file(&config_path);
+    }
+}
@@ -0,0 +1,61 @@
+// Command output formatting
+// Renders command results as JSON for scripts or as indented text for people
+
+use serde_json::Value;
+
+/// Render a result as pretty-printed JSON
+pub fn render_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Render a result as text
+///
+/// Objects become `key: value` lines with nested values indented, and lists
+/// of objects are separated by blank lines. Null fields are left out.
+pub fn render_text(value: &Value) -> String {
+    let mut lines = Vec::new();
+    render_value(value, 0, &mut lines);
+    lines.join("\n")
+}
+
+/// Append the lines for a value at an indentation level
+fn render_value(value: &Value, indent: usize, lines: &mut Vec<String>) {
+    let pad = "  ".repeat(indent);
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                match field {
+                    Value::Null => {}
+                    Value::Object(inner) if inner.is_empty() => {}
+                    Value::Array(items) if items.is_empty() => {}
+                    Value::Object(_) | Value::Array(_) => {
+                        lines.push(format!("{}{}:", pad, key));
+                        render_value(field, indent + 1, lines);
+                    }
+                    _ => lines.push(format!("{}{}: {}", pad, key, scalar(field))),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                if item.is_object() || item.is_array() {
+                    if index > 0 {
+                        lines.push(String::new());
+                    }
+                    render_value(item, indent, lines);
+                } else {
+                    lines.push(format!("{}- {}", pad, scalar(item)));
+                }
+            }
+        }
+        _ => lines.push(format!("{}{}", pad, scalar(value))),
+    }
+}
+
+/// A scalar without JSON quoting
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
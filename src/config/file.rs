@@ -0,0 +1,234 @@
+// Configuration files
+// TOML or YAML settings grouped by component, layered over the defaults
+//
+//   profile = "regtest"
+//
+//   [bitcoin]
+//   network = "regtest"
+//   rpc_url = "http://localhost:18443"
+//   rpc_user = "opsource"
+//   rpc_pass_file = "/run/secrets/bitcoin_rpc_pass"
+//
+//   [lightning]
+//   implementation = "mock"
+//   seed_passphrase_file = "/run/secrets/seed_passphrase"
+//
+//   [features]
+//   accept_dual_funding = true
+//
+//   [profiles.mainnet.lightning]
+//   implementation = "ldk"
+//
+// Every setting is optional. Secrets are never written inline: `*_file`
+// settings name a file holding the secret, resolved relative to the
+// configuration file. `[profiles.<name>]` sections apply on top of the rest of
+// the file when that profile is selected.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::config::{Config, ConfigError};
+
+/// Settings read from a configuration file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileSettings {
+    /// Profile to use unless the environment or command line picks another
+    pub profile: Option<String>,
+
+    /// Bitcoin settings
+    pub bitcoin: BitcoinSettings,
+
+    /// Lightning settings
+    pub lightning: LightningSettings,
+
+    /// Admin API settings
+    pub api: ApiSettings,
+
+    /// Feature flag overrides
+    pub features: FeatureSettings,
+
+    /// Per-profile overrides
+    pub profiles: HashMap<String, ProfileSettings>,
+}
+
+/// Overrides applied when a profile is selected
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileSettings {
+    /// Bitcoin settings
+    pub bitcoin: BitcoinSettings,
+
+    /// Lightning settings
+    pub lightning: LightningSettings,
+
+    /// Admin API settings
+    pub api: ApiSettings,
+
+    /// Feature flag overrides
+    pub features: FeatureSettings,
+}
+
+/// Bitcoin implementation to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BitcoinImplementation {
+    /// rust-bitcoin and BDK
+    Rust,
+    /// python-bitcoinlib
+    Python,
+}
+
+/// `[bitcoin]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BitcoinSettings {
+    /// Implementation to use
+    pub implementation: Option<BitcoinImplementation>,
+    /// Network (mainnet, testnet, signet, regtest)
+    pub network: Option<String>,
+    /// RPC URL
+    pub rpc_url: Option<String>,
+    /// RPC username
+    pub rpc_user: Option<String>,
+    /// File holding the RPC password
+    pub rpc_pass_file: Option<PathBuf>,
+    /// Data directory
+    pub data_dir: Option<String>,
+    /// Wallet file
+    pub wallet_path: Option<String>,
+}
+
+/// `[lightning]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightningSettings {
+    /// Implementation to use (ldk or mock)
+    pub implementation: Option<String>,
+    /// Pinned node public key
+    pub node_pubkey: Option<String>,
+    /// Listening address
+    pub listen_addr: Option<String>,
+    /// Data directory
+    pub data_dir: Option<String>,
+    /// Peers trusted for zero-conf channels
+    pub trusted_peers: Option<Vec<String>>,
+    /// File holding the seed passphrase
+    pub seed_passphrase_file: Option<PathBuf>,
+    /// Allow an empty seed passphrase when none is configured
+    pub allow_empty_seed_passphrase: Option<bool>,
+}
+
+/// `[api]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiSettings {
+    /// Listening address
+    pub listen_addr: Option<String>,
+}
+
+/// `[features]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureSettings {
+    /// Use Electrum servers for chain data
+    pub use_electrum: Option<bool>,
+    /// Verify blocks fully
+    pub verify_blocks: Option<bool>,
+    /// Run the Lightning node
+    pub lightning_enabled: Option<bool>,
+    /// Accept keysend payments
+    pub accept_keysend: Option<bool>,
+    /// Contribute to dual-funded channels
+    pub accept_dual_funding: Option<bool>,
+}
+
+impl FileSettings {
+    /// Read a TOML (`.toml`) or YAML (`.yaml`, `.yml`) configuration file
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+        let parse_error = |message: String| ConfigError::Parse { path: path.to_path_buf(), message };
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&contents).map_err(|e| parse_error(e.to_string())),
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&contents).map_err(|e| parse_error(e.to_string())),
+            _ => Err(parse_error("expected a .toml, .yaml or .yml file".to_string())),
+        }
+    }
+
+    /// Apply the file's settings, then those of the selected profile
+    ///
+    /// Secret files are resolved relative to `base_dir`.
+    pub fn apply(&self, config: &mut Config, profile: Option<&str>, base_dir: &Path) -> Result<(), ConfigError> {
+        apply_sections(config, &self.bitcoin, &self.lightning, &self.api, &self.features, base_dir)?;
+
+        if let Some(overrides) = profile.and_then(|profile| self.profiles.get(profile)) {
+            apply_sections(config, &overrides.bitcoin, &overrides.lightning, &overrides.api, &overrides.features, base_dir)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Apply one layer of sections
+fn apply_sections(
+    config: &mut Config,
+    bitcoin: &BitcoinSettings,
+    lightning: &LightningSettings,
+    api: &ApiSettings,
+    features: &FeatureSettings,
+    base_dir: &Path,
+) -> Result<(), ConfigError> {
+    if let Some(implementation) = bitcoin.implementation {
+        config.use_rust_bitcoin = implementation == BitcoinImplementation::Rust;
+    }
+    set(&mut config.bitcoin_network, &bitcoin.network);
+    set(&mut config.bitcoin_rpc_url, &bitcoin.rpc_url);
+    set(&mut config.bitcoin_rpc_user, &bitcoin.rpc_user);
+    if let Some(path) = &bitcoin.rpc_pass_file {
+        config.bitcoin_rpc_pass = Some(super::read_secret(&base_dir.join(path))?);
+    }
+    set(&mut config.bitcoin_data_dir, &bitcoin.data_dir);
+    set(&mut config.wallet_path, &bitcoin.wallet_path);
+
+    set(&mut config.lightning_implementation, &lightning.implementation);
+    set(&mut config.lightning_node_pubkey, &lightning.node_pubkey);
+    set(&mut config.lightning_listen_addr, &lightning.listen_addr);
+    set(&mut config.lightning_data_dir, &lightning.data_dir);
+    if let Some(peers) = &lightning.trusted_peers {
+        config.lightning_trusted_peers = peers.iter().map(|pubkey| pubkey.trim().to_lowercase()).collect();
+    }
+    if let Some(path) = &lightning.seed_passphrase_file {
+        config.lightning_seed_passphrase = Some(super::read_secret(&base_dir.join(path))?);
+    }
+    if let Some(allow) = lightning.allow_empty_seed_passphrase {
+        config.lightning_allow_empty_seed_passphrase = allow;
+    }
+
+    set(&mut config.api_listen_addr, &api.listen_addr);
+
+    let flags = &mut config.features;
+    for (flag, value) in [
+        (&mut flags.use_electrum, features.use_electrum),
+        (&mut flags.verify_blocks, features.verify_blocks),
+        (&mut flags.lightning_enabled, features.lightning_enabled),
+        (&mut flags.accept_keysend, features.accept_keysend),
+        (&mut flags.accept_dual_funding, features.accept_dual_funding),
+    ] {
+        if let Some(value) = value {
+            *flag = value;
+        }
+    }
+
+    Ok(())
+}
+
+/// Override a setting if the layer has a value for it
+fn set(target: &mut Option<String>, value: &Option<String>) {
+    if value.is_some() {
+        target.clone_from(value);
+    }
+}
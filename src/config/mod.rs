@@ -0,0 +1,582 @@
+// Configuration module for OPSource
+// Provides configuration settings for various components
+//
+// Settings are layered, each layer overriding the ones before it:
+//
+//   1. built-in defaults
+//   2. the selected profile (mainnet, testnet or regtest)
+//   3. the configuration file (TOML or YAML, see `file`)
+//   4. the file's section for the selected profile
+//   5. environment variables
+//   6. command line options (applied by the CLI)
+//
+// The profile is picked by the command line, else `OPSOURCE_PROFILE`, else the
+// file's `profile` setting. Secrets (the RPC password and seed passphrase) are
+// read from files named by `*_file` settings or `*_FILE` environment variables.
+
+pub mod file;
+
+use std::path::{Path, PathBuf};
+
+pub use file::FileSettings;
+
+/// Error type for loading and validating configuration
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    
+    #[error("Invalid configuration file {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    
+    #[error("Unknown profile: {0} (expected mainnet, testnet or regtest)")]
+    UnknownProfile(String),
+    
+    #[error("Invalid configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+/// Named bundles of settings for the usual networks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Real funds: LDK, block verification, strict validation
+    Mainnet,
+    /// Public test network
+    Testnet,
+    /// Local regression-test chain with the mock Lightning node
+    Regtest,
+}
+
+impl Profile {
+    /// Profile by name
+    pub fn from_name(name: &str) -> Result<Self, ConfigError> {
+        match name.to_lowercase().as_str() {
+            "mainnet" => Ok(Profile::Mainnet),
+            "testnet" => Ok(Profile::Testnet),
+            "regtest" => Ok(Profile::Regtest),
+            _ => Err(ConfigError::UnknownProfile(name.to_string())),
+        }
+    }
+    
+    /// Name of the profile
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Mainnet => "mainnet",
+            Profile::Testnet => "testnet",
+            Profile::Regtest => "regtest",
+        }
+    }
+    
+    /// Apply the profile's settings
+    fn apply(&self, config: &mut Config) {
+        let (rpc_port, lightning_implementation) = match self {
+            Profile::Mainnet => (8332, "ldk"),
+            Profile::Testnet => (18332, "ldk"),
+            Profile::Regtest => (18443, "mock"),
+        };
+        
+        config.profile = Some(*self);
+        config.bitcoin_network = Some(self.name().to_string());
+        config.bitcoin_rpc_url = Some(format!("http://localhost:{}", rpc_port));
+        config.lightning_implementation = Some(lightning_implementation.to_string());
+        config.features.verify_blocks = *self == Profile::Mainnet;
+    }
+}
+
+/// Feature flags for various components
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    /// Use Electrum servers for chain data
+    pub use_electrum: bool,
+    /// Verify blocks fully rather than trusting headers
+    pub verify_blocks: bool,
+    /// Run the Lightning node
+    pub lightning_enabled: bool,
+    /// Accept spontaneous (keysend) payments
+    pub accept_keysend: bool,
+    /// Contribute funds to channels peers open with dual funding
+    pub accept_dual_funding: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features {
+            use_electrum: true,
+            verify_blocks: false,
+            lightning_enabled: false,
+            accept_keysend: true,
+            accept_dual_funding: false,
+        }
+    }
+}
+
+/// Main configuration struct for the application
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Profile the settings were based on, if any
+    pub profile: Option<Profile>,
+    
+    /// Whether to use the Rust Bitcoin implementation (true) or Python (false)
+    pub use_rust_bitcoin: bool,
+    
+    /// Bitcoin network to connect to (mainnet, testnet, regtest)
+    pub bitcoin_network: Option<String>,
+    
+    /// Bitcoin RPC connection URL
+    pub bitcoin_rpc_url: Option<String>,
+    
+    /// Bitcoin RPC username
+    pub bitcoin_rpc_user: Option<String>,
+    
+    /// Bitcoin RPC password
+    pub bitcoin_rpc_pass: Option<String>,
+    
+    /// Path to Bitcoin data directory
+    pub bitcoin_data_dir: Option<String>,
+    
+    /// Path to wallet file
+    pub wallet_path: Option<String>,
+    
+    /// Lightning implementation type (ldk or mock)
+    pub lightning_implementation: Option<String>,
+    
+    /// Lightning Network node public key (if already known)
+    pub lightning_node_pubkey: Option<String>,
+    
+    /// Lightning Network listening address and port
+    pub lightning_listen_addr: Option<String>,
+    
+    /// Lightning Network data directory
+    pub lightning_data_dir: Option<String>,
+    
+    /// Node public keys trusted for zero-conf channels
+    pub lightning_trusted_peers: Vec<String>,
+    
+    /// Passphrase the node seed is encrypted with
+    pub lightning_seed_passphrase: Option<String>,
+    
+    /// Explicitly allow encrypting the node seed with an empty passphrase
+    pub lightning_allow_empty_seed_passphrase: bool,
+    
+    /// Admin API listening address and port
+    pub api_listen_addr: Option<String>,
+    
+    /// Feature flags for various components
+    pub features: Features,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            profile: None,
+            use_rust_bitcoin: true, // Default to Rust implementation
+            bitcoin_network: Some("testnet".to_string()),
+            bitcoin_rpc_url: Some("http://localhost:18332".to_string()),
+            bitcoin_rpc_user: None,
+            bitcoin_rpc_pass: None,
+            bitcoin_data_dir: None,
+            wallet_path: None,
+            lightning_implementation: Some("mock".to_string()), // Default to mock implementation
+            lightning_node_pubkey: None,
+            lightning_listen_addr: Some("0.0.0.0:9735".to_string()),
+            lightning_data_dir: None,
+            lightning_trusted_peers: Vec::new(),
+            lightning_seed_passphrase: None,
+            lightning_allow_empty_seed_passphrase: false,
+            api_listen_addr: Some("127.0.0.1:8080".to_string()),
+            features: Features::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from environment variables
+    ///
+    /// Fails if the environment names an unknown profile or an unreadable
+    /// secret file, rather than falling back to the defaults.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::load(None, None)
+    }
+    
+    /// Load configuration from a file, then environment variables
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        Self::load(Some(path), None)
+    }
+    
+    /// Build the configuration from every layer up to the environment
+    ///
+    /// `profile` (from the command line) takes precedence over the
+    /// environment's and the file's choice. Files ending in `.toml`, `.yaml` or
+    /// `.yml` are parsed as settings files; any other file is read as
+    /// `NAME=value` lines using the environment variable names. The result is
+    /// not validated, so command line overrides can still fix it up.
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let settings = match path {
+            Some(path) if is_settings_file(path) => Some(FileSettings::read(path)?),
+            _ => None,
+        };
+        let env_file_vars = match path {
+            Some(path) if !is_settings_file(path) => read_env_file(path)?,
+            _ => std::collections::HashMap::new(),
+        };
+        let base_dir = path.and_then(Path::parent).unwrap_or_else(|| Path::new("."));
+        
+        let profile_name = profile.map(str::to_string)
+            .or_else(|| std::env::var("OPSOURCE_PROFILE").ok())
+            .or_else(|| env_file_vars.get("OPSOURCE_PROFILE").cloned())
+            .or_else(|| settings.as_ref().and_then(|settings| settings.profile.clone()));
+        
+        let mut config = Config::default();
+        if let Some(name) = &profile_name {
+            Profile::from_name(name)?.apply(&mut config);
+        }
+        if let Some(settings) = &settings {
+            let profile = config.profile.map(|profile| profile.name());
+            settings.apply(&mut config, profile, base_dir)?;
+        }
+        config.apply_vars(|name| env_file_vars.get(name).cloned(), base_dir)?;
+        config.apply_vars(|name| std::env::var(name).ok(), Path::new("."))?;
+        
+        Ok(config)
+    }
+    
+    /// Override settings with the variables `lookup` knows about
+    ///
+    /// Secret files named by `*_FILE` variables are resolved relative to `base_dir`.
+    fn apply_vars(&mut self, lookup: impl Fn(&str) -> Option<String>, base_dir: &Path) -> Result<(), ConfigError> {
+        if let Some(val) = lookup("USE_RUST_BITCOIN") {
+            self.use_rust_bitcoin = val.to_lowercase() == "true";
+        }
+        
+        if let Some(val) = lookup("BITCOIN_NETWORK") {
+            self.bitcoin_network = Some(val);
+        }
+        
+        if let Some(val) = lookup("BITCOIN_RPC_URL") {
+            self.bitcoin_rpc_url = Some(val);
+        }
+        
+        if let Some(val) = lookup("BITCOIN_RPC_USER") {
+            self.bitcoin_rpc_user = Some(val);
+        }
+        
+        if let Some(val) = lookup("BITCOIN_RPC_PASS") {
+            println!("Warning: BITCOIN_RPC_PASS is deprecated, put the password in a file and set BITCOIN_RPC_PASS_FILE");
+            self.bitcoin_rpc_pass = Some(val);
+        }
+        
+        if let Some(val) = lookup("BITCOIN_RPC_PASS_FILE") {
+            self.bitcoin_rpc_pass = Some(read_secret(&base_dir.join(val))?);
+        }
+        
+        if let Some(val) = lookup("BITCOIN_DATA_DIR") {
+            self.bitcoin_data_dir = Some(val);
+        }
+        
+        if let Some(val) = lookup("WALLET_PATH") {
+            self.wallet_path = Some(val);
+        }
+        
+        // Lightning Network configuration
+        if let Some(val) = lookup("LIGHTNING_IMPLEMENTATION") {
+            self.lightning_implementation = Some(val);
+        }
+        
+        if let Some(val) = lookup("LIGHTNING_NODE_PUBKEY") {
+            self.lightning_node_pubkey = Some(val);
+        }
+        
+        if let Some(val) = lookup("LIGHTNING_LISTEN_ADDR") {
+            self.lightning_listen_addr = Some(val);
+        }
+        
+        if let Some(val) = lookup("LIGHTNING_DATA_DIR") {
+            self.lightning_data_dir = Some(val);
+        }
+        
+        if let Some(val) = lookup("LIGHTNING_TRUSTED_PEERS") {
+            self.lightning_trusted_peers = val.split(',')
+                .map(|pubkey| pubkey.trim().to_lowercase())
+                .filter(|pubkey| !pubkey.is_empty())
+                .collect();
+        }
+        
+        if let Some(val) = lookup("LIGHTNING_SEED_PASSPHRASE") {
+            println!("Warning: LIGHTNING_SEED_PASSPHRASE is deprecated, put the passphrase in a file and set LIGHTNING_SEED_PASSPHRASE_FILE");
+            self.lightning_seed_passphrase = Some(val);
+        }
+        
+        if let Some(val) = lookup("LIGHTNING_SEED_PASSPHRASE_FILE") {
+            self.lightning_seed_passphrase = Some(read_secret(&base_dir.join(val))?);
+        }
+        
+        if let Some(val) = lookup("LIGHTNING_ALLOW_EMPTY_SEED_PASSPHRASE") {
+            self.lightning_allow_empty_seed_passphrase = val.to_lowercase() == "true";
+        }
+        
+        if let Some(val) = lookup("API_LISTEN_ADDR") {
+            self.api_listen_addr = Some(val);
+        }
+        
+        // Feature flags
+        if let Some(val) = lookup("LIGHTNING_ENABLED") {
+            self.features.lightning_enabled = val.to_lowercase() == "true";
+        }
+        
+        if let Some(val) = lookup("ACCEPT_KEYSEND") {
+            self.features.accept_keysend = val.to_lowercase() == "true";
+        }
+        
+        if let Some(val) = lookup("ACCEPT_DUAL_FUNDING") {
+            self.features.accept_dual_funding = val.to_lowercase() == "true";
+        }
+        
+        Ok(())
+    }
+    
+    /// Directory holding the node seed and other Lightning state
+    pub fn lightning_data_path(&self) -> std::path::PathBuf {
+        self.lightning_data_dir.clone()
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| {
+                let base_dir = self.bitcoin_data_dir.clone()
+                    .unwrap_or_else(|| "./.ldk".to_string());
+                let mut path = std::path::PathBuf::from(base_dir);
+                path.push("lightning");
+                path
+            })
+    }
+    
+    /// Check the settings for mistakes, reporting every problem found
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        
+        if let Some(network) = &self.bitcoin_network {
+            if !["mainnet", "testnet", "signet", "regtest"].contains(&network.as_str()) {
+                problems.push(format!("Unknown Bitcoin network: {} (expected mainnet, testnet, signet or regtest)", network));
+            }
+        }
+        
+        if let (Some(profile), Some(network)) = (self.profile, &self.bitcoin_network) {
+            if profile.name() != network {
+                problems.push(format!("Profile {} cannot be used with the {} network", profile.name(), network));
+            }
+        }
+        
+        if let Some(implementation) = &self.lightning_implementation {
+            if !["ldk", "mock"].contains(&implementation.as_str()) {
+                problems.push(format!("Unknown Lightning implementation: {} (expected ldk or mock)", implementation));
+            }
+        }
+        
+        if self.bitcoin_network.as_deref() == Some("mainnet") {
+            if self.lightning_implementation.as_deref() != Some("ldk") {
+                problems.push("The mock Lightning implementation cannot be used on mainnet; set lightning.implementation = \"ldk\"".to_string());
+            } else if !cfg!(feature = "ldk") {
+                problems.push("This build has no LDK support and would fall back to the mock Lightning implementation on mainnet; build with the ldk feature".to_string());
+            }
+            if self.lightning_seed_passphrase.as_deref().unwrap_or("").is_empty() {
+                problems.push("A seed passphrase is required on mainnet; set lightning.seed_passphrase_file".to_string());
+            }
+        }
+        
+        if self.bitcoin_rpc_user.is_some() && self.bitcoin_rpc_pass.is_none() {
+            problems.push("bitcoin.rpc_user is set without a password; set bitcoin.rpc_pass_file".to_string());
+        }
+        
+        for (name, addr) in [("Lightning listen", &self.lightning_listen_addr), ("API listen", &self.api_listen_addr)] {
+            if let Some(addr) = addr {
+                if addr.parse::<std::net::SocketAddr>().is_err() {
+                    problems.push(format!("{} address is not host:port: {}", name, addr));
+                }
+            }
+        }
+        
+        // The admin API has no TLS, so its tokens must stay on this host
+        if let Some(Ok(addr)) = self.api_listen_addr.as_deref().map(str::parse::<std::net::SocketAddr>) {
+            if !addr.ip().is_loopback() {
+                problems.push(format!("API listen address {} is not loopback; the admin API has no TLS", addr));
+            }
+        }
+        
+        for pubkey in &self.lightning_trusted_peers {
+            if pubkey.len() != 66 || !pubkey.chars().all(|c| c.is_ascii_hexdigit()) {
+                problems.push(format!("Trusted peer is not a node public key: {}", pubkey));
+            }
+        }
+        
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+    
+    /// Get the Lightning implementation type based on configuration
+    pub fn get_lightning_implementation_type(&self) -> crate::lightning::interface::LightningImplementationType {
+        use crate::lightning::interface::LightningImplementationType;
+        
+        match self.lightning_implementation.as_deref() {
+            Some("ldk") => LightningImplementationType::LDK,
+            _ => LightningImplementationType::Mock,
+        }
+    }
+}
+
+/// Whether a file is a TOML or YAML settings file rather than `NAME=value` lines
+fn is_settings_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|extension| extension.to_str()), Some("toml" | "yaml" | "yml"))
+}
+
+/// Read a file of `NAME=value` lines using the environment variable names
+///
+/// Blank lines and lines starting with `#` are ignored.
+fn read_env_file(path: &Path) -> Result<std::collections::HashMap<String, String>, ConfigError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+    
+    let mut vars = std::collections::HashMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = line.split_once('=').ok_or_else(|| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: format!("line {}: expected NAME=value", number + 1),
+        })?;
+        vars.insert(name.trim().to_string(), value.trim().trim_matches('"').to_string());
+    }
+    
+    Ok(vars)
+}
+
+/// Read a secret from a file, without the trailing newline
+fn read_secret(path: &Path) -> Result<String, ConfigError> {
+    let secret = std::fs::read_to_string(path)
+        .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+    
+    // Secrets readable by other users are likely to leak
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            if metadata.permissions().mode() & 0o077 != 0 {
+                println!("Warning: secret file {} is readable by other users", path.display());
+            }
+        }
+    }
+    
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Create a default configuration for testing
+pub fn test_config() -> Config {
+    Config::default()
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lightning::channel_manager::generate_random_id;
+    
+    /// Write files into a fresh temporary directory
+    fn write_files(files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("opsource-config-test-{}", generate_random_id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        dir
+    }
+    
+    #[test]
+    fn test_toml_layers_and_profiles() {
+        let dir = write_files(&[
+            ("opsource.toml", "
+                profile = \"testnet\"
+                
+                [bitcoin]
+                rpc_user = \"opsource\"
+                rpc_pass_file = \"rpc_pass\"
+                
+                [features]
+                accept_dual_funding = true
+                
+                [profiles.testnet.lightning]
+                listen_addr = \"127.0.0.1:19735\"
+                
+                [profiles.regtest.lightning]
+                listen_addr = \"127.0.0.1:29735\"
+            "),
+            ("rpc_pass", "s3cret\n"),
+        ]);
+        let path = dir.join("opsource.toml");
+        
+        // The file picks testnet, and its testnet section applies on top of the base sections
+        let config = Config::load(Some(&path), None).unwrap();
+        assert_eq!(config.profile, Some(Profile::Testnet));
+        assert_eq!(config.bitcoin_network.as_deref(), Some("testnet"));
+        assert_eq!(config.bitcoin_rpc_url.as_deref(), Some("http://localhost:18332"));
+        assert_eq!(config.bitcoin_rpc_pass.as_deref(), Some("s3cret"));
+        assert_eq!(config.lightning_listen_addr.as_deref(), Some("127.0.0.1:19735"));
+        assert!(config.features.accept_dual_funding);
+        assert!(config.features.accept_keysend);
+        assert!(config.validate().is_ok());
+        
+        // A profile given on the command line wins over the file's
+        let config = Config::load(Some(&path), Some("regtest")).unwrap();
+        assert_eq!(config.profile, Some(Profile::Regtest));
+        assert_eq!(config.lightning_implementation.as_deref(), Some("mock"));
+        assert_eq!(config.lightning_listen_addr.as_deref(), Some("127.0.0.1:29735"));
+        
+        assert!(matches!(Config::load(Some(&path), Some("moonnet")), Err(ConfigError::UnknownProfile(_))));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_yaml_and_file_errors() {
+        let dir = write_files(&[
+            ("opsource.yaml", "bitcoin:\n  network: regtest\nlightning:\n  trusted_peers: []\nfeatures:\n  accept_keysend: false\n"),
+            ("typo.toml", "[bitcoin]\nnetwrok = \"regtest\"\n"),
+            ("missing_secret.toml", "[lightning]\nseed_passphrase_file = \"nowhere\"\n"),
+        ]);
+        
+        let config = Config::load(Some(&dir.join("opsource.yaml")), None).unwrap();
+        assert_eq!(config.bitcoin_network.as_deref(), Some("regtest"));
+        assert!(!config.features.accept_keysend);
+        
+        assert!(matches!(Config::load(Some(&dir.join("typo.toml")), None), Err(ConfigError::Parse { .. })));
+        assert!(matches!(Config::load(Some(&dir.join("missing_secret.toml")), None), Err(ConfigError::Io { .. })));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_mainnet_validation() {
+        let dir = write_files(&[
+            ("mock.toml", "profile = \"mainnet\"\n[lightning]\nimplementation = \"mock\"\n"),
+            ("ldk.toml", "profile = \"mainnet\"\n[lightning]\nseed_passphrase_file = \"passphrase\"\n"),
+            ("passphrase", "correct horse battery staple\n"),
+        ]);
+        
+        let config = Config::load(Some(&dir.join("mock.toml")), None).unwrap();
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("mock Lightning implementation cannot be used on mainnet"));
+        assert!(message.contains("seed passphrase is required"));
+        
+        let mut config = Config::load(Some(&dir.join("ldk.toml")), None).unwrap();
+        assert!(config.features.verify_blocks);
+        if cfg!(feature = "ldk") {
+            assert!(config.validate().is_ok());
+        } else {
+            assert!(config.validate().unwrap_err().to_string().contains("no LDK support"));
+        }
+        
+        // Overriding the network away from the profile's is reported
+        config.bitcoin_network = Some("testnet".to_string());
+        assert!(config.validate().unwrap_err().to_string().contains("Profile mainnet"));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -6,6 +6,8 @@
 pub mod config;
 pub mod bitcoin;
 pub mod lightning;
+pub mod api;
+pub mod cli;
 
 // Initialize all modules
 pub fn init() {
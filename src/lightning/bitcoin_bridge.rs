@@ -13,7 +13,7 @@
 
 use crate::lightning::interface::{
     LightningInterface, LightningError, LightningResult,
-    ChannelInfo, NodeInfo
+    ChannelInfo, NodeInfo, OpenChannelOptions
 };
 
 use crate::lightning::channel_manager::ChannelManagerWrapper;
@@ -63,6 +63,12 @@ pub struct ChannelTransaction {
     /// Closing transaction ID (if closed)
     pub closing_txid: Option<String>,
     
+    /// Splice transaction waiting to confirm (if any)
+    pub pending_splice_txid: Option<String>,
+    
+    /// Funding transactions replaced by confirmed splices, oldest first
+    pub previous_funding_txids: Vec<String>,
+    
     /// Created timestamp
     pub created_at: u64,
     
@@ -227,33 +233,79 @@ pub fn check_funding_transactions(&self) -> LightningResult<Vec<ChannelTransacti
                 let channel = self.lightning_interface.open_channel(
                     &funding_info.channel_params.peer_pubkey,
                     funding_info.required_amount,
-                    funding_info.channel_params.push_msat,
-                    funding_info.channel_params.is_private,
+                    OpenChannelOptions {
+                        push_msat: funding_info.channel_params.push_msat,
+                        is_private: funding_info.channel_params.is_private,
+                        ..OpenChannelOptions::default()
+                    },
                 )?;
                 
                 // Store channel transaction info
-                let tx_info = ChannelTransaction {
-                    channel_id: channel.channel_id.clone(),
-                    funding_txid: channel.funding_txid.clone(),
-                    funding_output_idx: channel.funding_output_idx,
-                    funding_amount: channel.capacity,
-                    status: ChannelTransactionStatus::Pending,
-                    confirmation_height: None,
-                    closing_txid: None,
-                    created_at: self.get_timestamp(),
-                    updated_at: self.get_timestamp(),
-                };
-                
-                let mut channel_txs = self.channel_transactions.lock().unwrap();
-                channel_txs.insert(channel.channel_id.clone(), tx_info.clone());
-                
-                result.push(tx_info);
+                result.push(self.register_channel(&channel));
             }
         }
         
         Ok(result)
     }
     
+    /// Track a channel opened outside the bridge
+    pub fn register_channel(&self, channel: &ChannelInfo) -> ChannelTransaction {
+        let tx_info = ChannelTransaction {
+            channel_id: channel.channel_id.clone(),
+            funding_txid: channel.funding_txid.clone(),
+            funding_output_idx: channel.funding_output_idx,
+            funding_amount: channel.capacity,
+            status: ChannelTransactionStatus::Pending,
+            confirmation_height: None,
+            closing_txid: None,
+            pending_splice_txid: channel.pending_splice.as_ref().map(|splice| splice.splice_txid.clone()),
+            previous_funding_txids: Vec::new(),
+            created_at: self.get_timestamp(),
+            updated_at: self.get_timestamp(),
+        };
+        
+        let mut channel_txs = self.channel_transactions.lock().unwrap();
+        channel_txs.insert(channel.channel_id.clone(), tx_info.clone());
+        
+        tx_info
+    }
+    
+    /// Bring channel records in line with splices reported by the Lightning node
+    ///
+    /// Records a pending splice's txid, and once the node has locked a splice in,
+    /// moves the record over to the new funding output and capacity. Returns the
+    /// records that changed.
+    pub fn sync_channel_splices(&self) -> LightningResult<Vec<ChannelTransaction>> {
+        let channels = self.lightning_interface.list_channels()?;
+        let mut channel_txs = self.channel_transactions.lock().unwrap();
+        let mut updated = Vec::new();
+        
+        for channel in channels {
+            let tx_info = match channel_txs.get_mut(&channel.channel_id) {
+                Some(tx_info) if tx_info.status != ChannelTransactionStatus::Closed => tx_info,
+                _ => continue,
+            };
+            
+            let pending_splice_txid = channel.pending_splice.as_ref().map(|splice| splice.splice_txid.clone());
+            let spliced = channel.funding_txid != tx_info.funding_txid;
+            if !spliced && pending_splice_txid == tx_info.pending_splice_txid {
+                continue;
+            }
+            
+            if spliced {
+                let previous = std::mem::replace(&mut tx_info.funding_txid, channel.funding_txid.clone());
+                tx_info.previous_funding_txids.push(previous);
+                tx_info.funding_output_idx = channel.funding_output_idx;
+                tx_info.funding_amount = channel.capacity;
+            }
+            tx_info.pending_splice_txid = pending_splice_txid;
+            tx_info.updated_at = self.get_timestamp();
+            updated.push(tx_info.clone());
+        }
+        
+        Ok(updated)
+    }
+    
     /// Monitor blockchain for channel transactions
     pub fn monitor_blockchain(&self) -> LightningResult<()> {
         // Get current block height
@@ -294,6 +346,11 @@ pub fn monitor_blockchain(&self) -> LightningResult<()> {
         
         // Update last scanned height
         *last_height = current_height;
+        drop(channel_txs);
+        drop(last_height);
+        
+        // New blocks may have locked in splices
+        self.sync_channel_splices()?;
         
         Ok(())
     }
@@ -360,7 +417,7 @@ fn test_bridge_initialization() {
         let lightning_interface = lightning::create_lightning_interface(
             &config,
             bitcoin_interface.clone(),
-        );
+        ).unwrap();
         
         let bridge = BitcoinLightningBridge::new(
             &config,
@@ -380,7 +437,7 @@ fn test_funding_address_creation() {
         let lightning_interface = lightning::create_lightning_interface(
             &config,
             bitcoin_interface.clone(),
-        );
+        ).unwrap();
         
         let bridge = BitcoinLightningBridge::new(
             &config,
@@ -401,4 +458,29 @@ fn test_funding_address_creation() {
             assert!(!address.address.is_empty());
         }
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_splice_updates_channel_record() {
+        use crate::lightning::test_network::{TestNetwork, FUNDING_CONFIRMATIONS};
+        
+        let network = TestNetwork::new(2).unwrap();
+        let channel = network.open_channel(0, 1, 1_000_000, 0).unwrap();
+        
+        let bridge = BitcoinLightningBridge::new(&Config::default(), network.chain(), network.node(0));
+        bridge.init().unwrap();
+        bridge.register_channel(&channel);
+        
+        let splice = network.node(0).splice_in(&channel.channel_id, 250_000).unwrap().pending_splice.unwrap();
+        let updated = bridge.sync_channel_splices().unwrap();
+        assert_eq!(updated[0].pending_splice_txid.as_deref(), Some(splice.splice_txid.as_str()));
+        
+        network.mine_blocks(FUNDING_CONFIRMATIONS).unwrap();
+        bridge.monitor_blockchain().unwrap();
+        
+        let record = bridge.get_channel_transaction(&channel.channel_id).unwrap().unwrap();
+        assert_eq!(record.funding_txid, splice.splice_txid);
+        assert_eq!(record.funding_amount, 1_250_000);
+        assert_eq!(record.previous_funding_txids, vec![channel.funding_txid]);
+        assert!(record.pending_splice_txid.is_none());
+    }
+}
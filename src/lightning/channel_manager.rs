@@ -8,7 +8,7 @@
 use std::fs;
 
 use crate::lightning::interface::{
-    LightningError, LightningResult, ChannelInfo
+    LightningError, LightningResult, ChannelInfo, ChannelType, OpenChannelOptions, PendingSplice
 };
 
 use crate::bitcoin::{
@@ -17,22 +17,24 @@
 
 #[cfg(feature = "ldk")]
 use lightning::{
-    chain::keysinterface::KeysManager,
-    ln::{
-        channelmanager::{ChannelManager, ChannelManagerReadArgs},
-        msgs::{ChannelMessageHandler, RoutingMessageHandler},
-    },
-    util::{
-        config::UserConfig,
-        events::{Event, EventHandler},
-    },
+    ln::channelmanager::ChannelDetails,
+    bitcoin::secp256k1::PublicKey,
 };
 
+#[cfg(feature = "ldk")]
+use crate::bitcoin::encoding::{to_hex, from_hex};
+
+#[cfg(feature = "ldk")]
+use crate::lightning::ldk_node::LdkChannelManager;
+
+/// Confirmations a splice needs before the channel moves to its funding output
+pub const SPLICE_CONFIRMATIONS: u32 = 3;
+
 /// LDK Channel Manager wrapper
 pub struct ChannelManagerWrapper {
     /// LDK Channel Manager
     #[cfg(feature = "ldk")]
-    channel_manager: Mutex<Option<Arc<ChannelManager>>>,
+    channel_manager: Mutex<Option<Arc<LdkChannelManager>>>,
     
     /// Channel cache (for both real and mock data)
     channel_cache: Mutex<HashMap<String, ChannelInfo>>,
@@ -42,10 +44,6 @@ pub struct ChannelManagerWrapper {
     
     /// Configuration
     config: Arc<crate::config::Config>,
-    
-    /// Keys manager
-    #[cfg(feature = "ldk")]
-    keys_manager: Option<Arc<KeysManager>>,
 }
 
 impl ChannelManagerWrapper {
@@ -57,41 +55,25 @@ pub fn new(config: &crate::config::Config, bitcoin_interface: Arc<dyn BitcoinInt
             channel_cache: Mutex::new(HashMap::new()),
             bitcoin_interface,
             config: Arc::new(config.clone()),
-            #[cfg(feature = "ldk")]
-            keys_manager: None,
         }
     }
     
-    /// Initialize the channel manager
+    /// Attach the running LDK ChannelManager
+    ///
+    /// Once attached, channel operations go through LDK instead of the channel cache.
     #[cfg(feature = "ldk")]
-    pub fn initialize(&mut self, keys_manager: Arc<KeysManager>) -> LightningResult<()> {
-        self.keys_manager = Some(keys_manager);
-        
-        // In a real implementation, we would initialize the ChannelManager here
-        // For now, we'll add some mock data to the channel cache
-        let mut channel_cache = self.channel_cache.lock().unwrap();
-        
-        // Add a mock channel
-        let channel = ChannelInfo {
-            channel_id: generate_random_id(),
-            funding_txid: generate_random_id(),
-            funding_output_idx: 0,
-            capacity: 1_000_000, // 0.01 BTC
-            local_balance: 900_000,
-            remote_balance: 100_000,
-            remote_pubkey: "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619".to_string(),
-            is_active: true,
-            is_public: true,
-            short_channel_id: Some("700000x1x0".to_string()),
-        };
-        
-        channel_cache.insert(channel.channel_id.clone(), channel);
-        
-        Ok(())
+    pub fn attach_ldk(&self, channel_manager: Arc<LdkChannelManager>) {
+        *self.channel_manager.lock().unwrap() = Some(channel_manager);
+    }
+    
+    /// Get the attached LDK ChannelManager
+    #[cfg(feature = "ldk")]
+    fn ldk(&self) -> Option<Arc<LdkChannelManager>> {
+        self.channel_manager.lock().unwrap().clone()
     }
     
     #[cfg(not(feature = "ldk"))]
-    pub fn initialize(&mut self) -> LightningResult<()> {
+    pub fn initialize(&self) -> LightningResult<()> {
         // Mock implementation - add some test data
         let mut channel_cache = self.channel_cache.lock().unwrap();
         
@@ -107,6 +89,9 @@ pub fn initialize(&mut self) -> LightningResult<()> {
             is_active: true,
             is_public: true,
             short_channel_id: Some("700000x1x0".to_string()),
+            channel_type: ChannelType::StaticRemoteKey,
+            is_zero_conf: false,
+            pending_splice: None,
         };
         
         channel_cache.insert(channel.channel_id.clone(), channel);
@@ -116,54 +101,116 @@ pub fn initialize(&mut self) -> LightningResult<()> {
     
     /// List all channels
     pub fn list_channels(&self) -> LightningResult<Vec<ChannelInfo>> {
+        #[cfg(feature = "ldk")]
+        if let Some(channel_manager) = self.ldk() {
+            return Ok(channel_manager.list_channels().iter().map(channel_info_from_details).collect());
+        }
+        
         let channel_cache = self.channel_cache.lock().unwrap();
         Ok(channel_cache.values().cloned().collect())
     }
     
+    /// Whether a peer is trusted for zero-conf channels
+    pub fn is_trusted_peer(&self, node_pubkey: &str) -> bool {
+        self.config.lightning_trusted_peers.iter()
+            .any(|trusted| trusted.eq_ignore_ascii_case(node_pubkey))
+    }
+    
+    /// Whether we contribute to channels peers open with dual funding
+    pub fn accepts_dual_funding(&self) -> bool {
+        self.config.features.accept_dual_funding
+    }
+    
+    /// Check channel options before anything is negotiated with the peer
+    pub fn validate_open_options(
+        &self,
+        node_pubkey: &str,
+        capacity: u64,
+        options: &OpenChannelOptions,
+    ) -> LightningResult<()> {
+        if capacity == 0 {
+            return Err(LightningError::ChannelError("Channel capacity must be positive".to_string()));
+        }
+        
+        if options.push_msat.unwrap_or(0) > capacity * 1000 {
+            return Err(LightningError::ChannelError(
+                format!("Cannot push more than our {} sat contribution", capacity)
+            ));
+        }
+        
+        if options.zero_conf && !self.is_trusted_peer(node_pubkey) {
+            return Err(LightningError::ChannelError(
+                format!("Zero-conf channels require a trusted peer, {} is not trusted", node_pubkey)
+            ));
+        }
+        
+        if options.remote_funding_sat == Some(0) {
+            return Err(LightningError::ChannelError("Peer contribution must be positive".to_string()));
+        }
+        
+        Ok(())
+    }
+    
     /// Open a channel with a peer
+    ///
+    /// `capacity` is our contribution; a dual-funded channel's capacity also includes the
+    /// peer's `remote_funding_sat`.
     pub fn open_channel(
         &self,
         node_pubkey: &str,
         capacity: u64,
-        push_msat: Option<u64>,
-        is_private: bool,
+        options: &OpenChannelOptions,
     ) -> LightningResult<ChannelInfo> {
-        // In a real implementation, we would use the LDK ChannelManager to open a channel
-        // For now, create a mock channel
+        self.validate_open_options(node_pubkey, capacity, options)?;
+        
+        #[cfg(feature = "ldk")]
+        if let Some(channel_manager) = self.ldk() {
+            return self.open_ldk_channel(&channel_manager, node_pubkey, capacity, options);
+        }
+        
+        // Without LDK, create a mock channel
         let channel_id = generate_random_id();
         let funding_txid = generate_random_id();
         
         // Calculate balances
-        let push_amount = push_msat.unwrap_or(0) / 1000;
+        let push_amount = options.push_msat.unwrap_or(0) / 1000;
+        let remote_funding = options.remote_funding_sat.unwrap_or(0);
         let local_balance = capacity - push_amount;
-        let remote_balance = push_amount;
+        let remote_balance = remote_funding + push_amount;
         
         let channel = ChannelInfo {
             channel_id: channel_id.clone(),
             funding_txid,
             funding_output_idx: 0,
-            capacity,
+            capacity: capacity + remote_funding,
             local_balance,
             remote_balance,
             remote_pubkey: node_pubkey.to_string(),
             is_active: true,
-            is_public: !is_private,
+            is_public: !options.is_private,
             short_channel_id: None, // Not confirmed yet
+            channel_type: options.channel_type,
+            is_zero_conf: options.zero_conf,
+            pending_splice: None,
         };
         
         // Store the channel
         let mut channel_cache = self.channel_cache.lock().unwrap();
         channel_cache.insert(channel_id, channel.clone());
         
-        println!("Opened channel with peer: {}, capacity: {}", node_pubkey, capacity);
+        println!("Opened channel with peer: {}, capacity: {}", node_pubkey, channel.capacity);
         
         Ok(channel)
     }
     
     /// Close a channel
     pub fn close_channel(&self, channel_id: &str, force: bool) -> LightningResult<String> {
-        // In a real implementation, we would use the LDK ChannelManager to close the channel
-        // For now, just remove it from our cache
+        #[cfg(feature = "ldk")]
+        if let Some(channel_manager) = self.ldk() {
+            return close_ldk_channel(&channel_manager, channel_id, force);
+        }
+        
+        // Without LDK, just remove it from our cache
         let mut channel_cache = self.channel_cache.lock().unwrap();
         
         match channel_cache.remove(channel_id) {
@@ -179,12 +226,135 @@ pub fn close_channel(&self, channel_id: &str, force: bool) -> LightningResult<St
         }
     }
     
+    /// Check that a channel can be spliced now, returning it
+    pub fn validate_splice(&self, channel_id: &str, amount_sat: u64) -> LightningResult<ChannelInfo> {
+        if amount_sat == 0 {
+            return Err(LightningError::ChannelError("Splice amount must be positive".to_string()));
+        }
+        
+        let channel = self.get_channel(channel_id)?.ok_or_else(|| LightningError::ChannelError(
+            format!("Channel {} not found", channel_id)
+        ))?;
+        
+        if !channel.is_active {
+            return Err(LightningError::ChannelError(format!("Channel {} is not active", channel_id)));
+        }
+        
+        if channel.pending_splice.is_some() {
+            return Err(LightningError::ChannelError(
+                format!("Channel {} already has a splice pending", channel_id)
+            ));
+        }
+        
+        Ok(channel)
+    }
+    
+    /// Add on-chain funds to a channel
+    ///
+    /// Broadcasts a splice funded from the on-chain wallet. The channel keeps its
+    /// current funding until `sync_splices` sees the splice confirmed.
+    pub fn splice_in(&self, channel_id: &str, amount_sat: u64) -> LightningResult<ChannelInfo> {
+        #[cfg(feature = "ldk")]
+        if self.ldk().is_some() {
+            return Err(splicing_unsupported());
+        }
+        
+        let mut channel = self.validate_splice(channel_id, amount_sat)?;
+        let splice_tx = self.create_funding_transaction(&channel.remote_pubkey, amount_sat)?;
+        let splice_txid = self.bitcoin_interface.broadcast_transaction(&splice_tx)?;
+        
+        channel.pending_splice = Some(PendingSplice {
+            splice_txid,
+            funding_output_idx: 0,
+            capacity: channel.capacity + amount_sat,
+            local_added_sat: amount_sat,
+            remote_added_sat: 0,
+        });
+        self.update_channel(channel.clone())?;
+        
+        Ok(channel)
+    }
+    
+    /// Pay an on-chain address from our balance in a channel
+    ///
+    /// The amount leaves our balance when the splice is broadcast; the capacity
+    /// shrinks once `sync_splices` sees the splice confirmed.
+    pub fn splice_out(&self, channel_id: &str, address: &str, amount_sat: u64) -> LightningResult<ChannelInfo> {
+        #[cfg(feature = "ldk")]
+        if self.ldk().is_some() {
+            return Err(splicing_unsupported());
+        }
+        
+        let mut channel = self.validate_splice(channel_id, amount_sat)?;
+        crate::bitcoin::encoding::address_to_script_pubkey(address)?;
+        
+        if amount_sat > channel.local_balance {
+            return Err(LightningError::ChannelError(format!(
+                "Cannot splice out {} sats with a local balance of {} sats", amount_sat, channel.local_balance
+            )));
+        }
+        
+        // Without a real funding output to spend, the wallet pays the address for the channel
+        let splice_tx = self.bitcoin_interface.create_transaction(vec![(address.to_string(), amount_sat)], 5)?;
+        let splice_txid = self.bitcoin_interface.broadcast_transaction(&splice_tx)?;
+        
+        channel.local_balance -= amount_sat;
+        channel.pending_splice = Some(PendingSplice {
+            splice_txid,
+            funding_output_idx: 0,
+            capacity: channel.capacity - amount_sat,
+            local_added_sat: 0,
+            remote_added_sat: 0,
+        });
+        self.update_channel(channel.clone())?;
+        
+        Ok(channel)
+    }
+    
+    /// Switch channels over to splices with enough confirmations, returning them
+    pub fn sync_splices(&self) -> LightningResult<Vec<ChannelInfo>> {
+        #[cfg(feature = "ldk")]
+        if self.ldk().is_some() {
+            return Ok(Vec::new());
+        }
+        
+        let mut channel_cache = self.channel_cache.lock().unwrap();
+        let mut locked_in = Vec::new();
+        
+        for channel in channel_cache.values_mut() {
+            let splice_txid = match &channel.pending_splice {
+                Some(splice) => splice.splice_txid.clone(),
+                None => continue,
+            };
+            
+            if self.bitcoin_interface.get_confirmations(&splice_txid)? < SPLICE_CONFIRMATIONS {
+                continue;
+            }
+            
+            let short_channel_id = channel.short_channel_id.clone();
+            lock_in_splice(channel, short_channel_id);
+            locked_in.push(channel.clone());
+        }
+        
+        Ok(locked_in)
+    }
+    
     /// Get a channel by ID
     pub fn get_channel(&self, channel_id: &str) -> LightningResult<Option<ChannelInfo>> {
+        #[cfg(feature = "ldk")]
+        if self.ldk().is_some() {
+            return Ok(self.list_channels()?.into_iter().find(|c| c.channel_id == channel_id));
+        }
+        
         let channel_cache = self.channel_cache.lock().unwrap();
         Ok(channel_cache.get(channel_id).cloned())
     }
     
+    /// Current chain height, against which HTLC expiries are set
+    pub fn block_height(&self) -> LightningResult<u32> {
+        Ok(self.bitcoin_interface.get_block_height()?)
+    }
+    
     /// Update a channel's state
     pub fn update_channel(&self, channel: ChannelInfo) -> LightningResult<()> {
         let mut channel_cache = self.channel_cache.lock().unwrap();
@@ -192,6 +362,37 @@ pub fn update_channel(&self, channel: ChannelInfo) -> LightningResult<()> {
         Ok(())
     }
     
+    /// Check and change several channels under one lock
+    ///
+    /// `modify` gets the channels in the order of `channel_ids`; its changes are stored
+    /// only if it succeeds, so no other update can slip in between check and write.
+    pub fn modify_channels<R>(
+        &self,
+        channel_ids: &[&str],
+        modify: impl FnOnce(&mut [ChannelInfo]) -> LightningResult<R>,
+    ) -> LightningResult<R> {
+        #[cfg(feature = "ldk")]
+        if self.ldk().is_some() {
+            return Err(LightningError::ChannelError(
+                "Channel balances are managed by LDK".to_string()
+            ));
+        }
+        
+        let mut channel_cache = self.channel_cache.lock().unwrap();
+        let mut channels = channel_ids.iter()
+            .map(|channel_id| channel_cache.get(*channel_id).cloned().ok_or_else(|| {
+                LightningError::ChannelError(format!("Channel {} not found", channel_id))
+            }))
+            .collect::<LightningResult<Vec<_>>>()?;
+        
+        let result = modify(&mut channels)?;
+        for channel in channels {
+            channel_cache.insert(channel.channel_id.clone(), channel);
+        }
+        
+        Ok(result)
+    }
+    
     /// Create a funding transaction for a channel
     pub fn create_funding_transaction(
         &self,
@@ -208,6 +409,144 @@ pub fn create_funding_transaction(
             Err(e) => Err(LightningError::BitcoinError(e)),
         }
     }
+    
+    /// Ask LDK to open a channel; funding happens when LDK emits FundingGenerationReady
+    #[cfg(feature = "ldk")]
+    fn open_ldk_channel(
+        &self,
+        channel_manager: &LdkChannelManager,
+        node_pubkey: &str,
+        capacity: u64,
+        options: &OpenChannelOptions,
+    ) -> LightningResult<ChannelInfo> {
+        let their_node_id = parse_pubkey(node_pubkey)?;
+        
+        let mut user_config = channel_manager.get_current_default_configuration().clone();
+        user_config.channel_handshake_config.announced_channel = !options.is_private;
+        user_config.channel_handshake_config.negotiate_anchors_zero_fee_htlc_tx =
+            options.channel_type == ChannelType::AnchorOutputs;
+        
+        // LDK has no opener-side zero-conf switch: the trusted peer signals it by
+        // accepting with a minimum depth of zero, which we already checked we trust
+        let temporary_channel_id = channel_manager.create_channel(
+            their_node_id,
+            capacity,
+            options.push_msat.unwrap_or(0),
+            0,
+            Some(user_config),
+        ).map_err(|e| LightningError::ChannelError(format!("Failed to open channel: {:?}", e)))?;
+        
+        println!("Opened channel with peer: {}, capacity: {}", node_pubkey, capacity);
+        
+        let channel_id = to_hex(&temporary_channel_id);
+        channel_manager.list_channels().iter()
+            .map(channel_info_from_details)
+            .find(|c| c.channel_id == channel_id)
+            .ok_or_else(|| LightningError::ChannelError(
+                format!("Channel {} not found after opening", channel_id)
+            ))
+    }
+}
+
+/// LDK 0.0.117 cannot splice channels
+#[cfg(feature = "ldk")]
+pub fn splicing_unsupported() -> LightningError {
+    LightningError::ChannelError("Splicing is not supported by the LDK implementation".to_string())
+}
+
+/// Close a channel through LDK
+///
+/// Cooperative closes are negotiated asynchronously, so the returned value is the
+/// channel's funding txid; the closing transaction is broadcast once negotiation ends.
+#[cfg(feature = "ldk")]
+fn close_ldk_channel(
+    channel_manager: &LdkChannelManager,
+    channel_id: &str,
+    force: bool,
+) -> LightningResult<String> {
+    let details = channel_manager.list_channels().into_iter()
+        .find(|details| to_hex(&details.channel_id) == channel_id)
+        .ok_or_else(|| LightningError::ChannelError(
+            format!("Channel {} not found", channel_id)
+        ))?;
+    
+    let counterparty = details.counterparty.node_id;
+    let result = if force {
+        channel_manager.force_close_broadcasting_latest_txn(&details.channel_id, &counterparty)
+    } else {
+        channel_manager.close_channel(&details.channel_id, &counterparty)
+    };
+    result.map_err(|e| LightningError::ChannelError(format!("Failed to close channel: {:?}", e)))?;
+    
+    println!("Closed channel: {}, forced: {}", channel_id, force);
+    
+    Ok(details.funding_txo
+        .map(|outpoint| outpoint.txid.to_string())
+        .unwrap_or_default())
+}
+
+/// Convert LDK channel details into our channel info
+#[cfg(feature = "ldk")]
+fn channel_info_from_details(details: &ChannelDetails) -> ChannelInfo {
+    let local_balance = details.balance_msat / 1000;
+    
+    ChannelInfo {
+        channel_id: to_hex(&details.channel_id),
+        funding_txid: details.funding_txo
+            .map(|outpoint| outpoint.txid.to_string())
+            .unwrap_or_default(),
+        funding_output_idx: details.funding_txo
+            .map(|outpoint| outpoint.index as u32)
+            .unwrap_or(0),
+        capacity: details.channel_value_satoshis,
+        local_balance,
+        remote_balance: details.channel_value_satoshis.saturating_sub(local_balance),
+        remote_pubkey: to_hex(&details.counterparty.node_id.serialize()),
+        is_active: details.is_usable,
+        is_public: details.is_public,
+        short_channel_id: details.short_channel_id.map(format_short_channel_id),
+        channel_type: match &details.channel_type {
+            Some(features) if features.supports_anchors_zero_fee_htlc_tx() => ChannelType::AnchorOutputs,
+            _ => ChannelType::StaticRemoteKey,
+        },
+        is_zero_conf: details.confirmations_required == Some(0),
+        pending_splice: None,
+    }
+}
+
+/// Format a short channel ID as block x transaction x output
+#[cfg(feature = "ldk")]
+pub fn format_short_channel_id(short_channel_id: u64) -> String {
+    format!(
+        "{}x{}x{}",
+        short_channel_id >> 40,
+        (short_channel_id >> 16) & 0xFF_FFFF,
+        short_channel_id & 0xFFFF
+    )
+}
+
+/// Parse a hex-encoded node public key
+#[cfg(feature = "ldk")]
+pub fn parse_pubkey(node_pubkey: &str) -> LightningResult<PublicKey> {
+    let bytes = from_hex(node_pubkey)
+        .map_err(|_| LightningError::NetworkError(format!("Invalid node pubkey: {}", node_pubkey)))?;
+    PublicKey::from_slice(&bytes)
+        .map_err(|_| LightningError::NetworkError(format!("Invalid node pubkey: {}", node_pubkey)))
+}
+
+/// Switch a channel over to its pending splice's funding output
+///
+/// Applies the new capacity and any spliced-in balance. Funds spliced out were
+/// already taken from the balance when the splice was broadcast.
+pub fn lock_in_splice(channel: &mut ChannelInfo, short_channel_id: Option<String>) {
+    if let Some(splice) = channel.pending_splice.take() {
+        channel.funding_txid = splice.splice_txid;
+        channel.funding_output_idx = splice.funding_output_idx;
+        channel.capacity = splice.capacity;
+        channel.local_balance += splice.local_added_sat;
+        channel.remote_balance += splice.remote_added_sat;
+        channel.short_channel_id = short_channel_id;
+    }
 }
 
 /// Generate a random ID for testing purposes
@@ -0,0 +1,861 @@
+// Lightning Network HTLC Forwarding
+// Handles routing-node behaviour: forwarding HTLCs between our channels,
+// per-channel fee/CLTV policies, channel_update broadcasts and fee earnings
+
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::lightning::interface::{
+    LightningError, LightningResult, ChannelInfo
+};
+
+use crate::lightning::channel_manager::ChannelManagerWrapper;
+use crate::lightning::payment_router::PaymentRouter;
+
+#[cfg(feature = "ldk")]
+use crate::bitcoin::encoding::to_hex;
+
+#[cfg(feature = "ldk")]
+use crate::lightning::ldk_node::LdkChannelManager;
+
+/// Default base fee charged per forwarded HTLC (in msats)
+pub const DEFAULT_FEE_BASE_MSAT: u32 = 1000;
+
+/// Default proportional fee (in millionths of the forwarded amount)
+pub const DEFAULT_FEE_PROPORTIONAL_MILLIONTHS: u32 = 100;
+
+/// Default CLTV delta we require between incoming and outgoing HTLCs
+pub const DEFAULT_CLTV_EXPIRY_DELTA: u16 = 40;
+
+/// Default minimum HTLC we are willing to forward (in msats)
+pub const DEFAULT_HTLC_MINIMUM_MSAT: u64 = 1000;
+
+/// Forwarding policy for a single channel, as advertised in channel_update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardingPolicy {
+    /// Base fee in millisatoshis
+    pub fee_base_msat: u32,
+
+    /// Proportional fee in millionths
+    pub fee_proportional_millionths: u32,
+
+    /// CLTV delta required for HTLCs leaving through this channel
+    pub cltv_expiry_delta: u16,
+
+    /// Minimum HTLC value in millisatoshis
+    pub htlc_minimum_msat: u64,
+
+    /// Maximum HTLC value in millisatoshis (None means channel capacity)
+    pub htlc_maximum_msat: Option<u64>,
+
+    /// Whether forwarding through this channel is enabled
+    pub enabled: bool,
+}
+
+impl Default for ForwardingPolicy {
+    fn default() -> Self {
+        ForwardingPolicy {
+            fee_base_msat: DEFAULT_FEE_BASE_MSAT,
+            fee_proportional_millionths: DEFAULT_FEE_PROPORTIONAL_MILLIONTHS,
+            cltv_expiry_delta: DEFAULT_CLTV_EXPIRY_DELTA,
+            htlc_minimum_msat: DEFAULT_HTLC_MINIMUM_MSAT,
+            htlc_maximum_msat: None,
+            enabled: true,
+        }
+    }
+}
+
+impl ForwardingPolicy {
+    /// Fee required to forward `amount_msat` out through a channel with this policy
+    pub fn fee_for_amount(&self, amount_msat: u64) -> u64 {
+        self.fee_base_msat as u64 +
+            (amount_msat * self.fee_proportional_millionths as u64) / 1_000_000
+    }
+}
+
+/// Fee management mode for the forwarding manager
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeeMode {
+    /// Fees are only changed through the policy API
+    Static,
+
+    /// Fees follow local liquidity: the less outbound liquidity a channel has,
+    /// the more we charge to forward through it
+    LiquidityBased(AutoFeeConfig),
+}
+
+/// Configuration for liquidity-based fee adjustment
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoFeeConfig {
+    /// Base fee applied to every channel (in msats)
+    pub fee_base_msat: u32,
+
+    /// Proportional fee when the channel is entirely on our side
+    pub min_fee_proportional_millionths: u32,
+
+    /// Proportional fee when the channel is entirely on the remote side
+    pub max_fee_proportional_millionths: u32,
+
+    /// Fraction of local balance we allow in a single HTLC
+    pub max_htlc_ratio: f64,
+
+    /// Minimum change in proportional fee before a new channel_update is sent
+    pub update_threshold_millionths: u32,
+}
+
+impl Default for AutoFeeConfig {
+    fn default() -> Self {
+        AutoFeeConfig {
+            fee_base_msat: DEFAULT_FEE_BASE_MSAT,
+            min_fee_proportional_millionths: 10,
+            max_fee_proportional_millionths: 2500,
+            max_htlc_ratio: 0.25,
+            update_threshold_millionths: 25,
+        }
+    }
+}
+
+impl AutoFeeConfig {
+    /// Compute the policy for a channel from its current balances
+    pub fn policy_for_channel(&self, channel: &ChannelInfo, current: &ForwardingPolicy) -> ForwardingPolicy {
+        let outbound_ratio = if channel.capacity == 0 {
+            0.0
+        } else {
+            channel.local_balance as f64 / channel.capacity as f64
+        };
+
+        let range = self.max_fee_proportional_millionths
+            .saturating_sub(self.min_fee_proportional_millionths) as f64;
+        let fee_proportional_millionths = self.min_fee_proportional_millionths +
+            (range * (1.0 - outbound_ratio)).round() as u32;
+
+        let htlc_maximum_msat = (channel.local_balance as f64 * 1000.0 * self.max_htlc_ratio) as u64;
+
+        ForwardingPolicy {
+            fee_base_msat: self.fee_base_msat,
+            fee_proportional_millionths,
+            cltv_expiry_delta: current.cltv_expiry_delta,
+            htlc_minimum_msat: current.htlc_minimum_msat,
+            htlc_maximum_msat: Some(htlc_maximum_msat.max(current.htlc_minimum_msat)),
+            enabled: current.enabled,
+        }
+    }
+}
+
+/// A channel_update message to be gossiped to the network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelUpdate {
+    /// Channel ID
+    pub channel_id: String,
+
+    /// Short channel ID (None for unconfirmed channels)
+    pub short_channel_id: Option<String>,
+
+    /// Update timestamp (must be strictly increasing per channel)
+    pub timestamp: u64,
+
+    /// Policy being announced
+    pub policy: ForwardingPolicy,
+}
+
+/// Record of a successfully forwarded HTLC
+#[derive(Debug, Clone)]
+pub struct ForwardingEvent {
+    /// Incoming channel ID
+    pub incoming_channel_id: String,
+
+    /// Outgoing channel ID
+    pub outgoing_channel_id: String,
+
+    /// Payment hash of the HTLC
+    pub payment_hash: String,
+
+    /// Amount received on the incoming channel (in msats)
+    pub amount_in_msat: u64,
+
+    /// Amount sent on the outgoing channel (in msats)
+    pub amount_out_msat: u64,
+
+    /// Fee earned by forwarding (in msats)
+    pub fee_earned_msat: u64,
+
+    /// Timestamp of the forward
+    pub timestamp: u64,
+}
+
+/// An HTLC received on one of our channels that should be forwarded
+#[derive(Debug, Clone)]
+pub struct IncomingHtlc {
+    /// Channel the HTLC arrived on
+    pub incoming_channel_id: String,
+
+    /// Channel the onion asks us to forward over
+    pub outgoing_channel_id: String,
+
+    /// Payment hash
+    pub payment_hash: String,
+
+    /// Amount of the incoming HTLC (in msats)
+    pub amount_in_msat: u64,
+
+    /// Amount to forward, as instructed by the onion (in msats)
+    pub amount_out_msat: u64,
+
+    /// CLTV expiry of the incoming HTLC
+    pub incoming_cltv_expiry: u32,
+
+    /// CLTV expiry requested for the outgoing HTLC
+    pub outgoing_cltv_expiry: u32,
+}
+
+/// Forwarding manager for acting as an intermediate routing node
+pub struct ForwardingManager {
+    /// Channel manager holding channel balances
+    channel_manager: Arc<ChannelManagerWrapper>,
+
+    /// Router whose graph reflects our advertised policies
+    router: Arc<PaymentRouter>,
+
+    /// Per-channel policies
+    policies: Mutex<HashMap<String, ForwardingPolicy>>,
+
+    /// Policy applied to channels without an explicit one
+    default_policy: Mutex<ForwardingPolicy>,
+
+    /// Fee management mode
+    fee_mode: Mutex<FeeMode>,
+
+    /// Timestamp of the last channel_update per channel
+    last_update: Mutex<HashMap<String, u64>>,
+
+    /// channel_update messages waiting to be broadcast
+    pending_updates: Mutex<Vec<ChannelUpdate>>,
+
+    /// Forwarding history
+    history: Mutex<Vec<ForwardingEvent>>,
+
+    /// Millisatoshis of our balance per channel below the whole satoshis its
+    /// `ChannelInfo` holds, so fees under a satoshi still add up
+    remainders_msat: Mutex<HashMap<String, u64>>,
+
+    /// LDK ChannelManager forwarding HTLCs in our place
+    #[cfg(feature = "ldk")]
+    ldk: Mutex<Option<Arc<LdkChannelManager>>>,
+}
+
+impl ForwardingManager {
+    /// Create a new Forwarding Manager
+    pub fn new(
+        channel_manager: Arc<ChannelManagerWrapper>,
+        router: Arc<PaymentRouter>,
+    ) -> Self {
+        ForwardingManager {
+            channel_manager,
+            router,
+            policies: Mutex::new(HashMap::new()),
+            default_policy: Mutex::new(ForwardingPolicy::default()),
+            fee_mode: Mutex::new(FeeMode::Static),
+            last_update: Mutex::new(HashMap::new()),
+            pending_updates: Mutex::new(Vec::new()),
+            history: Mutex::new(Vec::new()),
+            remainders_msat: Mutex::new(HashMap::new()),
+            #[cfg(feature = "ldk")]
+            ldk: Mutex::new(None),
+        }
+    }
+
+    /// Attach the running LDK ChannelManager
+    ///
+    /// LDK forwards HTLCs itself, so from then on policies are pushed into the
+    /// `ChannelConfig` of each channel.
+    #[cfg(feature = "ldk")]
+    pub fn attach_ldk(&self, channel_manager: Arc<LdkChannelManager>) -> LightningResult<()> {
+        *self.ldk.lock().unwrap() = Some(channel_manager);
+        self.sync_node_policies()
+    }
+
+    /// Push the policy in effect for every channel to the node forwarding HTLCs
+    ///
+    /// LDK gives newly opened channels its default config, so this runs whenever a
+    /// channel becomes ready.
+    pub fn sync_node_policies(&self) -> LightningResult<()> {
+        for channel in self.channel_manager.list_channels()? {
+            let policy = self.policy_for(&channel.channel_id);
+            let applied = self.apply_to_node(&channel.channel_id, &policy)?;
+            if applied != policy {
+                self.policies.lock().unwrap().insert(channel.channel_id, applied);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the policy in effect for a channel
+    pub fn get_channel_policy(&self, channel_id: &str) -> LightningResult<ForwardingPolicy> {
+        self.require_channel(channel_id)?;
+        Ok(self.policy_for(channel_id))
+    }
+
+    /// Set the policy for a single channel and announce it
+    pub fn set_channel_policy(
+        &self,
+        channel_id: &str,
+        policy: ForwardingPolicy,
+    ) -> LightningResult<ChannelUpdate> {
+        let channel = self.require_channel(channel_id)?;
+        validate_policy(&policy)?;
+
+        let policy = self.apply_to_node(channel_id, &policy)?;
+        self.policies.lock().unwrap().insert(channel_id.to_string(), policy.clone());
+        Ok(self.announce(&channel, policy))
+    }
+
+    /// Set the policy for all channels, including ones opened later
+    pub fn set_default_policy(&self, policy: ForwardingPolicy) -> LightningResult<Vec<ChannelUpdate>> {
+        validate_policy(&policy)?;
+
+        *self.default_policy.lock().unwrap() = policy.clone();
+        self.policies.lock().unwrap().clear();
+
+        let mut updates = Vec::new();
+        for channel in self.channel_manager.list_channels()? {
+            let applied = self.apply_to_node(&channel.channel_id, &policy)?;
+            if applied != policy {
+                self.policies.lock().unwrap().insert(channel.channel_id.clone(), applied.clone());
+            }
+            updates.push(self.announce(&channel, applied));
+        }
+        Ok(updates)
+    }
+
+    /// Get the current fee mode
+    pub fn get_fee_mode(&self) -> FeeMode {
+        self.fee_mode.lock().unwrap().clone()
+    }
+
+    /// Change the fee mode; switching to liquidity-based mode re-prices every channel
+    pub fn set_fee_mode(&self, mode: FeeMode) -> LightningResult<Vec<ChannelUpdate>> {
+        *self.fee_mode.lock().unwrap() = mode;
+        self.refresh_auto_fees()
+    }
+
+    /// Recompute liquidity-based fees and announce the channels whose fees moved
+    pub fn refresh_auto_fees(&self) -> LightningResult<Vec<ChannelUpdate>> {
+        let auto_config = match self.get_fee_mode() {
+            FeeMode::LiquidityBased(auto_config) => auto_config,
+            FeeMode::Static => return Ok(Vec::new()),
+        };
+
+        let mut updates = Vec::new();
+
+        for channel in self.channel_manager.list_channels()? {
+            let current = self.policy_for(&channel.channel_id);
+            let new_policy = auto_config.policy_for_channel(&channel, &current);
+
+            let fee_delta = (new_policy.fee_proportional_millionths as i64 -
+                current.fee_proportional_millionths as i64).unsigned_abs();
+            let has_policy = self.policies.lock().unwrap().contains_key(&channel.channel_id);
+
+            if has_policy && fee_delta < auto_config.update_threshold_millionths as u64 &&
+                new_policy.fee_base_msat == current.fee_base_msat {
+                continue;
+            }
+
+            let new_policy = self.apply_to_node(&channel.channel_id, &new_policy)?;
+            self.policies.lock().unwrap().insert(channel.channel_id.clone(), new_policy.clone());
+            updates.push(self.announce(&channel, new_policy));
+        }
+
+        Ok(updates)
+    }
+
+    /// Forward an HTLC from one of our channels to another
+    ///
+    /// Both channels settle in millisatoshis, so our balance across them grows
+    /// by exactly the fee earned.
+    pub fn forward_htlc(&self, htlc: &IncomingHtlc) -> LightningResult<ForwardingEvent> {
+        // Check and settle both sides of the forward under the channel lock
+        let mut remainders = self.remainders_msat.lock().unwrap();
+        let (offered_fee, incoming_remainder, outgoing_remainder) = self.channel_manager.modify_channels(
+            &[&htlc.incoming_channel_id, &htlc.outgoing_channel_id],
+            |channels| {
+                let [incoming, outgoing] = channels else {
+                    unreachable!("two channel IDs give two channels");
+                };
+                let incoming_remainder = remainders.get(&incoming.channel_id).copied().unwrap_or(0);
+                let outgoing_remainder = remainders.get(&outgoing.channel_id).copied().unwrap_or(0);
+                let offered_fee = self.check_htlc(htlc, incoming, outgoing, incoming_remainder, outgoing_remainder)?;
+
+                let (incoming_local_msat, _) = balances_msat(incoming, incoming_remainder);
+                let incoming_remainder = set_local_msat(incoming, incoming_local_msat + htlc.amount_in_msat)?;
+                let (outgoing_local_msat, _) = balances_msat(outgoing, outgoing_remainder);
+                let outgoing_local_msat = outgoing_local_msat.checked_sub(htlc.amount_out_msat)
+                    .ok_or_else(|| LightningError::ChannelError(format!(
+                        "Balance of channel {} cannot cover {} msat", outgoing.channel_id, htlc.amount_out_msat
+                    )))?;
+                let outgoing_remainder = set_local_msat(outgoing, outgoing_local_msat)?;
+
+                Ok((offered_fee, incoming_remainder, outgoing_remainder))
+            },
+        )?;
+        remainders.insert(htlc.incoming_channel_id.clone(), incoming_remainder);
+        remainders.insert(htlc.outgoing_channel_id.clone(), outgoing_remainder);
+        drop(remainders);
+
+        let event = ForwardingEvent {
+            incoming_channel_id: htlc.incoming_channel_id.clone(),
+            outgoing_channel_id: htlc.outgoing_channel_id.clone(),
+            payment_hash: htlc.payment_hash.clone(),
+            amount_in_msat: htlc.amount_in_msat,
+            amount_out_msat: htlc.amount_out_msat,
+            fee_earned_msat: offered_fee,
+            timestamp: get_timestamp(),
+        };
+
+        self.history.lock().unwrap().push(event.clone());
+
+        // Balances moved, so liquidity-based fees may need to follow
+        self.refresh_auto_fees()?;
+
+        Ok(event)
+    }
+
+    /// Check that an HTLC would be forwarded, without moving any balances
+    pub fn check_forward(&self, htlc: &IncomingHtlc) -> LightningResult<()> {
+        let incoming = self.require_channel(&htlc.incoming_channel_id)?;
+        let outgoing = self.require_channel(&htlc.outgoing_channel_id)?;
+        let incoming_remainder = self.remainder_msat(&htlc.incoming_channel_id);
+        let outgoing_remainder = self.remainder_msat(&htlc.outgoing_channel_id);
+        self.check_htlc(htlc, &incoming, &outgoing, incoming_remainder, outgoing_remainder).map(|_| ())
+    }
+
+    /// Our balance in a channel in millisatoshis, including forwarded fractions of a satoshi
+    pub fn local_balance_msat(&self, channel_id: &str) -> LightningResult<u64> {
+        let channel = self.require_channel(channel_id)?;
+        Ok(balances_msat(&channel, self.remainder_msat(channel_id)).0)
+    }
+
+    fn remainder_msat(&self, channel_id: &str) -> u64 {
+        self.remainders_msat.lock().unwrap().get(channel_id).copied().unwrap_or(0)
+    }
+
+    /// Apply policy and liquidity checks to an HTLC, returning the fee offered
+    fn check_htlc(
+        &self,
+        htlc: &IncomingHtlc,
+        incoming: &ChannelInfo,
+        outgoing: &ChannelInfo,
+        incoming_remainder_msat: u64,
+        outgoing_remainder_msat: u64,
+    ) -> LightningResult<u64> {
+        if htlc.incoming_channel_id == htlc.outgoing_channel_id {
+            return Err(LightningError::PaymentError(
+                "Cannot forward an HTLC back over the channel it arrived on".to_string()
+            ));
+        }
+
+        let policy = self.policy_for(&htlc.outgoing_channel_id);
+
+        if !incoming.is_active || !outgoing.is_active || !policy.enabled {
+            return Err(LightningError::ChannelError(
+                format!("Channel {} is disabled for forwarding", htlc.outgoing_channel_id)
+            ));
+        }
+
+        // Amount limits on the outgoing channel
+        if htlc.amount_out_msat < policy.htlc_minimum_msat {
+            return Err(LightningError::PaymentError(
+                format!("Amount {} msat below htlc_minimum_msat {}",
+                        htlc.amount_out_msat, policy.htlc_minimum_msat)
+            ));
+        }
+
+        if let Some(maximum) = policy.htlc_maximum_msat {
+            if htlc.amount_out_msat > maximum {
+                return Err(LightningError::PaymentError(
+                    format!("Amount {} msat above htlc_maximum_msat {}", htlc.amount_out_msat, maximum)
+                ));
+            }
+        }
+
+        // Fee and CLTV requirements
+        let required_fee = policy.fee_for_amount(htlc.amount_out_msat);
+        let offered_fee = htlc.amount_in_msat.saturating_sub(htlc.amount_out_msat);
+
+        if htlc.amount_in_msat < htlc.amount_out_msat || offered_fee < required_fee {
+            return Err(LightningError::PaymentError(
+                format!("Insufficient fee: offered {} msat, required {} msat", offered_fee, required_fee)
+            ));
+        }
+
+        let cltv_delta = htlc.incoming_cltv_expiry.saturating_sub(htlc.outgoing_cltv_expiry);
+        if htlc.incoming_cltv_expiry <= htlc.outgoing_cltv_expiry ||
+            cltv_delta < policy.cltv_expiry_delta as u32 {
+            return Err(LightningError::PaymentError(
+                format!("Incorrect CLTV expiry: delta {} below required {}",
+                        cltv_delta, policy.cltv_expiry_delta)
+            ));
+        }
+
+        // Liquidity on both sides
+        let (_, incoming_remote_msat) = balances_msat(incoming, incoming_remainder_msat);
+        let (outgoing_local_msat, _) = balances_msat(outgoing, outgoing_remainder_msat);
+
+        if incoming_remote_msat < htlc.amount_in_msat {
+            return Err(LightningError::ChannelError(
+                format!("Peer on channel {} cannot afford HTLC of {} msat",
+                        htlc.incoming_channel_id, htlc.amount_in_msat)
+            ));
+        }
+
+        if outgoing_local_msat < htlc.amount_out_msat {
+            return Err(LightningError::ChannelError(
+                format!("Insufficient outbound liquidity on channel {} for {} msat",
+                        htlc.outgoing_channel_id, htlc.amount_out_msat)
+            ));
+        }
+
+        Ok(offered_fee)
+    }
+
+    /// List forwarding events, optionally limited to a time range
+    pub fn list_forwards(&self, since: Option<u64>, until: Option<u64>) -> Vec<ForwardingEvent> {
+        let history = self.history.lock().unwrap();
+        history.iter()
+            .filter(|event| since.is_none_or(|since| event.timestamp >= since))
+            .filter(|event| until.is_none_or(|until| event.timestamp <= until))
+            .cloned()
+            .collect()
+    }
+
+    /// Total fees earned by forwarding (in msats)
+    pub fn total_fees_earned_msat(&self) -> u64 {
+        let history = self.history.lock().unwrap();
+        history.iter().map(|event| event.fee_earned_msat).sum()
+    }
+
+    /// Take all channel_update messages waiting to be broadcast
+    pub fn take_pending_updates(&self) -> Vec<ChannelUpdate> {
+        let mut pending = self.pending_updates.lock().unwrap();
+        std::mem::take(&mut *pending)
+    }
+
+    /// Look up a channel or fail with a channel error
+    fn require_channel(&self, channel_id: &str) -> LightningResult<ChannelInfo> {
+        self.channel_manager.get_channel(channel_id)?
+            .ok_or_else(|| LightningError::ChannelError(
+                format!("Channel {} not found", channel_id)
+            ))
+    }
+
+    /// Policy in effect for a channel, falling back to the default policy
+    fn policy_for(&self, channel_id: &str) -> ForwardingPolicy {
+        let policies = self.policies.lock().unwrap();
+        policies.get(channel_id)
+            .cloned()
+            .unwrap_or_else(|| self.default_policy.lock().unwrap().clone())
+    }
+
+    /// Make a policy take effect on the node that forwards our HTLCs
+    ///
+    /// Without LDK this manager forwards HTLCs itself and the policy applies as is.
+    /// With LDK the fees and CLTV delta go into the channel's `ChannelConfig`; LDK
+    /// 0.0.117 fixes HTLC limits when a channel opens, so the returned policy carries
+    /// the limits LDK enforces rather than the requested ones.
+    #[cfg_attr(not(feature = "ldk"), allow(unused_variables))]
+    fn apply_to_node(&self, channel_id: &str, policy: &ForwardingPolicy) -> LightningResult<ForwardingPolicy> {
+        #[cfg(feature = "ldk")]
+        if let Some(channel_manager) = self.ldk.lock().unwrap().clone() {
+            return apply_to_ldk(&channel_manager, channel_id, policy);
+        }
+
+        Ok(policy.clone())
+    }
+
+    /// Build a channel_update for a policy, queue it and apply it to our router graph
+    fn announce(&self, channel: &ChannelInfo, policy: ForwardingPolicy) -> ChannelUpdate {
+        // channel_update timestamps must strictly increase for each channel
+        let mut last_update = self.last_update.lock().unwrap();
+        let previous = last_update.get(&channel.channel_id).copied().unwrap_or(0);
+        let timestamp = get_timestamp().max(previous + 1);
+        last_update.insert(channel.channel_id.clone(), timestamp);
+
+        let update = ChannelUpdate {
+            channel_id: channel.channel_id.clone(),
+            short_channel_id: channel.short_channel_id.clone(),
+            timestamp,
+            policy,
+        };
+
+        // Our own channels are not always part of the router graph
+        let _ = self.router.update_channel_fees(
+            &channel.channel_id,
+            update.policy.fee_base_msat,
+            update.policy.fee_proportional_millionths,
+        );
+
+        if channel.is_public {
+            self.pending_updates.lock().unwrap().push(update.clone());
+        }
+
+        update
+    }
+}
+
+/// Reject policies that could never forward anything
+fn validate_policy(policy: &ForwardingPolicy) -> LightningResult<()> {
+    if let Some(maximum) = policy.htlc_maximum_msat {
+        if maximum < policy.htlc_minimum_msat {
+            return Err(LightningError::ChannelError(
+                format!("htlc_maximum_msat {} is below htlc_minimum_msat {}",
+                        maximum, policy.htlc_minimum_msat)
+            ));
+        }
+    }
+
+    if policy.cltv_expiry_delta == 0 {
+        return Err(LightningError::ChannelError(
+            "cltv_expiry_delta must be greater than zero".to_string()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Push a policy into an LDK channel's config, returning the policy LDK enforces
+#[cfg(feature = "ldk")]
+fn apply_to_ldk(
+    channel_manager: &LdkChannelManager,
+    channel_id: &str,
+    policy: &ForwardingPolicy,
+) -> LightningResult<ForwardingPolicy> {
+    if !policy.enabled {
+        return Err(LightningError::ChannelError(
+            "LDK cannot disable forwarding over an open channel".to_string()
+        ));
+    }
+
+    let details = channel_manager.list_channels().into_iter()
+        .find(|details| to_hex(&details.channel_id) == channel_id)
+        .ok_or_else(|| LightningError::ChannelError(
+            format!("Channel {} not found", channel_id)
+        ))?;
+
+    let mut channel_config = details.config.unwrap_or_default();
+    channel_config.forwarding_fee_base_msat = policy.fee_base_msat;
+    channel_config.forwarding_fee_proportional_millionths = policy.fee_proportional_millionths;
+    channel_config.cltv_expiry_delta = policy.cltv_expiry_delta;
+    channel_manager
+        .update_channel_config(&details.counterparty.node_id, &[details.channel_id], &channel_config)
+        .map_err(|e| LightningError::ChannelError(format!("Failed to update channel config: {:?}", e)))?;
+
+    Ok(ForwardingPolicy {
+        htlc_minimum_msat: details.counterparty.outbound_htlc_minimum_msat.unwrap_or(0),
+        htlc_maximum_msat: details.counterparty.outbound_htlc_maximum_msat,
+        ..policy.clone()
+    })
+}
+
+/// Take `amount` sats from a balance, failing instead of wrapping around
+fn debit(balance: u64, amount: u64, channel_id: &str) -> LightningResult<u64> {
+    balance.checked_sub(amount).ok_or_else(|| LightningError::ChannelError(
+        format!("Balance of channel {} cannot cover {} sats", channel_id, amount)
+    ))
+}
+
+/// Our and the peer's balance of a channel in millisatoshis
+///
+/// `ChannelInfo` holds whole satoshis; the millisatoshis below them on our
+/// side are `remainder_msat`, and the peer's side is short by as much.
+fn balances_msat(channel: &ChannelInfo, remainder_msat: u64) -> (u64, u64) {
+    (
+        channel.local_balance * 1000 + remainder_msat,
+        (channel.remote_balance * 1000).saturating_sub(remainder_msat),
+    )
+}
+
+/// Set our side of a channel to `local_msat`, moving the whole satoshis it
+/// gains or loses from or to the peer's side, and return the new remainder
+fn set_local_msat(channel: &mut ChannelInfo, local_msat: u64) -> LightningResult<u64> {
+    let local_sat = local_msat / 1000;
+    if local_sat > channel.local_balance {
+        channel.remote_balance = debit(channel.remote_balance, local_sat - channel.local_balance, &channel.channel_id)?;
+    } else {
+        channel.remote_balance += channel.local_balance - local_sat;
+    }
+    channel.local_balance = local_sat;
+    Ok(local_msat % 1000)
+}
+
+/// Get current timestamp
+fn get_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::bitcoin;
+    use crate::lightning::interface::OpenChannelOptions;
+
+    fn setup() -> (Arc<ChannelManagerWrapper>, ForwardingManager, String, String) {
+        let config = Config::default();
+        let bitcoin_interface = bitcoin::get_current_bitcoin_interface(&config);
+        let channel_manager = Arc::new(ChannelManagerWrapper::new(&config, bitcoin_interface));
+        let router = Arc::new(PaymentRouter::new(&config));
+
+        // Inbound-heavy channel from the payer's side, outbound-heavy channel towards the payee
+        let incoming = channel_manager.open_channel(
+            "03f25d220b14f3daae528bbb98cf142caf3477c8d5258d9f81b0af0370163f0df2",
+            1_000_000,
+            &OpenChannelOptions { push_msat: Some(500_000_000), ..OpenChannelOptions::default() },
+        ).unwrap();
+        let outgoing = channel_manager.open_channel(
+            "035566252e83e2a30ec88140ea7948d505615f057b0e4c186a854cfbef365ea3c5",
+            1_000_000,
+            &OpenChannelOptions::default(),
+        ).unwrap();
+
+        let forwarder = ForwardingManager::new(channel_manager.clone(), router);
+        (channel_manager, forwarder, incoming.channel_id, outgoing.channel_id)
+    }
+
+    #[test]
+    fn test_forward_htlc_earns_fee() {
+        let (channel_manager, forwarder, incoming, outgoing) = setup();
+
+        let policy = forwarder.get_channel_policy(&outgoing).unwrap();
+        let amount_out_msat = 100_000_000;
+        let fee = policy.fee_for_amount(amount_out_msat);
+
+        let event = forwarder.forward_htlc(&IncomingHtlc {
+            incoming_channel_id: incoming.clone(),
+            outgoing_channel_id: outgoing.clone(),
+            payment_hash: "00".repeat(32),
+            amount_in_msat: amount_out_msat + fee,
+            amount_out_msat,
+            incoming_cltv_expiry: 700_100,
+            outgoing_cltv_expiry: 700_100 - policy.cltv_expiry_delta as u32,
+        }).unwrap();
+
+        assert_eq!(event.fee_earned_msat, fee);
+        assert_eq!(forwarder.total_fees_earned_msat(), fee);
+        assert_eq!(forwarder.list_forwards(None, None).len(), 1);
+
+        let outgoing = channel_manager.get_channel(&outgoing).unwrap().unwrap();
+        assert_eq!(outgoing.local_balance, 900_000);
+        assert_eq!(outgoing.remote_balance, 100_000);
+    }
+
+    #[test]
+    fn test_forward_htlc_settles_msat() {
+        let (channel_manager, forwarder, incoming, outgoing) = setup();
+        forwarder.set_channel_policy(&outgoing, ForwardingPolicy {
+            fee_base_msat: 999,
+            fee_proportional_millionths: 0,
+            htlc_minimum_msat: 1,
+            ..ForwardingPolicy::default()
+        }).unwrap();
+        let total_msat = |forwarder: &ForwardingManager| {
+            forwarder.local_balance_msat(&incoming).unwrap() + forwarder.local_balance_msat(&outgoing).unwrap()
+        };
+        let before = total_msat(&forwarder);
+
+        // A fee under a satoshi, then an HTLC under a satoshi
+        for (amount_out_msat, payment_hash) in [(1_000_000, "22"), (500, "33")] {
+            forwarder.forward_htlc(&IncomingHtlc {
+                incoming_channel_id: incoming.clone(),
+                outgoing_channel_id: outgoing.clone(),
+                payment_hash: payment_hash.repeat(32),
+                amount_in_msat: amount_out_msat + 999,
+                amount_out_msat,
+                incoming_cltv_expiry: 700_100,
+                outgoing_cltv_expiry: 700_000,
+            }).unwrap();
+        }
+
+        assert_eq!(forwarder.total_fees_earned_msat(), 1_998);
+        assert_eq!(total_msat(&forwarder), before + 1_998);
+        assert_eq!(forwarder.local_balance_msat(&incoming).unwrap(), 501_002_498);
+        assert_eq!(forwarder.local_balance_msat(&outgoing).unwrap(), 998_999_500);
+
+        // Whole satoshis move with the peer's side, so channels still add up
+        for channel_id in [&incoming, &outgoing] {
+            let channel = channel_manager.get_channel(channel_id).unwrap().unwrap();
+            assert_eq!(channel.local_balance + channel.remote_balance, channel.capacity);
+        }
+        let incoming = channel_manager.get_channel(&incoming).unwrap().unwrap();
+        assert_eq!(incoming.local_balance, 501_002);
+    }
+
+    #[test]
+    fn test_forward_htlc_rejects_bad_fee_and_cltv() {
+        let (channel_manager, forwarder, incoming, outgoing) = setup();
+        let outgoing_before = channel_manager.get_channel(&outgoing).unwrap().unwrap();
+
+        let mut htlc = IncomingHtlc {
+            incoming_channel_id: incoming,
+            outgoing_channel_id: outgoing,
+            payment_hash: "11".repeat(32),
+            amount_in_msat: 10_000_000,
+            amount_out_msat: 10_000_000,
+            incoming_cltv_expiry: 700_100,
+            outgoing_cltv_expiry: 700_000,
+        };
+
+        // No fee offered
+        assert!(forwarder.forward_htlc(&htlc).is_err());
+
+        // Enough fee but CLTV delta too small
+        htlc.amount_in_msat += 100_000;
+        htlc.outgoing_cltv_expiry = 700_090;
+        assert!(forwarder.forward_htlc(&htlc).is_err());
+
+        // More than the outgoing channel holds
+        htlc.outgoing_cltv_expiry = 700_000;
+        htlc.amount_out_msat = 2_000_000_000;
+        htlc.amount_in_msat = 2_001_000_000;
+        assert!(forwarder.forward_htlc(&htlc).is_err());
+
+        assert!(forwarder.list_forwards(None, None).is_empty());
+        let outgoing_after = channel_manager.get_channel(&htlc.outgoing_channel_id).unwrap().unwrap();
+        assert_eq!(outgoing_after.local_balance, outgoing_before.local_balance);
+    }
+
+    #[test]
+    fn test_policy_update_is_broadcast() {
+        let (_, forwarder, _, outgoing) = setup();
+
+        let policy = ForwardingPolicy {
+            fee_base_msat: 0,
+            fee_proportional_millionths: 500,
+            ..ForwardingPolicy::default()
+        };
+
+        let update = forwarder.set_channel_policy(&outgoing, policy.clone()).unwrap();
+        assert_eq!(update.policy, policy);
+        assert_eq!(forwarder.get_channel_policy(&outgoing).unwrap(), policy);
+
+        let pending = forwarder.take_pending_updates();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].channel_id, outgoing);
+        assert!(forwarder.take_pending_updates().is_empty());
+    }
+
+    #[test]
+    fn test_liquidity_based_fees() {
+        let (_, forwarder, incoming, outgoing) = setup();
+
+        forwarder.set_fee_mode(FeeMode::LiquidityBased(AutoFeeConfig::default())).unwrap();
+
+        // The channel with most funds on the remote side should be the most expensive
+        let incoming_policy = forwarder.get_channel_policy(&incoming).unwrap();
+        let outgoing_policy = forwarder.get_channel_policy(&outgoing).unwrap();
+        assert!(incoming_policy.fee_proportional_millionths > outgoing_policy.fee_proportional_millionths);
+        assert_eq!(outgoing_policy.htlc_maximum_msat, Some(250_000_000));
+    }
+}
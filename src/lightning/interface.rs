@@ -5,7 +5,12 @@
 // allowing different implementations to be swapped while maintaining a consistent API.
 
 use std::sync::Arc;
+use std::collections::BTreeMap;
 use crate::bitcoin::BitcoinResult;
+use crate::lightning::forwarding::{ForwardingPolicy, FeeMode, ChannelUpdate, ForwardingEvent};
+use crate::lightning::rebalancer::{RebalancePolicy, RebalanceResult, RebalanceReport};
+use crate::lightning::invoice_manager::InvoiceState;
+use crate::lightning::key_manager::KeyFamily;
 
 /// Lightning implementation type selection enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -79,6 +84,52 @@ pub struct ChannelInfo {
     pub is_public: bool,
     /// Short channel ID (once confirmed)
     pub short_channel_id: Option<String>,
+    /// Commitment format negotiated for the channel
+    pub channel_type: ChannelType,
+    /// Whether the channel was usable before its funding transaction confirmed
+    pub is_zero_conf: bool,
+    /// Splice waiting to confirm; the channel keeps using the current funding output meanwhile
+    pub pending_splice: Option<PendingSplice>,
+}
+
+/// A splice transaction that has been broadcast but not yet locked in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSplice {
+    /// Splice transaction ID, the channel's funding transaction once locked in
+    pub splice_txid: String,
+    /// Index of the new funding output
+    pub funding_output_idx: u32,
+    /// Channel capacity once locked in, in satoshis
+    pub capacity: u64,
+    /// Satoshis added to our balance once locked in (splice-in by us)
+    pub local_added_sat: u64,
+    /// Satoshis added to the peer's balance once locked in (splice-in by the peer)
+    pub remote_added_sat: u64,
+}
+
+/// Commitment format of a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelType {
+    /// Static remote key commitments with fees paid up front by the funder
+    #[default]
+    StaticRemoteKey,
+    /// Anchor outputs, so either side can CPFP the commitment transaction on close
+    AnchorOutputs,
+}
+
+/// Options for opening a channel
+#[derive(Debug, Clone, Default)]
+pub struct OpenChannelOptions {
+    /// Amount to push to the peer on open, in millisatoshis
+    pub push_msat: Option<u64>,
+    /// Keep the channel out of gossip
+    pub is_private: bool,
+    /// Commitment format to negotiate
+    pub channel_type: ChannelType,
+    /// Use the channel before the funding transaction confirms (the peer must be trusted)
+    pub zero_conf: bool,
+    /// Amount the peer contributes with its own inputs (v2 interactive dual funding), in satoshis
+    pub remote_funding_sat: Option<u64>,
 }
 
 /// Lightning Network invoice
@@ -90,6 +141,8 @@ pub struct Invoice {
     pub payment_hash: String,
     /// Description
     pub description: String,
+    /// SHA256 of the description (hex), for invoices committing to a hash instead
+    pub description_hash: Option<String>,
     /// Amount in millisatoshis
     pub amount_msat: Option<u64>,
     /// Expiry time in seconds from creation
@@ -121,6 +174,8 @@ pub struct PaymentInfo {
     pub resolved_at: Option<u64>,
     /// Payment description or purpose
     pub description: Option<String>,
+    /// Custom TLV records carried in the final hop onion payload (type -> value)
+    pub custom_records: BTreeMap<u64, Vec<u8>>,
 }
 
 /// Payment status enum
@@ -139,19 +194,36 @@ pub trait LightningInterface: Send + Sync {
     /// Get information about the local node
     fn get_node_info(&self) -> LightningResult<NodeInfo>;
     
+    /// Decrypt the node seed with its passphrase
+    fn unlock(&self, passphrase: &str) -> LightningResult<()>;
+    
+    /// Forget the decrypted node seed
+    fn lock(&self) -> LightningResult<()>;
+    
+    /// Re-encrypt the node seed under a new passphrase
+    fn change_passphrase(&self, old_passphrase: &str, new_passphrase: &str) -> LightningResult<()>;
+    
+    /// Replace the keys of a non-identity key family, returning its new generation
+    fn rotate_keys(&self, family: KeyFamily) -> LightningResult<u32>;
+    
+    /// Sign a message with the node key (lnd `signmessage` format, z-base-32)
+    fn sign_message(&self, message: &str) -> LightningResult<String>;
+    
+    /// Recover the pubkey of the node that signed a message
+    fn verify_message(&self, message: &str, signature: &str) -> LightningResult<String>;
+    
     /// Connect to a remote node
     fn connect_peer(&self, node_pubkey: &str, host: &str, port: u16) -> LightningResult<()>;
     
     /// List connected peers
     fn list_peers(&self) -> LightningResult<Vec<NodeInfo>>;
     
-    /// Open a channel with a peer
+    /// Open a channel with a peer, funding `capacity` satoshis from our wallet
     fn open_channel(
         &self,
         node_pubkey: &str,
         capacity: u64,
-        push_msat: Option<u64>,
-        is_private: bool,
+        options: OpenChannelOptions,
     ) -> LightningResult<ChannelInfo>;
     
     /// List all channels
@@ -160,6 +232,15 @@ fn open_channel(
     /// Close a channel
     fn close_channel(&self, channel_id: &str, force: bool) -> LightningResult<String>;
     
+    /// CPFP a force-closed anchor channel's commitment up to `fee_rate` sat/vB, returning the child txid
+    fn bump_commitment_fee(&self, channel_id: &str, fee_rate: u64) -> LightningResult<String>;
+    
+    /// Add on-chain funds to a channel without closing it
+    fn splice_in(&self, channel_id: &str, amount_sat: u64) -> LightningResult<ChannelInfo>;
+    
+    /// Pay an on-chain address from our channel balance without closing the channel
+    fn splice_out(&self, channel_id: &str, address: &str, amount_sat: u64) -> LightningResult<ChannelInfo>;
+    
     /// Create an invoice
     fn create_invoice(
         &self,
@@ -168,9 +249,55 @@ fn create_invoice(
         expiry: Option<u32>,
     ) -> LightningResult<Invoice>;
     
+    /// Create an invoice committing to a description hash (as LNURL-pay services do)
+    fn create_invoice_with_description_hash(
+        &self,
+        amount_msat: Option<u64>,
+        description_hash: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice>;
+    
+    /// Create a hold invoice for a payment hash; HTLCs are held until settled or cancelled
+    fn create_hold_invoice(
+        &self,
+        payment_hash: &str,
+        amount_msat: Option<u64>,
+        description: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice>;
+    
+    /// Settle an accepted hold invoice with its preimage
+    fn settle_invoice(&self, payment_hash: &str, preimage: &str) -> LightningResult<()>;
+    
+    /// Cancel an open or accepted invoice
+    fn cancel_invoice(&self, payment_hash: &str) -> LightningResult<()>;
+    
+    /// Get the lifecycle state of one of our invoices
+    fn get_invoice_state(&self, payment_hash: &str) -> LightningResult<InvoiceState>;
+    
     /// Pay an invoice
     fn pay_invoice(&self, bolt11: &str, amount_msat: Option<u64>) -> LightningResult<PaymentInfo>;
     
+    /// Pay an LNURL-pay or Lightning Address, optionally attaching a comment
+    fn pay_lnurl(&self, target: &str, amount_msat: u64, comment: Option<&str>) -> LightningResult<PaymentInfo>;
+    
+    /// Withdraw from an LNURL-withdraw service (the maximum if no amount is given)
+    fn withdraw_lnurl(&self, lnurl: &str, amount_msat: Option<u64>) -> LightningResult<Invoice>;
+    
+    /// Log in to an LNURL-auth service, returning the linking public key used
+    fn lnurl_auth(&self, lnurl: &str) -> LightningResult<String>;
+    
+    /// Send a spontaneous (keysend) payment, optionally carrying custom TLV records
+    fn keysend(
+        &self,
+        node_pubkey: &str,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo>;
+    
+    /// List payments received by our node, including their custom TLV records
+    fn list_received_payments(&self) -> LightningResult<Vec<PaymentInfo>>;
+    
     /// Decode an invoice
     fn decode_invoice(&self, bolt11: &str) -> LightningResult<Invoice>;
     
@@ -180,17 +307,61 @@ fn create_invoice(
     /// List all payments
     fn list_payments(&self) -> LightningResult<Vec<PaymentInfo>>;
     
+    /// Get the forwarding policy of a channel
+    fn get_channel_policy(&self, channel_id: &str) -> LightningResult<ForwardingPolicy>;
+    
+    /// Update the forwarding policy of one channel, or of all channels if none is given
+    fn update_channel_policy(
+        &self,
+        channel_id: Option<&str>,
+        policy: ForwardingPolicy,
+    ) -> LightningResult<Vec<ChannelUpdate>>;
+    
+    /// Set how routing fees are managed (static or liquidity-based)
+    fn set_fee_mode(&self, mode: FeeMode) -> LightningResult<Vec<ChannelUpdate>>;
+    
+    /// List forwarded HTLCs and the fees earned on them
+    fn list_forwards(&self) -> LightningResult<Vec<ForwardingEvent>>;
+    
+    /// Move liquidity from one of our channels to another with a circular self-payment
+    fn rebalance_channel(
+        &self,
+        source_channel_id: &str,
+        target_channel_id: &str,
+        amount_sat: u64,
+        max_fee_msat: u64,
+    ) -> LightningResult<RebalanceResult>;
+    
+    /// Bring channels within the policy's balance ratios (or only report the plan on a dry run)
+    fn run_rebalance_autopilot(
+        &self,
+        policy: &RebalancePolicy,
+        dry_run: bool,
+    ) -> LightningResult<RebalanceReport>;
+    
     /// Implementation type
     fn implementation_type(&self) -> LightningImplementationType;
 }
 
 /// Factory to create the appropriate Lightning implementation
+///
+/// Refuses to fall back to the mock implementation on mainnet.
 pub fn create_lightning_interface(
     implementation_type: LightningImplementationType,
     config: &crate::config::Config,
     bitcoin_interface: Arc<dyn crate::bitcoin::BitcoinInterface>,
-) -> Arc<dyn LightningInterface> {
-    match implementation_type {
+) -> LightningResult<Arc<dyn LightningInterface>> {
+    let uses_mock = match implementation_type {
+        LightningImplementationType::Mock => cfg!(feature = "mock-lightning"),
+        LightningImplementationType::LDK => !cfg!(feature = "ldk"),
+    };
+    if uses_mock && config.bitcoin_network.as_deref() == Some("mainnet") {
+        return Err(LightningError::ImplementationError(
+            "The mock Lightning implementation cannot be used on mainnet".to_string()
+        ));
+    }
+    
+    let lightning: Arc<dyn LightningInterface> = match implementation_type {
         #[cfg(feature = "ldk")]
         LightningImplementationType::LDK => {
             use crate::lightning::ldk::LdkLightningImplementation;
@@ -216,14 +387,16 @@ pub fn create_lightning_interface(
             use crate::lightning::ldk::LdkLightningImplementation;
             Arc::new(LdkLightningImplementation::new(config, bitcoin_interface))
         }
-    }
+    };
+    
+    Ok(lightning)
 }
 
 /// Get the current default Lightning implementation
 pub fn get_current_lightning_interface(
     config: &crate::config::Config,
     bitcoin_interface: Arc<dyn crate::bitcoin::BitcoinInterface>,
-) -> Arc<dyn LightningInterface> {
+) -> LightningResult<Arc<dyn LightningInterface>> {
     // Check if the LDK implementation is available and preferred
     #[cfg(feature = "ldk")]
     {
@@ -254,6 +427,6 @@ fn test_interface_creation() {
         let bitcoin_interface = crate::bitcoin::get_current_bitcoin_interface(&config);
         
         // This will use whatever implementation is available based on features
-        let _lightning = get_current_lightning_interface(&config, bitcoin_interface);
+        let _lightning = get_current_lightning_interface(&config, bitcoin_interface).unwrap();
     }
 } 
\ No newline at end of file
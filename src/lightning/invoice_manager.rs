@@ -2,31 +2,49 @@
 // Handles invoice creation, parsing, and storage
 
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 
+use sha2::{Digest, Sha256};
+
 use crate::lightning::interface::{
     LightningError, LightningResult, Invoice
 };
 
+#[cfg(feature = "ldk")]
+use std::str::FromStr;
+
+#[cfg(feature = "ldk")]
+use std::sync::Weak;
+
 #[cfg(feature = "ldk")]
 use lightning_invoice::{
-    Invoice as LdkInvoice, 
-    InvoiceBuilder, 
-    Currency, 
-    Description, 
-    Bolt11InvoiceDescription,
-    SignOrCreationError,
-    utils::create_invoice_for_network, 
+    Bolt11Invoice as LdkInvoice, 
+    Bolt11InvoiceDescription as InvoiceDescription,
 };
 
 #[cfg(feature = "ldk")]
-use bitcoin::secp256k1::{
-    Secp256k1, SecretKey
-};
+use lightning::bitcoin::hashes::Hash;
+
+#[cfg(feature = "ldk")]
+use crate::bitcoin::encoding::to_hex;
+
+#[cfg(feature = "ldk")]
+use crate::lightning::ldk_node::LdkNode;
 
 use crate::lightning::key_manager::KeyManagerWrapper;
 
+use crate::bitcoin::BitcoinInterface;
+
+/// Blocks before a held HTLC's CLTV expiry at which it is failed back
+pub const HELD_HTLC_CANCEL_DELTA: u32 = 12;
+
+/// How often invoice expiry and held-HTLC deadlines are checked
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(10);
+
 /// Invoice Manager component for handling Lightning invoices
 pub struct InvoiceManager {
     /// Stored invoices
@@ -35,10 +53,85 @@ pub struct InvoiceManager {
     /// Key manager for signing invoices
     key_manager: Arc<KeyManagerWrapper>,
     
+    /// Subscribers notified on invoice state changes
+    subscribers: Mutex<Vec<Sender<InvoiceUpdate>>>,
+    
+    /// Running LDK node used to sign BOLT11 invoices
+    #[cfg(feature = "ldk")]
+    ldk_node: Mutex<Option<Weak<LdkNode>>>,
+    
     /// Configuration
     config: Arc<crate::config::Config>,
 }
 
+/// Invoice lifecycle state
+///
+/// Open -> Accepted -> Settled, with Cancelled reachable from Open or Accepted
+/// and Expired reachable from Open. Regular invoices settle as soon as they are
+/// fully paid; hold invoices stop at Accepted until explicitly settled or cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceState {
+    /// Waiting for payment
+    Open,
+    
+    /// HTLCs covering the amount are held, waiting for settle or cancel
+    Accepted,
+    
+    /// Preimage released, payment complete
+    Settled,
+    
+    /// Cancelled, HTLCs failed back
+    Cancelled,
+    
+    /// Expired before being paid
+    Expired,
+}
+
+/// State of an HTLC paying an invoice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceHtlcState {
+    /// HTLC is held by us
+    Accepted,
+    
+    /// HTLC was settled with the preimage
+    Settled,
+    
+    /// HTLC was failed back to the sender
+    Cancelled,
+}
+
+/// An HTLC received for an invoice
+#[derive(Debug, Clone)]
+pub struct InvoiceHtlc {
+    /// Channel the HTLC arrived on
+    pub channel_id: String,
+    
+    /// HTLC ID within the channel
+    pub htlc_id: u64,
+    
+    /// HTLC amount in millisatoshis
+    pub amount_msat: u64,
+    
+    /// Absolute CLTV expiry of the HTLC
+    pub cltv_expiry: u32,
+    
+    /// HTLC state
+    pub state: InvoiceHtlcState,
+}
+
+/// Notification sent to subscribers when an invoice changes state
+#[derive(Debug, Clone)]
+pub struct InvoiceUpdate {
+    /// Payment hash of the invoice
+    pub payment_hash: String,
+    
+    /// New state
+    pub state: InvoiceState,
+    
+    /// Total amount of held or settled HTLCs in millisatoshis
+    pub amount_received_msat: u64,
+}
+
 /// Invoice with additional status information
 #[derive(Clone, Debug)]
 pub struct InvoiceWithStatus {
@@ -53,6 +146,60 @@ pub struct InvoiceWithStatus {
     
     /// The preimage that was revealed (if paid)
     pub payment_preimage: Option<String>,
+    
+    /// Lifecycle state
+    pub state: InvoiceState,
+    
+    /// Whether this is a hold invoice (settled explicitly by the caller)
+    pub is_hold: bool,
+    
+    /// HTLCs received for this invoice
+    pub htlcs: Vec<InvoiceHtlc>,
+    
+    /// Preimage known to us (None for hold invoices until settled)
+    preimage: Option<String>,
+}
+
+impl InvoiceWithStatus {
+    /// Create a status record for a newly created invoice
+    fn new(invoice: Invoice, preimage: Option<String>) -> Self {
+        InvoiceWithStatus {
+            is_hold: preimage.is_none(),
+            invoice,
+            is_paid: false,
+            paid_at: None,
+            payment_preimage: None,
+            state: InvoiceState::Open,
+            htlcs: Vec::new(),
+            preimage,
+        }
+    }
+    
+    /// Total amount of HTLCs that are held or settled
+    pub fn amount_received_msat(&self) -> u64 {
+        self.htlcs.iter()
+            .filter(|htlc| htlc.state != InvoiceHtlcState::Cancelled)
+            .map(|htlc| htlc.amount_msat)
+            .sum()
+    }
+    
+    /// Move every held HTLC to a final state
+    fn resolve_htlcs(&mut self, state: InvoiceHtlcState) {
+        for htlc in self.htlcs.iter_mut() {
+            if htlc.state == InvoiceHtlcState::Accepted {
+                htlc.state = state;
+            }
+        }
+    }
+    
+    /// Settle the invoice with a preimage
+    fn settle(&mut self, preimage: &str, now: u64) {
+        self.state = InvoiceState::Settled;
+        self.is_paid = true;
+        self.paid_at = Some(now);
+        self.payment_preimage = Some(preimage.to_string());
+        self.resolve_htlcs(InvoiceHtlcState::Settled);
+    }
 }
 
 impl InvoiceManager {
@@ -61,10 +208,27 @@ pub fn new(config: &crate::config::Config, key_manager: Arc<KeyManagerWrapper>)
         InvoiceManager {
             invoices: Mutex::new(HashMap::new()),
             key_manager,
+            subscribers: Mutex::new(Vec::new()),
+            #[cfg(feature = "ldk")]
+            ldk_node: Mutex::new(None),
             config: Arc::new(config.clone()),
         }
     }
     
+    /// Attach the running LDK node
+    ///
+    /// Once attached, new invoices are real BOLT11 invoices signed with the node key
+    /// and registered with the LDK ChannelManager.
+    #[cfg(feature = "ldk")]
+    pub fn attach_ldk(&self, ldk_node: Weak<LdkNode>) {
+        *self.ldk_node.lock().unwrap() = Some(ldk_node);
+    }
+    
+    /// Public key of the node our invoices pay to
+    pub fn node_pubkey(&self) -> LightningResult<String> {
+        Ok(self.key_manager.get_node_info()?.pubkey)
+    }
+    
     /// Create a new invoice
     pub fn create_invoice(
         &self,
@@ -77,17 +241,15 @@ pub fn create_invoice(
         
         #[cfg(feature = "ldk")]
         {
-            // This would be implemented with actual LDK invoice creation logic
-            // using the key_manager to sign the invoice
-            
-            // For now, create a mock invoice
-            let payment_hash = generate_random_bytes_hex(32);
-            let bolt11 = self.generate_mock_bolt11(amount_msat, &payment_hash, description);
+            let preimage = generate_random_bytes_hex(32);
+            let payment_hash = payment_hash_for_preimage(&preimage)?;
+            let bolt11 = self.encode_bolt11(amount_msat, &payment_hash, description, expiry_time)?;
             
             let invoice = Invoice {
                 bolt11,
                 payment_hash,
                 description: description.to_string(),
+                description_hash: None,
                 amount_msat,
                 expiry: expiry_time,
                 timestamp: current_time,
@@ -96,12 +258,10 @@ pub fn create_invoice(
             
             // Store the invoice
             let mut invoices = self.invoices.lock().unwrap();
-            invoices.insert(invoice.payment_hash.clone(), InvoiceWithStatus {
-                invoice: invoice.clone(),
-                is_paid: false,
-                paid_at: None,
-                payment_preimage: None,
-            });
+            invoices.insert(
+                invoice.payment_hash.clone(),
+                InvoiceWithStatus::new(invoice.clone(), Some(preimage))
+            );
             
             Ok(invoice)
         }
@@ -109,13 +269,15 @@ pub fn create_invoice(
         #[cfg(not(feature = "ldk"))]
         {
             // Create a mock invoice
-            let payment_hash = generate_random_bytes_hex(32);
+            let preimage = generate_random_bytes_hex(32);
+            let payment_hash = payment_hash_for_preimage(&preimage)?;
             let bolt11 = self.generate_mock_bolt11(amount_msat, &payment_hash, description);
             
             let invoice = Invoice {
                 bolt11,
                 payment_hash,
                 description: description.to_string(),
+                description_hash: None,
                 amount_msat,
                 expiry: expiry_time,
                 timestamp: current_time,
@@ -124,56 +286,356 @@ pub fn create_invoice(
             
             // Store the invoice
             let mut invoices = self.invoices.lock().unwrap();
-            invoices.insert(invoice.payment_hash.clone(), InvoiceWithStatus {
-                invoice: invoice.clone(),
-                is_paid: false,
-                paid_at: None,
-                payment_preimage: None,
-            });
+            invoices.insert(
+                invoice.payment_hash.clone(),
+                InvoiceWithStatus::new(invoice.clone(), Some(preimage))
+            );
             
             Ok(invoice)
         }
     }
     
+    /// Create an invoice committing to the SHA256 of a description
+    ///
+    /// LNURL-pay services (LUD-06) commit to the hash of their metadata this way.
+    pub fn create_invoice_with_description_hash(
+        &self,
+        amount_msat: Option<u64>,
+        description_hash: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice> {
+        let description_hash = description_hash.to_lowercase();
+        if decode_hex(&description_hash).map(|bytes| bytes.len()) != Some(32) {
+            return Err(LightningError::InvoiceError(
+                format!("Invalid description hash: {}", description_hash)
+            ));
+        }
+        
+        let preimage = generate_random_bytes_hex(32);
+        let payment_hash = payment_hash_for_preimage(&preimage)?;
+        let expiry = expiry.unwrap_or(3600);
+        let invoice = Invoice {
+            bolt11: self.encode_bolt11_with_description_hash(amount_msat, &payment_hash, expiry)?,
+            payment_hash,
+            description: String::new(),
+            description_hash: Some(description_hash),
+            amount_msat,
+            expiry,
+            timestamp: self.get_timestamp(),
+            min_final_cltv_expiry: 40,
+        };
+        
+        let mut invoices = self.invoices.lock().unwrap();
+        invoices.insert(
+            invoice.payment_hash.clone(),
+            InvoiceWithStatus::new(invoice.clone(), Some(preimage))
+        );
+        
+        Ok(invoice)
+    }
+    
+    /// Create a hold invoice for a payment hash whose preimage only the caller knows
+    ///
+    /// Incoming HTLCs are held (state Accepted) until `settle_invoice` is called with
+    /// the preimage or `cancel_invoice` fails them back.
+    pub fn create_hold_invoice(
+        &self,
+        payment_hash: &str,
+        amount_msat: Option<u64>,
+        description: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice> {
+        let payment_hash = payment_hash.to_lowercase();
+        if decode_hex(&payment_hash).map(|bytes| bytes.len()) != Some(32) {
+            return Err(LightningError::InvoiceError(
+                format!("Invalid payment hash: {}", payment_hash)
+            ));
+        }
+        
+        let mut invoices = self.invoices.lock().unwrap();
+        if invoices.contains_key(&payment_hash) {
+            return Err(LightningError::InvoiceError(
+                format!("Invoice already exists for payment hash {}", payment_hash)
+            ));
+        }
+        
+        let expiry = expiry.unwrap_or(3600);
+        let invoice = Invoice {
+            bolt11: self.encode_bolt11(amount_msat, &payment_hash, description, expiry)?,
+            payment_hash: payment_hash.clone(),
+            description: description.to_string(),
+            description_hash: None,
+            amount_msat,
+            expiry,
+            timestamp: self.get_timestamp(),
+            min_final_cltv_expiry: 40,
+        };
+        
+        invoices.insert(payment_hash, InvoiceWithStatus::new(invoice.clone(), None));
+        
+        Ok(invoice)
+    }
+    
+    /// Handle an HTLC arriving for one of our invoices
+    ///
+    /// Expired invoices reject the HTLC and move to Expired. Partial payments keep the
+    /// invoice Open until the remaining parts arrive; anything over twice the invoice
+    /// amount is rejected. Once the amount is covered, regular invoices settle and
+    /// hold invoices move to Accepted. Returns the resulting invoice state.
+    pub fn receive_htlc(
+        &self,
+        payment_hash: &str,
+        channel_id: &str,
+        htlc_id: u64,
+        amount_msat: u64,
+        cltv_expiry: u32,
+    ) -> LightningResult<InvoiceState> {
+        let payment_hash = &payment_hash.to_lowercase();
+        let now = self.get_timestamp();
+        let mut invoices = self.invoices.lock().unwrap();
+        
+        let invoice_status = invoices.get_mut(payment_hash).ok_or_else(|| {
+            LightningError::InvoiceError(format!("Invoice not found: {}", payment_hash))
+        })?;
+        
+        if invoice_status.state == InvoiceState::Open && invoice_expired(&invoice_status.invoice, now) {
+            invoice_status.state = InvoiceState::Expired;
+            invoice_status.resolve_htlcs(InvoiceHtlcState::Cancelled);
+            let update = invoice_update(invoice_status);
+            drop(invoices);
+            self.notify(update);
+            
+            return Err(LightningError::InvoiceError(
+                format!("Invoice expired: {}", payment_hash)
+            ));
+        }
+        
+        if invoice_status.state != InvoiceState::Open {
+            return Err(LightningError::InvoiceError(
+                format!("Invoice {} is {:?} and cannot accept payments", payment_hash, invoice_status.state)
+            ));
+        }
+        
+        let total_msat = invoice_status.amount_received_msat() + amount_msat;
+        
+        if let Some(invoice_amount) = invoice_status.invoice.amount_msat {
+            if total_msat > invoice_amount * 2 {
+                return Err(LightningError::InvoiceError(
+                    format!("Overpayment: received {} msat for invoice of {} msat", total_msat, invoice_amount)
+                ));
+            }
+        }
+        
+        invoice_status.htlcs.push(InvoiceHtlc {
+            channel_id: channel_id.to_string(),
+            htlc_id,
+            amount_msat,
+            cltv_expiry,
+            state: InvoiceHtlcState::Accepted,
+        });
+        
+        let fully_paid = match invoice_status.invoice.amount_msat {
+            Some(invoice_amount) => total_msat >= invoice_amount,
+            None => total_msat > 0,
+        };
+        
+        if !fully_paid {
+            // Underpaid so far: hold the part and wait for the rest of the payment
+            return Ok(InvoiceState::Open);
+        }
+        
+        match invoice_status.preimage.clone() {
+            Some(preimage) => invoice_status.settle(&preimage, now),
+            None => invoice_status.state = InvoiceState::Accepted,
+        }
+        
+        let update = invoice_update(invoice_status);
+        drop(invoices);
+        self.notify(update.clone());
+        
+        Ok(update.state)
+    }
+    
+    /// Settle an accepted hold invoice by revealing its preimage
+    pub fn settle_invoice(&self, payment_hash: &str, preimage: &str) -> LightningResult<()> {
+        let payment_hash = &payment_hash.to_lowercase();
+        if payment_hash_for_preimage(preimage)? != *payment_hash {
+            return Err(LightningError::InvoiceError(
+                format!("Preimage does not match payment hash {}", payment_hash)
+            ));
+        }
+        
+        let mut invoices = self.invoices.lock().unwrap();
+        let invoice_status = invoices.get_mut(payment_hash).ok_or_else(|| {
+            LightningError::InvoiceError(format!("Invoice not found: {}", payment_hash))
+        })?;
+        
+        if invoice_status.state != InvoiceState::Accepted {
+            return Err(LightningError::InvoiceError(
+                format!("Invoice {} is {:?}, only accepted invoices can be settled", payment_hash, invoice_status.state)
+            ));
+        }
+        
+        invoice_status.settle(preimage, self.get_timestamp());
+        let update = invoice_update(invoice_status);
+        drop(invoices);
+        self.notify(update);
+        
+        Ok(())
+    }
+    
+    /// Cancel an open or accepted invoice, failing back any held HTLCs
+    pub fn cancel_invoice(&self, payment_hash: &str) -> LightningResult<()> {
+        let payment_hash = &payment_hash.to_lowercase();
+        let mut invoices = self.invoices.lock().unwrap();
+        let invoice_status = invoices.get_mut(payment_hash).ok_or_else(|| {
+            LightningError::InvoiceError(format!("Invoice not found: {}", payment_hash))
+        })?;
+        
+        match invoice_status.state {
+            InvoiceState::Open | InvoiceState::Accepted => {
+                invoice_status.state = InvoiceState::Cancelled;
+                invoice_status.resolve_htlcs(InvoiceHtlcState::Cancelled);
+            }
+            state => return Err(LightningError::InvoiceError(
+                format!("Invoice {} is {:?} and cannot be cancelled", payment_hash, state)
+            )),
+        }
+        
+        let update = invoice_update(invoice_status);
+        drop(invoices);
+        self.notify(update);
+        
+        Ok(())
+    }
+    
+    /// Move every open invoice past its expiry to Expired, returning their payment hashes
+    pub fn expire_invoices(&self) -> Vec<String> {
+        let now = self.get_timestamp();
+        let mut updates = Vec::new();
+        
+        {
+            let mut invoices = self.invoices.lock().unwrap();
+            for invoice_status in invoices.values_mut() {
+                if invoice_status.state == InvoiceState::Open && invoice_expired(&invoice_status.invoice, now) {
+                    invoice_status.state = InvoiceState::Expired;
+                    invoice_status.resolve_htlcs(InvoiceHtlcState::Cancelled);
+                    updates.push(invoice_update(invoice_status));
+                }
+            }
+        }
+        
+        let expired = updates.iter().map(|update| update.payment_hash.clone()).collect();
+        for update in updates {
+            self.notify(update);
+        }
+        
+        expired
+    }
+    
+    /// Cancel held HTLCs that are about to reach their CLTV expiry, returning the affected payment hashes
+    ///
+    /// An HTLC still held `HELD_HTLC_CANCEL_DELTA` blocks before its expiry is failed
+    /// back so the channel is not force-closed. Accepted hold invoices are cancelled
+    /// outright; open invoices drop the expiring parts of a partial payment.
+    pub fn cancel_expiring_htlcs(&self, current_height: u32) -> Vec<String> {
+        let mut updates = Vec::new();
+        
+        {
+            let mut invoices = self.invoices.lock().unwrap();
+            for invoice_status in invoices.values_mut() {
+                let expiring = |htlc: &InvoiceHtlc| {
+                    htlc.state == InvoiceHtlcState::Accepted &&
+                        htlc.cltv_expiry.saturating_sub(HELD_HTLC_CANCEL_DELTA) <= current_height
+                };
+                if !invoice_status.htlcs.iter().any(expiring) {
+                    continue;
+                }
+                
+                match invoice_status.state {
+                    InvoiceState::Accepted => {
+                        invoice_status.state = InvoiceState::Cancelled;
+                        invoice_status.resolve_htlcs(InvoiceHtlcState::Cancelled);
+                    }
+                    InvoiceState::Open => {
+                        for htlc in invoice_status.htlcs.iter_mut().filter(|htlc| expiring(htlc)) {
+                            htlc.state = InvoiceHtlcState::Cancelled;
+                        }
+                    }
+                    _ => continue,
+                }
+                updates.push(invoice_update(invoice_status));
+            }
+        }
+        
+        let cancelled = updates.iter().map(|update| update.payment_hash.clone()).collect();
+        for update in updates {
+            self.notify(update);
+        }
+        
+        cancelled
+    }
+    
+    /// Get the state of an invoice
+    pub fn get_invoice_state(&self, payment_hash: &str) -> LightningResult<InvoiceState> {
+        let payment_hash = &payment_hash.to_lowercase();
+        let invoices = self.invoices.lock().unwrap();
+        
+        match invoices.get(payment_hash) {
+            Some(invoice_status) => Ok(invoice_status.state),
+            None => Err(LightningError::InvoiceError(
+                format!("Invoice not found: {}", payment_hash)
+            )),
+        }
+    }
+    
+    /// Get an invoice with its status and HTLCs
+    pub fn get_invoice_status(&self, payment_hash: &str) -> LightningResult<Option<InvoiceWithStatus>> {
+        let payment_hash = &payment_hash.to_lowercase();
+        let invoices = self.invoices.lock().unwrap();
+        Ok(invoices.get(payment_hash).cloned())
+    }
+    
+    /// Subscribe to invoice state changes
+    pub fn subscribe(&self) -> Receiver<InvoiceUpdate> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+    
+    /// Send an update to all live subscribers
+    fn notify(&self, update: InvoiceUpdate) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(update.clone()).is_ok());
+    }
+    
     /// Parse/decode a BOLT11 invoice
     pub fn decode_invoice(&self, bolt11: &str) -> LightningResult<Invoice> {
+        // Invoices we issued ourselves decode to the stored record
+        if let Some(invoice) = self.find_invoice_by_bolt11(bolt11) {
+            return Ok(invoice);
+        }
+        
         #[cfg(feature = "ldk")]
         {
-            // In a real implementation, we would use LDK to parse the invoice
-            // For now, create a fake decoded invoice
+            let invoice = LdkInvoice::from_str(bolt11).map_err(|e| {
+                LightningError::InvoiceError(format!("Invalid invoice: {:?}", e))
+            })?;
             
-            // Try to extract some basic info from the BOLT11 string
-            let has_amount = bolt11.contains('m');
-            let amount_msat = if has_amount {
-                // Simple mock extraction
-                if let Some(start) = bolt11.find("lnbc") {
-                    let amount_str = bolt11[start+4..].chars()
-                        .take_while(|c| c.is_digit(10) || *c == 'm')
-                        .collect::<String>();
-                    
-                    if amount_str.ends_with('m') {
-                        let amount = amount_str[..amount_str.len()-1].parse::<u64>().unwrap_or(0);
-                        Some(amount)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
+            let (description, description_hash) = match invoice.description() {
+                InvoiceDescription::Direct(description) => (description.to_string(), None),
+                InvoiceDescription::Hash(hash) => (String::new(), Some(to_hex(&hash.0.into_inner()))),
             };
             
-            let payment_hash = generate_random_bytes_hex(32);
-            
             Ok(Invoice {
                 bolt11: bolt11.to_string(),
-                payment_hash,
-                description: "Decoded invoice".to_string(),
-                amount_msat,
-                expiry: 3600,
-                timestamp: self.get_timestamp(),
-                min_final_cltv_expiry: 40,
+                payment_hash: to_hex(&invoice.payment_hash().into_inner()),
+                description,
+                description_hash,
+                amount_msat: invoice.amount_milli_satoshis(),
+                expiry: invoice.expiry_time().as_secs() as u32,
+                timestamp: invoice.duration_since_epoch().as_secs(),
+                min_final_cltv_expiry: invoice.min_final_cltv_expiry_delta() as u32,
             })
         }
         
@@ -185,7 +647,7 @@ pub fn decode_invoice(&self, bolt11: &str) -> LightningResult<Invoice> {
                 // Simple mock extraction
                 if let Some(start) = bolt11.find("lnbc") {
                     let amount_str = bolt11[start+4..].chars()
-                        .take_while(|c| c.is_digit(10) || *c == 'm')
+                        .take_while(|c| c.is_ascii_digit() || *c == 'm')
                         .collect::<String>();
                     
                     if amount_str.ends_with('m') {
@@ -207,6 +669,7 @@ pub fn decode_invoice(&self, bolt11: &str) -> LightningResult<Invoice> {
                 bolt11: bolt11.to_string(),
                 payment_hash,
                 description: "Decoded invoice".to_string(),
+                description_hash: None,
                 amount_msat,
                 expiry: 3600,
                 timestamp: self.get_timestamp(),
@@ -215,14 +678,24 @@ pub fn decode_invoice(&self, bolt11: &str) -> LightningResult<Invoice> {
         }
     }
     
+    /// Find one of our invoices by its BOLT11 string
+    pub fn find_invoice_by_bolt11(&self, bolt11: &str) -> Option<Invoice> {
+        let invoices = self.invoices.lock().unwrap();
+        invoices.values()
+            .find(|invoice_status| invoice_status.invoice.bolt11 == bolt11)
+            .map(|invoice_status| invoice_status.invoice.clone())
+    }
+    
     /// Check if an invoice exists
     pub fn has_invoice(&self, payment_hash: &str) -> bool {
+        let payment_hash = &payment_hash.to_lowercase();
         let invoices = self.invoices.lock().unwrap();
         invoices.contains_key(payment_hash)
     }
     
     /// Get an invoice by payment hash
     pub fn get_invoice(&self, payment_hash: &str) -> LightningResult<Option<Invoice>> {
+        let payment_hash = &payment_hash.to_lowercase();
         let invoices = self.invoices.lock().unwrap();
         Ok(invoices.get(payment_hash).map(|i| i.invoice.clone()))
     }
@@ -239,13 +712,32 @@ pub fn mark_invoice_paid(
         payment_hash: &str, 
         payment_preimage: &str
     ) -> LightningResult<()> {
+        let payment_hash = &payment_hash.to_lowercase();
+        if payment_hash_for_preimage(payment_preimage)? != *payment_hash {
+            return Err(LightningError::InvoiceError(
+                format!("Preimage does not match payment hash {}", payment_hash)
+            ));
+        }
+        
         let mut invoices = self.invoices.lock().unwrap();
         
         match invoices.get_mut(payment_hash) {
             Some(invoice_status) => {
-                invoice_status.is_paid = true;
-                invoice_status.paid_at = Some(self.get_timestamp());
-                invoice_status.payment_preimage = Some(payment_preimage.to_string());
+                if invoice_status.state == InvoiceState::Settled {
+                    // Already settled through the HTLC path
+                    return Ok(());
+                }
+                
+                if matches!(invoice_status.state, InvoiceState::Cancelled | InvoiceState::Expired) {
+                    return Err(LightningError::InvoiceError(
+                        format!("Invoice {} is {:?} and cannot be paid", payment_hash, invoice_status.state)
+                    ));
+                }
+                
+                invoice_status.settle(payment_preimage, self.get_timestamp());
+                let update = invoice_update(invoice_status);
+                drop(invoices);
+                self.notify(update);
                 Ok(())
             },
             None => Err(LightningError::InvoiceError(
@@ -256,6 +748,7 @@ pub fn mark_invoice_paid(
     
     /// Check if an invoice is paid
     pub fn is_invoice_paid(&self, payment_hash: &str) -> LightningResult<bool> {
+        let payment_hash = &payment_hash.to_lowercase();
         let invoices = self.invoices.lock().unwrap();
         
         match invoices.get(payment_hash) {
@@ -268,14 +761,14 @@ pub fn is_invoice_paid(&self, payment_hash: &str) -> LightningResult<bool> {
     
     /// Check if an invoice is expired
     pub fn is_invoice_expired(&self, payment_hash: &str) -> LightningResult<bool> {
+        let payment_hash = &payment_hash.to_lowercase();
         let invoices = self.invoices.lock().unwrap();
         
         match invoices.get(payment_hash) {
             Some(invoice_status) => {
-                let invoice = &invoice_status.invoice;
-                let now = self.get_timestamp();
-                let expiry_time = invoice.timestamp + invoice.expiry as u64;
-                Ok(now > expiry_time)
+                Ok(invoice_status.state == InvoiceState::Expired ||
+                    (invoice_status.state == InvoiceState::Open &&
+                        invoice_expired(&invoice_status.invoice, self.get_timestamp())))
             },
             None => Err(LightningError::InvoiceError(
                 format!("Invoice not found: {}", payment_hash)
@@ -292,6 +785,58 @@ fn get_timestamp(&self) -> u64 {
     }
     
     /// Generate a mock BOLT11 invoice string (for testing without LDK)
+    /// Encode an invoice as BOLT11, signed by LDK once a node is attached
+    #[cfg(feature = "ldk")]
+    fn encode_bolt11(
+        &self,
+        amount_msat: Option<u64>,
+        payment_hash: &str,
+        description: &str,
+        expiry: u32,
+    ) -> LightningResult<String> {
+        let ldk_node = self.ldk_node.lock().unwrap().as_ref().and_then(Weak::upgrade);
+        match ldk_node {
+            Some(ldk_node) => ldk_node.create_bolt11(amount_msat, payment_hash, description, expiry, 40),
+            None => Ok(self.generate_mock_bolt11(amount_msat, payment_hash, description)),
+        }
+    }
+    
+    #[cfg(not(feature = "ldk"))]
+    fn encode_bolt11(
+        &self,
+        amount_msat: Option<u64>,
+        payment_hash: &str,
+        description: &str,
+        _expiry: u32,
+    ) -> LightningResult<String> {
+        Ok(self.generate_mock_bolt11(amount_msat, payment_hash, description))
+    }
+    
+    #[cfg(feature = "ldk")]
+    fn encode_bolt11_with_description_hash(
+        &self,
+        amount_msat: Option<u64>,
+        payment_hash: &str,
+        _expiry: u32,
+    ) -> LightningResult<String> {
+        if self.ldk_node.lock().unwrap().as_ref().and_then(Weak::upgrade).is_some() {
+            return Err(LightningError::ImplementationError(
+                "Description-hash invoices are not supported by the LDK implementation".to_string()
+            ));
+        }
+        Ok(self.generate_mock_bolt11(amount_msat, payment_hash, ""))
+    }
+    
+    #[cfg(not(feature = "ldk"))]
+    fn encode_bolt11_with_description_hash(
+        &self,
+        amount_msat: Option<u64>,
+        payment_hash: &str,
+        _expiry: u32,
+    ) -> LightningResult<String> {
+        Ok(self.generate_mock_bolt11(amount_msat, payment_hash, ""))
+    }
+    
     fn generate_mock_bolt11(
         &self, 
         amount_msat: Option<u64>, 
@@ -329,6 +874,71 @@ fn generate_mock_bolt11(
     }
 }
 
+/// Expire invoices and cancel held HTLCs near their deadline until stopped
+///
+/// Chain height comes from `bitcoin_interface`; if it cannot be read, only the
+/// time-based invoice expiry runs on that pass.
+pub fn spawn_invoice_maintenance(
+    invoice_manager: Arc<InvoiceManager>,
+    bitcoin_interface: Arc<dyn BitcoinInterface>,
+    stop_signal: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let tick = Duration::from_millis(100);
+        let mut elapsed = MAINTENANCE_INTERVAL;
+        
+        while !stop_signal.load(Ordering::SeqCst) {
+            if elapsed >= MAINTENANCE_INTERVAL {
+                elapsed = Duration::ZERO;
+                invoice_manager.expire_invoices();
+                if let Ok(height) = bitcoin_interface.get_block_height() {
+                    invoice_manager.cancel_expiring_htlcs(height);
+                }
+            }
+            
+            thread::sleep(tick);
+            elapsed += tick;
+        }
+    })
+}
+
+/// Whether an invoice's expiry time has been reached
+fn invoice_expired(invoice: &Invoice, now: u64) -> bool {
+    now >= invoice.timestamp + invoice.expiry as u64
+}
+
+/// Build a subscriber notification for an invoice
+fn invoice_update(invoice_status: &InvoiceWithStatus) -> InvoiceUpdate {
+    InvoiceUpdate {
+        payment_hash: invoice_status.invoice.payment_hash.clone(),
+        state: invoice_status.state,
+        amount_received_msat: invoice_status.amount_received_msat(),
+    }
+}
+
+/// Compute the payment hash (SHA256) of a hex-encoded preimage
+pub fn payment_hash_for_preimage(preimage: &str) -> LightningResult<String> {
+    let bytes = decode_hex(preimage)
+        .filter(|bytes| bytes.len() == 32)
+        .ok_or_else(|| LightningError::InvoiceError(
+            format!("Invalid payment preimage: {}", preimage)
+        ))?;
+    
+    Ok(Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Decode a hex string into bytes
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 /// Generate random bytes and return as hex string
 fn generate_random_bytes_hex(len: usize) -> String {
     use rand::{thread_rng, Rng};
@@ -1,29 +1,149 @@
 // Lightning Network Key Manager
 // Handles private key management, node identity, and secure storage
+//
+// The 32-byte node seed is stored encrypted at rest: a key derived from the
+// passphrase with scrypt encrypts it with ChaCha20-Poly1305. While locked the
+// seed is not held in memory and nothing can be signed or derived.
+//
+// Keys are derived from the seed with BIP32:
+//
+//   m/0'                                   node identity key (as LDK's KeysManager)
+//   m/138'/0                               LNURL-auth hashing key (LUD-05)
+//   m/1017'/<family>'/<generation>'/<index>'  rotatable key families
+//
+// Rotating a family bumps its generation, so every key in it changes while the
+// node identity stays the same.
 
 use std::sync::{Arc, Mutex};
 use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
 use std::fs;
-use std::io;
 
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use hmac::{Hmac, Mac};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::bitcoin::encoding::{derive_private_key, from_hex, HARDENED, sha256d, to_hex, zbase32_decode, zbase32_encode};
 use crate::lightning::interface::{
     LightningError, LightningResult, NodeInfo
 };
 
 #[cfg(feature = "ldk")]
-use lightning::{
-    chain::keysinterface::{KeysManager, KeysInterface, InMemorySigner},
-    util::ser::{Readable, Writeable},
-};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "ldk")]
+use lightning::sign::KeysManager;
+
+/// File holding the encrypted node seed
+const SEED_FILE: &str = "keys_seed.json";
+
+/// Plaintext seed file written by earlier versions, migrated on first unlock
+const LEGACY_SEED_FILE: &str = "keys_seed.dat";
+
+/// Prefix lnd hashes signed messages with
+const SIGNED_MESSAGE_PREFIX: &[u8] = b"Lightning Signed Message:";
+
+/// Associated data binding ciphertexts to the seed file format
+const SEED_FILE_AAD: &[u8] = b"opsource node seed v1";
+
+/// scrypt cost parameters for new seed files
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// BIP32 purpose under which rotatable key families live
+const KEY_FAMILY_PURPOSE: u32 = 1017;
+
+/// Key families derived from the node seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyFamily {
+    /// Node identity key; never rotated
+    Node,
+    /// Keys receiving on-chain sweeps
+    Destination,
+    /// Keys receiving cooperative close outputs
+    Shutdown,
+    /// Per-channel base points and commitment seeds
+    Channel,
+}
+
+impl KeyFamily {
+    /// BIP32 index of the family below the key family purpose
+    fn index(&self) -> u32 {
+        match self {
+            KeyFamily::Node => 0,
+            KeyFamily::Destination => 1,
+            KeyFamily::Shutdown => 2,
+            KeyFamily::Channel => 3,
+        }
+    }
+    
+    /// Name of the family in the seed file
+    fn name(&self) -> &'static str {
+        match self {
+            KeyFamily::Node => "node",
+            KeyFamily::Destination => "destination",
+            KeyFamily::Shutdown => "shutdown",
+            KeyFamily::Channel => "channel",
+        }
+    }
+}
+
+/// Keys for one channel, derived from the channel key family
+#[derive(Clone)]
+pub struct ChannelKeys {
+    /// Key for the 2-of-2 funding output
+    pub funding_key: SecretKey,
+    /// Base point for revocation keys
+    pub revocation_base_key: SecretKey,
+    /// Key receiving our balance in the counterparty's commitments
+    pub payment_key: SecretKey,
+    /// Base point for our delayed to_local outputs
+    pub delayed_payment_base_key: SecretKey,
+    /// Base point for HTLC keys
+    pub htlc_base_key: SecretKey,
+    /// Seed for per-commitment secrets
+    pub commitment_seed: [u8; 32],
+}
+
+/// Encrypted seed as stored on disk
+#[derive(Serialize, Deserialize)]
+struct SeedFile {
+    /// Format version
+    version: u32,
+    /// scrypt cost parameter (log2 of N)
+    scrypt_log_n: u8,
+    /// scrypt block size
+    scrypt_r: u32,
+    /// scrypt parallelism
+    scrypt_p: u32,
+    /// scrypt salt (hex)
+    salt: String,
+    /// ChaCha20-Poly1305 nonce (hex)
+    nonce: String,
+    /// Encrypted seed with authentication tag (hex)
+    ciphertext: String,
+    /// Current generation of each rotatable key family
+    #[serde(default)]
+    key_generations: BTreeMap<String, u32>,
+}
 
 /// LDK Key Manager wrapper
+#[derive(Clone)]
 pub struct KeyManagerWrapper {
     /// LDK Keys Manager
     #[cfg(feature = "ldk")]
-    keys_manager: Mutex<Option<Arc<KeysManager>>>,
+    keys_manager: Arc<Mutex<Option<Arc<KeysManager>>>>,
+    
+    /// Decrypted node seed, present while unlocked
+    seed: Arc<Mutex<Option<[u8; 32]>>>,
     
     /// Node info
-    node_info: Mutex<NodeInfo>,
+    node_info: Arc<Mutex<NodeInfo>>,
     
     /// Configuration
     config: Arc<crate::config::Config>,
@@ -35,20 +155,12 @@ pub struct KeyManagerWrapper {
 impl KeyManagerWrapper {
     /// Create a new Key Manager wrapper
     pub fn new(config: &crate::config::Config) -> Self {
-        let data_dir = config.lightning_data_dir.clone()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| {
-                let base_dir = config.bitcoin_data_dir.clone()
-                    .unwrap_or_else(|| "./.ldk".to_string());
-                let mut path = PathBuf::from(base_dir);
-                path.push("lightning");
-                path
-            });
+        let data_dir = config.lightning_data_path();
         
         // Create a default node info
         let node_pubkey = config.lightning_node_pubkey.clone()
             .unwrap_or_else(|| {
-                // Generate a placeholder pubkey
+                // Generate a placeholder pubkey until the seed is unlocked
                 "02eadbd9e7557375161df8b646776a547c5097cc8288021e9ee72cb33327f912cd".to_string()
             });
         
@@ -67,8 +179,9 @@ pub fn new(config: &crate::config::Config) -> Self {
         
         KeyManagerWrapper {
             #[cfg(feature = "ldk")]
-            keys_manager: Mutex::new(None),
-            node_info: Mutex::new(node_info),
+            keys_manager: Arc::new(Mutex::new(None)),
+            seed: Arc::new(Mutex::new(None)),
+            node_info: Arc::new(Mutex::new(node_info)),
             config: Arc::new(config.clone()),
             data_dir,
         }
@@ -77,51 +190,13 @@ pub fn new(config: &crate::config::Config) -> Self {
     /// Initialize the key manager
     #[cfg(feature = "ldk")]
     pub fn initialize(&mut self) -> LightningResult<Arc<KeysManager>> {
-        // Create the data directory if it doesn't exist
-        if !self.data_dir.exists() {
-            fs::create_dir_all(&self.data_dir).map_err(|e| {
-                LightningError::ImplementationError(format!("Failed to create data directory: {}", e))
-            })?;
-        }
-        
-        // Check if we have an existing seed file
-        let seed_path = self.data_dir.join("keys_seed.dat");
+        self.unlock_with_configured_passphrase()?;
         
-        let seed = if seed_path.exists() {
-            // Load existing seed
-            self.load_seed(&seed_path)?
-        } else {
-            // Generate a new seed
-            let mut seed = [0u8; 32];
-            get_random_bytes(&mut seed).map_err(|e| {
-                LightningError::ImplementationError(format!("Failed to generate random seed: {}", e))
-            })?;
-            
-            // Save the seed
-            self.save_seed(&seed_path, &seed)?;
-            
-            seed
-        };
-        
-        // Create the keys manager with the seed
-        let keys_manager = Arc::new(KeysManager::new(
-            &seed,
-            0, // Starting timestamp for ECDH nonces
-            0, // Starting timestamp for transactions
-        ));
-        
-        // Update our node info with the actual pubkey from the keys
-        #[cfg(feature = "ldk")]
-        {
-            let node_id = hex::encode(keys_manager.get_node_id().serialize());
-            let mut node_info = self.node_info.lock().unwrap();
-            node_info.pubkey = node_id;
-        }
-        
-        // Store the keys manager
-        *self.keys_manager.lock().unwrap() = Some(Arc::clone(&keys_manager));
+        let keys_manager = self.get_keys_manager().ok_or_else(|| {
+            LightningError::ImplementationError("Failed to create keys manager".to_string())
+        })?;
         
-        println!("Initialized Lightning key manager with node ID: {}", 
+        println!("Initialized Lightning key manager with node ID: {}",
                  self.node_info.lock().unwrap().pubkey);
         
         Ok(keys_manager)
@@ -129,19 +204,32 @@ pub fn initialize(&mut self) -> LightningResult<Arc<KeysManager>> {
     
     #[cfg(not(feature = "ldk"))]
     pub fn initialize(&mut self) -> LightningResult<()> {
-        // Mock implementation - just ensure the data directory exists
-        if !self.data_dir.exists() {
-            fs::create_dir_all(&self.data_dir).map_err(|e| {
-                LightningError::ImplementationError(format!("Failed to create data directory: {}", e))
-            })?;
+        // A wrong or missing passphrase leaves the node locked until `unlock`
+        if let Err(e) = self.unlock_with_configured_passphrase() {
+            println!("Lightning key manager (mock) is locked: {}", e);
+            return Ok(());
         }
         
-        println!("Initialized Lightning key manager (mock) with node ID: {}", 
+        println!("Initialized Lightning key manager (mock) with node ID: {}",
                  self.node_info.lock().unwrap().pubkey);
         
         Ok(())
     }
     
+    /// Unlock (or create) the seed with the passphrase from the configuration
+    ///
+    /// An empty passphrase is only used when the configuration explicitly
+    /// allows it, since it leaves the seed effectively unencrypted.
+    fn unlock_with_configured_passphrase(&self) -> LightningResult<()> {
+        match self.config.lightning_seed_passphrase.as_deref() {
+            Some(passphrase) if !passphrase.is_empty() => self.unlock(passphrase),
+            _ if self.config.lightning_allow_empty_seed_passphrase => self.unlock(""),
+            _ => Err(LightningError::ImplementationError(
+                "No seed passphrase configured; set lightning.seed_passphrase_file or allow an empty passphrase with lightning.allow_empty_seed_passphrase".to_string()
+            )),
+        }
+    }
+    
     /// Get node information
     pub fn get_node_info(&self) -> LightningResult<NodeInfo> {
         let node_info = self.node_info.lock().unwrap();
@@ -167,33 +255,269 @@ pub fn get_data_dir(&self) -> &Path {
         &self.data_dir
     }
     
-    // Helper methods for key operations
-    
-    /// Load a seed from a file
+    /// Get the LDK keys manager, once initialized
     #[cfg(feature = "ldk")]
-    fn load_seed(&self, path: &Path) -> LightningResult<[u8; 32]> {
+    pub fn get_keys_manager(&self) -> Option<Arc<KeysManager>> {
+        self.keys_manager.lock().unwrap().clone()
+    }
+    
+    /// Whether an encrypted (or legacy plaintext) seed exists on disk
+    pub fn has_seed(&self) -> bool {
+        self.data_dir.join(SEED_FILE).exists() || self.data_dir.join(LEGACY_SEED_FILE).exists()
+    }
+    
+    /// Whether the seed is currently locked
+    pub fn is_locked(&self) -> bool {
+        self.seed.lock().unwrap().is_none()
+    }
+    
+    /// Decrypt the seed with the passphrase, creating it on first use
+    ///
+    /// A plaintext seed left by earlier versions is encrypted with the
+    /// passphrase and the plaintext file removed.
+    pub fn unlock(&self, passphrase: &str) -> LightningResult<()> {
+        fs::create_dir_all(&self.data_dir).map_err(|e| {
+            LightningError::ImplementationError(format!("Failed to create data directory: {}", e))
+        })?;
+        
+        let seed_path = self.data_dir.join(SEED_FILE);
+        let legacy_path = self.data_dir.join(LEGACY_SEED_FILE);
+        
+        let seed = if seed_path.exists() {
+            decrypt_seed(&self.read_seed_file()?, passphrase)?
+        } else {
+            let seed = if legacy_path.exists() {
+                load_legacy_seed(&legacy_path)?
+            } else {
+                let mut seed = [0u8; 32];
+                get_random_bytes(&mut seed);
+                seed
+            };
+            
+            self.write_seed_file(&encrypt_seed(&seed, passphrase, BTreeMap::new())?)?;
+            if legacy_path.exists() {
+                fs::remove_file(&legacy_path).map_err(|e| {
+                    LightningError::ImplementationError(format!("Failed to remove plaintext seed: {}", e))
+                })?;
+            }
+            seed
+        };
+        
+        // The node key is fixed by the seed unless the configuration pins one
+        if self.config.lightning_node_pubkey.is_none() {
+            let secp = Secp256k1::new();
+            let node_key = node_secret_key(&seed)?;
+            self.node_info.lock().unwrap().pubkey = to_hex(&PublicKey::from_secret_key(&secp, &node_key).serialize());
+        }
+        
+        #[cfg(feature = "ldk")]
+        {
+            let mut keys_manager = self.keys_manager.lock().unwrap();
+            if keys_manager.is_none() {
+                // The start time keeps derived channel keys unique across restarts
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                *keys_manager = Some(Arc::new(KeysManager::new(
+                    &seed,
+                    now.as_secs(),
+                    now.subsec_nanos(),
+                )));
+            }
+        }
+        
+        *self.seed.lock().unwrap() = Some(seed);
+        Ok(())
+    }
+    
+    /// Forget the decrypted seed
+    ///
+    /// A running LDK node keeps the keys it was started with until it stops.
+    pub fn lock(&self) {
+        if let Some(mut seed) = self.seed.lock().unwrap().take() {
+            seed.fill(0);
+        }
+    }
+    
+    /// Re-encrypt the seed under a new passphrase
+    pub fn change_passphrase(&self, old_passphrase: &str, new_passphrase: &str) -> LightningResult<()> {
+        let seed_file = self.read_seed_file()?;
+        let mut seed = decrypt_seed(&seed_file, old_passphrase)?;
+        let result = encrypt_seed(&seed, new_passphrase, seed_file.key_generations)
+            .and_then(|seed_file| self.write_seed_file(&seed_file));
+        seed.fill(0);
+        result
+    }
+    
+    /// Create a new seed encrypted with the passphrase and unlock it
+    ///
+    /// Returns the seed as hex for the user to back up; it is never shown again.
+    pub fn create_seed(&self, passphrase: &str) -> LightningResult<String> {
         let mut seed = [0u8; 32];
+        get_random_bytes(&mut seed);
+        self.store_new_seed(&seed, passphrase)?;
+        Ok(to_hex(&seed))
+    }
+    
+    /// Restore a seed backed up from `create_seed`, encrypting it with the passphrase
+    pub fn restore_seed(&self, seed_hex: &str, passphrase: &str) -> LightningResult<()> {
+        let seed: [u8; 32] = from_hex(seed_hex.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| LightningError::ImplementationError("Seed must be 32 bytes of hex".to_string()))?;
+        self.store_new_seed(&seed, passphrase)
+    }
+    
+    /// Write a seed to an empty data directory and unlock it
+    fn store_new_seed(&self, seed: &[u8; 32], passphrase: &str) -> LightningResult<()> {
+        if self.has_seed() {
+            return Err(LightningError::ImplementationError(
+                format!("A seed already exists in {}", self.data_dir.display())
+            ));
+        }
         
-        let mut file = fs::File::open(path).map_err(|e| {
-            LightningError::ImplementationError(format!("Failed to open seed file: {}", e))
+        fs::create_dir_all(&self.data_dir).map_err(|e| {
+            LightningError::ImplementationError(format!("Failed to create data directory: {}", e))
         })?;
+        self.write_seed_file(&encrypt_seed(seed, passphrase, BTreeMap::new())?)?;
+        self.unlock(passphrase)
+    }
+    
+    /// Current generation of a key family
+    pub fn key_generation(&self, family: KeyFamily) -> LightningResult<u32> {
+        if family == KeyFamily::Node {
+            return Ok(0);
+        }
         
-        io::Read::read_exact(&mut file, &mut seed).map_err(|e| {
+        let seed_file = self.read_seed_file()?;
+        Ok(seed_file.key_generations.get(family.name()).copied().unwrap_or(0))
+    }
+    
+    /// Replace every key in a family with fresh ones, returning the new generation
+    ///
+    /// The node identity key cannot be rotated.
+    pub fn rotate_keys(&self, family: KeyFamily) -> LightningResult<u32> {
+        if family == KeyFamily::Node {
+            return Err(LightningError::ImplementationError(
+                "The node identity key cannot be rotated".to_string()
+            ));
+        }
+        
+        let mut seed_file = self.read_seed_file()?;
+        let generation = seed_file.key_generations.entry(family.name().to_string()).or_insert(0);
+        *generation += 1;
+        let generation = *generation;
+        
+        self.write_seed_file(&seed_file)?;
+        Ok(generation)
+    }
+    
+    /// Derive a key of a family at an index, using the family's current generation
+    pub fn derive_key(&self, family: KeyFamily, index: u32) -> LightningResult<SecretKey> {
+        let seed = self.unlocked_seed()?;
+        if family == KeyFamily::Node {
+            return node_secret_key(&seed);
+        }
+        
+        let generation = self.key_generation(family)?;
+        derive_path(&seed, &[
+            KEY_FAMILY_PURPOSE | HARDENED,
+            family.index() | HARDENED,
+            generation | HARDENED,
+            index | HARDENED,
+        ])
+    }
+    
+    /// Derive the keys of the channel at an index
+    pub fn channel_keys(&self, index: u32) -> LightningResult<ChannelKeys> {
+        let seed = self.unlocked_seed()?;
+        let generation = self.key_generation(KeyFamily::Channel)?;
+        let channel_path = [
+            KEY_FAMILY_PURPOSE | HARDENED,
+            KeyFamily::Channel.index() | HARDENED,
+            generation | HARDENED,
+            index | HARDENED,
+        ];
+        let key = |child: u32| {
+            let mut path = channel_path.to_vec();
+            path.push(child | HARDENED);
+            derive_path(&seed, &path)
+        };
+        
+        Ok(ChannelKeys {
+            funding_key: key(0)?,
+            revocation_base_key: key(1)?,
+            payment_key: key(2)?,
+            delayed_payment_base_key: key(3)?,
+            htlc_base_key: key(4)?,
+            commitment_seed: key(5)?.secret_bytes(),
+        })
+    }
+    
+    /// Sign a message with the node key, in lnd's `signmessage` format
+    ///
+    /// The signature is a recoverable ECDSA signature over the double SHA256
+    /// of the prefixed message, encoded as z-base-32.
+    pub fn sign_message(&self, message: &[u8]) -> LightningResult<String> {
+        let seed = self.unlocked_seed()?;
+        let node_key = node_secret_key(&seed)?;
+        
+        let secp = Secp256k1::new();
+        let signature = secp.sign_ecdsa_recoverable(&signed_message_digest(message), &node_key);
+        let (recovery_id, compact) = signature.serialize_compact();
+        
+        let mut encoded = Vec::with_capacity(65);
+        encoded.push(31 + recovery_id.to_i32() as u8);
+        encoded.extend_from_slice(&compact);
+        Ok(zbase32_encode(&encoded))
+    }
+    
+    /// Derive the LNURL-auth linking key for a domain (LUD-05)
+    ///
+    /// Follows LUD-05 exactly so linking keys carry over to other wallets: the
+    /// hashing key is the private key at m/138'/0, HMAC-SHA256 of the domain under
+    /// it gives the derivation material, and its first 16 bytes read as four
+    /// big-endian u32s select m/138'/<long1>/<long2>/<long3>/<long4>.
+    pub fn lnurl_linking_key(&self, domain: &str) -> LightningResult<SecretKey> {
+        let seed = self.unlocked_seed()?;
+        let hashing_key = derive_path(&seed, &[138 | HARDENED, 0])?;
+        let material = hmac_sha256(&hashing_key.secret_bytes(), domain.to_lowercase().as_bytes());
+        
+        let mut path = vec![138 | HARDENED];
+        path.extend(material[..16].chunks(4).map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])));
+        derive_path(&seed, &path)
+    }
+    
+    // Helper methods for key operations
+    
+    /// The decrypted seed, or an error while locked
+    fn unlocked_seed(&self) -> LightningResult<[u8; 32]> {
+        self.seed.lock().unwrap().ok_or_else(|| {
+            LightningError::ImplementationError("Key manager is locked".to_string())
+        })
+    }
+    
+    /// Read the encrypted seed file
+    fn read_seed_file(&self) -> LightningResult<SeedFile> {
+        let contents = fs::read_to_string(self.data_dir.join(SEED_FILE)).map_err(|e| {
             LightningError::ImplementationError(format!("Failed to read seed file: {}", e))
         })?;
         
-        Ok(seed)
+        serde_json::from_str(&contents).map_err(|e| {
+            LightningError::ImplementationError(format!("Invalid seed file: {}", e))
+        })
     }
     
-    /// Save a seed to a file
-    #[cfg(feature = "ldk")]
-    fn save_seed(&self, path: &Path, seed: &[u8; 32]) -> LightningResult<()> {
-        // Create with restrictive permissions
-        let mut file = fs::File::create(path).map_err(|e| {
-            LightningError::ImplementationError(format!("Failed to create seed file: {}", e))
+    /// Atomically replace the encrypted seed file
+    fn write_seed_file(&self, seed_file: &SeedFile) -> LightningResult<()> {
+        let contents = serde_json::to_string_pretty(seed_file).map_err(|e| {
+            LightningError::ImplementationError(format!("Failed to serialize seed file: {}", e))
         })?;
         
-        io::Write::write_all(&mut file, seed).map_err(|e| {
+        let mut suffix = [0u8; 8];
+        get_random_bytes(&mut suffix);
+        let temp_path = self.data_dir.join(format!("{}.{}.tmp", SEED_FILE, to_hex(&suffix)));
+        fs::write(&temp_path, contents).map_err(|e| {
             LightningError::ImplementationError(format!("Failed to write seed file: {}", e))
         })?;
         
@@ -201,23 +525,315 @@ pub fn get_data_dir(&self) -> &Path {
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            let metadata = fs::metadata(path).map_err(|e| {
-                LightningError::ImplementationError(format!("Failed to get file metadata: {}", e))
-            })?;
-            let mut permissions = metadata.permissions();
-            permissions.set_mode(0o600);
-            fs::set_permissions(path, permissions).map_err(|e| {
+            fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o600)).map_err(|e| {
                 LightningError::ImplementationError(format!("Failed to set file permissions: {}", e))
             })?;
         }
         
-        Ok(())
+        fs::rename(&temp_path, self.data_dir.join(SEED_FILE)).map_err(|e| {
+            LightningError::ImplementationError(format!("Failed to replace seed file: {}", e))
+        })
     }
 }
 
+/// Recover the node key that signed a message in lnd's `signmessage` format
+///
+/// Returns the signer's pubkey; callers compare it with the node they expect.
+pub fn verify_message(message: &[u8], signature: &str) -> LightningResult<String> {
+    let invalid = || LightningError::ImplementationError("Invalid message signature".to_string());
+    
+    let signature = zbase32_decode(signature).map_err(|_| invalid())?;
+    if signature.len() != 65 || !(27..=34).contains(&signature[0]) {
+        return Err(invalid());
+    }
+    
+    // Header bytes 27-30 mark uncompressed keys and 31-34 compressed ones
+    let recovery_id = RecoveryId::from_i32(((signature[0] - 27) % 4) as i32).map_err(|_| invalid())?;
+    let signature = RecoverableSignature::from_compact(&signature[1..], recovery_id).map_err(|_| invalid())?;
+    let pubkey = Secp256k1::verification_only()
+        .recover_ecdsa(&signed_message_digest(message), &signature)
+        .map_err(|_| invalid())?;
+    
+    Ok(to_hex(&pubkey.serialize()))
+}
+
+/// Digest signed by `signmessage`
+fn signed_message_digest(message: &[u8]) -> Message {
+    let mut prefixed = SIGNED_MESSAGE_PREFIX.to_vec();
+    prefixed.extend_from_slice(message);
+    Message::from_digest(sha256d(&prefixed))
+}
+
+/// Node identity key, at the path LDK's KeysManager uses
+fn node_secret_key(seed: &[u8; 32]) -> LightningResult<SecretKey> {
+    derive_path(seed, &[HARDENED])
+}
+
+/// BIP32 private key derivation from the seed along a path
+fn derive_path(seed: &[u8], path: &[u32]) -> LightningResult<SecretKey> {
+    Ok(derive_private_key(seed, path)?)
+}
+
+/// Encrypt a seed under a passphrase
+fn encrypt_seed(
+    seed: &[u8; 32],
+    passphrase: &str,
+    key_generations: BTreeMap<String, u32>,
+) -> LightningResult<SeedFile> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    get_random_bytes(&mut salt);
+    get_random_bytes(&mut nonce);
+    
+    let cipher = seed_cipher(passphrase, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: seed, aad: SEED_FILE_AAD })
+        .map_err(|_| LightningError::ImplementationError("Failed to encrypt seed".to_string()))?;
+    
+    Ok(SeedFile {
+        version: 1,
+        scrypt_log_n: SCRYPT_LOG_N,
+        scrypt_r: SCRYPT_R,
+        scrypt_p: SCRYPT_P,
+        salt: to_hex(&salt),
+        nonce: to_hex(&nonce),
+        ciphertext: to_hex(&ciphertext),
+        key_generations,
+    })
+}
+
+/// Decrypt a seed, failing on a wrong passphrase or a tampered file
+fn decrypt_seed(seed_file: &SeedFile, passphrase: &str) -> LightningResult<[u8; 32]> {
+    let field = |value: &str, name: &str| from_hex(value).map_err(|_| {
+        LightningError::ImplementationError(format!("Invalid {} in seed file", name))
+    });
+    let salt = field(&seed_file.salt, "salt")?;
+    let nonce = field(&seed_file.nonce, "nonce")?;
+    let ciphertext = field(&seed_file.ciphertext, "ciphertext")?;
+    if nonce.len() != 12 {
+        return Err(LightningError::ImplementationError("Invalid nonce in seed file".to_string()));
+    }
+    
+    let cipher = seed_cipher(passphrase, &salt, seed_file.scrypt_log_n, seed_file.scrypt_r, seed_file.scrypt_p)?;
+    let seed = cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: SEED_FILE_AAD })
+        .map_err(|_| LightningError::ImplementationError("Invalid passphrase".to_string()))?;
+    
+    seed.try_into().map_err(|_| {
+        LightningError::ImplementationError("Seed file does not contain a 32-byte seed".to_string())
+    })
+}
+
+/// Cipher keyed with the scrypt hash of a passphrase
+fn seed_cipher(passphrase: &str, salt: &[u8], log_n: u8, r: u32, p: u32) -> LightningResult<ChaCha20Poly1305> {
+    let params = scrypt::Params::new(log_n, r, p, 32).map_err(|e| {
+        LightningError::ImplementationError(format!("Invalid scrypt parameters: {}", e))
+    })?;
+    
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key).map_err(|e| {
+        LightningError::ImplementationError(format!("Failed to derive seed key: {}", e))
+    })?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.fill(0);
+    
+    Ok(cipher)
+}
+
+/// Load a plaintext seed written by earlier versions
+fn load_legacy_seed(path: &Path) -> LightningResult<[u8; 32]> {
+    let seed = fs::read(path).map_err(|e| {
+        LightningError::ImplementationError(format!("Failed to read seed file: {}", e))
+    })?;
+    
+    seed.get(..32).and_then(|seed| seed.try_into().ok()).ok_or_else(|| {
+        LightningError::ImplementationError("Seed file is too short".to_string())
+    })
+}
+
+/// HMAC-SHA256 of a message under a key
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
 /// Get random bytes for seed generation
-fn get_random_bytes(dest: &mut [u8]) -> Result<(), Box<dyn std::error::Error>> {
+fn get_random_bytes(dest: &mut [u8]) {
     use rand::{thread_rng, RngCore};
     thread_rng().fill_bytes(dest);
-    Ok(())
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::lightning::channel_manager::generate_random_id;
+
+    fn key_manager(passphrase: Option<&str>) -> (KeyManagerWrapper, PathBuf) {
+        let data_dir = std::env::temp_dir().join(format!("opsource-keys-{}", generate_random_id()));
+        let mut config = Config::default();
+        config.lightning_data_dir = Some(data_dir.to_string_lossy().to_string());
+        config.lightning_seed_passphrase = passphrase.map(str::to_string);
+        config.lightning_allow_empty_seed_passphrase = passphrase.is_none();
+        (KeyManagerWrapper::new(&config), data_dir)
+    }
+
+    #[test]
+    fn test_bip32_derivation() {
+        // BIP32 test vectors 1 and 4
+        let seed = from_hex("000102030405060708090a0b0c0d0e0f").unwrap();
+        let key = derive_path(&seed, &[HARDENED, 1]).unwrap();
+        assert_eq!(to_hex(&key.secret_bytes()), "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368");
+
+        let seed = from_hex("3ddd5602285899a946114506157c7997e5444528f3003f6134712147db19b678").unwrap();
+        let key = derive_path(&seed, &[HARDENED, 1 | HARDENED]).unwrap();
+        assert_eq!(to_hex(&key.secret_bytes()), "3a2086edd7d9df86c3487a5905a1712a9aa664bce8cc268141e07549eaa8661d");
+    }
+
+    #[test]
+    fn test_lnurl_linking_key_follows_lud05() {
+        let (mut keys, data_dir) = key_manager(None);
+        keys.initialize().unwrap();
+        let seed = keys.unlocked_seed().unwrap();
+
+        // Walk the LUD-05 steps by hand
+        let hashing_key = derive_private_key(&seed, &[138 | HARDENED, 0]).unwrap();
+        let material = hmac_sha256(&hashing_key.secret_bytes(), b"site.com");
+        let longs: Vec<u32> = material[..16].chunks(4)
+            .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
+            .collect();
+        let expected = derive_private_key(&seed, &[138 | HARDENED, longs[0], longs[1], longs[2], longs[3]]).unwrap();
+
+        assert_eq!(keys.lnurl_linking_key("site.com").unwrap(), expected);
+        assert_eq!(keys.lnurl_linking_key("Site.com").unwrap(), expected);
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_encrypted_seed_lock_and_unlock() {
+        let (mut keys, data_dir) = key_manager(Some("correct horse"));
+        keys.initialize().unwrap();
+        let pubkey = keys.get_node_info().unwrap().pubkey;
+        assert_ne!(pubkey, "02eadbd9e7557375161df8b646776a547c5097cc8288021e9ee72cb33327f912cd");
+
+        // Only the encrypted seed is written to disk
+        let stored = fs::read_to_string(data_dir.join(SEED_FILE)).unwrap();
+        assert!(stored.contains("ciphertext"));
+        assert!(!data_dir.join(LEGACY_SEED_FILE).exists());
+
+        keys.lock();
+        assert!(keys.is_locked());
+        assert!(keys.sign_message(b"hello").is_err());
+        assert!(keys.unlock("wrong horse").is_err());
+
+        // A fresh wrapper over the same directory derives the same identity
+        let (mut reopened, _) = key_manager(Some("correct horse"));
+        reopened.data_dir = data_dir.clone();
+        reopened.initialize().unwrap();
+        assert_eq!(reopened.get_node_info().unwrap().pubkey, pubkey);
+
+        keys.change_passphrase("correct horse", "battery staple").unwrap();
+        assert!(keys.unlock("correct horse").is_err());
+        keys.unlock("battery staple").unwrap();
+        assert_eq!(keys.get_node_info().unwrap().pubkey, pubkey);
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_legacy_seed_is_migrated() {
+        let (keys, data_dir) = key_manager(None);
+        fs::create_dir_all(&data_dir).unwrap();
+        let seed = [7u8; 32];
+        fs::write(data_dir.join(LEGACY_SEED_FILE), seed).unwrap();
+
+        keys.unlock("").unwrap();
+        let secp = Secp256k1::new();
+        let expected = PublicKey::from_secret_key(&secp, &node_secret_key(&seed).unwrap());
+        assert_eq!(keys.get_node_info().unwrap().pubkey, to_hex(&expected.serialize()));
+        assert!(!data_dir.join(LEGACY_SEED_FILE).exists());
+        assert!(data_dir.join(SEED_FILE).exists());
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_create_and_restore_seed() {
+        let (keys, data_dir) = key_manager(None);
+        let seed = keys.create_seed("backup").unwrap();
+        let pubkey = keys.get_node_info().unwrap().pubkey;
+        assert!(keys.create_seed("backup").is_err());
+
+        let (restored, restored_dir) = key_manager(None);
+        assert!(restored.restore_seed("abcd", "other").is_err());
+        restored.restore_seed(&seed, "other").unwrap();
+        assert_eq!(restored.get_node_info().unwrap().pubkey, pubkey);
+        restored.lock();
+        restored.unlock("other").unwrap();
+
+        let _ = fs::remove_dir_all(&data_dir);
+        let _ = fs::remove_dir_all(&restored_dir);
+    }
+
+    #[test]
+    fn test_sign_and_verify_message() {
+        let (mut keys, data_dir) = key_manager(None);
+        keys.initialize().unwrap();
+        let pubkey = keys.get_node_info().unwrap().pubkey;
+
+        let signature = keys.sign_message(b"opsource").unwrap();
+        assert_eq!(verify_message(b"opsource", &signature).unwrap(), pubkey);
+        assert_ne!(verify_message(b"tampered", &signature).unwrap(), pubkey);
+        assert!(verify_message(b"opsource", "notasignature").is_err());
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_key_rotation_keeps_identity() {
+        let (mut keys, data_dir) = key_manager(None);
+        keys.initialize().unwrap();
+        let pubkey = keys.get_node_info().unwrap().pubkey;
+        let channel_keys = keys.channel_keys(0).unwrap();
+        let destination = keys.derive_key(KeyFamily::Destination, 0).unwrap();
+        let node_key = keys.derive_key(KeyFamily::Node, 0).unwrap();
+        let linking_key = keys.lnurl_linking_key("example.com").unwrap();
+
+        assert!(keys.rotate_keys(KeyFamily::Node).is_err());
+        assert_eq!(keys.rotate_keys(KeyFamily::Channel).unwrap(), 1);
+        assert_eq!(keys.key_generation(KeyFamily::Channel).unwrap(), 1);
+
+        // Only the rotated family changes
+        assert_ne!(keys.channel_keys(0).unwrap().funding_key, channel_keys.funding_key);
+        assert_eq!(keys.derive_key(KeyFamily::Destination, 0).unwrap(), destination);
+        assert_eq!(keys.derive_key(KeyFamily::Node, 0).unwrap(), node_key);
+        assert_eq!(keys.lnurl_linking_key("example.com").unwrap(), linking_key);
+        assert_ne!(keys.lnurl_linking_key("example.org").unwrap(), linking_key);
+
+        // Generations survive re-encryption under a new passphrase
+        keys.change_passphrase("", "secret").unwrap();
+        assert_eq!(keys.key_generation(KeyFamily::Channel).unwrap(), 1);
+        keys.lock();
+        keys.unlock("secret").unwrap();
+        assert_eq!(keys.get_node_info().unwrap().pubkey, pubkey);
+        assert_eq!(keys.lnurl_linking_key("example.com").unwrap(), linking_key);
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_empty_passphrase_requires_opt_in() {
+        let (keys, data_dir) = key_manager(Some(""));
+        assert!(keys.unlock_with_configured_passphrase().is_err());
+        assert!(keys.is_locked());
+        assert!(!keys.has_seed());
+
+        let mut config = (*keys.config).clone();
+        config.lightning_allow_empty_seed_passphrase = true;
+        let keys = KeyManagerWrapper::new(&config);
+        keys.unlock_with_configured_passphrase().unwrap();
+        assert!(keys.has_seed());
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
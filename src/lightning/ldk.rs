@@ -2,38 +2,35 @@
 // Uses the Lightning Development Kit to provide a full Lightning Network node
 
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::path::PathBuf;
 use std::fs;
 
 use crate::lightning::interface::{
     LightningInterface, LightningError, LightningResult,
-    NodeInfo, ChannelInfo, Invoice, PaymentInfo, PaymentStatus,
+    NodeInfo, ChannelInfo, Invoice, PaymentInfo, PaymentStatus, OpenChannelOptions,
     LightningImplementationType
 };
 
 use crate::lightning::channel_manager::ChannelManagerWrapper;
 use crate::lightning::peer_manager::PeerManagerWrapper;
-use crate::lightning::key_manager::KeyManagerWrapper;
-use crate::lightning::invoice_manager::InvoiceManager;
+use crate::lightning::key_manager::{KeyManagerWrapper, KeyFamily, verify_message};
+use crate::lightning::invoice_manager::{InvoiceManager, InvoiceState};
 use crate::lightning::payment_router::PaymentRouter;
 use crate::lightning::payment_executor::PaymentExecutor;
+use crate::lightning::lnurl::{LnurlClient, LnurlRequest};
+use crate::lightning::forwarding::{
+    ForwardingManager, ForwardingPolicy, FeeMode, ChannelUpdate, ForwardingEvent
+};
+use crate::lightning::rebalancer::{
+    Rebalancer, RebalancePolicy, RebalanceResult, RebalanceReport
+};
 
 #[cfg(feature = "ldk")]
-use lightning::{
-    chain::keysinterface::{KeysManager, InMemorySigner},
-    ln::{
-        channelmanager::{ChannelManager, ChannelManagerReadArgs},
-        peerhandler::{PeerManager, SocketDescriptor},
-        msgs::SocketAddress,
-    },
-    util::{
-        config::UserConfig,
-        events::EventHandler,
-        ser::ReadableArgs,
-    },
-};
+use crate::lightning::channel_manager::splicing_unsupported;
+#[cfg(feature = "ldk")]
+use crate::lightning::ldk_node::LdkNode;
 
 /// LDK implementation of Lightning Network interface
 pub struct LdkLightningImplementation {
@@ -58,9 +55,22 @@ pub struct LdkLightningImplementation {
     /// Payment executor
     payment_executor: Arc<PaymentExecutor>,
     
+    /// LNURL and Lightning Address client
+    lnurl_client: Arc<LnurlClient>,
+    
+    /// HTLC forwarding manager
+    forwarding_manager: Arc<ForwardingManager>,
+    
+    /// Channel rebalancer
+    rebalancer: Arc<Rebalancer>,
+    
     /// Reference to Bitcoin interface
     bitcoin_interface: Arc<dyn crate::bitcoin::BitcoinInterface>,
     
+    /// Running LDK node (ChannelManager, ChainMonitor, background processor)
+    #[cfg(feature = "ldk")]
+    ldk_node: Mutex<Option<Arc<LdkNode>>>,
+    
     /// Initialization status
     initialized: Mutex<bool>,
 }
@@ -94,6 +104,23 @@ pub fn new(config: &crate::config::Config, bitcoin_interface: Arc<dyn crate::bit
             peer_manager.clone()
         ));
         
+        // LNURL-pay targets resolve to invoices inside the payment executor
+        let lnurl_client = Arc::new(LnurlClient::new(key_manager_arc.clone()));
+        payment_executor.attach_lnurl_client(lnurl_client.clone());
+        
+        // Create forwarding manager for routing HTLCs between our channels
+        let forwarding_manager = Arc::new(ForwardingManager::new(
+            channel_manager.clone(),
+            payment_router.clone()
+        ));
+        
+        // Create rebalancer for circular self-payments between our channels
+        let rebalancer = Arc::new(Rebalancer::new(
+            channel_manager.clone(),
+            invoice_manager.clone(),
+            payment_executor.clone()
+        ));
+        
         LdkLightningImplementation {
             config: Arc::new(config.clone()),
             key_manager,
@@ -102,7 +129,12 @@ pub fn new(config: &crate::config::Config, bitcoin_interface: Arc<dyn crate::bit
             invoice_manager,
             payment_router,
             payment_executor,
+            lnurl_client,
+            forwarding_manager,
+            rebalancer,
             bitcoin_interface,
+            #[cfg(feature = "ldk")]
+            ldk_node: Mutex::new(None),
             initialized: Mutex::new(false),
         }
     }
@@ -115,13 +147,38 @@ fn ensure_initialized(&self) -> LightningResult<()> {
             println!("Initializing LDK Lightning implementation...");
             
             // Initialize components
-            #[cfg(not(feature = "ldk"))]
+            #[cfg(feature = "ldk")]
             {
-                let mut peer_manager = self.peer_manager.clone();
-                peer_manager.initialize()?;
+                let keys_manager = self.key_manager.get_keys_manager().ok_or_else(|| {
+                    LightningError::ImplementationError("Key manager is locked".to_string())
+                })?;
                 
-                let mut channel_manager = self.channel_manager.clone();
-                channel_manager.initialize()?;
+                let ldk_node = Arc::new(LdkNode::start(
+                    &self.config,
+                    self.bitcoin_interface.clone(),
+                    keys_manager,
+                    self.key_manager.get_data_dir(),
+                    self.invoice_manager.clone(),
+                    self.payment_executor.clone(),
+                    self.forwarding_manager.clone(),
+                )?);
+                
+                // Route wrapper operations through the running node
+                self.channel_manager.attach_ldk(ldk_node.channel_manager());
+                if let Some(runtime) = ldk_node.runtime_handle() {
+                    self.peer_manager.attach_ldk(ldk_node.peer_manager(), ldk_node.network_graph(), runtime);
+                }
+                self.invoice_manager.attach_ldk(Arc::downgrade(&ldk_node));
+                self.payment_executor.attach_ldk(Arc::downgrade(&ldk_node));
+                self.forwarding_manager.attach_ldk(ldk_node.channel_manager())?;
+                
+                *self.ldk_node.lock().unwrap() = Some(ldk_node);
+            }
+            
+            #[cfg(not(feature = "ldk"))]
+            {
+                self.peer_manager.initialize()?;
+                self.channel_manager.initialize()?;
             }
             
             *initialized = true;
@@ -131,6 +188,16 @@ fn ensure_initialized(&self) -> LightningResult<()> {
         Ok(())
     }
     
+    /// Get the running LDK node, starting it if needed
+    #[cfg(feature = "ldk")]
+    pub fn ldk_node(&self) -> LightningResult<Arc<LdkNode>> {
+        self.ensure_initialized()?;
+        
+        self.ldk_node.lock().unwrap().clone().ok_or_else(|| {
+            LightningError::ImplementationError("LDK node not running".to_string())
+        })
+    }
+    
     /// Get current timestamp
     fn get_timestamp(&self) -> u64 {
         SystemTime::now()
@@ -173,6 +240,31 @@ fn get_node_info(&self) -> LightningResult<NodeInfo> {
         self.key_manager.get_node_info()
     }
     
+    fn unlock(&self, passphrase: &str) -> LightningResult<()> {
+        self.key_manager.unlock(passphrase)
+    }
+    
+    fn lock(&self) -> LightningResult<()> {
+        self.key_manager.lock();
+        Ok(())
+    }
+    
+    fn change_passphrase(&self, old_passphrase: &str, new_passphrase: &str) -> LightningResult<()> {
+        self.key_manager.change_passphrase(old_passphrase, new_passphrase)
+    }
+    
+    fn rotate_keys(&self, family: KeyFamily) -> LightningResult<u32> {
+        self.key_manager.rotate_keys(family)
+    }
+    
+    fn sign_message(&self, message: &str) -> LightningResult<String> {
+        self.key_manager.sign_message(message.as_bytes())
+    }
+    
+    fn verify_message(&self, message: &str, signature: &str) -> LightningResult<String> {
+        verify_message(message.as_bytes(), signature)
+    }
+    
     fn connect_peer(&self, node_pubkey: &str, host: &str, port: u16) -> LightningResult<()> {
         // Ensure we're initialized
         self.ensure_initialized()?;
@@ -193,9 +285,15 @@ fn open_channel(
         &self,
         node_pubkey: &str,
         capacity: u64,
-        push_msat: Option<u64>,
-        is_private: bool,
+        options: OpenChannelOptions,
     ) -> LightningResult<ChannelInfo> {
+        // LDK only opens single-funded channels; never drop the peer's contribution silently
+        if options.remote_funding_sat.is_some() {
+            return Err(LightningError::ChannelError(
+                "Dual-funded channels are not supported by the LDK implementation".to_string()
+            ));
+        }
+        
         // Ensure we're initialized
         self.ensure_initialized()?;
         
@@ -207,7 +305,7 @@ fn open_channel(
         }
         
         // Open channel using channel manager
-        self.channel_manager.open_channel(node_pubkey, capacity, push_msat, is_private)
+        self.channel_manager.open_channel(node_pubkey, capacity, &options)
     }
     
     fn list_channels(&self) -> LightningResult<Vec<ChannelInfo>> {
@@ -226,6 +324,61 @@ fn close_channel(&self, channel_id: &str, force: bool) -> LightningResult<String
         self.channel_manager.close_channel(channel_id, force)
     }
     
+    fn bump_commitment_fee(&self, channel_id: &str, fee_rate: u64) -> LightningResult<String> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // LDK reports force-closed anchor commitments through BumpTransaction events
+        #[cfg(feature = "ldk")]
+        {
+            self.ldk_node()?.bump_commitment_fee(channel_id, fee_rate)
+        }
+        
+        #[cfg(not(feature = "ldk"))]
+        {
+            let _ = fee_rate;
+            Err(LightningError::ChannelError(
+                format!("No anchor commitment to bump for channel {}", channel_id)
+            ))
+        }
+    }
+    
+    fn splice_in(&self, channel_id: &str, amount_sat: u64) -> LightningResult<ChannelInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // LDK cannot splice yet; refuse rather than splice the channel cache
+        #[cfg(feature = "ldk")]
+        {
+            let _ = (channel_id, amount_sat);
+            Err(splicing_unsupported())
+        }
+        
+        // Splice through channel manager
+        #[cfg(not(feature = "ldk"))]
+        {
+            self.channel_manager.splice_in(channel_id, amount_sat)
+        }
+    }
+    
+    fn splice_out(&self, channel_id: &str, address: &str, amount_sat: u64) -> LightningResult<ChannelInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // LDK cannot splice yet; refuse rather than splice the channel cache
+        #[cfg(feature = "ldk")]
+        {
+            let _ = (channel_id, address, amount_sat);
+            Err(splicing_unsupported())
+        }
+        
+        // Splice through channel manager
+        #[cfg(not(feature = "ldk"))]
+        {
+            self.channel_manager.splice_out(channel_id, address, amount_sat)
+        }
+    }
+    
     fn create_invoice(
         &self,
         amount_msat: Option<u64>,
@@ -239,6 +392,56 @@ fn create_invoice(
         self.invoice_manager.create_invoice(amount_msat, description, expiry)
     }
     
+    fn create_invoice_with_description_hash(
+        &self,
+        amount_msat: Option<u64>,
+        description_hash: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        self.invoice_manager.create_invoice_with_description_hash(amount_msat, description_hash, expiry)
+    }
+    
+    fn create_hold_invoice(
+        &self,
+        payment_hash: &str,
+        amount_msat: Option<u64>,
+        description: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Create hold invoice using invoice manager
+        self.invoice_manager.create_hold_invoice(payment_hash, amount_msat, description, expiry)
+    }
+    
+    fn settle_invoice(&self, payment_hash: &str, preimage: &str) -> LightningResult<()> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Settle invoice using invoice manager
+        self.invoice_manager.settle_invoice(payment_hash, preimage)
+    }
+    
+    fn cancel_invoice(&self, payment_hash: &str) -> LightningResult<()> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Cancel invoice using invoice manager
+        self.invoice_manager.cancel_invoice(payment_hash)
+    }
+    
+    fn get_invoice_state(&self, payment_hash: &str) -> LightningResult<InvoiceState> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Get invoice state from invoice manager
+        self.invoice_manager.get_invoice_state(payment_hash)
+    }
+    
     fn pay_invoice(&self, bolt11: &str, amount_msat: Option<u64>) -> LightningResult<PaymentInfo> {
         // Ensure we're initialized
         self.ensure_initialized()?;
@@ -247,6 +450,76 @@ fn pay_invoice(&self, bolt11: &str, amount_msat: Option<u64>) -> LightningResult
         self.payment_executor.pay_invoice(bolt11, amount_msat)
     }
     
+    fn pay_lnurl(&self, target: &str, amount_msat: u64, comment: Option<&str>) -> LightningResult<PaymentInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        let bolt11 = self.payment_executor.resolve_lnurl_invoice(target, Some(amount_msat), comment, &|bolt11| self.decode_invoice(bolt11))?;
+        self.pay_invoice(&bolt11, Some(amount_msat))
+    }
+    
+    fn withdraw_lnurl(&self, lnurl: &str, amount_msat: Option<u64>) -> LightningResult<Invoice> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        let withdraw = match self.lnurl_client.resolve(lnurl)? {
+            LnurlRequest::Withdraw(withdraw) => withdraw,
+            _ => return Err(LightningError::PaymentError(
+                format!("{} is not an LNURL-withdraw request", lnurl)
+            )),
+        };
+        
+        let amount_msat = amount_msat.unwrap_or(withdraw.max_withdrawable_msat);
+        if amount_msat == 0
+            || amount_msat < withdraw.min_withdrawable_msat
+            || amount_msat > withdraw.max_withdrawable_msat
+        {
+            return Err(LightningError::PaymentError(format!(
+                "Amount {} msat is outside the withdrawable range {}-{} msat",
+                amount_msat, withdraw.min_withdrawable_msat, withdraw.max_withdrawable_msat
+            )));
+        }
+        
+        // The service pays our invoice asynchronously once it accepts it
+        let invoice = self.create_invoice(Some(amount_msat), &withdraw.default_description, None)?;
+        self.lnurl_client.submit_withdraw(&withdraw, &invoice.bolt11)?;
+        
+        Ok(invoice)
+    }
+    
+    fn lnurl_auth(&self, lnurl: &str) -> LightningResult<String> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        match self.lnurl_client.resolve(lnurl)? {
+            LnurlRequest::Auth(auth) => self.lnurl_client.auth(&auth),
+            _ => Err(LightningError::InvoiceError(
+                format!("{} is not an LNURL-auth request", lnurl)
+            )),
+        }
+    }
+    
+    fn keysend(
+        &self,
+        node_pubkey: &str,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Send keysend payment using payment executor
+        self.payment_executor.keysend_payment_with_records(node_pubkey, amount_msat, None, custom_records)
+    }
+    
+    fn list_received_payments(&self) -> LightningResult<Vec<PaymentInfo>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // List received payments from payment executor
+        self.payment_executor.list_received_payments()
+    }
+    
     fn decode_invoice(&self, bolt11: &str) -> LightningResult<Invoice> {
         // Decode invoice using invoice manager
         self.invoice_manager.decode_invoice(bolt11)
@@ -268,6 +541,73 @@ fn list_payments(&self) -> LightningResult<Vec<PaymentInfo>> {
         self.payment_executor.list_payments()
     }
     
+    fn get_channel_policy(&self, channel_id: &str) -> LightningResult<ForwardingPolicy> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Get policy from forwarding manager
+        self.forwarding_manager.get_channel_policy(channel_id)
+    }
+    
+    fn update_channel_policy(
+        &self,
+        channel_id: Option<&str>,
+        policy: ForwardingPolicy,
+    ) -> LightningResult<Vec<ChannelUpdate>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Update a single channel or the default policy
+        match channel_id {
+            Some(channel_id) => self.forwarding_manager
+                .set_channel_policy(channel_id, policy)
+                .map(|update| vec![update]),
+            None => self.forwarding_manager.set_default_policy(policy),
+        }
+    }
+    
+    fn set_fee_mode(&self, mode: FeeMode) -> LightningResult<Vec<ChannelUpdate>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Set fee mode on forwarding manager
+        self.forwarding_manager.set_fee_mode(mode)
+    }
+    
+    fn list_forwards(&self) -> LightningResult<Vec<ForwardingEvent>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // List forwards from forwarding manager
+        Ok(self.forwarding_manager.list_forwards(None, None))
+    }
+    
+    fn rebalance_channel(
+        &self,
+        source_channel_id: &str,
+        target_channel_id: &str,
+        amount_sat: u64,
+        max_fee_msat: u64,
+    ) -> LightningResult<RebalanceResult> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Rebalance using rebalancer
+        self.rebalancer.rebalance(source_channel_id, target_channel_id, amount_sat, max_fee_msat)
+    }
+    
+    fn run_rebalance_autopilot(
+        &self,
+        policy: &RebalancePolicy,
+        dry_run: bool,
+    ) -> LightningResult<RebalanceReport> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Run autopilot on rebalancer
+        self.rebalancer.run_autopilot(policy, dry_run)
+    }
+    
     fn implementation_type(&self) -> LightningImplementationType {
         LightningImplementationType::LDK
     }
@@ -0,0 +1,1372 @@
+// LDK node runtime
+// Builds the real LDK ChannelManager, ChainMonitor, KeysManager, NetworkGraph,
+// scorer and BackgroundProcessor, and feeds them blocks from the Bitcoin interface
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lightning::bitcoin::{
+    BlockHash, BlockHeader as LdkBlockHeader, Network, PackedLockTime, Script, Transaction, TxMerkleNode,
+    TxOut,
+};
+use lightning::bitcoin::consensus::encode::deserialize;
+use lightning::bitcoin::hashes::Hash;
+use lightning::bitcoin::secp256k1::{PublicKey, Secp256k1};
+use lightning::chain::{BestBlock, ChannelMonitorUpdateStatus, Confirm, Filter, Watch};
+use lightning::chain::chaininterface::{BroadcasterInterface, ConfirmationTarget, FeeEstimator};
+use lightning::chain::chainmonitor;
+use lightning::chain::transaction::OutPoint as LdkOutPoint;
+use lightning::events::{Event, PaymentPurpose};
+use lightning::events::bump_transaction::{AnchorDescriptor, BumpTransactionEvent};
+use lightning::ln::{PaymentHash, PaymentPreimage};
+use lightning::ln::channelmanager::{
+    provided_channel_features, provided_node_features, ChainParameters, ChannelManager,
+    ChannelManagerReadArgs, PaymentId, RecipientOnionFields, Retry,
+};
+use lightning::ln::peer_handler::{IgnoringMessageHandler, MessageHandler, PeerManager};
+use lightning::routing::gossip::{NetworkGraph, P2PGossipSync};
+use lightning::routing::router::{find_route, DefaultRouter, PaymentParameters, RouteHop, RouteParameters};
+use lightning::routing::scoring::{
+    ProbabilisticScorer, ProbabilisticScoringDecayParameters, ProbabilisticScoringFeeParameters,
+};
+use lightning::routing::utxo::{UtxoLookup, UtxoLookupError, UtxoResult};
+use lightning::sign::{EcdsaChannelSigner, EntropySource, InMemorySigner, KeysManager, SpendableOutputDescriptor};
+use lightning::util::config::UserConfig;
+use lightning::util::logger::{Level, Logger, Record};
+use lightning::util::persist::read_channel_monitors;
+use lightning::util::ser::ReadableArgs;
+use lightning_background_processor::{BackgroundProcessor, GossipSync};
+use lightning_invoice::{Bolt11Invoice as LdkInvoice, Currency};
+use lightning_invoice::payment::{pay_invoice, pay_zero_value_invoice};
+use lightning_invoice::utils::create_invoice_from_channelmanager_and_duration_since_epoch_with_payment_hash;
+use lightning_net_tokio::SocketDescriptor;
+use lightning_persister::fs_store::FilesystemStore;
+
+use crate::bitcoin::{
+    AddressType, BitcoinInterface, BitcoinTransaction, BlockHeader, TransactionInput, TransactionOutput,
+};
+use crate::bitcoin::encoding::{
+    self, address_to_script_pubkey, from_hex, network_hrp, script_pubkey_to_address, to_hex,
+};
+use crate::lightning::forwarding::ForwardingManager;
+use crate::lightning::interface::{LightningError, LightningResult};
+use crate::lightning::invoice_manager::{InvoiceManager, InvoiceState, spawn_invoice_maintenance};
+use crate::lightning::payment_executor::{PaymentExecutor, KEYSEND_PREIMAGE_TLV_TYPE};
+
+/// Chain monitor persisting channel monitors to the data directory
+pub type LdkChainMonitor = chainmonitor::ChainMonitor<
+    InMemorySigner,
+    Arc<dyn Filter + Send + Sync>,
+    Arc<ChainClient>,
+    Arc<ChainClient>,
+    Arc<NodeLogger>,
+    Arc<FilesystemStore>,
+>;
+
+/// Network graph learned from gossip
+pub type LdkNetworkGraph = NetworkGraph<Arc<NodeLogger>>;
+
+/// Probabilistic scorer used by the router
+pub type LdkScorer = ProbabilisticScorer<Arc<LdkNetworkGraph>, Arc<NodeLogger>>;
+
+/// Router over the network graph and scorer
+pub type LdkRouter = DefaultRouter<
+    Arc<LdkNetworkGraph>,
+    Arc<NodeLogger>,
+    Arc<Mutex<LdkScorer>>,
+    ProbabilisticScoringFeeParameters,
+    LdkScorer,
+>;
+
+/// Channel manager wired to our chain client, keys and router
+pub type LdkChannelManager = ChannelManager<
+    Arc<LdkChainMonitor>,
+    Arc<ChainClient>,
+    Arc<KeysManager>,
+    Arc<KeysManager>,
+    Arc<KeysManager>,
+    Arc<ChainClient>,
+    Arc<LdkRouter>,
+    Arc<NodeLogger>,
+>;
+
+/// Gossip handler validating channel announcements against the chain
+pub type LdkGossipSync = P2PGossipSync<Arc<LdkNetworkGraph>, Arc<ChainClient>, Arc<NodeLogger>>;
+
+/// Peer manager over tokio sockets
+pub type LdkPeerManager = PeerManager<
+    SocketDescriptor,
+    Arc<LdkChannelManager>,
+    Arc<LdkGossipSync>,
+    IgnoringMessageHandler,
+    Arc<NodeLogger>,
+    IgnoringMessageHandler,
+    Arc<KeysManager>,
+>;
+
+/// How often the chain is polled for new blocks
+const CHAIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of recent blocks remembered for reorg detection
+const REORG_SAFETY_DEPTH: usize = 144;
+
+/// Number of times LDK retries a failed payment
+const PAYMENT_RETRY_ATTEMPTS: usize = 3;
+
+/// Final hop CLTV delta used for keysend payments
+const KEYSEND_FINAL_CLTV_EXPIRY_DELTA: u32 = 40;
+
+/// LDK's minimum fee rate (253 sat per 1000 weight units)
+const MIN_FEERATE_SAT_PER_KW: u32 = 253;
+
+/// Value of an anchor output on a commitment transaction
+const ANCHOR_OUTPUT_SAT: u64 = 330;
+
+/// Estimated virtual size of an anchor-spending child with one wallet input and change
+const ANCHOR_CHILD_VSIZE: u64 = 180;
+
+/// Chain access for LDK backed by our Bitcoin interface
+///
+/// Acts as the broadcaster, fee estimator and UTXO lookup for LDK.
+pub struct ChainClient {
+    /// Bitcoin interface
+    bitcoin_interface: Arc<dyn BitcoinInterface>,
+
+    /// Address HRP for the configured network
+    hrp: &'static str,
+}
+
+impl ChainClient {
+    /// Create a chain client over a Bitcoin interface
+    pub fn new(bitcoin_interface: Arc<dyn BitcoinInterface>, network: &str) -> Self {
+        ChainClient {
+            bitcoin_interface,
+            hrp: network_hrp(network),
+        }
+    }
+}
+
+impl BroadcasterInterface for ChainClient {
+    fn broadcast_transactions(&self, txs: &[&Transaction]) {
+        for tx in txs {
+            let transaction = from_ldk_transaction(tx, self.hrp);
+            match self.bitcoin_interface.broadcast_transaction(&transaction) {
+                Ok(txid) => println!("Broadcast LDK transaction: {}", txid),
+                Err(e) => eprintln!("Failed to broadcast LDK transaction {}: {}", transaction.txid, e),
+            }
+        }
+    }
+}
+
+impl FeeEstimator for ChainClient {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        let target_blocks = match confirmation_target {
+            ConfirmationTarget::HighPriority => 6,
+            ConfirmationTarget::Normal => 18,
+            _ => 144,
+        };
+
+        // sat/vB to sat per 1000 weight units
+        let sat_per_vbyte = self.bitcoin_interface.estimate_fee(target_blocks).unwrap_or(1);
+        ((sat_per_vbyte * 250) as u32).max(MIN_FEERATE_SAT_PER_KW)
+    }
+}
+
+impl UtxoLookup for ChainClient {
+    fn get_utxo(&self, _genesis_hash: &BlockHash, short_channel_id: u64) -> UtxoResult {
+        let height = (short_channel_id >> 40) as u32;
+        let tx_index = ((short_channel_id >> 16) & 0xFF_FFFF) as usize;
+        let vout = (short_channel_id & 0xFFFF) as usize;
+
+        let output = self.bitcoin_interface.get_block_hash(height)
+            .and_then(|hash| self.bitcoin_interface.get_block(&hash))
+            .ok()
+            .and_then(|transactions| transactions.get(tx_index)
+                .and_then(|tx| tx.outputs.get(vout).cloned()));
+
+        UtxoResult::Sync(match output {
+            Some(output) => Ok(TxOut {
+                value: output.value,
+                script_pubkey: Script::from(output.script_pubkey),
+            }),
+            None => Err(UtxoLookupError::UnknownTx),
+        })
+    }
+}
+
+/// Logger printing LDK records at or above a minimum level
+pub struct NodeLogger {
+    /// Lowest level that gets printed
+    min_level: Level,
+}
+
+impl Logger for NodeLogger {
+    fn log(&self, record: &Record) {
+        if record.level < self.min_level {
+            return;
+        }
+
+        println!("LDK {} [{}:{}] {}", record.level, record.module_path, record.line, record.args);
+    }
+}
+
+/// Feeds blocks from the Bitcoin interface to the ChannelManager and ChainMonitor
+///
+/// Uses LDK's Confirm interface: every block's transactions are handed over and
+/// LDK filters what is relevant. Blocks that leave the best chain are detected by
+/// comparing the remembered hashes against the chain, and their transactions are
+/// unconfirmed before the new branch is connected.
+struct ChainSync {
+    /// Bitcoin interface
+    bitcoin_interface: Arc<dyn BitcoinInterface>,
+
+    /// Channel manager
+    channel_manager: Arc<LdkChannelManager>,
+
+    /// Chain monitor
+    chain_monitor: Arc<LdkChainMonitor>,
+
+    /// Recently connected blocks (height, hash), oldest first
+    recent_blocks: Mutex<Vec<(u32, String)>>,
+}
+
+impl ChainSync {
+    /// Bring LDK up to the current chain tip
+    fn sync(&self) -> LightningResult<()> {
+        let mut recent_blocks = self.recent_blocks.lock().unwrap();
+        let tip_height = self.bitcoin_interface.get_block_height()?;
+
+        // Roll back blocks that are no longer part of the best chain
+        let mut disconnected = HashSet::new();
+        while let Some((height, hash)) = recent_blocks.last().cloned() {
+            if height <= tip_height && self.bitcoin_interface.get_block_hash(height)? == hash {
+                break;
+            }
+            recent_blocks.pop();
+            disconnected.insert(hash);
+        }
+
+        if !disconnected.is_empty() {
+            for confirmable in self.confirmables() {
+                for (txid, block_hash) in confirmable.get_relevant_txids() {
+                    if block_hash.map_or(false, |hash| disconnected.contains(&hash.to_string())) {
+                        confirmable.transaction_unconfirmed(&txid);
+                    }
+                }
+            }
+        }
+
+        let start_height = match recent_blocks.last() {
+            Some((height, _)) => height + 1,
+            None => self.channel_manager.current_best_block().height() + 1,
+        };
+
+        for height in start_height..=tip_height {
+            let hash = self.connect_block(height)?;
+            recent_blocks.push((height, hash));
+        }
+
+        let excess = recent_blocks.len().saturating_sub(REORG_SAFETY_DEPTH);
+        recent_blocks.drain(..excess);
+
+        Ok(())
+    }
+
+    /// Hand one block to LDK and return its hash
+    fn connect_block(&self, height: u32) -> LightningResult<String> {
+        let hash = self.bitcoin_interface.get_block_hash(height)?;
+        let header = to_ldk_header(&self.bitcoin_interface.get_block_header(&hash)?)?;
+
+        // Transactions LDK cannot decode cannot belong to its channels
+        let transactions: Vec<(usize, Transaction)> = self.bitcoin_interface.get_block(&hash)?
+            .iter()
+            .enumerate()
+            .filter_map(|(index, tx)| to_ldk_transaction(tx).ok().map(|tx| (index, tx)))
+            .collect();
+        let txdata: Vec<(usize, &Transaction)> = transactions.iter()
+            .map(|(index, tx)| (*index, tx))
+            .collect();
+
+        for confirmable in self.confirmables() {
+            confirmable.transactions_confirmed(&header, &txdata, height);
+            confirmable.best_block_updated(&header, height);
+        }
+
+        Ok(hash)
+    }
+
+    /// LDK components that need to hear about the chain
+    fn confirmables(&self) -> [&dyn Confirm; 2] {
+        [&*self.channel_manager as &dyn Confirm, &*self.chain_monitor as &dyn Confirm]
+    }
+}
+
+/// A force-closed commitment LDK asked us to get confirmed
+struct AnchorClaim {
+    /// Commitment transaction
+    commitment_tx: Transaction,
+
+    /// Fee paid by the commitment itself
+    commitment_fee: u64,
+
+    /// Our anchor output on the commitment
+    anchor_descriptor: AnchorDescriptor,
+}
+
+/// Anchor commitments awaiting confirmation, bumped with CPFP from the on-chain wallet
+struct AnchorClaims {
+    /// Bitcoin interface funding the child transactions
+    bitcoin_interface: Arc<dyn BitcoinInterface>,
+
+    /// Keys manager deriving the channel signers
+    keys_manager: Arc<KeysManager>,
+
+    /// Address HRP for the configured network
+    hrp: &'static str,
+
+    /// Claims by channel ID
+    claims: Mutex<HashMap<String, AnchorClaim>>,
+}
+
+impl AnchorClaims {
+    /// Remember a commitment and bump it to LDK's target fee rate
+    fn handle(&self, commitment_tx: Transaction, commitment_fee: u64, anchor_descriptor: AnchorDescriptor, feerate_sat_per_kw: u32) {
+        let funding = commitment_tx.input[0].previous_output;
+        let channel_id = to_hex(&LdkOutPoint { txid: funding.txid, index: funding.vout as u16 }.to_channel_id());
+
+        self.claims.lock().unwrap().insert(channel_id.clone(), AnchorClaim {
+            commitment_tx,
+            commitment_fee,
+            anchor_descriptor,
+        });
+
+        // sat per 1000 weight units to sat/vB, rounding up
+        let fee_rate = (feerate_sat_per_kw as u64 * 4).div_ceil(1000);
+        match self.bump(&channel_id, fee_rate) {
+            Ok(txid) => println!("Bumped commitment of channel {} with child {}", channel_id, txid),
+            Err(e) => eprintln!("Failed to bump commitment of channel {}: {}", channel_id, e),
+        }
+    }
+
+    /// CPFP a channel's commitment so the package pays `fee_rate` sat/vB
+    fn bump(&self, channel_id: &str, fee_rate: u64) -> LightningResult<String> {
+        let claims = self.claims.lock().unwrap();
+        let claim = claims.get(channel_id).ok_or_else(|| LightningError::ChannelError(
+            format!("No anchor commitment to bump for channel {}", channel_id)
+        ))?;
+
+        let commitment_vsize = (claim.commitment_tx.weight() as u64).div_ceil(4);
+        let package_fee = fee_rate * (commitment_vsize + ANCHOR_CHILD_VSIZE);
+        if package_fee <= claim.commitment_fee {
+            return Err(LightningError::ChannelError(format!(
+                "Commitment {} already pays at least {} sat/vB", claim.commitment_tx.txid(), fee_rate
+            )));
+        }
+
+        // Fund a child spending our anchor, then sign the anchor input once the transaction is final
+        let unsigned = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![claim.anchor_descriptor.unsigned_tx_input()],
+            output: Vec::new(),
+        };
+        let funded = self.bitcoin_interface.fund_transaction(
+            &from_ldk_transaction(&unsigned, self.hrp),
+            ANCHOR_OUTPUT_SAT,
+            package_fee - claim.commitment_fee,
+        )?;
+        let mut child = to_ldk_transaction(&funded)?;
+
+        let signer = claim.anchor_descriptor.derive_channel_signer(&self.keys_manager);
+        let signature = signer.sign_holder_anchor_input(&child, 0, &Secp256k1::new())
+            .map_err(|_| LightningError::ChannelError("Failed to sign anchor input".to_string()))?;
+        child.input[0].witness = claim.anchor_descriptor.tx_input_witness(&signature);
+
+        Ok(self.bitcoin_interface.broadcast_transaction(&from_ldk_transaction(&child, self.hrp))?)
+    }
+}
+
+/// Handles events emitted by LDK
+struct NodeEventHandler {
+    /// Channel manager
+    channel_manager: Arc<LdkChannelManager>,
+
+    /// Keys manager
+    keys_manager: Arc<KeysManager>,
+
+    /// Chain client
+    chain_client: Arc<ChainClient>,
+
+    /// Bitcoin interface
+    bitcoin_interface: Arc<dyn BitcoinInterface>,
+
+    /// Invoice manager
+    invoice_manager: Arc<InvoiceManager>,
+
+    /// Payment executor
+    payment_executor: Arc<PaymentExecutor>,
+
+    /// Forwarding manager whose policies new channels take on
+    forwarding_manager: Arc<ForwardingManager>,
+
+    /// Peers whose channels we accept as zero-conf
+    trusted_peers: HashSet<String>,
+
+    /// Anchor commitments to bump
+    anchor_claims: Arc<AnchorClaims>,
+}
+
+impl NodeEventHandler {
+    /// Handle a single LDK event
+    fn handle_event(&self, event: Event) {
+        match event {
+            Event::FundingGenerationReady {
+                temporary_channel_id,
+                counterparty_node_id,
+                channel_value_satoshis,
+                output_script,
+                ..
+            } => {
+                let result = self.fund_channel(&output_script, channel_value_satoshis)
+                    .and_then(|funding_tx| self.channel_manager
+                        .funding_transaction_generated(&temporary_channel_id, &counterparty_node_id, funding_tx)
+                        .map_err(|e| LightningError::ChannelError(format!("{:?}", e))));
+
+                if let Err(e) = result {
+                    eprintln!("Failed to fund channel {}: {}", to_hex(&temporary_channel_id), e);
+                    let _ = self.channel_manager
+                        .force_close_without_broadcasting_txn(&temporary_channel_id, &counterparty_node_id);
+                }
+            }
+            Event::OpenChannelRequest { temporary_channel_id, counterparty_node_id, .. } => {
+                let user_channel_id = u128::from_be_bytes(
+                    self.keys_manager.get_secure_random_bytes()[..16].try_into().unwrap()
+                );
+                let result = if self.trusted_peers.contains(&to_hex(&counterparty_node_id.serialize())) {
+                    self.channel_manager.accept_inbound_channel_from_trusted_peer_0conf(
+                        &temporary_channel_id, &counterparty_node_id, user_channel_id,
+                    )
+                } else {
+                    self.channel_manager.accept_inbound_channel(
+                        &temporary_channel_id, &counterparty_node_id, user_channel_id,
+                    )
+                };
+
+                if let Err(e) = result {
+                    eprintln!("Failed to accept channel from {}: {:?}", counterparty_node_id, e);
+                }
+            }
+            Event::BumpTransaction(BumpTransactionEvent::ChannelClose {
+                package_target_feerate_sat_per_1000_weight,
+                commitment_tx,
+                commitment_tx_fee_satoshis,
+                anchor_descriptor,
+                ..
+            }) => {
+                self.anchor_claims.handle(
+                    commitment_tx,
+                    commitment_tx_fee_satoshis,
+                    anchor_descriptor,
+                    package_target_feerate_sat_per_1000_weight,
+                );
+            }
+            Event::PaymentClaimable {
+                payment_hash,
+                amount_msat,
+                purpose,
+                via_channel_id,
+                claim_deadline,
+                onion_fields,
+                ..
+            } => {
+                let payment_hash_hex = to_hex(&payment_hash.0);
+                let channel_id = via_channel_id.map(|id| to_hex(&id)).unwrap_or_default();
+
+                // Invoice payments are settled or held by the invoice manager; the
+                // invoice subscription claims or fails the HTLCs once resolved
+                let invoice_result = self.invoice_manager.receive_htlc(
+                    &payment_hash_hex,
+                    &channel_id,
+                    0,
+                    amount_msat,
+                    claim_deadline.unwrap_or(0),
+                );
+
+                match (invoice_result, purpose) {
+                    (Ok(_), _) => {}
+                    (Err(_), PaymentPurpose::SpontaneousPayment(preimage)) => {
+                        let mut records: BTreeMap<u64, Vec<u8>> = onion_fields
+                            .map(|onion_fields| onion_fields.custom_tlvs().iter().cloned().collect())
+                            .unwrap_or_default();
+                        records.insert(KEYSEND_PREIMAGE_TLV_TYPE, preimage.0.to_vec());
+
+                        match self.payment_executor.receive_keysend(&payment_hash_hex, amount_msat, records) {
+                            Ok(_) => self.channel_manager.claim_funds(preimage),
+                            Err(e) => {
+                                eprintln!("Rejected keysend payment {}: {}", payment_hash_hex, e);
+                                self.channel_manager.fail_htlc_backwards(&payment_hash);
+                            }
+                        }
+                    }
+                    (Err(e), _) => {
+                        eprintln!("Rejected payment {}: {}", payment_hash_hex, e);
+                        self.channel_manager.fail_htlc_backwards(&payment_hash);
+                    }
+                }
+            }
+            Event::PaymentClaimed { payment_hash, amount_msat, .. } => {
+                println!("Claimed payment {} for {} msat", to_hex(&payment_hash.0), amount_msat);
+            }
+            Event::PaymentSent { payment_hash, payment_preimage, fee_paid_msat, .. } => {
+                self.payment_executor.handle_payment_sent(
+                    &to_hex(&payment_hash.0),
+                    &to_hex(&payment_preimage.0),
+                    fee_paid_msat.unwrap_or(0),
+                );
+            }
+            Event::PaymentFailed { payment_hash, .. } => {
+                self.payment_executor.handle_payment_failed(&to_hex(&payment_hash.0));
+            }
+            Event::PendingHTLCsForwardable { .. } => {
+                self.channel_manager.process_pending_htlc_forwards();
+            }
+            Event::SpendableOutputs { outputs } => {
+                if let Err(e) = self.sweep_spendable_outputs(&outputs) {
+                    eprintln!("Failed to sweep spendable outputs: {}", e);
+                }
+            }
+            Event::ChannelReady { channel_id, counterparty_node_id, .. } => {
+                println!("Channel {} with {} is ready", to_hex(&channel_id), counterparty_node_id);
+
+                // LDK opened the channel with its default config
+                if let Err(e) = self.forwarding_manager.sync_node_policies() {
+                    eprintln!("Failed to apply forwarding policy to channel {}: {}", to_hex(&channel_id), e);
+                }
+            }
+            Event::ChannelClosed { channel_id, reason, .. } => {
+                println!("Channel {} closed: {}", to_hex(&channel_id), reason);
+            }
+            _ => {}
+        }
+    }
+
+    /// Build a funding transaction paying the channel's output script from our wallet
+    fn fund_channel(&self, output_script: &Script, value: u64) -> LightningResult<Transaction> {
+        let address = script_pubkey_to_address(output_script.as_bytes(), self.chain_client.hrp)
+            .ok_or_else(|| LightningError::ChannelError("Unsupported funding script".to_string()))?;
+        let fee_rate = self.bitcoin_interface.estimate_fee(6)?;
+        let funding_tx = self.bitcoin_interface.create_transaction(vec![(address, value)], fee_rate)?;
+
+        to_ldk_transaction(&funding_tx)
+    }
+
+    /// Spend outputs LDK handed back to us (e.g. after a close) to a wallet address
+    fn sweep_spendable_outputs(&self, outputs: &[SpendableOutputDescriptor]) -> LightningResult<()> {
+        let address = self.bitcoin_interface.generate_address(AddressType::P2WPKH)?;
+        let destination = Script::from(address_to_script_pubkey(&address.address)?);
+        let descriptors: Vec<&SpendableOutputDescriptor> = outputs.iter().collect();
+        let fee_rate = self.chain_client.get_est_sat_per_1000_weight(ConfirmationTarget::Background);
+
+        let sweep = self.keys_manager
+            .spend_spendable_outputs(&descriptors, Vec::new(), destination, fee_rate, &Secp256k1::new())
+            .map_err(|_| LightningError::ImplementationError("Failed to build sweep transaction".to_string()))?;
+        self.chain_client.broadcast_transactions(&[&sweep]);
+
+        Ok(())
+    }
+}
+
+/// A running LDK node
+///
+/// Owns the LDK components plus the background processor, the chain poller and the
+/// socket runtime. Dropping the node stops everything and persists the ChannelManager.
+pub struct LdkNode {
+    /// Channel manager
+    channel_manager: Arc<LdkChannelManager>,
+
+    /// Peer manager
+    peer_manager: Arc<LdkPeerManager>,
+
+    /// Network graph
+    network_graph: Arc<LdkNetworkGraph>,
+
+    /// Scorer shared with the router
+    scorer: Arc<Mutex<LdkScorer>>,
+
+    /// Keys manager
+    keys_manager: Arc<KeysManager>,
+
+    /// Logger
+    logger: Arc<NodeLogger>,
+
+    /// Invoice currency for the configured network
+    currency: Currency,
+
+    /// Chain synchronisation state
+    chain_sync: Arc<ChainSync>,
+
+    /// Force-closed anchor commitments awaiting confirmation
+    anchor_claims: Arc<AnchorClaims>,
+
+    /// Tokio runtime driving peer sockets
+    runtime: Mutex<Option<tokio::runtime::Runtime>>,
+
+    /// Background processor (persistence, timers, event processing)
+    background_processor: Mutex<Option<BackgroundProcessor>>,
+
+    /// Chain poller and invoice watcher threads
+    threads: Mutex<Vec<JoinHandle<()>>>,
+
+    /// Stop signal for the threads
+    stop_signal: Arc<AtomicBool>,
+}
+
+impl LdkNode {
+    /// Build the LDK components, restore persisted state and start processing
+    pub fn start(
+        config: &crate::config::Config,
+        bitcoin_interface: Arc<dyn BitcoinInterface>,
+        keys_manager: Arc<KeysManager>,
+        data_dir: &Path,
+        invoice_manager: Arc<InvoiceManager>,
+        payment_executor: Arc<PaymentExecutor>,
+        forwarding_manager: Arc<ForwardingManager>,
+    ) -> LightningResult<LdkNode> {
+        let network_name = config.bitcoin_network.clone().unwrap_or_else(|| "testnet".to_string());
+        let network = parse_network(&network_name);
+
+        fs::create_dir_all(data_dir).map_err(|e| {
+            LightningError::ImplementationError(format!("Failed to create data directory: {}", e))
+        })?;
+
+        let logger = Arc::new(NodeLogger { min_level: Level::Info });
+        let chain_client = Arc::new(ChainClient::new(bitcoin_interface.clone(), &network_name));
+        let persister = Arc::new(FilesystemStore::new(data_dir.to_path_buf()));
+
+        let chain_monitor: Arc<LdkChainMonitor> = Arc::new(chainmonitor::ChainMonitor::new(
+            None,
+            chain_client.clone(),
+            logger.clone(),
+            chain_client.clone(),
+            persister.clone(),
+        ));
+
+        // Restore channel monitors
+        let mut channel_monitors = read_channel_monitors(persister.clone(), keys_manager.clone(), keys_manager.clone())
+            .map_err(|e| LightningError::ImplementationError(format!("Failed to read channel monitors: {}", e)))?;
+
+        // Restore or create the network graph and scorer
+        let network_graph = Arc::new(
+            read_persisted(data_dir, "network_graph", |reader| {
+                NetworkGraph::read(reader, logger.clone())
+            }).unwrap_or_else(|| NetworkGraph::new(network, logger.clone()))
+        );
+        let decay_params = ProbabilisticScoringDecayParameters::default();
+        let scorer = Arc::new(Mutex::new(
+            read_persisted(data_dir, "scorer", |reader| {
+                ProbabilisticScorer::read(reader, (decay_params, network_graph.clone(), logger.clone()))
+            }).unwrap_or_else(|| ProbabilisticScorer::new(decay_params, network_graph.clone(), logger.clone()))
+        ));
+        let router = Arc::new(DefaultRouter::new(
+            network_graph.clone(),
+            logger.clone(),
+            keys_manager.get_secure_random_bytes(),
+            scorer.clone(),
+            ProbabilisticScoringFeeParameters::default(),
+        ));
+
+        let mut user_config = UserConfig::default();
+        user_config.channel_handshake_limits.force_announced_channel_preference = false;
+        // Inbound channels are accepted by the event handler, zero-conf for trusted peers
+        user_config.manually_accept_inbound_channels = true;
+        user_config.channel_handshake_config.negotiate_anchors_zero_fee_htlc_tx = true;
+
+        // Restore the channel manager, or start a fresh one at the current tip
+        let manager_path = data_dir.join("manager");
+        let channel_manager = if manager_path.exists() {
+            let file = fs::File::open(&manager_path).map_err(|e| {
+                LightningError::ImplementationError(format!("Failed to open channel manager: {}", e))
+            })?;
+            let read_args = ChannelManagerReadArgs::new(
+                keys_manager.clone(),
+                keys_manager.clone(),
+                keys_manager.clone(),
+                chain_client.clone(),
+                chain_monitor.clone(),
+                chain_client.clone(),
+                router.clone(),
+                logger.clone(),
+                user_config,
+                channel_monitors.iter_mut().map(|(_, monitor)| monitor).collect(),
+            );
+            let (_, channel_manager) = <(BlockHash, LdkChannelManager)>::read(&mut BufReader::new(file), read_args)
+                .map_err(|e| LightningError::ImplementationError(format!("Failed to read channel manager: {:?}", e)))?;
+            channel_manager
+        } else {
+            let best_block = current_best_block(bitcoin_interface.as_ref(), network);
+            ChannelManager::new(
+                chain_client.clone(),
+                chain_monitor.clone(),
+                chain_client.clone(),
+                router,
+                logger.clone(),
+                keys_manager.clone(),
+                keys_manager.clone(),
+                keys_manager.clone(),
+                user_config,
+                ChainParameters { network, best_block },
+            )
+        };
+        let channel_manager = Arc::new(channel_manager);
+
+        // Hand the restored monitors to the chain monitor
+        for (_, monitor) in channel_monitors {
+            let (funding_outpoint, _) = monitor.get_funding_txo();
+            if chain_monitor.watch_channel(funding_outpoint, monitor) != ChannelMonitorUpdateStatus::Completed {
+                return Err(LightningError::ImplementationError(
+                    format!("Failed to restore channel monitor for {}", funding_outpoint.txid)
+                ));
+            }
+        }
+
+        // Networking
+        let gossip_sync = Arc::new(P2PGossipSync::new(
+            network_graph.clone(),
+            Some(chain_client.clone()),
+            logger.clone(),
+        ));
+        let message_handler = MessageHandler {
+            chan_handler: channel_manager.clone(),
+            route_handler: gossip_sync.clone(),
+            onion_message_handler: IgnoringMessageHandler {},
+            custom_message_handler: IgnoringMessageHandler {},
+        };
+        let peer_manager: Arc<LdkPeerManager> = Arc::new(PeerManager::new(
+            message_handler,
+            unix_time().as_secs() as u32,
+            &keys_manager.get_secure_random_bytes(),
+            logger.clone(),
+            keys_manager.clone(),
+        ));
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| LightningError::ImplementationError(format!("Failed to start runtime: {}", e)))?;
+        if let Some(listen_addr) = config.lightning_listen_addr.clone() {
+            runtime.spawn(accept_inbound_connections(peer_manager.clone(), listen_addr));
+        }
+
+        // Catch up with the chain before processing events
+        let chain_sync = Arc::new(ChainSync {
+            bitcoin_interface: bitcoin_interface.clone(),
+            channel_manager: channel_manager.clone(),
+            chain_monitor: chain_monitor.clone(),
+            recent_blocks: Mutex::new(Vec::new()),
+        });
+        chain_sync.sync()?;
+
+        let anchor_claims = Arc::new(AnchorClaims {
+            bitcoin_interface: bitcoin_interface.clone(),
+            keys_manager: keys_manager.clone(),
+            hrp: chain_client.hrp,
+            claims: Mutex::new(HashMap::new()),
+        });
+        let event_handler = NodeEventHandler {
+            channel_manager: channel_manager.clone(),
+            keys_manager: keys_manager.clone(),
+            chain_client: chain_client.clone(),
+            bitcoin_interface: bitcoin_interface.clone(),
+            invoice_manager: invoice_manager.clone(),
+            payment_executor,
+            forwarding_manager,
+            trusted_peers: config.lightning_trusted_peers.iter().map(|pubkey| pubkey.to_lowercase()).collect(),
+            anchor_claims: anchor_claims.clone(),
+        };
+        let background_processor = BackgroundProcessor::start(
+            persister,
+            move |event: Event| event_handler.handle_event(event),
+            chain_monitor.clone(),
+            channel_manager.clone(),
+            GossipSync::p2p(gossip_sync),
+            peer_manager.clone(),
+            logger.clone(),
+            Some(scorer.clone()),
+        );
+
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let threads = vec![
+            spawn_chain_poller(chain_sync.clone(), stop_signal.clone()),
+            spawn_invoice_maintenance(invoice_manager.clone(), bitcoin_interface.clone(), stop_signal.clone()),
+            spawn_invoice_watcher(invoice_manager, channel_manager.clone(), stop_signal.clone()),
+        ];
+
+        println!("Started LDK node {} on {}", channel_manager.get_our_node_id(), network);
+
+        Ok(LdkNode {
+            channel_manager,
+            peer_manager,
+            network_graph,
+            scorer,
+            keys_manager,
+            logger,
+            currency: Currency::from(network),
+            chain_sync,
+            anchor_claims,
+            runtime: Mutex::new(Some(runtime)),
+            background_processor: Mutex::new(Some(background_processor)),
+            threads: Mutex::new(threads),
+            stop_signal,
+        })
+    }
+
+    /// Get the channel manager
+    pub fn channel_manager(&self) -> Arc<LdkChannelManager> {
+        self.channel_manager.clone()
+    }
+
+    /// Get the peer manager
+    pub fn peer_manager(&self) -> Arc<LdkPeerManager> {
+        self.peer_manager.clone()
+    }
+
+    /// Get the network graph
+    pub fn network_graph(&self) -> Arc<LdkNetworkGraph> {
+        self.network_graph.clone()
+    }
+
+    /// Get a handle to the runtime driving peer sockets
+    pub fn runtime_handle(&self) -> Option<tokio::runtime::Handle> {
+        self.runtime.lock().unwrap().as_ref().map(|runtime| runtime.handle().clone())
+    }
+
+    /// Bring LDK up to the current chain tip without waiting for the poller
+    pub fn sync_chain(&self) -> LightningResult<()> {
+        self.chain_sync.sync()
+    }
+
+    /// CPFP a force-closed anchor channel's commitment up to `fee_rate` sat/vB
+    pub fn bump_commitment_fee(&self, channel_id: &str, fee_rate: u64) -> LightningResult<String> {
+        self.anchor_claims.bump(channel_id, fee_rate)
+    }
+
+    /// Build and sign a BOLT11 invoice for a payment hash we hold the preimage for
+    pub fn create_bolt11(
+        &self,
+        amount_msat: Option<u64>,
+        payment_hash: &str,
+        description: &str,
+        expiry: u32,
+        min_final_cltv_expiry: u32,
+    ) -> LightningResult<String> {
+        let invoice = create_invoice_from_channelmanager_and_duration_since_epoch_with_payment_hash(
+            &self.channel_manager,
+            self.keys_manager.clone(),
+            self.logger.clone(),
+            self.currency,
+            amount_msat,
+            description.to_string(),
+            unix_time(),
+            expiry,
+            PaymentHash(decode_hash(payment_hash)?),
+            Some(min_final_cltv_expiry as u16),
+        ).map_err(|e| LightningError::InvoiceError(format!("Failed to create invoice: {:?}", e)))?;
+
+        Ok(invoice.to_string())
+    }
+
+    /// Pay a BOLT11 invoice through LDK; the outcome arrives as a payment event
+    ///
+    /// Custom records are sent to the payee in the final hop's onion payload.
+    pub fn pay_invoice(
+        &self,
+        bolt11: &str,
+        amount_msat: Option<u64>,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<()> {
+        let invoice = LdkInvoice::from_str(bolt11)
+            .map_err(|e| LightningError::InvoiceError(format!("Invalid invoice: {:?}", e)))?;
+        let retry = Retry::Attempts(PAYMENT_RETRY_ATTEMPTS);
+
+        if custom_records.is_empty() {
+            let result = match (invoice.amount_milli_satoshis(), amount_msat) {
+                (None, Some(amount_msat)) => pay_zero_value_invoice(&invoice, amount_msat, retry, &*self.channel_manager),
+                _ => pay_invoice(&invoice, retry, &*self.channel_manager),
+            };
+
+            return result.map(|_| ())
+                .map_err(|e| LightningError::PaymentError(format!("Failed to send payment: {:?}", e)));
+        }
+
+        let amount_msat = invoice.amount_milli_satoshis().or(amount_msat).ok_or_else(|| {
+            LightningError::PaymentError("Amount not specified and not included in invoice".to_string())
+        })?;
+        let mut payment_params = PaymentParameters::from_node_id(
+            invoice.recover_payee_pub_key(),
+            invoice.min_final_cltv_expiry_delta() as u32,
+        )
+            .with_expiry_time(invoice.duration_since_epoch().as_secs() + invoice.expiry_time().as_secs())
+            .with_route_hints(invoice.route_hints())
+            .map_err(|_| LightningError::InvoiceError("Invalid route hints in invoice".to_string()))?;
+        if let Some(features) = invoice.features() {
+            payment_params = payment_params.with_bolt11_features(features.clone())
+                .map_err(|_| LightningError::InvoiceError("Invalid features in invoice".to_string()))?;
+        }
+
+        let payment_hash = PaymentHash(invoice.payment_hash().into_inner());
+        let onion_fields = RecipientOnionFields::secret_only(*invoice.payment_secret())
+            .with_custom_tlvs(custom_records.into_iter().collect())
+            .map_err(|_| LightningError::PaymentError("Invalid custom records".to_string()))?;
+
+        self.channel_manager.send_payment(
+            payment_hash,
+            onion_fields,
+            PaymentId(payment_hash.0),
+            RouteParameters::from_payment_params_and_value(payment_params, amount_msat),
+            retry,
+        ).map_err(|e| LightningError::PaymentError(format!("Failed to send payment: {:?}", e)))
+    }
+
+    /// Pay one of our own BOLT11 invoices out through one channel and back in through another
+    ///
+    /// LDK's router will not route to our own node, so the path is found to the peer
+    /// on the last-hop channel using only the outgoing channel as first hop, and the
+    /// final hop back into our channel is appended to it.
+    pub fn pay_invoice_circular(
+        &self,
+        bolt11: &str,
+        outgoing_channel_id: &str,
+        last_hop_channel_id: &str,
+        max_fee_msat: u64,
+    ) -> LightningResult<()> {
+        let invoice = LdkInvoice::from_str(bolt11)
+            .map_err(|e| LightningError::InvoiceError(format!("Invalid invoice: {:?}", e)))?;
+        let amount_msat = invoice.amount_milli_satoshis().ok_or_else(|| {
+            LightningError::PaymentError("Circular payments need an invoice with an amount".to_string())
+        })?;
+
+        let channels = self.channel_manager.list_usable_channels();
+        let usable_channel = |channel_id: &str| {
+            channels.iter()
+                .find(|details| to_hex(&details.channel_id) == channel_id)
+                .ok_or_else(|| LightningError::ChannelError(format!("Channel {} is not usable", channel_id)))
+        };
+        let outgoing = usable_channel(outgoing_channel_id)?;
+        let last_hop = usable_channel(last_hop_channel_id)?;
+
+        let last_hop_scid = last_hop.get_inbound_payment_scid().ok_or_else(|| {
+            LightningError::ChannelError(format!("Channel {} has no short channel ID yet", last_hop_channel_id))
+        })?;
+        let forwarding_info = last_hop.counterparty.forwarding_info.clone().ok_or_else(|| {
+            LightningError::ChannelError(format!("Peer on channel {} has not sent its forwarding policy", last_hop_channel_id))
+        })?;
+        let last_fee_msat = forwarding_info.fee_base_msat as u64 +
+            amount_msat * forwarding_info.fee_proportional_millionths as u64 / 1_000_000;
+        let final_cltv_expiry_delta = invoice.min_final_cltv_expiry_delta() as u32;
+
+        // Route to the last peer for the amount it has to forward back to us
+        let our_node_id = self.channel_manager.get_our_node_id();
+        let route_params = RouteParameters::from_payment_params_and_value(
+            PaymentParameters::from_node_id(last_hop.counterparty.node_id, final_cltv_expiry_delta),
+            amount_msat + last_fee_msat,
+        );
+        let mut route = find_route(
+            &our_node_id,
+            &route_params,
+            &self.network_graph.read_only(),
+            Some(&[outgoing]),
+            self.logger.clone(),
+            &*self.scorer.lock().unwrap(),
+            &ProbabilisticScoringFeeParameters::default(),
+            &self.keys_manager.get_secure_random_bytes(),
+        ).map_err(|e| LightningError::PaymentError(format!("No circular route found: {}", e.err)))?;
+
+        if route.paths.len() != 1 {
+            return Err(LightningError::PaymentError(
+                "Circular payments must use a single path".to_string()
+            ));
+        }
+        let path = &mut route.paths[0];
+
+        // The last peer now forwards to us instead of receiving the payment
+        let peer_hop = path.hops.last_mut().ok_or_else(|| {
+            LightningError::PaymentError("Router returned an empty path".to_string())
+        })?;
+        peer_hop.fee_msat = last_fee_msat;
+        peer_hop.cltv_expiry_delta = forwarding_info.cltv_expiry_delta as u32;
+
+        let user_config = UserConfig::default();
+        path.hops.push(RouteHop {
+            pubkey: our_node_id,
+            node_features: provided_node_features(&user_config),
+            short_channel_id: last_hop_scid,
+            channel_features: provided_channel_features(&user_config),
+            fee_msat: amount_msat,
+            cltv_expiry_delta: final_cltv_expiry_delta,
+        });
+
+        let fee_msat = path.fee_msat();
+        if fee_msat > max_fee_msat {
+            return Err(LightningError::PaymentError(
+                format!("Route fee {} msat exceeds budget of {} msat", fee_msat, max_fee_msat)
+            ));
+        }
+
+        let payment_hash = PaymentHash(invoice.payment_hash().into_inner());
+        self.channel_manager.send_payment_with_route(
+            &route,
+            payment_hash,
+            RecipientOnionFields::secret_only(*invoice.payment_secret()),
+            PaymentId(payment_hash.0),
+        ).map_err(|e| LightningError::PaymentError(format!("Failed to send circular payment: {:?}", e)))
+    }
+
+    /// Send a spontaneous payment revealing the given preimage
+    ///
+    /// Custom records travel next to the keysend preimage in the final hop's onion payload.
+    pub fn send_keysend(
+        &self,
+        node_pubkey: &PublicKey,
+        amount_msat: u64,
+        preimage: &str,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<()> {
+        let preimage = decode_hash(preimage)?;
+        let payment_hash = encoding::sha256(&preimage);
+        let route_params = RouteParameters::from_payment_params_and_value(
+            PaymentParameters::for_keysend(*node_pubkey, KEYSEND_FINAL_CLTV_EXPIRY_DELTA, false),
+            amount_msat,
+        );
+        let onion_fields = RecipientOnionFields::spontaneous_empty()
+            .with_custom_tlvs(custom_records.into_iter().collect())
+            .map_err(|_| LightningError::PaymentError("Invalid custom records".to_string()))?;
+
+        self.channel_manager.send_spontaneous_payment_with_retry(
+            Some(PaymentPreimage(preimage)),
+            onion_fields,
+            PaymentId(payment_hash),
+            route_params,
+            Retry::Attempts(PAYMENT_RETRY_ATTEMPTS),
+        ).map(|_| ())
+            .map_err(|e| LightningError::PaymentError(format!("Failed to send keysend payment: {:?}", e)))
+    }
+
+    /// Stop all processing and persist the ChannelManager
+    pub fn stop(&self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+
+        for thread in self.threads.lock().unwrap().drain(..) {
+            let _ = thread.join();
+        }
+
+        if let Some(background_processor) = self.background_processor.lock().unwrap().take() {
+            if let Err(e) = background_processor.stop() {
+                eprintln!("Failed to stop LDK background processor: {}", e);
+            }
+        }
+
+        self.peer_manager.disconnect_all_peers();
+
+        if let Some(runtime) = self.runtime.lock().unwrap().take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Drop for LdkNode {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Poll the chain for new blocks until stopped
+fn spawn_chain_poller(chain_sync: Arc<ChainSync>, stop_signal: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop_signal.load(Ordering::SeqCst) {
+            if let Err(e) = chain_sync.sync() {
+                eprintln!("LDK chain sync failed: {}", e);
+            }
+            thread::sleep(CHAIN_POLL_INTERVAL);
+        }
+    })
+}
+
+/// Claim or fail held HTLCs as invoices are settled or cancelled
+fn spawn_invoice_watcher(
+    invoice_manager: Arc<InvoiceManager>,
+    channel_manager: Arc<LdkChannelManager>,
+    stop_signal: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let updates = invoice_manager.subscribe();
+
+    thread::spawn(move || {
+        while !stop_signal.load(Ordering::SeqCst) {
+            let update = match updates.recv_timeout(Duration::from_millis(100)) {
+                Ok(update) => update,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let payment_hash = match decode_hash(&update.payment_hash) {
+                Ok(payment_hash) => PaymentHash(payment_hash),
+                Err(_) => continue,
+            };
+
+            match update.state {
+                InvoiceState::Settled => {
+                    let preimage = invoice_manager.get_invoice_status(&update.payment_hash).ok()
+                        .flatten()
+                        .and_then(|invoice_status| invoice_status.payment_preimage)
+                        .and_then(|preimage| decode_hash(&preimage).ok());
+                    if let Some(preimage) = preimage {
+                        channel_manager.claim_funds(PaymentPreimage(preimage));
+                    }
+                }
+                InvoiceState::Cancelled | InvoiceState::Expired => {
+                    channel_manager.fail_htlc_backwards(&payment_hash);
+                }
+                InvoiceState::Open | InvoiceState::Accepted => {}
+            }
+        }
+    })
+}
+
+/// Accept inbound peer connections on the listen address
+async fn accept_inbound_connections(peer_manager: Arc<LdkPeerManager>, listen_addr: String) {
+    let listener = match tokio::net::TcpListener::bind(&listen_addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen for Lightning peers on {}: {}", listen_addr, e);
+            return;
+        }
+    };
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_) => continue,
+        };
+        let peer_manager = peer_manager.clone();
+        tokio::spawn(async move {
+            if let Ok(stream) = stream.into_std() {
+                lightning_net_tokio::setup_inbound(peer_manager, stream).await;
+            }
+        });
+    }
+}
+
+/// Read an object LDK persisted into the data directory
+fn read_persisted<T, E>(
+    data_dir: &Path,
+    name: &str,
+    read: impl FnOnce(&mut BufReader<fs::File>) -> Result<T, E>,
+) -> Option<T> {
+    let file = fs::File::open(data_dir.join(name)).ok()?;
+    read(&mut BufReader::new(file)).ok()
+}
+
+/// Best block of the chain, falling back to genesis if the interface can't walk the chain
+fn current_best_block(bitcoin_interface: &dyn BitcoinInterface, network: Network) -> BestBlock {
+    let tip = bitcoin_interface.get_block_height().ok()
+        .and_then(|height| bitcoin_interface.get_block_hash(height).ok().map(|hash| (height, hash)))
+        .and_then(|(height, hash)| BlockHash::from_str(&hash).ok().map(|hash| (height, hash)));
+
+    match tip {
+        Some((height, hash)) => BestBlock::new(hash, height),
+        None => BestBlock::from_network(network),
+    }
+}
+
+/// Map our network names to LDK's
+fn parse_network(network: &str) -> Network {
+    match network {
+        "mainnet" | "bitcoin" => Network::Bitcoin,
+        "regtest" => Network::Regtest,
+        "signet" => Network::Signet,
+        _ => Network::Testnet,
+    }
+}
+
+/// Time since the Unix epoch
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Decode a 32-byte hex value (payment hash or preimage)
+fn decode_hash(hex: &str) -> LightningResult<[u8; 32]> {
+    from_hex(hex).ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| LightningError::PaymentError(format!("Invalid 32-byte hex value: {}", hex)))
+}
+
+/// Convert one of our transactions into LDK's representation
+pub fn to_ldk_transaction(transaction: &BitcoinTransaction) -> LightningResult<Transaction> {
+    let bytes = encoding::serialize_transaction(transaction, true)?;
+    deserialize(&bytes).map_err(|e| LightningError::ImplementationError(
+        format!("Failed to decode transaction {}: {}", transaction.txid, e)
+    ))
+}
+
+/// Convert an LDK transaction into our representation
+pub fn from_ldk_transaction(transaction: &Transaction, hrp: &str) -> BitcoinTransaction {
+    BitcoinTransaction {
+        txid: transaction.txid().to_string(),
+        version: transaction.version as u32,
+        inputs: transaction.input.iter().map(|input| TransactionInput {
+            txid: input.previous_output.txid.to_string(),
+            vout: input.previous_output.vout,
+            script_sig: input.script_sig.to_bytes(),
+            sequence: input.sequence.0,
+            witness: if input.witness.is_empty() { None } else { Some(input.witness.to_vec()) },
+        }).collect(),
+        outputs: transaction.output.iter().map(|output| TransactionOutput {
+            value: output.value,
+            script_pubkey: output.script_pubkey.to_bytes(),
+            address: script_pubkey_to_address(output.script_pubkey.as_bytes(), hrp),
+        }).collect(),
+        locktime: transaction.lock_time.0,
+        size: transaction.size(),
+        weight: transaction.weight(),
+        fee: None,
+    }
+}
+
+/// Convert one of our block headers into LDK's representation
+fn to_ldk_header(header: &BlockHeader) -> LightningResult<LdkBlockHeader> {
+    let invalid = |field: &str| LightningError::ImplementationError(
+        format!("Invalid {} in block header {}", field, header.hash)
+    );
+
+    Ok(LdkBlockHeader {
+        version: header.version,
+        prev_blockhash: BlockHash::from_str(&header.prev_hash).map_err(|_| invalid("previous hash"))?,
+        merkle_root: TxMerkleNode::from_str(&header.merkle_root).map_err(|_| invalid("merkle root"))?,
+        time: header.timestamp,
+        bits: header.bits,
+        nonce: header.nonce,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightning::bitcoin::{OutPoint, Sequence, TxIn, Txid, Witness};
+    use lightning::ln::chan_utils::{
+        get_anchor_redeemscript, ChannelTransactionParameters, CounterpartyChannelTransactionParameters,
+    };
+    use lightning::ln::features::ChannelTypeFeatures;
+    use lightning::sign::{ChannelDerivationParameters, ChannelSigner, SignerProvider};
+    use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+
+    const CHANNEL_VALUE_SAT: u64 = 100_000;
+
+    /// Broadcast a low-fee anchor commitment, as LDK would on force-close, with our anchor descriptor
+    fn broadcast_anchor_commitment(
+        chain: &SimulatedBitcoinImplementation,
+        keys_manager: &KeysManager,
+    ) -> (Transaction, u64, AnchorDescriptor) {
+        let keys_id = keys_manager.generate_channel_keys_id(false, CHANNEL_VALUE_SAT, 0);
+        let holder_pubkeys = keys_manager.derive_channel_signer(CHANNEL_VALUE_SAT, keys_id).pubkeys().clone();
+        let counterparty = KeysManager::new(&[2; 32], 1, 0);
+        let counterparty_keys_id = counterparty.generate_channel_keys_id(true, CHANNEL_VALUE_SAT, 0);
+        let counterparty_pubkeys = counterparty.derive_channel_signer(CHANNEL_VALUE_SAT, counterparty_keys_id)
+            .pubkeys().clone();
+
+        // Stand-in for the funding output; the simulated chain only checks P2WSH witness scripts
+        let funding_address = chain.generate_address(AddressType::P2WPKH).unwrap();
+        let funding_txid = Txid::from_str(&chain.fund_address(&funding_address.address, CHANNEL_VALUE_SAT).unwrap()).unwrap();
+
+        let anchor_script = get_anchor_redeemscript(&holder_pubkeys.funding_pubkey);
+        let commitment_fee = 670;
+        let commitment_tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint { txid: funding_txid, vout: 0 },
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![
+                TxOut { value: ANCHOR_OUTPUT_SAT, script_pubkey: anchor_script.to_v0_p2wsh() },
+                TxOut {
+                    value: CHANNEL_VALUE_SAT - ANCHOR_OUTPUT_SAT - commitment_fee,
+                    script_pubkey: address_to_script_pubkey(&funding_address.address).map(Script::from).unwrap(),
+                },
+            ],
+        };
+        chain.broadcast_transaction(&from_ldk_transaction(&commitment_tx, "bcrt")).unwrap();
+
+        let anchor_descriptor = AnchorDescriptor {
+            channel_derivation_parameters: ChannelDerivationParameters {
+                value_satoshis: CHANNEL_VALUE_SAT,
+                keys_id,
+                transaction_parameters: ChannelTransactionParameters {
+                    holder_pubkeys,
+                    holder_selected_contest_delay: 144,
+                    is_outbound_from_holder: true,
+                    counterparty_parameters: Some(CounterpartyChannelTransactionParameters {
+                        pubkeys: counterparty_pubkeys,
+                        selected_contest_delay: 144,
+                    }),
+                    funding_outpoint: Some(LdkOutPoint { txid: funding_txid, index: 0 }),
+                    channel_type_features: ChannelTypeFeatures::anchors_zero_htlc_fee_and_dependencies(),
+                },
+            },
+            outpoint: OutPoint { txid: commitment_tx.txid(), vout: 0 },
+        };
+
+        (commitment_tx, commitment_fee, anchor_descriptor)
+    }
+
+    #[test]
+    fn test_anchor_commitment_cpfp() {
+        let mut config = crate::config::Config::default();
+        config.bitcoin_network = Some("regtest".to_string());
+        let chain = Arc::new(SimulatedBitcoinImplementation::new(&config));
+        let wallet_address = chain.generate_address(AddressType::P2WPKH).unwrap();
+        chain.fund_address(&wallet_address.address, 1_000_000).unwrap();
+
+        let keys_manager = Arc::new(KeysManager::new(&[1; 32], 1, 0));
+        let (commitment_tx, commitment_fee, anchor_descriptor) = broadcast_anchor_commitment(&chain, &keys_manager);
+        let funding = commitment_tx.input[0].previous_output;
+        let channel_id = to_hex(&LdkOutPoint { txid: funding.txid, index: funding.vout as u16 }.to_channel_id());
+
+        let anchor_claims = AnchorClaims {
+            bitcoin_interface: chain.clone(),
+            keys_manager,
+            hrp: "bcrt",
+            claims: Mutex::new(HashMap::new()),
+        };
+
+        // LDK asks for 2500 sat/kw, 10 sat/vB
+        anchor_claims.handle(commitment_tx.clone(), commitment_fee, anchor_descriptor, 2_500);
+        let child_txid = chain.mempool_txids().into_iter()
+            .find(|txid| *txid != commitment_tx.txid().to_string())
+            .expect("handling the event broadcasts a child");
+
+        // The child spends our anchor with a valid witness script and lifts the package to the target rate
+        let child = chain.get_transaction(&child_txid).unwrap();
+        let anchor_input = &child.inputs[0];
+        assert_eq!(anchor_input.txid, commitment_tx.txid().to_string());
+        assert_eq!(anchor_input.vout, 0);
+
+        let package_vsize = (commitment_tx.weight() as u64).div_ceil(4) + ANCHOR_CHILD_VSIZE;
+        assert!(commitment_fee + child.fee.unwrap() >= 10 * package_vsize);
+
+        // Both confirm together, and a lower target than the package already pays is refused
+        chain.mine_blocks(1);
+        assert_eq!(chain.get_confirmations(&child_txid).unwrap(), 1);
+        assert!(anchor_claims.bump(&channel_id, 1).is_err());
+    }
+}
@@ -0,0 +1,701 @@
+// LNURL and Lightning Address support
+// Resolves LNURLs and `name@domain` addresses into Lightning operations
+//
+// Supported flows:
+//
+//   LUD-01  bech32 `lnurl1...` encoding of service URLs
+//   LUD-03  withdraw: the service pays an invoice we hand it
+//   LUD-04  auth: sign the service's k1 challenge with a per-domain linking key
+//   LUD-06  pay: the service issues an invoice for an amount we choose
+//   LUD-12  comments attached to pay requests
+//   LUD-16  Lightning Addresses (`name@domain`)
+//   LUD-17  `lnurlp://`, `lnurlw://` and `keyauth://` schemes
+//
+// All HTTP traffic goes through the `HttpClient` trait. The default client
+// speaks HTTPS (rustls); tests point the flows at a local plain-HTTP stub server.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bech32::{Bech32, Hrp};
+use secp256k1::{Message, PublicKey, Secp256k1};
+use serde::Deserialize;
+
+use crate::bitcoin::encoding::to_hex;
+use crate::lightning::interface::{LightningError, LightningResult};
+use crate::lightning::invoice_manager::decode_hex;
+use crate::lightning::key_manager::KeyManagerWrapper;
+
+/// Human-readable part of bech32-encoded LNURLs
+pub const LNURL_HRP: &str = "lnurl";
+
+/// Timeout for LNURL HTTP requests
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Minimal HTTP client used by the LNURL flows
+pub trait HttpClient: Send + Sync {
+    /// Perform a GET request and return the response body
+    fn get(&self, url: &str) -> LightningResult<String>;
+}
+
+impl<F> HttpClient for F
+where
+    F: Fn(&str) -> LightningResult<String> + Send + Sync,
+{
+    fn get(&self, url: &str) -> LightningResult<String> {
+        self(url)
+    }
+}
+
+/// HTTPS-capable client backed by ureq with rustls
+///
+/// Plain `http://` is still accepted for onion and local services.
+#[derive(Clone)]
+pub struct UreqHttpClient {
+    /// Agent carrying the connect, read and write timeouts
+    agent: ureq::Agent,
+}
+
+impl UreqHttpClient {
+    /// Create a client with the given request timeout
+    pub fn new(timeout: Duration) -> Self {
+        UreqHttpClient {
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(timeout)
+                .timeout(timeout)
+                .build(),
+        }
+    }
+}
+
+impl Default for UreqHttpClient {
+    fn default() -> Self {
+        Self::new(HTTP_TIMEOUT)
+    }
+}
+
+impl HttpClient for UreqHttpClient {
+    fn get(&self, url: &str) -> LightningResult<String> {
+        let (scheme, host, _) = split_url(url)?;
+        if scheme != "http" && scheme != "https" {
+            return Err(LightningError::NetworkError(format!("Unsupported URL scheme {}: {}", scheme, url)));
+        }
+
+        match self.agent.get(url).set("Accept", "application/json").call() {
+            Ok(response) => response.into_string()
+                .map_err(|e| LightningError::NetworkError(format!("Failed to read response from {}: {}", host, e))),
+            Err(ureq::Error::Status(status, response)) => Err(LightningError::NetworkError(format!(
+                "HTTP {}: {}", status, response.into_string().unwrap_or_default().trim()
+            ))),
+            Err(e) => Err(LightningError::NetworkError(format!("Request to {} failed: {}", host, e))),
+        }
+    }
+}
+
+/// Split a URL into its scheme, host (with port) and path (with query)
+fn split_url(url: &str) -> LightningResult<(String, &str, &str)> {
+    let (scheme, rest) = url.split_once("://")
+        .ok_or_else(|| LightningError::InvoiceError(format!("Not a URL: {}", url)))?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return Err(LightningError::InvoiceError(format!("URL has no host: {}", url)));
+    }
+
+    Ok((scheme.to_lowercase(), host, path))
+}
+
+/// Encode a service URL as a bech32 LNURL (uppercase, as used in QR codes)
+pub fn encode_lnurl(url: &str) -> LightningResult<String> {
+    let hrp = Hrp::parse(LNURL_HRP).expect("valid hrp");
+    bech32::encode::<Bech32>(hrp, url.as_bytes())
+        .map(|lnurl| lnurl.to_uppercase())
+        .map_err(|e| LightningError::InvoiceError(format!("Failed to encode LNURL: {}", e)))
+}
+
+/// Decode an LNURL into the service URL it points to
+///
+/// Accepts bech32 `lnurl1...` strings (optionally prefixed with `lightning:`)
+/// and the LUD-17 `lnurlp://`, `lnurlw://` and `keyauth://` schemes.
+pub fn decode_lnurl(lnurl: &str) -> LightningResult<String> {
+    let lnurl = strip_lightning_prefix(lnurl.trim());
+
+    if let Some((scheme, rest)) = lnurl.split_once("://") {
+        if matches!(scheme.to_lowercase().as_str(), "lnurlp" | "lnurlw" | "lnurlc" | "keyauth") {
+            let host = rest.split(['/', '?']).next().unwrap_or("");
+            return Ok(format!("{}://{}", web_scheme(host), rest));
+        }
+        return Err(LightningError::InvoiceError(format!("Unsupported LNURL scheme: {}", scheme)));
+    }
+
+    let (hrp, data) = bech32::decode(lnurl)
+        .map_err(|e| LightningError::InvoiceError(format!("Invalid LNURL: {}", e)))?;
+    if hrp.to_lowercase() != LNURL_HRP {
+        return Err(LightningError::InvoiceError(format!("Not an LNURL: unexpected prefix {}", hrp)));
+    }
+
+    String::from_utf8(data)
+        .map_err(|_| LightningError::InvoiceError("LNURL does not contain a UTF-8 URL".to_string()))
+}
+
+/// Resolve a Lightning Address (`name@domain`) to its LUD-16 pay endpoint
+pub fn parse_lightning_address(address: &str) -> LightningResult<String> {
+    let address = strip_lightning_prefix(address.trim());
+    let (name, domain) = address.split_once('@')
+        .ok_or_else(|| LightningError::InvoiceError(format!("Not a Lightning Address: {}", address)))?;
+
+    let valid_name = !name.is_empty() && name.chars().all(|c| {
+        c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.' | '+')
+    });
+    let valid_domain = !domain.is_empty() && !domain.contains(['/', '?', '#', '@']);
+    if !valid_name || !valid_domain {
+        return Err(LightningError::InvoiceError(format!("Not a Lightning Address: {}", address)));
+    }
+
+    Ok(format!("{}://{}/.well-known/lnurlp/{}", web_scheme(domain), domain, name))
+}
+
+/// Resolve an LNURL or Lightning Address to its service URL
+///
+/// Returns `None` for anything else, such as a BOLT11 invoice.
+pub fn lnurl_target_url(target: &str) -> Option<String> {
+    let stripped = strip_lightning_prefix(target.trim());
+    if stripped.contains('@') {
+        return parse_lightning_address(stripped).ok();
+    }
+    if stripped.contains("://") || stripped.to_lowercase().starts_with("lnurl1") {
+        return decode_lnurl(stripped).ok();
+    }
+
+    None
+}
+
+/// Strip a `lightning:` URI prefix
+fn strip_lightning_prefix(value: &str) -> &str {
+    match value.get(..10) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("lightning:") => &value[10..],
+        _ => value,
+    }
+}
+
+/// Services on onion and local hosts are reached over plain HTTP
+fn web_scheme(host: &str) -> &'static str {
+    let hostname = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    if hostname.ends_with(".onion") || hostname == "localhost" || hostname == "127.0.0.1" {
+        "http"
+    } else {
+        "https"
+    }
+}
+
+/// Host part of a URL, used to scope LNURL-auth linking keys
+pub fn url_domain(url: &str) -> LightningResult<String> {
+    let (_, host, _) = split_url(url)?;
+    let hostname = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    Ok(hostname.to_lowercase())
+}
+
+/// Read a query parameter from a URL
+fn query_param(url: &str, key: &str) -> Option<String> {
+    let query = url.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or(query);
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Append query parameters to a URL that may already have some
+fn append_query(url: &str, params: &[(&str, String)]) -> String {
+    let mut url = url.to_string();
+    for (key, value) in params {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(key);
+        url.push('=');
+        url.push_str(&percent_encode(value));
+    }
+    url
+}
+
+/// Percent-encode a query parameter value
+pub(crate) fn percent_encode(value: &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+/// Decode a percent-encoded query parameter value
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' if index + 2 < bytes.len() => {
+                let digits = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or("");
+                match u8::from_str_radix(digits, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        index += 3;
+                        continue;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// LUD-06 pay request
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayRequest {
+    /// URL to request invoices from
+    pub callback: String,
+    /// Minimum amount the service accepts, in millisatoshis
+    pub min_sendable_msat: u64,
+    /// Maximum amount the service accepts, in millisatoshis
+    pub max_sendable_msat: u64,
+    /// Raw metadata JSON string
+    pub metadata: String,
+    /// Maximum comment length (LUD-12), 0 if comments are not accepted
+    pub comment_allowed: usize,
+    /// Plain-text description taken from the metadata
+    pub description: String,
+}
+
+/// LUD-03 withdraw request
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithdrawRequest {
+    /// URL to submit our invoice to
+    pub callback: String,
+    /// Secret identifying this withdrawal
+    pub k1: String,
+    /// Description suggested for the invoice
+    pub default_description: String,
+    /// Minimum amount the service pays, in millisatoshis
+    pub min_withdrawable_msat: u64,
+    /// Maximum amount the service pays, in millisatoshis
+    pub max_withdrawable_msat: u64,
+}
+
+/// LUD-04 auth request
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthRequest {
+    /// Full login URL including k1
+    pub url: String,
+    /// Domain the linking key is derived for
+    pub domain: String,
+    /// Challenge to sign (32 bytes, hex)
+    pub k1: String,
+    /// Requested action (register, login, link or auth), if given
+    pub action: Option<String>,
+}
+
+/// A resolved LNURL
+#[derive(Debug, Clone, PartialEq)]
+pub enum LnurlRequest {
+    /// Pay the service
+    Pay(PayRequest),
+    /// Get paid by the service
+    Withdraw(WithdrawRequest),
+    /// Log in to the service
+    Auth(AuthRequest),
+}
+
+/// Service response, either a request description or a status
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceResponse {
+    tag: Option<String>,
+    status: Option<String>,
+    reason: Option<String>,
+    callback: Option<String>,
+    min_sendable: Option<u64>,
+    max_sendable: Option<u64>,
+    metadata: Option<String>,
+    comment_allowed: Option<usize>,
+    k1: Option<String>,
+    default_description: Option<String>,
+    min_withdrawable: Option<u64>,
+    max_withdrawable: Option<u64>,
+    pr: Option<String>,
+}
+
+impl ServiceResponse {
+    /// Parse a service response, turning `{"status": "ERROR"}` into an error
+    fn parse(body: &str) -> LightningResult<Self> {
+        let response: ServiceResponse = serde_json::from_str(body)
+            .map_err(|e| LightningError::InvoiceError(format!("Invalid LNURL response: {}", e)))?;
+
+        if response.status.as_deref().is_some_and(|status| status.eq_ignore_ascii_case("ERROR")) {
+            return Err(LightningError::PaymentError(format!(
+                "LNURL service error: {}",
+                response.reason.as_deref().unwrap_or("no reason given")
+            )));
+        }
+
+        Ok(response)
+    }
+
+    /// Take a required field or fail naming it
+    fn require<T>(field: Option<T>, name: &str) -> LightningResult<T> {
+        field.ok_or_else(|| LightningError::InvoiceError(format!("LNURL response is missing {}", name)))
+    }
+}
+
+/// Plain-text description from LUD-06 metadata
+fn metadata_description(metadata: &str) -> String {
+    let entries: Vec<Vec<serde_json::Value>> = serde_json::from_str(metadata).unwrap_or_default();
+    entries.iter()
+        .find(|entry| entry.first().and_then(|kind| kind.as_str()) == Some("text/plain"))
+        .and_then(|entry| entry.get(1).and_then(|text| text.as_str()))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Client for LNURL services
+pub struct LnurlClient {
+    /// HTTP client used to reach services
+    http: Arc<dyn HttpClient>,
+
+    /// Key manager deriving LNURL-auth linking keys
+    key_manager: Arc<KeyManagerWrapper>,
+}
+
+impl LnurlClient {
+    /// Create a new LNURL client using the HTTPS-capable ureq client
+    pub fn new(key_manager: Arc<KeyManagerWrapper>) -> Self {
+        Self::with_http_client(key_manager, Arc::new(UreqHttpClient::default()))
+    }
+
+    /// Create a new LNURL client with a custom HTTP client
+    pub fn with_http_client(key_manager: Arc<KeyManagerWrapper>, http: Arc<dyn HttpClient>) -> Self {
+        LnurlClient { http, key_manager }
+    }
+
+    /// Resolve an LNURL or Lightning Address into the request it describes
+    pub fn resolve(&self, target: &str) -> LightningResult<LnurlRequest> {
+        let url = lnurl_target_url(target)
+            .ok_or_else(|| LightningError::InvoiceError(format!("Not an LNURL or Lightning Address: {}", target)))?;
+
+        // Auth requests carry everything in the URL and must not be fetched
+        if query_param(&url, "tag").as_deref() == Some("login") {
+            let k1 = query_param(&url, "k1")
+                .ok_or_else(|| LightningError::InvoiceError("LNURL-auth request is missing k1".to_string()))?;
+            if decode_hex(&k1).map(|bytes| bytes.len()) != Some(32) {
+                return Err(LightningError::InvoiceError("LNURL-auth k1 must be 32 bytes of hex".to_string()));
+            }
+
+            return Ok(LnurlRequest::Auth(AuthRequest {
+                domain: url_domain(&url)?,
+                action: query_param(&url, "action"),
+                k1,
+                url,
+            }));
+        }
+
+        let response = ServiceResponse::parse(&self.http.get(&url)?)?;
+        match response.tag.as_deref() {
+            Some("payRequest") => {
+                let metadata = ServiceResponse::require(response.metadata, "metadata")?;
+                let pay = PayRequest {
+                    callback: ServiceResponse::require(response.callback, "callback")?,
+                    min_sendable_msat: ServiceResponse::require(response.min_sendable, "minSendable")?,
+                    max_sendable_msat: ServiceResponse::require(response.max_sendable, "maxSendable")?,
+                    comment_allowed: response.comment_allowed.unwrap_or(0),
+                    description: metadata_description(&metadata),
+                    metadata,
+                };
+                if pay.min_sendable_msat == 0 || pay.min_sendable_msat > pay.max_sendable_msat {
+                    return Err(LightningError::InvoiceError(format!(
+                        "Invalid pay request range {}-{} msat", pay.min_sendable_msat, pay.max_sendable_msat
+                    )));
+                }
+                Ok(LnurlRequest::Pay(pay))
+            }
+            Some("withdrawRequest") => {
+                let withdraw = WithdrawRequest {
+                    callback: ServiceResponse::require(response.callback, "callback")?,
+                    k1: ServiceResponse::require(response.k1, "k1")?,
+                    default_description: response.default_description.unwrap_or_default(),
+                    min_withdrawable_msat: response.min_withdrawable.unwrap_or(0),
+                    max_withdrawable_msat: ServiceResponse::require(response.max_withdrawable, "maxWithdrawable")?,
+                };
+                if withdraw.min_withdrawable_msat > withdraw.max_withdrawable_msat {
+                    return Err(LightningError::InvoiceError(format!(
+                        "Invalid withdraw request range {}-{} msat",
+                        withdraw.min_withdrawable_msat, withdraw.max_withdrawable_msat
+                    )));
+                }
+                Ok(LnurlRequest::Withdraw(withdraw))
+            }
+            Some(tag) => Err(LightningError::InvoiceError(format!("Unsupported LNURL tag: {}", tag))),
+            None => Err(LightningError::InvoiceError("LNURL response has no tag".to_string())),
+        }
+    }
+
+    /// Ask a pay service for an invoice of the given amount
+    ///
+    /// Returns the BOLT11 invoice; the caller checks its amount and description
+    /// hash before paying.
+    pub fn request_invoice(
+        &self,
+        pay: &PayRequest,
+        amount_msat: u64,
+        comment: Option<&str>,
+    ) -> LightningResult<String> {
+        if amount_msat < pay.min_sendable_msat || amount_msat > pay.max_sendable_msat {
+            return Err(LightningError::PaymentError(format!(
+                "Amount {} msat is outside the accepted range {}-{} msat",
+                amount_msat, pay.min_sendable_msat, pay.max_sendable_msat
+            )));
+        }
+
+        let mut params = vec![("amount", amount_msat.to_string())];
+        if let Some(comment) = comment.filter(|comment| !comment.is_empty()) {
+            if comment.chars().count() > pay.comment_allowed {
+                return Err(LightningError::PaymentError(format!(
+                    "Comment exceeds the {} characters the service accepts", pay.comment_allowed
+                )));
+            }
+            params.push(("comment", comment.to_string()));
+        }
+
+        let response = ServiceResponse::parse(&self.http.get(&append_query(&pay.callback, &params))?)?;
+        ServiceResponse::require(response.pr, "pr")
+    }
+
+    /// Hand our invoice to a withdraw service for it to pay
+    pub fn submit_withdraw(&self, withdraw: &WithdrawRequest, bolt11: &str) -> LightningResult<()> {
+        let url = append_query(&withdraw.callback, &[
+            ("k1", withdraw.k1.clone()),
+            ("pr", bolt11.to_string()),
+        ]);
+        ServiceResponse::parse(&self.http.get(&url)?)?;
+        Ok(())
+    }
+
+    /// Sign an auth challenge with the domain's linking key and submit it
+    ///
+    /// Returns the linking public key the service now knows us by.
+    pub fn auth(&self, auth: &AuthRequest) -> LightningResult<String> {
+        let challenge = decode_hex(&auth.k1)
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| LightningError::InvoiceError("LNURL-auth k1 must be 32 bytes of hex".to_string()))?;
+
+        let secp = Secp256k1::new();
+        let linking_key = self.key_manager.lnurl_linking_key(&auth.domain)?;
+        let message = Message::from_digest_slice(&challenge)
+            .map_err(|e| LightningError::ImplementationError(format!("Invalid auth challenge: {}", e)))?;
+        let signature = secp.sign_ecdsa(&message, &linking_key);
+        let pubkey = to_hex(&PublicKey::from_secret_key(&secp, &linking_key).serialize());
+
+        let url = append_query(&auth.url, &[
+            ("sig", to_hex(&signature.serialize_der())),
+            ("key", pubkey.clone()),
+        ]);
+        ServiceResponse::parse(&self.http.get(&url)?)?;
+
+        Ok(pubkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use secp256k1::ecdsa::Signature;
+    use sha2::Digest;
+    use crate::lightning::interface::{LightningInterface, PaymentStatus};
+    use crate::lightning::invoice_manager::InvoiceState;
+    use crate::lightning::test_network::TestNetwork;
+
+    /// Serve each request's path and query with `handler` until the test ends
+    fn stub_server<F>(listener: TcpListener, handler: F)
+    where
+        F: Fn(&str) -> String + Send + 'static,
+    {
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut request_line = String::new();
+                let mut reader = std::io::BufReader::new(&mut stream);
+                if reader.read_line(&mut request_line).is_err() {
+                    continue;
+                }
+                let mut header = String::new();
+                while reader.read_line(&mut header).map(|read| read > 2).unwrap_or(false) {
+                    header.clear();
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let body = handler(&path);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                );
+            }
+        });
+    }
+
+    fn bind() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        (listener, base)
+    }
+
+    #[test]
+    fn test_lnurl_encoding() {
+        let url = "https://service.com/api/v1/lnurl/pay?id=42";
+        let lnurl = encode_lnurl(url).unwrap();
+        assert!(lnurl.starts_with("LNURL1"));
+        assert_eq!(decode_lnurl(&lnurl).unwrap(), url);
+        assert_eq!(decode_lnurl(&format!("lightning:{}", lnurl.to_lowercase())).unwrap(), url);
+
+        // LUD-17 schemes map to https, or http for onion services
+        assert_eq!(decode_lnurl("lnurlp://service.com/pay/1").unwrap(), "https://service.com/pay/1");
+        assert_eq!(decode_lnurl("lnurlw://abc.onion/w?k1=1").unwrap(), "http://abc.onion/w?k1=1");
+
+        // Lightning Addresses resolve to the well-known pay endpoint
+        assert_eq!(
+            parse_lightning_address("alice@example.com").unwrap(),
+            "https://example.com/.well-known/lnurlp/alice"
+        );
+        assert!(parse_lightning_address("Alice Smith@example.com").is_err());
+
+        // Invoices and other bech32 strings are not LNURLs
+        let bitcoin_address = crate::bitcoin::encoding::encode_segwit_address("bcrt", 0, &[7u8; 20]).unwrap();
+        assert!(decode_lnurl(&bitcoin_address).is_err());
+        assert!(lnurl_target_url("lnbcrt500u1pjexample").is_none());
+
+        assert_eq!(percent_decode(&percent_encode("thanks & 100% ✓")), "thanks & 100% ✓");
+    }
+
+    #[test]
+    fn test_pay_lightning_address() {
+        let network = TestNetwork::new(2).unwrap();
+        network.open_channel(0, 1, 1_000_000, 0).unwrap();
+
+        let (listener, base) = bind();
+        let recipient = network.node(1);
+        let comments = Arc::new(Mutex::new(Vec::new()));
+        let seen = comments.clone();
+        let callback = format!("{}/lnurlp/bob/callback", base);
+        let metadata = "[[\"text/plain\",\"Pay to bob\"]]";
+        let metadata_hash = to_hex(&sha2::Sha256::digest(metadata.as_bytes()));
+        stub_server(listener, move |path| {
+            if let Some(name) = path.strip_prefix("/.well-known/lnurlp/") {
+                return serde_json::json!({
+                    "tag": "payRequest",
+                    "callback": callback.replace("bob", name),
+                    "minSendable": 1_000,
+                    "maxSendable": 100_000_000,
+                    "metadata": metadata,
+                    "commentAllowed": 20,
+                }).to_string();
+            }
+            let url = format!("http://stub{}", path);
+            let amount = query_param(&url, "amount").unwrap().parse::<u64>().unwrap();
+            if let Some(comment) = query_param(&url, "comment") {
+                seen.lock().unwrap().push(comment);
+            }
+            let invoice = match path.split('?').next().unwrap() {
+                // Commits to a plain description instead of the metadata hash
+                "/lnurlp/mallory/callback" => recipient.create_invoice(Some(amount), "Pay to bob", None),
+                "/lnurlp/carol/callback" => recipient.create_invoice_with_description_hash(None, &metadata_hash, None),
+                _ => recipient.create_invoice_with_description_hash(Some(amount), &metadata_hash, None),
+            }.unwrap();
+            serde_json::json!({ "pr": invoice.bolt11, "routes": [] }).to_string()
+        });
+
+        let address = format!("bob@{}", base.trim_start_matches("http://"));
+        let payment = network.node(0).pay_lnurl(&address, 25_000_000, Some("for the pizza")).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Succeeded);
+        assert_eq!(comments.lock().unwrap().as_slice(), ["for the pizza"]);
+
+        // A bech32 LNURL pasted into pay_invoice works the same way
+        let lnurl = encode_lnurl(&format!("{}/.well-known/lnurlp/bob", base)).unwrap();
+        network.node(0).pay_invoice(&lnurl, Some(5_000_000)).unwrap();
+        assert_eq!(network.total_local_balance(1).unwrap(), 30_000);
+
+        assert!(network.node(0).pay_lnurl(&address, 500, None).is_err());
+        assert!(network.node(0).pay_lnurl(&address, 1_000_000, Some("this comment is far too long")).is_err());
+        assert!(network.node(0).pay_invoice(&lnurl, None).is_err());
+
+        // Invoices must commit to the metadata and carry the requested amount
+        let host = base.trim_start_matches("http://");
+        assert!(network.node(0).pay_lnurl(&format!("mallory@{}", host), 1_000_000, None).is_err());
+        assert!(network.node(0).pay_lnurl(&format!("carol@{}", host), 1_000_000, None).is_err());
+        assert_eq!(network.total_local_balance(1).unwrap(), 30_000);
+    }
+
+    #[test]
+    fn test_withdraw_and_auth() {
+        let network = TestNetwork::new(2).unwrap();
+        network.open_channel(0, 1, 1_000_000, 500_000_000).unwrap();
+
+        let (listener, base) = bind();
+        let service = network.node(1);
+        let logins = Arc::new(Mutex::new(Vec::new()));
+        let seen = logins.clone();
+        let withdraw_callback = format!("{}/withdraw/callback", base);
+        stub_server(listener, move |path| {
+            let url = format!("http://stub{}", path);
+            if path == "/withdraw" {
+                return serde_json::json!({
+                    "tag": "withdrawRequest",
+                    "callback": withdraw_callback,
+                    "k1": "withdraw-secret",
+                    "defaultDescription": "Faucet",
+                    "minWithdrawable": 1_000,
+                    "maxWithdrawable": 20_000_000,
+                }).to_string();
+            }
+            if path.starts_with("/withdraw/callback") {
+                if query_param(&url, "k1").as_deref() != Some("withdraw-secret") {
+                    return serde_json::json!({ "status": "ERROR", "reason": "bad k1" }).to_string();
+                }
+                service.pay_invoice(&query_param(&url, "pr").unwrap(), None).unwrap();
+                return serde_json::json!({ "status": "OK" }).to_string();
+            }
+
+            // LUD-04: verify the signature over k1 with the presented linking key
+            let k1 = decode_hex(&query_param(&url, "k1").unwrap()).unwrap();
+            let signature = Signature::from_der(&decode_hex(&query_param(&url, "sig").unwrap()).unwrap()).unwrap();
+            let key = query_param(&url, "key").unwrap();
+            let pubkey = PublicKey::from_slice(&decode_hex(&key).unwrap()).unwrap();
+            let message = Message::from_digest_slice(&k1).unwrap();
+            if Secp256k1::verification_only().verify_ecdsa(&message, &signature, &pubkey).is_err() {
+                return serde_json::json!({ "status": "ERROR", "reason": "bad signature" }).to_string();
+            }
+            seen.lock().unwrap().push(key);
+            serde_json::json!({ "status": "OK" }).to_string()
+        });
+
+        let invoice = network.node(0).withdraw_lnurl(&encode_lnurl(&format!("{}/withdraw", base)).unwrap(), None).unwrap();
+        assert_eq!(invoice.amount_msat, Some(20_000_000));
+        assert_eq!(invoice.description, "Faucet");
+        assert_eq!(network.node(0).get_invoice_state(&invoice.payment_hash).unwrap(), InvoiceState::Settled);
+        assert!(network.node(0).withdraw_lnurl(&encode_lnurl(&format!("{}/withdraw", base)).unwrap(), Some(30_000_000)).is_err());
+
+        // The same node presents the same linking key to the same domain
+        let login = encode_lnurl(&format!("{}/auth?tag=login&k1={}&action=login", base, "ab".repeat(32))).unwrap();
+        let key = network.node(0).lnurl_auth(&login).unwrap();
+        assert_eq!(network.node(0).lnurl_auth(&login).unwrap(), key);
+        assert_ne!(network.node(1).lnurl_auth(&login).unwrap(), key);
+        assert_eq!(logins.lock().unwrap()[0], key);
+
+        let short_k1 = encode_lnurl(&format!("{}/auth?tag=login&k1=abcd", base)).unwrap();
+        assert!(network.node(0).lnurl_auth(&short_k1).is_err());
+    }
+}
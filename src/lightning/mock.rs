@@ -2,21 +2,122 @@
 // Used for testing and development when LDK is not available
 
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{HashMap, BTreeMap};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::lightning::interface::{
     LightningInterface, LightningError, LightningResult,
-    NodeInfo, ChannelInfo, Invoice, PaymentInfo, PaymentStatus,
+    NodeInfo, ChannelInfo, Invoice, PaymentInfo, PaymentStatus, OpenChannelOptions,
     LightningImplementationType
 };
 
 use crate::lightning::channel_manager::ChannelManagerWrapper;
 use crate::lightning::peer_manager::PeerManagerWrapper;
-use crate::lightning::key_manager::KeyManagerWrapper;
-use crate::lightning::invoice_manager::InvoiceManager;
+use crate::lightning::key_manager::{KeyManagerWrapper, KeyFamily, verify_message};
+use crate::lightning::invoice_manager::{InvoiceManager, InvoiceState, spawn_invoice_maintenance};
 use crate::lightning::payment_router::PaymentRouter;
 use crate::lightning::payment_executor::PaymentExecutor;
+use crate::lightning::lnurl::{LnurlClient, LnurlRequest};
+use crate::lightning::forwarding::{
+    ForwardingManager, ForwardingPolicy, FeeMode, ChannelUpdate, ForwardingEvent
+};
+use crate::lightning::rebalancer::{
+    Rebalancer, RebalancePolicy, RebalanceResult, RebalanceReport
+};
+
+/// Handles to the components of a node attached to a transport
+#[derive(Clone)]
+pub struct NodeEndpoint {
+    /// Node public key
+    pub pubkey: String,
+
+    /// Key manager holding the node's announcement
+    pub key_manager: KeyManagerWrapper,
+
+    /// Channel manager holding the node's view of its channels
+    pub channel_manager: Arc<ChannelManagerWrapper>,
+
+    /// Peer manager holding the node's connections
+    pub peer_manager: Arc<PeerManagerWrapper>,
+
+    /// Invoice manager receiving HTLCs for the node's invoices
+    pub invoice_manager: Arc<InvoiceManager>,
+
+    /// Router holding the node's view of the network graph
+    pub payment_router: Arc<PaymentRouter>,
+
+    /// Payment executor tracking the node's payments
+    pub payment_executor: Arc<PaymentExecutor>,
+
+    /// Forwarding manager relaying HTLCs through the node
+    pub forwarding_manager: Arc<ForwardingManager>,
+}
+
+
+/// What a splice does with the channel
+#[derive(Debug, Clone)]
+pub enum SpliceRequest {
+    /// Add funds from the chain wallet
+    In {
+        /// Amount added to the channel
+        amount_sat: u64,
+    },
+
+    /// Pay an address from the initiator's balance
+    Out {
+        /// Destination address
+        address: String,
+
+        /// Amount paid to the address
+        amount_sat: u64,
+    },
+}
+
+/// Link from a mock node to the other nodes it can reach
+///
+/// A standalone mock node has none. Nodes on a test network share one that
+/// carries peer connections, channel funding and payments between them.
+pub trait NodeTransport: Send + Sync {
+    /// Chain the connected nodes fund and close their channels on
+    fn bitcoin_interface(&self) -> Arc<dyn crate::bitcoin::BitcoinInterface>;
+
+    /// Attach a node to the transport
+    fn register(&self, endpoint: NodeEndpoint) -> LightningResult<()>;
+
+    /// Connect two nodes as peers
+    fn connect(&self, from: &str, to: &str) -> LightningResult<()>;
+
+    /// Fund a channel between two nodes
+    fn open_channel(&self, funder: &str, fundee: &str, capacity: u64, options: &OpenChannelOptions) -> LightningResult<ChannelInfo>;
+
+    /// Close a channel cooperatively or by force, returning the closing txid
+    fn close_channel(&self, initiator: &str, channel_id: &str, force: bool) -> LightningResult<String>;
+
+    /// Splice funds into or out of a channel
+    fn splice(&self, initiator: &str, channel_id: &str, request: SpliceRequest) -> LightningResult<ChannelInfo>;
+
+    /// CPFP a force-closed anchor commitment, returning the child txid
+    fn bump_commitment_fee(&self, pubkey: &str, channel_id: &str, fee_rate: u64) -> LightningResult<String>;
+
+    /// Find an invoice issued by any node, with the issuing node's pubkey
+    fn find_invoice(&self, bolt11: &str) -> Option<(String, Invoice)>;
+
+    /// Pay another node's invoice
+    fn pay_invoice(&self, sender: &str, bolt11: &str, amount_msat: Option<u64>) -> LightningResult<PaymentInfo>;
+
+    /// Send a spontaneous payment to another node
+    fn keysend(
+        &self,
+        sender: &str,
+        recipient: &str,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo>;
+
+    /// Settle or fail payments waiting on hold invoices
+    fn process_held_payments(&self) -> LightningResult<()>;
+}
 
 /// Mock implementation of Lightning Network interface
 pub struct MockLightningImplementation {
@@ -41,16 +142,63 @@ pub struct MockLightningImplementation {
     /// Payment executor
     payment_executor: Arc<PaymentExecutor>,
     
+    /// LNURL and Lightning Address client
+    lnurl_client: Arc<LnurlClient>,
+    
+    /// HTLC forwarding manager
+    forwarding_manager: Arc<ForwardingManager>,
+    
+    /// Channel rebalancer
+    rebalancer: Arc<Rebalancer>,
+    
     /// Reference to Bitcoin interface
     bitcoin_interface: Arc<dyn crate::bitcoin::BitcoinInterface>,
     
+    /// In-memory transport to other nodes, when part of a test network
+    transport: Option<Arc<dyn NodeTransport>>,
+    
     /// Initialization status
     initialized: Mutex<bool>,
+    
+    /// Stop signal for the invoice maintenance thread
+    stop_signal: Arc<AtomicBool>,
 }
 
 impl MockLightningImplementation {
     /// Create a new mock Lightning implementation
     pub fn new(config: &crate::config::Config, bitcoin_interface: Arc<dyn crate::bitcoin::BitcoinInterface>) -> Self {
+        Self::build(config, bitcoin_interface, None)
+    }
+    
+    /// Create a mock node attached to a transport
+    ///
+    /// The node uses the transport's chain and reaches other nodes on
+    /// the same transport for peers, channels and payments. It starts without the
+    /// placeholder peer and channel of a standalone mock node.
+    pub fn with_transport(config: &crate::config::Config, transport: Arc<dyn NodeTransport>) -> LightningResult<Self> {
+        let bitcoin_interface = transport.bitcoin_interface();
+        let implementation = Self::build(config, bitcoin_interface, Some(transport.clone()));
+        
+        transport.register(NodeEndpoint {
+            pubkey: implementation.key_manager.get_node_info()?.pubkey,
+            key_manager: implementation.key_manager.clone(),
+            channel_manager: implementation.channel_manager.clone(),
+            peer_manager: implementation.peer_manager.clone(),
+            invoice_manager: implementation.invoice_manager.clone(),
+            payment_router: implementation.payment_router.clone(),
+            payment_executor: implementation.payment_executor.clone(),
+            forwarding_manager: implementation.forwarding_manager.clone(),
+        })?;
+        
+        Ok(implementation)
+    }
+    
+    /// Assemble the components of a mock node
+    fn build(
+        config: &crate::config::Config,
+        bitcoin_interface: Arc<dyn crate::bitcoin::BitcoinInterface>,
+        transport: Option<Arc<dyn NodeTransport>>,
+    ) -> Self {
         // Create required components
         let mut key_manager = KeyManagerWrapper::new(config);
         let peer_manager = Arc::new(PeerManagerWrapper::new(config));
@@ -73,6 +221,23 @@ pub fn new(config: &crate::config::Config, bitcoin_interface: Arc<dyn crate::bit
             peer_manager.clone()
         ));
         
+        // LNURL-pay targets resolve to invoices inside the payment executor
+        let lnurl_client = Arc::new(LnurlClient::new(key_manager_arc.clone()));
+        payment_executor.attach_lnurl_client(lnurl_client.clone());
+        
+        // Create forwarding manager for routing HTLCs between our channels
+        let forwarding_manager = Arc::new(ForwardingManager::new(
+            channel_manager.clone(),
+            payment_router.clone()
+        ));
+        
+        // Create rebalancer for circular self-payments between our channels
+        let rebalancer = Arc::new(Rebalancer::new(
+            channel_manager.clone(),
+            invoice_manager.clone(),
+            payment_executor.clone()
+        ));
+        
         MockLightningImplementation {
             config: Arc::new(config.clone()),
             key_manager,
@@ -81,8 +246,13 @@ pub fn new(config: &crate::config::Config, bitcoin_interface: Arc<dyn crate::bit
             invoice_manager,
             payment_router,
             payment_executor,
+            lnurl_client,
+            forwarding_manager,
+            rebalancer,
             bitcoin_interface,
+            transport,
             initialized: Mutex::new(false),
+            stop_signal: Arc::new(AtomicBool::new(false)),
         }
     }
     
@@ -93,12 +263,19 @@ fn ensure_initialized(&self) -> LightningResult<()> {
         if !*initialized {
             println!("Initializing Mock Lightning implementation...");
             
-            // Initialize components
-            let mut peer_manager = self.peer_manager.clone();
-            peer_manager.initialize()?;
+            // Standalone nodes get a placeholder peer and channel to work with
+            #[cfg(not(feature = "ldk"))]
+            if self.transport.is_none() {
+                self.peer_manager.initialize()?;
+                self.channel_manager.initialize()?;
+            }
             
-            let mut channel_manager = self.channel_manager.clone();
-            channel_manager.initialize()?;
+            // Expire invoices and fail back held HTLCs near their deadline
+            spawn_invoice_maintenance(
+                self.invoice_manager.clone(),
+                self.bitcoin_interface.clone(),
+                self.stop_signal.clone(),
+            );
             
             *initialized = true;
             println!("Mock Lightning implementation initialized");
@@ -106,6 +283,17 @@ fn ensure_initialized(&self) -> LightningResult<()> {
         
         Ok(())
     }
+    
+    /// Our node's public key
+    fn local_pubkey(&self) -> LightningResult<String> {
+        Ok(self.key_manager.get_node_info()?.pubkey)
+    }
+}
+
+impl Drop for MockLightningImplementation {
+    fn drop(&mut self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+    }
 }
 
 impl LightningInterface for MockLightningImplementation {
@@ -117,10 +305,39 @@ fn get_node_info(&self) -> LightningResult<NodeInfo> {
         self.key_manager.get_node_info()
     }
     
+    fn unlock(&self, passphrase: &str) -> LightningResult<()> {
+        self.key_manager.unlock(passphrase)
+    }
+    
+    fn lock(&self) -> LightningResult<()> {
+        self.key_manager.lock();
+        Ok(())
+    }
+    
+    fn change_passphrase(&self, old_passphrase: &str, new_passphrase: &str) -> LightningResult<()> {
+        self.key_manager.change_passphrase(old_passphrase, new_passphrase)
+    }
+    
+    fn rotate_keys(&self, family: KeyFamily) -> LightningResult<u32> {
+        self.key_manager.rotate_keys(family)
+    }
+    
+    fn sign_message(&self, message: &str) -> LightningResult<String> {
+        self.key_manager.sign_message(message.as_bytes())
+    }
+    
+    fn verify_message(&self, message: &str, signature: &str) -> LightningResult<String> {
+        verify_message(message.as_bytes(), signature)
+    }
+    
     fn connect_peer(&self, node_pubkey: &str, host: &str, port: u16) -> LightningResult<()> {
         // Ensure we're initialized
         self.ensure_initialized()?;
         
+        if let Some(transport) = &self.transport {
+            return transport.connect(&self.local_pubkey()?, node_pubkey);
+        }
+        
         // Connect using peer manager
         self.peer_manager.connect_peer(node_pubkey, host, port)
     }
@@ -137,12 +354,15 @@ fn open_channel(
         &self,
         node_pubkey: &str,
         capacity: u64,
-        push_msat: Option<u64>,
-        is_private: bool,
+        options: OpenChannelOptions,
     ) -> LightningResult<ChannelInfo> {
         // Ensure we're initialized
         self.ensure_initialized()?;
         
+        if let Some(transport) = &self.transport {
+            return transport.open_channel(&self.local_pubkey()?, node_pubkey, capacity, &options);
+        }
+        
         // Check if we're connected to the peer
         if !self.peer_manager.is_connected(node_pubkey) {
             return Err(LightningError::ChannelError(
@@ -151,13 +371,18 @@ fn open_channel(
         }
         
         // Open channel using channel manager
-        self.channel_manager.open_channel(node_pubkey, capacity, push_msat, is_private)
+        self.channel_manager.open_channel(node_pubkey, capacity, &options)
     }
     
     fn list_channels(&self) -> LightningResult<Vec<ChannelInfo>> {
         // Ensure we're initialized
         self.ensure_initialized()?;
         
+        // Standalone splices lock in here; on a network the transport watches the chain
+        if self.transport.is_none() {
+            self.channel_manager.sync_splices()?;
+        }
+        
         // List channels from channel manager
         self.channel_manager.list_channels()
     }
@@ -166,10 +391,53 @@ fn close_channel(&self, channel_id: &str, force: bool) -> LightningResult<String
         // Ensure we're initialized
         self.ensure_initialized()?;
         
+        if let Some(transport) = &self.transport {
+            return transport.close_channel(&self.local_pubkey()?, channel_id, force);
+        }
+        
         // Close channel using channel manager
         self.channel_manager.close_channel(channel_id, force)
     }
     
+    fn bump_commitment_fee(&self, channel_id: &str, fee_rate: u64) -> LightningResult<String> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        if let Some(transport) = &self.transport {
+            return transport.bump_commitment_fee(&self.local_pubkey()?, channel_id, fee_rate);
+        }
+        
+        // Mock channels never broadcast a commitment to bump
+        Err(LightningError::ChannelError(
+            format!("No anchor commitment to bump for channel {}", channel_id)
+        ))
+    }
+    
+    fn splice_in(&self, channel_id: &str, amount_sat: u64) -> LightningResult<ChannelInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        if let Some(transport) = &self.transport {
+            return transport.splice(&self.local_pubkey()?, channel_id, SpliceRequest::In { amount_sat });
+        }
+        
+        // Splice through channel manager
+        self.channel_manager.splice_in(channel_id, amount_sat)
+    }
+    
+    fn splice_out(&self, channel_id: &str, address: &str, amount_sat: u64) -> LightningResult<ChannelInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        if let Some(transport) = &self.transport {
+            let request = SpliceRequest::Out { address: address.to_string(), amount_sat };
+            return transport.splice(&self.local_pubkey()?, channel_id, request);
+        }
+        
+        // Splice through channel manager
+        self.channel_manager.splice_out(channel_id, address, amount_sat)
+    }
+    
     fn create_invoice(
         &self,
         amount_msat: Option<u64>,
@@ -183,15 +451,165 @@ fn create_invoice(
         self.invoice_manager.create_invoice(amount_msat, description, expiry)
     }
     
+    fn create_invoice_with_description_hash(
+        &self,
+        amount_msat: Option<u64>,
+        description_hash: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        self.invoice_manager.create_invoice_with_description_hash(amount_msat, description_hash, expiry)
+    }
+    
+    fn create_hold_invoice(
+        &self,
+        payment_hash: &str,
+        amount_msat: Option<u64>,
+        description: &str,
+        expiry: Option<u32>,
+    ) -> LightningResult<Invoice> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Create hold invoice using invoice manager
+        self.invoice_manager.create_hold_invoice(payment_hash, amount_msat, description, expiry)
+    }
+    
+    fn settle_invoice(&self, payment_hash: &str, preimage: &str) -> LightningResult<()> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Settle invoice using invoice manager
+        self.invoice_manager.settle_invoice(payment_hash, preimage)?;
+        
+        // Release the HTLCs held along the route
+        match &self.transport {
+            Some(transport) => transport.process_held_payments(),
+            None => Ok(()),
+        }
+    }
+    
+    fn cancel_invoice(&self, payment_hash: &str) -> LightningResult<()> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Cancel invoice using invoice manager
+        self.invoice_manager.cancel_invoice(payment_hash)?;
+        
+        // Fail the HTLCs held along the route
+        match &self.transport {
+            Some(transport) => transport.process_held_payments(),
+            None => Ok(()),
+        }
+    }
+    
+    fn get_invoice_state(&self, payment_hash: &str) -> LightningResult<InvoiceState> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Get invoice state from invoice manager
+        self.invoice_manager.get_invoice_state(payment_hash)
+    }
+    
     fn pay_invoice(&self, bolt11: &str, amount_msat: Option<u64>) -> LightningResult<PaymentInfo> {
         // Ensure we're initialized
         self.ensure_initialized()?;
         
+        // LNURLs and Lightning Addresses resolve to an invoice first
+        let bolt11 = &self.payment_executor.resolve_lnurl_invoice(bolt11, amount_msat, None, &|bolt11| self.decode_invoice(bolt11))?;
+        
+        // Invoices from other nodes on the network are routed by the transport
+        if let Some(transport) = &self.transport {
+            if self.invoice_manager.find_invoice_by_bolt11(bolt11).is_none() {
+                return transport.pay_invoice(&self.local_pubkey()?, bolt11, amount_msat);
+            }
+        }
+        
         // Pay invoice using payment executor
         self.payment_executor.pay_invoice(bolt11, amount_msat)
     }
     
+    fn pay_lnurl(&self, target: &str, amount_msat: u64, comment: Option<&str>) -> LightningResult<PaymentInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        let bolt11 = self.payment_executor.resolve_lnurl_invoice(target, Some(amount_msat), comment, &|bolt11| self.decode_invoice(bolt11))?;
+        self.pay_invoice(&bolt11, Some(amount_msat))
+    }
+    
+    fn withdraw_lnurl(&self, lnurl: &str, amount_msat: Option<u64>) -> LightningResult<Invoice> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        let withdraw = match self.lnurl_client.resolve(lnurl)? {
+            LnurlRequest::Withdraw(withdraw) => withdraw,
+            _ => return Err(LightningError::PaymentError(
+                format!("{} is not an LNURL-withdraw request", lnurl)
+            )),
+        };
+        
+        let amount_msat = amount_msat.unwrap_or(withdraw.max_withdrawable_msat);
+        if amount_msat == 0
+            || amount_msat < withdraw.min_withdrawable_msat
+            || amount_msat > withdraw.max_withdrawable_msat
+        {
+            return Err(LightningError::PaymentError(format!(
+                "Amount {} msat is outside the withdrawable range {}-{} msat",
+                amount_msat, withdraw.min_withdrawable_msat, withdraw.max_withdrawable_msat
+            )));
+        }
+        
+        // The service pays our invoice asynchronously once it accepts it
+        let invoice = self.create_invoice(Some(amount_msat), &withdraw.default_description, None)?;
+        self.lnurl_client.submit_withdraw(&withdraw, &invoice.bolt11)?;
+        
+        Ok(invoice)
+    }
+    
+    fn lnurl_auth(&self, lnurl: &str) -> LightningResult<String> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        match self.lnurl_client.resolve(lnurl)? {
+            LnurlRequest::Auth(auth) => self.lnurl_client.auth(&auth),
+            _ => Err(LightningError::InvoiceError(
+                format!("{} is not an LNURL-auth request", lnurl)
+            )),
+        }
+    }
+    
+    fn keysend(
+        &self,
+        node_pubkey: &str,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        if let Some(transport) = &self.transport {
+            return transport.keysend(&self.local_pubkey()?, node_pubkey, amount_msat, custom_records);
+        }
+        
+        // Send keysend payment using payment executor
+        self.payment_executor.keysend_payment_with_records(node_pubkey, amount_msat, None, custom_records)
+    }
+    
+    fn list_received_payments(&self) -> LightningResult<Vec<PaymentInfo>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // List received payments from payment executor
+        self.payment_executor.list_received_payments()
+    }
+    
     fn decode_invoice(&self, bolt11: &str) -> LightningResult<Invoice> {
+        if let Some((_, invoice)) = self.transport.as_ref().and_then(|transport| transport.find_invoice(bolt11)) {
+            return Ok(invoice);
+        }
+        
         // Decode invoice using invoice manager
         self.invoice_manager.decode_invoice(bolt11)
     }
@@ -212,6 +630,73 @@ fn list_payments(&self) -> LightningResult<Vec<PaymentInfo>> {
         self.payment_executor.list_payments()
     }
     
+    fn get_channel_policy(&self, channel_id: &str) -> LightningResult<ForwardingPolicy> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Get policy from forwarding manager
+        self.forwarding_manager.get_channel_policy(channel_id)
+    }
+    
+    fn update_channel_policy(
+        &self,
+        channel_id: Option<&str>,
+        policy: ForwardingPolicy,
+    ) -> LightningResult<Vec<ChannelUpdate>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Update a single channel or the default policy
+        match channel_id {
+            Some(channel_id) => self.forwarding_manager
+                .set_channel_policy(channel_id, policy)
+                .map(|update| vec![update]),
+            None => self.forwarding_manager.set_default_policy(policy),
+        }
+    }
+    
+    fn set_fee_mode(&self, mode: FeeMode) -> LightningResult<Vec<ChannelUpdate>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Set fee mode on forwarding manager
+        self.forwarding_manager.set_fee_mode(mode)
+    }
+    
+    fn list_forwards(&self) -> LightningResult<Vec<ForwardingEvent>> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // List forwards from forwarding manager
+        Ok(self.forwarding_manager.list_forwards(None, None))
+    }
+    
+    fn rebalance_channel(
+        &self,
+        source_channel_id: &str,
+        target_channel_id: &str,
+        amount_sat: u64,
+        max_fee_msat: u64,
+    ) -> LightningResult<RebalanceResult> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Rebalance using rebalancer
+        self.rebalancer.rebalance(source_channel_id, target_channel_id, amount_sat, max_fee_msat)
+    }
+    
+    fn run_rebalance_autopilot(
+        &self,
+        policy: &RebalancePolicy,
+        dry_run: bool,
+    ) -> LightningResult<RebalanceReport> {
+        // Ensure we're initialized
+        self.ensure_initialized()?;
+        
+        // Run autopilot on rebalancer
+        self.rebalancer.run_autopilot(policy, dry_run)
+    }
+    
     fn implementation_type(&self) -> LightningImplementationType {
         LightningImplementationType::Mock
     }
@@ -4,6 +4,8 @@
 pub mod interface;
 pub mod mock;
 pub mod ldk;
+#[cfg(feature = "ldk")]
+pub mod ldk_node;
 pub mod channel_manager;
 pub mod peer_manager;
 pub mod key_manager;
@@ -11,21 +13,39 @@
 pub mod payment_router;
 pub mod payment_executor;
 pub mod bitcoin_bridge;
+pub mod forwarding;
+pub mod rebalancer;
+pub mod swap;
+pub mod lnurl;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_network;
 
 use std::sync::Arc;
 use crate::config::Config;
 use interface::{
     LightningInterface,
     LightningImplementationType,
+    LightningError, LightningResult,
     NodeInfo, ChannelInfo, Invoice, PaymentInfo,
 };
 
 /// Create a Lightning Network interface based on the configuration
+///
+/// Fails rather than run the mock implementation on mainnet, including when
+/// LDK is requested but the `ldk` feature is not compiled in.
 pub fn create_lightning_interface(
     config: &Config,
     bitcoin_interface: Arc<dyn crate::bitcoin::BitcoinInterface>,
-) -> Arc<dyn LightningInterface> {
-    match config.get_lightning_implementation_type() {
+) -> LightningResult<Arc<dyn LightningInterface>> {
+    let implementation_type = config.get_lightning_implementation_type();
+    let uses_mock = implementation_type == LightningImplementationType::Mock || !cfg!(feature = "ldk");
+    if uses_mock && config.bitcoin_network.as_deref() == Some("mainnet") {
+        return Err(LightningError::ImplementationError(
+            "The mock Lightning implementation cannot be used on mainnet; build with the ldk feature and set lightning.implementation = \"ldk\"".to_string()
+        ));
+    }
+    
+    let lightning: Arc<dyn LightningInterface> = match implementation_type {
         LightningImplementationType::LDK => {
             #[cfg(feature = "ldk")]
             {
@@ -40,7 +60,9 @@ pub fn create_lightning_interface(
         LightningImplementationType::Mock => {
             Arc::new(mock::MockLightningImplementation::new(config, bitcoin_interface))
         }
-    }
+    };
+    
+    Ok(lightning)
 }
 
 /// Initialize the Lightning module
@@ -59,21 +81,34 @@ fn test_create_lightning_interface() {
         let config = Config::default();
         let bitcoin_interface = bitcoin::get_current_bitcoin_interface(&config);
         
-        let lightning = create_lightning_interface(&config, bitcoin_interface);
+        let lightning = create_lightning_interface(&config, bitcoin_interface.clone()).unwrap();
         assert_eq!(lightning.implementation_type(), LightningImplementationType::Mock);
         
         let node_info = lightning.get_node_info().unwrap();
         assert!(!node_info.pubkey.is_empty());
+        
+        // The mock never runs against mainnet, even when LDK is not compiled in
+        let mainnet = Config {
+            bitcoin_network: Some("mainnet".to_string()),
+            lightning_implementation: Some("ldk".to_string()),
+            ..Config::default()
+        };
+        if !cfg!(feature = "ldk") {
+            assert!(create_lightning_interface(&mainnet, bitcoin_interface.clone()).is_err());
+        }
+        let mainnet_mock = Config { lightning_implementation: Some("mock".to_string()), ..mainnet };
+        assert!(create_lightning_interface(&mainnet_mock, bitcoin_interface).is_err());
     }
     
     #[test]
     fn test_channel_manager() {
         use super::channel_manager::ChannelManagerWrapper;
+        use super::interface::OpenChannelOptions;
         
         let config = Config::default();
         let bitcoin_interface = bitcoin::get_current_bitcoin_interface(&config);
         
-        let mut channel_manager = ChannelManagerWrapper::new(&config, bitcoin_interface);
+        let channel_manager = ChannelManagerWrapper::new(&config, bitcoin_interface);
         
         #[cfg(feature = "ldk")]
         {
@@ -91,9 +126,16 @@ fn test_channel_manager() {
             
             // Open a new channel
             let peer_pubkey = "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619";
-            let channel = channel_manager.open_channel(peer_pubkey, 100_000, None, false).unwrap();
+            let channel = channel_manager.open_channel(peer_pubkey, 100_000, &OpenChannelOptions::default()).unwrap();
             assert_eq!(channel.remote_pubkey, peer_pubkey);
             
+            // Zero-conf needs a trusted peer; a dual-funded channel counts both contributions
+            let zero_conf = OpenChannelOptions { zero_conf: true, ..OpenChannelOptions::default() };
+            assert!(channel_manager.open_channel(peer_pubkey, 100_000, &zero_conf).is_err());
+            let dual_funded = OpenChannelOptions { remote_funding_sat: Some(50_000), ..OpenChannelOptions::default() };
+            let dual = channel_manager.open_channel(peer_pubkey, 100_000, &dual_funded).unwrap();
+            assert_eq!((dual.capacity, dual.local_balance, dual.remote_balance), (150_000, 100_000, 50_000));
+            
             // Close the channel
             let result = channel_manager.close_channel(&channel.channel_id, false).unwrap();
             assert!(!result.is_empty());
@@ -106,7 +148,7 @@ fn test_peer_manager() {
         
         let config = Config::default();
         
-        let mut peer_manager = PeerManagerWrapper::new(&config);
+        let peer_manager = PeerManagerWrapper::new(&config);
         
         #[cfg(not(feature = "ldk"))]
         {
@@ -197,14 +239,190 @@ fn test_invoice_manager() {
         // Check invoice exists
         assert!(invoice_manager.has_invoice(&invoice.payment_hash));
         
-        // Mark as paid
+        // Mark as paid, which needs the preimage of the payment hash
         let preimage = "0000111122223333444455556666777788889999aaaabbbbccccddddeeeeffff";
+        let payment_hash = super::invoice_manager::payment_hash_for_preimage(preimage).unwrap();
+        let invoice = invoice_manager.create_hold_invoice(&payment_hash, Some(50_000), "Test payment", None).unwrap();
         invoice_manager.mark_invoice_paid(&invoice.payment_hash, preimage).unwrap();
         
         // Check if paid
         assert!(invoice_manager.is_invoice_paid(&invoice.payment_hash).unwrap());
     }
     
+    #[test]
+    fn test_hold_invoice_lifecycle() {
+        use super::invoice_manager::{InvoiceManager, InvoiceState, payment_hash_for_preimage};
+        use super::key_manager::KeyManagerWrapper;
+        use std::sync::Arc;
+        
+        let config = Config::default();
+        let invoice_manager = InvoiceManager::new(&config, Arc::new(KeyManagerWrapper::new(&config)));
+        let updates = invoice_manager.subscribe();
+        
+        let preimage = "0000111122223333444455556666777788889999aaaabbbbccccddddeeeeffff";
+        let payment_hash = payment_hash_for_preimage(preimage).unwrap();
+        
+        let invoice = invoice_manager.create_hold_invoice(&payment_hash, Some(100_000), "Escrow", None).unwrap();
+        assert_eq!(invoice.payment_hash, payment_hash);
+        assert_eq!(invoice_manager.get_invoice_state(&payment_hash).unwrap(), InvoiceState::Open);
+        
+        // Settling before any HTLC arrives is not allowed
+        assert!(invoice_manager.settle_invoice(&payment_hash, preimage).is_err());
+        
+        // A partial payment keeps the invoice open
+        let state = invoice_manager.receive_htlc(&payment_hash, "chan1", 0, 40_000, 800_000).unwrap();
+        assert_eq!(state, InvoiceState::Open);
+        
+        // The remaining part moves it to Accepted and notifies subscribers
+        let state = invoice_manager.receive_htlc(&payment_hash, "chan2", 0, 60_000, 800_000).unwrap();
+        assert_eq!(state, InvoiceState::Accepted);
+        let update = updates.try_recv().unwrap();
+        assert_eq!(update.state, InvoiceState::Accepted);
+        assert_eq!(update.amount_received_msat, 100_000);
+        
+        // Wrong preimage is rejected, correct one settles
+        let wrong = "ffff111122223333444455556666777788889999aaaabbbbccccddddeeeeffff";
+        assert!(invoice_manager.settle_invoice(&payment_hash, wrong).is_err());
+        invoice_manager.settle_invoice(&payment_hash, preimage).unwrap();
+        assert_eq!(invoice_manager.get_invoice_state(&payment_hash).unwrap(), InvoiceState::Settled);
+        assert!(invoice_manager.is_invoice_paid(&payment_hash).unwrap());
+        assert_eq!(updates.try_recv().unwrap().state, InvoiceState::Settled);
+        
+        // No further payments or cancellation once settled
+        assert!(invoice_manager.receive_htlc(&payment_hash, "chan1", 1, 1_000, 800_000).is_err());
+        assert!(invoice_manager.cancel_invoice(&payment_hash).is_err());
+    }
+    
+    #[test]
+    fn test_invoice_state_rules() {
+        use super::invoice_manager::{InvoiceManager, InvoiceState};
+        use super::key_manager::KeyManagerWrapper;
+        use std::sync::Arc;
+        
+        let config = Config::default();
+        let invoice_manager = InvoiceManager::new(&config, Arc::new(KeyManagerWrapper::new(&config)));
+        
+        // Regular invoices settle immediately once paid
+        let invoice = invoice_manager.create_invoice(Some(50_000), "Coffee", None).unwrap();
+        let state = invoice_manager.receive_htlc(&invoice.payment_hash, "chan1", 0, 50_000, 800_000).unwrap();
+        assert_eq!(state, InvoiceState::Settled);
+        
+        // Overpaying by more than 2x is rejected
+        let invoice = invoice_manager.create_invoice(Some(50_000), "Coffee", None).unwrap();
+        assert!(invoice_manager.receive_htlc(&invoice.payment_hash, "chan1", 1, 100_001, 800_000).is_err());
+        assert_eq!(invoice_manager.get_invoice_state(&invoice.payment_hash).unwrap(), InvoiceState::Open);
+        
+        // Cancelled invoices reject payments
+        invoice_manager.cancel_invoice(&invoice.payment_hash).unwrap();
+        assert!(invoice_manager.receive_htlc(&invoice.payment_hash, "chan1", 2, 50_000, 800_000).is_err());
+        
+        // Expired invoices reject payments and move to Expired
+        let invoice = invoice_manager.create_invoice(Some(50_000), "Expired", Some(0)).unwrap();
+        assert!(invoice_manager.is_invoice_expired(&invoice.payment_hash).unwrap());
+        assert!(invoice_manager.receive_htlc(&invoice.payment_hash, "chan1", 3, 50_000, 800_000).is_err());
+        assert_eq!(invoice_manager.get_invoice_state(&invoice.payment_hash).unwrap(), InvoiceState::Expired);
+        
+        let invoice = invoice_manager.create_invoice(Some(50_000), "Swept", Some(0)).unwrap();
+        assert_eq!(invoice_manager.expire_invoices(), vec![invoice.payment_hash]);
+    }
+    
+    #[test]
+    fn test_invoice_preimage_and_htlc_deadlines() {
+        use super::invoice_manager::{
+            InvoiceManager, InvoiceState, InvoiceHtlcState, payment_hash_for_preimage, HELD_HTLC_CANCEL_DELTA
+        };
+        use super::key_manager::KeyManagerWrapper;
+        use std::sync::Arc;
+        
+        let config = Config::default();
+        let invoice_manager = InvoiceManager::new(&config, Arc::new(KeyManagerWrapper::new(&config)));
+        
+        // Marking an invoice paid needs the preimage of its payment hash
+        let invoice = invoice_manager.create_invoice(Some(50_000), "Coffee", None).unwrap();
+        let wrong = "ffff111122223333444455556666777788889999aaaabbbbccccddddeeeeffff";
+        assert!(invoice_manager.mark_invoice_paid(&invoice.payment_hash, wrong).is_err());
+        assert_eq!(invoice_manager.get_invoice_state(&invoice.payment_hash).unwrap(), InvoiceState::Open);
+        
+        // Payment hashes are looked up case-insensitively
+        let preimage = "0000111122223333444455556666777788889999aaaabbbbccccddddeeeeffff";
+        let payment_hash = payment_hash_for_preimage(preimage).unwrap();
+        invoice_manager.create_hold_invoice(&payment_hash.to_uppercase(), Some(100_000), "Escrow", None).unwrap();
+        invoice_manager.receive_htlc(&payment_hash.to_uppercase(), "chan1", 0, 100_000, 800_100).unwrap();
+        assert_eq!(invoice_manager.get_invoice_state(&payment_hash).unwrap(), InvoiceState::Accepted);
+        
+        // Held HTLCs are failed back once within the safety delta of their expiry
+        assert!(invoice_manager.cancel_expiring_htlcs(800_100 - HELD_HTLC_CANCEL_DELTA - 1).is_empty());
+        assert_eq!(invoice_manager.cancel_expiring_htlcs(800_100 - HELD_HTLC_CANCEL_DELTA), vec![payment_hash.clone()]);
+        let invoice_status = invoice_manager.get_invoice_status(&payment_hash).unwrap().unwrap();
+        assert_eq!(invoice_status.state, InvoiceState::Cancelled);
+        assert_eq!(invoice_status.htlcs[0].state, InvoiceHtlcState::Cancelled);
+        
+        // Partial payments drop the expiring part and stay open
+        let invoice = invoice_manager.create_invoice(Some(100_000), "Split", None).unwrap();
+        invoice_manager.receive_htlc(&invoice.payment_hash, "chan1", 1, 40_000, 800_010).unwrap();
+        invoice_manager.receive_htlc(&invoice.payment_hash, "chan2", 2, 40_000, 900_000).unwrap();
+        invoice_manager.cancel_expiring_htlcs(800_000);
+        let invoice_status = invoice_manager.get_invoice_status(&invoice.payment_hash).unwrap().unwrap();
+        assert_eq!(invoice_status.state, InvoiceState::Open);
+        assert_eq!(invoice_status.amount_received_msat(), 40_000);
+    }
+    
+    #[test]
+    fn test_keysend_custom_records() {
+        use super::payment_executor::{PaymentExecutor, KEYSEND_PREIMAGE_TLV_TYPE};
+        use super::payment_router::PaymentRouter;
+        use super::invoice_manager::{InvoiceManager, payment_hash_for_preimage};
+        use super::channel_manager::ChannelManagerWrapper;
+        use super::peer_manager::PeerManagerWrapper;
+        use super::key_manager::KeyManagerWrapper;
+        use std::collections::BTreeMap;
+        use std::sync::Arc;
+        
+        let config = Config::default();
+        let bitcoin_interface = bitcoin::get_current_bitcoin_interface(&config);
+        
+        let executor = PaymentExecutor::new(
+            &config,
+            Arc::new(PaymentRouter::new(&config)),
+            Arc::new(InvoiceManager::new(&config, Arc::new(KeyManagerWrapper::new(&config)))),
+            Arc::new(ChannelManagerWrapper::new(&config, bitcoin_interface)),
+            Arc::new(PeerManagerWrapper::new(&config))
+        );
+        
+        // Boostagram-style metadata in a custom record
+        let mut records = BTreeMap::new();
+        records.insert(7629169, b"{\"action\":\"boost\"}".to_vec());
+        
+        let destination = "035566252e83e2a30ec88140ea7948d505615f057b0e4c186a854cfbef365ea3c5";
+        let payment = executor.keysend_payment_with_records(destination, 10_000, None, records).unwrap();
+        
+        // The preimage travels in the keysend record and matches the payment hash
+        let preimage_record = payment.custom_records.get(&KEYSEND_PREIMAGE_TLV_TYPE).unwrap();
+        let preimage: String = preimage_record.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(payment_hash_for_preimage(&preimage).unwrap(), payment.payment_hash);
+        assert_eq!(payment.preimage, Some(preimage));
+        assert!(payment.custom_records.contains_key(&7629169));
+        
+        // Records outside the custom range are rejected
+        let mut invalid = BTreeMap::new();
+        invalid.insert(100, vec![1]);
+        assert!(executor.keysend_payment_with_records(destination, 10_000, None, invalid).is_err());
+        
+        // The receiving side recovers the preimage and exposes the custom records
+        let received = executor.receive_keysend(&payment.payment_hash.to_uppercase(), 10_000, payment.custom_records.clone()).unwrap();
+        assert_eq!(received.payment_hash, payment.payment_hash);
+        assert_eq!(received.custom_records.get(&7629169), payment.custom_records.get(&7629169));
+        assert_eq!(executor.list_received_payments().unwrap().len(), 1);
+        
+        // The same payment hash in another case is still a duplicate
+        assert!(executor.receive_keysend(&payment.payment_hash, 10_000, payment.custom_records.clone()).is_err());
+        
+        // A preimage that does not match the HTLC's payment hash is rejected
+        let mut forged = payment.custom_records.clone();
+        forged.insert(KEYSEND_PREIMAGE_TLV_TYPE, vec![0u8; 32]);
+        assert!(executor.receive_keysend(&"11".repeat(32), 10_000, forged).is_err());
+    }
+    
     #[test]
     fn test_payment_router() {
         use super::payment_router::{PaymentRouter, PaymentRoute};
@@ -290,4 +508,174 @@ fn test_payment_executor() {
         let payments = executor.list_payments().unwrap();
         assert!(!payments.is_empty());
     }
-} 
\ No newline at end of file
+    
+    // Helper function to run the same test on every available implementation
+    fn test_all_implementations<F>(test_fn: F)
+    where
+        F: Fn(&dyn LightningInterface) -> interface::LightningResult<()>
+    {
+        use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+        
+        let mut config = Config::default();
+        config.bitcoin_network = Some("regtest".to_string());
+        config.lightning_allow_empty_seed_passphrase = true;
+        let bitcoin_interface: Arc<dyn bitcoin::BitcoinInterface> =
+            Arc::new(SimulatedBitcoinImplementation::new(&config));
+        
+        let mock_impl = mock::MockLightningImplementation::new(&config, bitcoin_interface.clone());
+        if let Err(e) = test_fn(&mock_impl) {
+            panic!("Mock implementation test failed: {:?}", e);
+        }
+        
+        // Test LDK implementation if available, on a throwaway data directory
+        #[cfg(feature = "ldk")]
+        {
+            let data_dir = std::env::temp_dir()
+                .join(format!("opsource-ldk-{}", channel_manager::generate_random_id()));
+            config.lightning_data_dir = Some(data_dir.to_string_lossy().to_string());
+            config.lightning_listen_addr = Some("127.0.0.1:0".to_string());
+            
+            let ldk_impl = ldk::LdkLightningImplementation::new(&config, bitcoin_interface);
+            let result = test_fn(&ldk_impl);
+            drop(ldk_impl);
+            let _ = std::fs::remove_dir_all(&data_dir);
+            
+            if let Err(e) = result {
+                panic!("LDK implementation test failed: {:?}", e);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_interface_node_info() {
+        test_all_implementations(|lightning| {
+            let node_info = lightning.get_node_info()?;
+            
+            // Compressed secp256k1 public key
+            assert_eq!(node_info.pubkey.len(), 66);
+            assert!(node_info.pubkey.starts_with("02") || node_info.pubkey.starts_with("03"));
+            
+            Ok(())
+        });
+    }
+    
+    #[test]
+    fn test_interface_invoice_roundtrip() {
+        use super::invoice_manager::InvoiceState;
+        
+        test_all_implementations(|lightning| {
+            let invoice = lightning.create_invoice(Some(50_000), "Interface test", Some(600))?;
+            assert!(!invoice.bolt11.is_empty());
+            assert_eq!(invoice.expiry, 600);
+            
+            let decoded = lightning.decode_invoice(&invoice.bolt11)?;
+            assert_eq!(decoded.payment_hash, invoice.payment_hash);
+            assert_eq!(decoded.amount_msat, Some(50_000));
+            assert_eq!(decoded.description, "Interface test");
+            
+            assert_eq!(lightning.get_invoice_state(&invoice.payment_hash)?, InvoiceState::Open);
+            
+            Ok(())
+        });
+    }
+    
+    #[test]
+    fn test_interface_hold_invoice() {
+        use super::invoice_manager::{InvoiceState, payment_hash_for_preimage};
+        
+        test_all_implementations(|lightning| {
+            let preimage = "0000111122223333444455556666777788889999aaaabbbbccccddddeeeeffff";
+            let payment_hash = payment_hash_for_preimage(preimage)?;
+            
+            // Paying our own hold invoice leaves the payment pending until settled
+            let invoice = lightning.create_hold_invoice(&payment_hash, Some(25_000), "Held", None)?;
+            let payment = lightning.pay_invoice(&invoice.bolt11, None)?;
+            assert_eq!(payment.status, interface::PaymentStatus::Pending);
+            assert_eq!(lightning.get_invoice_state(&payment_hash)?, InvoiceState::Accepted);
+            
+            lightning.settle_invoice(&payment_hash, preimage)?;
+            assert_eq!(lightning.get_invoice_state(&payment_hash)?, InvoiceState::Settled);
+            
+            let payment = lightning.get_payment(&payment_hash)?.unwrap();
+            assert_eq!(payment.status, interface::PaymentStatus::Succeeded);
+            assert_eq!(payment.preimage.as_deref(), Some(preimage));
+            
+            // A cancelled hold invoice rejects payment
+            let other_hash = payment_hash_for_preimage(&"ab".repeat(32))?;
+            let invoice = lightning.create_hold_invoice(&other_hash, Some(25_000), "Cancelled", None)?;
+            lightning.cancel_invoice(&other_hash)?;
+            assert_eq!(lightning.get_invoice_state(&other_hash)?, InvoiceState::Cancelled);
+            assert!(lightning.pay_invoice(&invoice.bolt11, None).is_err());
+            
+            Ok(())
+        });
+    }
+    
+    #[test]
+    fn test_interface_channels_require_peer() {
+        use super::interface::OpenChannelOptions;
+        
+        test_all_implementations(|lightning| {
+            let unknown_peer = "03f02d965ffe0315fd7470b35a09584edb7ae4d2049c7e78584cc2f476db2c5bed";
+            assert!(lightning.open_channel(unknown_peer, 100_000, OpenChannelOptions::default()).is_err());
+            
+            // Whatever channels exist must account for their full capacity
+            for channel in lightning.list_channels()? {
+                assert_eq!(channel.local_balance + channel.remote_balance, channel.capacity);
+            }
+            
+            Ok(())
+        });
+    }
+    
+    #[cfg(not(feature = "ldk"))]
+    #[test]
+    fn test_standalone_splice_waits_for_confirmations() {
+        use crate::bitcoin::BitcoinInterface;
+        use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+        use super::channel_manager::SPLICE_CONFIRMATIONS;
+        
+        let mut config = Config::default();
+        config.bitcoin_network = Some("regtest".to_string());
+        let chain = Arc::new(SimulatedBitcoinImplementation::new(&config));
+        let wallet_address = chain.generate_address(bitcoin::AddressType::P2WPKH).unwrap();
+        chain.fund_address(&wallet_address.address, 5_000_000).unwrap();
+        
+        let lightning = mock::MockLightningImplementation::new(&config, chain.clone());
+        let channel = lightning.list_channels().unwrap().remove(0);
+        
+        // The splice is broadcast, but the channel keeps its funding output until it is buried
+        let spliced = lightning.splice_in(&channel.channel_id, 200_000).unwrap();
+        let splice_txid = spliced.pending_splice.unwrap().splice_txid;
+        assert_eq!(spliced.capacity, channel.capacity);
+        assert!(chain.mempool_txids().contains(&splice_txid));
+        
+        chain.mine_blocks(SPLICE_CONFIRMATIONS - 1);
+        let pending = lightning.list_channels().unwrap().remove(0);
+        assert_eq!(pending.funding_txid, channel.funding_txid);
+        assert!(lightning.splice_in(&channel.channel_id, 1_000).is_err());
+        
+        chain.mine_blocks(1);
+        let locked_in = lightning.list_channels().unwrap().remove(0);
+        assert_eq!(locked_in.funding_txid, splice_txid);
+        assert_eq!(locked_in.capacity, channel.capacity + 200_000);
+        assert_eq!(locked_in.local_balance, channel.local_balance + 200_000);
+        assert!(locked_in.pending_splice.is_none());
+    }
+    
+    #[cfg(feature = "ldk")]
+    #[test]
+    fn test_ldk_rejects_dual_funding() {
+        use super::interface::OpenChannelOptions;
+        
+        let config = Config::default();
+        let bitcoin_interface = bitcoin::get_current_bitcoin_interface(&config);
+        let ldk_impl = ldk::LdkLightningImplementation::new(&config, bitcoin_interface);
+        
+        // LDK cannot take a contribution from the peer, so the open fails instead of dropping it
+        let peer_pubkey = "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619";
+        let dual_funded = OpenChannelOptions { remote_funding_sat: Some(50_000), ..OpenChannelOptions::default() };
+        let err = ldk_impl.open_channel(peer_pubkey, 100_000, dual_funded).unwrap_err();
+        assert!(err.to_string().contains("Dual-funded"));
+    }
+}
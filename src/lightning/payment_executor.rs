@@ -2,32 +2,49 @@
 // Manages payment execution, tracking, and recovery
 
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap};
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 
+use sha2::{Digest, Sha256};
+
+use crate::bitcoin::encoding::to_hex;
 use crate::lightning::interface::{
-    LightningError, LightningResult, PaymentInfo, PaymentStatus
+    LightningError, LightningResult, PaymentInfo, PaymentStatus, Invoice, ChannelInfo
 };
 
-use crate::lightning::payment_router::{PaymentRouter, PaymentRoute};
-use crate::lightning::invoice_manager::InvoiceManager;
+use crate::lightning::payment_router::{PaymentRouter, PaymentRoute, PaymentHop};
+use crate::lightning::invoice_manager::{InvoiceManager, InvoiceState, payment_hash_for_preimage};
 
 use crate::lightning::channel_manager::ChannelManagerWrapper;
 use crate::lightning::peer_manager::PeerManagerWrapper;
+use crate::lightning::lnurl::{LnurlClient, LnurlRequest, lnurl_target_url};
 
 #[cfg(feature = "ldk")]
-use lightning::{
-    ln::channelmanager::{ChannelManager, PaymentId, PaymentPreimage, PaymentHash},
-    ln::msgs::RoutingFees,
-    ln::outbound_payment::{OutboundPayment, OutboundPaymentStatus, Retry},
-    util::config::UserConfig,
-};
+use std::sync::Weak;
+
+#[cfg(feature = "ldk")]
+use crate::lightning::channel_manager::parse_pubkey;
+
+#[cfg(feature = "ldk")]
+use crate::lightning::ldk_node::LdkNode;
+
+/// TLV type carrying the payment preimage in keysend payments
+pub const KEYSEND_PREIMAGE_TLV_TYPE: u64 = 5482373484;
+
+/// Lowest TLV type applications may use for custom records
+pub const MIN_CUSTOM_RECORD_TYPE: u64 = 65536;
+
+/// CLTV delta used for the hops touching our own channels on circular routes
+const CIRCULAR_CLTV_EXPIRY_DELTA: u32 = 40;
 
 /// Payment execution manager
 pub struct PaymentExecutor {
     /// Ongoing payments
     payments: Mutex<HashMap<String, TrackedPayment>>,
     
+    /// Payments received by our node (payment hash -> info)
+    received_payments: Mutex<HashMap<String, PaymentInfo>>,
+    
     /// Router for finding payment paths
     router: Arc<PaymentRouter>,
     
@@ -45,6 +62,13 @@ pub struct PaymentExecutor {
     
     /// Auto-retry configuration
     auto_retry: Mutex<AutoRetryConfig>,
+    
+    /// Client resolving LNURLs and Lightning Addresses into invoices
+    lnurl_client: Mutex<Option<Arc<LnurlClient>>>,
+    
+    /// Running LDK node that sends payments
+    #[cfg(feature = "ldk")]
+    ldk_node: Mutex<Option<Weak<LdkNode>>>,
 }
 
 /// Tracked payment with additional metadata
@@ -105,6 +129,19 @@ pub enum PaymentOrigin {
     Spontaneous,
 }
 
+/// Channels a circular payment is pinned to at either end of its route
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteConstraints {
+    /// Our channel the payment has to leave through
+    pub outgoing_channel_id: String,
+    
+    /// Our channel the payment has to come back in through on the last hop
+    pub last_hop_channel_id: String,
+    
+    /// Largest routing fee we are willing to pay (in msats)
+    pub max_fee_msat: u64,
+}
+
 /// Configuration for auto-retry behavior
 #[derive(Clone, Debug)]
 pub struct AutoRetryConfig {
@@ -147,21 +184,116 @@ pub fn new(
     ) -> Self {
         PaymentExecutor {
             payments: Mutex::new(HashMap::new()),
+            received_payments: Mutex::new(HashMap::new()),
             router,
             invoice_manager,
             channel_manager,
             peer_manager,
             config: Arc::new(config.clone()),
             auto_retry: Mutex::new(AutoRetryConfig::default()),
+            lnurl_client: Mutex::new(None),
+            #[cfg(feature = "ldk")]
+            ldk_node: Mutex::new(None),
         }
     }
     
+    /// Attach the running LDK node
+    ///
+    /// Once attached, payments to other nodes are sent through LDK and resolved by
+    /// its PaymentSent/PaymentFailed events.
+    #[cfg(feature = "ldk")]
+    pub fn attach_ldk(&self, ldk_node: Weak<LdkNode>) {
+        *self.ldk_node.lock().unwrap() = Some(ldk_node);
+    }
+    
+    /// Get the attached LDK node, if it is still running
+    #[cfg(feature = "ldk")]
+    fn ldk_node(&self) -> Option<Arc<LdkNode>> {
+        self.ldk_node.lock().unwrap().as_ref().and_then(Weak::upgrade)
+    }
+    
+    /// Attach the client used to pay LNURLs and Lightning Addresses
+    pub fn attach_lnurl_client(&self, client: Arc<LnurlClient>) {
+        *self.lnurl_client.lock().unwrap() = Some(client);
+    }
+    
+    /// Turn an LNURL-pay or Lightning Address into an invoice for the amount
+    ///
+    /// Anything else (such as a BOLT11 invoice) is returned unchanged. `decode`
+    /// reads the returned invoice so it can be checked against the pay request.
+    pub fn resolve_lnurl_invoice(
+        &self,
+        target: &str,
+        amount_msat: Option<u64>,
+        comment: Option<&str>,
+        decode: &dyn Fn(&str) -> LightningResult<Invoice>,
+    ) -> LightningResult<String> {
+        if lnurl_target_url(target).is_none() {
+            return Ok(target.to_string());
+        }
+        
+        let client = self.lnurl_client.lock().unwrap().clone().ok_or_else(|| {
+            LightningError::ImplementationError("No LNURL client attached".to_string())
+        })?;
+        let amount_msat = amount_msat.ok_or_else(|| {
+            LightningError::PaymentError("Paying an LNURL requires an amount".to_string())
+        })?;
+        
+        let pay = match client.resolve(target)? {
+            LnurlRequest::Pay(pay) => pay,
+            _ => return Err(LightningError::PaymentError(
+                format!("{} is not an LNURL-pay request", target)
+            )),
+        };
+        let bolt11 = client.request_invoice(&pay, amount_msat, comment)?;
+        
+        // The service must not charge more (or less) than we asked to pay
+        let invoice = decode(&bolt11)?;
+        match invoice.amount_msat {
+            Some(invoiced_msat) if invoiced_msat == amount_msat => {}
+            Some(invoiced_msat) => return Err(LightningError::PaymentError(format!(
+                "LNURL service returned an invoice for {} msat instead of {} msat",
+                invoiced_msat, amount_msat
+            ))),
+            None => return Err(LightningError::PaymentError(
+                "LNURL service returned an invoice without an amount".to_string()
+            )),
+        }
+        
+        // LUD-06: the invoice must commit to the metadata we were shown
+        let metadata_hash = to_hex(&Sha256::digest(pay.metadata.as_bytes()));
+        if invoice.description_hash.as_deref().map(str::to_lowercase) != Some(metadata_hash) {
+            return Err(LightningError::PaymentError(
+                "LNURL service returned an invoice whose description hash does not match its metadata".to_string()
+            ));
+        }
+        
+        Ok(bolt11)
+    }
+    
     /// Pay a BOLT11 invoice
     pub fn pay_invoice(
         &self,
         bolt11: &str,
         amount_msat: Option<u64>,
     ) -> LightningResult<PaymentInfo> {
+        self.pay_invoice_with_custom_records(bolt11, amount_msat, BTreeMap::new())
+    }
+    
+    /// Pay a BOLT11 invoice, attaching custom TLV records to the final hop
+    pub fn pay_invoice_with_custom_records(
+        &self,
+        bolt11: &str,
+        amount_msat: Option<u64>,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        validate_custom_records(&custom_records)?;
+        
+        // LNURLs and Lightning Addresses resolve to an invoice first
+        let bolt11 = &self.resolve_lnurl_invoice(bolt11, amount_msat, None, &|bolt11| {
+            self.invoice_manager.decode_invoice(bolt11)
+        })?;
+        
         // First, decode the invoice
         let invoice = self.invoice_manager.decode_invoice(bolt11)?;
         
@@ -175,6 +307,24 @@ pub fn pay_invoice(
             })?,
         };
         
+        // Invoices issued by our own node are delivered locally without routing
+        if self.invoice_manager.has_invoice(&invoice.payment_hash) {
+            return self.pay_local_invoice(bolt11, &invoice, payment_amount, custom_records);
+        }
+        
+        #[cfg(feature = "ldk")]
+        if let Some(ldk_node) = self.ldk_node() {
+            ldk_node.pay_invoice(bolt11, amount_msat, custom_records.clone())?;
+            return Ok(self.track_pending_payment(
+                &invoice.payment_hash,
+                None,
+                payment_amount,
+                Some(invoice.description.clone()),
+                PaymentOrigin::Invoice(bolt11.to_string()),
+                custom_records,
+            ));
+        }
+        
         // Check that our node has enough inbound capacity to receive this payment
         let channels = self.channel_manager.list_channels()?;
         let total_inbound_capacity: u64 = channels.iter()
@@ -223,6 +373,7 @@ pub fn pay_invoice(
             created_at: self.get_timestamp(),
             resolved_at: None,
             description: Some(invoice.description.clone()),
+            custom_records,
         };
         
         // Create a payment attempt
@@ -241,8 +392,8 @@ pub fn pay_invoice(
             origin: PaymentOrigin::Invoice(bolt11.to_string()),
         };
         
-        let mut payments = self.payments.lock().unwrap();
-        payments.insert(payment_id.clone(), tracked_payment);
+        // Release the lock before completing, which locks payments again
+        self.payments.lock().unwrap().insert(payment_id.clone(), tracked_payment);
         
         // In a real implementation, we would now execute the payment using LDK
         // For now, automatically complete the payment
@@ -271,6 +422,322 @@ pub fn pay_invoice(
             ))
     }
     
+    /// Pay an invoice issued by our own node
+    ///
+    /// The HTLC is handed straight to the invoice manager. Regular invoices settle
+    /// immediately; hold invoices leave the payment pending until the invoice is
+    /// settled or cancelled.
+    fn pay_local_invoice(
+        &self,
+        bolt11: &str,
+        invoice: &Invoice,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        let payment_id = format!("pid_{}", generate_random_bytes_hex(16));
+        let cltv_expiry = self.channel_manager.block_height()? + invoice.min_final_cltv_expiry;
+        let state = self.invoice_manager.receive_htlc(
+            &invoice.payment_hash,
+            "local",
+            0,
+            amount_msat,
+            cltv_expiry,
+        )?;
+        
+        let mut payment_info = PaymentInfo {
+            payment_id: payment_id.clone(),
+            payment_hash: invoice.payment_hash.clone(),
+            preimage: None,
+            amount_msat,
+            fee_msat: 0,
+            status: PaymentStatus::Pending,
+            created_at: self.get_timestamp(),
+            resolved_at: None,
+            description: Some(invoice.description.clone()),
+            custom_records,
+        };
+        
+        if state == InvoiceState::Settled {
+            payment_info.status = PaymentStatus::Succeeded;
+            payment_info.resolved_at = Some(self.get_timestamp());
+            payment_info.preimage = self.invoice_manager
+                .get_invoice_status(&invoice.payment_hash)?
+                .and_then(|invoice_status| invoice_status.payment_preimage);
+        }
+        
+        self.payments.lock().unwrap().insert(payment_id, TrackedPayment {
+            info: payment_info.clone(),
+            route: None,
+            attempts: Vec::new(),
+            origin: PaymentOrigin::Invoice(bolt11.to_string()),
+        });
+        
+        Ok(payment_info)
+    }
+    
+    /// Find a route out through the outgoing channel and back in through the last-hop channel
+    ///
+    /// The part between the two peers never passes through our own node.
+    pub fn find_constrained_route(
+        &self,
+        amount_msat: u64,
+        constraints: &RouteConstraints,
+    ) -> LightningResult<PaymentRoute> {
+        if constraints.outgoing_channel_id == constraints.last_hop_channel_id {
+            return Err(LightningError::ChannelError(
+                "Outgoing and last-hop channels must differ".to_string()
+            ));
+        }
+        
+        let local_pubkey = self.invoice_manager.node_pubkey()?;
+        let outgoing = self.require_channel(&constraints.outgoing_channel_id)?;
+        let last_hop = self.require_channel(&constraints.last_hop_channel_id)?;
+        
+        let middle = if outgoing.remote_pubkey == last_hop.remote_pubkey {
+            PaymentRoute {
+                hops: Vec::new(),
+                total_amount_msat: amount_msat,
+                total_fee_msat: 0,
+                total_cltv_expiry_delta: 0,
+            }
+        } else {
+            self.router.find_route_excluding(
+                &outgoing.remote_pubkey,
+                &last_hop.remote_pubkey,
+                amount_msat,
+                144,
+                std::slice::from_ref(&local_pubkey),
+            )?
+        };
+        
+        // The last peer charges its advertised fee for forwarding into our channel
+        let (fee_base_msat, fee_proportional_millionths) = self.router
+            .get_channel_fees(&last_hop.channel_id)
+            .unwrap_or((0, 0));
+        let last_fee_msat = fee_base_msat as u64 +
+            amount_msat * fee_proportional_millionths as u64 / 1_000_000;
+        
+        let mut hops = Vec::with_capacity(middle.hops.len() + 2);
+        hops.push(PaymentHop {
+            src_node_id: local_pubkey.clone(),
+            dest_node_id: outgoing.remote_pubkey.clone(),
+            channel_id: outgoing.channel_id.clone(),
+            amount_msat,
+            fee_msat: 0, // We do not charge ourselves
+            cltv_expiry_delta: CIRCULAR_CLTV_EXPIRY_DELTA,
+        });
+        hops.extend(middle.hops.iter().cloned());
+        hops.push(PaymentHop {
+            src_node_id: last_hop.remote_pubkey.clone(),
+            dest_node_id: local_pubkey,
+            channel_id: last_hop.channel_id.clone(),
+            amount_msat,
+            fee_msat: last_fee_msat,
+            cltv_expiry_delta: CIRCULAR_CLTV_EXPIRY_DELTA,
+        });
+        
+        Ok(PaymentRoute {
+            hops,
+            total_amount_msat: amount_msat,
+            total_fee_msat: middle.total_fee_msat + last_fee_msat,
+            total_cltv_expiry_delta: middle.total_cltv_expiry_delta + 2 * CIRCULAR_CLTV_EXPIRY_DELTA,
+        })
+    }
+    
+    /// Pay one of our own invoices around a circle of channels
+    ///
+    /// The payment leaves through `outgoing_channel_id`, crosses the network and comes
+    /// back in through `last_hop_channel_id`, where the HTLC is settled with the
+    /// invoice's preimage. Under LDK the route is handed to the node; otherwise both
+    /// channels' balances move together with the settled HTLC.
+    pub fn pay_invoice_with_constraints(
+        &self,
+        bolt11: &str,
+        constraints: &RouteConstraints,
+    ) -> LightningResult<PaymentInfo> {
+        let invoice = self.invoice_manager.decode_invoice(bolt11)?;
+        let invoice_status = self.invoice_manager.get_invoice_status(&invoice.payment_hash)?
+            .ok_or_else(|| LightningError::PaymentError(
+                "Only payments to our own invoices can be pinned to channels".to_string()
+            ))?;
+        
+        if invoice_status.is_hold {
+            return Err(LightningError::PaymentError(
+                "Hold invoices cannot be paid over a pinned route".to_string()
+            ));
+        }
+        
+        let amount_msat = invoice.amount_msat.ok_or_else(|| {
+            LightningError::PaymentError("Circular payments need an invoice with an amount".to_string())
+        })?;
+        
+        #[cfg(feature = "ldk")]
+        if let Some(ldk_node) = self.ldk_node() {
+            ldk_node.pay_invoice_circular(
+                bolt11,
+                &constraints.outgoing_channel_id,
+                &constraints.last_hop_channel_id,
+                constraints.max_fee_msat,
+            )?;
+            return Ok(self.track_pending_payment(
+                &invoice.payment_hash,
+                None,
+                amount_msat,
+                Some(invoice.description.clone()),
+                PaymentOrigin::Invoice(bolt11.to_string()),
+                BTreeMap::new(),
+            ));
+        }
+        
+        let route = self.find_constrained_route(amount_msat, constraints)?;
+        if route.total_fee_msat > constraints.max_fee_msat {
+            return Err(LightningError::PaymentError(format!(
+                "Route fee {} msat exceeds budget of {} msat",
+                route.total_fee_msat, constraints.max_fee_msat
+            )));
+        }
+        
+        // The fee leaves through the outgoing channel together with the amount
+        let outgoing_sat = (amount_msat + route.total_fee_msat).div_ceil(1000);
+        let incoming_sat = amount_msat / 1000;
+        let cltv_expiry = self.channel_manager.block_height()? + invoice.min_final_cltv_expiry;
+        
+        let payment_id = format!("pid_{}", generate_random_bytes_hex(16));
+        self.payments.lock().unwrap().insert(payment_id.clone(), TrackedPayment {
+            info: PaymentInfo {
+                payment_id: payment_id.clone(),
+                payment_hash: invoice.payment_hash.clone(),
+                preimage: None,
+                amount_msat,
+                fee_msat: route.total_fee_msat,
+                status: PaymentStatus::Pending,
+                created_at: self.get_timestamp(),
+                resolved_at: None,
+                description: Some(invoice.description.clone()),
+                custom_records: BTreeMap::new(),
+            },
+            route: Some(route.clone()),
+            attempts: vec![PaymentAttempt {
+                timestamp: self.get_timestamp(),
+                route,
+                status: PaymentAttemptStatus::InFlight,
+                error: None,
+            }],
+            origin: PaymentOrigin::Invoice(bolt11.to_string()),
+        });
+        
+        let settled = self.channel_manager.modify_channels(
+            &[&constraints.outgoing_channel_id, &constraints.last_hop_channel_id],
+            |channels| {
+                let [outgoing, last_hop] = channels else {
+                    unreachable!("two channel IDs give two channels");
+                };
+                
+                if !outgoing.is_active || !last_hop.is_active {
+                    return Err(LightningError::ChannelError(
+                        "Both channels of a circular payment must be active".to_string()
+                    ));
+                }
+                if outgoing.local_balance < outgoing_sat {
+                    return Err(LightningError::ChannelError(
+                        format!("Insufficient local balance on channel {}", outgoing.channel_id)
+                    ));
+                }
+                if last_hop.remote_balance < incoming_sat {
+                    return Err(LightningError::ChannelError(
+                        format!("Insufficient remote balance on channel {}", last_hop.channel_id)
+                    ));
+                }
+                
+                // The HTLC arrives on the last hop and settles the invoice it pays
+                let state = self.invoice_manager.receive_htlc(
+                    &invoice.payment_hash,
+                    &last_hop.channel_id,
+                    0,
+                    amount_msat,
+                    cltv_expiry,
+                )?;
+                if state != InvoiceState::Settled {
+                    return Err(LightningError::PaymentError(
+                        format!("Invoice {} did not settle ({:?})", invoice.payment_hash, state)
+                    ));
+                }
+                
+                outgoing.local_balance -= outgoing_sat;
+                outgoing.remote_balance += outgoing_sat;
+                last_hop.remote_balance -= incoming_sat;
+                last_hop.local_balance += incoming_sat;
+                
+                Ok(())
+            },
+        ).and_then(|_| {
+            self.invoice_manager.get_invoice_status(&invoice.payment_hash)?
+                .and_then(|invoice_status| invoice_status.payment_preimage)
+                .ok_or_else(|| LightningError::PaymentError(
+                    format!("Invoice {} settled without a preimage", invoice.payment_hash)
+                ))
+        });
+        
+        match settled {
+            Ok(preimage) => {
+                if payment_hash_for_preimage(&preimage)? != invoice.payment_hash {
+                    return Err(LightningError::PaymentError(
+                        format!("Preimage does not match payment hash {}", invoice.payment_hash)
+                    ));
+                }
+                self.complete_payment(&payment_id, &invoice.payment_hash, &preimage, PaymentStatus::Succeeded)?;
+            }
+            Err(e) => {
+                self.complete_payment(&payment_id, &invoice.payment_hash, "", PaymentStatus::Failed)?;
+                return Err(e);
+            }
+        }
+        
+        self.payments.lock().unwrap().get(&payment_id)
+            .map(|tracked| tracked.info.clone())
+            .ok_or_else(|| LightningError::PaymentError(
+                format!("Payment not found after completion: {}", payment_id)
+            ))
+    }
+    
+    /// Look up one of our channels or fail with a channel error
+    fn require_channel(&self, channel_id: &str) -> LightningResult<ChannelInfo> {
+        self.channel_manager.get_channel(channel_id)?
+            .ok_or_else(|| LightningError::ChannelError(
+                format!("Channel {} not found", channel_id)
+            ))
+    }
+    
+    /// Resolve pending payments to our own hold invoices once the invoice is settled or cancelled
+    fn sync_local_payments(&self) {
+        let mut payments = self.payments.lock().unwrap();
+        
+        for tracked in payments.values_mut() {
+            if tracked.info.status != PaymentStatus::Pending
+                || !matches!(tracked.origin, PaymentOrigin::Invoice(_)) {
+                continue;
+            }
+            
+            let invoice_status = match self.invoice_manager.get_invoice_status(&tracked.info.payment_hash) {
+                Ok(Some(invoice_status)) => invoice_status,
+                _ => continue,
+            };
+            
+            match invoice_status.state {
+                InvoiceState::Settled => {
+                    tracked.info.status = PaymentStatus::Succeeded;
+                    tracked.info.preimage = invoice_status.payment_preimage;
+                    tracked.info.resolved_at = invoice_status.paid_at;
+                }
+                InvoiceState::Cancelled | InvoiceState::Expired => {
+                    tracked.info.status = PaymentStatus::Failed;
+                    tracked.info.resolved_at = Some(self.get_timestamp());
+                }
+                InvoiceState::Open | InvoiceState::Accepted => {}
+            }
+        }
+    }
+    
     /// Make a spontaneous payment (keysend)
     pub fn keysend_payment(
         &self,
@@ -278,6 +745,22 @@ pub fn keysend_payment(
         amount_msat: u64,
         description: Option<&str>,
     ) -> LightningResult<PaymentInfo> {
+        self.keysend_payment_with_records(destination, amount_msat, description, BTreeMap::new())
+    }
+    
+    /// Make a spontaneous payment (keysend) carrying custom TLV records
+    ///
+    /// The preimage is chosen by the sender and travels to the recipient in the
+    /// keysend TLV record (type 5482373484) alongside any custom records.
+    pub fn keysend_payment_with_records(
+        &self,
+        destination: &str,
+        amount_msat: u64,
+        description: Option<&str>,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        validate_custom_records(&custom_records)?;
+        
         // Get our node's pubkey
         let node_info = match self.peer_manager.list_peers()?.first() {
             Some(node) => {
@@ -293,11 +776,26 @@ pub fn keysend_payment(
         // Generate a payment ID
         let payment_id = format!("pid_{}", generate_random_bytes_hex(16));
         
-        // Generate a payment hash
-        let payment_hash = generate_random_bytes_hex(32);
-        
-        // Generate a preimage
+        // Generate the preimage; the payment hash commits to it
         let preimage = generate_random_bytes_hex(32);
+        let payment_hash = payment_hash_for_preimage(&preimage)?;
+        
+        #[cfg(feature = "ldk")]
+        if let Some(ldk_node) = self.ldk_node() {
+            ldk_node.send_keysend(&parse_pubkey(destination)?, amount_msat, &preimage, custom_records.clone())?;
+            return Ok(self.track_pending_payment(
+                &payment_hash,
+                Some(preimage),
+                amount_msat,
+                description.map(String::from),
+                PaymentOrigin::Spontaneous,
+                custom_records,
+            ));
+        }
+        
+        // Attach the preimage to the final hop onion payload
+        let mut onion_records = custom_records;
+        onion_records.insert(KEYSEND_PREIMAGE_TLV_TYPE, decode_preimage(&preimage)?);
         
         // Find a route to the destination
         let route = self.router.find_route(&node_info, destination, amount_msat, 144)?;
@@ -313,6 +811,7 @@ pub fn keysend_payment(
             created_at: self.get_timestamp(),
             resolved_at: None,
             description: description.map(String::from),
+            custom_records: onion_records,
         };
         
         // Create a payment attempt
@@ -331,8 +830,8 @@ pub fn keysend_payment(
             origin: PaymentOrigin::Spontaneous,
         };
         
-        let mut payments = self.payments.lock().unwrap();
-        payments.insert(payment_id.clone(), tracked_payment);
+        // Release the lock before completing, which locks payments again
+        self.payments.lock().unwrap().insert(payment_id.clone(), tracked_payment);
         
         // In a real implementation, we would now execute the payment using LDK
         // For now, automatically complete the payment
@@ -351,13 +850,76 @@ pub fn keysend_payment(
             ))
     }
     
+    /// Handle an incoming keysend payment addressed to our node
+    ///
+    /// The preimage is taken from the keysend TLV record and must hash to the HTLC's
+    /// payment hash. The remaining custom records are kept on the received payment.
+    pub fn receive_keysend(
+        &self,
+        payment_hash: &str,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        let payment_hash = &payment_hash.to_lowercase();
+        
+        if !self.config.features.accept_keysend {
+            return Err(LightningError::PaymentError(
+                "Keysend payments are not accepted by this node".to_string()
+            ));
+        }
+        
+        let preimage_bytes = custom_records.get(&KEYSEND_PREIMAGE_TLV_TYPE)
+            .ok_or_else(|| LightningError::PaymentError(
+                "Keysend payment is missing the preimage record".to_string()
+            ))?;
+        let preimage: String = preimage_bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        
+        if payment_hash_for_preimage(&preimage)? != *payment_hash {
+            return Err(LightningError::PaymentError(
+                format!("Keysend preimage does not match payment hash {}", payment_hash)
+            ));
+        }
+        
+        let mut received_payments = self.received_payments.lock().unwrap();
+        if received_payments.contains_key(payment_hash) {
+            return Err(LightningError::PaymentError(
+                format!("Duplicate keysend payment: {}", payment_hash)
+            ));
+        }
+        
+        let now = self.get_timestamp();
+        let payment_info = PaymentInfo {
+            payment_id: format!("pid_{}", generate_random_bytes_hex(16)),
+            payment_hash: payment_hash.clone(),
+            preimage: Some(preimage),
+            amount_msat,
+            fee_msat: 0,
+            status: PaymentStatus::Succeeded,
+            created_at: now,
+            resolved_at: Some(now),
+            description: None,
+            custom_records,
+        };
+        
+        received_payments.insert(payment_info.payment_hash.clone(), payment_info.clone());
+        
+        Ok(payment_info)
+    }
+    
+    /// List payments received by our node
+    pub fn list_received_payments(&self) -> LightningResult<Vec<PaymentInfo>> {
+        let received_payments = self.received_payments.lock().unwrap();
+        Ok(received_payments.values().cloned().collect())
+    }
+    
     /// Get a payment by hash
     pub fn get_payment(&self, payment_hash: &str) -> LightningResult<Option<PaymentInfo>> {
+        self.sync_local_payments();
         let payments = self.payments.lock().unwrap();
         
         // Find the payment by hash
         for tracked in payments.values() {
-            if tracked.info.payment_hash == payment_hash {
+            if tracked.info.payment_hash.eq_ignore_ascii_case(payment_hash) {
                 return Ok(Some(tracked.info.clone()));
             }
         }
@@ -367,6 +929,7 @@ pub fn get_payment(&self, payment_hash: &str) -> LightningResult<Option<PaymentI
     
     /// List all payments
     pub fn list_payments(&self) -> LightningResult<Vec<PaymentInfo>> {
+        self.sync_local_payments();
         let payments = self.payments.lock().unwrap();
         Ok(payments.values().map(|p| p.info.clone()).collect())
     }
@@ -430,6 +993,70 @@ fn complete_payment(
         }
     }
     
+    /// Record a payment handed to another backend (LDK or the in-memory test network)
+    ///
+    /// The payment stays pending until the backend reports the outcome through
+    /// `handle_payment_sent` or `handle_payment_failed`.
+    pub fn track_pending_payment(
+        &self,
+        payment_hash: &str,
+        preimage: Option<String>,
+        amount_msat: u64,
+        description: Option<String>,
+        origin: PaymentOrigin,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> PaymentInfo {
+        let payment_info = PaymentInfo {
+            payment_id: format!("pid_{}", generate_random_bytes_hex(16)),
+            payment_hash: payment_hash.to_string(),
+            preimage,
+            amount_msat,
+            fee_msat: 0,
+            status: PaymentStatus::Pending,
+            created_at: self.get_timestamp(),
+            resolved_at: None,
+            description,
+            custom_records,
+        };
+        
+        self.payments.lock().unwrap().insert(payment_info.payment_id.clone(), TrackedPayment {
+            info: payment_info.clone(),
+            route: None,
+            attempts: Vec::new(),
+            origin,
+        });
+        
+        payment_info
+    }
+    
+    /// Record that an in-flight payment succeeded
+    pub fn handle_payment_sent(&self, payment_hash: &str, preimage: &str, fee_msat: u64) {
+        let mut payments = self.payments.lock().unwrap();
+        let now = self.get_timestamp();
+        
+        for tracked in payments.values_mut() {
+            if tracked.info.payment_hash == payment_hash && tracked.info.status == PaymentStatus::Pending {
+                tracked.info.status = PaymentStatus::Succeeded;
+                tracked.info.preimage = Some(preimage.to_string());
+                tracked.info.fee_msat = fee_msat;
+                tracked.info.resolved_at = Some(now);
+            }
+        }
+    }
+    
+    /// Record that an in-flight payment failed after all retries
+    pub fn handle_payment_failed(&self, payment_hash: &str) {
+        let mut payments = self.payments.lock().unwrap();
+        let now = self.get_timestamp();
+        
+        for tracked in payments.values_mut() {
+            if tracked.info.payment_hash == payment_hash && tracked.info.status == PaymentStatus::Pending {
+                tracked.info.status = PaymentStatus::Failed;
+                tracked.info.resolved_at = Some(now);
+            }
+        }
+    }
+    
     /// Configure auto-retry behavior
     pub fn configure_auto_retry(&self, config: AutoRetryConfig) {
         let mut auto_retry = self.auto_retry.lock().unwrap();
@@ -445,6 +1072,31 @@ fn get_timestamp(&self) -> u64 {
     }
 }
 
+/// Check that custom records use the custom type range and do not clash with keysend
+pub fn validate_custom_records(custom_records: &BTreeMap<u64, Vec<u8>>) -> LightningResult<()> {
+    for record_type in custom_records.keys() {
+        if *record_type < MIN_CUSTOM_RECORD_TYPE {
+            return Err(LightningError::PaymentError(
+                format!("Custom record type {} is below {}", record_type, MIN_CUSTOM_RECORD_TYPE)
+            ));
+        }
+        
+        if *record_type == KEYSEND_PREIMAGE_TLV_TYPE {
+            return Err(LightningError::PaymentError(
+                "The keysend preimage record is set by the payment executor".to_string()
+            ));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Decode a hex preimage into the raw bytes carried in the onion
+fn decode_preimage(preimage: &str) -> LightningResult<Vec<u8>> {
+    crate::lightning::invoice_manager::decode_hex(preimage)
+        .ok_or_else(|| LightningError::PaymentError(format!("Invalid preimage: {}", preimage)))
+}
+
 /// Generate random bytes and return as hex string
 fn generate_random_bytes_hex(len: usize) -> String {
     use rand::{thread_rng, Rng};
@@ -130,10 +130,22 @@ pub fn find_route(
         if let Some(network_graph) = &self.network_graph {
             // In a real implementation, we would use LDK's router to find a path
             // For now, use our manual graph as a fallback
-            return self.find_route_manual(source, destination, amount_msat, max_cltv_expiry);
+            return self.find_route_manual(source, destination, amount_msat, max_cltv_expiry, &[]);
         }
         
-        self.find_route_manual(source, destination, amount_msat, max_cltv_expiry)
+        self.find_route_manual(source, destination, amount_msat, max_cltv_expiry, &[])
+    }
+    
+    /// Find a route from source to destination that does not pass through the excluded nodes
+    pub fn find_route_excluding(
+        &self,
+        source: &str,
+        destination: &str,
+        amount_msat: u64,
+        max_cltv_expiry: u32,
+        excluded_nodes: &[String],
+    ) -> LightningResult<PaymentRoute> {
+        self.find_route_manual(source, destination, amount_msat, max_cltv_expiry, excluded_nodes)
     }
     
     /// Find a route using our manual graph
@@ -143,6 +155,7 @@ fn find_route_manual(
         destination: &str,
         amount_msat: u64,
         max_cltv_expiry: u32,
+        excluded_nodes: &[String],
     ) -> LightningResult<PaymentRoute> {
         let graph = self.manual_graph.lock().unwrap();
         
@@ -150,10 +163,11 @@ fn find_route_manual(
         if graph.edges.is_empty() {
             drop(graph);
             self.add_mock_graph_data();
-            return self.find_route_dijkstra(source, destination, amount_msat, max_cltv_expiry);
+            return self.find_route_dijkstra(source, destination, amount_msat, max_cltv_expiry, excluded_nodes);
         }
         
-        self.find_route_dijkstra(source, destination, amount_msat, max_cltv_expiry)
+        drop(graph);
+        self.find_route_dijkstra(source, destination, amount_msat, max_cltv_expiry, excluded_nodes)
     }
     
     /// Find a route using Dijkstra's algorithm
@@ -163,6 +177,7 @@ fn find_route_dijkstra(
         destination: &str,
         amount_msat: u64,
         _max_cltv_expiry: u32,
+        excluded_nodes: &[String],
     ) -> LightningResult<PaymentRoute> {
         let graph = self.manual_graph.lock().unwrap();
         
@@ -226,6 +241,11 @@ fn find_route_dijkstra(
             // Check all outgoing edges
             if let Some(edges) = graph.edges.get(&node.pubkey) {
                 for (target, channel_id, capacity, fee_base_msat, fee_proportional_millionths) in edges {
+                    // Skip nodes the caller asked us to avoid
+                    if excluded_nodes.contains(target) {
+                        continue;
+                    }
+                    
                     // Skip if insufficient capacity
                     if *capacity < amount_msat / 1000 {
                         continue;
@@ -310,10 +330,10 @@ fn reconstruct_path(
         path.reverse();
         
         Ok(PaymentRoute {
+            total_cltv_expiry_delta: 40 * path.len() as u32, // 40 blocks per hop
             hops: path,
             total_amount_msat: amount_msat,
             total_fee_msat,
-            total_cltv_expiry_delta: 40 * path.len() as u32, // 40 blocks per hop
         })
     }
     
@@ -399,32 +419,32 @@ pub fn update_channel_capacity(
         
         // Update channel info
         if let Some((node1, node2, _, fee_base_msat, fee_proportional_millionths)) = 
-            graph.channels.get(channel_id) {
+            graph.channels.get(channel_id).cloned() {
             
             // Create updated channel info
             let updated_info = (
                 node1.clone(), 
                 node2.clone(), 
                 new_capacity, 
-                *fee_base_msat, 
-                *fee_proportional_millionths
+                fee_base_msat, 
+                fee_proportional_millionths
             );
             
             // Update the channel info
             graph.channels.insert(channel_id.to_string(), updated_info);
             
             // Update edges in both directions
-            if let Some(edges) = graph.edges.get_mut(node1) {
+            if let Some(edges) = graph.edges.get_mut(&node1) {
                 for edge in edges.iter_mut() {
-                    if edge.0 == *node2 && edge.1 == channel_id {
+                    if edge.0 == node2 && edge.1 == channel_id {
                         edge.2 = new_capacity;
                     }
                 }
             }
             
-            if let Some(edges) = graph.edges.get_mut(node2) {
+            if let Some(edges) = graph.edges.get_mut(&node2) {
                 for edge in edges.iter_mut() {
-                    if edge.0 == *node1 && edge.1 == channel_id {
+                    if edge.0 == node1 && edge.1 == channel_id {
                         edge.2 = new_capacity;
                     }
                 }
@@ -438,6 +458,47 @@ pub fn update_channel_capacity(
         }
     }
     
+    /// Get the fees advertised for a channel (fee_base_msat, fee_proportional_millionths)
+    pub fn get_channel_fees(&self, channel_id: &str) -> Option<(u32, u32)> {
+        let graph = self.manual_graph.lock().unwrap();
+        graph.channels.get(channel_id).map(|channel| (channel.3, channel.4))
+    }
+    
+    /// Update the fees advertised for a channel (from a channel_update)
+    pub fn update_channel_fees(
+        &self,
+        channel_id: &str,
+        fee_base_msat: u32,
+        fee_proportional_millionths: u32,
+    ) -> LightningResult<()> {
+        let mut graph = self.manual_graph.lock().unwrap();
+
+        let (node1, node2) = match graph.channels.get_mut(channel_id) {
+            Some(channel) => {
+                channel.3 = fee_base_msat;
+                channel.4 = fee_proportional_millionths;
+                (channel.0.clone(), channel.1.clone())
+            }
+            None => return Err(LightningError::ChannelError(
+                format!("Channel not found: {}", channel_id)
+            )),
+        };
+
+        // Update edges in both directions
+        for (source, target) in [(&node1, &node2), (&node2, &node1)] {
+            if let Some(edges) = graph.edges.get_mut(source) {
+                for edge in edges.iter_mut() {
+                    if edge.0 == *target && edge.1 == channel_id {
+                        edge.3 = fee_base_msat;
+                        edge.4 = fee_proportional_millionths;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Add mock data to the graph for testing
     fn add_mock_graph_data(&self) {
         // Create a small test network with 5 nodes
@@ -12,28 +12,45 @@
 
 use crate::lightning::channel_manager::generate_random_id;
 
+#[cfg(feature = "ldk")]
+use std::time::Duration;
+
 #[cfg(feature = "ldk")]
 use lightning::{
-    ln::{
-        peer_handler::{PeerManager, SocketDescriptor},
-        msgs::SocketAddress,
-    },
-    routing::gossip::NetworkGraph,
-    util::ser::ReadableArgs,
+    ln::msgs::NetAddress,
+    routing::gossip::NodeId,
+    bitcoin::secp256k1::PublicKey,
 };
 
+#[cfg(feature = "ldk")]
+use crate::bitcoin::encoding::to_hex;
+
+#[cfg(feature = "ldk")]
+use crate::lightning::channel_manager::parse_pubkey;
+
+#[cfg(feature = "ldk")]
+use crate::lightning::ldk_node::{LdkPeerManager, LdkNetworkGraph};
+
+/// How long to wait for the noise handshake after opening a connection
+#[cfg(feature = "ldk")]
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// LDK Peer Manager wrapper
 pub struct PeerManagerWrapper {
     /// LDK Peer Manager
     #[cfg(feature = "ldk")]
-    peer_manager: Mutex<Option<Arc<PeerManager>>>,
+    peer_manager: Mutex<Option<Arc<LdkPeerManager>>>,
     
     /// Connected peers (for both real and mock data)
     connected_peers: Mutex<HashMap<String, NodeInfo>>,
     
     /// Network graph
     #[cfg(feature = "ldk")]
-    network_graph: Mutex<Option<Arc<NetworkGraph>>>,
+    network_graph: Mutex<Option<Arc<LdkNetworkGraph>>>,
+    
+    /// Runtime driving LDK's socket handling
+    #[cfg(feature = "ldk")]
+    runtime: Mutex<Option<tokio::runtime::Handle>>,
     
     /// Configuration
     config: Arc<crate::config::Config>,
@@ -48,32 +65,35 @@ pub fn new(config: &crate::config::Config) -> Self {
             connected_peers: Mutex::new(HashMap::new()),
             #[cfg(feature = "ldk")]
             network_graph: Mutex::new(None),
+            #[cfg(feature = "ldk")]
+            runtime: Mutex::new(None),
             config: Arc::new(config.clone()),
         }
     }
     
-    /// Initialize the peer manager
+    /// Attach the running LDK PeerManager
+    ///
+    /// Once attached, peer operations go through LDK instead of the peer cache.
     #[cfg(feature = "ldk")]
-    pub fn initialize(&mut self) -> LightningResult<()> {
-        // In a real implementation, we would initialize the PeerManager here
-        // For now, add a mock peer to show it works
-        let mut peers = self.connected_peers.lock().unwrap();
-        
-        // Add a mock peer
-        let peer = NodeInfo {
-            pubkey: "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619".to_string(),
-            addresses: vec!["127.0.0.1:9735".to_string()],
-            alias: Some("Mock Peer".to_string()),
-            color: Some("#FF9900".to_string()),
-            features: vec!["option_static_remotekey".to_string()],
-        };
-        
-        peers.insert(peer.pubkey.clone(), peer);
-        Ok(())
+    pub fn attach_ldk(
+        &self,
+        peer_manager: Arc<LdkPeerManager>,
+        network_graph: Arc<LdkNetworkGraph>,
+        runtime: tokio::runtime::Handle,
+    ) {
+        *self.peer_manager.lock().unwrap() = Some(peer_manager);
+        *self.network_graph.lock().unwrap() = Some(network_graph);
+        *self.runtime.lock().unwrap() = Some(runtime);
+    }
+    
+    /// Get the attached LDK PeerManager
+    #[cfg(feature = "ldk")]
+    fn ldk(&self) -> Option<Arc<LdkPeerManager>> {
+        self.peer_manager.lock().unwrap().clone()
     }
     
     #[cfg(not(feature = "ldk"))]
-    pub fn initialize(&mut self) -> LightningResult<()> {
+    pub fn initialize(&self) -> LightningResult<()> {
         // Mock implementation - add a test peer
         let mut peers = self.connected_peers.lock().unwrap();
         
@@ -92,14 +112,25 @@ pub fn initialize(&mut self) -> LightningResult<()> {
     
     /// List all connected peers
     pub fn list_peers(&self) -> LightningResult<Vec<NodeInfo>> {
+        #[cfg(feature = "ldk")]
+        if let Some(peer_manager) = self.ldk() {
+            return Ok(peer_manager.get_peer_node_ids().into_iter()
+                .map(|(node_id, address)| self.peer_info_from_ldk(&node_id, address.as_ref()))
+                .collect());
+        }
+        
         let peers = self.connected_peers.lock().unwrap();
         Ok(peers.values().cloned().collect())
     }
     
     /// Connect to a peer
     pub fn connect_peer(&self, node_pubkey: &str, host: &str, port: u16) -> LightningResult<()> {
-        // In a real implementation, we would use the LDK PeerManager to connect
-        // For now, just add to our mock data
+        #[cfg(feature = "ldk")]
+        if let Some(peer_manager) = self.ldk() {
+            return self.connect_ldk_peer(peer_manager, node_pubkey, host, port);
+        }
+        
+        // Without LDK, just add to our mock data
         let mut peers = self.connected_peers.lock().unwrap();
         
         // Check if already connected
@@ -134,6 +165,16 @@ pub fn connect_peer(&self, node_pubkey: &str, host: &str, port: u16) -> Lightnin
     
     /// Disconnect from a peer
     pub fn disconnect_peer(&self, node_pubkey: &str) -> LightningResult<()> {
+        #[cfg(feature = "ldk")]
+        if let Some(peer_manager) = self.ldk() {
+            if !self.is_connected(node_pubkey) {
+                return Err(LightningError::NetworkError(format!("Not connected to {}", node_pubkey)));
+            }
+            peer_manager.disconnect_by_node_id(parse_pubkey(node_pubkey)?);
+            println!("Disconnected from peer: {}", node_pubkey);
+            return Ok(());
+        }
+        
         let mut peers = self.connected_peers.lock().unwrap();
         
         if peers.remove(node_pubkey).is_none() {
@@ -146,12 +187,25 @@ pub fn disconnect_peer(&self, node_pubkey: &str) -> LightningResult<()> {
     
     /// Check if we're connected to a peer
     pub fn is_connected(&self, node_pubkey: &str) -> bool {
+        #[cfg(feature = "ldk")]
+        if let Some(peer_manager) = self.ldk() {
+            return peer_manager.get_peer_node_ids().iter()
+                .any(|(node_id, _)| to_hex(&node_id.serialize()) == node_pubkey);
+        }
+        
         let peers = self.connected_peers.lock().unwrap();
         peers.contains_key(node_pubkey)
     }
     
     /// Get info about a connected peer
     pub fn get_peer_info(&self, node_pubkey: &str) -> LightningResult<NodeInfo> {
+        #[cfg(feature = "ldk")]
+        if self.ldk().is_some() {
+            return self.list_peers()?.into_iter()
+                .find(|peer| peer.pubkey == node_pubkey)
+                .ok_or_else(|| LightningError::NetworkError(format!("Not connected to {}", node_pubkey)));
+        }
+        
         let peers = self.connected_peers.lock().unwrap();
         
         match peers.get(node_pubkey) {
@@ -201,38 +255,105 @@ pub fn process_message(&self, node_pubkey: &str, _message: &[u8]) -> LightningRe
     }
 }
 
+#[cfg(feature = "ldk")]
+impl PeerManagerWrapper {
+    /// Open an outbound connection through lightning-net-tokio and wait for the handshake
+    fn connect_ldk_peer(
+        &self,
+        peer_manager: Arc<LdkPeerManager>,
+        node_pubkey: &str,
+        host: &str,
+        port: u16,
+    ) -> LightningResult<()> {
+        if self.is_connected(node_pubkey) {
+            return Err(LightningError::NetworkError(format!("Already connected to {}", node_pubkey)));
+        }
+        
+        let node_id = parse_pubkey(node_pubkey)?;
+        let socket_addr = format!("{}:{}", host, port).to_socket_addrs().ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| LightningError::NetworkError(format!("Invalid address: {}:{}", host, port)))?;
+        
+        let runtime = self.runtime.lock().unwrap().clone()
+            .ok_or_else(|| LightningError::ImplementationError("LDK runtime not started".to_string()))?;
+        
+        let connection = runtime.block_on(
+            lightning_net_tokio::connect_outbound(peer_manager, node_id, socket_addr)
+        ).ok_or_else(|| LightningError::NetworkError(
+            format!("Failed to connect to {}@{}:{}", node_pubkey, host, port)
+        ))?;
+        runtime.spawn(connection);
+        
+        // The connection is only usable once the noise handshake completes
+        let started = SystemTime::now();
+        while !self.is_connected(node_pubkey) {
+            if started.elapsed().unwrap_or_default() > HANDSHAKE_TIMEOUT {
+                return Err(LightningError::NetworkError(
+                    format!("Timed out connecting to {}@{}:{}", node_pubkey, host, port)
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        
+        println!("Connected to peer: {}@{}:{}", node_pubkey, host, port);
+        Ok(())
+    }
+    
+    /// Build peer info from LDK's view of a peer and its node announcement
+    fn peer_info_from_ldk(&self, node_id: &PublicKey, address: Option<&NetAddress>) -> NodeInfo {
+        let mut peer = NodeInfo {
+            pubkey: to_hex(&node_id.serialize()),
+            addresses: address.map(format_socket_addr).into_iter().collect(),
+            alias: None,
+            color: None,
+            features: Vec::new(),
+        };
+        
+        if let Some(network_graph) = self.network_graph.lock().unwrap().as_ref() {
+            let graph = network_graph.read_only();
+            if let Some(info) = graph.node(&NodeId::from_pubkey(node_id))
+                .and_then(|node| node.announcement_info.as_ref()) {
+                peer.alias = Some(info.alias.to_string());
+                peer.color = Some(format!("#{}", to_hex(&info.rgb)));
+            }
+        }
+        
+        peer
+    }
+}
+
 // Additional network operation functions
 
 /// Parse a socket address from string into LDK format
 #[cfg(feature = "ldk")]
-pub fn parse_socket_addr(addr_str: &str) -> Option<SocketAddress> {
+pub fn parse_socket_addr(addr_str: &str) -> Option<NetAddress> {
     match addr_str.parse::<SocketAddr>() {
-        Ok(addr) => {
-            if addr.is_ipv4() {
-                Some(SocketAddress::TcpIpV4 {
-                    addr: addr.ip().to_string(),
-                    port: addr.port(),
-                })
-            } else {
-                Some(SocketAddress::TcpIpV6 {
-                    addr: addr.ip().to_string(),
-                    port: addr.port(),
-                })
-            }
-        }
+        Ok(SocketAddr::V4(addr)) => Some(NetAddress::IPv4 {
+            addr: addr.ip().octets(),
+            port: addr.port(),
+        }),
+        Ok(SocketAddr::V6(addr)) => Some(NetAddress::IPv6 {
+            addr: addr.ip().octets(),
+            port: addr.port(),
+        }),
         Err(_) => None,
     }
 }
 
 /// Format a socket address from LDK format to string
 #[cfg(feature = "ldk")]
-pub fn format_socket_addr(addr: &SocketAddress) -> String {
+pub fn format_socket_addr(addr: &NetAddress) -> String {
     match addr {
-        SocketAddress::TcpIpV4 { addr, port } => format!("{}:{}", addr, port),
-        SocketAddress::TcpIpV6 { addr, port } => format!("[{}]:{}", addr, port),
-        SocketAddress::OnionV2 { addr, port } => format!("{}:{}", addr, port),
-        SocketAddress::OnionV3 { ed25519_pubkey, checksum, version, port } => {
-            format!("{}.onion:{}", hex::encode(ed25519_pubkey), port)
+        NetAddress::IPv4 { addr, port } => {
+            format!("{}:{}", std::net::Ipv4Addr::from(*addr), port)
+        }
+        NetAddress::IPv6 { addr, port } => {
+            format!("[{}]:{}", std::net::Ipv6Addr::from(*addr), port)
         }
+        NetAddress::OnionV2(addr) => format!("{}.onion", to_hex(addr)),
+        NetAddress::OnionV3 { ed25519_pubkey, port, .. } => {
+            format!("{}.onion:{}", to_hex(ed25519_pubkey), port)
+        }
+        NetAddress::Hostname { hostname, port } => format!("{}:{}", hostname.as_str(), port),
     }
-} 
\ No newline at end of file
+}
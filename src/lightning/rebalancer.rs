@@ -0,0 +1,489 @@
+// Lightning Network Channel Rebalancer
+// Moves liquidity between our own channels with circular self-payments
+// and keeps channel balances within configured ratios
+
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::lightning::interface::{
+    LightningError, LightningResult, ChannelInfo
+};
+
+use crate::lightning::channel_manager::ChannelManagerWrapper;
+use crate::lightning::payment_router::PaymentRoute;
+use crate::lightning::invoice_manager::InvoiceManager;
+use crate::lightning::payment_executor::{PaymentExecutor, RouteConstraints};
+
+/// Policy that the rebalancing autopilot enforces
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebalancePolicy {
+    /// Channels with a lower local/capacity ratio need inbound-to-outbound rebalancing
+    pub min_local_ratio: f64,
+
+    /// Channels with a higher local/capacity ratio have liquidity to give away
+    pub max_local_ratio: f64,
+
+    /// Ratio that rebalanced channels are moved towards
+    pub target_local_ratio: f64,
+
+    /// Maximum fee for a single rebalance, in parts per million of the amount
+    pub max_fee_ppm: u64,
+
+    /// Absolute cap on the fee for a single rebalance (in msats)
+    pub max_fee_msat: u64,
+
+    /// Smallest rebalance worth doing (in satoshis)
+    pub min_amount_sat: u64,
+
+    /// Largest single rebalance (in satoshis)
+    pub max_amount_sat: u64,
+}
+
+impl Default for RebalancePolicy {
+    fn default() -> Self {
+        RebalancePolicy {
+            min_local_ratio: 0.2,
+            max_local_ratio: 0.8,
+            target_local_ratio: 0.5,
+            max_fee_ppm: 1000,
+            max_fee_msat: 50_000,
+            min_amount_sat: 10_000,
+            max_amount_sat: 1_000_000,
+        }
+    }
+}
+
+impl RebalancePolicy {
+    /// Fee budget for rebalancing `amount_sat` under this policy (in msats)
+    pub fn fee_budget_msat(&self, amount_sat: u64) -> u64 {
+        (amount_sat * 1000 * self.max_fee_ppm / 1_000_000).min(self.max_fee_msat)
+    }
+
+    /// Check that the ratios are ordered and within [0, 1]
+    fn validate(&self) -> LightningResult<()> {
+        let ordered = 0.0 <= self.min_local_ratio &&
+            self.min_local_ratio <= self.target_local_ratio &&
+            self.target_local_ratio <= self.max_local_ratio &&
+            self.max_local_ratio <= 1.0;
+
+        if !ordered {
+            return Err(LightningError::ChannelError(
+                "Rebalance ratios must satisfy 0 <= min <= target <= max <= 1".to_string()
+            ));
+        }
+
+        if self.min_amount_sat > self.max_amount_sat {
+            return Err(LightningError::ChannelError(
+                "Rebalance min_amount_sat is above max_amount_sat".to_string()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A rebalance the autopilot intends to perform
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedRebalance {
+    /// Outbound-heavy channel the payment leaves through
+    pub source_channel_id: String,
+
+    /// Inbound-heavy channel the payment comes back through
+    pub target_channel_id: String,
+
+    /// Amount to move (in satoshis)
+    pub amount_sat: u64,
+
+    /// Fee budget for this rebalance (in msats)
+    pub max_fee_msat: u64,
+}
+
+/// Outcome of an executed rebalance
+#[derive(Debug, Clone)]
+pub struct RebalanceResult {
+    /// Outbound-heavy channel the payment left through
+    pub source_channel_id: String,
+
+    /// Inbound-heavy channel the payment came back through
+    pub target_channel_id: String,
+
+    /// Amount moved (in satoshis)
+    pub amount_sat: u64,
+
+    /// Fee paid to intermediate nodes (in msats)
+    pub fee_msat: u64,
+
+    /// Payment hash of the self-payment
+    pub payment_hash: String,
+
+    /// The circular route that was used
+    pub route: PaymentRoute,
+
+    /// Timestamp of the rebalance
+    pub timestamp: u64,
+}
+
+/// Report produced by an autopilot run
+#[derive(Debug, Clone, Default)]
+pub struct RebalanceReport {
+    /// Whether this was a dry run (nothing was paid)
+    pub dry_run: bool,
+
+    /// Rebalances the autopilot decided on
+    pub planned: Vec<PlannedRebalance>,
+
+    /// Rebalances that completed
+    pub executed: Vec<RebalanceResult>,
+
+    /// Rebalances that failed, with the reason
+    pub failed: Vec<(PlannedRebalance, String)>,
+}
+
+/// Rebalancer for moving liquidity between our own channels
+pub struct Rebalancer {
+    /// Channel manager holding channel balances
+    channel_manager: Arc<ChannelManagerWrapper>,
+
+    /// Invoice manager for the self-payment invoice
+    invoice_manager: Arc<InvoiceManager>,
+
+    /// Payment executor that sends the circular payment
+    payment_executor: Arc<PaymentExecutor>,
+
+    /// Completed rebalances
+    history: Mutex<Vec<RebalanceResult>>,
+}
+
+impl Rebalancer {
+    /// Create a new Rebalancer
+    pub fn new(
+        channel_manager: Arc<ChannelManagerWrapper>,
+        invoice_manager: Arc<InvoiceManager>,
+        payment_executor: Arc<PaymentExecutor>,
+    ) -> Self {
+        Rebalancer {
+            channel_manager,
+            invoice_manager,
+            payment_executor,
+            history: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Find a circular route out through `source_channel_id` and back in through `target_channel_id`
+    pub fn find_circular_route(
+        &self,
+        source_channel_id: &str,
+        target_channel_id: &str,
+        amount_sat: u64,
+    ) -> LightningResult<PaymentRoute> {
+        self.payment_executor.find_constrained_route(
+            amount_sat * 1000,
+            &route_constraints(source_channel_id, target_channel_id, u64::MAX),
+        )
+    }
+
+    /// Pay ourselves `amount_sat` from `source_channel_id` around to `target_channel_id`
+    pub fn rebalance(
+        &self,
+        source_channel_id: &str,
+        target_channel_id: &str,
+        amount_sat: u64,
+        max_fee_msat: u64,
+    ) -> LightningResult<RebalanceResult> {
+        let constraints = route_constraints(source_channel_id, target_channel_id, max_fee_msat);
+        let route = self.payment_executor.find_constrained_route(amount_sat * 1000, &constraints)?;
+
+        if route.total_fee_msat > max_fee_msat {
+            return Err(LightningError::PaymentError(
+                format!("Rebalance fee {} msat exceeds budget of {} msat", route.total_fee_msat, max_fee_msat)
+            ));
+        }
+
+        // Self-invoice so the payment can be tracked like any other
+        let invoice = self.invoice_manager.create_invoice(
+            Some(amount_sat * 1000),
+            "Circular rebalance",
+            None,
+        )?;
+
+        let payment = self.payment_executor
+            .pay_invoice_with_constraints(&invoice.bolt11, &constraints)
+            .inspect_err(|_| {
+                // Nothing will pay this invoice any more
+                let _ = self.invoice_manager.cancel_invoice(&invoice.payment_hash);
+            })?;
+
+        let result = RebalanceResult {
+            source_channel_id: source_channel_id.to_string(),
+            target_channel_id: target_channel_id.to_string(),
+            amount_sat,
+            fee_msat: route.total_fee_msat,
+            payment_hash: payment.payment_hash,
+            route,
+            timestamp: get_timestamp(),
+        };
+
+        self.history.lock().unwrap().push(result.clone());
+
+        Ok(result)
+    }
+
+    /// Work out which rebalances are needed to bring channels within the policy ratios
+    pub fn plan(&self, policy: &RebalancePolicy) -> LightningResult<Vec<PlannedRebalance>> {
+        policy.validate()?;
+
+        let channels: Vec<ChannelInfo> = self.channel_manager.list_channels()?
+            .into_iter()
+            .filter(|channel| channel.is_active && channel.capacity > 0)
+            .collect();
+
+        // Liquidity each outbound-heavy channel can give while staying at or above target
+        let mut sources: Vec<(String, u64)> = channels.iter()
+            .filter(|channel| local_ratio(channel) > policy.max_local_ratio)
+            .map(|channel| {
+                let target_local = (channel.capacity as f64 * policy.target_local_ratio) as u64;
+                (channel.channel_id.clone(), channel.local_balance.saturating_sub(target_local))
+            })
+            .collect();
+
+        // Liquidity each inbound-heavy channel needs to reach its target
+        let mut sinks: Vec<(String, u64)> = channels.iter()
+            .filter(|channel| local_ratio(channel) < policy.min_local_ratio)
+            .map(|channel| {
+                let target_local = (channel.capacity as f64 * policy.target_local_ratio) as u64;
+                (channel.channel_id.clone(), target_local.saturating_sub(channel.local_balance))
+            })
+            .collect();
+
+        // Largest imbalances first
+        sources.sort_by_key(|(_, available)| std::cmp::Reverse(*available));
+        sinks.sort_by_key(|(_, needed)| std::cmp::Reverse(*needed));
+
+        let mut planned = Vec::new();
+
+        for (sink_id, needed) in sinks.iter_mut() {
+            for (source_id, available) in sources.iter_mut() {
+                if *needed < policy.min_amount_sat {
+                    break;
+                }
+
+                let amount_sat = (*needed).min(*available).min(policy.max_amount_sat);
+                if amount_sat < policy.min_amount_sat {
+                    continue;
+                }
+
+                planned.push(PlannedRebalance {
+                    source_channel_id: source_id.clone(),
+                    target_channel_id: sink_id.clone(),
+                    amount_sat,
+                    max_fee_msat: policy.fee_budget_msat(amount_sat),
+                });
+
+                *needed -= amount_sat;
+                *available -= amount_sat;
+            }
+        }
+
+        Ok(planned)
+    }
+
+    /// Plan rebalances and, unless this is a dry run, execute them
+    pub fn run_autopilot(&self, policy: &RebalancePolicy, dry_run: bool) -> LightningResult<RebalanceReport> {
+        let planned = self.plan(policy)?;
+        let mut report = RebalanceReport {
+            dry_run,
+            planned: planned.clone(),
+            ..RebalanceReport::default()
+        };
+
+        if dry_run {
+            return Ok(report);
+        }
+
+        for rebalance in planned {
+            match self.rebalance(
+                &rebalance.source_channel_id,
+                &rebalance.target_channel_id,
+                rebalance.amount_sat,
+                rebalance.max_fee_msat,
+            ) {
+                Ok(result) => report.executed.push(result),
+                Err(e) => report.failed.push((rebalance, e.to_string())),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// List completed rebalances
+    pub fn list_rebalances(&self) -> Vec<RebalanceResult> {
+        self.history.lock().unwrap().clone()
+    }
+}
+
+/// Constraints pinning a rebalance to its source and target channels
+fn route_constraints(source_channel_id: &str, target_channel_id: &str, max_fee_msat: u64) -> RouteConstraints {
+    RouteConstraints {
+        outgoing_channel_id: source_channel_id.to_string(),
+        last_hop_channel_id: target_channel_id.to_string(),
+        max_fee_msat,
+    }
+}
+
+/// Fraction of a channel's capacity that is on our side
+fn local_ratio(channel: &ChannelInfo) -> f64 {
+    channel.local_balance as f64 / channel.capacity as f64
+}
+
+/// Get current timestamp
+fn get_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::bitcoin;
+    use crate::lightning::key_manager::KeyManagerWrapper;
+    use crate::lightning::peer_manager::PeerManagerWrapper;
+    use crate::lightning::payment_router::PaymentRouter;
+    use crate::lightning::invoice_manager::{InvoiceState, payment_hash_for_preimage};
+    use crate::lightning::interface::PaymentStatus;
+    use crate::lightning::interface::OpenChannelOptions;
+
+    const LOCAL: &str = "02eadbd9e7557375161df8b646776a547c5097cc8288021e9ee72cb33327f912cd";
+    const PEER_A: &str = "03f25d220b14f3daae528bbb98cf142caf3477c8d5258d9f81b0af0370163f0df2";
+    const PEER_B: &str = "023c6e150630c0a9bba412795203fa7ad86c9b24b103d8e05f0905d4b0f5bf6c3b";
+    const HUB: &str = "027a0d65b1ae0abad97fb80723d80c760b9e9c1f7a92fffb18ca3d57401225b56c";
+
+    /// Our node with an outbound-heavy channel to A and an inbound-heavy channel to B,
+    /// and a public path A -> HUB -> B
+    fn setup() -> (Arc<ChannelManagerWrapper>, Rebalancer, String, String) {
+        let config = Config::default();
+        let bitcoin_interface = bitcoin::get_current_bitcoin_interface(&config);
+        let channel_manager = Arc::new(ChannelManagerWrapper::new(&config, bitcoin_interface));
+        let router = Arc::new(PaymentRouter::new(&config));
+        let invoice_manager = Arc::new(InvoiceManager::new(&config, Arc::new(KeyManagerWrapper::new(&config))));
+
+        let outbound = channel_manager.open_channel(PEER_A, 1_000_000, &OpenChannelOptions { push_msat: Some(50_000_000), ..OpenChannelOptions::default() }).unwrap();
+        let inbound = channel_manager.open_channel(PEER_B, 1_000_000, &OpenChannelOptions { push_msat: Some(950_000_000), ..OpenChannelOptions::default() }).unwrap();
+
+        router.add_channel(&outbound.channel_id, LOCAL, PEER_A, 1_000_000, 0, 0).unwrap();
+        router.add_channel("a-hub", PEER_A, HUB, 2_000_000, 1000, 100).unwrap();
+        router.add_channel("hub-b", HUB, PEER_B, 2_000_000, 1000, 100).unwrap();
+        router.add_channel(&inbound.channel_id, PEER_B, LOCAL, 1_000_000, 500, 50).unwrap();
+
+        let payment_executor = Arc::new(PaymentExecutor::new(
+            &config,
+            router,
+            invoice_manager.clone(),
+            channel_manager.clone(),
+            Arc::new(PeerManagerWrapper::new(&config)),
+        ));
+        assert_eq!(invoice_manager.node_pubkey().unwrap(), LOCAL);
+
+        let rebalancer = Rebalancer::new(channel_manager.clone(), invoice_manager, payment_executor);
+        (channel_manager, rebalancer, outbound.channel_id, inbound.channel_id)
+    }
+
+    #[test]
+    fn test_circular_route() {
+        let (_, rebalancer, outbound, inbound) = setup();
+
+        let route = rebalancer.find_circular_route(&outbound, &inbound, 100_000).unwrap();
+
+        assert_eq!(route.hops.first().unwrap().src_node_id, LOCAL);
+        assert_eq!(route.hops.first().unwrap().channel_id, outbound);
+        assert_eq!(route.hops.last().unwrap().dest_node_id, LOCAL);
+        assert_eq!(route.hops.last().unwrap().channel_id, inbound);
+        assert!(route.hops.iter().skip(1).all(|hop| hop.src_node_id != LOCAL));
+        assert!(route.total_fee_msat > 0);
+    }
+
+    #[test]
+    fn test_rebalance_moves_liquidity() {
+        let (channel_manager, rebalancer, outbound, inbound) = setup();
+
+        let result = rebalancer.rebalance(&outbound, &inbound, 100_000, 100_000).unwrap();
+        assert_eq!(rebalancer.list_rebalances().len(), 1);
+
+        let inbound_channel = channel_manager.get_channel(&inbound).unwrap().unwrap();
+        assert_eq!(inbound_channel.local_balance, 150_000);
+
+        let outbound_channel = channel_manager.get_channel(&outbound).unwrap().unwrap();
+        assert!(outbound_channel.local_balance <= 850_000 - result.fee_msat / 1000);
+
+        // The self-invoice settled with its own preimage through the payment executor
+        let invoice_status = rebalancer.invoice_manager.get_invoice_status(&result.payment_hash).unwrap().unwrap();
+        assert_eq!(invoice_status.state, InvoiceState::Settled);
+        assert_eq!(invoice_status.htlcs[0].channel_id, inbound);
+        let preimage = invoice_status.payment_preimage.unwrap();
+        assert_eq!(payment_hash_for_preimage(&preimage).unwrap(), result.payment_hash);
+
+        let payment = rebalancer.payment_executor.get_payment(&result.payment_hash).unwrap().unwrap();
+        assert_eq!(payment.status, PaymentStatus::Succeeded);
+        assert_eq!(payment.preimage, Some(preimage));
+    }
+
+    #[test]
+    fn test_rebalance_fails_without_liquidity() {
+        let (channel_manager, rebalancer, outbound, inbound) = setup();
+
+        // Only 50_000 sats sit on our side of the inbound channel to send back out
+        assert!(rebalancer.rebalance(&inbound, &outbound, 100_000, 100_000).is_err());
+
+        let inbound_channel = channel_manager.get_channel(&inbound).unwrap().unwrap();
+        assert_eq!(inbound_channel.local_balance, 50_000);
+        assert!(rebalancer.list_rebalances().is_empty());
+    }
+
+    #[test]
+    fn test_rebalance_respects_fee_budget() {
+        let (channel_manager, rebalancer, outbound, inbound) = setup();
+
+        assert!(rebalancer.rebalance(&outbound, &inbound, 100_000, 1).is_err());
+
+        // Nothing moved
+        let inbound_channel = channel_manager.get_channel(&inbound).unwrap().unwrap();
+        assert_eq!(inbound_channel.local_balance, 50_000);
+    }
+
+    #[test]
+    fn test_autopilot_dry_run() {
+        let (channel_manager, rebalancer, outbound, inbound) = setup();
+
+        let report = rebalancer.run_autopilot(&RebalancePolicy::default(), true).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.planned.len(), 1);
+        assert_eq!(report.planned[0].source_channel_id, outbound);
+        assert_eq!(report.planned[0].target_channel_id, inbound);
+        assert_eq!(report.planned[0].amount_sat, 450_000);
+        assert!(report.executed.is_empty());
+
+        // Dry run leaves balances untouched
+        let inbound_channel = channel_manager.get_channel(&inbound).unwrap().unwrap();
+        assert_eq!(inbound_channel.local_balance, 50_000);
+    }
+
+    #[test]
+    fn test_autopilot_executes_plan() {
+        let (channel_manager, rebalancer, _, inbound) = setup();
+
+        let policy = RebalancePolicy {
+            max_fee_ppm: 5000,
+            max_fee_msat: 10_000_000,
+            ..RebalancePolicy::default()
+        };
+
+        let report = rebalancer.run_autopilot(&policy, false).unwrap();
+        assert_eq!(report.executed.len(), 1);
+        assert!(report.failed.is_empty());
+
+        let inbound_channel = channel_manager.get_channel(&inbound).unwrap().unwrap();
+        assert_eq!(inbound_channel.local_balance, 500_000);
+    }
+}
@@ -0,0 +1,1363 @@
+// Submarine Swaps
+// Moves liquidity between on-chain funds and Lightning without touching channels
+//
+// Both directions lock on-chain funds in a P2WSH HTLC:
+//
+//   OP_SHA256 <payment_hash> OP_EQUAL
+//   OP_IF
+//       <claim_pubkey>
+//   OP_ELSE
+//       <timeout_height> OP_CHECKLOCKTIMEVERIFY OP_DROP <refund_pubkey>
+//   OP_ENDIF
+//   OP_CHECKSIG
+//
+// In a submarine swap (on-chain -> LN) the client locks funds and the server
+// claims them with the preimage it learns by paying the client's invoice. In a
+// reverse swap (LN -> on-chain) the client pays the server's hold invoice, the
+// server locks funds, and the client claims them on-chain, revealing the
+// preimage the server needs to settle the invoice. Either side can refund its
+// lockup once the timeout height is reached.
+
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+use crate::bitcoin::{
+    BitcoinInterface, BitcoinTransaction, AddressType,
+    TransactionInput, TransactionOutput
+};
+use crate::bitcoin::encoding::{
+    self, opcodes, push_data, push_int, address_to_script_pubkey, encode_segwit_address,
+    finalize_transaction, network_hrp, segwit_v0_signature_hash, SIGHASH_ALL,
+};
+
+use crate::lightning::interface::{
+    LightningInterface, LightningError, LightningResult, PaymentStatus
+};
+use crate::lightning::invoice_manager::{InvoiceState, decode_hex, payment_hash_for_preimage};
+
+/// Sequence used by claim and refund inputs (enables locktime, opts out of RBF signalling)
+const HTLC_INPUT_SEQUENCE: u32 = 0xffff_fffe;
+
+/// Minimum number of blocks a client requires before a swap times out
+pub const MIN_TIMEOUT_BLOCKS: u32 = 6;
+
+/// Swap direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapKind {
+    /// On-chain -> Lightning: the client locks on-chain funds and receives a Lightning payment
+    Submarine,
+
+    /// Lightning -> on-chain: the client pays a Lightning invoice and claims on-chain funds
+    Reverse,
+}
+
+/// Swap progress, shared by client and server records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapState {
+    /// Swap agreed, nothing locked yet
+    Created,
+
+    /// On-chain HTLC funded
+    LockedUp,
+
+    /// On-chain HTLC claimed with the preimage; the swap is complete
+    Claimed,
+
+    /// On-chain HTLC refunded after the timeout
+    Refunded,
+
+    /// Swap aborted before anything was locked or after a failed payment
+    Failed,
+}
+
+/// The HTLC script locking swap funds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapScript {
+    /// SHA256 payment hash
+    pub payment_hash: [u8; 32],
+
+    /// Key that can claim with the preimage
+    pub claim_pubkey: PublicKey,
+
+    /// Key that can refund after the timeout
+    pub refund_pubkey: PublicKey,
+
+    /// Absolute block height after which the refund path opens
+    pub timeout_height: u32,
+}
+
+impl SwapScript {
+    /// Serialize the witness script
+    pub fn witness_script(&self) -> Vec<u8> {
+        let mut script = vec![opcodes::OP_SHA256];
+        push_data(&mut script, &self.payment_hash);
+        script.push(opcodes::OP_EQUAL);
+        script.push(opcodes::OP_IF);
+        push_data(&mut script, &self.claim_pubkey.serialize());
+        script.push(opcodes::OP_ELSE);
+        push_int(&mut script, self.timeout_height as i64);
+        script.push(opcodes::OP_CHECKLOCKTIMEVERIFY);
+        script.push(opcodes::OP_DROP);
+        push_data(&mut script, &self.refund_pubkey.serialize());
+        script.push(opcodes::OP_ENDIF);
+        script.push(opcodes::OP_CHECKSIG);
+        script
+    }
+
+    /// P2WSH scriptPubKey of the lockup output
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        encoding::p2wsh_script_pubkey(&self.witness_script())
+    }
+
+    /// P2WSH lockup address on a network
+    pub fn address(&self, network: &str) -> LightningResult<String> {
+        let program = encoding::sha256(&self.witness_script());
+        Ok(encode_segwit_address(network_hrp(network), 0, &program)?)
+    }
+
+    /// Payment hash as hex
+    pub fn payment_hash_hex(&self) -> String {
+        encoding::to_hex(&self.payment_hash)
+    }
+
+    /// Parse a witness script, accepting only the exact swap template
+    pub fn parse(script: &[u8]) -> LightningResult<SwapScript> {
+        let invalid = || LightningError::ImplementationError("Not a swap HTLC script".to_string());
+
+        if script.len() < 2 + 32 + 2 + 1 + 33 + 1 {
+            return Err(invalid());
+        }
+
+        let payment_hash: [u8; 32] = script[2..34].try_into().map_err(|_| invalid())?;
+        let claim_pubkey = PublicKey::from_slice(&script[37..70]).map_err(|_| invalid())?;
+
+        // Timeout push follows OP_ELSE: either OP_1..OP_16 or a minimal number push
+        let timeout_start = 71;
+        let (timeout_height, timeout_end) = match *script.get(timeout_start).ok_or_else(invalid)? {
+            op @ opcodes::OP_1..=0x60 => ((op - opcodes::OP_1 + 1) as u32, timeout_start + 1),
+            len @ 1..=5 => {
+                let bytes = script.get(timeout_start + 1..timeout_start + 1 + len as usize).ok_or_else(invalid)?;
+                let value = bytes.iter().rev().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+                (u32::try_from(value).map_err(|_| invalid())?, timeout_start + 1 + len as usize)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let refund_start = timeout_end + 3;
+        let refund_pubkey = script.get(refund_start..refund_start + 33)
+            .and_then(|bytes| PublicKey::from_slice(bytes).ok())
+            .ok_or_else(invalid)?;
+
+        let parsed = SwapScript { payment_hash, claim_pubkey, refund_pubkey, timeout_height };
+
+        // Re-serializing must reproduce the script byte for byte
+        if parsed.witness_script() != script {
+            return Err(invalid());
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// A swap as seen by either party
+#[derive(Debug, Clone)]
+pub struct SwapRecord {
+    /// Swap ID assigned by the server
+    pub id: String,
+
+    /// Swap direction
+    pub kind: SwapKind,
+
+    /// Current state
+    pub state: SwapState,
+
+    /// BOLT11 invoice paid over Lightning
+    pub invoice: String,
+
+    /// Payment hash shared by the invoice and the HTLC script
+    pub payment_hash: String,
+
+    /// Invoice amount in satoshis
+    pub invoice_amount_sat: u64,
+
+    /// Amount locked on-chain in satoshis
+    pub onchain_amount_sat: u64,
+
+    /// HTLC script
+    pub script: SwapScript,
+
+    /// Lockup address
+    pub lockup_address: String,
+
+    /// Lockup transaction ID (once funded)
+    pub lockup_txid: Option<String>,
+
+    /// Lockup output index (once funded)
+    pub lockup_vout: Option<u32>,
+
+    /// Claim transaction ID (once claimed)
+    pub claim_txid: Option<String>,
+
+    /// Refund transaction ID (once refunded)
+    pub refund_txid: Option<String>,
+
+    /// Failure reason, if failed
+    pub error: Option<String>,
+
+    /// Created timestamp
+    pub created_at: u64,
+}
+
+/// A funded swap lockup output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockupOutput {
+    /// Lockup transaction ID
+    pub txid: String,
+
+    /// Output index
+    pub vout: u32,
+
+    /// Output value in satoshis
+    pub value: u64,
+}
+
+/// Build a transaction spending a swap lockup output to an address
+///
+/// The fee is taken from the locked amount at `fee_rate` sat/vB.
+fn build_htlc_spend(
+    script: &SwapScript,
+    lockup: &LockupOutput,
+    destination_address: &str,
+    fee_rate: u64,
+    locktime: u32,
+    key: &SecretKey,
+    preimage: Option<&[u8]>,
+) -> LightningResult<BitcoinTransaction> {
+    let witness_script = script.witness_script();
+    let preimage_item = preimage.map(|preimage| preimage.to_vec()).unwrap_or_default();
+
+    let mut tx = BitcoinTransaction {
+        txid: String::new(),
+        version: 2,
+        inputs: vec![TransactionInput {
+            txid: lockup.txid.clone(),
+            vout: lockup.vout,
+            script_sig: Vec::new(),
+            sequence: HTLC_INPUT_SEQUENCE,
+            // Placeholder signature of maximum size for fee estimation
+            witness: Some(vec![vec![0u8; 73], preimage_item.clone(), witness_script.clone()]),
+        }],
+        outputs: vec![TransactionOutput {
+            value: lockup.value,
+            script_pubkey: address_to_script_pubkey(destination_address)?,
+            address: Some(destination_address.to_string()),
+        }],
+        locktime,
+        size: 0,
+        weight: 0,
+        fee: None,
+    };
+    finalize_transaction(&mut tx)?;
+
+    let fee = ((tx.weight as u64 + 3) / 4) * fee_rate;
+    if fee >= lockup.value {
+        return Err(LightningError::ImplementationError(format!(
+            "Lockup of {} sat cannot cover a fee of {} sat", lockup.value, fee
+        )));
+    }
+    tx.outputs[0].value = lockup.value - fee;
+    tx.fee = Some(fee);
+
+    let sighash = segwit_v0_signature_hash(&tx, 0, &witness_script, lockup.value, SIGHASH_ALL)?;
+    let secp = Secp256k1::signing_only();
+    let mut signature = secp.sign_ecdsa(&Message::from_digest(sighash), key)
+        .serialize_der()
+        .to_vec();
+    signature.push(SIGHASH_ALL as u8);
+
+    tx.inputs[0].witness = Some(vec![signature, preimage_item, witness_script]);
+    finalize_transaction(&mut tx)?;
+
+    Ok(tx)
+}
+
+/// Build a claim transaction spending the hash path with the preimage
+pub fn build_claim_transaction(
+    script: &SwapScript,
+    lockup: &LockupOutput,
+    preimage: &[u8],
+    claim_key: &SecretKey,
+    destination_address: &str,
+    fee_rate: u64,
+) -> LightningResult<BitcoinTransaction> {
+    if encoding::sha256(preimage) != script.payment_hash {
+        return Err(LightningError::ImplementationError(
+            "Preimage does not match the swap payment hash".to_string()
+        ));
+    }
+
+    build_htlc_spend(script, lockup, destination_address, fee_rate, 0, claim_key, Some(preimage))
+}
+
+/// Build a refund transaction spending the timeout path
+///
+/// The transaction is timelocked to the script timeout and is only valid once
+/// the chain reaches that height.
+pub fn build_refund_transaction(
+    script: &SwapScript,
+    lockup: &LockupOutput,
+    refund_key: &SecretKey,
+    destination_address: &str,
+    fee_rate: u64,
+) -> LightningResult<BitcoinTransaction> {
+    build_htlc_spend(script, lockup, destination_address, fee_rate, script.timeout_height, refund_key, None)
+}
+
+/// Extract a preimage for `payment_hash` from the witnesses of a transaction
+pub fn extract_preimage(tx: &BitcoinTransaction, payment_hash: &[u8; 32]) -> Option<Vec<u8>> {
+    tx.inputs.iter()
+        .filter_map(|input| input.witness.as_ref())
+        .flat_map(|witness| witness.iter())
+        .find(|item| item.len() == 32 && encoding::sha256(item) == *payment_hash)
+        .cloned()
+}
+
+/// Find the output of a transaction paying a swap script
+fn find_lockup_output(tx: &BitcoinTransaction, script: &SwapScript) -> Option<LockupOutput> {
+    let script_pubkey = script.script_pubkey();
+    tx.outputs.iter()
+        .enumerate()
+        .find(|(_, output)| output.script_pubkey == script_pubkey)
+        .map(|(vout, output)| LockupOutput {
+            txid: tx.txid.clone(),
+            vout: vout as u32,
+            value: output.value,
+        })
+}
+
+impl SwapRecord {
+    /// The funded lockup output, if known
+    pub fn lockup_output(&self) -> Option<LockupOutput> {
+        match (&self.lockup_txid, self.lockup_vout) {
+            (Some(txid), Some(vout)) => Some(LockupOutput {
+                txid: txid.clone(),
+                vout,
+                value: self.onchain_amount_sat,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Swap server API
+///
+/// Mirrors the calls a client makes against a swap provider. The server
+/// decides the HTLC script, timeout and on-chain amount; the client verifies
+/// them before locking anything.
+pub trait SwapServer: Send + Sync {
+    /// Create a submarine swap paying `invoice` in exchange for on-chain funds
+    fn create_submarine_swap(&self, invoice: &str, refund_pubkey: &PublicKey) -> LightningResult<SwapRecord>;
+
+    /// Create a reverse swap: the server issues a hold invoice for `payment_hash`
+    /// and locks on-chain funds claimable by `claim_pubkey` once it is paid
+    fn create_reverse_swap(
+        &self,
+        payment_hash: &str,
+        invoice_amount_sat: u64,
+        claim_pubkey: &PublicKey,
+    ) -> LightningResult<SwapRecord>;
+
+    /// Tell the server about a transaction relevant to a swap (lockup or claim)
+    fn notify_transaction(&self, swap_id: &str, txid: &str) -> LightningResult<SwapRecord>;
+
+    /// Get the server's view of a swap
+    fn swap_status(&self, swap_id: &str) -> LightningResult<SwapRecord>;
+}
+
+/// Swap server configuration
+#[derive(Debug, Clone)]
+pub struct SwapServerConfig {
+    /// Timeout for submarine swaps in blocks
+    pub submarine_timeout_blocks: u32,
+
+    /// Timeout for reverse swaps in blocks (shorter, the server's funds are locked)
+    pub reverse_timeout_blocks: u32,
+
+    /// Service fee in parts per million of the swapped amount
+    pub fee_ppm: u64,
+
+    /// Fee rate for server claims, lockups and refunds in sat/vB
+    pub fee_rate: u64,
+
+    /// Smallest swap accepted in satoshis
+    pub min_amount_sat: u64,
+
+    /// Largest swap accepted in satoshis
+    pub max_amount_sat: u64,
+
+    /// Confirmations a submarine lockup needs before the server pays the invoice
+    pub lockup_confirmations: u32,
+
+    /// Blocks that must remain before a submarine swap times out for the server
+    /// to still pay, leaving time to get its claim confirmed
+    pub cltv_safety_delta: u32,
+}
+
+impl Default for SwapServerConfig {
+    fn default() -> Self {
+        SwapServerConfig {
+            submarine_timeout_blocks: 144,
+            reverse_timeout_blocks: 72,
+            fee_ppm: 1_000, // 0.1%
+            fee_rate: 2,
+            min_amount_sat: 10_000,
+            max_amount_sat: 10_000_000,
+            lockup_confirmations: 1,
+            cltv_safety_delta: 12,
+        }
+    }
+}
+
+impl SwapServerConfig {
+    /// Service fee for a swap amount
+    pub fn service_fee_sat(&self, amount_sat: u64) -> u64 {
+        amount_sat * self.fee_ppm / 1_000_000
+    }
+}
+
+/// Server-side swap with its private key
+struct ServerSwap {
+    record: SwapRecord,
+    key: SecretKey,
+}
+
+/// Local swap server stand-in
+///
+/// Runs the provider side of both swap directions against a Bitcoin and a
+/// Lightning interface, so swaps can be tested end to end on regtest or the
+/// simulated chain. Instead of a chain watcher, each swap is driven forward
+/// when the client notifies the server or polls its status.
+pub struct LocalSwapServer {
+    /// Bitcoin interface used for lockups, claims and refunds
+    bitcoin_interface: Arc<dyn BitcoinInterface>,
+
+    /// Lightning interface paying and issuing invoices
+    lightning_interface: Arc<dyn LightningInterface>,
+
+    /// Server configuration
+    server_config: SwapServerConfig,
+
+    /// Bitcoin network name
+    network: String,
+
+    /// Swaps by ID
+    swaps: Mutex<HashMap<String, ServerSwap>>,
+}
+
+impl LocalSwapServer {
+    /// Create a new local swap server
+    pub fn new(
+        config: &crate::config::Config,
+        bitcoin_interface: Arc<dyn BitcoinInterface>,
+        lightning_interface: Arc<dyn LightningInterface>,
+        server_config: SwapServerConfig,
+    ) -> Self {
+        LocalSwapServer {
+            bitcoin_interface,
+            lightning_interface,
+            server_config,
+            network: config.bitcoin_network.clone().unwrap_or_else(|| "testnet".to_string()),
+            swaps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check an amount against the server limits
+    fn check_amount(&self, amount_sat: u64) -> LightningResult<()> {
+        if amount_sat < self.server_config.min_amount_sat || amount_sat > self.server_config.max_amount_sat {
+            return Err(LightningError::PaymentError(format!(
+                "Swap amount {} sat is outside the limits {}-{} sat",
+                amount_sat, self.server_config.min_amount_sat, self.server_config.max_amount_sat
+            )));
+        }
+        Ok(())
+    }
+
+    /// Store a new swap and return its record
+    fn insert_swap(&self, record: SwapRecord, key: SecretKey) -> SwapRecord {
+        let mut swaps = self.swaps.lock().unwrap();
+        swaps.insert(record.id.clone(), ServerSwap { record: record.clone(), key });
+        record
+    }
+
+    /// Look up a swap, returning a copy of the record and key
+    fn get_swap(&self, swap_id: &str) -> LightningResult<(SwapRecord, SecretKey)> {
+        let swaps = self.swaps.lock().unwrap();
+        swaps.get(swap_id)
+            .map(|swap| (swap.record.clone(), swap.key))
+            .ok_or_else(|| LightningError::ImplementationError(format!("Swap not found: {}", swap_id)))
+    }
+
+    /// Persist an updated record
+    fn update_swap(&self, record: &SwapRecord) {
+        let mut swaps = self.swaps.lock().unwrap();
+        if let Some(swap) = swaps.get_mut(&record.id) {
+            swap.record = record.clone();
+        }
+    }
+
+    /// Mark a swap failed
+    fn fail_swap(&self, record: &mut SwapRecord, reason: String) {
+        record.state = SwapState::Failed;
+        record.error = Some(reason);
+        self.update_swap(record);
+    }
+
+    /// Submarine swap: verify the client's lockup, pay the invoice once it has
+    /// confirmed and claim on-chain
+    fn process_submarine_lockup(&self, mut record: SwapRecord, key: SecretKey, txid: &str) -> LightningResult<SwapRecord> {
+        if !matches!(record.state, SwapState::Created | SwapState::LockedUp) {
+            return Ok(record);
+        }
+
+        let lockup_tx = self.bitcoin_interface.get_transaction(txid)?;
+        let lockup = find_lockup_output(&lockup_tx, &record.script).ok_or_else(|| {
+            LightningError::ImplementationError(format!("Transaction {} does not pay the swap address", txid))
+        })?;
+
+        if lockup.value < record.onchain_amount_sat {
+            return Err(LightningError::PaymentError(format!(
+                "Lockup of {} sat is below the expected {} sat", lockup.value, record.onchain_amount_sat
+            )));
+        }
+
+        record.state = SwapState::LockedUp;
+        record.lockup_txid = Some(lockup.txid.clone());
+        record.lockup_vout = Some(lockup.vout);
+        self.update_swap(&record);
+
+        // An unconfirmed lockup can still be double-spent; wait for the swap status poll
+        if self.server_config.lockup_confirmations > 0
+            && self.bitcoin_interface.get_confirmations(&lockup.txid)? < self.server_config.lockup_confirmations {
+            return Ok(record);
+        }
+
+        // Do not pay unless the claim can confirm before the client can refund
+        let height = self.bitcoin_interface.get_block_height()?;
+        if record.script.timeout_height.saturating_sub(height) < self.server_config.cltv_safety_delta.max(1) {
+            let reason = format!(
+                "Lockup confirmed at height {}, too close to the swap timeout at height {}",
+                height, record.script.timeout_height
+            );
+            self.fail_swap(&mut record, reason);
+            return Ok(record);
+        }
+
+        let payment = match self.lightning_interface.pay_invoice(&record.invoice, None) {
+            Ok(payment) => payment,
+            Err(e) => {
+                self.fail_swap(&mut record, format!("Invoice payment failed: {}", e));
+                return Ok(record);
+            }
+        };
+
+        let preimage = match (payment.status, payment.preimage.as_deref().and_then(decode_hex)) {
+            (PaymentStatus::Succeeded, Some(preimage)) => preimage,
+            _ => {
+                self.fail_swap(&mut record, "Invoice payment did not return a preimage".to_string());
+                return Ok(record);
+            }
+        };
+
+        let destination = self.bitcoin_interface.generate_address(AddressType::P2WPKH)?;
+        let claim_tx = build_claim_transaction(
+            &record.script, &lockup, &preimage, &key, &destination.address, self.server_config.fee_rate,
+        )?;
+
+        record.claim_txid = Some(self.bitcoin_interface.broadcast_transaction(&claim_tx)?);
+        record.state = SwapState::Claimed;
+        self.update_swap(&record);
+
+        Ok(record)
+    }
+
+    /// Reverse swap: settle the hold invoice with the preimage revealed by the client's claim
+    fn process_reverse_claim(&self, mut record: SwapRecord, txid: &str) -> LightningResult<SwapRecord> {
+        if record.state != SwapState::LockedUp {
+            return Ok(record);
+        }
+
+        let claim_tx = self.bitcoin_interface.get_transaction(txid)?;
+        let spends_lockup = claim_tx.inputs.iter().any(|input| {
+            Some(&input.txid) == record.lockup_txid.as_ref() && Some(input.vout) == record.lockup_vout
+        });
+
+        if !spends_lockup {
+            return Err(LightningError::ImplementationError(
+                format!("Transaction {} does not spend the swap lockup", txid)
+            ));
+        }
+
+        let preimage = extract_preimage(&claim_tx, &record.script.payment_hash).ok_or_else(|| {
+            LightningError::ImplementationError(format!("Transaction {} does not reveal the preimage", txid))
+        })?;
+
+        self.lightning_interface.settle_invoice(&record.payment_hash, &encoding::to_hex(&preimage))?;
+
+        record.claim_txid = Some(txid.to_string());
+        record.state = SwapState::Claimed;
+        self.update_swap(&record);
+
+        Ok(record)
+    }
+
+    /// Reverse swap: lock on-chain funds once the hold invoice is accepted, or
+    /// refund and cancel the invoice once the swap has timed out
+    fn process_reverse(&self, mut record: SwapRecord, key: SecretKey) -> LightningResult<SwapRecord> {
+        match record.state {
+            SwapState::Created => {
+                if self.lightning_interface.get_invoice_state(&record.payment_hash)? != InvoiceState::Accepted {
+                    return Ok(record);
+                }
+
+                let lockup_tx = self.bitcoin_interface.create_transaction(
+                    vec![(record.lockup_address.clone(), record.onchain_amount_sat)],
+                    self.server_config.fee_rate,
+                )?;
+                let lockup = find_lockup_output(&lockup_tx, &record.script).ok_or_else(|| {
+                    LightningError::ImplementationError("Lockup transaction is missing the swap output".to_string())
+                })?;
+                self.bitcoin_interface.broadcast_transaction(&lockup_tx)?;
+
+                record.state = SwapState::LockedUp;
+                record.lockup_txid = Some(lockup.txid);
+                record.lockup_vout = Some(lockup.vout);
+                self.update_swap(&record);
+            }
+            SwapState::LockedUp => {
+                let height = self.bitcoin_interface.get_block_height()?;
+                if height < record.script.timeout_height {
+                    return Ok(record);
+                }
+
+                let lockup = record.lockup_output().ok_or_else(|| {
+                    LightningError::ImplementationError(format!("Swap {} has no lockup to refund", record.id))
+                })?;
+                let destination = self.bitcoin_interface.generate_address(AddressType::P2WPKH)?;
+                let refund_tx = build_refund_transaction(
+                    &record.script, &lockup, &key, &destination.address, self.server_config.fee_rate,
+                )?;
+
+                record.refund_txid = Some(self.bitcoin_interface.broadcast_transaction(&refund_tx)?);
+                record.state = SwapState::Refunded;
+                self.update_swap(&record);
+
+                // The client never claimed, so fail its Lightning payment back
+                self.lightning_interface.cancel_invoice(&record.payment_hash)?;
+            }
+            _ => {}
+        }
+
+        Ok(record)
+    }
+}
+
+impl SwapServer for LocalSwapServer {
+    fn create_submarine_swap(&self, invoice: &str, refund_pubkey: &PublicKey) -> LightningResult<SwapRecord> {
+        let decoded = self.lightning_interface.decode_invoice(invoice)?;
+        let invoice_amount_sat = decoded.amount_msat
+            .ok_or_else(|| LightningError::InvoiceError("Swap invoices must have an amount".to_string()))?
+            / 1000;
+        self.check_amount(invoice_amount_sat)?;
+
+        let payment_hash: [u8; 32] = decode_hex(&decoded.payment_hash)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| LightningError::InvoiceError("Invalid invoice payment hash".to_string()))?;
+
+        let (key, claim_pubkey) = generate_keypair();
+        let height = self.bitcoin_interface.get_block_height()?;
+        let script = SwapScript {
+            payment_hash,
+            claim_pubkey,
+            refund_pubkey: *refund_pubkey,
+            timeout_height: height + self.server_config.submarine_timeout_blocks,
+        };
+
+        let record = SwapRecord {
+            id: format!("swap_{}", generate_random_bytes_hex(8)),
+            kind: SwapKind::Submarine,
+            state: SwapState::Created,
+            invoice: invoice.to_string(),
+            payment_hash: decoded.payment_hash,
+            invoice_amount_sat,
+            onchain_amount_sat: invoice_amount_sat + self.server_config.service_fee_sat(invoice_amount_sat),
+            lockup_address: script.address(&self.network)?,
+            script,
+            lockup_txid: None,
+            lockup_vout: None,
+            claim_txid: None,
+            refund_txid: None,
+            error: None,
+            created_at: get_timestamp(),
+        };
+
+        Ok(self.insert_swap(record, key))
+    }
+
+    fn create_reverse_swap(
+        &self,
+        payment_hash: &str,
+        invoice_amount_sat: u64,
+        claim_pubkey: &PublicKey,
+    ) -> LightningResult<SwapRecord> {
+        self.check_amount(invoice_amount_sat)?;
+
+        let hash_bytes: [u8; 32] = decode_hex(payment_hash)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| LightningError::InvoiceError(format!("Invalid payment hash: {}", payment_hash)))?;
+
+        let (key, refund_pubkey) = generate_keypair();
+        let height = self.bitcoin_interface.get_block_height()?;
+        let script = SwapScript {
+            payment_hash: hash_bytes,
+            claim_pubkey: *claim_pubkey,
+            refund_pubkey,
+            timeout_height: height + self.server_config.reverse_timeout_blocks,
+        };
+
+        let invoice = self.lightning_interface.create_hold_invoice(
+            payment_hash,
+            Some(invoice_amount_sat * 1000),
+            "Reverse swap",
+            None,
+        )?;
+
+        let record = SwapRecord {
+            id: format!("swap_{}", generate_random_bytes_hex(8)),
+            kind: SwapKind::Reverse,
+            state: SwapState::Created,
+            invoice: invoice.bolt11,
+            payment_hash: payment_hash.to_lowercase(),
+            invoice_amount_sat,
+            onchain_amount_sat: invoice_amount_sat - self.server_config.service_fee_sat(invoice_amount_sat),
+            lockup_address: script.address(&self.network)?,
+            script,
+            lockup_txid: None,
+            lockup_vout: None,
+            claim_txid: None,
+            refund_txid: None,
+            error: None,
+            created_at: get_timestamp(),
+        };
+
+        Ok(self.insert_swap(record, key))
+    }
+
+    fn notify_transaction(&self, swap_id: &str, txid: &str) -> LightningResult<SwapRecord> {
+        let (record, key) = self.get_swap(swap_id)?;
+
+        match record.kind {
+            SwapKind::Submarine => self.process_submarine_lockup(record, key, txid),
+            SwapKind::Reverse => self.process_reverse_claim(record, txid),
+        }
+    }
+
+    fn swap_status(&self, swap_id: &str) -> LightningResult<SwapRecord> {
+        let (record, key) = self.get_swap(swap_id)?;
+
+        match (record.kind, record.lockup_txid.clone()) {
+            (SwapKind::Submarine, Some(txid)) if record.state == SwapState::LockedUp => {
+                self.process_submarine_lockup(record, key, &txid)
+            }
+            (SwapKind::Submarine, _) => Ok(record),
+            (SwapKind::Reverse, _) => self.process_reverse(record, key),
+        }
+    }
+}
+
+/// Client-side swap with its key and preimage
+struct ClientSwap {
+    record: SwapRecord,
+    key: SecretKey,
+    preimage: Option<Vec<u8>>,
+}
+
+/// Swap client
+///
+/// Performs the user side of submarine and reverse swaps against any swap server.
+pub struct SwapClient {
+    /// Bitcoin interface used to fund lockups and broadcast claims and refunds
+    bitcoin_interface: Arc<dyn BitcoinInterface>,
+
+    /// Lightning interface issuing and paying invoices
+    lightning_interface: Arc<dyn LightningInterface>,
+
+    /// Swap server
+    server: Arc<dyn SwapServer>,
+
+    /// Bitcoin network name
+    network: String,
+
+    /// Swaps by ID
+    swaps: Mutex<HashMap<String, ClientSwap>>,
+}
+
+impl SwapClient {
+    /// Create a new swap client
+    pub fn new(
+        config: &crate::config::Config,
+        bitcoin_interface: Arc<dyn BitcoinInterface>,
+        lightning_interface: Arc<dyn LightningInterface>,
+        server: Arc<dyn SwapServer>,
+    ) -> Self {
+        SwapClient {
+            bitcoin_interface,
+            lightning_interface,
+            server,
+            network: config.bitcoin_network.clone().unwrap_or_else(|| "testnet".to_string()),
+            swaps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Swap on-chain funds into Lightning
+    ///
+    /// Creates an invoice for `amount_sat`, lets the server set up the HTLC, checks
+    /// the script and fee, then funds the lockup from the on-chain wallet.
+    pub fn submarine_swap(&self, amount_sat: u64, max_fee_sat: u64, fee_rate: u64) -> LightningResult<SwapRecord> {
+        let invoice = self.lightning_interface.create_invoice(
+            Some(amount_sat * 1000),
+            "Submarine swap",
+            None,
+        )?;
+
+        let (key, refund_pubkey) = generate_keypair();
+        let mut record = self.server.create_submarine_swap(&invoice.bolt11, &refund_pubkey)?;
+
+        if record.kind != SwapKind::Submarine
+            || record.payment_hash != invoice.payment_hash
+            || record.script.payment_hash_hex() != invoice.payment_hash
+            || record.script.refund_pubkey != refund_pubkey {
+            return Err(LightningError::ImplementationError(
+                "Swap server returned a script that does not match our invoice and key".to_string()
+            ));
+        }
+        self.verify_server_terms(&record, amount_sat, max_fee_sat)?;
+
+        let lockup_tx = self.bitcoin_interface.create_transaction(
+            vec![(record.lockup_address.clone(), record.onchain_amount_sat)],
+            fee_rate,
+        )?;
+        let lockup = find_lockup_output(&lockup_tx, &record.script).ok_or_else(|| {
+            LightningError::ImplementationError("Lockup transaction is missing the swap output".to_string())
+        })?;
+        let txid = self.bitcoin_interface.broadcast_transaction(&lockup_tx)?;
+
+        record.state = SwapState::LockedUp;
+        record.lockup_txid = Some(txid.clone());
+        record.lockup_vout = Some(lockup.vout);
+        self.store_swap(&record, key, None);
+
+        // The server claims once it has paid; a failed notification leaves the refund path open
+        if let Ok(server_record) = self.server.notify_transaction(&record.id, &txid) {
+            record = self.merge_server_record(&record.id, server_record)?;
+        }
+
+        Ok(record)
+    }
+
+    /// Refund a submarine swap lockup after its timeout
+    pub fn refund_submarine_swap(&self, swap_id: &str, destination_address: &str, fee_rate: u64) -> LightningResult<String> {
+        let (mut record, key, _) = self.get_client_swap(swap_id)?;
+
+        if record.kind != SwapKind::Submarine || !matches!(record.state, SwapState::LockedUp | SwapState::Failed) {
+            return Err(LightningError::ImplementationError(
+                format!("Swap {} is {:?} and cannot be refunded", swap_id, record.state)
+            ));
+        }
+
+        let height = self.bitcoin_interface.get_block_height()?;
+        if height < record.script.timeout_height {
+            return Err(LightningError::ImplementationError(format!(
+                "Swap {} can be refunded from height {}, current height is {}",
+                swap_id, record.script.timeout_height, height
+            )));
+        }
+
+        let lockup = record.lockup_output().ok_or_else(|| {
+            LightningError::ImplementationError(format!("Swap {} has no lockup to refund", swap_id))
+        })?;
+
+        let refund_tx = build_refund_transaction(&record.script, &lockup, &key, destination_address, fee_rate)?;
+        let txid = self.bitcoin_interface.broadcast_transaction(&refund_tx)?;
+
+        record.state = SwapState::Refunded;
+        record.refund_txid = Some(txid.clone());
+        self.update_record(&record);
+
+        Ok(txid)
+    }
+
+    /// Swap Lightning funds to on-chain
+    ///
+    /// Generates a preimage, lets the server issue a hold invoice for its hash,
+    /// checks the invoice, script and fee, then pays the invoice. The payment stays
+    /// pending until the on-chain funds are claimed with `claim_reverse_swap`.
+    pub fn reverse_swap(&self, amount_sat: u64, max_fee_sat: u64) -> LightningResult<SwapRecord> {
+        let preimage = generate_random_bytes(32);
+        let payment_hash = encoding::to_hex(&encoding::sha256(&preimage));
+        let (key, claim_pubkey) = generate_keypair();
+
+        let mut record = self.server.create_reverse_swap(&payment_hash, amount_sat, &claim_pubkey)?;
+
+        let invoice = self.lightning_interface.decode_invoice(&record.invoice)?;
+        if record.kind != SwapKind::Reverse
+            || invoice.payment_hash != payment_hash
+            || invoice.amount_msat != Some(amount_sat * 1000)
+            || record.script.payment_hash_hex() != payment_hash
+            || record.script.claim_pubkey != claim_pubkey {
+            return Err(LightningError::ImplementationError(
+                "Swap server returned an invoice or script that does not match our request".to_string()
+            ));
+        }
+        self.verify_server_terms(&record, amount_sat, max_fee_sat)?;
+
+        self.store_swap(&record, key, Some(preimage));
+
+        let payment = self.lightning_interface.pay_invoice(&record.invoice, None)?;
+        if payment.status == PaymentStatus::Failed {
+            record.state = SwapState::Failed;
+            record.error = Some("Invoice payment failed".to_string());
+            self.update_record(&record);
+        }
+
+        Ok(record)
+    }
+
+    /// Claim the on-chain funds of a reverse swap once the server has locked them
+    ///
+    /// Returns the claim txid, or None if the server has not locked up yet.
+    pub fn claim_reverse_swap(&self, swap_id: &str, destination_address: &str, fee_rate: u64) -> LightningResult<Option<String>> {
+        let (record, key, preimage) = self.get_client_swap(swap_id)?;
+
+        if record.kind != SwapKind::Reverse {
+            return Err(LightningError::ImplementationError(format!("Swap {} is not a reverse swap", swap_id)));
+        }
+
+        if record.state == SwapState::Claimed {
+            return Ok(record.claim_txid);
+        }
+
+        let mut record = self.merge_server_record(swap_id, self.server.swap_status(swap_id)?)?;
+        if record.state != SwapState::LockedUp {
+            return Ok(None);
+        }
+
+        let lockup_txid = record.lockup_txid.clone().unwrap_or_default();
+        let lockup_tx = self.bitcoin_interface.get_transaction(&lockup_txid)?;
+        let lockup = find_lockup_output(&lockup_tx, &record.script).ok_or_else(|| {
+            LightningError::ImplementationError(format!("Lockup {} does not pay the swap script", lockup_txid))
+        })?;
+
+        if lockup.value < record.onchain_amount_sat {
+            return Err(LightningError::ImplementationError(format!(
+                "Lockup of {} sat is below the agreed {} sat", lockup.value, record.onchain_amount_sat
+            )));
+        }
+
+        let preimage = preimage.ok_or_else(|| {
+            LightningError::ImplementationError(format!("Missing preimage for swap {}", swap_id))
+        })?;
+        let claim_tx = build_claim_transaction(&record.script, &lockup, &preimage, &key, destination_address, fee_rate)?;
+        let txid = self.bitcoin_interface.broadcast_transaction(&claim_tx)?;
+
+        record.state = SwapState::Claimed;
+        record.claim_txid = Some(txid.clone());
+        self.update_record(&record);
+
+        // Let the server settle the invoice; it can also learn the preimage from the chain
+        let _ = self.server.notify_transaction(swap_id, &txid);
+
+        Ok(Some(txid))
+    }
+
+    /// Get a swap, refreshed from the server while it is still in progress
+    pub fn get_swap(&self, swap_id: &str) -> LightningResult<SwapRecord> {
+        let (record, _, _) = self.get_client_swap(swap_id)?;
+
+        if record.state != SwapState::LockedUp && record.state != SwapState::Created {
+            return Ok(record);
+        }
+
+        match self.server.swap_status(swap_id) {
+            Ok(server_record) => self.merge_server_record(swap_id, server_record),
+            Err(_) => Ok(record),
+        }
+    }
+
+    /// List all swaps
+    pub fn list_swaps(&self) -> Vec<SwapRecord> {
+        let swaps = self.swaps.lock().unwrap();
+        swaps.values().map(|swap| swap.record.clone()).collect()
+    }
+
+    /// Check the server's terms against our request: amount, script, address, timeout and fee
+    fn verify_server_terms(&self, record: &SwapRecord, amount_sat: u64, max_fee_sat: u64) -> LightningResult<()> {
+        if record.invoice_amount_sat != amount_sat {
+            return Err(LightningError::ImplementationError(format!(
+                "Swap server quoted {} sat instead of the requested {} sat", record.invoice_amount_sat, amount_sat
+            )));
+        }
+
+        if SwapScript::parse(&record.script.witness_script())? != record.script
+            || record.script.address(&self.network)? != record.lockup_address {
+            return Err(LightningError::ImplementationError(
+                "Swap lockup address does not match its script".to_string()
+            ));
+        }
+
+        let height = self.bitcoin_interface.get_block_height()?;
+        if record.script.timeout_height < height + MIN_TIMEOUT_BLOCKS {
+            return Err(LightningError::ImplementationError(format!(
+                "Swap timeout at height {} is too close to the current height {}",
+                record.script.timeout_height, height
+            )));
+        }
+
+        let fee_sat = match record.kind {
+            SwapKind::Submarine => record.onchain_amount_sat.saturating_sub(amount_sat),
+            SwapKind::Reverse => amount_sat.saturating_sub(record.onchain_amount_sat),
+        };
+        if fee_sat > max_fee_sat {
+            return Err(LightningError::PaymentError(format!(
+                "Swap fee of {} sat exceeds the maximum of {} sat", fee_sat, max_fee_sat
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Store a new client swap
+    fn store_swap(&self, record: &SwapRecord, key: SecretKey, preimage: Option<Vec<u8>>) {
+        let mut swaps = self.swaps.lock().unwrap();
+        swaps.insert(record.id.clone(), ClientSwap { record: record.clone(), key, preimage });
+    }
+
+    /// Persist an updated record
+    fn update_record(&self, record: &SwapRecord) {
+        let mut swaps = self.swaps.lock().unwrap();
+        if let Some(swap) = swaps.get_mut(&record.id) {
+            swap.record = record.clone();
+        }
+    }
+
+    /// Look up a swap with its key and preimage
+    fn get_client_swap(&self, swap_id: &str) -> LightningResult<(SwapRecord, SecretKey, Option<Vec<u8>>)> {
+        let swaps = self.swaps.lock().unwrap();
+        swaps.get(swap_id)
+            .map(|swap| (swap.record.clone(), swap.key, swap.preimage.clone()))
+            .ok_or_else(|| LightningError::ImplementationError(format!("Swap not found: {}", swap_id)))
+    }
+
+    /// Take the server's progress fields into our record
+    ///
+    /// The script and amounts agreed at creation are never taken from later responses.
+    fn merge_server_record(&self, swap_id: &str, server_record: SwapRecord) -> LightningResult<SwapRecord> {
+        let mut swaps = self.swaps.lock().unwrap();
+        let swap = swaps.get_mut(swap_id).ok_or_else(|| {
+            LightningError::ImplementationError(format!("Swap not found: {}", swap_id))
+        })?;
+
+        let record = &mut swap.record;
+        if matches!(record.state, SwapState::Refunded | SwapState::Claimed) {
+            return Ok(record.clone());
+        }
+
+        record.state = server_record.state;
+        record.error = server_record.error;
+        record.lockup_txid = record.lockup_txid.clone().or(server_record.lockup_txid);
+        record.lockup_vout = record.lockup_vout.or(server_record.lockup_vout);
+        record.claim_txid = record.claim_txid.clone().or(server_record.claim_txid);
+        record.refund_txid = record.refund_txid.clone().or(server_record.refund_txid);
+
+        Ok(record.clone())
+    }
+}
+
+/// Check that a preimage matches a hex payment hash
+pub fn preimage_matches(preimage: &[u8], payment_hash: &str) -> bool {
+    payment_hash_for_preimage(&encoding::to_hex(preimage))
+        .map(|hash| hash == payment_hash.to_lowercase())
+        .unwrap_or(false)
+}
+
+/// Generate a fresh secp256k1 key pair
+fn generate_keypair() -> (SecretKey, PublicKey) {
+    let secp = Secp256k1::new();
+    secp.generate_keypair(&mut secp256k1::rand::thread_rng())
+}
+
+/// Generate random bytes
+fn generate_random_bytes(len: usize) -> Vec<u8> {
+    use rand::{thread_rng, Rng};
+    let mut rng = thread_rng();
+    (0..len).map(|_| rng.gen::<u8>()).collect()
+}
+
+/// Generate random bytes as hex
+fn generate_random_bytes_hex(len: usize) -> String {
+    encoding::to_hex(&generate_random_bytes(len))
+}
+
+/// Get current timestamp
+fn get_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+    use crate::lightning::mock::MockLightningImplementation;
+
+    struct SwapTestEnv {
+        chain: Arc<SimulatedBitcoinImplementation>,
+        lightning: Arc<dyn LightningInterface>,
+        client: SwapClient,
+    }
+
+    /// Client and server share one simulated chain and one mock node
+    fn setup(server_config: SwapServerConfig) -> SwapTestEnv {
+        let mut config = crate::config::Config::default();
+        config.bitcoin_network = Some("regtest".to_string());
+
+        let chain = Arc::new(SimulatedBitcoinImplementation::new(&config));
+        let lightning: Arc<dyn LightningInterface> =
+            Arc::new(MockLightningImplementation::new(&config, chain.clone()));
+
+        let wallet_address = chain.generate_address(AddressType::P2WPKH).unwrap();
+        chain.fund_address(&wallet_address.address, 5_000_000).unwrap();
+
+        let server = Arc::new(LocalSwapServer::new(&config, chain.clone(), lightning.clone(), server_config));
+        let client = SwapClient::new(&config, chain.clone(), lightning.clone(), server);
+
+        SwapTestEnv { chain, lightning, client }
+    }
+
+    #[test]
+    fn test_swap_script_round_trip() {
+        let (_, claim_pubkey) = generate_keypair();
+        let (_, refund_pubkey) = generate_keypair();
+
+        for timeout_height in [5, 16, 17, 800_000] {
+            let script = SwapScript {
+                payment_hash: encoding::sha256(b"preimage"),
+                claim_pubkey,
+                refund_pubkey,
+                timeout_height,
+            };
+
+            assert_eq!(SwapScript::parse(&script.witness_script()).unwrap(), script);
+            assert!(script.address("regtest").unwrap().starts_with("bcrt1q"));
+        }
+
+        assert!(SwapScript::parse(&[opcodes::OP_SHA256, 0x20]).is_err());
+    }
+
+    #[test]
+    fn test_claim_and_refund_transactions() {
+        let (claim_key, claim_pubkey) = generate_keypair();
+        let (refund_key, refund_pubkey) = generate_keypair();
+        let preimage = generate_random_bytes(32);
+        let script = SwapScript {
+            payment_hash: encoding::sha256(&preimage),
+            claim_pubkey,
+            refund_pubkey,
+            timeout_height: 500,
+        };
+        let lockup = LockupOutput { txid: generate_random_bytes_hex(32), vout: 0, value: 100_000 };
+        let destination = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080";
+
+        let claim = build_claim_transaction(&script, &lockup, &preimage, &claim_key, destination, 2).unwrap();
+        assert_eq!(claim.locktime, 0);
+        assert!(claim.outputs[0].value < 100_000);
+        assert_eq!(extract_preimage(&claim, &script.payment_hash), Some(preimage.clone()));
+
+        // A wrong preimage cannot build a claim
+        assert!(build_claim_transaction(&script, &lockup, &[0u8; 32], &claim_key, destination, 2).is_err());
+
+        let refund = build_refund_transaction(&script, &lockup, &refund_key, destination, 2).unwrap();
+        assert_eq!(refund.locktime, 500);
+        assert_eq!(refund.inputs[0].sequence, HTLC_INPUT_SEQUENCE);
+        assert_eq!(extract_preimage(&refund, &script.payment_hash), None);
+    }
+
+    #[test]
+    fn test_submarine_swap_end_to_end() {
+        let env = setup(SwapServerConfig::default());
+
+        let record = env.client.submarine_swap(100_000, 500, 2).unwrap();
+        assert_eq!(record.state, SwapState::LockedUp);
+        assert_eq!(record.onchain_amount_sat, 100_100);
+
+        // The server waits for the lockup to confirm before paying
+        let invoice_state = env.lightning.get_invoice_state(&record.payment_hash).unwrap();
+        assert_eq!(invoice_state, InvoiceState::Open);
+
+        env.chain.mine_blocks(1);
+        let record = env.client.get_swap(&record.id).unwrap();
+        assert_eq!(record.state, SwapState::Claimed);
+
+        // The invoice was paid and the server's claim revealed its preimage on-chain
+        let invoice_state = env.lightning.get_invoice_state(&record.payment_hash).unwrap();
+        assert_eq!(invoice_state, InvoiceState::Settled);
+
+        let claim_txid = record.claim_txid.unwrap();
+        let claim_tx = env.chain.get_transaction(&claim_txid).unwrap();
+        assert!(extract_preimage(&claim_tx, &record.script.payment_hash).is_some());
+
+        env.chain.mine_blocks(1);
+        assert_eq!(env.chain.get_confirmations(&claim_txid).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_submarine_swap_fee_limit() {
+        let env = setup(SwapServerConfig { fee_ppm: 50_000, ..SwapServerConfig::default() });
+
+        // 5% fee on 100k sat exceeds a 1k sat budget; nothing is locked
+        assert!(env.client.submarine_swap(100_000, 1_000, 2).is_err());
+        assert!(env.chain.mempool_txids().is_empty());
+    }
+
+    /// Server that overstates the invoice amount to hide its fee
+    struct InflatedQuotes(LocalSwapServer);
+
+    impl SwapServer for InflatedQuotes {
+        fn create_submarine_swap(&self, invoice: &str, refund_pubkey: &PublicKey) -> LightningResult<SwapRecord> {
+            let mut record = self.0.create_submarine_swap(invoice, refund_pubkey)?;
+            record.invoice_amount_sat += 5_000;
+            record.onchain_amount_sat += 5_000;
+            Ok(record)
+        }
+
+        fn create_reverse_swap(&self, payment_hash: &str, invoice_amount_sat: u64, claim_pubkey: &PublicKey) -> LightningResult<SwapRecord> {
+            self.0.create_reverse_swap(payment_hash, invoice_amount_sat, claim_pubkey)
+        }
+
+        fn notify_transaction(&self, swap_id: &str, txid: &str) -> LightningResult<SwapRecord> {
+            self.0.notify_transaction(swap_id, txid)
+        }
+
+        fn swap_status(&self, swap_id: &str) -> LightningResult<SwapRecord> {
+            self.0.swap_status(swap_id)
+        }
+    }
+
+    #[test]
+    fn test_submarine_swap_rejects_inflated_invoice_amount() {
+        let env = setup(SwapServerConfig::default());
+        let config = crate::config::Config { bitcoin_network: Some("regtest".to_string()), ..crate::config::Config::default() };
+        let server = LocalSwapServer::new(&config, env.chain.clone(), env.lightning.clone(), SwapServerConfig::default());
+        let client = SwapClient::new(&config, env.chain.clone(), env.lightning.clone(), Arc::new(InflatedQuotes(server)));
+
+        // The 5.1k sat fee looks like 100 sat against the inflated amount
+        assert!(client.submarine_swap(100_000, 500, 2).is_err());
+        assert!(env.chain.mempool_txids().is_empty());
+    }
+
+    #[test]
+    fn test_submarine_swap_refuses_lockup_near_timeout() {
+        let env = setup(SwapServerConfig::default());
+        let server_config = SwapServerConfig::default();
+        let server = LocalSwapServer::new(
+            &crate::config::Config { bitcoin_network: Some("regtest".to_string()), ..crate::config::Config::default() },
+            env.chain.clone(),
+            env.lightning.clone(),
+            server_config.clone(),
+        );
+
+        let invoice = env.lightning.create_invoice(Some(30_000_000), "Late lockup", None).unwrap();
+        let (_, refund_pubkey) = generate_keypair();
+        let record = server.create_submarine_swap(&invoice.bolt11, &refund_pubkey).unwrap();
+
+        // The lockup confirms with fewer blocks left than the safety delta
+        env.chain.mine_blocks(server_config.submarine_timeout_blocks - server_config.cltv_safety_delta);
+        let lockup_tx = env.chain.create_transaction(vec![(record.lockup_address.clone(), record.onchain_amount_sat)], 2).unwrap();
+        let lockup_txid = env.chain.broadcast_transaction(&lockup_tx).unwrap();
+
+        let record = server.notify_transaction(&record.id, &lockup_txid).unwrap();
+        assert_eq!(record.state, SwapState::LockedUp);
+
+        env.chain.mine_blocks(1);
+        let record = server.swap_status(&record.id).unwrap();
+        assert_eq!(record.state, SwapState::Failed);
+        assert_eq!(env.lightning.get_invoice_state(&invoice.payment_hash).unwrap(), InvoiceState::Open);
+    }
+
+    #[test]
+    fn test_reverse_swap_end_to_end() {
+        let env = setup(SwapServerConfig::default());
+        let destination = env.chain.generate_address(AddressType::P2WPKH).unwrap();
+
+        let record = env.client.reverse_swap(200_000, 1_000).unwrap();
+        assert_eq!(record.onchain_amount_sat, 199_800);
+
+        // Paying the hold invoice leaves our payment pending
+        let payment = env.lightning.get_payment(&record.payment_hash).unwrap().unwrap();
+        assert_eq!(payment.status, PaymentStatus::Pending);
+
+        let claim_txid = env.client.claim_reverse_swap(&record.id, &destination.address, 2).unwrap().unwrap();
+        let claimed = env.chain.get_utxo(&claim_txid, 0).unwrap();
+        assert_eq!(claimed.address.as_deref(), Some(destination.address.as_str()));
+
+        // The claim revealed the preimage, so the server settled and our payment completed
+        assert_eq!(env.lightning.get_invoice_state(&record.payment_hash).unwrap(), InvoiceState::Settled);
+        let payment = env.lightning.get_payment(&record.payment_hash).unwrap().unwrap();
+        assert_eq!(payment.status, PaymentStatus::Succeeded);
+        assert!(preimage_matches(&decode_hex(payment.preimage.as_deref().unwrap()).unwrap(), &record.payment_hash));
+
+        assert_eq!(env.client.get_swap(&record.id).unwrap().state, SwapState::Claimed);
+    }
+
+    #[test]
+    fn test_submarine_swap_refund_after_timeout() {
+        let env = setup(SwapServerConfig { max_amount_sat: 50_000, ..SwapServerConfig::default() });
+        let server_config = SwapServerConfig::default();
+
+        // Lock funds for a swap the server will never pay by skipping the notification
+        let invoice = env.lightning.create_invoice(Some(30_000_000), "Refund test", None).unwrap();
+        let server = LocalSwapServer::new(
+            &crate::config::Config { bitcoin_network: Some("regtest".to_string()), ..crate::config::Config::default() },
+            env.chain.clone(),
+            env.lightning.clone(),
+            server_config.clone(),
+        );
+        let (refund_key, refund_pubkey) = generate_keypair();
+        let record = server.create_submarine_swap(&invoice.bolt11, &refund_pubkey).unwrap();
+
+        let lockup_tx = env.chain.create_transaction(vec![(record.lockup_address.clone(), record.onchain_amount_sat)], 2).unwrap();
+        let lockup_txid = env.chain.broadcast_transaction(&lockup_tx).unwrap();
+        let lockup = find_lockup_output(&lockup_tx, &record.script).unwrap();
+        env.chain.mine_blocks(1);
+
+        let destination = env.chain.generate_address(AddressType::P2WPKH).unwrap();
+        let refund_tx = build_refund_transaction(&record.script, &lockup, &refund_key, &destination.address, 2).unwrap();
+
+        // The timelock keeps the refund out until the timeout height
+        assert!(env.chain.broadcast_transaction(&refund_tx).is_err());
+        env.chain.mine_blocks(server_config.submarine_timeout_blocks);
+        assert!(env.chain.broadcast_transaction(&refund_tx).is_ok());
+
+        // The server can no longer claim, and a late notification fails the swap instead of paying
+        let record = server.notify_transaction(&record.id, &lockup_txid).unwrap();
+        assert_eq!(record.state, SwapState::Failed);
+        assert_eq!(env.lightning.get_invoice_state(&invoice.payment_hash).unwrap(), InvoiceState::Open);
+
+        // Swaps above the server limit are refused
+        assert!(env.client.submarine_swap(60_000, 1_000, 2).is_err());
+    }
+}
@@ -0,0 +1,1868 @@
+// In-process Lightning test network
+// Runs several mock Lightning nodes in one process over a shared simulated chain
+// and an in-memory transport, so multi-node features (routing, forwarding, hold
+// invoices, keysend, channel lifecycle) can be tested deterministically.
+//
+// The transport plays the part of the wire: it connects peers, funds channels
+// with real 2-of-2 P2WSH outputs on the simulated chain, gossips confirmed
+// channels and channel_updates into every node's router, and carries HTLCs hop
+// by hop through each node's forwarding manager. Payments are atomic: every hop
+// is checked before any balance moves, so a failed attempt leaves all channels
+// untouched and the sender retries around the failing node.
+//
+// Channel types are modelled on chain too: dual-funded channels combine inputs
+// from both sides in one funding transaction, zero-conf channels are usable
+// before their funding confirms, and force-closing an anchor channel broadcasts
+// a low-fee commitment whose anchors either side can spend to CPFP it. Splices
+// spend the funding output into a new one, and the channel keeps forwarding on
+// the old output until the splice is buried.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use secp256k1::{PublicKey, Secp256k1};
+
+use crate::bitcoin::encoding::{
+    self, encode_segwit_address, network_hrp, opcodes, p2wsh_script_pubkey, push_data,
+};
+use crate::bitcoin::simulated::SimulatedBitcoinImplementation;
+use crate::bitcoin::{
+    AddressType, BitcoinInterface, BitcoinTransaction, TransactionInput, TransactionOutput,
+};
+use crate::config::Config;
+use crate::lightning::channel_manager::{generate_random_id, lock_in_splice};
+use crate::lightning::forwarding::{ForwardingPolicy, IncomingHtlc, DEFAULT_CLTV_EXPIRY_DELTA};
+use crate::lightning::interface::{
+    ChannelInfo, ChannelType, Invoice, LightningError, LightningInterface, LightningResult,
+    OpenChannelOptions, PaymentInfo, PendingSplice,
+};
+use crate::lightning::invoice_manager::{InvoiceState, payment_hash_for_preimage};
+use crate::lightning::key_manager::KeyManagerWrapper;
+use crate::lightning::mock::{MockLightningImplementation, NodeEndpoint, NodeTransport, SpliceRequest};
+use crate::lightning::payment_executor::{
+    PaymentOrigin, KEYSEND_PREIMAGE_TLV_TYPE, validate_custom_records,
+};
+use crate::lightning::payment_router::{PaymentHop, PaymentRouter};
+
+/// Confirmations a funding transaction needs before the channel is usable
+pub const FUNDING_CONFIRMATIONS: u32 = 3;
+
+/// Routes tried per payment before giving up
+pub const MAX_PAYMENT_ATTEMPTS: usize = 5;
+
+/// Wallet balance the harness starts the shared chain with (in sats)
+pub const INITIAL_WALLET_BALANCE_SAT: u64 = 1_000_000_000;
+
+/// Outputs below this value are dropped from closing transactions
+const DUST_LIMIT_SAT: u64 = 546;
+
+/// Estimated virtual size of a 2-of-2 cooperative close in vbytes
+const CLOSING_TX_VSIZE: u64 = 170;
+
+/// Value of each anchor output on an anchor channel commitment
+pub const ANCHOR_OUTPUT_SAT: u64 = 330;
+
+/// Fee rate of anchor channel commitments, left for CPFP to raise (sat/vB)
+const ANCHOR_COMMITMENT_FEE_RATE: u64 = 1;
+
+/// Estimated virtual size of an anchor-spending child with one wallet input and change
+const ANCHOR_CHILD_VSIZE: u64 = 180;
+
+/// Virtual size of a dual-funded transaction's shared parts (overhead and funding output)
+const DUAL_FUNDING_SHARED_VSIZE: u64 = 54;
+
+/// Virtual size budgeted for each side's inputs and change in a dual-funded transaction
+const DUAL_FUNDING_CONTRIBUTION_VSIZE: u64 = 130;
+
+/// Estimated virtual size of a splice: the 2-of-2 input, the new funding output and one more output
+const SPLICE_TX_VSIZE: u64 = 180;
+
+/// A channel funded through the transport
+#[derive(Debug, Clone)]
+struct NetworkChannel {
+    /// Channel ID
+    channel_id: String,
+
+    /// Node that opened and funded the channel
+    funder: String,
+
+    /// Node that accepted the channel
+    fundee: String,
+
+    /// Funding transaction ID
+    funding_txid: String,
+
+    /// Funding output index
+    funding_output_idx: u32,
+
+    /// Channel capacity in satoshis
+    capacity: u64,
+
+    /// Whether the channel is announced to the whole network
+    is_public: bool,
+
+    /// 2-of-2 multisig witness script of the funding output
+    witness_script: Vec<u8>,
+
+    /// Short channel ID, set once the funding transaction is buried
+    short_channel_id: Option<String>,
+
+    /// Commitment format
+    channel_type: ChannelType,
+
+    /// Whether the channel is usable before its funding confirms
+    zero_conf: bool,
+
+    /// Splice transaction waiting to be buried: (txid, new funding output index, new capacity)
+    pending_splice: Option<(String, u32, u64)>,
+}
+
+impl NetworkChannel {
+    /// The other party of the channel
+    fn counterparty(&self, pubkey: &str) -> &str {
+        if self.funder == pubkey { &self.fundee } else { &self.funder }
+    }
+
+    /// Whether a node is one of the channel parties
+    fn has_party(&self, pubkey: &str) -> bool {
+        self.funder == pubkey || self.fundee == pubkey
+    }
+}
+
+/// A broadcast anchor commitment whose anchors can still be spent
+#[derive(Debug, Clone)]
+struct AnchorClaim {
+    /// Commitment transaction ID
+    commitment_txid: String,
+
+    /// Fee paid by the commitment itself
+    commitment_fee: u64,
+
+    /// Virtual size of the commitment
+    commitment_vsize: u64,
+
+    /// Anchor outputs by owner: (pubkey, output index, witness script)
+    anchors: Vec<(String, u32, Vec<u8>)>,
+}
+
+/// How the final node of a payment claims the HTLC
+#[derive(Debug, Clone)]
+enum FinalHop {
+    /// Pay one of the recipient's invoices
+    Invoice,
+
+    /// Spontaneous payment carrying the preimage and custom records
+    Keysend(BTreeMap<u64, Vec<u8>>),
+}
+
+/// An HTLC at one hop of a payment, as laid out by the sender
+#[derive(Debug, Clone)]
+struct HopHtlc {
+    /// The hop in the route
+    hop: PaymentHop,
+
+    /// Amount carried over the hop's channel (in msats)
+    amount_msat: u64,
+
+    /// CLTV expiry of the HTLC over the hop's channel
+    cltv_expiry: u32,
+}
+
+/// A payment whose HTLCs are held by a hold invoice at the recipient
+#[derive(Debug, Clone)]
+struct HeldPayment {
+    /// Paying node
+    sender: String,
+
+    /// Receiving node
+    recipient: String,
+
+    /// Payment hash
+    payment_hash: String,
+
+    /// HTLCs along the route, first hop first
+    htlcs: Vec<HopHtlc>,
+}
+
+/// Why an attempt failed, and at which node
+#[derive(Debug)]
+struct HopFailure {
+    /// Node that could not carry the HTLC
+    node: String,
+
+    /// Whether retrying over a different route cannot help
+    permanent: bool,
+
+    /// Underlying error
+    error: LightningError,
+}
+
+impl HopFailure {
+    fn at(node: &str, error: LightningError) -> Self {
+        HopFailure { node: node.to_string(), permanent: false, error }
+    }
+
+    fn permanent(node: &str, error: LightningError) -> Self {
+        HopFailure { node: node.to_string(), permanent: true, error }
+    }
+}
+
+/// In-memory "wire" shared by the nodes of a test network
+pub struct InMemoryTransport {
+    /// Shared simulated chain
+    chain: Arc<SimulatedBitcoinImplementation>,
+
+    /// Segwit human-readable part of the chain's network
+    hrp: &'static str,
+
+    /// Registered nodes by pubkey
+    nodes: Mutex<HashMap<String, NodeEndpoint>>,
+
+    /// Nodes currently offline
+    offline: Mutex<HashSet<String>>,
+
+    /// Peer connections, kept across a node going offline (sorted pubkey pairs)
+    links: Mutex<HashSet<(String, String)>>,
+
+    /// Channels by channel ID
+    channels: Mutex<HashMap<String, NetworkChannel>>,
+
+    /// Payments waiting on hold invoices
+    held_payments: Mutex<Vec<HeldPayment>>,
+
+    /// Force-closed anchor channels by channel ID
+    anchor_claims: Mutex<HashMap<String, AnchorClaim>>,
+
+    /// Serializes payments so every attempt sees consistent balances
+    payment_lock: Mutex<()>,
+}
+
+impl InMemoryTransport {
+    /// Create a transport over a simulated chain
+    pub fn new(config: &Config, chain: Arc<SimulatedBitcoinImplementation>) -> Self {
+        let network = config.bitcoin_network.clone().unwrap_or_else(|| "regtest".to_string());
+
+        InMemoryTransport {
+            chain,
+            hrp: network_hrp(&network),
+            nodes: Mutex::new(HashMap::new()),
+            offline: Mutex::new(HashSet::new()),
+            links: Mutex::new(HashSet::new()),
+            channels: Mutex::new(HashMap::new()),
+            held_payments: Mutex::new(Vec::new()),
+            anchor_claims: Mutex::new(HashMap::new()),
+            payment_lock: Mutex::new(()),
+        }
+    }
+
+    /// The shared simulated chain
+    pub fn chain(&self) -> Arc<SimulatedBitcoinImplementation> {
+        self.chain.clone()
+    }
+
+    /// Attach a node; it learns every public channel confirmed so far
+    pub(crate) fn register(&self, endpoint: NodeEndpoint) -> LightningResult<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if nodes.contains_key(&endpoint.pubkey) {
+            return Err(LightningError::NetworkError(
+                format!("Node {} is already on the network", endpoint.pubkey)
+            ));
+        }
+
+        for channel in self.channels.lock().unwrap().values() {
+            if channel.is_public && channel.short_channel_id.is_some() {
+                let policy = nodes.get(&channel.funder)
+                    .and_then(|funder| funder.forwarding_manager.get_channel_policy(&channel.channel_id).ok())
+                    .unwrap_or_default();
+                announce_to(&endpoint, channel, &policy);
+            }
+        }
+
+        nodes.insert(endpoint.pubkey.clone(), endpoint);
+        Ok(())
+    }
+
+    /// Whether a node is registered and online
+    pub fn is_online(&self, pubkey: &str) -> bool {
+        self.nodes.lock().unwrap().contains_key(pubkey) &&
+            !self.offline.lock().unwrap().contains(pubkey)
+    }
+
+    /// Take a node offline or bring it back
+    ///
+    /// Going offline drops the node's connections and disables its channels on
+    /// both ends; coming back restores connections to online peers and re-enables
+    /// confirmed channels.
+    pub fn set_online(&self, pubkey: &str, online: bool) -> LightningResult<()> {
+        self.endpoint(pubkey)?;
+
+        if online {
+            self.offline.lock().unwrap().remove(pubkey);
+        } else {
+            self.offline.lock().unwrap().insert(pubkey.to_string());
+        }
+
+        let links: Vec<(String, String)> = self.links.lock().unwrap().iter()
+            .filter(|(a, b)| a == pubkey || b == pubkey)
+            .cloned()
+            .collect();
+
+        for (a, b) in links {
+            let peer = if a == pubkey { b } else { a };
+            if online && self.is_online(&peer) {
+                self.add_peers(pubkey, &peer)?;
+            } else if !online {
+                let _ = self.endpoint(pubkey)?.peer_manager.disconnect_peer(&peer);
+                let _ = self.endpoint(&peer)?.peer_manager.disconnect_peer(pubkey);
+            }
+        }
+
+        let channels: Vec<NetworkChannel> = self.channels.lock().unwrap().values()
+            .filter(|channel| channel.has_party(pubkey))
+            .cloned()
+            .collect();
+
+        for channel in channels {
+            let active = (channel.short_channel_id.is_some() || channel.zero_conf) &&
+                self.is_online(&channel.funder) && self.is_online(&channel.fundee);
+            self.update_both_views(&channel, |view| view.is_active = active)?;
+        }
+
+        Ok(())
+    }
+
+    /// Connect two nodes
+    ///
+    /// Addresses are not dialled; the transport links the nodes directly.
+    pub(crate) fn connect(&self, from: &str, to: &str) -> LightningResult<()> {
+        if from == to {
+            return Err(LightningError::NetworkError("Cannot connect to ourselves".to_string()));
+        }
+
+        if !self.nodes.lock().unwrap().contains_key(to) {
+            return Err(LightningError::NetworkError(format!("Unknown peer {}", to)));
+        }
+
+        for pubkey in [from, to] {
+            if !self.is_online(pubkey) {
+                return Err(LightningError::NetworkError(format!("Peer {} is offline", pubkey)));
+            }
+        }
+
+        if self.endpoint(from)?.peer_manager.is_connected(to) {
+            return Err(LightningError::NetworkError(format!("Already connected to {}", to)));
+        }
+
+        self.add_peers(from, to)?;
+        self.links.lock().unwrap().insert(link_key(from, to));
+        Ok(())
+    }
+
+    /// Open a channel funded from the shared chain wallet
+    ///
+    /// The funding transaction pays a 2-of-2 P2WSH output and is broadcast
+    /// immediately. Both ends see the channel as inactive until `sync_chain`
+    /// finds the funding transaction buried under FUNDING_CONFIRMATIONS blocks,
+    /// unless the fundee trusts the funder with a zero-conf channel. A dual-funded
+    /// channel adds the fundee's inputs to the same transaction.
+    pub(crate) fn open_channel(
+        &self,
+        funder: &str,
+        fundee: &str,
+        capacity: u64,
+        options: &OpenChannelOptions,
+    ) -> LightningResult<ChannelInfo> {
+        let funder_endpoint = self.endpoint(funder)?;
+        let fundee_endpoint = self.endpoint(fundee)?;
+
+        if !funder_endpoint.peer_manager.is_connected(fundee) || !self.is_online(fundee) {
+            return Err(LightningError::ChannelError(format!("Not connected to peer {}", fundee)));
+        }
+
+        funder_endpoint.channel_manager.validate_open_options(fundee, capacity, options)?;
+
+        if options.zero_conf && !fundee_endpoint.channel_manager.is_trusted_peer(funder) {
+            return Err(LightningError::ChannelError(
+                format!("Peer {} does not trust us with zero-conf channels", fundee)
+            ));
+        }
+
+        let remote_funding = options.remote_funding_sat.unwrap_or(0);
+        if remote_funding > 0 && !fundee_endpoint.channel_manager.accepts_dual_funding() {
+            return Err(LightningError::ChannelError(
+                format!("Peer {} does not accept dual-funded channels", fundee)
+            ));
+        }
+
+        let witness_script = funding_witness_script(funder, fundee)?;
+        let total_capacity = capacity + remote_funding;
+        let funding_tx = if remote_funding > 0 {
+            self.dual_funding_transaction(&witness_script, capacity, remote_funding)?
+        } else {
+            let address = encode_segwit_address(self.hrp, 0, &encoding::sha256(&witness_script))?;
+            let fee_rate = self.chain.estimate_fee(6)?;
+            self.chain.create_transaction(vec![(address, capacity)], fee_rate)?
+        };
+        let funding_txid = self.chain.broadcast_transaction(&funding_tx)?;
+
+        let funding_script_pubkey = p2wsh_script_pubkey(&witness_script);
+        let funding_output_idx = funding_tx.outputs.iter()
+            .position(|output| output.script_pubkey == funding_script_pubkey)
+            .ok_or_else(|| LightningError::ChannelError(
+                "Funding transaction is missing the channel output".to_string()
+            ))? as u32;
+
+        let channel = NetworkChannel {
+            channel_id: channel_id_from_funding(&funding_txid, funding_output_idx)?,
+            funder: funder.to_string(),
+            fundee: fundee.to_string(),
+            funding_txid,
+            funding_output_idx,
+            capacity: total_capacity,
+            is_public: !options.is_private,
+            witness_script,
+            short_channel_id: None,
+            channel_type: options.channel_type,
+            zero_conf: options.zero_conf,
+            pending_splice: None,
+        };
+
+        let push_sat = options.push_msat.unwrap_or(0) / 1000;
+        let funder_balance = capacity - push_sat;
+        let fundee_balance = remote_funding + push_sat;
+        let mut funder_view = channel_view(&channel, fundee, funder_balance, fundee_balance);
+        let mut fundee_view = channel_view(&channel, funder, fundee_balance, funder_balance);
+
+        // Zero-conf channels are usable, and known to both routers, right away
+        if channel.zero_conf {
+            funder_view.is_active = true;
+            fundee_view.is_active = true;
+        }
+
+        funder_endpoint.channel_manager.update_channel(funder_view.clone())?;
+        fundee_endpoint.channel_manager.update_channel(fundee_view)?;
+        self.channels.lock().unwrap().insert(channel.channel_id.clone(), channel.clone());
+
+        if channel.zero_conf {
+            let policy = funder_endpoint.forwarding_manager.get_channel_policy(&channel.channel_id)?;
+            for endpoint in [&funder_endpoint, &fundee_endpoint] {
+                announce_to(endpoint, &channel, &policy);
+            }
+        }
+
+        Ok(funder_view)
+    }
+
+    /// Build a funding transaction both parties contribute inputs to
+    ///
+    /// Interactive construction in two rounds: the funder adds inputs for its
+    /// share (counting the fundee's promised contribution), then the fundee adds
+    /// its own. Each side pays the fee for its inputs and change, and the funder
+    /// also pays for the shared funding output.
+    fn dual_funding_transaction(
+        &self,
+        witness_script: &[u8],
+        local_funding: u64,
+        remote_funding: u64,
+    ) -> LightningResult<BitcoinTransaction> {
+        let address = encode_segwit_address(self.hrp, 0, &encoding::sha256(witness_script))?;
+        let fee_rate = self.chain.estimate_fee(6)?;
+        let funder_fee = fee_rate * (DUAL_FUNDING_SHARED_VSIZE + DUAL_FUNDING_CONTRIBUTION_VSIZE);
+        let fundee_fee = fee_rate * DUAL_FUNDING_CONTRIBUTION_VSIZE;
+
+        let skeleton = BitcoinTransaction {
+            txid: String::new(),
+            version: 2,
+            inputs: Vec::new(),
+            outputs: vec![TransactionOutput {
+                value: local_funding + remote_funding,
+                script_pubkey: p2wsh_script_pubkey(witness_script),
+                address: Some(address),
+            }],
+            locktime: 0,
+            size: 0,
+            weight: 0,
+            fee: None,
+        };
+
+        // Round one: the funder's inputs, crediting the fundee's share
+        let with_funder = self.chain.fund_transaction(&skeleton, remote_funding, funder_fee)?;
+        let mut funder_inputs = 0;
+        for input in &with_funder.inputs {
+            let previous = self.chain.get_transaction(&input.txid)?;
+            funder_inputs += previous.outputs[input.vout as usize].value;
+        }
+
+        // Round two: the fundee's inputs, now that the funder's are known
+        Ok(self.chain.fund_transaction(&with_funder, funder_inputs, funder_fee + fundee_fee)?)
+    }
+
+    /// Close a channel, paying each side its balance on chain
+    ///
+    /// A cooperative close needs the counterparty online; a force close does not.
+    /// Force-closing an anchor channel broadcasts a commitment at the minimum fee
+    /// rate with an anchor output per side, to be bumped with `bump_commitment_fee`.
+    /// Returns the closing transaction ID.
+    pub(crate) fn close_channel(&self, initiator: &str, channel_id: &str, force: bool) -> LightningResult<String> {
+        let channel = self.channels.lock().unwrap().get(channel_id).cloned()
+            .filter(|channel| channel.has_party(initiator))
+            .ok_or_else(|| LightningError::ChannelError(format!("Channel {} not found", channel_id)))?;
+
+        if channel.pending_splice.is_some() {
+            return Err(LightningError::ChannelError(
+                format!("Channel {} has a splice waiting to confirm", channel_id)
+            ));
+        }
+
+        let counterparty = channel.counterparty(initiator).to_string();
+        if !force && !self.is_online(&counterparty) {
+            return Err(LightningError::ChannelError(
+                format!("Peer {} is offline; only a force close is possible", counterparty)
+            ));
+        }
+
+        let initiator_endpoint = self.endpoint(initiator)?;
+        let view = initiator_endpoint.channel_manager.get_channel(channel_id)?
+            .ok_or_else(|| LightningError::ChannelError(format!("Channel {} not found", channel_id)))?;
+
+        // The funder pays the closing fee (and the anchors), as it paid for opening the channel
+        let anchors = force && channel.channel_type == ChannelType::AnchorOutputs;
+        let fee = if anchors {
+            ANCHOR_COMMITMENT_FEE_RATE * CLOSING_TX_VSIZE + 2 * ANCHOR_OUTPUT_SAT
+        } else {
+            CLOSING_TX_VSIZE * self.chain.estimate_fee(6)?
+        };
+        let (mut initiator_amount, mut counterparty_amount) = (view.local_balance, view.remote_balance);
+        if channel.funder == initiator {
+            initiator_amount = initiator_amount.saturating_sub(fee);
+        } else {
+            counterparty_amount = counterparty_amount.saturating_sub(fee);
+        }
+
+        let mut outputs = Vec::new();
+        for amount in [initiator_amount, counterparty_amount] {
+            if amount >= DUST_LIMIT_SAT {
+                let address = self.chain.generate_address(AddressType::P2WPKH)?;
+                outputs.push(TransactionOutput {
+                    value: amount,
+                    script_pubkey: encoding::address_to_script_pubkey(&address.address)?,
+                    address: Some(address.address),
+                });
+            }
+        }
+
+        let mut anchor_outputs = Vec::new();
+        if anchors {
+            for pubkey in [initiator, counterparty.as_str()] {
+                let script = anchor_witness_script(pubkey)?;
+                anchor_outputs.push((pubkey.to_string(), outputs.len() as u32, script.clone()));
+                outputs.push(TransactionOutput {
+                    value: ANCHOR_OUTPUT_SAT,
+                    script_pubkey: p2wsh_script_pubkey(&script),
+                    address: Some(encode_segwit_address(self.hrp, 0, &encoding::sha256(&script))?),
+                });
+            }
+        }
+
+        let mut closing_tx = BitcoinTransaction {
+            txid: String::new(),
+            version: 2,
+            inputs: vec![TransactionInput {
+                txid: channel.funding_txid.clone(),
+                vout: channel.funding_output_idx,
+                script_sig: Vec::new(),
+                sequence: 0xffff_ffff,
+                // CHECKMULTISIG dummy, both signatures and the witness script
+                witness: Some(vec![Vec::new(), vec![0u8; 72], vec![0u8; 72], channel.witness_script.clone()]),
+            }],
+            outputs,
+            locktime: 0,
+            size: 0,
+            weight: 0,
+            fee: None,
+        };
+        encoding::finalize_transaction(&mut closing_tx)?;
+        let closing_txid = self.chain.broadcast_transaction(&closing_tx)?;
+
+        if anchors {
+            let paid_out: u64 = closing_tx.outputs.iter().map(|output| output.value).sum();
+            self.anchor_claims.lock().unwrap().insert(channel_id.to_string(), AnchorClaim {
+                commitment_txid: closing_txid.clone(),
+                commitment_fee: channel.capacity - paid_out,
+                commitment_vsize: (closing_tx.weight as u64).div_ceil(4),
+                anchors: anchor_outputs,
+            });
+        }
+
+        self.channels.lock().unwrap().remove(channel_id);
+        for pubkey in [initiator, counterparty.as_str()] {
+            self.endpoint(pubkey)?.channel_manager.close_channel(channel_id, force)?;
+        }
+        for endpoint in self.endpoints() {
+            let _ = endpoint.payment_router.remove_channel(channel_id);
+        }
+
+        Ok(closing_txid)
+    }
+
+    /// Splice funds into or out of a channel
+    ///
+    /// The splice transaction spends the current funding output into a new one
+    /// with the same 2-of-2 script and is broadcast immediately. The channel stays
+    /// usable on the old output; `sync_chain` switches both ends over once the
+    /// splice is buried under FUNDING_CONFIRMATIONS blocks. Spliced-out funds
+    /// and the splice fee leave the initiator's balance straight away.
+    pub(crate) fn splice(&self, initiator: &str, channel_id: &str, request: SpliceRequest) -> LightningResult<ChannelInfo> {
+        let channel = self.channels.lock().unwrap().get(channel_id).cloned()
+            .filter(|channel| channel.has_party(initiator))
+            .ok_or_else(|| LightningError::ChannelError(format!("Channel {} not found", channel_id)))?;
+
+        let counterparty = channel.counterparty(initiator).to_string();
+        if !self.is_online(&counterparty) {
+            return Err(LightningError::ChannelError(
+                format!("Peer {} is offline; splicing needs both sides", counterparty)
+            ));
+        }
+
+        let amount_sat = match &request {
+            SpliceRequest::In { amount_sat } | SpliceRequest::Out { amount_sat, .. } => *amount_sat,
+        };
+        let view = self.endpoint(initiator)?.channel_manager.validate_splice(channel_id, amount_sat)?;
+
+        let fee = SPLICE_TX_VSIZE * self.chain.estimate_fee(6)?;
+        let (capacity, initiator_spent, initiator_added) = match &request {
+            SpliceRequest::In { .. } => (channel.capacity + amount_sat, 0, amount_sat),
+            SpliceRequest::Out { .. } => {
+                if amount_sat < DUST_LIMIT_SAT || amount_sat + fee > view.local_balance {
+                    return Err(LightningError::ChannelError(format!(
+                        "Cannot splice out {} sats plus a {} sat fee with a local balance of {} sats",
+                        amount_sat, fee, view.local_balance
+                    )));
+                }
+                (channel.capacity - amount_sat - fee, amount_sat + fee, 0)
+            }
+        };
+
+        let funding_script_pubkey = p2wsh_script_pubkey(&channel.witness_script);
+        let mut outputs = vec![TransactionOutput {
+            value: capacity,
+            script_pubkey: funding_script_pubkey.clone(),
+            address: Some(encode_segwit_address(self.hrp, 0, &encoding::sha256(&channel.witness_script))?),
+        }];
+        if let SpliceRequest::Out { address, .. } = &request {
+            outputs.push(TransactionOutput {
+                value: amount_sat,
+                script_pubkey: encoding::address_to_script_pubkey(address)?,
+                address: Some(address.clone()),
+            });
+        }
+
+        let mut splice_tx = BitcoinTransaction {
+            txid: String::new(),
+            version: 2,
+            inputs: vec![TransactionInput {
+                txid: channel.funding_txid.clone(),
+                vout: channel.funding_output_idx,
+                script_sig: Vec::new(),
+                sequence: 0xffff_fffd,
+                // CHECKMULTISIG dummy, both signatures and the witness script
+                witness: Some(vec![Vec::new(), vec![0u8; 72], vec![0u8; 72], channel.witness_script.clone()]),
+            }],
+            outputs,
+            locktime: 0,
+            size: 0,
+            weight: 0,
+            fee: Some(fee),
+        };
+        if let SpliceRequest::In { .. } = request {
+            splice_tx = self.chain.fund_transaction(&splice_tx, channel.capacity, fee)?;
+        }
+        let splice_txid = self.chain.broadcast_transaction(&splice_tx)?;
+
+        let funding_output_idx = splice_tx.outputs.iter()
+            .position(|output| output.script_pubkey == funding_script_pubkey)
+            .unwrap_or(0) as u32;
+
+        let mut pending = channel.clone();
+        pending.pending_splice = Some((splice_txid.clone(), funding_output_idx, capacity));
+        self.channels.lock().unwrap().insert(channel_id.to_string(), pending);
+
+        let splice = PendingSplice {
+            splice_txid,
+            funding_output_idx,
+            capacity,
+            local_added_sat: 0,
+            remote_added_sat: 0,
+        };
+        self.update_both_views(&channel, |view| {
+            let mut splice = splice.clone();
+            if view.remote_pubkey == counterparty {
+                view.local_balance -= initiator_spent;
+                splice.local_added_sat = initiator_added;
+            } else {
+                view.remote_balance -= initiator_spent;
+                splice.remote_added_sat = initiator_added;
+            }
+            view.pending_splice = Some(splice);
+        })?;
+
+        self.endpoint(initiator)?.channel_manager.get_channel(channel_id)?
+            .ok_or_else(|| LightningError::ChannelError(format!("Channel {} not found", channel_id)))
+    }
+
+    /// CPFP a force-closed anchor channel's commitment by spending our anchor
+    ///
+    /// The child pays enough that the commitment and child together reach
+    /// `fee_rate` sat/vB, taking the extra from the chain wallet. Returns the
+    /// child transaction ID.
+    pub(crate) fn bump_commitment_fee(&self, pubkey: &str, channel_id: &str, fee_rate: u64) -> LightningResult<String> {
+        let claim = self.anchor_claims.lock().unwrap().get(channel_id).cloned()
+            .ok_or_else(|| LightningError::ChannelError(
+                format!("No anchor commitment to bump for channel {}", channel_id)
+            ))?;
+
+        if self.chain.get_confirmations(&claim.commitment_txid)? > 0 {
+            return Err(LightningError::ChannelError(
+                format!("Commitment {} is already confirmed", claim.commitment_txid)
+            ));
+        }
+
+        let (_, vout, script) = claim.anchors.iter()
+            .find(|(owner, _, _)| owner == pubkey)
+            .cloned()
+            .ok_or_else(|| LightningError::ChannelError(
+                format!("Channel {} has no anchor for {}", channel_id, pubkey)
+            ))?;
+
+        let package_fee = fee_rate * (claim.commitment_vsize + ANCHOR_CHILD_VSIZE);
+        if package_fee <= claim.commitment_fee {
+            return Err(LightningError::ChannelError(
+                format!("Commitment {} already pays at least {} sat/vB", claim.commitment_txid, fee_rate)
+            ));
+        }
+
+        let child = BitcoinTransaction {
+            txid: String::new(),
+            version: 2,
+            inputs: vec![TransactionInput {
+                txid: claim.commitment_txid.clone(),
+                vout,
+                script_sig: Vec::new(),
+                sequence: 0xffff_fffd,
+                // Our anchor signature and the anchor script
+                witness: Some(vec![vec![0u8; 72], script]),
+            }],
+            outputs: Vec::new(),
+            locktime: 0,
+            size: 0,
+            weight: 0,
+            fee: None,
+        };
+        let child = self.chain.fund_transaction(&child, ANCHOR_OUTPUT_SAT, package_fee - claim.commitment_fee)?;
+        let child_txid = self.chain.broadcast_transaction(&child)?;
+
+        self.anchor_claims.lock().unwrap().remove(channel_id);
+        Ok(child_txid)
+    }
+
+    /// Bring channels up to date with the chain and relay gossip
+    ///
+    /// Channels whose funding is buried deep enough get a short channel ID, are
+    /// activated and announced; pending channel_updates reach every router; held
+    /// payments whose invoice was resolved are settled or failed.
+    pub fn sync_chain(&self) -> LightningResult<()> {
+        let pending: Vec<NetworkChannel> = self.channels.lock().unwrap().values()
+            .filter(|channel| channel.short_channel_id.is_none())
+            .cloned()
+            .collect();
+
+        for mut channel in pending {
+            let confirmations = self.chain.get_confirmations(&channel.funding_txid)?;
+            if confirmations < FUNDING_CONFIRMATIONS {
+                continue;
+            }
+
+            let short_channel_id = self.short_channel_id(&channel.funding_txid, channel.funding_output_idx)?;
+
+            channel.short_channel_id = Some(short_channel_id.clone());
+            self.channels.lock().unwrap().insert(channel.channel_id.clone(), channel.clone());
+
+            let active = self.is_online(&channel.funder) && self.is_online(&channel.fundee);
+            self.update_both_views(&channel, |view| {
+                view.short_channel_id = Some(short_channel_id.clone());
+                view.is_active = active;
+            })?;
+
+            let policy = self.endpoint(&channel.funder)?.forwarding_manager
+                .get_channel_policy(&channel.channel_id)?;
+            for endpoint in self.endpoints() {
+                if channel.is_public || channel.has_party(&endpoint.pubkey) {
+                    announce_to(&endpoint, &channel, &policy);
+                }
+            }
+        }
+
+        self.lock_in_splices()?;
+        self.relay_channel_updates();
+        self.process_held_payments()
+    }
+
+    /// Switch channels whose splice is buried deep enough over to the new funding output
+    fn lock_in_splices(&self) -> LightningResult<()> {
+        let spliced: Vec<NetworkChannel> = self.channels.lock().unwrap().values()
+            .filter(|channel| channel.pending_splice.is_some())
+            .cloned()
+            .collect();
+
+        for mut channel in spliced {
+            let (splice_txid, funding_output_idx, capacity) = channel.pending_splice.take().unwrap();
+            if self.chain.get_confirmations(&splice_txid)? < FUNDING_CONFIRMATIONS {
+                continue;
+            }
+
+            let short_channel_id = self.short_channel_id(&splice_txid, funding_output_idx)?;
+            channel.funding_txid = splice_txid;
+            channel.funding_output_idx = funding_output_idx;
+            channel.capacity = capacity;
+            channel.short_channel_id = Some(short_channel_id.clone());
+            self.channels.lock().unwrap().insert(channel.channel_id.clone(), channel.clone());
+
+            self.update_both_views(&channel, |view| lock_in_splice(view, Some(short_channel_id.clone())))?;
+
+            let policy = self.endpoint(&channel.funder)?.forwarding_manager
+                .get_channel_policy(&channel.channel_id)?;
+            for endpoint in self.endpoints() {
+                if channel.is_public || channel.has_party(&endpoint.pubkey) {
+                    announce_to(&endpoint, &channel, &policy);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Short channel ID of a confirmed output: block height x transaction index x output index
+    fn short_channel_id(&self, txid: &str, output_idx: u32) -> LightningResult<String> {
+        let confirmations = self.chain.get_confirmations(txid)?;
+        let height = self.chain.get_block_height()? + 1 - confirmations;
+        let block = self.chain.get_block(&self.chain.get_block_hash(height)?)?;
+        let tx_index = block.iter()
+            .position(|tx| tx.txid == txid)
+            .unwrap_or(0);
+        Ok(format!("{}x{}x{}", height, tx_index, output_idx))
+    }
+
+    /// Find the node that issued an invoice
+    pub(crate) fn find_invoice(&self, bolt11: &str) -> Option<(String, Invoice)> {
+        self.endpoints().into_iter().find_map(|endpoint| {
+            endpoint.invoice_manager.find_invoice_by_bolt11(bolt11)
+                .map(|invoice| (endpoint.pubkey.clone(), invoice))
+        })
+    }
+
+    /// Pay another node's invoice
+    pub(crate) fn pay_invoice(
+        &self,
+        sender: &str,
+        bolt11: &str,
+        amount_msat: Option<u64>,
+    ) -> LightningResult<PaymentInfo> {
+        let (recipient, invoice) = self.find_invoice(bolt11)
+            .ok_or_else(|| LightningError::InvoiceError(format!("Unknown invoice: {}", bolt11)))?;
+
+        let amount_msat = amount_msat.or(invoice.amount_msat).ok_or_else(|| {
+            LightningError::PaymentError("Amount not specified and not included in invoice".to_string())
+        })?;
+
+        let executor = self.endpoint(sender)?.payment_executor;
+        executor.track_pending_payment(
+            &invoice.payment_hash,
+            None,
+            amount_msat,
+            Some(invoice.description.clone()),
+            PaymentOrigin::Invoice(bolt11.to_string()),
+            BTreeMap::new(),
+        );
+
+        self.send(
+            sender,
+            &recipient,
+            &invoice.payment_hash,
+            amount_msat,
+            invoice.min_final_cltv_expiry,
+            FinalHop::Invoice,
+        )
+    }
+
+    /// Send a keysend payment to another node
+    pub(crate) fn keysend(
+        &self,
+        sender: &str,
+        recipient: &str,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        validate_custom_records(&custom_records)?;
+
+        let preimage = generate_random_id();
+        let payment_hash = payment_hash_for_preimage(&preimage)?;
+
+        let mut onion_records = custom_records;
+        onion_records.insert(KEYSEND_PREIMAGE_TLV_TYPE, encoding::from_hex(&preimage)?);
+
+        self.endpoint(sender)?.payment_executor.track_pending_payment(
+            &payment_hash,
+            Some(preimage),
+            amount_msat,
+            None,
+            PaymentOrigin::Spontaneous,
+            onion_records.clone(),
+        );
+
+        self.send(
+            sender,
+            recipient,
+            &payment_hash,
+            amount_msat,
+            DEFAULT_CLTV_EXPIRY_DELTA as u32,
+            FinalHop::Keysend(onion_records),
+        )
+    }
+
+    /// Settle or fail payments held by hold invoices that have been resolved
+    pub(crate) fn process_held_payments(&self) -> LightningResult<()> {
+        let _payment_guard = self.payment_lock.lock().unwrap();
+        let held: Vec<HeldPayment> = std::mem::take(&mut *self.held_payments.lock().unwrap());
+        let mut still_held = Vec::new();
+
+        for payment in held {
+            let recipient = self.endpoint(&payment.recipient)?;
+            let sender = self.endpoint(&payment.sender)?;
+            let invoice_status = recipient.invoice_manager.get_invoice_status(&payment.payment_hash)?;
+
+            match invoice_status.as_ref().map(|invoice_status| &invoice_status.state) {
+                Some(InvoiceState::Settled) => {
+                    let preimage = invoice_status.and_then(|invoice_status| invoice_status.payment_preimage)
+                        .unwrap_or_default();
+                    self.apply_htlcs(&payment.sender, &payment.recipient, &payment.payment_hash, &payment.htlcs)?;
+                    sender.payment_executor.handle_payment_sent(
+                        &payment.payment_hash,
+                        &preimage,
+                        route_fee(&payment.htlcs),
+                    );
+                }
+                Some(InvoiceState::Cancelled) | Some(InvoiceState::Expired) | None => {
+                    sender.payment_executor.handle_payment_failed(&payment.payment_hash);
+                }
+                Some(InvoiceState::Open) | Some(InvoiceState::Accepted) => still_held.push(payment),
+            }
+        }
+
+        self.held_payments.lock().unwrap().extend(still_held);
+        Ok(())
+    }
+
+    /// Route a payment, retrying around nodes that fail to carry it
+    fn send(
+        &self,
+        sender: &str,
+        recipient: &str,
+        payment_hash: &str,
+        amount_msat: u64,
+        final_cltv_delta: u32,
+        final_hop: FinalHop,
+    ) -> LightningResult<PaymentInfo> {
+        let executor = self.endpoint(sender)?.payment_executor;
+
+        match self.route_payment(sender, recipient, payment_hash, amount_msat, final_cltv_delta, &final_hop) {
+            Ok(()) => executor.get_payment(payment_hash)?.ok_or_else(|| {
+                LightningError::PaymentError(format!("Payment not found: {}", payment_hash))
+            }),
+            Err(e) => {
+                executor.handle_payment_failed(payment_hash);
+                Err(e)
+            }
+        }
+    }
+
+    /// Try routes until one carries the payment
+    fn route_payment(
+        &self,
+        sender: &str,
+        recipient: &str,
+        payment_hash: &str,
+        amount_msat: u64,
+        final_cltv_delta: u32,
+        final_hop: &FinalHop,
+    ) -> LightningResult<()> {
+        let _payment_guard = self.payment_lock.lock().unwrap();
+
+        if sender == recipient {
+            return Err(LightningError::PaymentError("Cannot route a payment to ourselves".to_string()));
+        }
+
+        if !self.is_online(sender) {
+            return Err(LightningError::NetworkError(format!("Node {} is offline", sender)));
+        }
+
+        let router = self.endpoint(sender)?.payment_router;
+        let mut excluded: Vec<String> = Vec::new();
+        let mut last_error = None;
+
+        for _ in 0..MAX_PAYMENT_ATTEMPTS {
+            let route = match router.find_route_excluding(sender, recipient, amount_msat, 0, &excluded) {
+                Ok(route) => route,
+                Err(e) => return Err(last_error.unwrap_or(e)),
+            };
+
+            let htlcs = self.build_htlcs(&router, &route.hops, amount_msat, final_cltv_delta)?;
+
+            match self.try_route(sender, recipient, payment_hash, &htlcs, final_hop) {
+                Ok(()) => return Ok(()),
+                Err(failure) if failure.permanent => return Err(failure.error),
+                Err(failure) => {
+                    // A failing first hop is our own channel; avoid the peer behind it
+                    let node = if failure.node == sender {
+                        htlcs[0].hop.dest_node_id.clone()
+                    } else {
+                        failure.node
+                    };
+
+                    if node == recipient {
+                        return Err(failure.error);
+                    }
+
+                    excluded.push(node);
+                    last_error = Some(failure.error);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            LightningError::PaymentError(format!("No route to {}", recipient))
+        }))
+    }
+
+    /// Lay out the HTLC amounts and expiries for a route, from the last hop back
+    fn build_htlcs(
+        &self,
+        router: &PaymentRouter,
+        hops: &[PaymentHop],
+        amount_msat: u64,
+        final_cltv_delta: u32,
+    ) -> LightningResult<Vec<HopHtlc>> {
+        let mut amount = amount_msat;
+        let mut cltv_expiry = self.chain.get_block_height()? + final_cltv_delta;
+        let mut htlcs = Vec::with_capacity(hops.len());
+
+        for (index, hop) in hops.iter().enumerate().rev() {
+            htlcs.push(HopHtlc { hop: hop.clone(), amount_msat: amount, cltv_expiry });
+
+            // The node forwarding over this hop charges its advertised fee
+            if index > 0 {
+                let (fee_base_msat, fee_proportional_millionths) =
+                    router.get_channel_fees(&hop.channel_id).unwrap_or((0, 0));
+                amount += fee_base_msat as u64 + amount * fee_proportional_millionths as u64 / 1_000_000;
+                cltv_expiry += hop.cltv_expiry_delta;
+            }
+        }
+
+        htlcs.reverse();
+        Ok(htlcs)
+    }
+
+    /// Check every hop, hand the payment to the recipient and move the balances
+    fn try_route(
+        &self,
+        sender: &str,
+        recipient: &str,
+        payment_hash: &str,
+        htlcs: &[HopHtlc],
+        final_hop: &FinalHop,
+    ) -> Result<(), HopFailure> {
+        let first = &htlcs[0];
+        let last = &htlcs[htlcs.len() - 1];
+
+        for htlc in htlcs {
+            if !self.is_online(&htlc.hop.dest_node_id) {
+                let error = LightningError::NetworkError(format!("Node {} is offline", htlc.hop.dest_node_id));
+                return Err(HopFailure::at(&htlc.hop.dest_node_id, error));
+            }
+        }
+
+        // Our own outbound liquidity on the first hop
+        let sender_endpoint = self.endpoint(sender).map_err(|e| HopFailure::permanent(sender, e))?;
+        let outbound = sender_endpoint.channel_manager.get_channel(&first.hop.channel_id)
+            .ok().flatten()
+            .filter(|channel| channel.is_active);
+        match outbound {
+            Some(channel) if channel.local_balance >= first.amount_msat / 1000 => {}
+            _ => return Err(HopFailure::at(sender, LightningError::ChannelError(format!(
+                "Insufficient outbound liquidity on channel {} for {} msat",
+                first.hop.channel_id, first.amount_msat
+            )))),
+        }
+
+        // Every intermediate node applies its forwarding policy
+        for pair in htlcs.windows(2) {
+            let node = &pair[0].hop.dest_node_id;
+            let endpoint = self.endpoint(node).map_err(|e| HopFailure::at(node, e))?;
+            endpoint.forwarding_manager.check_forward(&incoming_htlc(payment_hash, &pair[0], &pair[1]))
+                .map_err(|e| HopFailure::at(node, e))?;
+        }
+
+        // The recipient accepts the HTLC
+        let recipient_endpoint = self.endpoint(recipient).map_err(|e| HopFailure::permanent(recipient, e))?;
+        let state = match final_hop {
+            FinalHop::Invoice => recipient_endpoint.invoice_manager.receive_htlc(
+                payment_hash,
+                &last.hop.channel_id,
+                0,
+                last.amount_msat,
+                last.cltv_expiry,
+            ),
+            FinalHop::Keysend(records) => recipient_endpoint.payment_executor
+                .receive_keysend(payment_hash, last.amount_msat, records.clone())
+                .map(|_| InvoiceState::Settled),
+        }.map_err(|e| HopFailure::permanent(recipient, e))?;
+
+        match state {
+            InvoiceState::Settled => {
+                self.apply_htlcs(sender, recipient, payment_hash, htlcs)
+                    .map_err(|e| HopFailure::permanent(recipient, e))?;
+
+                let preimage = match final_hop {
+                    FinalHop::Keysend(records) => records.get(&KEYSEND_PREIMAGE_TLV_TYPE)
+                        .map(|preimage| encoding::to_hex(preimage)),
+                    FinalHop::Invoice => recipient_endpoint.invoice_manager
+                        .get_invoice_status(payment_hash).ok().flatten()
+                        .and_then(|invoice_status| invoice_status.payment_preimage),
+                }.unwrap_or_default();
+
+                sender_endpoint.payment_executor.handle_payment_sent(payment_hash, &preimage, route_fee(htlcs));
+            }
+            // Hold invoice (or partial payment): the HTLCs stay in flight
+            _ => self.held_payments.lock().unwrap().push(HeldPayment {
+                sender: sender.to_string(),
+                recipient: recipient.to_string(),
+                payment_hash: payment_hash.to_string(),
+                htlcs: htlcs.to_vec(),
+            }),
+        }
+
+        Ok(())
+    }
+
+    /// Settle the HTLCs of a route, moving balances on every channel it used
+    fn apply_htlcs(
+        &self,
+        sender: &str,
+        recipient: &str,
+        payment_hash: &str,
+        htlcs: &[HopHtlc],
+    ) -> LightningResult<()> {
+        let first = &htlcs[0];
+        let last = &htlcs[htlcs.len() - 1];
+
+        self.move_balance(sender, &first.hop.channel_id, first.amount_msat / 1000, false)?;
+
+        for pair in htlcs.windows(2) {
+            self.endpoint(&pair[0].hop.dest_node_id)?.forwarding_manager
+                .forward_htlc(&incoming_htlc(payment_hash, &pair[0], &pair[1]))?;
+        }
+
+        self.move_balance(recipient, &last.hop.channel_id, last.amount_msat / 1000, true)
+    }
+
+    /// Move an amount across one node's view of a channel
+    fn move_balance(&self, pubkey: &str, channel_id: &str, amount_sat: u64, incoming: bool) -> LightningResult<()> {
+        let channel_manager = self.endpoint(pubkey)?.channel_manager;
+        let mut channel = channel_manager.get_channel(channel_id)?
+            .ok_or_else(|| LightningError::ChannelError(format!("Channel {} not found", channel_id)))?;
+
+        if incoming {
+            channel.remote_balance = channel.remote_balance.checked_sub(amount_sat)
+                .ok_or_else(|| LightningError::ChannelError(format!("Peer on channel {} cannot afford {} sat", channel_id, amount_sat)))?;
+            channel.local_balance += amount_sat;
+        } else {
+            channel.local_balance = channel.local_balance.checked_sub(amount_sat)
+                .ok_or_else(|| LightningError::ChannelError(format!("Insufficient balance on channel {}", channel_id)))?;
+            channel.remote_balance += amount_sat;
+        }
+
+        channel_manager.update_channel(channel)
+    }
+
+    /// Deliver queued channel_updates to every node's router
+    fn relay_channel_updates(&self) {
+        let endpoints = self.endpoints();
+        for endpoint in &endpoints {
+            for update in endpoint.forwarding_manager.take_pending_updates() {
+                for other in &endpoints {
+                    let _ = other.payment_router.update_channel_fees(
+                        &update.channel_id,
+                        update.policy.fee_base_msat,
+                        update.policy.fee_proportional_millionths,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Record two nodes as each other's peers
+    fn add_peers(&self, a: &str, b: &str) -> LightningResult<()> {
+        let a_endpoint = self.endpoint(a)?;
+        let b_endpoint = self.endpoint(b)?;
+        a_endpoint.peer_manager.update_peer_info(b_endpoint.key_manager.get_node_info()?)?;
+        b_endpoint.peer_manager.update_peer_info(a_endpoint.key_manager.get_node_info()?)
+    }
+
+    /// Apply a change to both parties' view of a channel
+    fn update_both_views<F>(&self, channel: &NetworkChannel, update: F) -> LightningResult<()>
+    where
+        F: Fn(&mut ChannelInfo),
+    {
+        for pubkey in [&channel.funder, &channel.fundee] {
+            let channel_manager = self.endpoint(pubkey)?.channel_manager;
+            if let Some(mut view) = channel_manager.get_channel(&channel.channel_id)? {
+                update(&mut view);
+                channel_manager.update_channel(view)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Look up a registered node
+    fn endpoint(&self, pubkey: &str) -> LightningResult<NodeEndpoint> {
+        self.nodes.lock().unwrap().get(pubkey).cloned()
+            .ok_or_else(|| LightningError::NetworkError(format!("Unknown node {}", pubkey)))
+    }
+
+    /// All registered nodes
+    fn endpoints(&self) -> Vec<NodeEndpoint> {
+        self.nodes.lock().unwrap().values().cloned().collect()
+    }
+}
+
+impl NodeTransport for InMemoryTransport {
+    fn bitcoin_interface(&self) -> Arc<dyn BitcoinInterface> {
+        self.chain.clone()
+    }
+
+    fn register(&self, endpoint: NodeEndpoint) -> LightningResult<()> {
+        InMemoryTransport::register(self, endpoint)
+    }
+
+    fn connect(&self, from: &str, to: &str) -> LightningResult<()> {
+        InMemoryTransport::connect(self, from, to)
+    }
+
+    fn open_channel(&self, funder: &str, fundee: &str, capacity: u64, options: &OpenChannelOptions) -> LightningResult<ChannelInfo> {
+        InMemoryTransport::open_channel(self, funder, fundee, capacity, options)
+    }
+
+    fn close_channel(&self, initiator: &str, channel_id: &str, force: bool) -> LightningResult<String> {
+        InMemoryTransport::close_channel(self, initiator, channel_id, force)
+    }
+
+    fn splice(&self, initiator: &str, channel_id: &str, request: SpliceRequest) -> LightningResult<ChannelInfo> {
+        InMemoryTransport::splice(self, initiator, channel_id, request)
+    }
+
+    fn bump_commitment_fee(&self, pubkey: &str, channel_id: &str, fee_rate: u64) -> LightningResult<String> {
+        InMemoryTransport::bump_commitment_fee(self, pubkey, channel_id, fee_rate)
+    }
+
+    fn find_invoice(&self, bolt11: &str) -> Option<(String, Invoice)> {
+        InMemoryTransport::find_invoice(self, bolt11)
+    }
+
+    fn pay_invoice(&self, sender: &str, bolt11: &str, amount_msat: Option<u64>) -> LightningResult<PaymentInfo> {
+        InMemoryTransport::pay_invoice(self, sender, bolt11, amount_msat)
+    }
+
+    fn keysend(
+        &self,
+        sender: &str,
+        recipient: &str,
+        amount_msat: u64,
+        custom_records: BTreeMap<u64, Vec<u8>>,
+    ) -> LightningResult<PaymentInfo> {
+        InMemoryTransport::keysend(self, sender, recipient, amount_msat, custom_records)
+    }
+
+    fn process_held_payments(&self) -> LightningResult<()> {
+        InMemoryTransport::process_held_payments(self)
+    }
+}
+
+/// A set of nodes on one simulated chain and transport
+///
+/// Nodes are addressed by index in the order they were added. Each gets a
+/// deterministic pubkey and its own data directory, removed when the network
+/// is dropped.
+pub struct TestNetwork {
+    /// Configuration shared by every node
+    config: Config,
+
+    /// Shared simulated chain
+    chain: Arc<SimulatedBitcoinImplementation>,
+
+    /// Shared transport
+    transport: Arc<InMemoryTransport>,
+
+    /// Nodes in the order they were added
+    nodes: Vec<Arc<MockLightningImplementation>>,
+
+    /// Root of the nodes' data directories
+    data_dir: PathBuf,
+}
+
+impl TestNetwork {
+    /// Create a network of `node_count` nodes with a funded chain wallet
+    pub fn new(node_count: usize) -> LightningResult<Self> {
+        Self::with_config(node_count, Config::default())
+    }
+
+    /// Create a network whose nodes share a base configuration
+    ///
+    /// Useful for node policies such as trusted peers or accepting dual funding;
+    /// the network, implementation and per-node settings are overridden. Node
+    /// seeds use an empty passphrase unless the configuration sets one.
+    pub fn with_config(node_count: usize, mut config: Config) -> LightningResult<Self> {
+        config.bitcoin_network = Some("regtest".to_string());
+        config.lightning_implementation = Some("mock".to_string());
+        config.lightning_allow_empty_seed_passphrase = true;
+        config.features.accept_keysend = true;
+
+        let chain = Arc::new(SimulatedBitcoinImplementation::new(&config));
+        let wallet_address = chain.generate_address(AddressType::P2WPKH)?;
+        chain.fund_address(&wallet_address.address, INITIAL_WALLET_BALANCE_SAT)?;
+
+        let transport = Arc::new(InMemoryTransport::new(&config, chain.clone()));
+        let data_dir = std::env::temp_dir()
+            .join(format!("opsource-test-network-{}", generate_random_id()));
+
+        let mut network = TestNetwork {
+            config,
+            chain,
+            transport,
+            nodes: Vec::new(),
+            data_dir,
+        };
+
+        for _ in 0..node_count {
+            network.add_node()?;
+        }
+
+        Ok(network)
+    }
+
+    /// Add a node to the network, returning its index
+    pub fn add_node(&mut self) -> LightningResult<usize> {
+        let index = self.nodes.len();
+        let mut config = self.config.clone();
+        config.lightning_data_dir = Some(self.data_dir.join(format!("node-{}", index)).to_string_lossy().to_string());
+        config.lightning_listen_addr = Some(format!("127.0.0.1:{}", 9735 + index));
+
+        // A fixed seed gives each node a stable identity it can also sign with
+        let passphrase = config.lightning_seed_passphrase.clone().unwrap_or_default();
+        KeyManagerWrapper::new(&config).restore_seed(&encoding::to_hex(&test_node_seed(index)), &passphrase)?;
+
+        let node = MockLightningImplementation::with_transport(&config, self.transport.clone())?;
+        self.nodes.push(Arc::new(node));
+        Ok(index)
+    }
+
+    /// A node by index
+    pub fn node(&self, index: usize) -> Arc<MockLightningImplementation> {
+        self.nodes[index].clone()
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the network has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Public key of a node
+    pub fn pubkey(&self, index: usize) -> String {
+        test_node_pubkey(index)
+    }
+
+    /// The shared simulated chain
+    pub fn chain(&self) -> Arc<SimulatedBitcoinImplementation> {
+        self.chain.clone()
+    }
+
+    /// The shared transport
+    pub fn transport(&self) -> Arc<InMemoryTransport> {
+        self.transport.clone()
+    }
+
+    /// Connect two nodes
+    pub fn connect(&self, a: usize, b: usize) -> LightningResult<()> {
+        self.nodes[a].connect_peer(&self.pubkey(b), "127.0.0.1", 9735 + b as u16)
+    }
+
+    /// Open a public channel from `a` to `b` and mine until it is usable
+    ///
+    /// Connects the nodes first if needed. Returns `a`'s view of the channel.
+    pub fn open_channel(&self, a: usize, b: usize, capacity: u64, push_msat: u64) -> LightningResult<ChannelInfo> {
+        let options = OpenChannelOptions { push_msat: Some(push_msat), ..OpenChannelOptions::default() };
+        self.open_channel_with(a, b, capacity, options)
+    }
+
+    /// Open a channel from `a` to `b` with explicit options and mine until it is confirmed
+    pub fn open_channel_with(
+        &self,
+        a: usize,
+        b: usize,
+        capacity: u64,
+        options: OpenChannelOptions,
+    ) -> LightningResult<ChannelInfo> {
+        if !self.transport.endpoint(&self.pubkey(a))?.peer_manager.is_connected(&self.pubkey(b)) {
+            self.connect(a, b)?;
+        }
+
+        let channel = self.nodes[a].open_channel(&self.pubkey(b), capacity, options)?;
+        self.mine_blocks(FUNDING_CONFIRMATIONS)?;
+        self.channel(a, &channel.channel_id)
+    }
+
+    /// Mine blocks and let every node process them
+    pub fn mine_blocks(&self, count: u32) -> LightningResult<()> {
+        self.chain.mine_blocks(count);
+        self.transport.sync_chain()
+    }
+
+    /// Take a node offline or bring it back
+    pub fn set_online(&self, index: usize, online: bool) -> LightningResult<()> {
+        self.transport.set_online(&self.pubkey(index), online)
+    }
+
+    /// Have `to` issue an invoice and `from` pay it
+    pub fn pay(&self, from: usize, to: usize, amount_msat: u64) -> LightningResult<PaymentInfo> {
+        let invoice = self.nodes[to].create_invoice(Some(amount_msat), "Test network payment", None)?;
+        self.nodes[from].pay_invoice(&invoice.bolt11, None)
+    }
+
+    /// A node's view of a channel
+    pub fn channel(&self, index: usize, channel_id: &str) -> LightningResult<ChannelInfo> {
+        self.nodes[index].list_channels()?.into_iter()
+            .find(|channel| channel.channel_id == channel_id)
+            .ok_or_else(|| LightningError::ChannelError(format!("Channel {} not found", channel_id)))
+    }
+
+    /// Local balance of a node on a channel (in sats)
+    pub fn local_balance(&self, index: usize, channel_id: &str) -> LightningResult<u64> {
+        Ok(self.channel(index, channel_id)?.local_balance)
+    }
+
+    /// Total local balance of a node across its channels (in sats)
+    pub fn total_local_balance(&self, index: usize) -> LightningResult<u64> {
+        Ok(self.nodes[index].list_channels()?.iter().map(|channel| channel.local_balance).sum())
+    }
+}
+
+impl Drop for TestNetwork {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+/// Deterministic seed of the node at an index
+fn test_node_seed(index: usize) -> [u8; 32] {
+    encoding::sha256(format!("opsource test network node {}", index).as_bytes())
+}
+
+/// Deterministic pubkey of the node at an index, from its seed's node key (m/0')
+pub fn test_node_pubkey(index: usize) -> String {
+    let node_key = encoding::derive_private_key(&test_node_seed(index), &[encoding::HARDENED])
+        .expect("hashes are valid seeds");
+    encoding::to_hex(&PublicKey::from_secret_key(&Secp256k1::new(), &node_key).serialize())
+}
+
+/// Unordered key for a peer connection
+fn link_key(a: &str, b: &str) -> (String, String) {
+    if a < b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) }
+}
+
+/// 2-of-2 multisig witness script over the two node keys, in BIP 69 key order
+fn funding_witness_script(a: &str, b: &str) -> LightningResult<Vec<u8>> {
+    let mut keys = [encoding::from_hex(a)?, encoding::from_hex(b)?];
+    keys.sort();
+
+    let mut script = vec![opcodes::OP_2];
+    for key in &keys {
+        push_data(&mut script, key);
+    }
+    script.push(opcodes::OP_2);
+    script.push(opcodes::OP_CHECKMULTISIG);
+    Ok(script)
+}
+
+/// BOLT 3 anchor output script: spendable by the owner at once, by anyone after 16 blocks
+fn anchor_witness_script(pubkey: &str) -> LightningResult<Vec<u8>> {
+    let mut script = Vec::new();
+    push_data(&mut script, &encoding::from_hex(pubkey)?);
+    script.extend_from_slice(&[
+        opcodes::OP_CHECKSIG,
+        opcodes::OP_IFDUP,
+        opcodes::OP_NOTIF,
+        opcodes::OP_16,
+        opcodes::OP_CHECKSEQUENCEVERIFY,
+        opcodes::OP_ENDIF,
+    ]);
+    Ok(script)
+}
+
+/// BOLT 2 channel ID: the funding txid XORed with the output index
+fn channel_id_from_funding(funding_txid: &str, funding_output_idx: u32) -> LightningResult<String> {
+    let mut channel_id = encoding::from_hex(funding_txid)?;
+    let len = channel_id.len();
+    channel_id[len - 2] ^= (funding_output_idx >> 8) as u8;
+    channel_id[len - 1] ^= funding_output_idx as u8;
+    Ok(encoding::to_hex(&channel_id))
+}
+
+/// Add a channel to a node's router with the funder's forwarding policy
+fn announce_to(endpoint: &NodeEndpoint, channel: &NetworkChannel, policy: &ForwardingPolicy) {
+    let _ = endpoint.payment_router.remove_channel(&channel.channel_id);
+    let _ = endpoint.payment_router.add_channel(
+        &channel.channel_id,
+        &channel.funder,
+        &channel.fundee,
+        channel.capacity,
+        policy.fee_base_msat,
+        policy.fee_proportional_millionths,
+    );
+}
+
+/// One party's view of a network channel
+fn channel_view(channel: &NetworkChannel, remote_pubkey: &str, local_balance: u64, remote_balance: u64) -> ChannelInfo {
+    ChannelInfo {
+        channel_id: channel.channel_id.clone(),
+        funding_txid: channel.funding_txid.clone(),
+        funding_output_idx: channel.funding_output_idx,
+        capacity: channel.capacity,
+        local_balance,
+        remote_balance,
+        remote_pubkey: remote_pubkey.to_string(),
+        is_active: false,
+        is_public: channel.is_public,
+        short_channel_id: None,
+        channel_type: channel.channel_type,
+        is_zero_conf: channel.zero_conf,
+        pending_splice: None,
+    }
+}
+
+/// The HTLC an intermediate node receives on one hop and forwards over the next
+fn incoming_htlc(payment_hash: &str, incoming: &HopHtlc, outgoing: &HopHtlc) -> IncomingHtlc {
+    IncomingHtlc {
+        incoming_channel_id: incoming.hop.channel_id.clone(),
+        outgoing_channel_id: outgoing.hop.channel_id.clone(),
+        payment_hash: payment_hash.to_string(),
+        amount_in_msat: incoming.amount_msat,
+        amount_out_msat: outgoing.amount_msat,
+        incoming_cltv_expiry: incoming.cltv_expiry,
+        outgoing_cltv_expiry: outgoing.cltv_expiry,
+    }
+}
+
+/// Total routing fee paid along a route (in msats)
+fn route_fee(htlcs: &[HopHtlc]) -> u64 {
+    htlcs[0].amount_msat - htlcs[htlcs.len() - 1].amount_msat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lightning::interface::PaymentStatus;
+
+    /// A line of `len` nodes with a 1,000,000 sat channel between neighbours
+    fn line(len: usize) -> (TestNetwork, Vec<String>) {
+        let network = TestNetwork::new(len).unwrap();
+        let channels = (0..len - 1)
+            .map(|index| network.open_channel(index, index + 1, 1_000_000, 0).unwrap().channel_id)
+            .collect();
+        (network, channels)
+    }
+
+    #[test]
+    fn test_channel_confirms_and_is_announced() {
+        let network = TestNetwork::new(3).unwrap();
+        network.connect(0, 1).unwrap();
+
+        let channel = network.node(0).open_channel(
+            &network.pubkey(1),
+            500_000,
+            OpenChannelOptions { push_msat: Some(100_000_000), ..OpenChannelOptions::default() },
+        ).unwrap();
+        assert!(!channel.is_active);
+        assert_eq!(channel.local_balance, 400_000);
+        assert_eq!(network.channel(1, &channel.channel_id).unwrap().local_balance, 100_000);
+
+        // Funding pays a 2-of-2 P2WSH output on the shared chain
+        let output = network.chain().get_utxo(&channel.funding_txid, channel.funding_output_idx).unwrap();
+        assert_eq!(output.value, 500_000);
+        assert_eq!(output.script_pubkey.len(), 34);
+
+        network.mine_blocks(FUNDING_CONFIRMATIONS - 1).unwrap();
+        assert!(!network.channel(0, &channel.channel_id).unwrap().is_active);
+
+        network.mine_blocks(1).unwrap();
+        let confirmed = network.channel(1, &channel.channel_id).unwrap();
+        assert!(confirmed.is_active);
+        assert!(confirmed.short_channel_id.is_some());
+
+        // A node without channels still learns the public channel
+        let node2 = network.transport().endpoint(&network.pubkey(2)).unwrap();
+        assert!(node2.payment_router.get_channel_fees(&channel.channel_id).is_some());
+    }
+
+    #[test]
+    fn test_multi_hop_payment() {
+        let (network, channels) = line(4);
+
+        let payment = network.pay(0, 3, 100_000_000).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Succeeded);
+        assert!(payment.preimage.is_some());
+
+        // Two forwarding nodes each charge 1000 msat + 100 ppm
+        let fee_msat = payment.fee_msat;
+        assert!(fee_msat >= 2 * 11_000);
+        assert_eq!(network.local_balance(3, &channels[2]).unwrap(), 100_000);
+        assert_eq!(network.local_balance(0, &channels[0]).unwrap(), 1_000_000 - 100_000 - fee_msat / 1000);
+
+        // Forwarders keep their fee and record the forward
+        assert_eq!(network.node(1).list_forwards().unwrap().len(), 1);
+        assert!(network.total_local_balance(1).unwrap() > 1_000_000);
+
+        // Both ends of every channel agree on the balances
+        for (index, channel_id) in channels.iter().enumerate() {
+            let near = network.channel(index, channel_id).unwrap();
+            let far = network.channel(index + 1, channel_id).unwrap();
+            assert_eq!(near.local_balance, far.remote_balance);
+            assert_eq!(near.local_balance + near.remote_balance, near.capacity);
+        }
+    }
+
+    #[test]
+    fn test_offline_node_is_routed_around() {
+        // 0 -> 1 -> 3 and 0 -> 2 -> 3
+        let network = TestNetwork::new(4).unwrap();
+        for (a, b) in [(0, 1), (1, 3), (0, 2), (2, 3)] {
+            network.open_channel(a, b, 1_000_000, 0).unwrap();
+        }
+
+        network.set_online(1, false).unwrap();
+        network.set_online(2, false).unwrap();
+        assert!(network.pay(0, 3, 10_000_000).is_err());
+
+        network.set_online(2, true).unwrap();
+        let payment = network.pay(0, 3, 10_000_000).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Succeeded);
+        assert!(network.node(1).list_forwards().unwrap().is_empty());
+        assert_eq!(network.node(2).list_forwards().unwrap().len(), 1);
+
+        // An offline recipient cannot be paid at all
+        network.set_online(3, false).unwrap();
+        assert!(network.pay(0, 3, 1_000_000).is_err());
+    }
+
+    #[test]
+    fn test_insufficient_liquidity_fails_atomically() {
+        let network = TestNetwork::new(3).unwrap();
+        let channels = [
+            network.open_channel(0, 1, 2_000_000, 0).unwrap().channel_id,
+            network.open_channel(1, 2, 1_000_000, 0).unwrap().channel_id,
+        ];
+
+        // Node 1 cannot forward the second payment on to node 2 after the first
+        network.pay(0, 2, 900_000_000).unwrap();
+        let before: Vec<u64> = (0..2)
+            .map(|index| network.local_balance(index, &channels[index]).unwrap())
+            .collect();
+
+        let result = network.pay(0, 2, 200_000_000);
+        assert!(result.is_err());
+
+        let after: Vec<u64> = (0..2)
+            .map(|index| network.local_balance(index, &channels[index]).unwrap())
+            .collect();
+        assert_eq!(before, after);
+
+        // The failed payment is recorded as such
+        let failed = network.node(0).list_payments().unwrap().into_iter()
+            .filter(|payment| payment.status == PaymentStatus::Failed)
+            .count();
+        assert_eq!(failed, 1);
+    }
+
+    #[test]
+    fn test_hold_invoice_and_keysend_across_nodes() {
+        let (network, channels) = line(3);
+
+        let preimage = "0000111122223333444455556666777788889999aaaabbbbccccddddeeeeffff";
+        let payment_hash = payment_hash_for_preimage(preimage).unwrap();
+        let invoice = network.node(2).create_hold_invoice(&payment_hash, Some(50_000_000), "Held", None).unwrap();
+
+        let payment = network.node(0).pay_invoice(&invoice.bolt11, None).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Pending);
+        assert_eq!(network.local_balance(2, &channels[1]).unwrap(), 0);
+
+        network.node(2).settle_invoice(&payment_hash, preimage).unwrap();
+        let payment = network.node(0).get_payment(&payment_hash).unwrap().unwrap();
+        assert_eq!(payment.status, PaymentStatus::Succeeded);
+        assert_eq!(payment.preimage.as_deref(), Some(preimage));
+        assert_eq!(network.local_balance(2, &channels[1]).unwrap(), 50_000);
+
+        // Keysend carries custom records to the recipient
+        let mut records = BTreeMap::new();
+        records.insert(70_000u64, b"hello".to_vec());
+        let sent = network.node(0).keysend(&network.pubkey(2), 5_000_000, records).unwrap();
+        assert_eq!(sent.status, PaymentStatus::Succeeded);
+
+        let received = network.node(2).list_received_payments().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].custom_records.get(&70_000), Some(&b"hello".to_vec()));
+    }
+
+    #[test]
+    fn test_cooperative_close_pays_out_on_chain() {
+        let (network, channels) = line(2);
+        network.pay(0, 1, 300_000_000).unwrap();
+
+        network.set_online(1, false).unwrap();
+        assert!(network.node(0).close_channel(&channels[0], false).is_err());
+        network.set_online(1, true).unwrap();
+
+        let closing_txid = network.node(0).close_channel(&channels[0], false).unwrap();
+        network.mine_blocks(1).unwrap();
+        assert_eq!(network.chain().get_confirmations(&closing_txid).unwrap(), 1);
+
+        assert!(network.node(0).list_channels().unwrap().is_empty());
+        assert!(network.node(1).list_channels().unwrap().is_empty());
+        assert!(network.pay(0, 1, 1_000_000).is_err());
+    }
+
+    #[test]
+    fn test_zero_conf_channel_with_trusted_peer() {
+        let mut config = Config::default();
+        config.lightning_trusted_peers = vec![test_node_pubkey(0), test_node_pubkey(1)];
+        let network = TestNetwork::with_config(3, config).unwrap();
+        network.connect(0, 1).unwrap();
+        network.connect(2, 0).unwrap();
+
+        let options = OpenChannelOptions { zero_conf: true, ..OpenChannelOptions::default() };
+        let channel = network.node(0).open_channel(&network.pubkey(1), 1_000_000, options.clone()).unwrap();
+        assert!(channel.is_active && channel.is_zero_conf);
+        assert!(channel.short_channel_id.is_none());
+
+        // Usable before the funding transaction confirms
+        let payment = network.pay(0, 1, 10_000_000).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Succeeded);
+
+        network.mine_blocks(FUNDING_CONFIRMATIONS).unwrap();
+        assert!(network.channel(1, &channel.channel_id).unwrap().short_channel_id.is_some());
+
+        // Node 0 does not trust node 2 with zero-conf channels
+        assert!(network.node(2).open_channel(&network.pubkey(0), 1_000_000, options).is_err());
+    }
+
+    #[test]
+    fn test_dual_funded_channel() {
+        let dual_funded = OpenChannelOptions { remote_funding_sat: Some(400_000), ..OpenChannelOptions::default() };
+
+        let network = TestNetwork::new(2).unwrap();
+        assert!(network.open_channel_with(0, 1, 600_000, dual_funded.clone()).is_err());
+
+        let mut config = Config::default();
+        config.features.accept_dual_funding = true;
+        let network = TestNetwork::with_config(2, config).unwrap();
+
+        // Give the second contributor a wallet output of its own to spend
+        let address = network.chain().generate_address(AddressType::P2WPKH).unwrap();
+        network.chain().fund_address(&address.address, 5_000_000).unwrap();
+
+        let channel = network.open_channel_with(0, 1, 600_000, dual_funded).unwrap();
+        assert_eq!(channel.capacity, 1_000_000);
+        assert_eq!(channel.local_balance, 600_000);
+        assert_eq!(network.local_balance(1, &channel.channel_id).unwrap(), 400_000);
+
+        let funding_tx = network.chain().get_transaction(&channel.funding_txid).unwrap();
+        assert!(funding_tx.inputs.len() >= 2);
+
+        // The fundee can pay with its own contribution straight away
+        network.pay(1, 0, 100_000_000).unwrap();
+        assert_eq!(network.local_balance(0, &channel.channel_id).unwrap(), 700_000);
+    }
+
+    #[test]
+    fn test_anchor_commitment_is_bumped_with_cpfp() {
+        let network = TestNetwork::new(2).unwrap();
+        let anchors = OpenChannelOptions { channel_type: ChannelType::AnchorOutputs, ..OpenChannelOptions::default() };
+        let channel = network.open_channel_with(0, 1, 1_000_000, anchors).unwrap();
+        assert_eq!(channel.channel_type, ChannelType::AnchorOutputs);
+        network.pay(0, 1, 200_000_000).unwrap();
+
+        network.set_online(1, false).unwrap();
+        let commitment_txid = network.node(0).close_channel(&channel.channel_id, true).unwrap();
+        let commitment = network.chain().get_transaction(&commitment_txid).unwrap();
+        assert_eq!(commitment.outputs.iter().filter(|output| output.value == ANCHOR_OUTPUT_SAT).count(), 2);
+
+        // Only force-closed anchor channels have a commitment to bump
+        assert!(network.node(0).bump_commitment_fee(&"00".repeat(32), 20).is_err());
+        let child_txid = network.node(0).bump_commitment_fee(&channel.channel_id, 20).unwrap();
+        let child = network.chain().get_transaction(&child_txid).unwrap();
+        assert!(child.inputs.iter().any(|input| input.txid == commitment_txid));
+
+        network.mine_blocks(1).unwrap();
+        assert_eq!(network.chain().get_confirmations(&commitment_txid).unwrap(), 1);
+        assert_eq!(network.chain().get_confirmations(&child_txid).unwrap(), 1);
+        assert!(network.node(0).bump_commitment_fee(&channel.channel_id, 50).is_err());
+    }
+
+    #[test]
+    fn test_splice_in_and_out_keep_channel_usable() {
+        let (network, channels) = line(2);
+        let channel_id = &channels[0];
+
+        let spliced = network.node(0).splice_in(channel_id, 200_000).unwrap();
+        let splice = spliced.pending_splice.clone().unwrap();
+        assert_eq!((spliced.capacity, splice.capacity), (1_000_000, 1_200_000));
+        assert!(network.node(0).splice_in(channel_id, 1_000).is_err());
+
+        // Payments keep flowing over the old funding output while the splice confirms
+        network.pay(0, 1, 100_000_000).unwrap();
+
+        network.mine_blocks(FUNDING_CONFIRMATIONS).unwrap();
+        let channel = network.channel(0, channel_id).unwrap();
+        assert!(channel.pending_splice.is_none());
+        assert_eq!(channel.funding_txid, splice.splice_txid);
+        assert_eq!(channel.capacity, 1_200_000);
+        assert_eq!(channel.local_balance, 1_100_000);
+        assert_eq!(network.local_balance(1, channel_id).unwrap(), 100_000);
+
+        // The peer splices out part of its balance to an on-chain address
+        let address = network.chain().generate_address(AddressType::P2WPKH).unwrap().address;
+        let spliced = network.node(1).splice_out(channel_id, &address, 60_000).unwrap();
+        let splice = spliced.pending_splice.clone().unwrap();
+        assert!(spliced.local_balance < 40_000);
+        let splice_tx = network.chain().get_transaction(&splice.splice_txid).unwrap();
+        assert!(splice_tx.outputs.iter().any(|output| output.value == 60_000 && output.address.as_deref() == Some(address.as_str())));
+
+        network.mine_blocks(FUNDING_CONFIRMATIONS).unwrap();
+        let channel = network.channel(0, channel_id).unwrap();
+        assert_eq!(channel.capacity, splice.capacity);
+        assert_eq!(channel.local_balance + channel.remote_balance, channel.capacity);
+        network.pay(0, 1, 10_000_000).unwrap();
+    }
+}